@@ -0,0 +1,378 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of aerial perspective over a large terrain.
+//
+// Distant mountains look pale and blue because the air between them and the
+// eye takes away some of their light and scatters sunlight in, the same
+// light that makes the sky. Each pixel of the terrain works out how much air
+// lies along its view ray, with the density of the molecules and of the
+// aerosols falling off exponentially with height, and applies the
+// extinction and the in-scattering of the atmosphere of `gfx_examples::sky`,
+// which draws the sky behind. The in-scattered light depends on the angle to
+// the sun, through the phase functions, and on how much of the sunlight gets
+// through the air above, so the haze glows around the sun and turns orange
+// at sunset, and always blends into the sky at the horizon. Plain
+// exponential fog of a fixed color is there for comparison.
+//
+// Positions are in meters. Look around with the mouse and fly with the keys
+// of the free-fly camera. Press H to cycle through aerial perspective, plain
+// fog and no haze, Up and Down to raise and lower the sun, Left and Right to
+// turn it around, and Plus and Minus to change the amount of aerosols. A
+// path recorded by the camera path example takes over the camera when
+// GFX_EXAMPLES_CAMERA_PATH names its file, for repeatable benchmark runs.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate noise;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::camera_path::CameraPath;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::sky::Sky;
+use gfx_examples::{window, Clock, Thumbnail};
+use noise::{Seed, perlin2};
+
+const GRID: usize = 257;
+const TERRAIN_SIZE: f32 = 60000.0;
+const MOUNTAIN_HEIGHT: f32 = 2400.0;
+// Radians per second
+const SUN_SPEED: f32 = 0.3;
+const MODES: [&'static str; 3] = ["aerial perspective", "plain fog", "no haze"];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_CameraPos"]
+    camera_pos: [f32; 3],
+    #[name = "u_SunDir"]
+    sun_dir: [f32; 3],
+    // 0 aerial perspective, 1 plain fog, 2 nothing
+    #[name = "u_Mode"]
+    mode: f32,
+    // scale of the density of the aerosols
+    #[name = "u_Turbidity"]
+    turbidity: f32,
+    #[name = "u_Exposure"]
+    exposure: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Pos = a_Pos;
+        v_Normal = a_Normal;
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    // the atmosphere of gfx_examples::sky
+    const float PI = 3.14159265;
+    const vec3 RAYLEIGH_BETA = vec3(5.5e-6, 13.0e-6, 22.4e-6);
+    const float MIE_BETA = 21e-6;
+    const float RAYLEIGH_HEIGHT = 8e3;
+    const float MIE_HEIGHT = 1.2e3;
+    const float MIE_G = 0.76;
+    const float SUN_INTENSITY = 22.0;
+    const vec3 FOG_COLOR = vec3(0.45, 0.5, 0.55);
+    const float FOG_DENSITY = 4e-5;
+
+    uniform vec3 u_CameraPos;
+    uniform vec3 u_SunDir;
+    uniform float u_Mode;
+    uniform float u_Turbidity;
+    uniform float u_Exposure;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    // the average density of a layer thinning out over `scale` meters,
+    // between two heights
+    float mean_density(float h0, float h1, float scale) {
+        float a = exp(-h0 / scale);
+        if (abs(h1 - h0) < 1.0) return a;
+        return scale * (a - exp(-h1 / scale)) / (h1 - h0);
+    }
+
+    // the fraction of the sunlight reaching a height, through a flat
+    // atmosphere, which is close enough while the sun is above the horizon
+    vec3 sun_transmittance(float h, vec3 sun) {
+        vec3 tau = RAYLEIGH_BETA * RAYLEIGH_HEIGHT * exp(-h / RAYLEIGH_HEIGHT)
+                 + 1.1 * MIE_BETA * u_Turbidity * MIE_HEIGHT * exp(-h / MIE_HEIGHT);
+        return exp(-tau / max(sun.z, 0.02)) * smoothstep(-0.02, 0.02, sun.z);
+    }
+
+    vec3 aerial_perspective(vec3 color, vec3 sun) {
+        vec3 ray = v_Pos - u_CameraPos;
+        float d = length(ray);
+        float h0 = max(u_CameraPos.z, 0.0), h1 = max(v_Pos.z, 0.0);
+        vec3 tau_rayleigh = RAYLEIGH_BETA * mean_density(h0, h1, RAYLEIGH_HEIGHT) * d;
+        vec3 tau_mie = vec3(MIE_BETA * u_Turbidity * mean_density(h0, h1, MIE_HEIGHT) * d);
+        vec3 extinction = tau_rayleigh + 1.1 * tau_mie;
+        vec3 transmittance = exp(-extinction);
+
+        float mu = dot(ray / d, sun);
+        float phase_rayleigh = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+        float g2 = MIE_G * MIE_G;
+        float phase_mie = 3.0 / (8.0 * PI) * (1.0 - g2) * (1.0 + mu * mu)
+                        / ((2.0 + g2) * pow(1.0 + g2 - 2.0 * MIE_G * mu, 1.5));
+        // the light scattered towards the eye by every bit of air, which the
+        // air in front of it dims in turn, adds up to its share of what the
+        // whole path takes away; the sunlight is taken halfway up the path
+        vec3 scattering = phase_rayleigh * tau_rayleigh + phase_mie * tau_mie;
+        vec3 sunlight = SUN_INTENSITY * sun_transmittance(0.5 * (h0 + h1), sun);
+        vec3 in_scattered = sunlight * scattering / extinction * (1.0 - transmittance);
+        return color * transmittance + in_scattered;
+    }
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 sun = normalize(u_SunDir);
+
+        // grass in the valleys, rock on the slopes, snow on the peaks
+        vec3 albedo = mix(vec3(0.2, 0.18, 0.16), vec3(0.08, 0.12, 0.05),
+                          smoothstep(0.7, 0.85, n.z));
+        float snow = smoothstep(1300.0, 1500.0, v_Pos.z + 300.0 * n.z);
+        albedo = mix(albedo, vec3(0.8), snow * smoothstep(0.55, 0.7, n.z));
+
+        vec3 sunlight = SUN_INTENSITY * sun_transmittance(v_Pos.z, sun);
+        vec3 skylight = SUN_INTENSITY * 0.015 * vec3(0.5, 0.7, 1.0)
+                      * clamp(4.0 * sun.z + 0.2, 0.0, 1.0);
+        vec3 color = albedo * (sunlight * max(dot(n, sun), 0.0) / PI + skylight);
+
+        if (u_Mode < 0.5) {
+            color = aerial_perspective(color, sun);
+        } else if (u_Mode < 1.5) {
+            color = mix(FOG_COLOR, color, exp(-FOG_DENSITY * distance(v_Pos, u_CameraPos)));
+        }
+        o_Color = vec4(1.0 - exp(-u_Exposure * color), 1.0);
+    }
+";
+
+// Ridged noise, with sharp crests where the noise crosses zero, over a
+// few octaves
+fn height(seed: &Seed, x: f32, y: f32) -> f32 {
+    let (mut sum, mut amplitude, mut frequency) = (0.0, 0.5, 1.0 / 16000.0);
+    for _ in 0 .. 7 {
+        let n = 1.0 - perlin2(seed, &[x * frequency, y * frequency]).abs();
+        sum += n * n * amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    MOUNTAIN_HEIGHT * sum
+}
+
+fn make_terrain(seed: &Seed) -> (Vec<Vertex>, Vec<u32>) {
+    let spacing = TERRAIN_SIZE / (GRID - 1) as f32;
+    let position = |i: usize| -0.5 * TERRAIN_SIZE + i as f32 * spacing;
+    let heights: Vec<f32> = (0 .. GRID * GRID).map(|i| {
+        height(seed, position(i % GRID), position(i / GRID))
+    }).collect();
+
+    let mut vertices = Vec::with_capacity(GRID * GRID);
+    for y in 0 .. GRID {
+        for x in 0 .. GRID {
+            let at = |x: usize, y: usize| heights[y * GRID + x];
+            let (x0, x1) = (x.saturating_sub(1), (x + 1).min(GRID - 1));
+            let (y0, y1) = (y.saturating_sub(1), (y + 1).min(GRID - 1));
+            let dx = (at(x1, y) - at(x0, y)) / ((x1 - x0) as f32 * spacing);
+            let dy = (at(x, y1) - at(x, y0)) / ((y1 - y0) as f32 * spacing);
+            let l = (dx * dx + dy * dy + 1.0).sqrt();
+            vertices.push(Vertex {
+                pos: [position(x), position(y), at(x, y)],
+                normal: [-dx / l, -dy / l, 1.0 / l],
+            });
+        }
+    }
+
+    let mut indices = Vec::with_capacity((GRID - 1) * (GRID - 1) * 6);
+    for y in 0 .. GRID - 1 {
+        for x in 0 .. GRID - 1 {
+            let i = (y * GRID + x) as u32;
+            let g = GRID as u32;
+            indices.extend([i, i + 1, i + g + 1, i + g + 1, i + g, i].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Aerial perspective example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let seed = Seed::new(7);
+    let mut batch = {
+        let (vertex_data, index_data) = make_terrain(&seed);
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            camera_pos: [0.0; 3],
+            sun_dir: [0.0, 0.0, 1.0],
+            mode: 0.0,
+            turbidity: 1.0,
+            exposure: 1.0,
+            _dummy: std::marker::PhantomData,
+        };
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+    let mut meshes = MeshCache::new();
+    let mut sky = Sky::new(&mut factory, &mut meshes);
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 10.0, 100000.0);
+    // from the south edge, looking north at the mountains and the sun
+    let mut camera = FlyCamera::new(Point3::new(0.0, -28000.0, 2200.0), 0.5 * PI, -0.05, 300.0);
+    let camera_path = CameraPath::from_env();
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut mode = 0;
+    let mut turbidity = 1.0f32;
+    // The sun, from the horizon and from +X
+    let (mut elevation, mut azimuth) = (0.12f32, 0.5 * PI);
+    // how the sun moves, -1, 0 or 1 along each angle
+    let (mut raise, mut turn) = (0.0f32, 0.0f32);
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(state, _, Some(key)) => {
+                    let amount = if state == ElementState::Pressed { 1.0 } else { 0.0 };
+                    match key {
+                        VirtualKeyCode::Up => raise = amount,
+                        VirtualKeyCode::Down => raise = -amount,
+                        VirtualKeyCode::Right => turn = amount,
+                        VirtualKeyCode::Left => turn = -amount,
+                        VirtualKeyCode::H if amount > 0.0 => mode = (mode + 1) % MODES.len(),
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals if amount > 0.0 =>
+                            turbidity = (turbidity * 1.5).min(20.0),
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus if amount > 0.0 =>
+                            turbidity = (turbidity / 1.5).max(0.1),
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            let dt = clock.step();
+            match camera_path {
+                Some(ref path) => path.apply(clock.time() as f32, &mut camera),
+                None => camera.update(dt),
+            }
+            // stays above the ground, which is only known at the vertices
+            // of the mesh, so with some room
+            let ground = height(&seed, camera.position.x, camera.position.y);
+            if camera.position.z < ground + 50.0 {
+                camera.position.z = ground + 50.0;
+            }
+            elevation = (elevation + raise * SUN_SPEED * dt).max(-0.2).min(0.5 * PI);
+            azimuth += turn * SUN_SPEED * dt;
+        }
+
+        let sun_dir = [elevation.cos() * azimuth.cos(),
+                       elevation.cos() * azimuth.sin(),
+                       elevation.sin()];
+        let view = camera.view().mat;
+        batch.params.view_proj = proj.mul_m(&view).into_fixed();
+        batch.params.camera_pos = camera.position.into_fixed();
+        batch.params.sun_dir = sun_dir;
+        batch.params.mode = mode as f32;
+        batch.params.turbidity = turbidity;
+        batch.params.exposure = sky.exposure;
+        sky.sun_dir = sun_dir;
+        sky.altitude = camera.position.z;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+        sky.draw(&mut renderer, &wrap, &proj, &view);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Aerial perspective example: {}, sun at {:.1} degrees, aerosols x{:.2}",
+            MODES[mode], elevation.to_degrees(), turbidity));
+    }
+}
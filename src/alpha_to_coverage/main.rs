@@ -0,0 +1,330 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of drawing foliage and fences with alpha-to-coverage.
+//
+// Leaves and wire mesh are usually flat cards with a texture whose alpha cuts
+// out the shape. Alpha testing discards the fragments below a threshold,
+// which leaves hard, aliased edges that multisampling can't smooth, since
+// the whole pixel is either kept or thrown away. Further away, the mipmaps
+// average the alpha towards the threshold and thin shapes wear away.
+//
+// With alpha-to-coverage, the alpha of a fragment decides how many of the
+// samples of its pixel it covers, so that edges get graded like the edges of
+// triangles, and resolve smoothly. Sharpening the alpha over the width of a
+// pixel first keeps the edges crisp while still graded.
+//
+// The window is multisampled, and the cards are drawn with the multisample
+// state of the DrawState on, which turning off shows the difference it
+// makes. gfx-rs doesn't expose alpha-to-coverage yet, so it is switched on
+// with GL directly around the submission of the frame.
+//
+// Press C to cycle through alpha testing, alpha-to-coverage and sharpened
+// alpha-to-coverage, and M to toggle the multisample state.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{window, Thumbnail};
+
+const SAMPLES: u16 = 4;
+const TEXTURE_SIZE: usize = 128;
+const MODES: [&'static str; 3] = ["alpha testing", "alpha-to-coverage",
+                                  "sharpened alpha-to-coverage"];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_TexCoord"]
+    tex_coord: [f32; 2],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "u_Mode"]
+    mode: f32,
+    #[name = "t_Color"]
+    texture: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+    out float v_Height;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        v_Height = a_Pos.z;
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Mode;
+    uniform sampler2D t_Color;
+    in vec2 v_TexCoord;
+    in float v_Height;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 color = texture(t_Color, v_TexCoord);
+        if (u_Mode < 0.5) {
+            // alpha testing: all or nothing
+            if (color.a < 0.5) {
+                discard;
+            }
+            color.a = 1.0;
+        } else if (u_Mode < 1.5) {
+            // the alpha as it is becomes the coverage
+        } else {
+            // from 0 to 1 over about a pixel around the threshold
+            color.a = clamp((color.a - 0.5) / max(fwidth(color.a), 1e-4) + 0.5, 0.0, 1.0);
+        }
+        // darker towards the ground, for some depth
+        float shade = 0.6 + 0.4 * clamp(v_Height / 2.0, 0.0, 1.0);
+        o_Color = vec4(color.rgb * u_Color * shade, color.a);
+    }
+";
+
+// Overlapping leaves, each an ellipse with a lighter vein along it
+fn create_leaves() -> Vec<u8> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let leaves: Vec<(f32, f32, f32, f32)> = (0 .. 48).map(|_| {
+        (0.1 + 0.8 * rng.gen::<f32>(), 0.1 + 0.8 * rng.gen::<f32>(),
+         PI * rng.gen::<f32>(), 0.7 + 0.3 * rng.gen::<f32>())
+    }).collect();
+    let mut data = Vec::with_capacity(TEXTURE_SIZE * TEXTURE_SIZE * 4);
+    for py in 0 .. TEXTURE_SIZE {
+        for px in 0 .. TEXTURE_SIZE {
+            let (x, y) = ((px as f32 + 0.5) / TEXTURE_SIZE as f32,
+                          (py as f32 + 0.5) / TEXTURE_SIZE as f32);
+            let mut texel = [0u8; 4];
+            for &(cx, cy, angle, light) in leaves.iter() {
+                let (s, c) = angle.sin_cos();
+                let (dx, dy) = (x - cx, y - cy);
+                let (u, v) = ((c * dx + s * dy) / 0.09, (c * dy - s * dx) / 0.035);
+                if u * u + v * v < 1.0 {
+                    let vein = if v.abs() < 0.12 { 1.25 } else { 1.0 };
+                    texel = [(70.0 * light * vein) as u8, (150.0 * light * vein) as u8,
+                             (40.0 * light) as u8, 0xFF];
+                }
+            }
+            data.extend(texel.iter().cloned());
+        }
+    }
+    data
+}
+
+// Diamonds of thin wire, repeating
+fn create_fence() -> Vec<u8> {
+    let mut data = Vec::with_capacity(TEXTURE_SIZE * TEXTURE_SIZE * 4);
+    for py in 0 .. TEXTURE_SIZE {
+        for px in 0 .. TEXTURE_SIZE {
+            let (x, y) = ((px as f32 + 0.5) / TEXTURE_SIZE as f32,
+                          (py as f32 + 0.5) / TEXTURE_SIZE as f32);
+            let d1 = ((x + y) * 2.0).fract();
+            let d2 = ((x - y + 1.0) * 2.0).fract();
+            let wire = (d1 - 0.5).abs() > 0.46 || (d2 - 0.5).abs() > 0.46;
+            data.extend([190, 195, 200, if wire { 0xFF } else { 0 }].iter().cloned());
+        }
+    }
+    data
+}
+
+fn quad(corners: [[f32; 3]; 4], repeat: [f32; 2], vertices: &mut Vec<Vertex>) {
+    let uv = [[0.0, 0.0], [repeat[0], 0.0], [repeat[0], repeat[1]], [0.0, repeat[1]]];
+    for &i in [0, 1, 2, 2, 3, 0].iter() {
+        vertices.push(Vertex { pos: corners[i], tex_coord: uv[i] });
+    }
+}
+
+// A long fence along X, with the wire repeating every half meter
+fn create_fence_mesh() -> Vec<Vertex> {
+    let mut vertices = Vec::new();
+    quad([[-8.0, 0.0, 0.0], [8.0, 0.0, 0.0], [8.0, 0.0, 2.0], [-8.0, 0.0, 2.0]],
+         [32.0, 4.0], &mut vertices);
+    vertices
+}
+
+// Bushes on both sides of the fence, each of three crossing cards
+fn create_bush_mesh() -> Vec<Vertex> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut vertices = Vec::new();
+    for i in 0 .. 16 {
+        let x = -7.0 + 14.0 * (i as f32 + rng.gen::<f32>()) / 16.0;
+        let side = if i % 2 == 0 { 1.5 } else { -1.5 };
+        let y = side + 0.8 * (rng.gen::<f32>() - 0.5);
+        let size = 0.8 + 0.6 * rng.gen::<f32>();
+        for k in 0 .. 3 {
+            let angle = (k as f32 / 3.0 + rng.gen::<f32>() * 0.1) * PI;
+            let (dx, dy) = (size * angle.cos(), size * angle.sin());
+            quad([[x - dx, y - dy, 0.0], [x + dx, y + dy, 0.0],
+                  [x + dx, y + dy, 2.0 * size], [x - dx, y - dy, 2.0 * size]],
+                 [1.0, 1.0], &mut vertices);
+        }
+    }
+    vertices
+}
+
+fn create_ground_mesh() -> Vec<Vertex> {
+    let mut vertices = Vec::new();
+    quad([[-20.0, -20.0, 0.0], [20.0, -20.0, 0.0], [20.0, 20.0, 0.0], [-20.0, 20.0, 0.0]],
+         [1.0, 1.0], &mut vertices);
+    vertices
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Alpha-to-coverage example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_multisampling(SAMPLES)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+    let gl = gl::Gl::load_with(|s| wrap.window.get_proc_address(s));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let card_state = state.clone().multi_sample();
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                   gfx::tex::WrapMode::Tile)
+    );
+
+    // (vertices, texels, tint) of the ground, the fence and the bushes
+    let ground_texel = vec![0xFFu8; 4];
+    let meshes = vec![
+        (create_ground_mesh(), ground_texel, [0.35, 0.45, 0.2]),
+        (create_fence_mesh(), create_fence(), [1.0, 1.0, 1.0]),
+        (create_bush_mesh(), create_leaves(), [1.0, 1.0, 1.0]),
+    ];
+    let mut batches: Vec<_> = meshes.into_iter().enumerate().map(|(i, mesh_data)| {
+        let (vertices, texels, color) = mesh_data;
+        let size = if i == 0 { 1 } else { TEXTURE_SIZE as u16 };
+        let texture = factory.create_texture_rgba8(size, size, true).unwrap();
+        factory.update_texture(&texture, &texture.get_info().to_image_info(), &texels, None)
+               .unwrap();
+        factory.generate_mipmap(&texture);
+        let mesh = factory.create_mesh(&vertices);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            color: color,
+            mode: 0.0,
+            texture: (texture, Some(sampler.clone())),
+        };
+        let state = if i == 0 { &state } else { &card_state };
+        context.make_batch(&program, data, &mesh, slice, state).unwrap()
+    }).collect();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(50.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 1.0), 6.0, -1.2, 0.2);
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.75, 0.9, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut mode = 1;
+    let mut multi_sample = true;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::C)) =>
+                    mode = (mode + 1) % MODES.len(),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::M)) => {
+                    multi_sample = !multi_sample;
+                    let cards = if multi_sample { &card_state } else { &state };
+                    for batch in batches[1 ..].iter_mut() {
+                        batch.state = cards.clone();
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for batch in batches.iter_mut() {
+            batch.params.view_proj = view_proj;
+            batch.params.mode = mode as f32;
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        // The ground is opaque, so the coverage only changes for the cards
+        let coverage = mode > 0;
+        if coverage {
+            unsafe { gl.Enable(gl::SAMPLE_ALPHA_TO_COVERAGE) };
+        }
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+        if coverage {
+            unsafe { gl.Disable(gl::SAMPLE_ALPHA_TO_COVERAGE) };
+        }
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Alpha-to-coverage example: {}, multisampling {}",
+                                       MODES[mode], if multi_sample { "on" } else { "off" }));
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of anaglyph stereo, for red and cyan glasses.
+//
+// The scene is drawn twice into the window, once from each eye, with the
+// color mask of the draw state keeping only red for the left eye, and only
+// green and blue for the right one. The eyes sit half the interocular
+// distance to each side of the camera and look the same way, and their
+// projections are sheared so that both frustums meet at the convergence
+// distance: what is that far away shows at the same place for both eyes and
+// appears on the screen, what is closer pops out of it, and what is further
+// sinks behind it. Colors are turned to gray by default, since a red or a
+// cyan object is seen by one eye only.
+//
+// Drag with the left mouse button to turn around the scene, and use the
+// mouse wheel to zoom. Press Up and Down to change the interocular distance,
+// Left and Right to move the convergence, G to switch between gray and
+// colors, and Space to show one eye or both.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 1 to draw in gray, 0 in colors
+    #[name = "u_Gray"]
+    gray: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Gray;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.4, 0.3, 0.8))), 0.0);
+        vec3 color = u_Color * (0.8 * diffuse + 0.2);
+        float luma = dot(color, vec3(0.299, 0.587, 0.114));
+        color = mix(color, vec3(luma), u_Gray);
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// Vertical field of view in degrees, and the clip planes
+const FOV: f32 = 50.0;
+const NEAR: f32 = 0.1;
+const FAR: f32 = 100.0;
+
+// Shape, position, scale and color of each object
+const OBJECTS: [(usize, [f32; 3], f32, [f32; 3]); 9] = [
+    (0, [0.0, 0.0, -1.05], 6.0, [0.5, 0.5, 0.5]),
+    (1, [0.0, 0.0, 0.0], 1.0, [0.9, 0.3, 0.2]),
+    (2, [-2.5, 1.5, 0.0], 0.8, [0.2, 0.7, 0.3]),
+    (3, [2.5, -1.5, 0.0], 1.0, [0.2, 0.4, 0.9]),
+    (1, [-3.0, -3.0, 0.5], 0.5, [0.9, 0.8, 0.2]),
+    (2, [3.0, 3.5, -0.4], 0.6, [0.7, 0.3, 0.8]),
+    (3, [-4.5, 4.0, -0.5], 0.6, [0.3, 0.8, 0.8]),
+    (1, [4.5, 0.5, -0.3], 0.6, [0.9, 0.5, 0.2]),
+    (2, [0.5, -4.5, -0.2], 0.8, [0.8, 0.8, 0.8]),
+];
+
+// Both eyes, or one of them
+const VIEWS: [&'static str; 3] = ["both eyes", "left eye", "right eye"];
+
+// The view and projection of the eye `offset` to the right of the camera,
+// sheared to converge at `convergence`
+fn eye_view_proj(view: &Matrix4<f32>, aspect: f32, offset: f32, convergence: f32)
+                 -> Matrix4<f32> {
+    let top = NEAR * (0.5 * FOV).to_radians().tan();
+    let shift = -offset * NEAR / convergence;
+    let proj = cgmath::frustum(-aspect * top + shift, aspect * top + shift, -top, top,
+                               NEAR, FAR);
+    let mut eye = Matrix4::identity();
+    eye.w = Vector4::new(-offset, 0.0, 0.0, 1.0);
+    proj.mul_m(&eye).mul_m(view)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Anaglyph example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.plane(&mut factory), meshes.sphere(&mut factory, 24, 48),
+                      meshes.cube(&mut factory), meshes.torus(&mut factory, 48, 24, 1.0, 0.35)];
+    let mut batches: Vec<_> = shapes.into_iter().map(|(mesh, slice)| {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: [1.0; 3],
+            gray: 1.0,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    }).collect();
+    // the left eye only writes red, the right one green and blue
+    let masks = [gfx::state::RED, gfx::state::GREEN | gfx::state::BLUE];
+
+    let aspect = w as f32 / h as f32;
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 10.0, 0.6, 0.4);
+    // in meters
+    let mut interocular = 0.065f32;
+    let mut convergence = 10.0f32;
+    let mut gray = true;
+    let mut shown = 0;
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let mut time = 0.0f32;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => interocular += 0.005,
+                    VirtualKeyCode::Down => interocular -= 0.005,
+                    VirtualKeyCode::Right => convergence *= 1.1,
+                    VirtualKeyCode::Left => convergence /= 1.1,
+                    VirtualKeyCode::G => gray = !gray,
+                    VirtualKeyCode::Space => shown = (shown + 1) % VIEWS.len(),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+        interocular = interocular.max(0.0).min(0.5);
+        convergence = convergence.max(1.0).min(50.0);
+
+        clock.update();
+        while clock.tick() {
+            time = clock.time() as f32;
+        }
+
+        let view = camera.view().mat;
+        // the eyes are in scene units, where the camera is 10 from the scene
+        // seen at about a meter from the screen
+        let half = 0.5 * interocular * 10.0;
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for (eye, &offset) in [-half, half].iter().enumerate() {
+            if shown != 0 && shown != eye + 1 {
+                continue;
+            }
+            renderer.clear(clear_data, gfx::DEPTH, &wrap);
+            let view_proj = eye_view_proj(&view, aspect, offset, convergence).into_fixed();
+            for (i, &(shape, position, scale, color)) in OBJECTS.iter().enumerate() {
+                let spin = if shape == 0 { 0.0 } else { 0.3 * time + i as f32 };
+                let mut model = Matrix3::from_angle_z(cgmath::rad(spin))
+                                        .mul_s(scale).to_matrix4();
+                model.w = Vector4::new(position[0], position[1], position[2], 1.0);
+                let batch = &mut batches[shape];
+                batch.params.view_proj = view_proj;
+                batch.params.model = model.into_fixed();
+                batch.params.color = color;
+                batch.params.gray = if gray { 1.0 } else { 0.0 };
+                batch.state.color_mask = masks[eye];
+                renderer.draw(&(&*batch, &context), &wrap).unwrap();
+            }
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Anaglyph example: {}, interocular {:.0} mm, convergence {:.1}, {}",
+            VIEWS[shown], interocular * 1000.0, convergence,
+            if gray { "gray" } else { "colors" }));
+    }
+}
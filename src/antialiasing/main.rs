@@ -0,0 +1,513 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example comparing two ways of antialiasing.
+//
+// A turning fan of thin slats and rings, full of long edges and detail
+// smaller than a pixel, is drawn twice every frame. The left half of the
+// window shows it drawn into a multisampled frame buffer, resolved by a blit.
+// The right half shows it drawn into a plain texture, then filtered by FXAA,
+// which looks for edges in the finished image and blends across them.
+//
+// Each path is timed on the GPU with a timer query, read back a frame later
+// when the result is ready. The time covers drawing the scene into the
+// path's target and then resolving or filtering the half it shows.
+//
+// gfx-rs doesn't expose multisampled frame buffers nor queries yet, so this
+// example talks to GL directly, like the occlusion example.
+//
+// Press Up and Down to change the number of MSAA samples, F to toggle FXAA,
+// and Space to stop the fan. The window title shows the timings.
+
+extern crate cgmath;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx_examples::{shape, window, Clock, Thumbnail};
+use gfx_examples::gl_program::{compile_shader, link_program, uniform_location};
+use gl::Gl;
+use gl::types::*;
+use std::f32::consts::PI;
+use std::mem;
+use std::ptr;
+
+const NUM_SLATS: usize = 48;
+// how much a new timing counts in the displayed average
+const SMOOTHING: f64 = 0.05;
+
+static VERTEX_SRC: &'static str = "
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static str = "
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(u_Color * (0.2 + 0.8 * d), 1.0);
+    }
+";
+
+static FXAA_VERTEX_SRC: &'static str = "
+    #version 150 core
+
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+// The simple FXAA of Timothy Lottes: blend along the direction of the local
+// luminance gradient, unless that goes out of the range of the neighbours
+static FXAA_FRAGMENT_SRC: &'static str = "
+    #version 150 core
+
+    const float SPAN_MAX = 8.0;
+    const float REDUCE_MUL = 1.0 / 8.0;
+    const float REDUCE_MIN = 1.0 / 128.0;
+    const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+    uniform sampler2D t_Color;
+    uniform vec2 u_InvSize;
+    out vec4 o_Color;
+
+    vec3 fetch(vec2 uv) {
+        return texture(t_Color, uv).rgb;
+    }
+
+    void main() {
+        vec2 uv = gl_FragCoord.xy * u_InvSize;
+        float nw = dot(fetch(uv + vec2(-1.0, -1.0) * u_InvSize), LUMA);
+        float ne = dot(fetch(uv + vec2( 1.0, -1.0) * u_InvSize), LUMA);
+        float sw = dot(fetch(uv + vec2(-1.0,  1.0) * u_InvSize), LUMA);
+        float se = dot(fetch(uv + vec2( 1.0,  1.0) * u_InvSize), LUMA);
+        vec3 center = fetch(uv);
+        float m = dot(center, LUMA);
+        float luma_min = min(m, min(min(nw, ne), min(sw, se)));
+        float luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+        // across the gradient, along the edge
+        vec2 dir = vec2(sw + se - nw - ne, nw + sw - ne - se);
+        float reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+        float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+        dir = clamp(dir * scale, -SPAN_MAX, SPAN_MAX) * u_InvSize;
+
+        vec3 a = 0.5 * (fetch(uv + dir * (1.0 / 3.0 - 0.5)) + fetch(uv + dir * (2.0 / 3.0 - 0.5)));
+        vec3 b = 0.5 * a + 0.25 * (fetch(uv - 0.5 * dir) + fetch(uv + 0.5 * dir));
+        float luma_b = dot(b, LUMA);
+        o_Color = vec4(luma_b < luma_min || luma_b > luma_max ? a : b, 1.0);
+    }
+";
+
+/// A vertex array with its buffers, drawn with indices.
+struct Mesh {
+    vao: GLuint,
+    buffers: [GLuint; 2],
+    num_indices: GLsizei,
+}
+
+impl Mesh {
+    fn new(gl: &Gl, vertices: &[shape::Vertex], indices: &[u16]) -> Mesh {
+        let mut vao = 0;
+        let mut buffers = [0; 2];
+        unsafe {
+            gl.GenVertexArrays(1, &mut vao);
+            gl.BindVertexArray(vao);
+            gl.GenBuffers(2, buffers.as_mut_ptr());
+
+            gl.BindBuffer(gl::ARRAY_BUFFER, buffers[0]);
+            gl.BufferData(gl::ARRAY_BUFFER,
+                          (vertices.len() * mem::size_of::<shape::Vertex>()) as GLsizeiptr,
+                          mem::transmute(&vertices[0]),
+                          gl::STATIC_DRAW);
+            let stride = mem::size_of::<shape::Vertex>() as GLsizei;
+            gl.EnableVertexAttribArray(0);
+            gl.VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl.EnableVertexAttribArray(1);
+            gl.VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride,
+                                   mem::size_of::<[f32; 3]>() as *const GLvoid);
+
+            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffers[1]);
+            gl.BufferData(gl::ELEMENT_ARRAY_BUFFER,
+                          (indices.len() * mem::size_of::<u16>()) as GLsizeiptr,
+                          mem::transmute(&indices[0]),
+                          gl::STATIC_DRAW);
+            gl.BindVertexArray(0);
+        }
+        Mesh {
+            vao: vao,
+            buffers: buffers,
+            num_indices: indices.len() as GLsizei,
+        }
+    }
+
+    fn draw(&self, gl: &Gl) {
+        unsafe {
+            gl.BindVertexArray(self.vao);
+            gl.DrawElements(gl::TRIANGLES, self.num_indices, gl::UNSIGNED_SHORT, ptr::null());
+        }
+    }
+
+    fn delete(&self, gl: &Gl) {
+        unsafe {
+            gl.DeleteBuffers(2, self.buffers.as_ptr());
+            gl.DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// The frame buffers of both paths: a multisampled one made of render
+/// buffers, and a plain one with a color texture for FXAA to read.
+struct Targets {
+    msaa_fbo: GLuint,
+    msaa_buffers: [GLuint; 2],
+    fbo: GLuint,
+    texture: GLuint,
+    depth: GLuint,
+}
+
+fn check_framebuffer(gl: &Gl) {
+    let status = unsafe { gl.CheckFramebufferStatus(gl::FRAMEBUFFER) };
+    if status != gl::FRAMEBUFFER_COMPLETE {
+        panic!("Incomplete frame buffer: {:x}", status);
+    }
+}
+
+impl Targets {
+    fn new(gl: &Gl, width: GLsizei, height: GLsizei, samples: GLsizei) -> Targets {
+        let (mut msaa_fbo, mut fbo) = (0, 0);
+        let mut msaa_buffers = [0; 2];
+        let (mut texture, mut depth) = (0, 0);
+        unsafe {
+            gl.GenRenderbuffers(2, msaa_buffers.as_mut_ptr());
+            gl.BindRenderbuffer(gl::RENDERBUFFER, msaa_buffers[0]);
+            gl.RenderbufferStorageMultisample(gl::RENDERBUFFER, samples, gl::RGBA8,
+                                              width, height);
+            gl.BindRenderbuffer(gl::RENDERBUFFER, msaa_buffers[1]);
+            gl.RenderbufferStorageMultisample(gl::RENDERBUFFER, samples, gl::DEPTH_COMPONENT24,
+                                              width, height);
+            gl.GenFramebuffers(1, &mut msaa_fbo);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, msaa_fbo);
+            gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0,
+                                       gl::RENDERBUFFER, msaa_buffers[0]);
+            gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT,
+                                       gl::RENDERBUFFER, msaa_buffers[1]);
+            check_framebuffer(gl);
+
+            gl.GenTextures(1, &mut texture);
+            gl.BindTexture(gl::TEXTURE_2D, texture);
+            gl.TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as GLint, width, height, 0,
+                          gl::RGBA, gl::UNSIGNED_BYTE, ptr::null());
+            // FXAA reads between the pixels
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl.GenRenderbuffers(1, &mut depth);
+            gl.BindRenderbuffer(gl::RENDERBUFFER, depth);
+            gl.RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width, height);
+            gl.GenFramebuffers(1, &mut fbo);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl.FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0,
+                                    gl::TEXTURE_2D, texture, 0);
+            gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT,
+                                       gl::RENDERBUFFER, depth);
+            check_framebuffer(gl);
+
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        Targets {
+            msaa_fbo: msaa_fbo,
+            msaa_buffers: msaa_buffers,
+            fbo: fbo,
+            texture: texture,
+            depth: depth,
+        }
+    }
+
+    fn delete(&self, gl: &Gl) {
+        unsafe {
+            gl.DeleteFramebuffers(1, &self.msaa_fbo);
+            gl.DeleteFramebuffers(1, &self.fbo);
+            gl.DeleteRenderbuffers(2, self.msaa_buffers.as_ptr());
+            gl.DeleteRenderbuffers(1, &self.depth);
+            gl.DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+fn model_matrix(rotation: Matrix3<f32>, pos: Vector3<f32>, scale: [f32; 3]) -> Matrix4<f32> {
+    let scale = Matrix3::new(scale[0], 0.0, 0.0,
+                             0.0, scale[1], 0.0,
+                             0.0, 0.0, scale[2]);
+    let mut model = rotation.mul_m(&scale).to_matrix4();
+    model.w = Vector4::new(pos.x, pos.y, pos.z, 1.0);
+    model
+}
+
+pub fn main() {
+    let window = glutin::WindowBuilder::new()
+        .with_title("Antialiasing example".to_string())
+        .with_dimensions(800, 600)
+        .with_gl(glutin::GlRequest::Latest)
+        .build().unwrap();
+    unsafe { window.make_current() };
+    let gl = Gl::load_with(|s| window.get_proc_address(s));
+
+    let (w, h) = window::physical_size(&window).unwrap();
+    let (width, height) = (w as GLsizei, h as GLsizei);
+    let mut thumbnail = Thumbnail::from_env();
+
+    let vs = compile_shader(&gl, VERTEX_SRC, gl::VERTEX_SHADER);
+    let fs = compile_shader(&gl, FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let program = link_program(&gl, &[vs, fs], &["a_Pos", "a_Normal"]);
+    let u_model = uniform_location(&gl, program, "u_Model");
+    let u_view_proj = uniform_location(&gl, program, "u_ViewProj");
+    let u_color = uniform_location(&gl, program, "u_Color");
+
+    let fxaa_vs = compile_shader(&gl, FXAA_VERTEX_SRC, gl::VERTEX_SHADER);
+    let fxaa_fs = compile_shader(&gl, FXAA_FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let fxaa_program = link_program(&gl, &[fxaa_vs, fxaa_fs], &["a_Pos", "a_Normal"]);
+    unsafe {
+        gl.UseProgram(fxaa_program);
+        gl.Uniform1i(uniform_location(&gl, fxaa_program, "t_Color"), 0);
+        gl.Uniform2f(uniform_location(&gl, fxaa_program, "u_InvSize"),
+                     1.0 / w as f32, 1.0 / h as f32);
+    }
+
+    let slat = {
+        let (vertices, indices) = shape::cube();
+        Mesh::new(&gl, &vertices, &indices)
+    };
+    let hub = {
+        let (vertices, indices) = shape::sphere(16, 32);
+        Mesh::new(&gl, &vertices, &indices)
+    };
+    let rings: Vec<Mesh> = [2.0, 3.5, 5.0].iter().map(|&radius| {
+        let (vertices, indices) = shape::torus(16, 128, radius, 0.03);
+        Mesh::new(&gl, &vertices, &indices)
+    }).collect();
+    let quad = {
+        let vertices = shape::quad();
+        let indices: Vec<u16> = (0 .. vertices.len() as u16).collect();
+        Mesh::new(&gl, &vertices, &indices)
+    };
+
+    let mut max_samples = 0;
+    unsafe { gl.GetIntegerv(gl::MAX_SAMPLES, &mut max_samples) };
+    let mut samples = 4.min(max_samples);
+    let mut targets = Targets::new(&gl, width, height, samples);
+
+    // One for each path
+    let mut queries = [0; 2];
+    unsafe { gl.GenQueries(2, queries.as_mut_ptr()) };
+    let mut issued = false;
+    let mut timings = [0.0f64; 2];
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 100.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(0.0, -9.0, 5.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_z(),
+    );
+    let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+    let mut fxaa = true;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                        let new_samples = match key {
+                            VirtualKeyCode::Up => (samples * 2).min(max_samples),
+                            _ => (samples / 2).max(2),
+                        };
+                        if new_samples != samples {
+                            samples = new_samples;
+                            targets.delete(&gl);
+                            targets = Targets::new(&gl, width, height, samples);
+                        }
+                    },
+                    VirtualKeyCode::F => fxaa = !fxaa,
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.1 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        // Collect the timings of the previous frame
+        if issued {
+            for (query, timing) in queries.iter().zip(timings.iter_mut()) {
+                let mut available = 0;
+                unsafe { gl.GetQueryObjectuiv(*query, gl::QUERY_RESULT_AVAILABLE, &mut available) };
+                if available != 0 {
+                    let mut nanoseconds = 0;
+                    unsafe { gl.GetQueryObjectui64v(*query, gl::QUERY_RESULT, &mut nanoseconds) };
+                    let ms = nanoseconds as f64 * 1e-6;
+                    *timing = if *timing == 0.0 { ms }
+                              else { *timing + (ms - *timing) * SMOOTHING };
+                }
+            }
+        }
+
+        let draw_scene = |fbo: GLuint| {
+            unsafe {
+                gl.BindFramebuffer(gl::FRAMEBUFFER, fbo);
+                gl.Viewport(0, 0, width, height);
+                gl.ClearColor(0.8, 0.85, 0.9, 1.0);
+                gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                gl.Enable(gl::DEPTH_TEST);
+                gl.DepthFunc(gl::LEQUAL);
+                gl.UseProgram(program);
+                gl.UniformMatrix4fv(u_view_proj, 1, gl::FALSE, &view_proj[0][0]);
+            }
+            let set_object = |model: &Matrix4<f32>, color: [f32; 3]| unsafe {
+                gl.UniformMatrix4fv(u_model, 1, gl::FALSE, &model.into_fixed()[0][0]);
+                gl.Uniform3f(u_color, color[0], color[1], color[2]);
+            };
+
+            for i in 0 .. NUM_SLATS {
+                let rotation = Matrix3::from_angle_z(
+                    cgmath::rad(a + i as f32 * 2.0 * PI / NUM_SLATS as f32));
+                let pos = rotation.mul_v(&Vector3::new(0.0, 3.5, 0.0));
+                set_object(&model_matrix(rotation, pos, [0.02, 3.0, 0.02]), [0.1, 0.1, 0.15]);
+                slat.draw(&gl);
+            }
+            for (i, ring) in rings.iter().enumerate() {
+                let rotation = Matrix3::from_angle_x(cgmath::rad((i + 1) as f32 * a));
+                set_object(&model_matrix(rotation, Vector3::new(0.0, 0.0, 0.0), [1.0; 3]),
+                           [0.9, 0.4, 0.1]);
+                ring.draw(&gl);
+            }
+            set_object(&model_matrix(Matrix3::identity(), Vector3::new(0.0, 0.0, 0.0),
+                                     [0.5; 3]), [0.9, 0.9, 0.9]);
+            hub.draw(&gl);
+        };
+
+        // MSAA: resolve the left half straight into the window
+        unsafe { gl.BeginQuery(gl::TIME_ELAPSED, queries[0]) };
+        draw_scene(targets.msaa_fbo);
+        unsafe {
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, targets.msaa_fbo);
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl.BlitFramebuffer(0, 0, width / 2, height, 0, 0, width / 2, height,
+                               gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            gl.EndQuery(gl::TIME_ELAPSED);
+        }
+
+        // FXAA: filter the right half, reading the texture around each pixel
+        unsafe { gl.BeginQuery(gl::TIME_ELAPSED, queries[1]) };
+        draw_scene(targets.fbo);
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl.Disable(gl::DEPTH_TEST);
+            gl.Enable(gl::SCISSOR_TEST);
+            gl.Scissor(width / 2, 0, width - width / 2, height);
+            gl.UseProgram(fxaa_program);
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, targets.texture);
+        }
+        if fxaa {
+            quad.draw(&gl);
+        } else {
+            unsafe {
+                gl.BindFramebuffer(gl::READ_FRAMEBUFFER, targets.fbo);
+                gl.BlitFramebuffer(width / 2, 0, width, height, width / 2, 0, width, height,
+                                   gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            }
+        }
+        unsafe {
+            gl.EndQuery(gl::TIME_ELAPSED);
+
+            // A line between the halves
+            gl.Scissor(width / 2 - 1, 0, 2, height);
+            gl.ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT);
+            gl.Disable(gl::SCISSOR_TEST);
+        }
+        issued = true;
+
+        let win = &window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        window.swap_buffers();
+
+        window.set_title(&format!("Antialiasing example: MSAA x{} {:.2} ms | {} {:.2} ms",
+                                  samples, timings[0],
+                                  if fxaa { "FXAA" } else { "no AA" }, timings[1]));
+    }
+
+    // Cleanup
+    targets.delete(&gl);
+    unsafe {
+        gl.DeleteQueries(2, queries.as_ptr());
+        gl.DeleteProgram(fxaa_program);
+        gl.DeleteShader(fxaa_fs);
+        gl.DeleteShader(fxaa_vs);
+        gl.DeleteProgram(program);
+        gl.DeleteShader(fs);
+        gl.DeleteShader(vs);
+    }
+    slat.delete(&gl);
+    hub.delete(&gl);
+    for ring in rings.iter() {
+        ring.delete(&gl);
+    }
+    quad.delete(&gl);
+}
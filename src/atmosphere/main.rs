@@ -0,0 +1,145 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of atmospheric scattering.
+//
+// The whole picture is the sky of `gfx_examples::sky`, a full-screen pass
+// integrating the sunlight scattered by the air and the aerosols along every
+// view ray. The deferred example can draw the same sky behind its terrain.
+//
+// Look around with the mouse and fly with the keys of the free-fly camera, up
+// to space. Press Up and Down to raise and lower the sun, Left and Right to
+// turn it around, Space to let the day go by, and Plus and Minus to change the
+// exposure.
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::Point3;
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::sky::Sky;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// Radians per second
+const SUN_SPEED: f32 = 0.5;
+const DAY_SPEED: f32 = 0.1;
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Atmosphere example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+
+    let mut meshes = MeshCache::new();
+    let mut sky = Sky::new(&mut factory, &mut meshes);
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 10.0);
+    // Positions are in meters, for the altitude
+    let mut camera = FlyCamera::new(Point3::new(0.0, 0.0, 2.0), 0.5 * PI, 0.15, 500.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // The sun, from the horizon and from +X
+    let (mut elevation, mut azimuth) = (0.1f32, 0.5 * PI);
+    // how the sun moves, -1, 0 or 1 along each angle
+    let (mut raise, mut turn) = (0.0f32, 0.0f32);
+    let mut day = false;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(state, _, Some(key)) => {
+                    let amount = if state == ElementState::Pressed { 1.0 } else { 0.0 };
+                    match key {
+                        VirtualKeyCode::Up => raise = amount,
+                        VirtualKeyCode::Down => raise = -amount,
+                        VirtualKeyCode::Right => turn = amount,
+                        VirtualKeyCode::Left => turn = -amount,
+                        VirtualKeyCode::Space if amount > 0.0 => day = !day,
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals if amount > 0.0 =>
+                            sky.exposure *= 1.25,
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus if amount > 0.0 =>
+                            sky.exposure *= 0.8,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            let dt = clock.step();
+            camera.update(dt);
+            if camera.position.z < 1.0 {
+                camera.position.z = 1.0;
+            }
+            let day_motion = if day { DAY_SPEED } else { 0.0 };
+            elevation = (elevation + (raise * SUN_SPEED + day_motion) * dt) % (2.0 * PI);
+            azimuth += turn * SUN_SPEED * dt;
+        }
+
+        sky.sun_dir = [elevation.cos() * azimuth.cos(),
+                       elevation.cos() * azimuth.sin(),
+                       elevation.sin()];
+        sky.altitude = camera.position.z;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        sky.draw(&mut renderer, &wrap, &proj, &camera.view().mat);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Atmosphere example: sun at {:.1} degrees, altitude {:.0} m, exposure {:.2}",
+            elevation.to_degrees(), camera.position.z, sky.exposure));
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of blend states, side by side.
+//
+// The window is split into twelve panes, each drawn with its own scissor
+// rectangle and a transform shifted into it. Each pane first draws an opaque
+// background, a gradient from black to white under a faint checkerboard,
+// then three overlapping red, green and blue discs with the same alpha,
+// blended with the pane's state: the presets of `gfx::BlendPreset` first,
+// then blend equations and factors set by hand in a `gfx::state::Blend`.
+//
+// Press Up and Down to change the alpha of the discs.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4};
+use gfx::traits::*;
+use gfx::state::{Blend, BlendChannel, BlendValue, Equation, Factor};
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::text::{Font, Span, TextRenderer};
+use gfx_examples::{window, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Transform"]
+    transform: [[f32; 4]; 4],
+    // 1 for a disc, 0 for the background
+    #[name = "u_Disc"]
+    disc: f32,
+    #[name = "u_Color"]
+    color: [f32; 4],
+    // 1 to multiply the color by the alpha
+    #[name = "u_Premultiply"]
+    premultiply: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Transform;
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        gl_Position = u_Transform * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Disc;
+    uniform vec4 u_Color;
+    uniform float u_Premultiply;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        if (u_Disc < 0.5) {
+            vec2 square = floor(v_TexCoord * 8.0);
+            float check = mod(square.x + square.y, 2.0);
+            o_Color = vec4(vec3(v_TexCoord.x * 0.9 + 0.1 * check), 1.0);
+            return;
+        }
+        if (length(v_TexCoord - 0.5) > 0.5) {
+            discard;
+        }
+        vec4 color = u_Color;
+        if (u_Premultiply > 0.5) {
+            color.rgb *= color.a;
+        }
+        o_Color = color;
+    }
+";
+
+// The center of each disc in the pane, and its color
+const DISCS: [([f32; 2], [f32; 3]); 3] = [
+    ([0.0, 0.22], [1.0, 0.15, 0.1]),
+    ([-0.2, -0.13], [0.1, 0.9, 0.2]),
+    ([0.2, -0.13], [0.15, 0.3, 1.0]),
+];
+
+fn channel(equation: Equation, source: Factor, destination: Factor) -> BlendChannel {
+    BlendChannel {
+        equation: equation,
+        source: source,
+        destination: destination,
+    }
+}
+
+// The same channel for color and alpha
+fn custom(equation: Equation, source: Factor, destination: Factor) -> Blend {
+    Blend {
+        color: channel(equation, source, destination),
+        alpha: channel(equation, source, destination),
+        value: [0.0, 0.0, 0.0, 0.3],
+    }
+}
+
+// The label of each pane, its blend state, and whether its colors are
+// premultiplied by their alpha
+fn panes() -> Vec<(&'static str, gfx::DrawState, bool)> {
+    let state = gfx::DrawState::new();
+    let by_hand = |blend: Blend| {
+        let mut state = state.clone();
+        state.blend = Some(blend);
+        state
+    };
+    let src_alpha = Factor::ZeroPlus(BlendValue::SourceAlpha);
+    let inv_src_alpha = Factor::OneMinus(BlendValue::SourceAlpha);
+    vec![
+        ("no blending", state.clone(), false),
+        ("preset Alpha", state.clone().blend(gfx::BlendPreset::Alpha), false),
+        ("preset Add", state.clone().blend(gfx::BlendPreset::Add), false),
+        ("preset Multiply", state.clone().blend(gfx::BlendPreset::Multiply), false),
+        ("preset Invert", state.clone().blend(gfx::BlendPreset::Invert), false),
+        ("premultiplied alpha", by_hand(custom(Equation::Add, Factor::One, inv_src_alpha)), true),
+        ("additive with alpha", by_hand(custom(Equation::Add, src_alpha, Factor::One)), false),
+        ("screen", by_hand(custom(Equation::Add, Factor::One,
+                                  Factor::OneMinus(BlendValue::SourceColor))), false),
+        ("reverse subtract", by_hand(custom(Equation::RevSub, src_alpha, Factor::One)), false),
+        ("min", by_hand(custom(Equation::Min, Factor::One, Factor::One)), false),
+        ("max", by_hand(custom(Equation::Max, Factor::One, Factor::One)), false),
+        ("constant alpha 0.3", by_hand(custom(Equation::Add,
+                                              Factor::ZeroPlus(BlendValue::ConstAlpha),
+                                              Factor::OneMinus(BlendValue::ConstAlpha))), false),
+    ]
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Blending example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let mut meshes = MeshCache::new();
+    let (mesh, slice) = meshes.plane(&mut factory);
+    let data = Params {
+        transform: Matrix4::identity().into_fixed(),
+        disc: 0.0,
+        color: [1.0; 4],
+        premultiply: 0.0,
+        _dummy: std::marker::PhantomData,
+    };
+    let mut batch = context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new())
+                           .unwrap();
+    let panes = panes();
+
+    let mut hud = match Font::load_default(16) {
+        Ok(font) => Some(TextRenderer::new(&mut factory, font, 512)),
+        Err(e) => {
+            println!("{}, without labels: the panes are, row by row, {}", e,
+                     panes.iter().map(|p| p.0).collect::<Vec<_>>().join(", "));
+            None
+        },
+    };
+
+    // four columns and three rows
+    let (pane_w, pane_h) = (w / 4, h / 3);
+    let aspect = pane_w as f32 / pane_h as f32;
+    let mut alpha = 0.6f32;
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => alpha = (alpha + 0.1).min(1.0),
+                    VirtualKeyCode::Down => alpha = (alpha - 0.1).max(0.0),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for (i, &(label, ref state, premultiply)) in panes.iter().enumerate() {
+            let (col, row) = (i as u16 % 4, i as u16 / 4);
+            // squeezes the transform into the pane, whose bottom left corner
+            // is at (px, py) in pixels, counting from the bottom
+            let (px, py) = (col * pane_w, (2 - row) * pane_h);
+            let (sx, sy) = (pane_w as f32 / w as f32, pane_h as f32 / h as f32);
+            let pane = Matrix4::new(sx, 0.0, 0.0, 0.0,
+                                    0.0, sy, 0.0, 0.0,
+                                    0.0, 0.0, 1.0, 0.0,
+                                    (2.0 * px as f32 + pane_w as f32) / w as f32 - 1.0,
+                                    (2.0 * py as f32 + pane_h as f32) / h as f32 - 1.0,
+                                    0.0, 1.0);
+
+            batch.params.transform = pane.into_fixed();
+            batch.params.disc = 0.0;
+            batch.state = gfx::DrawState::new().scissor(px, py, pane_w, pane_h);
+            renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+            batch.params.disc = 1.0;
+            batch.params.premultiply = if premultiply { 1.0 } else { 0.0 };
+            batch.state = state.clone().scissor(px, py, pane_w, pane_h);
+            for &(center, color) in DISCS.iter() {
+                let disc = Matrix4::new(0.3 / aspect, 0.0, 0.0, 0.0,
+                                        0.0, 0.3, 0.0, 0.0,
+                                        0.0, 0.0, 1.0, 0.0,
+                                        center[0] / aspect, center[1], 0.0, 1.0);
+                batch.params.transform = pane.mul_m(&disc).into_fixed();
+                batch.params.color = [color[0], color[1], color[2], alpha];
+                renderer.draw(&(&batch, &context), &wrap).unwrap();
+            }
+
+            if let Some(ref mut hud) = hud {
+                hud.add(&[Span::new(label, [1.0, 0.85, 0.3, 1.0])],
+                        [(col * pane_w) as f32 + 10.0, (row * pane_h) as f32 + 10.0], None);
+            }
+        }
+        if let Some(ref mut hud) = hud {
+            hud.draw(&mut factory, &mut renderer, &wrap);
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Blending example: alpha {:.1}", alpha));
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of HDR bloom.
+//
+// The scene is rendered to a half float target, where the glowing spheres go
+// well above 1. A bright pass keeps what is above a threshold while halving
+// the resolution, and is halved again a few times into a chain of smaller
+// targets. Going back up the chain, each level is blurred into the larger
+// one, adding up into a wide glow with a sharp core. The glow is added to the
+// scene before it is tone mapped to the window.
+//
+// Press B to toggle the bloom, V to view the glow alone, Up and Down to change
+// the threshold, Left and Right to change the strength of the glow, Plus and
+// Minus to change the exposure, and Space to stop the spheres.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// levels of the chain, from half the window size down
+const LEVELS: usize = 6;
+const NUM_SPHERES: usize = 5;
+
+static SPHERE_COLORS: [[f32; 3]; NUM_SPHERES] = [
+    [8.0, 2.0, 0.5],
+    [0.5, 4.0, 10.0],
+    [6.0, 6.0, 1.0],
+    [1.0, 8.0, 2.0],
+    [10.0, 1.0, 6.0],
+];
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 1 for a sphere giving off its color, 0 for a surface lit by them
+    #[name = "u_Emissive"]
+    emissive: f32,
+    // the positions and colors of the spheres lighting the floor
+    #[name = "u_Lights"]
+    lights: [[f32; 4]; 4],
+    #[name = "u_LightColors"]
+    light_colors: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct DownParams<R: gfx::Resources> {
+    #[name = "u_Threshold"]
+    threshold: f32,
+    #[name = "t_Source"]
+    source: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct UpParams<R: gfx::Resources> {
+    #[name = "t_Source"]
+    source: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct CompositeParams<R: gfx::Resources> {
+    #[name = "u_Strength"]
+    strength: f32,
+    #[name = "u_Exposure"]
+    exposure: f32,
+    // 1 to show the glow alone
+    #[name = "u_GlowOnly"]
+    glow_only: f32,
+    #[name = "t_Scene"]
+    scene: gfx::shade::TextureParam<R>,
+    #[name = "t_Glow"]
+    glow: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec4 u_Offset;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Pos = u_Offset.w * a_Pos + u_Offset.xyz;
+        v_Normal = a_Normal;
+        gl_Position = u_ViewProj * vec4(v_Pos, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Emissive;
+    uniform mat4 u_Lights;
+    uniform mat4 u_LightColors;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 light = vec3(0.02);
+        for (int i = 0; i < 4; ++i) {
+            vec3 to_light = u_Lights[i].xyz - v_Pos;
+            float d2 = dot(to_light, to_light);
+            light += u_LightColors[i].rgb * max(dot(n, to_light), 0.0) / (d2 * sqrt(d2));
+        }
+        vec3 color = mix(u_Color * light, u_Color, u_Emissive);
+        o_Color = vec4(color, 1.0);
+    }
+";
+
+static QUAD_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static DOWN_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Threshold;
+    uniform sampler2D t_Source;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // four bilinear taps between the texels average a 4 by 4 block of
+        // the source, around the 2 by 2 block under this pixel
+        vec2 texel = 1.0 / vec2(textureSize(t_Source, 0));
+        vec3 c = texture(t_Source, v_TexCoord + texel * vec2(-1.0, -1.0)).rgb
+               + texture(t_Source, v_TexCoord + texel * vec2( 1.0, -1.0)).rgb
+               + texture(t_Source, v_TexCoord + texel * vec2(-1.0,  1.0)).rgb
+               + texture(t_Source, v_TexCoord + texel * vec2( 1.0,  1.0)).rgb;
+        c *= 0.25;
+        // keep what is brighter than the threshold, without changing its hue
+        float luma = dot(c, vec3(0.2126, 0.7152, 0.0722));
+        c *= max(luma - u_Threshold, 0.0) / max(luma, 1e-4);
+        o_Color = vec4(c, 1.0);
+    }
+";
+
+static UP_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Source;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // a 3 by 3 tent filter over the smaller level, added to the larger
+        vec2 texel = 1.0 / vec2(textureSize(t_Source, 0));
+        vec3 c = vec3(0.0);
+        for (int y = -1; y <= 1; ++y) {
+            for (int x = -1; x <= 1; ++x) {
+                float weight = (2.0 - abs(float(x))) * (2.0 - abs(float(y))) / 16.0;
+                c += weight * texture(t_Source, v_TexCoord + texel * vec2(x, y)).rgb;
+            }
+        }
+        o_Color = vec4(c, 1.0);
+    }
+";
+
+static COMPOSITE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Strength;
+    uniform float u_Exposure;
+    uniform float u_GlowOnly;
+    uniform sampler2D t_Scene;
+    uniform sampler2D t_Glow;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 scene = texture(t_Scene, v_TexCoord).rgb * (1.0 - u_GlowOnly);
+        vec3 color = scene + u_Strength * texture(t_Glow, v_TexCoord).rgb;
+        color = 1.0 - exp(-u_Exposure * color);
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+fn create_target<R: gfx::Resources, F: Factory<R>>(
+                 width: gfx::tex::Size, height: gfx::tex::Size, factory: &mut F)
+                 -> (gfx::Frame<R>, gfx::TextureHandle<R>) {
+    let texture_info = gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F16),
+    };
+    let texture = factory.create_texture(texture_info).unwrap();
+    let frame = gfx::Frame {
+        colors: vec![Plane::Texture(texture.clone(), 0, None)],
+        .. gfx::Frame::empty(width, height)
+    };
+    (frame, texture)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("HDR bloom example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let (mut scene_frame, scene_texture) = create_target(w, h, &mut factory);
+    scene_frame.depth = Some(Plane::Texture(factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap(), 0, None));
+    let chain: Vec<_> = (0 .. LEVELS).map(|i| {
+        let (cw, ch) = ((w >> (i + 1)).max(1), (h >> (i + 1)).max(1));
+        create_target(cw, ch, &mut factory)
+    }).collect();
+
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let mut meshes = MeshCache::new();
+    let (mut sphere, mut floor) = {
+        let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let mut batches = Vec::new();
+        let shapes = vec![meshes.sphere(&mut factory, 24, 48), meshes.plane(&mut factory)];
+        for (i, (mesh, slice)) in shapes.into_iter().enumerate() {
+            let data = SceneParams {
+                view_proj: Matrix4::identity().into_fixed(),
+                offset: if i == 0 { [0.0, 0.0, 0.0, 0.3] } else { [0.0, 0.0, -1.0, 12.0] },
+                color: [0.5; 3],
+                emissive: if i == 0 { 1.0 } else { 0.0 },
+                lights: [[0.0; 4]; 4],
+                light_colors: [[0.0; 4]; 4],
+                _dummy: std::marker::PhantomData,
+            };
+            batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+        }
+        let floor = batches.pop().unwrap();
+        (batches.pop().unwrap(), floor)
+    };
+
+    let (mesh, slice) = meshes.quad(&mut factory);
+    let mut down = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, DOWN_FRAGMENT_SRC).unwrap();
+        let data = DownParams {
+            threshold: 1.0,
+            source: (scene_texture.clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice.clone(), &gfx::DrawState::new())
+               .unwrap()
+    };
+    let mut up = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, UP_FRAGMENT_SRC).unwrap();
+        let data = UpParams {
+            source: (scene_texture.clone(), Some(sampler.clone())),
+        };
+        let state = gfx::DrawState::new().blend(gfx::BlendPreset::Add);
+        context.make_batch(&program, data, &mesh, slice.clone(), &state).unwrap()
+    };
+    let mut composite = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, COMPOSITE_FRAGMENT_SRC).unwrap();
+        let data = CompositeParams {
+            strength: 1.0,
+            exposure: 1.0,
+            glow_only: 0.0,
+            scene: (scene_texture.clone(), Some(sampler.clone())),
+            glow: (chain[0].1.clone(), Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(0.0, -7.0, 3.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_z(),
+    );
+    let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut bloom = true;
+    let mut strength = 1.0f32;
+    let mut moving = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let params = &mut composite.params;
+                    match key {
+                        VirtualKeyCode::B => bloom = !bloom,
+                        VirtualKeyCode::V => params.glow_only = 1.0 - params.glow_only,
+                        VirtualKeyCode::Up => down.params.threshold += 0.25,
+                        VirtualKeyCode::Down =>
+                            down.params.threshold = (down.params.threshold - 0.25).max(0.0),
+                        VirtualKeyCode::Right => strength *= 1.25,
+                        VirtualKeyCode::Left => strength *= 0.8,
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals => params.exposure *= 1.25,
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus =>
+                            params.exposure *= 0.8,
+                        VirtualKeyCode::Space => moving = !moving,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if moving {
+                angle += 0.3 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let mut positions = [[0.0f32; 4]; NUM_SPHERES];
+        for (i, pos) in positions.iter_mut().enumerate() {
+            let phase = a + 2.0 * PI * i as f32 / NUM_SPHERES as f32;
+            let radius = 2.0 + 0.8 * (3.0 * phase).sin();
+            *pos = [radius * phase.cos(), radius * phase.sin(), 0.2 + 0.6 * (2.0 * phase).cos(),
+                    0.3];
+        }
+        // the floor is lit by the first four spheres
+        let mut lights = [[0.0f32; 4]; 4];
+        let mut light_colors = [[0.0f32; 4]; 4];
+        for i in 0 .. 4 {
+            lights[i] = positions[i];
+            let c = SPHERE_COLORS[i];
+            light_colors[i] = [c[0], c[1], c[2], 0.0];
+        }
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &scene_frame);
+        floor.params.view_proj = view_proj;
+        floor.params.lights = lights;
+        floor.params.light_colors = light_colors;
+        renderer.draw(&(&floor, &context), &scene_frame).unwrap();
+        for (pos, color) in positions.iter().zip(SPHERE_COLORS.iter()) {
+            sphere.params.view_proj = view_proj;
+            sphere.params.offset = *pos;
+            sphere.params.color = *color;
+            renderer.draw(&(&sphere, &context), &scene_frame).unwrap();
+        }
+
+        if bloom {
+            // down the chain, thresholding only on the way to the first level
+            let threshold = down.params.threshold;
+            for i in 0 .. LEVELS {
+                down.params.source.0 = if i == 0 { scene_texture.clone() }
+                                       else { chain[i - 1].1.clone() };
+                let output = &chain[i].0;
+                renderer.draw(&(&down, &context), output).unwrap();
+                down.params.threshold = 0.0;
+            }
+            down.params.threshold = threshold;
+            // and back up, adding each level into the larger one
+            for i in (0 .. LEVELS - 1).rev() {
+                up.params.source.0 = chain[i + 1].1.clone();
+                renderer.draw(&(&up, &context), &chain[i].0).unwrap();
+            }
+        } else {
+            renderer.clear(clear_data, gfx::COLOR, &chain[0].0);
+        }
+
+        // every level adds to the glow, so it is scaled back by their number
+        composite.params.strength = strength / LEVELS as f32;
+        renderer.draw(&(&composite, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "HDR bloom example: threshold {:.2}, strength {:.2}, exposure {:.2}",
+            down.params.threshold, strength, composite.params.exposure));
+    }
+}
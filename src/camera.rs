@@ -0,0 +1,396 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cameras driven by window events.
+//!
+//! Both cameras look around a Z-up world with a yaw angle around Z, starting
+//! from +X, and a pitch angle above the horizon. Pass every event to
+//! `handle_event`, call `FlyCamera::update` once per simulation step, then
+//! build the view matrix with `view`.
+//!
+//! - `OrbitCamera` turns around a target while the left mouse button is held,
+//!   and the mouse wheel moves it closer or further.
+//! - `FlyCamera` looks around while the left mouse button is held, and moves
+//!   with W, A, S and D, E and Q for up and down, and Left Shift to go faster.
+//! - `WalkCamera` looks around the same way, and walks on the ground given to
+//!   its `update` with W, A, S and D, jumps with Space, and runs with Left
+//!   Shift.
+//! - `FollowCamera` trails a moving target on a spring arm, turned like an
+//!   `OrbitCamera`, and pulls in wherever the ground given to its `update`
+//!   would come between them.
+//!
+//! `oblique` clips a projection at a plane, for the mirrored or displaced
+//! cameras of reflections and portals.
+
+use std::f32::consts::PI;
+use cgmath::{Matrix4, Point, Point3, Vector, Vector2, Vector3, Vector4, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3};
+use glutin::{ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode};
+use walk::Walker;
+
+// Radians per pixel of mouse motion
+const MOUSE_SENSITIVITY: f32 = 0.005;
+// Keep away from the poles, where the view flips
+const MAX_PITCH: f32 = 0.49 * PI;
+
+/// Tracks the mouse motion while the left button is held.
+struct Drag {
+    active: bool,
+    last: Option<(i32, i32)>,
+}
+
+impl Drag {
+    fn new() -> Drag {
+        Drag { active: false, last: None }
+    }
+
+    // Returns the motion in pixels, if the event moved the dragged mouse
+    fn handle_event(&mut self, event: &Event) -> Option<(f32, f32)> {
+        match *event {
+            Event::MouseInput(state, MouseButton::Left) => {
+                self.active = state == ElementState::Pressed;
+                None
+            },
+            Event::MouseMoved((x, y)) => {
+                let delta = match self.last {
+                    Some((lx, ly)) if self.active => Some(((x - lx) as f32, (y - ly) as f32)),
+                    _ => None,
+                };
+                self.last = Some((x, y));
+                delta
+            },
+            _ => None,
+        }
+    }
+}
+
+fn direction(yaw: f32, pitch: f32) -> Vector3<f32> {
+    Vector3::new(yaw.cos() * pitch.cos(), yaw.sin() * pitch.cos(), pitch.sin())
+}
+
+fn clamp_pitch(pitch: f32) -> f32 {
+    pitch.max(-MAX_PITCH).min(MAX_PITCH)
+}
+
+/// A camera turning around a target point.
+pub struct OrbitCamera {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    drag: Drag,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Point3<f32>, distance: f32, yaw: f32, pitch: f32) -> OrbitCamera {
+        OrbitCamera {
+            target: target,
+            distance: distance,
+            yaw: yaw,
+            pitch: clamp_pitch(pitch),
+            drag: Drag::new(),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Some((dx, dy)) = self.drag.handle_event(event) {
+            self.yaw -= dx * MOUSE_SENSITIVITY;
+            self.pitch = clamp_pitch(self.pitch + dy * MOUSE_SENSITIVITY);
+        }
+        if let Event::MouseWheel(delta) = *event {
+            let lines = match delta {
+                MouseScrollDelta::LineDelta(_, y) => y,
+                MouseScrollDelta::PixelDelta(_, y) => y / 20.0,
+            };
+            self.distance *= 0.9f32.powf(lines);
+        }
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.target.add_v(&direction(self.yaw, self.pitch).mul_s(self.distance))
+    }
+
+    pub fn view(&self) -> AffineMatrix3<f32> {
+        Transform::look_at(&self.position(), &self.target, &Vector3::unit_z())
+    }
+}
+
+/// A free-flying first person camera.
+pub struct FlyCamera {
+    pub position: Point3<f32>,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second, sixteen times faster with Left Shift held.
+    pub speed: f32,
+    drag: Drag,
+    // held W, S, D, A, E, Q and Left Shift
+    keys: [bool; 7],
+}
+
+impl FlyCamera {
+    pub fn new(position: Point3<f32>, yaw: f32, pitch: f32, speed: f32) -> FlyCamera {
+        FlyCamera {
+            position: position,
+            yaw: yaw,
+            pitch: clamp_pitch(pitch),
+            speed: speed,
+            drag: Drag::new(),
+            keys: [false; 7],
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Some((dx, dy)) = self.drag.handle_event(event) {
+            self.yaw -= dx * MOUSE_SENSITIVITY;
+            self.pitch = clamp_pitch(self.pitch - dy * MOUSE_SENSITIVITY);
+        }
+        if let Event::KeyboardInput(state, _, Some(key)) = *event {
+            let index = match key {
+                VirtualKeyCode::W => 0,
+                VirtualKeyCode::S => 1,
+                VirtualKeyCode::D => 2,
+                VirtualKeyCode::A => 3,
+                VirtualKeyCode::E => 4,
+                VirtualKeyCode::Q => 5,
+                VirtualKeyCode::LShift => 6,
+                _ => return,
+            };
+            self.keys[index] = state == ElementState::Pressed;
+        }
+    }
+
+    /// Moves the camera by the keys held during `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        let axis = |plus: bool, minus: bool| (plus as i32 - minus as i32) as f32;
+        let k = &self.keys;
+        let motion = self.forward().mul_s(axis(k[0], k[1]))
+                         .add_v(&self.right().mul_s(axis(k[2], k[3])))
+                         .add_v(&Vector3::unit_z().mul_s(axis(k[4], k[5])));
+        if motion.length2() > 0.0 {
+            let speed = if k[6] { 16.0 * self.speed } else { self.speed };
+            self.position = self.position.add_v(&motion.normalize().mul_s(speed * dt));
+        }
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        direction(self.yaw, self.pitch)
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        self.forward().cross(&Vector3::unit_z()).normalize()
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.right().cross(&self.forward())
+    }
+
+    pub fn view(&self) -> AffineMatrix3<f32> {
+        Transform::look_at(&self.position, &self.position.add_v(&self.forward()),
+                           &Vector3::unit_z())
+    }
+}
+
+/// A first person camera walking on the ground.
+pub struct WalkCamera {
+    /// The body the eye sits on.
+    pub walker: Walker,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Height of the eye above the feet.
+    pub eye_height: f32,
+    drag: Drag,
+    // held W, S, D, A and Left Shift
+    keys: [bool; 5],
+    jump: bool,
+}
+
+impl WalkCamera {
+    /// Creates a camera with its feet at `position`.
+    pub fn new(position: Point3<f32>, yaw: f32, pitch: f32) -> WalkCamera {
+        WalkCamera {
+            walker: Walker::new(position),
+            yaw: yaw,
+            pitch: clamp_pitch(pitch),
+            eye_height: 1.7,
+            drag: Drag::new(),
+            keys: [false; 5],
+            jump: false,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Some((dx, dy)) = self.drag.handle_event(event) {
+            self.yaw -= dx * MOUSE_SENSITIVITY;
+            self.pitch = clamp_pitch(self.pitch - dy * MOUSE_SENSITIVITY);
+        }
+        if let Event::KeyboardInput(state, _, Some(key)) = *event {
+            let index = match key {
+                VirtualKeyCode::W => 0,
+                VirtualKeyCode::S => 1,
+                VirtualKeyCode::D => 2,
+                VirtualKeyCode::A => 3,
+                VirtualKeyCode::LShift => 4,
+                VirtualKeyCode::Space => {
+                    self.jump |= state == ElementState::Pressed;
+                    return;
+                },
+                _ => return,
+            };
+            self.keys[index] = state == ElementState::Pressed;
+        }
+    }
+
+    /// Walks by the keys held during `dt` seconds, on the ground `ground`
+    /// returns the height of.
+    pub fn update<F: Fn(f32, f32) -> f32>(&mut self, dt: f32, ground: F) {
+        let axis = |plus: bool, minus: bool| (plus as i32 - minus as i32) as f32;
+        let k = self.keys;
+        let (forward, right) = (direction(self.yaw, 0.0), direction(self.yaw - 0.5 * PI, 0.0));
+        let motion = forward.mul_s(axis(k[0], k[1])).add_v(&right.mul_s(axis(k[2], k[3])));
+        let mut heading = Vector2::new(motion.x, motion.y);
+        if heading.length2() > 0.0 {
+            heading = heading.normalize().mul_s(if k[4] { 2.0 } else { 1.0 });
+        }
+        self.walker.update(dt, heading, self.jump, ground);
+        self.jump = false;
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        self.walker.position.add_v(&Vector3::new(0.0, 0.0, self.eye_height))
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        direction(self.yaw, self.pitch)
+    }
+
+    pub fn view(&self) -> AffineMatrix3<f32> {
+        let eye = self.eye();
+        Transform::look_at(&eye, &eye.add_v(&self.forward()), &Vector3::unit_z())
+    }
+}
+
+// Samples along the arm of a `FollowCamera` before refining a hit
+const ARM_STEPS: usize = 32;
+const ARM_REFINE: usize = 8;
+
+/// A third person camera on a spring arm behind a target.
+pub struct FollowCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Length of the arm at rest.
+    pub distance: f32,
+    /// Height the camera keeps above the ground.
+    pub clearance: f32,
+    /// How fast the arm springs back out, per second.
+    pub stiffness: f32,
+    target: Point3<f32>,
+    length: f32,
+    drag: Drag,
+}
+
+impl FollowCamera {
+    pub fn new(target: Point3<f32>, distance: f32, yaw: f32, pitch: f32) -> FollowCamera {
+        FollowCamera {
+            yaw: yaw,
+            pitch: clamp_pitch(pitch),
+            distance: distance,
+            clearance: 0.3,
+            stiffness: 4.0,
+            target: target,
+            length: distance,
+            drag: Drag::new(),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Some((dx, dy)) = self.drag.handle_event(event) {
+            self.yaw -= dx * MOUSE_SENSITIVITY;
+            self.pitch = clamp_pitch(self.pitch + dy * MOUSE_SENSITIVITY);
+        }
+        if let Event::MouseWheel(delta) = *event {
+            let lines = match delta {
+                MouseScrollDelta::LineDelta(_, y) => y,
+                MouseScrollDelta::PixelDelta(_, y) => y / 20.0,
+            };
+            self.distance *= 0.9f32.powf(lines);
+        }
+    }
+
+    /// Follows `target` for `dt` seconds. The arm is cast from the target
+    /// against the ground `ground` returns the height of, and shortens at
+    /// once to stay clear of it, but grows back smoothly.
+    pub fn update<F: Fn(f32, f32) -> f32>(&mut self, dt: f32, target: Point3<f32>, ground: F) {
+        self.target = target;
+        let (dir, distance, clearance) = (direction(self.yaw, self.pitch), self.distance,
+                                          self.clearance);
+        let blocked = |t: f32| {
+            let p = target.add_v(&dir.mul_s(t));
+            p.z < ground(p.x, p.y) + clearance
+        };
+        let sample = |i: usize| distance * i as f32 / ARM_STEPS as f32;
+        let mut free = distance;
+        if let Some(i) = (1 .. ARM_STEPS + 1).find(|&i| blocked(sample(i))) {
+            // the hit is between the last free sample and this one
+            let (mut near, mut far) = (sample(i - 1), sample(i));
+            for _ in 0 .. ARM_REFINE {
+                let mid = 0.5 * (near + far);
+                if blocked(mid) { far = mid } else { near = mid }
+            }
+            free = near;
+        }
+        if free < self.length {
+            self.length = free;
+        } else {
+            self.length += (free - self.length) * (1.0 - (-self.stiffness * dt).exp());
+        }
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.target.add_v(&direction(self.yaw, self.pitch).mul_s(self.length))
+    }
+
+    /// The horizontal direction the camera looks in, to move the target by.
+    pub fn forward(&self) -> Vector3<f32> {
+        direction(self.yaw + PI, 0.0)
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        direction(self.yaw + 0.5 * PI, 0.0)
+    }
+
+    pub fn view(&self) -> AffineMatrix3<f32> {
+        Transform::look_at(&self.position(), &self.target, &Vector3::unit_z())
+    }
+}
+
+/// Moves the near plane of the perspective projection `proj` onto `plane`,
+/// given in view space with its normal towards the visible side, as
+/// described by Eric Lengyel in "Oblique View Frustum Depth Projection and
+/// Clipping". The camera has to be on the other side of the plane. The far
+/// plane tilts along, so some depth precision is lost.
+pub fn oblique(proj: &Matrix4<f32>, plane: Vector4<f32>) -> Matrix4<f32> {
+    let sign = |x: f32| if x > 0.0 { 1.0 } else if x < 0.0 { -1.0 } else { 0.0 };
+    // the corner of the frustum opposite to the plane
+    let q = Vector4::new((sign(plane.x) + proj.z.x) / proj.x.x,
+                         (sign(plane.y) + proj.z.y) / proj.y.y,
+                         -1.0,
+                         (1.0 + proj.z.z) / proj.w.z);
+    let c = plane.mul_s(2.0 / plane.dot(&q));
+    let mut m = *proj;
+    m.x.z = c.x - m.x.w;
+    m.y.z = c.y - m.y.w;
+    m.z.z = c.z - m.z.w;
+    m.w.z = c.w - m.w.w;
+    m
+}
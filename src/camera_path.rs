@@ -0,0 +1,199 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Camera paths through keyframes.
+//!
+//! A `CameraPath` holds the position, yaw and pitch of a camera at evenly
+//! spaced moments, and goes smoothly through all of them with uniform
+//! Catmull-Rom splines. Sampling depends on nothing but the time given, so
+//! with the time of a `Clock::fixed` a path plays back the same frames on
+//! every run, which makes it a repeatable track for benchmarks and frame
+//! captures.
+//!
+//! Paths are saved as text, a key per line. An example following a
+//! `FlyCamera` can take one from the file named by `PATH_VAR`:
+//!
+//! ```ignore
+//! let path = CameraPath::from_env();
+//! // every tick
+//! if let Some(ref path) = path {
+//!     path.apply(clock.time() as f32 % path.duration(), &mut camera);
+//! }
+//! ```
+
+use std::env;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use cgmath::Point3;
+use camera::FlyCamera;
+
+/// Environment variable holding the path of a camera path file.
+pub const PATH_VAR: &'static str = "GFX_EXAMPLES_CAMERA_PATH";
+
+#[derive(Clone, Copy, Debug)]
+pub struct Key {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+pub struct CameraPath {
+    keys: Vec<Key>,
+    /// Seconds from one key to the next.
+    pub interval: f32,
+}
+
+impl CameraPath {
+    pub fn new(interval: f32) -> CameraPath {
+        CameraPath {
+            keys: Vec::new(),
+            interval: interval,
+        }
+    }
+
+    /// Loads the keys of a file written by `save`, taking the interval from
+    /// its first line.
+    pub fn load(path: &str) -> Result<CameraPath, String> {
+        let file = try!(File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e)));
+        let mut camera_path = None;
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = try!(line.map_err(|e| format!("Failed to read {}: {}", path, e)));
+            let error = || format!("Malformed line {} of {}: {}", number + 1, path, line);
+            let v: Vec<f32> = try!(line.split_whitespace()
+                                       .map(|w| w.parse().map_err(|_| error()))
+                                       .collect());
+            if v.is_empty() {
+                continue;
+            }
+            // the interval, then x y z yaw pitch for every key
+            match camera_path {
+                None if v.len() == 1 && v[0] > 0.0 =>
+                    camera_path = Some(CameraPath::new(v[0])),
+                Some(ref mut p) if v.len() == 5 =>
+                    p.push(Point3::new(v[0], v[1], v[2]), v[3], v[4]),
+                _ => return Err(error()),
+            }
+        }
+        camera_path.ok_or(format!("Missing the interval on the first line of {}", path))
+    }
+
+    /// Loads the file named by `PATH_VAR`, if it is set.
+    pub fn from_env() -> Option<CameraPath> {
+        env::var(PATH_VAR).ok().and_then(|path| match CameraPath::load(&path) {
+            Ok(camera_path) if !camera_path.is_empty() => Some(camera_path),
+            Ok(_) => {
+                println!("{} has no keys, ignoring it", path);
+                None
+            },
+            Err(e) => {
+                println!("{}, ignoring it", e);
+                None
+            },
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut file = try!(File::create(path).map_err(|e| {
+            format!("Failed to create {}: {}", path, e)
+        }));
+        let mut text = format!("{}\n", self.interval);
+        for key in self.keys.iter() {
+            text.push_str(&format!("{} {} {} {} {}\n", key.position[0], key.position[1],
+                                   key.position[2], key.yaw, key.pitch));
+        }
+        file.write_all(text.as_bytes()).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// Adds a key at the end. The yaw is moved by whole turns to within
+    /// half a turn of the previous key, so the camera turns the short way.
+    pub fn push(&mut self, position: Point3<f32>, yaw: f32, pitch: f32) {
+        let mut yaw = yaw;
+        if let Some(last) = self.keys.last() {
+            yaw = last.yaw + wrap_angle(yaw - last.yaw);
+        }
+        self.keys.push(Key {
+            position: [position.x, position.y, position.z],
+            yaw: yaw,
+            pitch: pitch,
+        });
+    }
+
+    pub fn pop(&mut self) -> Option<Key> {
+        self.keys.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Seconds from the first key to the last.
+    pub fn duration(&self) -> f32 {
+        self.keys.len().saturating_sub(1) as f32 * self.interval
+    }
+
+    /// The camera at `time` seconds, clamped to the ends of the path, or
+    /// `None` if it has no keys.
+    pub fn sample(&self, time: f32) -> Option<Key> {
+        let last = match self.keys.len() {
+            0 => return None,
+            n => n - 1,
+        };
+        let t = (time / self.interval).max(0.0).min(last as f32);
+        let i = (t as usize).min(last.saturating_sub(1));
+        let f = t - i as f32;
+        // the ends repeat, so the path stops on them
+        let key = |k: isize| self.keys[k.max(0).min(last as isize) as usize];
+        let (k0, k1, k2, k3) = (key(i as isize - 1), key(i as isize), key(i as isize + 1),
+                                key(i as isize + 2));
+        let spline = |a: f32, b: f32, c: f32, d: f32| {
+            0.5 * (2.0 * b + (c - a) * f + (2.0 * a - 5.0 * b + 4.0 * c - d) * f * f
+                   + (3.0 * b - a - 3.0 * c + d) * f * f * f)
+        };
+        Some(Key {
+            position: [spline(k0.position[0], k1.position[0], k2.position[0], k3.position[0]),
+                       spline(k0.position[1], k1.position[1], k2.position[1], k3.position[1]),
+                       spline(k0.position[2], k1.position[2], k2.position[2], k3.position[2])],
+            yaw: spline(k0.yaw, k1.yaw, k2.yaw, k3.yaw),
+            pitch: spline(k0.pitch, k1.pitch, k2.pitch, k3.pitch),
+        })
+    }
+
+    /// Moves `camera` to where the path is at `time`.
+    pub fn apply(&self, time: f32, camera: &mut FlyCamera) {
+        if let Some(key) = self.sample(time) {
+            camera.position = Point3::new(key.position[0], key.position[1], key.position[2]);
+            camera.yaw = key.yaw;
+            camera.pitch = key.pitch;
+        }
+    }
+}
+
+// Moves an angle by whole turns to between -PI and PI
+fn wrap_angle(a: f32) -> f32 {
+    let r = (a + PI) % (2.0 * PI);
+    if r < 0.0 { r + PI } else { r - PI }
+}
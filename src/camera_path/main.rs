@@ -0,0 +1,350 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of recording and playing back a camera path.
+//
+// Fly around a field of towers and drop keys along the way. The path of
+// `gfx_examples::camera_path` goes through them with Catmull-Rom splines,
+// which pass through every key with a continuous direction, and plays back
+// from the time of the clock alone, so it shows the same frames on every
+// run. While flying, the curve and the keys are drawn in the scene.
+//
+// The path is loaded from the file given on the command line, or from
+// camera_path.txt, and saved back there. Other examples following a free-fly
+// camera, like the aerial perspective one, play such a file during their
+// benchmark and thumbnail runs when GFX_EXAMPLES_CAMERA_PATH names it.
+//
+// Fly with the keys of the free-fly camera. Press K to drop a key, Back to
+// remove the last one, C to clear them, P to play the path or stop, and
+// Return to save it.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::env;
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::camera_path::CameraPath;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const DEFAULT_PATH: &'static str = "camera_path.txt";
+// seconds between two keys
+const INTERVAL: f32 = 2.0;
+const MAX_KEYS: usize = 256;
+// points of the drawn curve between two keys
+const CURVE_STEPS: usize = 16;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct LineVertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float light = 0.35 + 0.65 * max(dot(n, normalize(vec3(0.4, 0.2, 1.0))), 0.0);
+        o_Color = vec4(u_Color * light, 1.0);
+    }
+";
+
+static LINE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static LINE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(u_Color, 1.0);
+    }
+";
+
+// Scales the shape to the half sizes and moves it to the center
+fn place(center: [f32; 3], size: [f32; 3]) -> Matrix4<f32> {
+    Matrix4::new(size[0], 0.0, 0.0, 0.0,
+                 0.0, size[1], 0.0, 0.0,
+                 0.0, 0.0, size[2], 0.0,
+                 center[0], center[1], center[2], 1.0)
+}
+
+// A loop around the middle of the field, for a first run without a file
+fn default_path() -> CameraPath {
+    let mut camera_path = CameraPath::new(INTERVAL);
+    for i in 0 .. 9 {
+        let a = i as f32 * 2.0 * PI / 8.0;
+        let radius = if i % 2 == 0 { 30.0 } else { 20.0 };
+        let height = if i % 2 == 0 { 8.0 } else { 3.0 };
+        // looking ahead along the loop, and a little towards the middle
+        camera_path.push(Point3::new(radius * a.cos(), radius * a.sin(), height),
+                         a + 0.6 * PI, -0.15);
+    }
+    camera_path
+}
+
+// The points of the curve, sampled for drawing
+fn curve(camera_path: &CameraPath) -> Vec<LineVertex> {
+    let steps = camera_path.len().saturating_sub(1) * CURVE_STEPS;
+    (0 .. steps + 1).filter_map(|i| {
+        camera_path.sample(i as f32 * camera_path.interval / CURVE_STEPS as f32)
+    }).map(|key| LineVertex { pos: key.position }).collect()
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Camera path example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let file = env::args().nth(1).unwrap_or(DEFAULT_PATH.to_string());
+    let mut camera_path = match CameraPath::load(&file) {
+        Ok(camera_path) => camera_path,
+        Err(e) => {
+            println!("{}, starting with a loop around the field", e);
+            default_path()
+        },
+    };
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let params = || Params {
+        model: Matrix4::identity().into_fixed(),
+        view_proj: Matrix4::identity().into_fixed(),
+        color: [1.0; 3],
+        _dummy: std::marker::PhantomData,
+    };
+    let mut meshes = MeshCache::new();
+    let (mut floor, mut cubes) = {
+        let (mesh, slice) = meshes.plane(&mut factory);
+        let floor = context.make_batch(&program, params(), &mesh, slice, &state).unwrap();
+        let (mesh, slice) = meshes.cube(&mut factory);
+        let cubes = context.make_batch(&program, params(), &mesh, slice, &state).unwrap();
+        (floor, cubes)
+    };
+
+    // the towers, on a jittered grid with a clearing in the middle
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut towers = Vec::new();
+    for y in -6 .. 7 {
+        for x in -6 .. 7 {
+            if x * x + y * y < 4 {
+                continue;
+            }
+            let height = rng.gen_range(1.0f32, 8.0);
+            let center = [x as f32 * 8.0 + rng.gen_range(-2.0, 2.0),
+                          y as f32 * 8.0 + rng.gen_range(-2.0, 2.0), height];
+            let shade = rng.gen_range(0.5f32, 1.0);
+            towers.push((place(center, [1.5, 1.5, height]).into_fixed(),
+                         [shade, shade * 0.8, shade * 0.6]));
+        }
+    }
+
+    let line_buffer = factory.create_buffer::<LineVertex>(MAX_KEYS * CURVE_STEPS + 1,
+                                                          gfx::BufferUsage::Dynamic);
+    let mut line = {
+        let mesh = gfx::Mesh::from_format(line_buffer.clone(),
+                                          (MAX_KEYS * CURVE_STEPS + 1) as gfx::VertexCount);
+        let slice = mesh.to_slice(gfx::PrimitiveType::LineStrip);
+        let program = factory.link_program(LINE_VERTEX_SRC, LINE_FRAGMENT_SRC).unwrap();
+        context.make_batch(&program, params(), &mesh, slice, &state).unwrap()
+    };
+    let mut curve_dirty = true;
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 300.0);
+    let mut camera = FlyCamera::new(Point3::new(0.0, -40.0, 6.0), 0.5 * PI, -0.1, 10.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.55, 0.65, 0.8, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // the time the playback started at, while playing
+    let mut playing = Some(0.0);
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            if playing.is_none() {
+                camera.handle_event(&event);
+            }
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::K if playing.is_none() && camera_path.len() < MAX_KEYS => {
+                        camera_path.push(camera.position, camera.yaw, camera.pitch);
+                        curve_dirty = true;
+                    },
+                    VirtualKeyCode::Back if playing.is_none() => {
+                        camera_path.pop();
+                        curve_dirty = true;
+                    },
+                    VirtualKeyCode::C if playing.is_none() => {
+                        camera_path.clear();
+                        curve_dirty = true;
+                    },
+                    VirtualKeyCode::P => {
+                        playing = match playing {
+                            None if !camera_path.is_empty() => Some(clock.time()),
+                            _ => None,
+                        };
+                    },
+                    VirtualKeyCode::Return => match camera_path.save(&file) {
+                        Ok(()) => println!("Saved {} keys to {}", camera_path.len(), file),
+                        Err(e) => println!("{}", e),
+                    },
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            match playing {
+                Some(start) => {
+                    let t = (clock.time() - start) as f32;
+                    camera_path.apply(t, &mut camera);
+                    // back to flying where the path ends
+                    if t >= camera_path.duration() {
+                        playing = None;
+                    }
+                },
+                None => camera.update(clock.step()),
+            }
+        }
+
+        if curve_dirty {
+            let points = curve(&camera_path);
+            factory.update_buffer(&line_buffer, &points, 0);
+            line.slice.end = points.len() as gfx::VertexCount;
+            curve_dirty = false;
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+
+        floor.params.view_proj = view_proj;
+        floor.params.model = place([0.0; 3], [60.0, 60.0, 1.0]).into_fixed();
+        floor.params.color = [0.4, 0.5, 0.3];
+        renderer.draw(&(&floor, &context), &wrap).unwrap();
+
+        cubes.params.view_proj = view_proj;
+        for &(model, color) in towers.iter() {
+            cubes.params.model = model;
+            cubes.params.color = color;
+            renderer.draw(&(&cubes, &context), &wrap).unwrap();
+        }
+
+        if playing.is_none() {
+            line.params.view_proj = view_proj;
+            line.params.color = [1.0, 0.9, 0.2];
+            renderer.draw(&(&line, &context), &wrap).unwrap();
+            cubes.params.color = [1.0, 0.3, 0.2];
+            for key in camera_path.keys().iter() {
+                cubes.params.model = place(key.position, [0.2; 3]).into_fixed();
+                renderer.draw(&(&cubes, &context), &wrap).unwrap();
+            }
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&match playing {
+            Some(start) => format!("Camera path example: playing, {:.1} of {:.1} s",
+                                   clock.time() - start, camera_path.duration()),
+            None => format!("Camera path example: flying, {} keys", camera_path.len()),
+        });
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Thumbnail capture, driven by the `thumbnail` binary.
+//!
+//! The binary starts every example with `GFX_EXAMPLES_THUMBNAIL` set to the
+//! output path. While that variable is present an example uses `SEED` for
+//! its random content, runs its simulation on a `Clock::fixed` instead of the
+//! wall clock, and exits after writing the requested frame.
+
+use std::env;
+use std::iter::repeat;
+use std::path::PathBuf;
+use gl;
+use gl::types::*;
+use image;
+
+/// Environment variable holding the PNG output path.
+pub const PATH_VAR: &'static str = "GFX_EXAMPLES_THUMBNAIL";
+/// Environment variable holding the index of the frame to capture.
+pub const FRAME_VAR: &'static str = "GFX_EXAMPLES_THUMBNAIL_FRAME";
+/// Seed to use for random content while capturing.
+pub const SEED: u32 = 0x5eed;
+/// Frame captured when `FRAME_VAR` is not set.
+pub const DEFAULT_FRAME: u32 = 60;
+
+pub struct Thumbnail {
+    path: PathBuf,
+    frame: u32,
+    current: u32,
+}
+
+impl Thumbnail {
+    /// Returns the capture request passed by the `thumbnail` binary, if any.
+    pub fn from_env() -> Option<Thumbnail> {
+        env::var_os(PATH_VAR).map(|path| Thumbnail {
+            path: PathBuf::from(path),
+            frame: env::var(FRAME_VAR).ok()
+                                      .and_then(|s| s.parse().ok())
+                                      .unwrap_or(DEFAULT_FRAME),
+            current: 0,
+        })
+    }
+
+    /// Call once per frame, once the frame has been submitted and before the
+    /// buffers are swapped, since the back buffer is undefined after a swap.
+    ///
+    /// On the requested frame the back buffer is read back and written to
+    /// the output path, and `true` is returned to tell the example to quit.
+    pub fn end_frame<F>(&mut self, loader: F, (width, height): (u16, u16)) -> bool
+                        where F: FnMut(&str) -> *const GLvoid {
+        if self.current < self.frame {
+            self.current += 1;
+            return false;
+        }
+
+        let gl = gl::Gl::load_with(loader);
+        let stride = width as usize * 4;
+        let mut pixels: Vec<u8> = repeat(0u8).take(stride * height as usize).collect();
+        unsafe {
+            gl.ReadBuffer(gl::BACK);
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.ReadPixels(0, 0, width as GLsizei, height as GLsizei,
+                          gl::RGBA, gl::UNSIGNED_BYTE,
+                          pixels.as_mut_ptr() as *mut GLvoid);
+        }
+
+        // GL returns the rows bottom-up, PNG expects them top-down
+        let flipped: Vec<u8> = pixels.chunks(stride).rev()
+                                     .flat_map(|row| row.iter().cloned())
+                                     .collect();
+        image::save_buffer(&self.path, &flipped, width as u32, height as u32,
+                           image::RGBA(8))
+              .ok().expect("Failed to write the thumbnail");
+        true
+    }
+}
@@ -0,0 +1,542 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of caustics, the bright lines sunlight draws under
+// water.
+//
+// Waves focus the sunlight they refract in some places and spread it in
+// others. Each frame, a grid covering a tile of the water surface is
+// rendered into a caustics texture: every vertex is moved to where the ray
+// of sunlight refracted there meets a plane at a reference depth, and every
+// fragment adds how much surface area the light in it came through, as in
+// the WebGL water demo of Evan Wallace. The waves repeat with the tile, so
+// the texture does too.
+//
+// The geometry under water then looks up the texture where the sunlight
+// reaching it would have come through a flat surface, projecting the
+// pattern along the light. The caustics grow from nothing at the surface to
+// their full contrast at the reference depth, and the light that makes them
+// gets bluer and dimmer the deeper it goes.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press C to
+// toggle the caustics, Up and Down to raise and lower the sun, Left and
+// Right to turn it around, and Plus and Minus to change the height of the
+// waves.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// Remember to also change the constants in the shaders
+const TILE: f32 = 8.0;
+const CAUSTICS_SIZE: gfx::tex::Size = 512;
+// the depth the caustics are computed at, and the depth of the pool
+const REFERENCE_DEPTH: f32 = 3.0;
+const POOL_DEPTH: f32 = 4.0;
+const POOL_HALF_WIDTH: f32 = 8.0;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 2],
+}
+
+#[shader_param]
+struct CausticsParams<R: gfx::Resources> {
+    #[name = "u_Time"]
+    time: f32,
+    #[name = "u_Amplitude"]
+    amplitude: f32,
+    #[name = "u_SunDir"]
+    sun_dir: [f32; 3],
+    #[name = "u_Depth"]
+    depth: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_Scale"]
+    scale: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "u_Eye"]
+    eye: [f32; 3],
+    #[name = "u_SunDir"]
+    sun_dir: [f32; 3],
+    #[name = "u_Depth"]
+    depth: f32,
+    // 1 to draw the caustics, 0 for the light of a flat surface
+    #[name = "u_Caustics"]
+    caustics: f32,
+    #[name = "t_Caustics"]
+    caustics_tex: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct WaterParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Time"]
+    time: f32,
+    #[name = "u_Amplitude"]
+    amplitude: f32,
+    #[name = "u_Eye"]
+    eye: [f32; 3],
+    #[name = "u_SunDir"]
+    sun_dir: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+// The waves, shared by the caustics and the water surface.
+static WAVES_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+    const float TILE = 8.0;
+    // wave numbers in whole waves per tile, so the waves repeat with it,
+    // and amplitudes
+    const vec3 WAVES[4] = vec3[](
+        vec3(1.0, 2.0, 0.06),
+        vec3(3.0, -1.0, 0.03),
+        vec3(-2.0, 3.0, 0.025),
+        vec3(5.0, 2.0, 0.012)
+    );
+
+    uniform float u_Time;
+    uniform float u_Amplitude;
+
+    // the height of the surface, and its slopes along x and y
+    vec3 wave(vec2 p) {
+        vec3 sum = vec3(0.0);
+        for (int i = 0; i < 4; i++) {
+            vec2 k = 2.0 * PI / TILE * WAVES[i].xy;
+            // deep water waves, which travel faster the longer they are
+            float omega = sqrt(9.81 * length(k));
+            float phase = dot(k, p) - omega * u_Time + 1.7 * float(i);
+            float a = u_Amplitude * WAVES[i].z;
+            sum += vec3(a * sin(phase), a * cos(phase) * k);
+        }
+        return sum;
+    }
+
+    vec3 wave_normal(vec2 p) {
+        return normalize(vec3(-wave(p).yz, 1.0));
+    }
+";
+
+static CAUSTICS_VERTEX_SRC: &'static [u8] = b"
+    const float ETA = 1.0 / 1.33;
+
+    uniform vec3 u_SunDir;
+    uniform float u_Depth;
+    in vec2 a_Pos;
+    out vec2 v_Old;
+    out vec2 v_New;
+
+    void main() {
+        vec3 r = refract(-u_SunDir, wave_normal(a_Pos), ETA);
+        vec2 hit = a_Pos + r.xy * u_Depth / -r.z;
+        // back along the ray a flat surface would refract, to where it
+        // would have come in, which is what the texture is looked up by
+        vec3 flat_ray = refract(-u_SunDir, vec3(0.0, 0.0, 1.0), ETA);
+        v_Old = a_Pos;
+        v_New = hit + flat_ray.xy * u_Depth / flat_ray.z;
+        gl_Position = vec4(v_New / (0.5 * TILE), 0.0, 1.0);
+    }
+";
+
+static CAUSTICS_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec2 v_Old;
+    in vec2 v_New;
+    out vec4 o_Color;
+
+    void main() {
+        // the area of surface the light comes through, over the area it
+        // lights at the reference depth
+        float old_area = length(dFdx(v_Old)) * length(dFdy(v_Old));
+        float new_area = length(dFdx(v_New)) * length(dFdy(v_New));
+        o_Color = vec4(vec3(min(old_area / max(new_area, 1e-8), 20.0)), 1.0);
+    }
+";
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    uniform vec3 u_Scale;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        v_World = u_Scale * a_Pos + u_Offset;
+        v_Normal = a_Normal / u_Scale;
+        gl_Position = u_ViewProj * vec4(v_World, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float TILE = 8.0;
+    const float ETA = 1.0 / 1.33;
+    // of the light, per unit traveled in the water
+    const vec3 ABSORPTION = vec3(0.3, 0.08, 0.05);
+    const vec3 SUN = vec3(2.2, 2.1, 1.9);
+    const vec3 SKY = vec3(0.25, 0.3, 0.4);
+
+    uniform vec3 u_Color;
+    uniform vec3 u_Eye;
+    uniform vec3 u_SunDir;
+    uniform float u_Depth;
+    uniform float u_Caustics;
+    uniform sampler2D t_Caustics;
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        // tiles
+        vec3 cell = floor(2.0 * v_World + 0.01 * n);
+        float check = mod(cell.x + cell.y + cell.z, 2.0);
+        vec3 albedo = u_Color * (0.85 + 0.15 * check);
+
+        float depth = -v_World.z;
+        vec3 color;
+        if (depth > 0.0) {
+            vec3 flat_ray = refract(-u_SunDir, vec3(0.0, 0.0, 1.0), ETA);
+            vec2 entry = v_World.xy + flat_ray.xy * depth / flat_ray.z;
+            float caustics = texture(t_Caustics, entry / TILE + 0.5).r;
+            caustics = mix(1.0, caustics, u_Caustics * clamp(depth / u_Depth, 0.0, 1.0));
+            vec3 sun = SUN * caustics * exp(-ABSORPTION * depth / -flat_ray.z);
+            vec3 light = sun * max(dot(n, -flat_ray), 0.0) + SKY * exp(-ABSORPTION * depth);
+            // on the way to the eye, only the part of the ray under water
+            // absorbs the light
+            float height = u_Eye.z - v_World.z;
+            float path = length(u_Eye - v_World) * (height > depth ? depth / height : 1.0);
+            vec3 transmitted = exp(-ABSORPTION * path);
+            color = albedo * light * transmitted + 0.15 * SKY * (1.0 - transmitted);
+        } else {
+            color = albedo * (SUN * max(dot(n, u_SunDir), 0.0) + SKY);
+        }
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+static WATER_VERTEX_SRC: &'static [u8] = b"
+    uniform mat4 u_ViewProj;
+    in vec2 a_Pos;
+    out vec3 v_World;
+
+    void main() {
+        v_World = vec3(a_Pos, wave(a_Pos).x);
+        gl_Position = u_ViewProj * vec4(v_World, 1.0);
+    }
+";
+
+static WATER_FRAGMENT_SRC: &'static [u8] = b"
+    uniform vec3 u_Eye;
+    uniform vec3 u_SunDir;
+    in vec3 v_World;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = wave_normal(v_World.xy);
+        vec3 v = normalize(u_Eye - v_World);
+        if (dot(n, v) < 0.0) {
+            n = -n;
+        }
+        float fresnel = 0.02 + 0.98 * pow(1.0 - max(dot(n, v), 0.0), 5.0);
+        vec3 r = reflect(-v, n);
+        vec3 sky = mix(vec3(0.75, 0.8, 0.85), vec3(0.35, 0.5, 0.8), max(r.z, 0.0));
+        float sun = pow(max(dot(r, u_SunDir), 0.0), 400.0);
+        // a little of the water itself, so the surface shows from above
+        float alpha = mix(0.1, 1.0, fresnel);
+        vec3 color = (fresnel * sky + (alpha - fresnel) * vec3(0.05, 0.2, 0.25)) / alpha
+                   + 8.0 * sun;
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), alpha);
+    }
+";
+
+// A grid of `segments` squares a side, from `-extent` to `extent` in x and y
+fn grid(extent: f32, segments: u16) -> (Vec<Vertex>, Vec<u16>) {
+    let step = 2.0 * extent / segments as f32;
+    let mut vertices = Vec::new();
+    for j in 0 .. segments + 1 {
+        for i in 0 .. segments + 1 {
+            vertices.push(Vertex {
+                pos: [i as f32 * step - extent, j as f32 * step - extent],
+            });
+        }
+    }
+    let row = segments + 1;
+    let mut indices = Vec::new();
+    for j in 0 .. segments {
+        for i in 0 .. segments {
+            let a = j * row + i;
+            indices.extend([a, a + 1, a + row + 1, a, a + row + 1, a + row].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Caustics example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let caustics_tex = factory.create_texture(gfx::tex::TextureInfo {
+        width: CAUSTICS_SIZE,
+        height: CAUSTICS_SIZE,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::R, gfx::attrib::FloatSize::F16),
+    }).unwrap();
+    let caustics_frame = gfx::Frame {
+        colors: vec![Plane::Texture(caustics_tex.clone(), 0, None)],
+        .. gfx::Frame::empty(CAUSTICS_SIZE, CAUSTICS_SIZE)
+    };
+
+    // The grid goes past the tile, for the light bent into it from around
+    let mut caustics = {
+        let (vertex_data, index_data) = grid(0.75 * TILE, 192);
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let vs = [WAVES_SRC, CAUSTICS_VERTEX_SRC].concat();
+        let program = factory.link_program(&vs, CAUSTICS_FRAGMENT_SRC).unwrap();
+        let data = CausticsParams {
+            time: 0.0,
+            amplitude: 1.0,
+            sun_dir: [0.0, 0.0, 1.0],
+            depth: REFERENCE_DEPTH,
+            _dummy: std::marker::PhantomData,
+        };
+        // light coming through overlapping folds adds up
+        let state = gfx::DrawState::new().blend(gfx::BlendPreset::Add);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Tile)
+    );
+    let mut meshes = MeshCache::new();
+    let mut batches = Vec::new();
+    for (mesh, slice) in vec![meshes.sphere(&mut factory, 24, 48), meshes.cube(&mut factory),
+                              meshes.plane(&mut factory)].into_iter() {
+        let data = SceneParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [0.0; 3],
+            scale: [1.0; 3],
+            color: [1.0; 3],
+            eye: [0.0; 3],
+            sun_dir: [0.0, 0.0, 1.0],
+            depth: REFERENCE_DEPTH,
+            caustics: 1.0,
+            caustics_tex: (caustics_tex.clone(), Some(sampler.clone())),
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+    }
+    let mut floor = batches.pop().unwrap();
+    floor.params.offset = [0.0, 0.0, -POOL_DEPTH];
+    floor.params.scale = [POOL_HALF_WIDTH, POOL_HALF_WIDTH, 1.0];
+    floor.params.color = [0.75, 0.85, 0.9];
+    let mut cube = batches.pop().unwrap();
+    let mut sphere = batches.pop().unwrap();
+
+    // offset, scale and color of the boxes: the rim around the pool, down
+    // to its floor, and a few steps and blocks in it
+    let (half, rim_depth) = (POOL_HALF_WIDTH, 0.5 * POOL_DEPTH + 0.2);
+    let boxes = [
+        ([0.0, half + 1.0, 0.2 - rim_depth], [half + 2.0, 1.0, rim_depth], [0.8, 0.8, 0.75]),
+        ([0.0, -half - 1.0, 0.2 - rim_depth], [half + 2.0, 1.0, rim_depth], [0.8, 0.8, 0.75]),
+        ([half + 1.0, 0.0, 0.2 - rim_depth], [1.0, half, rim_depth], [0.8, 0.8, 0.75]),
+        ([-half - 1.0, 0.0, 0.2 - rim_depth], [1.0, half, rim_depth], [0.8, 0.8, 0.75]),
+        ([-6.5, -6.5, -3.0], [1.5, 1.5, 1.0], [0.9, 0.75, 0.5]),
+        ([-6.5, -3.5, -3.5], [1.5, 1.5, 0.5], [0.9, 0.75, 0.5]),
+        ([4.0, 3.0, -3.25], [1.0, 2.5, 0.75], [0.7, 0.3, 0.3]),
+    ];
+    let spheres = [
+        ([0.0, 0.0, -3.0], 1.0, [0.95, 0.95, 0.9]),
+        ([2.5, -3.0, -3.4], 0.6, [0.3, 0.7, 0.3]),
+        ([-2.0, 4.0, -2.5], 1.5, [0.9, 0.6, 0.2]),
+    ];
+
+    let mut water = {
+        let (vertex_data, index_data) = grid(POOL_HALF_WIDTH, 160);
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let vs = [WAVES_SRC, WATER_VERTEX_SRC].concat();
+        let fs = [WAVES_SRC, WATER_FRAGMENT_SRC].concat();
+        let program = factory.link_program(&vs, &fs).unwrap();
+        let data = WaterParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            time: 0.0,
+            amplitude: 1.0,
+            eye: [0.0; 3],
+            sun_dir: [0.0, 0.0, 1.0],
+            _dummy: std::marker::PhantomData,
+        };
+        let state = gfx::DrawState::new()
+            .depth(gfx::state::Comparison::LessEqual, false)
+            .blend(gfx::BlendPreset::Alpha);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, -2.0), 18.0, -1.2, 0.7);
+
+    let clear_data = gfx::ClearData {
+        color: [0.55, 0.7, 0.9, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+    let caustics_clear = gfx::ClearData {
+        color: [0.0; 4],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut caustics_on = true;
+    let mut amplitude = 1.0f32;
+    let (mut elevation, mut azimuth) = (1.1f32, 0.6f32);
+    let mut time = 0.0f32;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::C => caustics_on = !caustics_on,
+                    VirtualKeyCode::Up => elevation = (elevation + 0.1).min(0.5 * PI),
+                    VirtualKeyCode::Down => elevation = (elevation - 0.1).max(0.2),
+                    VirtualKeyCode::Left => azimuth -= 0.2,
+                    VirtualKeyCode::Right => azimuth += 0.2,
+                    VirtualKeyCode::Add | VirtualKeyCode::Equals => amplitude *= 1.25,
+                    VirtualKeyCode::Subtract | VirtualKeyCode::Minus => amplitude *= 0.8,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            time = clock.time() as f32;
+        }
+        let sun_dir = [azimuth.cos() * elevation.cos(), azimuth.sin() * elevation.cos(),
+                       elevation.sin()];
+        let eye = camera.position().into_fixed();
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+
+        renderer.clear(caustics_clear, gfx::COLOR, &caustics_frame);
+        caustics.params.time = time;
+        caustics.params.amplitude = amplitude;
+        caustics.params.sun_dir = sun_dir;
+        renderer.draw(&(&caustics, &context), &caustics_frame).unwrap();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for batch in [&mut floor, &mut cube, &mut sphere].iter_mut() {
+            batch.params.view_proj = view_proj;
+            batch.params.eye = eye;
+            batch.params.sun_dir = sun_dir;
+            batch.params.caustics = if caustics_on { 1.0 } else { 0.0 };
+        }
+        renderer.draw(&(&floor, &context), &wrap).unwrap();
+        for &(offset, scale, color) in boxes.iter() {
+            cube.params.offset = offset;
+            cube.params.scale = scale;
+            cube.params.color = color;
+            renderer.draw(&(&cube, &context), &wrap).unwrap();
+        }
+        for &(offset, radius, color) in spheres.iter() {
+            sphere.params.offset = offset;
+            sphere.params.scale = [radius; 3];
+            sphere.params.color = color;
+            renderer.draw(&(&sphere, &context), &wrap).unwrap();
+        }
+
+        water.params.view_proj = view_proj;
+        water.params.time = time;
+        water.params.amplitude = amplitude;
+        water.params.eye = eye;
+        water.params.sun_dir = sun_dir;
+        renderer.draw(&(&water, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Caustics example: {}, waves {:.2}",
+                                       if caustics_on { "caustics" } else { "flat light" },
+                                       amplitude));
+    }
+}
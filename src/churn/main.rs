@@ -0,0 +1,337 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example that creates and destroys resources all the time, to
+// put the handle management of gfx-rs under pressure.
+//
+// Every frame, a batch of tiles is created, each with a texture of its own,
+// its own vertex and index buffers, and a sampler. Every few frames a new
+// program is linked too, with its own tint compiled into the shader. Each
+// tile lives for a random number of frames, drawn with its texture, and is
+// then dropped along with its handles. The resources they held are only
+// freed by `factory.cleanup()` at the end of the frame, once the renderer
+// no longer needs them.
+//
+// The window title shows how many resources are alive, how many bytes of
+// texture and buffer data they hold, and how many were created and
+// destroyed per second. Once a second, the same goes to the console, along
+// with the resident memory of the process where that is known, and how much
+// it grew since the tiles first reached a steady number. If handles leaked,
+// that growth would not stop.
+//
+// Press Up and Down to change the number of tiles created per frame, P to
+// stop linking new programs, and Space to pause the churn.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+extern crate time;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use rand::Rng;
+use gfx::traits::*;
+use gfx_examples::{window, Thumbnail};
+use time::precise_time_s;
+
+// how many frames a tile lives, at most
+const MAX_LIFETIME: u64 = 120;
+// a new program is linked every so many frames
+const PROGRAM_EVERY: u64 = 10;
+const GRID: usize = 16;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 2],
+    #[name = "a_TexCoord"]
+    tex_coord: [f32; 2],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "t_Tile"]
+    tile: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec2 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        gl_Position = vec4(a_Pos, 0.0, 1.0);
+    }
+";
+
+// Each program has its own tint in place of the `{}`
+static FRAGMENT_TEMPLATE: &'static str = "
+    #version 150 core
+
+    const vec3 TINT = vec3({});
+    uniform sampler2D t_Tile;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(texture(t_Tile, v_TexCoord).rgb * TINT, 1.0);
+    }
+";
+
+// A tile and everything it holds, all freed once it is dropped
+struct Tile<R: gfx::Resources> {
+    batch: gfx::batch::OwnedBatch<Params<R>>,
+    program: u64,
+    expires: u64,
+    bytes: usize,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Counts {
+    textures: usize,
+    buffers: usize,
+    samplers: usize,
+    programs: usize,
+}
+
+impl Counts {
+    fn total(&self) -> usize {
+        self.textures + self.buffers + self.samplers + self.programs
+    }
+}
+
+// Links a new program with a random tint
+fn create_program<R, F, G>(factory: &mut F, rng: &mut G) -> gfx::ProgramHandle<R>
+                           where R: gfx::Resources, F: gfx::Factory<R>, G: Rng {
+    let tint = format!("{:.3}, {:.3}, {:.3}", rng.gen_range(0.5f32, 1.0),
+                       rng.gen_range(0.5f32, 1.0), rng.gen_range(0.5f32, 1.0));
+    let fragment = FRAGMENT_TEMPLATE.replace("{}", &tint);
+    factory.link_program(VERTEX_SRC, fragment.as_bytes()).unwrap()
+}
+
+// Creates a tile in a random cell of the grid, with a checkerboard texture
+// of a random size and color
+fn create_tile<R, F, G>(factory: &mut F, rng: &mut G, program: &gfx::ProgramHandle<R>,
+                        program_id: u64, frame: u64) -> Tile<R>
+                        where R: gfx::Resources, F: gfx::Factory<R>, G: Rng {
+    let size = 16u16 << rng.gen_range(0, 5);
+    let color = [rng.gen_range(64, 256) as u8, rng.gen_range(64, 256) as u8,
+                 rng.gen_range(64, 256) as u8, 255];
+    let mut texels = Vec::with_capacity(size as usize * size as usize * 4);
+    for y in 0 .. size {
+        for x in 0 .. size {
+            let k = if (x / 8 + y / 8) % 2 == 0 { 1 } else { 2 };
+            texels.extend(color.iter().map(|&c| c / k));
+        }
+    }
+    let texture = factory.create_texture_rgba8(size, size, false).unwrap();
+    factory.update_texture(&texture, &texture.get_info().to_image_info(), &texels, None)
+           .unwrap();
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let cell = 2.0 / GRID as f32;
+    let (x, y) = (rng.gen_range(0, GRID) as f32 * cell - 1.0,
+                  rng.gen_range(0, GRID) as f32 * cell - 1.0);
+    let (x0, y0, x1, y1) = (x + 0.1 * cell, y + 0.1 * cell, x + 0.9 * cell, y + 0.9 * cell);
+    let vertex_data = [
+        Vertex { pos: [x0, y0], tex_coord: [0.0, 0.0] },
+        Vertex { pos: [x1, y0], tex_coord: [1.0, 0.0] },
+        Vertex { pos: [x1, y1], tex_coord: [1.0, 1.0] },
+        Vertex { pos: [x0, y1], tex_coord: [0.0, 1.0] },
+    ];
+    let index_data: [u16; 6] = [0, 1, 2, 2, 3, 0];
+    let mesh = factory.create_mesh(&vertex_data);
+    let slice = factory.create_buffer_index::<u16>(&index_data)
+                       .to_slice(gfx::PrimitiveType::TriangleList);
+
+    let data = Params {
+        tile: (texture, Some(sampler)),
+    };
+    let mut batch = gfx::batch::OwnedBatch::new(mesh, program.clone(), data).unwrap();
+    batch.slice = slice;
+    Tile {
+        batch: batch,
+        program: program_id,
+        expires: frame + rng.gen_range(1, MAX_LIFETIME + 1),
+        bytes: texels.len() + std::mem::size_of::<[Vertex; 4]>() + std::mem::size_of::<[u16; 6]>(),
+    }
+}
+
+// The resident memory of the process, where the system tells it
+fn resident_memory() -> Option<usize> {
+    let mut statm = String::new();
+    if File::open("/proc/self/statm").and_then(|mut f| f.read_to_string(&mut statm)).is_err() {
+        return None;
+    }
+    // the second number is the resident size, in pages
+    statm.split_whitespace().nth(1)
+         .and_then(|s| s.parse::<usize>().ok())
+         .map(|pages| pages * 4096)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Churn example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut rng = rand::XorShiftRng::new_unseeded();
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.1, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut tiles: Vec<Tile<_>> = Vec::new();
+    let mut program = create_program(&mut factory, &mut rng);
+    let mut program_id = 0u64;
+    let mut per_frame = 8;
+    let mut new_programs = true;
+    let mut paused = false;
+    let mut frame = 0u64;
+
+    let (mut created, mut destroyed) = (Counts::default(), Counts::default());
+    let (mut created_rate, mut destroyed_rate) = (0.0f64, 0.0f64);
+    let (mut created_before, mut destroyed_before) = (0, 0);
+    let mut since = precise_time_s();
+    // the resident memory once the tiles first reach a steady number
+    let mut baseline = None;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => per_frame = (per_frame * 2).min(256),
+                    VirtualKeyCode::Down => per_frame = (per_frame / 2).max(1),
+                    VirtualKeyCode::P => new_programs = !new_programs,
+                    VirtualKeyCode::Space => paused = !paused,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        if !paused {
+            // Drop the tiles whose time is up
+            let before = tiles.len();
+            let programs_before = tiles.iter().map(|t| t.program).collect::<HashSet<_>>();
+            tiles.retain(|t| t.expires > frame);
+            let gone = before - tiles.len();
+            destroyed.textures += gone;
+            destroyed.buffers += 2 * gone;
+            destroyed.samplers += gone;
+
+            if new_programs && frame % PROGRAM_EVERY == 0 && frame > 0 {
+                program = create_program(&mut factory, &mut rng);
+                program_id += 1;
+                created.programs += 1;
+            }
+            // the programs no tile uses anymore, other than the current one
+            let programs_after = tiles.iter().map(|t| t.program).collect::<HashSet<_>>();
+            destroyed.programs += programs_before.iter().filter(|&&p| {
+                p != program_id && !programs_after.contains(&p)
+            }).count();
+
+            for _ in 0 .. per_frame {
+                tiles.push(create_tile(&mut factory, &mut rng, &program, program_id, frame));
+            }
+            created.textures += per_frame;
+            created.buffers += 2 * per_frame;
+            created.samplers += per_frame;
+            frame += 1;
+        }
+
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        for tile in tiles.iter() {
+            renderer.draw(&tile.batch, &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        // only now are the dropped resources really freed
+        factory.cleanup();
+
+        let live = Counts {
+            textures: tiles.len(),
+            buffers: 2 * tiles.len(),
+            samplers: tiles.len(),
+            programs: tiles.iter().map(|t| t.program).chain(Some(program_id))
+                           .collect::<HashSet<_>>().len(),
+        };
+        let bytes = tiles.iter().fold(0, |sum, t| sum + t.bytes);
+
+        let now = precise_time_s();
+        if now - since >= 1.0 {
+            created_rate = (created.total() - created_before) as f64 / (now - since);
+            destroyed_rate = (destroyed.total() - destroyed_before) as f64 / (now - since);
+            created_before = created.total();
+            destroyed_before = destroyed.total();
+            since = now;
+
+            let memory = resident_memory();
+            if baseline.is_none() && frame > MAX_LIFETIME {
+                baseline = memory;
+            }
+            let memory = match (memory, baseline) {
+                (Some(m), Some(b)) => format!("{:.1} MB resident, {:+.1} MB since steady",
+                                             m as f64 / 1048576.0,
+                                             (m as f64 - b as f64) / 1048576.0),
+                (Some(m), None) => format!("{:.1} MB resident", m as f64 / 1048576.0),
+                _ => "resident memory unknown".to_string(),
+            };
+            println!("frame {}: {} textures, {} buffers, {} samplers, {} programs alive, \
+                      {} created and {} destroyed in all, {}",
+                     frame, live.textures, live.buffers, live.samplers, live.programs,
+                     created.total(), destroyed.total(), memory);
+        }
+
+        wrap.window.set_title(&format!(
+            "Churn example: {} tiles a frame, {} resources alive holding {:.1} MB, \
+             {:.0} created and {:.0} destroyed per second",
+            per_frame, live.total(), bytes as f64 / 1048576.0, created_rate, destroyed_rate));
+    }
+}
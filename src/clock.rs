@@ -0,0 +1,100 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fixed-timestep simulation clock.
+//!
+//! The simulation advances in ticks of constant length, no matter how fast
+//! frames are rendered. A frame looks like this:
+//!
+//! ```ignore
+//! clock.update();
+//! while clock.tick() {
+//!     // step the simulation to clock.time()
+//! }
+//! // render, blending the last two simulated states by clock.alpha()
+//! ```
+
+use time::precise_time_s;
+
+/// Longest frame the clock catches up with, in seconds. Anything beyond that
+/// is dropped, so a stall doesn't turn into a burst of ticks.
+const MAX_FRAME_TIME: f64 = 0.25;
+
+pub struct Clock {
+    step: f64,
+    fixed: bool,
+    last: f64,
+    accumulator: f64,
+    ticks: u64,
+}
+
+impl Clock {
+    /// Creates a clock running `rate` ticks per second of wall time.
+    pub fn new(rate: u32) -> Clock {
+        Clock {
+            step: 1.0 / rate as f64,
+            fixed: false,
+            last: precise_time_s(),
+            accumulator: 0.0,
+            ticks: 0,
+        }
+    }
+
+    /// Creates a clock that ignores the wall time and runs one tick per
+    /// frame, so that every run produces the same frames.
+    pub fn fixed(rate: u32) -> Clock {
+        Clock {
+            fixed: true,
+            .. Clock::new(rate)
+        }
+    }
+
+    /// Accounts for the time passed since the last frame. Call it once at
+    /// the beginning of every frame.
+    pub fn update(&mut self) {
+        if self.fixed {
+            self.accumulator += self.step;
+        } else {
+            let now = precise_time_s();
+            self.accumulator += (now - self.last).min(MAX_FRAME_TIME);
+            self.last = now;
+        }
+    }
+
+    /// Consumes one tick of the accumulated time, if there is one.
+    pub fn tick(&mut self) -> bool {
+        if self.accumulator < self.step {
+            return false;
+        }
+        self.accumulator -= self.step;
+        self.ticks += 1;
+        true
+    }
+
+    /// Length of a tick, in seconds.
+    pub fn step(&self) -> f32 {
+        self.step as f32
+    }
+
+    /// Simulation time of the latest tick, in seconds.
+    pub fn time(&self) -> f64 {
+        self.ticks as f64 * self.step
+    }
+
+    /// Fraction of a tick passed since the latest one. Rendering uses it to
+    /// blend between the previous and the latest simulated state.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step) as f32
+    }
+}
@@ -0,0 +1,535 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of raymarching volumetric clouds.
+//
+// A tiling 3D texture of Perlin and Worley noise is generated at startup. The
+// terrain is drawn first, then a full-screen pass at the far plane covers the
+// rest with a sky gradient, marching each view ray through a slab of the
+// atmosphere where the noise, drifting with the wind, defines the density of
+// the clouds. Every sample is lit by marching a few steps towards the sun,
+// with Beer's law for the absorption and a "powder" term darkening the edges
+// facing away from it.
+//
+// Look around with the mouse and fly with the keys of the free-fly camera,
+// under the clouds. Press Up and Down to raise and lower the sun, Plus and
+// Minus to change the cloud coverage, and Space to stop the wind.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate noise;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector3, Vector4, EuclideanVector};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+use noise::{Seed, perlin2};
+
+// All distances are in meters
+const FIELD_SIZE: f32 = 40000.0;
+const TERRAIN_CELLS: usize = 256;
+const CLOUD_BASE: f32 = 1500.0;
+const NOISE_SIZE: usize = 64;
+
+#[shader_param]
+struct TerrainParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_SunDir"]
+    sun_dir: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct CloudParams<R: gfx::Resources> {
+    #[name = "u_InvViewProj"]
+    inv_view_proj: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_SunDir"]
+    sun_dir: [f32; 3],
+    #[name = "u_Time"]
+    time: f32,
+    #[name = "u_Coverage"]
+    coverage: f32,
+    #[name = "t_Noise"]
+    noise: gfx::shade::TextureParam<R>,
+}
+
+static TERRAIN_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Pos = a_Pos;
+        v_Normal = a_Normal;
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static TERRAIN_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_CamPos;
+    uniform vec3 u_SunDir;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 sun = normalize(u_SunDir);
+        vec3 n = normalize(v_Normal);
+        vec3 color = mix(vec3(0.2, 0.3, 0.1), vec3(0.45, 0.4, 0.35),
+                         smoothstep(200.0, 500.0, v_Pos.z));
+        vec3 light = vec3(1.0, 0.9, 0.8) * max(0.0, dot(n, sun)) * smoothstep(-0.05, 0.1, sun.z)
+                   + vec3(0.25, 0.3, 0.4);
+        // fade into the horizon color of the sky
+        float fog = 1.0 - exp(-distance(v_Pos, u_CamPos) / 15000.0);
+        o_Color = vec4(mix(color * light, vec3(0.75, 0.8, 0.88), fog), 1.0);
+    }
+";
+
+static CLOUD_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_Ndc;
+
+    void main() {
+        v_Ndc = a_Pos.xy;
+        gl_Position = vec4(a_Pos.xy, 1.0, 1.0);
+    }
+";
+
+static CLOUD_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+    const float CLOUD_BASE = 1500.0;
+    const float CLOUD_TOP = 4000.0;
+    const float MAX_DISTANCE = 40000.0;
+    const int STEPS = 64;
+    const int LIGHT_STEPS = 6;
+    const float LIGHT_STEP = 120.0;
+    // the noise repeats every few kilometers, and its detail more often
+    const float SHAPE_SCALE = 1.0 / 8000.0;
+    const float DETAIL_SCALE = 1.0 / 1500.0;
+    // extinction per meter at full density
+    const float EXTINCTION = 0.02;
+
+    uniform mat4 u_InvViewProj;
+    uniform vec3 u_CamPos;
+    uniform vec3 u_SunDir;
+    uniform float u_Time;
+    uniform float u_Coverage;
+    uniform sampler3D t_Noise;
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    float remap(float v, float lo, float hi, float new_lo, float new_hi) {
+        return new_lo + (v - lo) / (hi - lo) * (new_hi - new_lo);
+    }
+
+    vec3 sky(vec3 rd, vec3 sun) {
+        vec3 color = mix(vec3(0.75, 0.8, 0.88), vec3(0.25, 0.45, 0.8), sqrt(max(rd.z, 0.0)));
+        float s = max(dot(rd, sun), 0.0);
+        return color + vec3(1.0, 0.8, 0.6) * (0.3 * pow(s, 8.0) + 10.0 * pow(s, 2000.0));
+    }
+
+    float density(vec3 p, bool detail) {
+        float h = (p.z - CLOUD_BASE) / (CLOUD_TOP - CLOUD_BASE);
+        // rounded bottoms and thinning tops
+        float profile = clamp(8.0 * h, 0.0, 1.0) * clamp(2.5 * (1.0 - h), 0.0, 1.0);
+        vec3 wind = u_Time * vec3(20.0, 8.0, 0.0);
+        // Perlin noise made billowy with the Worley octaves
+        vec4 n = texture(t_Noise, (p + wind) * SHAPE_SCALE);
+        float worley = 0.625 * n.g + 0.25 * n.b + 0.125 * n.a;
+        float shape = remap(n.r, worley - 1.0, 1.0, 0.0, 1.0);
+        float d = clamp(remap(shape * profile, 1.0 - u_Coverage, 1.0, 0.0, 1.0), 0.0, 1.0);
+        if (detail && d > 0.0) {
+            // wear the edges away with the finer noise
+            vec4 m = texture(t_Noise, (p + 2.0 * wind) * DETAIL_SCALE);
+            float erosion = 0.625 * m.g + 0.25 * m.b + 0.125 * m.a;
+            d = clamp(remap(d, 0.4 * erosion, 1.0, 0.0, 1.0), 0.0, 1.0);
+        }
+        return d;
+    }
+
+    // density integrated from a point towards the sun
+    float light_depth(vec3 p, vec3 sun) {
+        float depth = 0.0;
+        for (int i = 0; i < LIGHT_STEPS; ++i) {
+            p += LIGHT_STEP * sun;
+            depth += density(p, false) * LIGHT_STEP;
+        }
+        return depth;
+    }
+
+    float henyey_greenstein(float mu, float g) {
+        float g2 = g * g;
+        return (1.0 - g2) / (4.0 * PI * pow(1.0 + g2 - 2.0 * g * mu, 1.5));
+    }
+
+    void main() {
+        vec4 far = u_InvViewProj * vec4(v_Ndc, 1.0, 1.0);
+        vec3 rd = normalize(far.xyz / far.w);
+        vec3 sun = normalize(u_SunDir);
+        vec3 background = sky(rd, sun);
+        float t0 = (CLOUD_BASE - u_CamPos.z) / rd.z;
+        float t1 = min((CLOUD_TOP - u_CamPos.z) / rd.z, MAX_DISTANCE);
+        if (rd.z < 0.01 || t0 >= t1) {
+            o_Color = vec4(background, 1.0);
+            return;
+        }
+
+        float ds = (t1 - t0) / float(STEPS);
+        // start each pixel at a random fraction of a step, trading the
+        // banding of the fixed steps for noise
+        float jitter = fract(sin(dot(gl_FragCoord.xy, vec2(12.9898, 78.233))) * 43758.5453);
+        float t = t0 + jitter * ds;
+
+        float mu = dot(rd, sun);
+        // bright silver linings looking towards the sun, some back scattering
+        float phase = mix(henyey_greenstein(mu, 0.7), henyey_greenstein(mu, -0.2), 0.4);
+        // the sun turns red and fades out at the horizon
+        vec3 sun_color = mix(vec3(1.0, 0.45, 0.2), vec3(1.0, 0.95, 0.85),
+                             smoothstep(0.0, 0.4, sun.z)) * 40.0 * smoothstep(-0.05, 0.05, sun.z);
+        vec3 ambient = vec3(0.45, 0.55, 0.7);
+
+        float transmittance = 1.0;
+        vec3 light = vec3(0.0);
+        for (int i = 0; i < STEPS; ++i) {
+            if (transmittance < 0.01) break;
+            vec3 p = u_CamPos + t * rd;
+            float d = density(p, true);
+            if (d > 0.0) {
+                float tau = EXTINCTION * light_depth(p, sun);
+                float beer = exp(-tau);
+                // thin parts scatter less light towards the eye than they
+                // receive, which shows on the side away from the sun
+                float powder = 1.0 - exp(-2.0 * tau);
+                float energy = beer * mix(1.0, 2.0 * powder, 0.5 - 0.5 * mu);
+                float h = (p.z - CLOUD_BASE) / (CLOUD_TOP - CLOUD_BASE);
+                vec3 luminance = sun_color * phase * energy + ambient * (0.4 + 0.6 * h);
+                float step_transmittance = exp(-EXTINCTION * d * ds);
+                light += transmittance * luminance * (1.0 - step_transmittance);
+                transmittance *= step_transmittance;
+            }
+            t += ds;
+        }
+
+        // distant clouds melt into the haze of the horizon
+        float fade = exp(-t0 / 20000.0);
+        vec3 color = mix(background, background * transmittance + light, fade);
+        o_Color = vec4(color, 1.0);
+    }
+";
+
+fn height(seed: &Seed, x: f32, y: f32) -> f32 {
+    400.0 * perlin2(seed, &[x / 6000.0, y / 6000.0]) +
+    100.0 * perlin2(seed, &[x / 1500.0, y / 1500.0])
+}
+
+fn create_terrain(seed: &Seed) -> (Vec<shape::Vertex>, Vec<u32>) {
+    let n = TERRAIN_CELLS + 1;
+    let cell = FIELD_SIZE / TERRAIN_CELLS as f32;
+    let mut vertices = Vec::with_capacity(n * n);
+    for j in 0 .. n {
+        for i in 0 .. n {
+            let x = i as f32 * cell - 0.5 * FIELD_SIZE;
+            let y = j as f32 * cell - 0.5 * FIELD_SIZE;
+            let e = 10.0;
+            let dx = (height(seed, x + e, y) - height(seed, x - e, y)) / (2.0 * e);
+            let dy = (height(seed, x, y + e) - height(seed, x, y - e)) / (2.0 * e);
+            vertices.push(shape::Vertex {
+                pos: [x, y, height(seed, x, y)],
+                normal: Vector3::new(-dx, -dy, 1.0).normalize().into_fixed(),
+                tex_coord: [i as f32 / TERRAIN_CELLS as f32, j as f32 / TERRAIN_CELLS as f32],
+            });
+        }
+    }
+    let mut indices = Vec::with_capacity(TERRAIN_CELLS * TERRAIN_CELLS * 6);
+    for j in 0 .. TERRAIN_CELLS {
+        for i in 0 .. TERRAIN_CELLS {
+            let a = (j * n + i) as u32;
+            let (b, c, d) = (a + 1, a + 1 + n as u32, a + n as u32);
+            indices.extend([a, b, c, a, c, d].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(73856093) ^ (y as u32).wrapping_mul(19349663)
+              ^ (z as u32).wrapping_mul(83492791);
+    h = (h ^ (h >> 13)).wrapping_mul(0x5bd1e995);
+    h ^ (h >> 15)
+}
+
+// A number from 0 to 1 out of a hash, different for each k
+fn random(h: u32, k: u32) -> f32 {
+    (hash(h as i32, k as i32, 0) & 0xFFFFFF) as f32 / 16777216.0
+}
+
+fn repeat(i: i32, period: i32) -> i32 {
+    ((i % period) + period) % period
+}
+
+// Perlin noise repeating every `period` units, roughly from -1 to 1
+fn perlin3(p: [f32; 3], period: i32) -> f32 {
+    static GRADIENTS: [[f32; 3]; 12] = [
+        [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+        [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+        [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+    ];
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let cell = [p[0].floor(), p[1].floor(), p[2].floor()];
+    let f = [p[0] - cell[0], p[1] - cell[1], p[2] - cell[2]];
+    let mut corners = [0.0f32; 8];
+    for k in 0 .. 8 {
+        let d = [(k & 1) as f32, ((k >> 1) & 1) as f32, ((k >> 2) & 1) as f32];
+        let h = hash(repeat(cell[0] as i32 + d[0] as i32, period),
+                     repeat(cell[1] as i32 + d[1] as i32, period),
+                     repeat(cell[2] as i32 + d[2] as i32, period));
+        let g = GRADIENTS[(h % 12) as usize];
+        corners[k] = g[0] * (f[0] - d[0]) + g[1] * (f[1] - d[1]) + g[2] * (f[2] - d[2]);
+    }
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let (u, v, w) = (fade(f[0]), fade(f[1]), fade(f[2]));
+    lerp(lerp(lerp(corners[0], corners[1], u), lerp(corners[2], corners[3], u), v),
+         lerp(lerp(corners[4], corners[5], u), lerp(corners[6], corners[7], u), v), w)
+}
+
+// Distance to the closest of one random point per cell, repeating every
+// `period` cells, inverted so that the cell centers are bright
+fn worley(p: [f32; 3], period: i32) -> f32 {
+    let cell = [p[0].floor() as i32, p[1].floor() as i32, p[2].floor() as i32];
+    let mut best = 1.0f32;
+    for dz in -1 .. 2 {
+        for dy in -1 .. 2 {
+            for dx in -1 .. 2 {
+                let c = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                let h = hash(repeat(c[0], period), repeat(c[1], period), repeat(c[2], period));
+                let feature = [c[0] as f32 + random(h, 1),
+                               c[1] as f32 + random(h, 2),
+                               c[2] as f32 + random(h, 3)];
+                let (x, y, z) = (feature[0] - p[0], feature[1] - p[1], feature[2] - p[2]);
+                best = best.min(x * x + y * y + z * z);
+            }
+        }
+    }
+    1.0 - best.sqrt().min(1.0)
+}
+
+// Perlin noise in red, and three octaves of Worley noise in green, blue and
+// alpha, all tiling over the texture
+fn create_noise() -> Vec<[f32; 4]> {
+    let mut data = Vec::with_capacity(NOISE_SIZE * NOISE_SIZE * NOISE_SIZE);
+    for z in 0 .. NOISE_SIZE {
+        for y in 0 .. NOISE_SIZE {
+            for x in 0 .. NOISE_SIZE {
+                let s = 1.0 / NOISE_SIZE as f32;
+                let p = [x as f32 * s, y as f32 * s, z as f32 * s];
+                let at = |freq: i32| {
+                    let f = freq as f32;
+                    [p[0] * f, p[1] * f, p[2] * f]
+                };
+                let perlin = perlin3(at(4), 4) + 0.5 * perlin3(at(8), 8) +
+                             0.25 * perlin3(at(16), 16);
+                data.push([(0.5 + 0.5 * perlin).max(0.0).min(1.0),
+                           worley(at(4), 4), worley(at(8), 8), worley(at(16), 16)]);
+            }
+        }
+    }
+    data
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Clouds example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let seed = Seed::new(3);
+    let mut terrain = {
+        let (vertex_data, index_data) = create_terrain(&seed);
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(TERRAIN_VERTEX_SRC, TERRAIN_FRAGMENT_SRC)
+                             .unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = TerrainParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            cam_pos: [0.0; 3],
+            sun_dir: [0.0, 0.0, 1.0],
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let mut meshes = MeshCache::new();
+    let mut clouds = {
+        let info = gfx::tex::TextureInfo {
+            width: NOISE_SIZE as u16,
+            height: NOISE_SIZE as u16,
+            depth: NOISE_SIZE as u16,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture3D,
+            format: gfx::tex::Format::Float(gfx::tex::Components::RGBA,
+                                            gfx::attrib::FloatSize::F32),
+        };
+        let texture = factory.create_texture(info).unwrap();
+        factory.update_texture(&texture, &info.to_image_info(), &create_noise(), None)
+               .unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Tile)
+        );
+
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(CLOUD_VERTEX_SRC, CLOUD_FRAGMENT_SRC).unwrap();
+        // only where the terrain left the far plane
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, false);
+        let data = CloudParams {
+            inv_view_proj: Matrix4::identity().into_fixed(),
+            cam_pos: [0.0; 3],
+            sun_dir: [0.0, 0.0, 1.0],
+            time: 0.0,
+            coverage: 0.5,
+            noise: (texture, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 1.0, FIELD_SIZE);
+    let start = Point3::new(0.0, -5000.0, height(&seed, 0.0, -5000.0) + 300.0);
+    let mut camera = FlyCamera::new(start, 0.5 * PI, 0.25, 100.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut sun_elevation = 0.5f32;
+    // -1, 0 or 1 while Down or Up is held
+    let mut raise = 0.0f32;
+    let mut windy = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut sim_time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(state, _, Some(VirtualKeyCode::Up)) =>
+                    raise = if state == ElementState::Pressed { 1.0 } else { 0.0 },
+                Event::KeyboardInput(state, _, Some(VirtualKeyCode::Down)) =>
+                    raise = if state == ElementState::Pressed { -1.0 } else { 0.0 },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let coverage = &mut clouds.params.coverage;
+                    match key {
+                        VirtualKeyCode::Space => windy = !windy,
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals =>
+                            *coverage = (*coverage + 0.05).min(1.0),
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus =>
+                            *coverage = (*coverage - 0.05).max(0.0),
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            let dt = clock.step();
+            prev_time = sim_time;
+            if windy {
+                sim_time += dt;
+            }
+            sun_elevation = (sun_elevation + 0.3 * raise * dt).max(-0.1).min(0.5 * PI);
+            camera.update(dt);
+            // stay between the ground and the clouds
+            let ground = height(&seed, camera.position.x, camera.position.y) + 2.0;
+            camera.position.z = camera.position.z.max(ground).min(CLOUD_BASE - 200.0);
+        }
+        let t = prev_time + (sim_time - prev_time) * clock.alpha();
+
+        let view = camera.view().mat;
+        let sun_dir = [0.3 * sun_elevation.cos(), sun_elevation.cos(), sun_elevation.sin()];
+        terrain.params.view_proj = proj.mul_m(&view).into_fixed();
+        terrain.params.cam_pos = camera.position.into_fixed();
+        terrain.params.sun_dir = sun_dir;
+        // the clouds only need the view direction
+        let mut rotation = view;
+        rotation.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        clouds.params.inv_view_proj = proj.mul_m(&rotation).invert().unwrap().into_fixed();
+        clouds.params.cam_pos = camera.position.into_fixed();
+        clouds.params.sun_dir = sun_dir;
+        clouds.params.time = t;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&terrain, &context), &wrap).unwrap();
+        renderer.draw(&(&clouds, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -17,6 +17,7 @@
 
 extern crate cgmath;
 extern crate gfx;
+extern crate gfx_examples;
 extern crate gfx_window_glfw;
 extern crate glfw;
 
@@ -24,6 +25,7 @@ use cgmath::FixedArray;
 use cgmath::{Matrix, Point3, Vector3};
 use cgmath::{Transform, AffineMatrix3};
 use gfx::traits::*;
+use gfx_examples::Thumbnail;
 
 #[vertex_format]
 #[derive(Clone, Copy)]
@@ -60,6 +62,7 @@ pub fn main() {
     window.set_key_polling(true);
 
     let mut canvas = gfx_window_glfw::init(window).into_canvas();
+    let mut thumbnail = Thumbnail::from_env();
 
     let vertex_data = [
         // top (0, 0, 1)
@@ -165,6 +168,17 @@ pub fn main() {
             stencil: 0,
         });
         canvas.draw(&batch).unwrap();
+
+        if thumbnail.is_some() {
+            // the frame has to be in the back buffer before `present` swaps it
+            canvas.device.submit(canvas.renderer.as_buffer());
+            canvas.renderer.reset();
+            let size = canvas.output.get_size();
+            let window = &mut canvas.output.window;
+            if thumbnail.as_mut().unwrap().end_frame(|s| window.get_proc_address(s), size) {
+                window.set_should_close(true);
+            }
+        }
         canvas.present();
     }
 }
@@ -0,0 +1,263 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cubemaps for environments and skyboxes.
+//!
+//! `load` reads the six faces of a cubemap from image files, and `generate`
+//! fills one with a function of the direction, such as `environment`, for
+//! the examples to run without any image. Both come with all their mipmaps.
+//! The faces are looked up with world directions, so in the Z-up space of
+//! the examples the sky is in the `posz` face. `Skybox` draws a cubemap
+//! behind the scene.
+//!
+//! ```ignore
+//! let cube = cubemap::generate(&mut factory, 256, cubemap::environment);
+//! let mut skybox = cubemap::Skybox::new(&mut factory, &mut meshes, cube.clone());
+//! skybox.draw(&mut renderer, &wrap, &proj, &view.mat);
+//! ```
+
+use std::path::Path;
+use cgmath::{Matrix, Matrix4, Vector, Vector3, Vector4, EuclideanVector};
+use cgmath::FixedArray;
+use gfx;
+use gfx::traits::*;
+use image;
+use mesh_cache::MeshCache;
+
+/// Names of the faces, in the order of the layers of a cubemap.
+pub static FACE_NAMES: [&'static str; 6] = ["posx", "negx", "posy", "negy", "posz", "negz"];
+
+/// Returns the direction through a point of a face, with `s` and `t` from
+/// -1 to 1 along the rows and columns of its texels, as GL samples it.
+pub fn direction(face: usize, s: f32, t: f32) -> Vector3<f32> {
+    let v = match face {
+        0 => Vector3::new(1.0, -t, -s),
+        1 => Vector3::new(-1.0, -t, s),
+        2 => Vector3::new(s, 1.0, t),
+        3 => Vector3::new(s, -1.0, -t),
+        4 => Vector3::new(s, -t, 1.0),
+        _ => Vector3::new(-s, -t, -1.0),
+    };
+    v.normalize()
+}
+
+fn cube_info(size: u16, levels: u8, format: gfx::tex::Format) -> gfx::tex::TextureInfo {
+    gfx::tex::TextureInfo {
+        width: size,
+        height: size,
+        depth: 1,
+        levels: levels,
+        kind: gfx::tex::TextureKind::TextureCube,
+        format: format,
+    }
+}
+
+/// Returns the number of mipmap levels down to one texel.
+pub fn full_levels(size: u16) -> u8 {
+    (16 - size.leading_zeros()) as u8
+}
+
+/// Creates an empty float cubemap with room for `levels` mipmaps, to be
+/// rendered to through `gfx::Plane::Texture(cube, level, Some(face))`.
+pub fn create<R, F>(factory: &mut F, size: u16, levels: u8) -> gfx::TextureHandle<R>
+              where R: gfx::Resources, F: gfx::Factory<R> {
+    let format = gfx::tex::Format::Float(gfx::tex::Components::RGBA,
+                                         gfx::attrib::FloatSize::F32);
+    factory.create_texture(cube_info(size, levels, format)).unwrap()
+}
+
+/// Loads a cubemap from the square images named after `FACE_NAMES` with the
+/// `.png` extension in `dir`.
+pub fn load<R, F>(factory: &mut F, dir: &str) -> Result<gfx::TextureHandle<R>, String>
+            where R: gfx::Resources, F: gfx::Factory<R> {
+    let mut faces = Vec::new();
+    for name in FACE_NAMES.iter() {
+        let path = Path::new(dir).join(&format!("{}.png", name));
+        match image::open(&path) {
+            Ok(img) => faces.push(img.to_rgba()),
+            Err(e) => return Err(format!("Failed to load {}: {:?}", path.display(), e)),
+        }
+    }
+    let (size, _) = faces[0].dimensions();
+    if faces.iter().any(|face| face.dimensions() != (size, size)) {
+        return Err(format!("The faces in {} are not squares of the same size", dir));
+    }
+
+    let info = cube_info(size as u16, full_levels(size as u16), gfx::tex::RGBA8);
+    let texture = factory.create_texture(info).unwrap();
+    for (layer, face) in faces.into_iter().enumerate() {
+        upload_face(factory, &texture, &info, layer, &face.into_raw());
+    }
+    factory.generate_mipmap(&texture);
+    Ok(texture)
+}
+
+// The layer of a cubemap is its Z offset
+fn upload_face<R, F, T>(factory: &mut F, texture: &gfx::TextureHandle<R>,
+                        info: &gfx::tex::TextureInfo, layer: usize, data: &[T])
+                        where R: gfx::Resources, F: gfx::Factory<R>, T: Copy {
+    let mut image = info.to_image_info();
+    image.zoffset = layer as u16;
+    image.depth = 1;
+    factory.update_texture(texture, &image, data, None).unwrap();
+}
+
+/// Creates a float cubemap of `size` texels across each face, with the
+/// color `f` returns for the direction through every texel.
+pub fn generate<R, F, G>(factory: &mut F, size: u16, f: G) -> gfx::TextureHandle<R>
+                where R: gfx::Resources, F: gfx::Factory<R>, G: Fn(Vector3<f32>) -> [f32; 3] {
+    let texture = create(factory, size, full_levels(size));
+    let info = *texture.get_info();
+    let mut data = Vec::with_capacity(size as usize * size as usize);
+    for layer in 0 .. 6 {
+        data.clear();
+        for row in 0 .. size {
+            for column in 0 .. size {
+                let s = (column as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let t = (row as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let c = f(direction(layer, s, t));
+                data.push([c[0], c[1], c[2], 1.0]);
+            }
+        }
+        upload_face(factory, &texture, &info, layer, &data);
+    }
+    factory.generate_mipmap(&texture);
+    texture
+}
+
+/// A high dynamic range outdoor environment: a sky fading from the horizon,
+/// a sun much brighter than the rest, and a checkered ground with a grid of
+/// lines, so that reflections and blur show clearly.
+pub fn environment(dir: Vector3<f32>) -> [f32; 3] {
+    let sun = Vector3::new(0.5, 0.6, 0.5).normalize();
+    if dir.z >= 0.0 {
+        let up = dir.z.sqrt();
+        let sky = Vector3::new(0.9, 0.9, 0.95).mul_s(1.0 - up)
+                                              .add_v(&Vector3::new(0.25, 0.45, 0.9).mul_s(up));
+        let s = dir.dot(&sun).max(0.0);
+        let glow = 0.5 * s.powf(16.0) + if s > 0.9995 { 200.0 } else { 0.0 };
+        [sky.x + glow, sky.y + 0.9 * glow, sky.z + 0.7 * glow]
+    } else {
+        // the ground is a plane one unit below, lit by the sky and the sun
+        let p = dir.mul_s(-1.0 / dir.z);
+        let checker = ((p.x.floor() + p.y.floor()) as i32 & 1) as f32;
+        let line = (p.x - p.x.round()).abs().min((p.y - p.y.round()).abs()) < 0.02;
+        let base = if line { 0.05 } else { 0.25 + 0.2 * checker };
+        let ground = Vector3::new(1.0, 0.95, 0.85).mul_s(base * (0.6 + 2.0 * sun.z));
+        // fading into the horizon in the distance
+        let horizon = Vector3::new(0.9, 0.9, 0.95);
+        let c = horizon.add_v(&ground.sub_v(&horizon).mul_s((-dir.z * 8.0).min(1.0)));
+        [c.x, c.y, c.z]
+    }
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_InvViewProj"]
+    inv_view_proj: [[f32; 4]; 4],
+    #[name = "u_Exposure"]
+    exposure: f32,
+    #[name = "u_Lod"]
+    lod: f32,
+    #[name = "t_Cube"]
+    cube: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_Ndc;
+
+    void main() {
+        v_Ndc = a_Pos.xy;
+        gl_Position = vec4(a_Pos.xy, 1.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_InvViewProj;
+    uniform float u_Exposure;
+    uniform float u_Lod;
+    uniform samplerCube t_Cube;
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 far = u_InvViewProj * vec4(v_Ndc, 1.0, 1.0);
+        vec3 color = textureLod(t_Cube, far.xyz / far.w, u_Lod).rgb;
+        o_Color = vec4(1.0 - exp(-u_Exposure * color), 1.0);
+    }
+";
+
+/// Draws a cubemap as the background of the scene.
+///
+/// The colors are mapped to the displayable range with `1 - exp(-exposure *
+/// color)`, as in `sky::Sky`, so objects shaded with the same curve match
+/// the background.
+pub struct Skybox<R: gfx::Resources> {
+    /// Scale of the light before it is mapped to the displayable range.
+    pub exposure: f32,
+    /// Mipmap level to show, for a blurred background.
+    pub lod: f32,
+    batch: gfx::batch::OwnedBatch<Params<R>>,
+}
+
+impl<R: gfx::Resources> Skybox<R> {
+    /// Creates the pass, drawing the quad of `meshes`.
+    pub fn new<F: gfx::Factory<R>>(factory: &mut F, meshes: &mut MeshCache<R>,
+                                   cube: gfx::TextureHandle<R>) -> Skybox<R> {
+        let (mesh, _) = meshes.quad(factory);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = Params {
+            inv_view_proj: Matrix4::identity().into_fixed(),
+            exposure: 1.0,
+            lod: 0.0,
+            cube: (cube, Some(sampler)),
+        };
+        let mut batch = gfx::batch::OwnedBatch::new(mesh, program, data).unwrap();
+        batch.state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, false);
+
+        Skybox {
+            exposure: 1.0,
+            lod: 0.0,
+            batch: batch,
+        }
+    }
+
+    /// Changes the cubemap drawn.
+    pub fn set_cubemap(&mut self, cube: gfx::TextureHandle<R>) {
+        self.batch.params.cube.0 = cube;
+    }
+
+    /// Draws the cubemap behind what `output` already holds, seen through the
+    /// `proj` and `view` matrices. Only the rotation of the view is used.
+    pub fn draw<C, O>(&mut self, renderer: &mut gfx::Renderer<R, C>, output: &O,
+                      proj: &Matrix4<f32>, view: &Matrix4<f32>)
+                      where C: gfx::CommandBuffer<R>, O: gfx::Output<R> {
+        let mut rotation = *view;
+        rotation.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let inv_view_proj = proj.mul_m(&rotation).invert().unwrap();
+        self.batch.params.inv_view_proj = inv_view_proj.into_fixed();
+        self.batch.params.exposure = self.exposure;
+        self.batch.params.lod = self.lod;
+        renderer.draw(&self.batch, output).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of view frustum culling on the CPU.
+//
+// Thousands of cubes and spheres are scattered around a camera turning in
+// place, each drawn with its own call. Every frame the six planes of the view
+// frustum are extracted from the view-projection matrix, and objects whose
+// bounding box lies fully outside one of them are skipped.
+//
+// Press C to toggle culling. The number of drawn and culled objects and the
+// frame time are shown in a text overlay, or in the window title if no font
+// is found.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+extern crate time;
+
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::text::{Font, Span, TextRenderer};
+use gfx_examples::{window, Clock, Thumbnail};
+
+const NUM_OBJECTS: usize = 5000;
+const FIELD_SIZE: f32 = 200.0;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(u_Color * (0.2 + 0.8 * d), 1.0);
+    }
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Shape {
+    Cube,
+    Sphere,
+}
+
+struct Object {
+    shape: Shape,
+    model: [[f32; 4]; 4],
+    color: [f32; 3],
+    // world space bounding box
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+fn create_objects() -> Vec<Object> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    (0 .. NUM_OBJECTS).map(|_| {
+        let pos = [FIELD_SIZE * (rng.gen::<f32>() - 0.5),
+                   FIELD_SIZE * (rng.gen::<f32>() - 0.5),
+                   10.0 * (rng.gen::<f32>() - 0.5)];
+        let size = 0.5 + 1.5 * rng.gen::<f32>();
+        let shape = if rng.gen() { Shape::Cube } else { Shape::Sphere };
+        let mut model = Matrix3::identity().mul_s(size).to_matrix4();
+        model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+        Object {
+            shape: shape,
+            model: model.into_fixed(),
+            color: [0.3 + 0.7 * rng.gen::<f32>(),
+                    0.3 + 0.7 * rng.gen::<f32>(),
+                    0.3 + 0.7 * rng.gen::<f32>()],
+            min: [pos[0] - size, pos[1] - size, pos[2] - size],
+            max: [pos[0] + size, pos[1] + size, pos[2] + size],
+        }
+    }).collect()
+}
+
+// The planes bounding the clip volume, as (normal, distance) with the normal
+// pointing inside, taken from the rows of the view-projection matrix
+fn frustum_planes(m: &Matrix4<f32>) -> [[f32; 4]; 6] {
+    let row = |i: usize| [m.x[i], m.y[i], m.z[i], m.w[i]];
+    let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+    let add = |a: [f32; 4], b: [f32; 4], s: f32| {
+        [a[0] + s * b[0], a[1] + s * b[1], a[2] + s * b[2], a[3] + s * b[3]]
+    };
+    [
+        add(r3, r0, 1.0), add(r3, r0, -1.0), // left, right
+        add(r3, r1, 1.0), add(r3, r1, -1.0), // bottom, top
+        add(r3, r2, 1.0), add(r3, r2, -1.0), // near, far
+    ]
+}
+
+// A box is outside if its corner furthest along the normal of a plane is
+// still behind it
+fn is_visible(planes: &[[f32; 4]; 6], min: &[f32; 3], max: &[f32; 3]) -> bool {
+    planes.iter().all(|p| {
+        let mut d = p[3];
+        for k in 0 .. 3 {
+            d += p[k] * if p[k] > 0.0 { max[k] } else { min[k] };
+        }
+        d >= 0.0
+    })
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Culling example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut meshes = MeshCache::new();
+    let (mut cube_batch, mut sphere_batch) = {
+        let mut make_batch = |(mesh, slice): (gfx::Mesh<_>, gfx::Slice<_>)| {
+            let data = Params {
+                model: Matrix4::identity().into_fixed(),
+                view_proj: Matrix4::identity().into_fixed(),
+                color: [1.0; 3],
+                _dummy: std::marker::PhantomData,
+            };
+            context.make_batch(&program, data, &mesh, slice, &state)
+                   .unwrap()
+        };
+        let (cube, sphere) = (meshes.cube(&mut factory), meshes.sphere(&mut factory, 16, 32));
+        (make_batch(cube), make_batch(sphere))
+    };
+
+    let objects = create_objects();
+
+    let mut hud = match Font::load_default(16) {
+        Ok(font) => Some(TextRenderer::new(&mut factory, font, 256)),
+        Err(e) => {
+            println!("{}, showing statistics in the window title", e);
+            None
+        },
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 150.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.2, 0.2, 0.25, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut culling = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+    let (mut frames, mut last_report, mut frame_time) = (0u32, time::precise_time_s(), 0.0);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::C)) =>
+                    culling = !culling,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            angle = 0.2 * clock.time() as f32;
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        // Turn in place at the center of the field
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(0.0, 0.0, 2.0),
+            &Point3::new(a.cos(), a.sin(), 1.8),
+            &Vector3::unit_z(),
+        );
+        let view_proj = proj.mul_m(&view.mat);
+        cube_batch.params.view_proj = view_proj.into_fixed();
+        sphere_batch.params.view_proj = view_proj.into_fixed();
+
+        let cull_start = time::precise_time_s();
+        let planes = frustum_planes(&view_proj);
+        let visible: Vec<&Object> = objects.iter().filter(|o| {
+            !culling || is_visible(&planes, &o.min, &o.max)
+        }).collect();
+        let cull_time = time::precise_time_s() - cull_start;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for object in visible.iter() {
+            let batch = match object.shape {
+                Shape::Cube => &mut cube_batch,
+                Shape::Sphere => &mut sphere_batch,
+            };
+            batch.params.model = object.model;
+            batch.params.color = object.color;
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        let stats = format!("culling {}: {} drawn, {} culled, {:.2} ms/frame, {:.3} ms culling",
+                            if culling { "on" } else { "off" },
+                            visible.len(), objects.len() - visible.len(),
+                            1000.0 * frame_time, 1000.0 * cull_time);
+        match hud {
+            Some(ref mut hud) => {
+                hud.add(&[Span::new(&stats, [1.0, 1.0, 1.0, 1.0])], [10.0, 10.0], None);
+                hud.draw(&mut factory, &mut renderer, &wrap);
+            },
+            None => wrap.window.set_title(&stats),
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        frames += 1;
+        let now = time::precise_time_s();
+        if now - last_report >= 0.5 {
+            frame_time = (now - last_report) / frames as f64;
+            frames = 0;
+            last_report = now;
+        }
+    }
+}
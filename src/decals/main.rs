@@ -0,0 +1,435 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of forward rendered decals.
+//
+// A decal is a box in the world, with a texture projected through it along
+// one of its axes. After the scene is drawn, every mesh that reaches into the
+// box is drawn again with the decal program. It transforms each fragment into
+// the box, throws away the ones outside of it and those on faces turned away
+// from the projection, where the texture would be stretched, and blends the
+// texture over the rest. So a decal follows the surface it lands on, around
+// the corners of the cube or over the curve of the sphere, instead of being a
+// flat quad floating in front of it.
+//
+// The meshes are drawn again with the same vertices, but by another program,
+// and nothing makes the two programs agree on the depth bit for bit. A depth
+// bias in the rasterizer state pulls the decal pass slightly towards the
+// camera, so that it always wins the depth test instead of flickering.
+//
+// gfx-rs can't read the depth buffer back yet, so the depth under the cursor
+// is read with GL directly, to find where to put a new decal.
+//
+// Right click to stick a decal where the cursor points, projected along the
+// view. Press T to cycle through the textures of the next decals, B to toggle
+// the depth bias, and C to clear the decals.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point, Point3, Vector, Vector3, Vector4};
+use cgmath::{EuclideanVector, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Thumbnail};
+use gl::types::*;
+
+const DECAL_SIZE: u16 = 128;
+const TEXTURES: [&'static str; 3] = ["paint", "scorch", "target"];
+const MAX_DECALS: usize = 32;
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct DecalParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    // from world space into the box of the decal, spanning -1 to 1
+    #[name = "u_Decal"]
+    decal: [[f32; 4]; 4],
+    #[name = "u_Forward"]
+    forward: [f32; 3],
+    #[name = "t_Decal"]
+    texture: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        float diffuse = max(dot(normalize(v_Normal), normalize(vec3(0.4, -0.6, 0.8))), 0.0);
+        o_Color = vec4(u_Color * (0.25 + 0.75 * diffuse), 1.0);
+    }
+";
+
+static DECAL_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_Pos = world.xyz;
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static DECAL_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Decal;
+    uniform vec3 u_Forward;
+    uniform sampler2D t_Decal;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 local = (u_Decal * vec4(v_Pos, 1.0)).xyz;
+        if (any(greaterThan(abs(local), vec3(1.0)))) {
+            discard;
+        }
+        vec3 n = normalize(v_Normal);
+        // fading out on the faces along the projection, and towards the
+        // front and the back of the box
+        float fade = smoothstep(0.2, 0.5, dot(n, -u_Forward))
+                   * (1.0 - smoothstep(0.7, 1.0, abs(local.z)));
+        if (fade <= 0.0) {
+            discard;
+        }
+        vec4 tex = texture(t_Decal, local.xy * 0.5 + 0.5);
+        float diffuse = max(dot(n, normalize(vec3(0.4, -0.6, 0.8))), 0.0);
+        o_Color = vec4(tex.rgb * (0.25 + 0.75 * diffuse), tex.a * fade);
+    }
+";
+
+struct Decal {
+    matrix: Matrix4<f32>,
+    forward: Vector3<f32>,
+    center: Point3<f32>,
+    // of the sphere around the box
+    radius: f32,
+    texture: usize,
+}
+
+impl Decal {
+    // A square of `size` around `center`, projected along `forward` and
+    // turned by `angle`, reaching as far in front of and behind the center
+    fn new(center: Point3<f32>, forward: Vector3<f32>, angle: f32, size: f32, texture: usize)
+           -> Decal {
+        let forward = forward.normalize();
+        let helper = if forward.z.abs() < 0.9 { Vector3::unit_z() } else { Vector3::unit_x() };
+        let right = forward.cross(&helper).normalize();
+        let up = right.cross(&forward);
+        let (right, up) = (right.mul_s(angle.cos()).add_v(&up.mul_s(angle.sin())),
+                           up.mul_s(angle.cos()).sub_v(&right.mul_s(angle.sin())));
+        // the rows take a point to the box, relative to its center
+        let c = center.to_vec();
+        let (r, u, f) = (right.div_s(size), up.div_s(size), forward.div_s(size));
+        let matrix = Matrix4::new(r.x, u.x, f.x, 0.0,
+                                  r.y, u.y, f.y, 0.0,
+                                  r.z, u.z, f.z, 0.0,
+                                  -r.dot(&c), -u.dot(&c), -f.dot(&c), 1.0);
+        Decal {
+            matrix: matrix,
+            forward: forward,
+            center: center,
+            radius: size * 3.0f32.sqrt(),
+            texture: texture,
+        }
+    }
+}
+
+fn make_texture<R: rand::Rng>(kind: usize, rng: &mut R) -> Vec<u8> {
+    let size = DECAL_SIZE as usize;
+    // the drops around the paint splat
+    let drops: Vec<_> = (0 .. 12).map(|_| {
+        let (a, d) = (rng.gen::<f32>() * 2.0 * PI, rng.gen_range(0.3f32, 0.45));
+        (0.5 + d * a.cos(), 0.5 + d * a.sin(), rng.gen_range(0.015f32, 0.04))
+    }).collect();
+    let mut data = Vec::with_capacity(size * size * 4);
+    for py in 0 .. size {
+        for px in 0 .. size {
+            let (x, y) = ((px as f32 + 0.5) / size as f32, (py as f32 + 0.5) / size as f32);
+            let (dx, dy) = (x - 0.5, y - 0.5);
+            let (r, a) = ((dx * dx + dy * dy).sqrt(), dy.atan2(dx));
+            let (color, alpha) = match kind {
+                0 => {
+                    let edge = 0.28 + 0.04 * (7.0 * a).sin() + 0.02 * (13.0 * a + 1.0).sin();
+                    let drop = drops.iter().any(|&(cx, cy, cr)| {
+                        (x - cx) * (x - cx) + (y - cy) * (y - cy) < cr * cr
+                    });
+                    ([0.15, 0.35, 0.9], if r < edge || drop { 1.0 } else { 0.0 })
+                },
+                1 => ([0.05, 0.04, 0.03], 0.9 * (1.0 - r / 0.5).max(0.0).powf(1.5)),
+                _ => {
+                    let ring = (r / 0.09) as usize % 2 == 0;
+                    (if ring { [0.9, 0.15, 0.1] } else { [0.95, 0.95, 0.9] },
+                     if r < 0.45 { 1.0 } else { 0.0 })
+                },
+            };
+            data.extend([(color[0] * 255.0) as u8, (color[1] * 255.0) as u8,
+                         (color[2] * 255.0) as u8, (alpha * 255.0) as u8].iter().cloned());
+        }
+    }
+    data
+}
+
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Decal example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+    let gl = gl::Gl::load_with(|s| wrap.window.get_proc_address(s));
+    let hidpi = window::scale_factor(&wrap.window);
+    let mut rng = rand::XorShiftRng::new_unseeded();
+
+    let textures: Vec<_> = (0 .. TEXTURES.len()).map(|kind| {
+        let texture = factory.create_texture_rgba8(DECAL_SIZE, DECAL_SIZE, true).unwrap();
+        factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                               &make_texture(kind, &mut rng), None).unwrap();
+        factory.generate_mipmap(&texture);
+        texture
+    }).collect();
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let scene_program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let decal_program = factory.link_program(DECAL_VERTEX_SRC, DECAL_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let unbiased = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, false)
+                                        .blend(gfx::BlendPreset::Alpha);
+    let mut biased = unbiased.clone();
+    // towards the camera by the slope of the triangle and a unit of depth
+    biased.primitive.offset = Some(gfx::state::Offset(-1.0, -1));
+
+    let scale = |s: f32| Matrix3::new(s, 0.0, 0.0, 0.0, s, 0.0, 0.0, 0.0, s);
+    let mut meshes = MeshCache::new();
+    // (mesh, model, color, radius of the bounding sphere) of each object
+    let objects = vec![
+        (meshes.plane(&mut factory), place(scale(6.0), 0.0, 0.0, -1.0), [0.75, 0.75, 0.72],
+         6.0 * 1.5),
+        (meshes.cube(&mut factory), place(scale(1.0), -2.0, 0.0, 0.0), [0.85, 0.6, 0.4], 1.8),
+        (meshes.sphere(&mut factory, 24, 48), place(scale(1.0), 1.5, -1.0, 0.0),
+         [0.5, 0.75, 0.5], 1.0),
+        (meshes.torus(&mut factory, 32, 64, 0.8, 0.35), place(scale(1.0), 1.0, 2.0, -0.6),
+         [0.6, 0.6, 0.85], 1.2),
+    ];
+    let mut batches: Vec<_> = objects.into_iter().map(|((mesh, slice), model, color, radius)| {
+        let scene_data = SceneParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: model.into_fixed(),
+            color: color,
+            _dummy: std::marker::PhantomData,
+        };
+        let decal_data = DecalParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: model.into_fixed(),
+            decal: Matrix4::identity().into_fixed(),
+            forward: [0.0, 0.0, -1.0],
+            texture: (textures[0].clone(), Some(sampler.clone())),
+        };
+        let center = Point3::new(model.w.x, model.w.y, model.w.z);
+        (context.make_batch(&scene_program, scene_data, &mesh, slice.clone(), &state).unwrap(),
+         context.make_batch(&decal_program, decal_data, &mesh, slice, &biased).unwrap(),
+         center, radius)
+    }).collect();
+
+    // a few to begin with: on the ground, the sphere, and over a corner of
+    // the cube
+    let mut decals: VecDeque<Decal> = vec![
+        Decal::new(Point3::new(0.0, -2.0, -1.0), Vector3::new(0.0, 0.0, -1.0), 0.3, 1.0, 0),
+        Decal::new(Point3::new(1.5, -2.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 0.0, 0.6, 2),
+        Decal::new(Point3::new(-2.0, -1.0, 0.2), Vector3::new(0.0, 1.0, 0.0), 1.0, 0.8, 1),
+        Decal::new(Point3::new(-1.0, -1.0, 1.0), Vector3::new(-1.0, 1.0, -1.0), 0.5, 0.9, 0),
+    ].into_iter().collect();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, -0.5), 10.0, -1.8, 0.6);
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.35, 0.4, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut texture = 0;
+    let mut bias = true;
+    // the cursor in pixels from the bottom left, as GL counts them
+    let mut cursor: Option<(i32, i32)> = None;
+    let mut placing = false;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, MouseButton, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::MouseMoved((x, y)) => {
+                    let (x, y) = ((x as f32 * hidpi) as i32, (y as f32 * hidpi) as i32);
+                    cursor = Some((x, h as i32 - 1 - y));
+                },
+                Event::MouseInput(ElementState::Pressed, MouseButton::Right) =>
+                    placing = true,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::T => texture = (texture + 1) % TEXTURES.len(),
+                    VirtualKeyCode::B => bias = !bias,
+                    VirtualKeyCode::C => decals.clear(),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        let view = camera.view().mat;
+        let view_proj = proj.mul_m(&view);
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for &mut (ref mut batch, _, _, _) in batches.iter_mut() {
+            batch.params.view_proj = view_proj.into_fixed();
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        // Each decal over every object that reaches into it, in the order
+        // they were placed
+        let mut draws = 0;
+        for decal in decals.iter() {
+            for &mut (_, ref mut batch, center, radius) in batches.iter_mut() {
+                let reach = decal.radius + radius;
+                if decal.center.sub_p(&center).length2() > reach * reach {
+                    continue;
+                }
+                batch.state = if bias { biased.clone() } else { unbiased.clone() };
+                batch.params.view_proj = view_proj.into_fixed();
+                batch.params.decal = decal.matrix.into_fixed();
+                batch.params.forward = decal.forward.into_fixed();
+                batch.params.texture.0 = textures[decal.texture].clone();
+                renderer.draw(&(&*batch, &context), &wrap).unwrap();
+                draws += 1;
+            }
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        // The point under the cursor, from its depth
+        match cursor {
+            Some((x, y)) if placing && x >= 0 && y >= 0 && x < w as i32 && y < h as i32 => {
+                let mut depth = 1.0f32;
+                unsafe {
+                    gl.ReadBuffer(gl::BACK);
+                    gl.ReadPixels(x, y, 1, 1, gl::DEPTH_COMPONENT, gl::FLOAT,
+                                  &mut depth as *mut f32 as *mut GLvoid);
+                }
+                if depth < 1.0 {
+                    let ndc = Vector4::new(2.0 * (x as f32 + 0.5) / w as f32 - 1.0,
+                                           2.0 * (y as f32 + 0.5) / h as f32 - 1.0,
+                                           2.0 * depth - 1.0, 1.0);
+                    let p = view_proj.invert().unwrap().mul_v(&ndc);
+                    let point = Point3::new(p.x / p.w, p.y / p.w, p.z / p.w);
+                    let forward = point.sub_p(&camera.position());
+                    let size = rng.gen_range(0.4f32, 0.9);
+                    let angle = rng.gen::<f32>() * 2.0 * PI;
+                    decals.push_back(Decal::new(point, forward, angle, size, texture));
+                    if decals.len() > MAX_DECALS {
+                        decals.pop_front();
+                    }
+                }
+            },
+            _ => {},
+        }
+        placing = false;
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Decal example: {} decals in {} draws, next {}, bias {}",
+                                       decals.len(), draws, TEXTURES[texture],
+                                       if bias { "on" } else { "off" }));
+    }
+}
@@ -15,16 +15,62 @@
 
 // This is an example of deferred shading with gfx-rs.
 //
-// Two render targets are created: a geometry buffer and a result buffer.
+// A render target is created for the geometry buffer.
 //
 // Rendering happens in two passes:
 // First,  the terrain is rendered, writing position, normal and color to the geometry buffer.
 // Second, the lights are rendered as cubes. each fragment reads from the geometry buffer,
-//         light is applied, and the result is written to the result buffer.
+//         light is applied, and the result is written to the window.
 //
-// The result buffer is then displayed.
+// The depth of the geometry buffer is copied to the window before the lights,
+// so that they are drawn straight into it without a result buffer in between.
 //
 // Press 1-4 to show the immediate buffers. Press 0 to show the final result.
+//
+// Run with --inspector to open a second window showing all of the immediate
+// buffers side by side, with --sky to draw a dusk sky from
+// `gfx_examples::sky` behind the terrain, and with --outline to draw
+// outlines from the normals and the depth of the geometry buffer with
+// `gfx_examples::outline`. Run with --heightmap to displace a flat grid by a
+// height texture instead of generating the terrain vertices, and press Up
+// and Down to change how fine the grid is.
+//
+// The terrain triangles are reordered by `gfx_examples::vertex_cache` before
+// they are uploaded. Run with --benchmark to draw the geometry pass in both
+// orders every frame, and show how long the GPU took with each in the title.
+//
+// The blits and the depth copy to the window and the light emitters are
+// recorded once into `gfx_examples::retained::RetainedPass`es and submitted
+// again every frame, the emitters only being recorded again when the camera
+// has moved. Press C to stop the camera, and R to record every pass every
+// frame instead.
+//
+// The batches having a program to themselves wrap their parameters in a
+// `gfx_examples::dirty::Dirty`, which leaves out the uniforms that haven't
+// changed since the last draw, like the projection.
+//
+// Nothing is allocated or built in the main loop: every batch, including one
+// blit per buffer that can be shown, is made once before it, and each frame
+// only changes a few matrices in their parameters and fills the same vector
+// of light positions. The title shows the CPU time spent on a frame. It is
+// written into the same string, and only every few frames, since the window
+// system copies it whenever it is set.
+//
+// After the geometry pass, its depth is reduced by a
+// `gfx_examples::depth_pyramid::DepthPyramid` into levels keeping the
+// farthest depth of the texels below, and the coarsest level is read back.
+// The lights whose cubes are entirely behind it are left out of the light
+// positions, and aren't drawn. Press H to draw all of them again.
+//
+// The light positions of each tick are sampled from the noise on a worker
+// thread, which works out the next tick while the main thread renders.
+//
+// The light positions are written straight into their mapped uniform buffer
+// by a `gfx_examples::writer::BufferWriter`. Press M to switch to writing them
+// into a vector and uploading it instead, then to uploading only the runs of
+// positions that changed, or run with --copy-lights or --changed-lights to
+// start that way. Run with --torches to keep the first 50 lights still, which
+// leaves them out of the changed runs.
 
 #![feature(plugin, custom_attribute)]
 #![plugin(gfx_macros)]
@@ -32,56 +78,74 @@
 extern crate cgmath;
 extern crate env_logger;
 extern crate gfx;
+extern crate gfx_gl as gl;
+extern crate gfx_examples;
 extern crate gfx_window_glutin;
 extern crate glutin;
-extern crate time;
 extern crate rand;
 extern crate genmesh;
 extern crate noise;
+extern crate time;
 
+use std::env;
+use std::fmt::Write;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
 use rand::Rng;
 use cgmath::FixedArray;
-use cgmath::{Matrix, Matrix4, Point3, Vector3, EuclideanVector};
+use cgmath::{Matrix, Matrix4, Point, Point3, Vector, Vector3, Vector4, EuclideanVector};
 use cgmath::{Transform, AffineMatrix3};
 use gfx::traits::*;
 use gfx::{Plane, RawBufferHandle};
+use gl::types::*;
+use gfx_examples::depth_pyramid::DepthPyramid;
+use gfx_examples::dirty::Dirty;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::outline::Outline;
+use gfx_examples::retained::RetainedPass;
+use gfx_examples::sky::Sky;
+use gfx_examples::writer::{BufferWriter, WriteMode};
+use gfx_examples::{capture, index, vertex_cache, window, Clock, Thumbnail};
 use genmesh::{Vertices, Triangulate};
 use genmesh::generators::{SharedVertex, IndexedPolygon};
-use time::precise_time_s;
 
 use noise::{Seed, perlin2};
 
 // Remember to also change the constants in the shaders
 const NUM_LIGHTS: usize = 250;
-
+// Threads generating the terrain at startup
+const TERRAIN_THREADS: usize = 8;
+// Weight of the latest frame in the smoothed timings
+const SMOOTHING: f64 = 0.05;
+// Frames between two updates of the title
+const TITLE_FRAMES: u32 = 30;
+// Lights standing still with --torches, the first ones, on the outer ring
+const NUM_TORCHES: usize = 50;
+// Levels of the depth pyramid after the depth of the geometry pass, each
+// half the size of the one before. The last one is read back to cull the
+// lights against.
+const HIZ_LEVELS: usize = 4;
+// Texels along each side of the height texture, and the squares along each
+// side of the grid displaced by it
+const HEIGHTMAP_SIZE: usize = 512;
+const MIN_GRID_SIZE: usize = 32;
+const MAX_GRID_SIZE: usize = 1024;
+
+// 20 bytes instead of the 36 of three float vectors: the normal and the
+// color are read as normalized integers, the fourth component of each only
+// being there to keep them 4 bytes long
 #[vertex_format]
 #[derive(Clone, Copy)]
 struct TerrainVertex {
     #[name = "a_Pos"]
     pos: [f32; 3],
+    #[normalized]
     #[name = "a_Normal"]
-    normal: [f32; 3],
+    normal: [i8; 4],
+    #[normalized]
     #[name = "a_Color"]
-    color: [f32; 3],
-}
-
-#[vertex_format]
-#[derive(Clone, Copy)]
-struct BlitVertex {
-    #[as_float]
-    #[name = "a_Pos"]
-    pos: [i8; 3],
-    #[as_float]
-    #[name = "a_TexCoord"]
-    tex_coord: [u8; 2],
-}
-
-#[vertex_format]
-#[derive(Clone, Copy)]
-struct CubeVertex {
-    #[as_float]
-    #[name = "a_Pos"]
-    pos: [i8; 3],
+    color: [u8; 4],
 }
 
 #[shader_param]
@@ -97,6 +161,24 @@ struct TerrainParams<R: gfx::Resources> {
     _dummy: std::marker::PhantomData<R>,
 }
 
+#[shader_param]
+struct DisplacedParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_View"]
+    view: [[f32; 4]; 4],
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    #[name = "u_CameraPos"]
+    cam_pos: [f32; 3],
+    #[name = "t_Height"]
+    height: gfx::shade::TextureParam<R>,
+    #[name = "u_Scale"]
+    scale: [f32; 3],
+    #[name = "u_GridSize"]
+    grid_size: f32,
+}
+
 #[shader_param]
 struct LightParams<R: gfx::Resources> {
     #[name = "u_Transform"]
@@ -133,6 +215,14 @@ struct BlitParams<R: gfx::Resources> {
     tex: gfx::shade::TextureParam<R>,
 }
 
+#[shader_param]
+struct TileParams<R: gfx::Resources> {
+    #[name = "u_Tex"]
+    tex: gfx::shade::TextureParam<R>,
+    #[name = "u_Rect"]
+    rect: [f32; 4],
+}
+
 static TERRAIN_VERTEX_SRC: &'static [u8] = b"
     #version 150 core
 
@@ -154,6 +244,54 @@ static TERRAIN_VERTEX_SRC: &'static [u8] = b"
     }
 ";
 
+// The corners of the two triangles of a square, as in the index buffer of
+// the generated terrain, and the color from the height as `calculate_color`
+static DISPLACED_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_View;
+    uniform mat4 u_Proj;
+    uniform sampler2D t_Height;
+    uniform vec3 u_Scale;
+    uniform float u_GridSize;
+    out vec3 v_FragPos;
+    out vec3 v_Normal;
+    out vec3 v_Color;
+
+    const ivec2 CORNERS[6] = ivec2[](ivec2(0, 0), ivec2(1, 0), ivec2(1, 1),
+                                     ivec2(0, 0), ivec2(1, 1), ivec2(0, 1));
+
+    // from 0 to 1 over the grid, which ends at the centers of the texels on
+    // the edges
+    float height(vec2 uv) {
+        vec2 size = vec2(textureSize(t_Height, 0));
+        return u_Scale.z * texture(t_Height, (uv * (size - 1.0) + 0.5) / size).r;
+    }
+
+    void main() {
+        int size = int(u_GridSize);
+        int square = gl_VertexID / 6;
+        ivec2 corner = ivec2(square % size, square / size) + CORNERS[gl_VertexID % 6];
+        vec2 uv = vec2(corner) / u_GridSize;
+        vec3 pos = vec3(u_Scale.xy * (2.0 * uv - 1.0), height(uv));
+
+        // the slope over a texel on each side, the grid being twice u_Scale
+        // wide
+        vec2 texel = 1.0 / vec2(textureSize(t_Height, 0) - 1);
+        vec2 span = 4.0 * u_Scale.xy * texel;
+        float dzdx = (height(uv + vec2(texel.x, 0.0)) - height(uv - vec2(texel.x, 0.0))) / span.x;
+        float dzdy = (height(uv + vec2(0.0, texel.y)) - height(uv - vec2(0.0, texel.y))) / span.y;
+
+        v_FragPos = (u_Model * vec4(pos, 1.0)).xyz;
+        v_Normal = vec3(-dzdx, -dzdy, 1.0);
+        v_Color = pos.z > 8.0 ? vec3(0.9, 0.9, 0.9) :
+                  pos.z > 0.0 ? vec3(0.7, 0.7, 0.7) :
+                  pos.z > -5.0 ? vec3(0.2, 0.7, 0.2) : vec3(0.2, 0.2, 0.7);
+        gl_Position = u_Proj * u_View * u_Model * vec4(pos, 1.0);
+    }
+";
+
 static TERRAIN_FRAGMENT_SRC: &'static [u8] = b"
     #version 150 core
 
@@ -199,6 +337,51 @@ static BLIT_FRAGMENT_SRC: &'static [u8] = b"
     }
 ";
 
+// Fills the window with the depth of the geometry pass, and with black in
+// place of a clear
+static DEPTH_COPY_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D u_Tex;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        gl_FragDepth = texture(u_Tex, v_TexCoord).r;
+        o_Color = vec4(0.0, 0.0, 0.0, 1.0);
+    }
+";
+
+// Writes the last level of the pyramid into the depth of the window's corner,
+// texel for pixel, to be read back
+static HIZ_READ_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D u_Tex;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        gl_FragDepth = texelFetch(u_Tex, ivec2(gl_FragCoord.xy), 0).r;
+        o_Color = vec4(0.0, 0.0, 0.0, 1.0);
+    }
+";
+
+static TILE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Rect;
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        // the tile center is in xy, its half size in zw
+        gl_Position = vec4(a_Pos.xy * u_Rect.zw + u_Rect.xy, 0.0, 1.0);
+    }
+";
+
 static LIGHT_VERTEX_SRC: &'static [u8] = b"
     #version 150 core
 
@@ -236,6 +419,10 @@ static LIGHT_FRAGMENT_SRC: &'static [u8] = b"
         vec3 pos     = texture(u_TexPos,     texCoord).xyz;
         vec3 normal  = texture(u_TexNormal,  texCoord).xyz;
         vec3 diffuse = texture(u_TexDiffuse, texCoord).xyz;
+        // nothing was drawn here
+        if (dot(normal, normal) == 0.0) {
+            discard;
+        }
 
         vec3 light    = v_LightPos;
         vec3 to_light = normalize(light - pos);
@@ -311,6 +498,229 @@ fn calculate_color(height: f32) -> [f32; 3] {
     }
 }
 
+// From -1 to 1 into the range of an i8, and from 0 to 1 into that of a u8
+fn pack_snorm(v: f32) -> i8 {
+    (v.max(-1.0).min(1.0) * 127.0).round() as i8
+}
+
+fn pack_unorm(v: f32) -> u8 {
+    (v.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+fn terrain_vertex(seed: &Seed, scale: &Vector3<f32>, x: f32, y: f32) -> TerrainVertex {
+    let h = scale.z * perlin2(seed, &[x, y]);
+    let n = calculate_normal(seed, x, y);
+    let c = calculate_color(h);
+    TerrainVertex {
+        pos: [scale.x * x, scale.y * y, h],
+        normal: [pack_snorm(n[0]), pack_snorm(n[1]), pack_snorm(n[2]), 0],
+        color: [pack_unorm(c[0]), pack_unorm(c[1]), pack_unorm(c[2]), 255],
+    }
+}
+
+// Each of the noise samples is independent, so the points are split into
+// one run per thread, and the runs are put back together in order
+fn terrain_vertices(points: &[(f32, f32)], rand_seed: u32, scale: Vector3<f32>)
+                    -> Vec<TerrainVertex> {
+    let chunk = (points.len() + TERRAIN_THREADS - 1) / TERRAIN_THREADS;
+    let threads: Vec<_> = points.chunks(chunk).map(|run| {
+        let run = run.to_vec();
+        thread::spawn(move || {
+            let seed = Seed::new(rand_seed);
+            run.iter().map(|&(x, y)| terrain_vertex(&seed, &scale, x, y))
+               .collect::<Vec<_>>()
+        })
+    }).collect();
+    let mut vertices = Vec::with_capacity(points.len());
+    for handle in threads {
+        vertices.extend(handle.join().unwrap().into_iter());
+    }
+    vertices
+}
+
+// The noise of the terrain over the same square as the generated one, with
+// the centers of the texels on the edges at its ends
+fn create_heightmap<R: gfx::Resources, F: Factory<R>>(factory: &mut F, seed: &Seed)
+                    -> gfx::TextureHandle<R> {
+    let last = (HEIGHTMAP_SIZE - 1) as f32;
+    let mut data = Vec::with_capacity(HEIGHTMAP_SIZE * HEIGHTMAP_SIZE);
+    for j in 0 .. HEIGHTMAP_SIZE {
+        for i in 0 .. HEIGHTMAP_SIZE {
+            let (x, y) = (2.0 * i as f32 / last - 1.0, 2.0 * j as f32 / last - 1.0);
+            data.push(perlin2(seed, &[x, y]));
+        }
+    }
+    let texture_info = gfx::tex::TextureInfo {
+        width: HEIGHTMAP_SIZE as gfx::tex::Size,
+        height: HEIGHTMAP_SIZE as gfx::tex::Size,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::R, gfx::attrib::FloatSize::F32),
+    };
+    let texture = factory.create_texture(texture_info).unwrap();
+    factory.update_texture(&texture, &texture_info.to_image_info(), &data, None).unwrap();
+    texture
+}
+
+// GPU time of the geometry pass with each of `slices`, from a query each
+struct Benchmark<'a, R: gfx::Resources> {
+    gl: &'a gl::Gl,
+    slices: [gfx::Slice<R>; 2],
+    queries: [GLuint; 2],
+    issued: bool,
+    timings: [f64; 2],
+}
+
+impl<'a, R: gfx::Resources> Benchmark<'a, R> {
+    fn new(gl: &'a gl::Gl, slices: [gfx::Slice<R>; 2]) -> Benchmark<'a, R> {
+        let mut queries = [0; 2];
+        unsafe { gl.GenQueries(2, queries.as_mut_ptr()) };
+        Benchmark {
+            gl: gl,
+            slices: slices,
+            queries: queries,
+            issued: false,
+            timings: [0.0; 2],
+        }
+    }
+
+    // Collects the timings of the previous frame
+    fn collect(&mut self) {
+        if !self.issued {
+            return;
+        }
+        for (query, timing) in self.queries.iter().zip(self.timings.iter_mut()) {
+            let mut available = 0;
+            unsafe {
+                self.gl.GetQueryObjectuiv(*query, gl::QUERY_RESULT_AVAILABLE, &mut available)
+            };
+            if available != 0 {
+                let mut nanoseconds = 0;
+                unsafe { self.gl.GetQueryObjectui64v(*query, gl::QUERY_RESULT, &mut nanoseconds) };
+                let ms = nanoseconds as f64 * 1e-6;
+                *timing = if *timing == 0.0 { ms }
+                          else { *timing + (ms - *timing) * SMOOTHING };
+            }
+        }
+    }
+}
+
+impl<'a, R: gfx::Resources> Drop for Benchmark<'a, R> {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteQueries(2, self.queries.as_ptr()) };
+    }
+}
+
+// Whether the cube of a light is hidden behind what the geometry pass drew,
+// or off the screen: the nearest of its corners is farther than the farthest
+// depth of every texel of the last pyramid level that it covers. `depths` has
+// the `size` texels of that level, each covering `scale` pixels a side.
+fn light_hidden(transform: &Matrix4<f32>, pos: [f32; 4], radius: f32,
+                depths: &[f32], size: (usize, usize), scale: f32,
+                frame: (f32, f32)) -> bool {
+    // the bounds in normalized device coordinates, only clamped to the
+    // screen when turned into texels
+    let (mut x0, mut y0) = (std::f32::INFINITY, std::f32::INFINITY);
+    let (mut x1, mut y1) = (std::f32::NEG_INFINITY, std::f32::NEG_INFINITY);
+    let mut near = 1.0f32;
+    for i in 0 .. 8 {
+        let corner = Vector4::new(pos[0] + if i & 1 == 0 { -radius } else { radius },
+                                  pos[1] + if i & 2 == 0 { -radius } else { radius },
+                                  pos[2] + if i & 4 == 0 { -radius } else { radius },
+                                  1.0);
+        let clip = transform.mul_v(&corner);
+        if clip.w <= 0.0 {
+            // around or behind the camera
+            return false;
+        }
+        let (x, y, z) = (clip.x / clip.w, clip.y / clip.w, clip.z / clip.w);
+        x0 = x0.min(x);
+        y0 = y0.min(y);
+        x1 = x1.max(x);
+        y1 = y1.max(y);
+        near = near.min(0.5 * z + 0.5);
+    }
+    if x1 < -1.0 || y1 < -1.0 || x0 > 1.0 || y0 > 1.0 {
+        return true;
+    }
+    let texel = |ndc: f32, pixels: f32, texels: usize| {
+        let t = ((0.5 * ndc + 0.5) * pixels / scale).floor();
+        t.max(0.0).min((texels - 1) as f32) as usize
+    };
+    let (w, h) = size;
+    for ty in texel(y0, frame.1, h) .. texel(y1, frame.1, h) + 1 {
+        for tx in texel(x0, frame.0, w) .. texel(x1, frame.0, w) + 1 {
+            if depths[ty * w + tx] >= near {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn camera_position(time: f32) -> Point3<f32> {
+    // Slowly circle the center
+    let x = (0.05*time).sin();
+    let y = (0.05*time).cos();
+    Point3::new(x * 32.0, y * 32.0, 16.0)
+}
+
+// The first `still` lights stay where they are at the start
+fn update_lights(positions: &mut [[f32; 4]], seed: &Seed,
+                 terrain_scale: &Vector3<f32>, time: f32, still: usize) {
+    for (i, p) in positions.iter_mut().enumerate() {
+        let time = if i < still { 0.0 } else { time };
+        let (x, y) = {
+            let fi = i as f32;
+            // Distribute lights nicely
+            let r = 1.0 - (fi*fi) / ((NUM_LIGHTS*NUM_LIGHTS) as f32);
+            (r * (0.2*time + i as f32).cos(), r * (0.2*time + i as f32).sin())
+        };
+        let h = perlin2(seed, &[x, y]);
+
+        p[0] = terrain_scale.x * x;
+        p[1] = terrain_scale.y * y;
+        p[2] = terrain_scale.z * h + 0.5;
+    }
+}
+
+// Runs `update_lights` on a thread of its own, filling the vectors it is sent
+// with the positions at the time sent along, and sending them back in order
+struct LightWorker {
+    requests: Sender<(f32, Vec<[f32; 4]>)>,
+    results: Receiver<Vec<[f32; 4]>>,
+}
+
+impl LightWorker {
+    fn new(rand_seed: u32, terrain_scale: Vector3<f32>, still: usize) -> LightWorker {
+        let (requests, pending) = channel::<(f32, Vec<[f32; 4]>)>();
+        let (done, results) = channel();
+        thread::spawn(move || {
+            let seed = Seed::new(rand_seed);
+            for (time, mut positions) in pending.iter() {
+                update_lights(&mut positions, &seed, &terrain_scale, time, still);
+                if done.send(positions).is_err() {
+                    break;
+                }
+            }
+        });
+        LightWorker {
+            requests: requests,
+            results: results,
+        }
+    }
+
+    fn request(&self, time: f32, positions: Vec<[f32; 4]>) {
+        self.requests.send((time, positions)).unwrap();
+    }
+
+    // waits for the oldest request still pending
+    fn receive(&self) -> Vec<[f32; 4]> {
+        self.results.recv().unwrap()
+    }
+}
+
 fn create_g_buffer<R: gfx::Resources, F: Factory<R>>(
                    width: gfx::tex::Size, height: gfx::tex::Size, factory: &mut F)
                    -> (gfx::Frame<R>, gfx::TextureHandle<R>, gfx::TextureHandle<R>,
@@ -349,51 +759,50 @@ fn create_g_buffer<R: gfx::Resources, F: Factory<R>>(
     (frame, texture_pos, texture_normal, texture_diffuse, texture_depth)
 }
 
-fn create_res_buffer<R: gfx::Resources, F: Factory<R>>(
-                     width: gfx::tex::Size, height: gfx::tex::Size,
-                     factory: &mut F, texture_depth: &gfx::TextureHandle<R>)
-                     -> (gfx::Frame<R>, gfx::TextureHandle<R>, gfx::TextureHandle<R>) {
-    let texture_info_float = gfx::tex::TextureInfo {
-        width: width,
-        height: height,
-        depth: 1,
-        levels: 1,
-        kind: gfx::tex::TextureKind::Texture2D,
-        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F32),
-    };
-
-    let texture_frame = factory.create_texture(texture_info_float).unwrap();
-
-    let frame = gfx::Frame {
-        colors: vec![Plane::Texture(texture_frame.clone(), 0, None)],
-        depth: Some(Plane::Texture(texture_depth.clone(), 0, None)),
-       .. gfx::Frame::empty(width, height)
-    };
-
-    (frame, texture_frame, texture_depth.clone())
-}
-
 pub fn main() {
     env_logger::init().unwrap();
-    let (wrap, mut device, mut factory) = gfx_window_glutin::init(
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
         glutin::WindowBuilder::new()
             .with_title("Deferred rendering example with gfx-rs".to_string())
             .with_dimensions(800, 600)
             .with_gl(glutin::GlRequest::Latest)
             .build().unwrap()
-    );
+    ));
+
+    // The inspector window gets a device of its own, see `window::init_shared`.
+    // Its context shares objects with the main one, allowing it to draw the
+    // textures and batches created below.
+    let mut inspector = if env::args().any(|arg| arg == "--inspector") {
+        let (i_wrap, i_device, mut i_factory) = window::init_shared(&wrap,
+            glutin::WindowBuilder::new()
+                .with_title("G-buffer inspector".to_string())
+                .with_dimensions(800, 600)
+                .with_gl(glutin::GlRequest::Latest),
+            gfx_window_glutin::init);
+        let i_renderer = i_factory.create_renderer();
+        Some((i_wrap, i_device, i_renderer))
+    } else {
+        None
+    };
 
-    let (w, h) = wrap.get_size();
+    // Render targets have to match the surface in pixels, not in points
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
     let mut renderer = factory.create_renderer();
     let mut context = gfx::batch::Context::new();
 
     let (g_buffer, texture_pos, texture_normal, texture_diffuse, texture_depth) = create_g_buffer(w, h, &mut factory);
-    let (res_buffer, texture_frame, _) = create_res_buffer(w, h, &mut factory, &texture_depth);
-
-    let seed = {
-        let rand_seed = rand::thread_rng().gen();
-        Seed::new(rand_seed)
+    // the cube of the lights and the quad of every full-screen pass, shared by
+    // all their batches
+    let mut meshes = MeshCache::new();
+    let pyramid = DepthPyramid::new(&mut factory, &mut meshes, &texture_depth, HIZ_LEVELS);
+    let gl = gl::Gl::load_with(|s| wrap.window.get_proc_address(s));
+
+    let rand_seed = match thumbnail {
+        Some(_) => capture::SEED,
+        None => rand::thread_rng().gen(),
     };
+    let seed = Seed::new(rand_seed);
 
     let sampler = factory.create_sampler(
         gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
@@ -404,120 +813,170 @@ pub fn main() {
     let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 5.0, 100.0);
 
     let terrain_scale = Vector3::new(25.0, 25.0, 25.0);
-    let mut terrain = {
-        let plane = genmesh::generators::Plane::subdivide(256, 256);
-        let vertex_data: Vec<TerrainVertex> = plane.shared_vertex_iter()
-            .map(|(x, y)| {
-                let h = terrain_scale.z * perlin2(&seed, &[x, y]);
-                TerrainVertex {
-                    pos: [terrain_scale.x * x, terrain_scale.y * y, h],
-                    normal: calculate_normal(&seed, x, y),
-                    color: calculate_color(h),
-                }
-            })
-            .collect();
+    let use_heightmap = env::args().any(|arg| arg == "--heightmap");
+    let benchmarking = env::args().any(|arg| arg == "--benchmark");
+    let mut generated_slice = None;
+    let mut terrain = if use_heightmap { None } else { Some({
+            // 257 by 257 vertices, a few more than 16 bit indices can count,
+            // so `index::slice` keeps them in 32 bits
+            let plane = genmesh::generators::Plane::subdivide(256, 256);
+            let start = time::precise_time_s();
+            let points: Vec<(f32, f32)> = plane.shared_vertex_iter().collect();
+            let vertex_data = terrain_vertices(&points, rand_seed, terrain_scale);
+            println!("Generated {} terrain vertices in {:.0} ms on {} threads",
+                     vertex_data.len(), (time::precise_time_s() - start) * 1000.0,
+                     TERRAIN_THREADS);
+
+            let generated: Vec<u32> = plane.indexed_polygon_iter()
+                .triangulate()
+                .vertices()
+                .map(|i| i as u32)
+                .collect();
+            let start = time::precise_time_s();
+            let index_data = vertex_cache::optimize(&generated);
+            println!("Reordered the terrain triangles in {:.0} ms, from {:.2} to {:.2} vertices \
+                      shaded per triangle", (time::precise_time_s() - start) * 1000.0,
+                     vertex_cache::acmr(&generated, 32), vertex_cache::acmr(&index_data, 32));
+            if benchmarking {
+                generated_slice = Some(index::slice(&mut factory, &generated,
+                                                    gfx::PrimitiveType::TriangleList));
+            }
 
-        let index_data: Vec<u32> = plane.indexed_polygon_iter()
-            .triangulate()
-            .vertices()
-            .map(|i| i as u32)
-            .collect();
+            let mesh = factory.create_mesh(&vertex_data);
 
-        let mesh = factory.create_mesh(&vertex_data);
+            let slice = index::slice(&mut factory, &index_data, gfx::PrimitiveType::TriangleList);
 
-        let slice = factory
-            .create_buffer_index::<u32>(&index_data)
-            .to_slice(gfx::PrimitiveType::TriangleList);
+            let program = factory.link_program(TERRAIN_VERTEX_SRC, TERRAIN_FRAGMENT_SRC)
+                                 .unwrap();
+            let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+            let data = TerrainParams {
+                model: Matrix4::identity().into_fixed(),
+                view: Matrix4::identity().into_fixed(),
+                proj: proj.into_fixed(),
+                cam_pos: Vector3::new(0.0, 0.0, 0.0).into_fixed(),
+                _dummy: std::marker::PhantomData,
+            };
 
-        let program = factory.link_program(TERRAIN_VERTEX_SRC, TERRAIN_FRAGMENT_SRC)
+            context.make_batch(&program, Dirty::new(data), &mesh, slice, &state)
+                   .unwrap()
+    }) };
+
+    let mut benchmark = generated_slice.map(|generated| {
+        let reordered = terrain.as_ref().unwrap().slice.clone();
+        Benchmark::new(&gl, [generated, reordered])
+    });
+
+    // The same terrain from a height texture over a flat grid of squares,
+    // made in the vertex shader from the index of each vertex, so it has no
+    // vertex buffer and the grid can be made finer or coarser at will
+    let height_sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+    let mut grid_size = 256;
+    let mut displaced = if use_heightmap { Some({
+        let start = time::precise_time_s();
+        let height_texture = create_heightmap(&mut factory, &seed);
+        println!("Baked a {0}x{0} heightmap in {1:.0} ms", HEIGHTMAP_SIZE,
+                 (time::precise_time_s() - start) * 1000.0);
+
+        let mesh = gfx::Mesh::new((MAX_GRID_SIZE * MAX_GRID_SIZE * 6) as gfx::VertexCount);
+        let mut slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        slice.end = (grid_size * grid_size * 6) as gfx::VertexCount;
+
+        let program = factory.link_program(DISPLACED_VERTEX_SRC, TERRAIN_FRAGMENT_SRC)
                              .unwrap();
         let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
 
-        let data = TerrainParams {
+        let data = DisplacedParams {
             model: Matrix4::identity().into_fixed(),
             view: Matrix4::identity().into_fixed(),
             proj: proj.into_fixed(),
             cam_pos: Vector3::new(0.0, 0.0, 0.0).into_fixed(),
-            _dummy: std::marker::PhantomData,
+            height: (height_texture, Some(height_sampler.clone())),
+            scale: terrain_scale.into_fixed(),
+            grid_size: grid_size as f32,
         };
 
-        context.make_batch(&program, data, &mesh, slice, &state)
+        context.make_batch(&program, Dirty::new(data), &mesh, slice, &state)
                .unwrap()
-    };
+    }) } else { None };
 
-    let mut blit = {
-        let vertex_data = [
-            BlitVertex { pos: [-1, -1, 0], tex_coord: [0, 0] },
-            BlitVertex { pos: [ 1, -1, 0], tex_coord: [1, 0] },
-            BlitVertex { pos: [ 1,  1, 0], tex_coord: [1, 1] },
-            BlitVertex { pos: [-1, -1, 0], tex_coord: [0, 0] },
-            BlitVertex { pos: [ 1,  1, 0], tex_coord: [1, 1] },
-            BlitVertex { pos: [-1,  1, 0], tex_coord: [0, 1] },
-        ];
-        let mesh = factory.create_mesh(&vertex_data);
-        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+    // The immediate buffers that the window can show in place of the result
+    let planes = [&texture_pos, &texture_normal, &texture_diffuse, &texture_depth];
+    let (blits, depth_copy, hiz_read, tiles) = {
+        let (mesh, slice) = meshes.quad(&mut factory);
 
         let program = factory.link_program(BLIT_VERTEX_SRC, BLIT_FRAGMENT_SRC)
                              .unwrap();
         let state = gfx::DrawState::new();
 
-        let data = BlitParams {
-          tex: (texture_pos.clone(), Some(sampler.clone())),
+        // A batch per buffer, so that switching is only picking another one
+        let blits: Vec<_> = planes.iter().map(|tex| {
+            let data = BlitParams {
+              tex: ((*tex).clone(), Some(sampler.clone())),
+            };
+            context.make_batch(&program, data, &mesh, slice.clone(), &state)
+                   .unwrap()
+        }).collect();
+
+        let depth_copy = {
+            let program = factory.link_program(BLIT_VERTEX_SRC, DEPTH_COPY_FRAGMENT_SRC)
+                                 .unwrap();
+            let state = gfx::DrawState::new().depth(gfx::state::Comparison::Always, true);
+            let data = BlitParams {
+              tex: (texture_depth.clone(), Some(sampler.clone())),
+            };
+            context.make_batch(&program, data, &mesh, slice.clone(), &state)
+                   .unwrap()
         };
 
-        context.make_batch(&program, data, &mesh, slice, &state)
-               .unwrap()
+        // The last level in the corner of the window, as many pixels as it
+        // has texels
+        let hiz_read = {
+            let program = factory.link_program(TILE_VERTEX_SRC, HIZ_READ_FRAGMENT_SRC)
+                                 .unwrap();
+            let state = gfx::DrawState::new().depth(gfx::state::Comparison::Always, true);
+            let (level_w, level_h) = pyramid.size(HIZ_LEVELS - 1);
+            let (sx, sy) = (level_w as f32 / w as f32, level_h as f32 / h as f32);
+            let data = TileParams {
+                tex: (pyramid.levels[HIZ_LEVELS - 1].0.clone(), Some(sampler.clone())),
+                rect: [sx - 1.0, sy - 1.0, sx, sy],
+            };
+            context.make_batch(&program, data, &mesh, slice.clone(), &state)
+                   .unwrap()
+        };
+
+        // One quarter of the inspector window per immediate buffer
+        let tile_program = factory.link_program(TILE_VERTEX_SRC, BLIT_FRAGMENT_SRC)
+                                  .unwrap();
+        let tiles: Vec<_> = planes.iter().enumerate().map(|(i, tex)| {
+            let data = TileParams {
+                tex: ((*tex).clone(), Some(sampler.clone())),
+                rect: [(i % 2) as f32 - 0.5, 0.5 - (i / 2) as f32, 0.5, 0.5],
+            };
+            context.make_batch(&tile_program, data, &mesh, slice.clone(), &state)
+                   .unwrap()
+        }).collect();
+
+        (blits, depth_copy, hiz_read, tiles)
     };
 
-    let light_pos_buffer = factory.create_buffer::<[f32; 4]>(NUM_LIGHTS, gfx::BufferUsage::Stream);
+    let torches = if env::args().any(|arg| arg == "--torches") { NUM_TORCHES } else { 0 };
+    let mode = if env::args().any(|arg| arg == "--copy-lights") {
+        WriteMode::Copy
+    } else if env::args().any(|arg| arg == "--changed-lights") {
+        WriteMode::Changed
+    } else {
+        WriteMode::Mapped
+    };
+    let mut light_pos_writer = BufferWriter::<_, [f32; 4]>::new(&mut factory, NUM_LIGHTS, mode);
 
     let (mut light, mut emitter) = {
-        let vertex_data = [
-            // top (0, 0, 1)
-            CubeVertex { pos: [-1, -1,  1] },
-            CubeVertex { pos: [ 1, -1,  1] },
-            CubeVertex { pos: [ 1,  1,  1] },
-            CubeVertex { pos: [-1,  1,  1] },
-            // bottom (0, 0, -1)
-            CubeVertex { pos: [-1,  1, -1] },
-            CubeVertex { pos: [ 1,  1, -1] },
-            CubeVertex { pos: [ 1, -1, -1] },
-            CubeVertex { pos: [-1, -1, -1] },
-            // right (1, 0, 0)
-            CubeVertex { pos: [ 1, -1, -1] },
-            CubeVertex { pos: [ 1,  1, -1] },
-            CubeVertex { pos: [ 1,  1,  1] },
-            CubeVertex { pos: [ 1, -1,  1] },
-            // left (-1, 0, 0)
-            CubeVertex { pos: [-1, -1,  1] },
-            CubeVertex { pos: [-1,  1,  1] },
-            CubeVertex { pos: [-1,  1, -1] },
-            CubeVertex { pos: [-1, -1, -1] },
-            // front (0, 1, 0)
-            CubeVertex { pos: [ 1,  1, -1] },
-            CubeVertex { pos: [-1,  1, -1] },
-            CubeVertex { pos: [-1,  1,  1] },
-            CubeVertex { pos: [ 1,  1,  1] },
-            // back (0, -1, 0)
-            CubeVertex { pos: [ 1, -1,  1] },
-            CubeVertex { pos: [-1, -1,  1] },
-            CubeVertex { pos: [-1, -1, -1] },
-            CubeVertex { pos: [ 1, -1, -1] },
-        ];
-
-        let index_data: &[u8] = &[
-             0,  1,  2,  2,  3,  0, // top
-             4,  5,  6,  6,  7,  4, // bottom
-             8,  9, 10, 10, 11,  8, // right
-            12, 13, 14, 14, 15, 12, // left
-            16, 17, 18, 18, 19, 16, // front
-            20, 21, 22, 22, 23, 20, // back
-        ];
-
-        let mesh = factory.create_mesh(&vertex_data);
-        let slice = factory
-            .create_buffer_index::<u8>(index_data)
-            .to_slice(gfx::PrimitiveType::TriangleList);
+        // the cube of `shape`, scaled to the radius of each light in the
+        // vertex shader
+        let (mesh, slice) = meshes.cube(&mut factory);
 
         let state = gfx::DrawState::new()
             .depth(gfx::state::Comparison::LessEqual, false)
@@ -525,7 +984,7 @@ pub fn main() {
 
         let light_data = LightParams {
             transform: Matrix4::identity().into_fixed(),
-            light_pos_buf: light_pos_buffer.raw().clone(),
+            light_pos_buf: light_pos_writer.buffer().raw().clone(),
             radius: 3.0,
             cam_pos: Vector3::new(0.0, 0.0, 0.0).into_fixed(),
             frame_res: [w as f32, h as f32],
@@ -538,13 +997,13 @@ pub fn main() {
             let program = factory.link_program(LIGHT_VERTEX_SRC, LIGHT_FRAGMENT_SRC)
                                  .unwrap();
 
-            context.make_batch(&program, light_data, &mesh, slice.clone(), &state)
+            context.make_batch(&program, Dirty::new(light_data), &mesh, slice.clone(), &state)
                    .unwrap()
         };
 
         let emitter_data = EmitterParams {
             transform: Matrix4::identity().into_fixed(),
-            light_pos_buf: light_pos_buffer.raw().clone(),
+            light_pos_buf: light_pos_writer.buffer().raw().clone(),
             radius: 0.2,
         };
 
@@ -552,7 +1011,7 @@ pub fn main() {
             let program = factory.link_program(EMITTER_VERTEX_SRC, EMITTER_FRAGMENT_SRC)
                                  .unwrap();
 
-            context.make_batch(&program, emitter_data, &mesh, slice, &state)
+            context.make_batch(&program, Dirty::new(emitter_data), &mesh, slice, &state)
                    .unwrap()
         };
 
@@ -565,13 +1024,78 @@ pub fn main() {
         stencil: 0,
     };
 
-    let mut debug_buf: Option<gfx::TextureHandle<_>> = None;
-
-    let mut light_pos_vec: Vec<[f32; 4]> = (0 ..NUM_LIGHTS).map(|_| {
-        [0.0, 0.0, 0.0, 0.0]
+    // The blits and the depth copy to the window never change, so one is
+    // recorded for each of them. The emitters change with the camera only,
+    // their positions being read from the uniform buffer.
+    let mut show_passes: Vec<_> = blits.iter().map(|_| {
+        RetainedPass::new(factory.create_renderer())
     }).collect();
+    let mut copy_pass = RetainedPass::new(factory.create_renderer());
+    let mut emitter_pass = RetainedPass::new(factory.create_renderer());
+    let mut retain = true;
+
+    let mut sky = if env::args().any(|arg| arg == "--sky") {
+        let mut sky = Sky::new(&mut factory, &mut meshes);
+        // just after sunset, to match the lights
+        sky.sun_dir = [0.0, 1.0, -0.02];
+        Some(sky)
+    } else {
+        None
+    };
+
+    let mut outline = if env::args().any(|arg| arg == "--outline") {
+        let mut outline = Outline::new(&mut factory, &mut meshes, &texture_normal,
+                                       &texture_depth);
+        // faint, so that the lights still stand out
+        outline.color = [1.0, 0.9, 0.7, 0.35];
+        Some(outline)
+    } else {
+        None
+    };
+
+    // the immediate buffer shown, from 1 to 4, or 0 for the final result
+    let mut shown = 0;
+
+    // The camera and the lights are simulated at a fixed rate, keeping the
+    // previous and the latest state to interpolate between when rendering.
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let mut camera_time = 0.0;
+    let mut camera_moving = true;
+    let mut prev_cam_pos = camera_position(0.0);
+    let mut next_cam_pos = prev_cam_pos;
+    let mut prev_light_pos = vec![[0.0; 4]; NUM_LIGHTS];
+    update_lights(&mut prev_light_pos, &seed, &terrain_scale, 0.0, torches);
+    let mut next_light_pos = prev_light_pos.clone();
+    // The positions of the next tick are always being worked out in the
+    // background, in a third vector, while the frame is rendered
+    let light_worker = LightWorker::new(rand_seed, terrain_scale, torches);
+    light_worker.request(clock.step(), vec![[0.0; 4]; NUM_LIGHTS]);
+
+    // The last level of the depth pyramid as read back, the lights left
+    // after culling against it, and how many emitters the emitter pass draws
+    let hiz_size = {
+        let (level_w, level_h) = pyramid.size(HIZ_LEVELS - 1);
+        (level_w as usize, level_h as usize)
+    };
+    let hiz_scale = (1 << HIZ_LEVELS) as f32;
+    let mut hiz_depths = vec![1.0f32; hiz_size.0 * hiz_size.1];
+    let mut visible = Vec::with_capacity(NUM_LIGHTS);
+    let mut culling = true;
+    let mut emitters_drawn = NUM_LIGHTS;
+
+    // CPU time from the start of a frame until it is submitted, smoothed
+    let mut cpu_time = 0.0;
+    // and its sum and the frames over the whole run, with every pass recorded
+    // each frame and with the retained passes, printed when quitting
+    let mut cpu_totals = [(0.0, 0); 2];
+    let mut title = String::with_capacity(256);
+    let mut title_frame = 0;
 
      'main: loop {
+        let frame_start = time::precise_time_s();
         // quit when Esc is pressed.
         for event in wrap.window.poll_events() {
             use glutin::{Event, VirtualKeyCode};
@@ -580,98 +1104,289 @@ pub fn main() {
                 Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
                     break 'main,
                 Event::KeyboardInput(_, _, Some(VirtualKeyCode::Numpad1)) =>
-                    debug_buf = Some(texture_pos.clone()),
+                    shown = 1,
                 Event::KeyboardInput(_, _, Some(VirtualKeyCode::Numpad2)) =>
-                    debug_buf = Some(texture_normal.clone()),
+                    shown = 2,
                 Event::KeyboardInput(_, _, Some(VirtualKeyCode::Numpad3)) =>
-                    debug_buf = Some(texture_diffuse.clone()),
+                    shown = 3,
                 Event::KeyboardInput(_, _, Some(VirtualKeyCode::Numpad4)) =>
-                    debug_buf = Some(texture_depth.clone()),
+                    shown = 4,
                 Event::KeyboardInput(_, _, Some(VirtualKeyCode::Numpad0)) =>
-                    debug_buf = None,
+                    shown = 0,
+                Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(VirtualKeyCode::M)) =>
+                    light_pos_writer.mode = match light_pos_writer.mode {
+                        WriteMode::Mapped => WriteMode::Copy,
+                        WriteMode::Copy => WriteMode::Changed,
+                        WriteMode::Changed => WriteMode::Mapped,
+                    },
+                Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(VirtualKeyCode::C)) =>
+                    camera_moving = !camera_moving,
+                Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(VirtualKeyCode::R)) =>
+                    retain = !retain,
+                Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(VirtualKeyCode::H)) =>
+                    culling = !culling,
+                Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(VirtualKeyCode::Up))
+                    if grid_size < MAX_GRID_SIZE => grid_size *= 2,
+                Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(VirtualKeyCode::Down))
+                    if grid_size > MIN_GRID_SIZE => grid_size /= 2,
                 _ => {},
             }
         }
+        if let Some((ref i_wrap, _, _)) = inspector {
+            for event in i_wrap.window.poll_events() {
+                if let glutin::Event::Closed = event {
+                    break 'main;
+                }
+            }
+        }
 
-        let time = precise_time_s() as f32;
+        clock.update();
+        while clock.tick() {
+            let next_time = (clock.time() + clock.step() as f64) as f32;
+            if camera_moving {
+                camera_time += clock.step();
+            }
+            prev_cam_pos = next_cam_pos;
+            next_cam_pos = camera_position(camera_time);
+            // the positions at this tick were requested at the one before,
+            // and the oldest vector goes back to the worker for the next one
+            let latest = light_worker.receive();
+            let oldest = mem::replace(&mut prev_light_pos,
+                                      mem::replace(&mut next_light_pos, latest));
+            light_worker.request(next_time, oldest);
+        }
+        let alpha = clock.alpha();
 
         // Update camera position
-        {
-            let cam_pos = {
-                // Slowly circle the center
-                let x = (0.05*time).sin();
-                let y = (0.05*time).cos();
-                Point3::new(x * 32.0, y * 32.0, 16.0)
-            };
+        let (view_mat, view_proj) = {
+            let cam_pos = prev_cam_pos.add_v(&next_cam_pos.sub_p(&prev_cam_pos).mul_s(alpha));
             let view: AffineMatrix3<f32> = Transform::look_at(
                 &cam_pos,
                 &Point3::new(0.0, 0.0, 0.0),
                 &Vector3::unit_z(),
             );
-            terrain.params.view = view.mat.into_fixed();
-            terrain.params.cam_pos = cam_pos.into_fixed();
+            let view_proj = proj.mul_m(&view.mat);
+            let transform = view_proj.into_fixed();
+            if let Some(ref mut terrain) = terrain {
+                terrain.params.view = view.mat.into_fixed();
+                terrain.params.cam_pos = cam_pos.into_fixed();
+            }
+            if let Some(ref mut displaced) = displaced {
+                displaced.params.view = view.mat.into_fixed();
+                displaced.params.cam_pos = cam_pos.into_fixed();
+            }
 
-            light.params.transform = proj.mul_m(&view.mat).into_fixed();
+            light.params.transform = transform;
             light.params.cam_pos = cam_pos.into_fixed();
 
-            emitter.params.transform = proj.mul_m(&view.mat).into_fixed();
+            if emitter.params.transform != transform {
+                emitter.params.transform = transform;
+                emitter_pass.invalidate();
+            }
+            (view.mat, view_proj)
+        };
+        let light_pos = |i: usize| {
+            let (a, b) = (prev_light_pos[i], next_light_pos[i]);
+            let mut p = [0.0; 4];
+            for k in 0 ..3 {
+                p[k] = a[k] + (b[k] - a[k]) * alpha;
+            }
+            p
+        };
+
+        if let Some(ref mut displaced) = displaced {
+            displaced.slice.end = (grid_size * grid_size * 6) as gfx::VertexCount;
+            displaced.params.grid_size = grid_size as f32;
         }
 
-        // Update light positions
-        for (i, p) in light_pos_vec.iter_mut().enumerate() {
-            let (x, y) = {
-                let fi = i as f32;
-                // Distribute lights nicely
-                let r = 1.0 - (fi*fi) / ((NUM_LIGHTS*NUM_LIGHTS) as f32);
-                (r * (0.2*time + i as f32).cos(), r * (0.2*time + i as f32).sin())
-            };
-            let h = perlin2(&seed, &[x, y]);
+        // Render the terrain to the geometry buffer
+        match (terrain.as_mut(), benchmark.as_mut()) {
+            (Some(terrain), Some(benchmark)) => {
+                // In both orders, each submitted on its own between the
+                // queries, the reordered one last so the rest of the frame
+                // sees the same as without the benchmark
+                benchmark.collect();
+                for (slice, &query) in benchmark.slices.iter().zip(benchmark.queries.iter()) {
+                    terrain.slice = slice.clone();
+                    unsafe { benchmark.gl.BeginQuery(gl::TIME_ELAPSED, query) };
+                    renderer.clear(clear_data, gfx::COLOR|gfx::DEPTH, &g_buffer);
+                    renderer.draw(&(&*terrain, &context), &g_buffer).unwrap();
+                    device.submit(renderer.as_buffer());
+                    renderer.reset();
+                    unsafe { benchmark.gl.EndQuery(gl::TIME_ELAPSED) };
+                }
+                benchmark.issued = true;
+            },
+            (terrain, _) => {
+                renderer.clear(clear_data, gfx::COLOR|gfx::DEPTH, &g_buffer);
+                if let Some(terrain) = terrain {
+                    renderer.draw(&(&*terrain, &context), &g_buffer).unwrap();
+                }
+                if let Some(ref displaced) = displaced {
+                    renderer.draw(&(displaced, &context), &g_buffer).unwrap();
+                }
+            },
+        }
 
-            p[0] = terrain_scale.x * x;
-            p[1] = terrain_scale.y * y;
-            p[2] = terrain_scale.z * h + 0.5;
-        };
-        factory.update_buffer(&light_pos_buffer, &light_pos_vec, 0);
+        // The immediate buffers are blitted as they are, the lights are
+        // drawn straight into the window over the depth of the geometry pass
+        if shown == 0 {
+            // Build the depth pyramid, and read its last level back through
+            // the depth of the window, which the copy overwrites right after
+            pyramid.draw(&mut renderer);
+            renderer.draw(&(&hiz_read, &context), &wrap).unwrap();
+            device.submit(renderer.as_buffer());
+            renderer.reset();
+            unsafe {
+                gl.ReadBuffer(gl::BACK);
+                gl.ReadPixels(0, 0, hiz_size.0 as GLsizei, hiz_size.1 as GLsizei,
+                              gl::DEPTH_COMPONENT, gl::FLOAT,
+                              hiz_depths.as_mut_ptr() as *mut GLvoid);
+            }
 
-        // Render the terrain to the geometry buffer
-        renderer.clear(clear_data, gfx::COLOR|gfx::DEPTH, &g_buffer);
-        renderer.draw(&(&terrain, &context), &g_buffer).unwrap();
+            // Only the lights left are written, at the front of the buffer,
+            // and drawn
+            visible.clear();
+            for i in 0 .. NUM_LIGHTS {
+                if !culling || !light_hidden(&view_proj, light_pos(i), light.params.radius,
+                                             &hiz_depths, hiz_size, hiz_scale,
+                                             (w as f32, h as f32)) {
+                    visible.push(i);
+                }
+            }
+            light_pos_writer.write(&mut factory, |i| {
+                if i < visible.len() { light_pos(visible[i]) } else { [0.0; 4] }
+            });
 
-        match debug_buf {
-            Some(ref tex) => {
-                // Show one of the immediate buffers
-                blit.params.tex = (tex.clone(), Some(sampler.clone()));
-                renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
-                renderer.draw(
-                    &(&blit, &context),
-                    &wrap)
-                    .unwrap();
-            },
-            None => {
-                renderer.clear(clear_data, gfx::COLOR, &res_buffer);
+            if !retain {
+                copy_pass.invalidate();
+            }
+            device.submit(copy_pass.record(|renderer| {
+                renderer.draw(&(&depth_copy, &context), &wrap).unwrap();
+            }).as_buffer());
+
+            // Fill the background the terrain left uncovered, using
+            // the depth from the geometry pass
+            if let Some(ref mut sky) = sky {
+                sky.draw(&mut renderer, &wrap, &proj, &view_mat);
+            }
 
-                // Apply light
-                renderer.draw_instanced(
-                    &(&light, &context),
-                    NUM_LIGHTS as u32, 0, &res_buffer)
-                    .unwrap();
-                // Draw light emitters
+            // Apply light
+            renderer.draw_instanced(
+                &(&light, &context),
+                visible.len() as u32, 0, &wrap)
+                .unwrap();
+            device.submit(renderer.as_buffer());
+            renderer.reset();
+
+            // Draw light emitters, those of the hidden lights being hidden
+            // as well
+            if !retain || emitters_drawn != visible.len() {
+                emitter_pass.invalidate();
+                emitters_drawn = visible.len();
+            }
+            device.submit(emitter_pass.record(|renderer| {
                 renderer.draw_instanced(
                     &(&emitter, &context),
-                    NUM_LIGHTS as u32, 0, &res_buffer)
+                    emitters_drawn as u32, 0, &wrap)
                     .unwrap();
+            }).as_buffer());
 
-                // Show the result
+            if let Some(ref mut outline) = outline {
+                outline.draw(&mut renderer, &wrap, &proj);
+            }
+            device.submit(renderer.as_buffer());
+            renderer.reset();
+        } else {
+            device.submit(renderer.as_buffer());
+            renderer.reset();
+
+            // Show the buffer picked
+            let show_pass = &mut show_passes[shown - 1];
+            if !retain {
+                show_pass.invalidate();
+            }
+            device.submit(show_pass.record(|renderer| {
                 renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
-                blit.params.tex = (texture_frame.clone(), Some(sampler.clone()));
-                renderer.draw(&(&blit, &context), &wrap).unwrap();
+                renderer.draw(&(&blits[shown - 1], &context), &wrap).unwrap();
+            }).as_buffer());
+        }
+        let frame_cpu = time::precise_time_s() - frame_start;
+        cpu_time += (frame_cpu - cpu_time) * SMOOTHING;
+        let total = &mut cpu_totals[retain as usize];
+        total.0 += frame_cpu;
+        total.1 += 1;
+
+        if let Some((ref i_wrap, ref mut i_device, ref mut i_renderer)) = inspector {
+            unsafe { i_wrap.window.make_current() };
+            i_renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, i_wrap);
+            for tile in tiles.iter() {
+                i_renderer.draw(&(tile, &context), i_wrap).unwrap();
             }
+            i_device.submit(i_renderer.as_buffer());
+            i_renderer.reset();
+            i_wrap.window.swap_buffers();
+            i_device.after_frame();
+            unsafe { wrap.window.make_current() };
+        }
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
         }
-        device.submit(renderer.as_buffer());
-        renderer.reset();
 
         wrap.window.swap_buffers();
         device.after_frame();
         factory.cleanup();
+
+        // the uniforms of the last frame, over the batches skipping them
+        let (mut uploaded, mut skipped) = (0, 0);
+        for &(u, s) in [light.params.take_counts(), emitter.params.take_counts(),
+                        terrain.as_ref().map_or((0, 0), |t| t.params.take_counts()),
+                        displaced.as_ref().map_or((0, 0), |d| d.params.take_counts())].iter() {
+            uploaded += u;
+            skipped += s;
+        }
+        title_frame += 1;
+        if title_frame == TITLE_FRAMES {
+            title_frame = 0;
+            let recordings = show_passes.iter()
+                .fold(copy_pass.recordings() + emitter_pass.recordings(),
+                      |n, pass| n + pass.recordings());
+            title.clear();
+            write!(title, "Deferred rendering example: {:.2} ms of CPU a frame, \
+                           {} light positions ({} written), \
+                           {} passes ({} recordings), \
+                           {} uniforms uploaded and {} skipped",
+                   cpu_time * 1000.0,
+                   match light_pos_writer.mode {
+                       WriteMode::Mapped => "mapped",
+                       WriteMode::Copy => "copied",
+                       WriteMode::Changed => "changed",
+                   },
+                   light_pos_writer.written(),
+                   if retain { "retained" } else { "recorded every frame" },
+                   recordings, uploaded, skipped).unwrap();
+            if culling && shown == 0 {
+                write!(title, ", {} lights culled by the depth pyramid",
+                       NUM_LIGHTS - visible.len()).unwrap();
+            }
+            if displaced.is_some() {
+                write!(title, ", {0}x{0} grid", grid_size).unwrap();
+            }
+            if let Some(ref b) = benchmark {
+                write!(title, ", geometry pass {:.2} ms as generated, {:.2} ms reordered",
+                       b.timings[0], b.timings[1]).unwrap();
+            }
+            wrap.window.set_title(&title);
+        }
+    }
+
+    for (&(total, frames), passes) in cpu_totals.iter().zip(["recorded every frame",
+                                                             "retained"].iter()) {
+        if frames > 0 {
+            println!("{:.3} ms of CPU a frame on average over {} frames, passes {}",
+                     total * 1000.0 / frames as f64, frames, passes);
+        }
     }
 }
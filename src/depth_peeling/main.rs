@@ -0,0 +1,399 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of order-independent transparency by depth peeling.
+//
+// Blending transparent surfaces over each other only gives the right colors
+// when they are drawn from back to front, which sorting by object can't
+// promise for shapes that overlap or go through each other. Depth peeling
+// finds the layers per pixel instead, from front to back:
+//
+// 1. The glass shapes are drawn with an ordinary depth test, which keeps the
+//    closest surface of every pixel: the first layer.
+// 2. They are drawn again, discarding every fragment that isn't behind the
+//    depth of the layer before, read from the last pass's depth texture. The
+//    depth test now keeps the closest of the rest, the next layer.
+// 3. After each pass, the layer is blended under what was found so far, in
+//    a shader reading one accumulation texture and writing the other.
+//
+// After as many passes as there are peels, the accumulated color is put over
+// the background. Surfaces past the last peel are left out, so a few peels
+// are usually enough for what they add to become hard to see.
+//
+// Press Up and Down to change the number of peels, L to show a single layer,
+// cycling through them, and Space to stop the shapes.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const MAX_PEELS: usize = 8;
+
+#[shader_param]
+struct PeelParams<R: gfx::Resources> {
+    #[name = "u_View"]
+    view: [[f32; 4]; 4],
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 4],
+    #[name = "u_Resolution"]
+    resolution: [f32; 2],
+    // 1 for the first layer, which has nothing to peel away
+    #[name = "u_First"]
+    first: f32,
+    #[name = "t_PrevDepth"]
+    prev_depth: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct CompositeParams<R: gfx::Resources> {
+    #[name = "t_Accum"]
+    accum: gfx::shade::TextureParam<R>,
+    #[name = "t_Layer"]
+    layer: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct ShowParams<R: gfx::Resources> {
+    #[name = "u_Background"]
+    background: [f32; 3],
+    #[name = "t_Source"]
+    source: gfx::shade::TextureParam<R>,
+}
+
+static PEEL_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_View;
+    uniform mat4 u_Proj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_View) * mat3(u_Model) * a_Normal;
+        gl_Position = u_Proj * u_View * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static PEEL_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Color;
+    uniform vec2 u_Resolution;
+    uniform float u_First;
+    uniform sampler2D t_PrevDepth;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        if (u_First < 0.5) {
+            // keep only what is behind the last layer, with some slack for
+            // the rounding of the stored depth
+            float prev = texture(t_PrevDepth, gl_FragCoord.xy / u_Resolution).r;
+            if (gl_FragCoord.z <= prev + 1e-6) {
+                discard;
+            }
+        }
+        vec3 n = normalize(v_Normal);
+        float facing = abs(n.z);
+        float light = 0.4 + 0.6 * abs(dot(n, normalize(vec3(0.3, 0.5, 0.8))));
+        // glass lets less through when seen at a grazing angle
+        float alpha = mix(1.0, u_Color.a, facing);
+        o_Color = vec4(u_Color.rgb * light, alpha);
+    }
+";
+
+static QUAD_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+// Front to back: the new layer only shows through what is already in front
+static COMPOSITE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Accum;
+    uniform sampler2D t_Layer;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 accum = texture(t_Accum, v_TexCoord);
+        vec4 layer = texture(t_Layer, v_TexCoord);
+        o_Color = accum + (1.0 - accum.a) * vec4(layer.rgb * layer.a, layer.a);
+    }
+";
+
+static SHOW_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Background;
+    uniform sampler2D t_Source;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 color = texture(t_Source, v_TexCoord);
+        o_Color = vec4(color.rgb + (1.0 - color.a) * u_Background, 1.0);
+    }
+";
+
+fn create_color<R: gfx::Resources, F: Factory<R>>(
+                width: gfx::tex::Size, height: gfx::tex::Size, factory: &mut F)
+                -> (gfx::Frame<R>, gfx::TextureHandle<R>) {
+    let texture = factory.create_texture(gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F16),
+    }).unwrap();
+    let frame = gfx::Frame {
+        colors: vec![Plane::Texture(texture.clone(), 0, None)],
+        .. gfx::Frame::empty(width, height)
+    };
+    (frame, texture)
+}
+
+fn create_depth<R: gfx::Resources, F: Factory<R>>(
+                width: gfx::tex::Size, height: gfx::tex::Size, factory: &mut F)
+                -> gfx::TextureHandle<R> {
+    factory.create_texture(gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap()
+}
+
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Depth peeling example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    // A layer is drawn into `layer`, with the depth going into one of the
+    // two depth textures while the other holds the depth of the layer before
+    let depths = [create_depth(w, h, &mut factory), create_depth(w, h, &mut factory)];
+    let (_, layer) = create_color(w, h, &mut factory);
+    let peel_frames: Vec<_> = depths.iter().map(|depth| gfx::Frame {
+        colors: vec![Plane::Texture(layer.clone(), 0, None)],
+        depth: Some(Plane::Texture(depth.clone(), 0, None)),
+        .. gfx::Frame::empty(w, h)
+    }).collect();
+    let accums = [create_color(w, h, &mut factory), create_color(w, h, &mut factory)];
+
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    // One batch per shape
+    let mut meshes = MeshCache::new();
+    let mut shapes: Vec<_> = {
+        let program = factory.link_program(PEEL_VERTEX_SRC, PEEL_FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        vec![meshes.cube(&mut factory), meshes.sphere(&mut factory, 32, 64),
+             meshes.torus(&mut factory, 64, 32, 0.8, 0.3)]
+            .into_iter().map(|(mesh, slice)| {
+                let data = PeelParams {
+                    view: Matrix4::identity().into_fixed(),
+                    proj: Matrix4::identity().into_fixed(),
+                    model: Matrix4::identity().into_fixed(),
+                    color: [0.0; 4],
+                    resolution: [w as f32, h as f32],
+                    first: 1.0,
+                    prev_depth: (depths[0].clone(), Some(sampler.clone())),
+                };
+                context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+            }).collect()
+    };
+
+    let (quad, quad_slice) = meshes.quad(&mut factory);
+    let mut composite = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, COMPOSITE_FRAGMENT_SRC).unwrap();
+        let data = CompositeParams {
+            accum: (accums[0].1.clone(), Some(sampler.clone())),
+            layer: (layer.clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &quad, quad_slice.clone(), &gfx::DrawState::new())
+               .unwrap()
+    };
+    let background = [0.15, 0.15, 0.2];
+    let mut show = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, SHOW_FRAGMENT_SRC).unwrap();
+        let data = ShowParams {
+            background: background,
+            source: (accums[0].1.clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &quad, quad_slice, &gfx::DrawState::new())
+               .unwrap()
+    };
+
+    // (shape, position, color) of the glass objects, crossing each other
+    let objects = [
+        (0, [-0.8, 0.0, 0.0], [0.9, 0.2, 0.2, 0.4]),
+        (1, [0.8, 0.3, 0.0], [0.2, 0.8, 0.3, 0.35]),
+        (2, [0.0, -0.2, 0.3], [0.2, 0.4, 0.9, 0.45]),
+        (2, [0.2, 0.6, -0.4], [0.9, 0.8, 0.2, 0.4]),
+        (1, [-0.3, -0.8, 0.6], [0.8, 0.3, 0.9, 0.3]),
+        (0, [0.5, -0.6, -0.6], [0.2, 0.8, 0.9, 0.35]),
+    ];
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.5, 30.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 7.0, -0.6, 0.4);
+
+    let clear_peel = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 0.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut peels = 4;
+    // 0 for the whole stack, otherwise the single layer shown
+    let mut shown_layer = 0;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => peels = (peels + 1).min(MAX_PEELS),
+                    VirtualKeyCode::Down => {
+                        peels = (peels - 1).max(1);
+                        shown_layer = shown_layer.min(peels);
+                    },
+                    VirtualKeyCode::L => shown_layer = (shown_layer + 1) % (peels + 1),
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.3 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+        let view = camera.view().mat.into_fixed();
+
+        // Peel the layers, blending each under the ones before
+        renderer.clear(clear_peel, gfx::COLOR, &accums[0].0);
+        let mut current = 0;
+        let passes = if shown_layer > 0 { shown_layer } else { peels };
+        for i in 0 .. passes {
+            let frame = &peel_frames[i % 2];
+            renderer.clear(clear_peel, gfx::COLOR | gfx::DEPTH, frame);
+            for (j, &(kind, pos, color)) in objects.iter().enumerate() {
+                let (z, x) = (a + j as f32, 0.5 * a + j as f32);
+                let spin = Matrix3::from_angle_z(cgmath::rad(z))
+                                   .mul_m(&Matrix3::from_angle_x(cgmath::rad(x)));
+                let batch = &mut shapes[kind];
+                batch.params.view = view;
+                batch.params.proj = proj.into_fixed();
+                batch.params.model = place(spin.mul_s(0.8), pos[0], pos[1], pos[2]).into_fixed();
+                batch.params.color = color;
+                batch.params.first = if i == 0 { 1.0 } else { 0.0 };
+                batch.params.prev_depth = (depths[(i + 1) % 2].clone(), Some(sampler.clone()));
+                renderer.draw(&(&*batch, &context), frame).unwrap();
+            }
+
+            // a single layer is shown by blending it alone over nothing
+            if shown_layer == 0 || i + 1 == passes {
+                composite.params.accum = (accums[current].1.clone(), Some(sampler.clone()));
+                renderer.draw(&(&composite, &context), &accums[1 - current].0).unwrap();
+                current = 1 - current;
+            }
+        }
+
+        // The stack or the single layer, over the background
+        show.params.source = (accums[current].1.clone(), Some(sampler.clone()));
+        renderer.draw(&(&show, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&match shown_layer {
+            0 => format!("Depth peeling example: {} peels", peels),
+            n => format!("Depth peeling example: layer {} of {}", n, peels),
+        });
+    }
+}
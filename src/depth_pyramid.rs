@@ -0,0 +1,145 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pyramids of the farthest depths of a depth texture, known as Hi-Z.
+//!
+//! A `DepthPyramid` has levels of single float textures, the first half the
+//! size of the source and each next one half the size of the one before,
+//! rounded up. `draw` fills each level with a full-screen pass over the one
+//! before it, every texel keeping the farthest of the four texels under it,
+//! so a texel of any level is at least as far as everything drawn in the
+//! pixels it covers. Whatever is nearer than a texel can't be hidden there,
+//! and whatever is farther than all the texels of a region is hidden behind
+//! what was drawn, which occlusion culling and the ray marches of screen space
+//! effects test again and again.
+//!
+//! The source can be any depth texture, or a float texture of depths, that is
+//! read with `texelFetch` only, so it needs no mipmaps or filtering.
+//!
+//! ```ignore
+//! let pyramid = DepthPyramid::new(&mut factory, &mut meshes, &depth_tex, 4);
+//! // every frame, after the depth has been drawn
+//! pyramid.draw(&mut renderer);
+//! let (ref coarsest, _) = pyramid.levels[3];
+//! ```
+
+use gfx;
+use gfx::traits::*;
+use gfx::Plane;
+use mesh_cache::MeshCache;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "t_Source"]
+    source: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = vec4(a_Pos, 1.0);
+    }
+";
+
+// The last row and column of an odd sized source are clamped to, the level
+// being rounded up
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Source;
+    out vec4 o_Depth;
+
+    float fetch(ivec2 p) {
+        return texelFetch(t_Source, min(p, textureSize(t_Source, 0) - 1), 0).r;
+    }
+
+    void main() {
+        ivec2 p = 2 * ivec2(gl_FragCoord.xy);
+        float d = max(max(fetch(p), fetch(p + ivec2(1, 0))),
+                      max(fetch(p + ivec2(0, 1)), fetch(p + ivec2(1, 1))));
+        o_Depth = vec4(d, 0.0, 0.0, 1.0);
+    }
+";
+
+pub struct DepthPyramid<R: gfx::Resources> {
+    /// The texture of each level and the frame to draw into it, from the
+    /// largest.
+    pub levels: Vec<(gfx::TextureHandle<R>, gfx::Frame<R>)>,
+    // a pass per level, each reading the level before or the source
+    batches: Vec<gfx::batch::OwnedBatch<Params<R>>>,
+}
+
+impl<R: gfx::Resources> DepthPyramid<R> {
+    /// Creates `levels` levels for `source`, which keeps its size, drawn
+    /// with the quad of `meshes`.
+    pub fn new<F: gfx::Factory<R>>(factory: &mut F, meshes: &mut MeshCache<R>,
+                                   source: &gfx::TextureHandle<R>,
+                                   levels: usize) -> DepthPyramid<R> {
+        let (mesh, _) = meshes.quad(factory);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+
+        let (mut width, mut height) = (source.get_info().width, source.get_info().height);
+        let mut previous = source.clone();
+        let mut pyramid = DepthPyramid {
+            levels: Vec::with_capacity(levels),
+            batches: Vec::with_capacity(levels),
+        };
+        for _ in 0 .. levels {
+            width = (width + 1) / 2;
+            height = (height + 1) / 2;
+            let texture = factory.create_texture(gfx::tex::TextureInfo {
+                width: width,
+                height: height,
+                depth: 1,
+                levels: 1,
+                kind: gfx::tex::TextureKind::Texture2D,
+                format: gfx::tex::Format::Float(gfx::tex::Components::R,
+                                                gfx::attrib::FloatSize::F32),
+            }).unwrap();
+            let frame = gfx::Frame {
+                colors: vec![Plane::Texture(texture.clone(), 0, None)],
+                .. gfx::Frame::empty(width, height)
+            };
+            let data = Params {
+                source: (previous, Some(sampler.clone())),
+            };
+            let batch = gfx::batch::OwnedBatch::new(mesh.clone(), program.clone(), data)
+                                               .unwrap();
+            previous = texture.clone();
+            pyramid.levels.push((texture, frame));
+            pyramid.batches.push(batch);
+        }
+        pyramid
+    }
+
+    /// Width and height of a level.
+    pub fn size(&self, level: usize) -> (gfx::tex::Size, gfx::tex::Size) {
+        let frame = &self.levels[level].1;
+        (frame.width, frame.height)
+    }
+
+    /// Fills every level from what the source holds now.
+    pub fn draw<C: gfx::CommandBuffer<R>>(&self, renderer: &mut gfx::Renderer<R, C>) {
+        for (batch, &(_, ref frame)) in self.batches.iter().zip(self.levels.iter()) {
+            renderer.draw(batch, frame).unwrap();
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Skipping the uniforms that haven't changed since the last draw.
+//!
+//! Every draw of a batch fills in all the values of its parameters, and the
+//! renderer uploads every one of them, though most are the same as the last
+//! time, like a projection matrix. GL keeps the values of the uniforms in
+//! the program, so a `Dirty` wrapped around the parameters leaves out the
+//! uniforms equal to what it sent the last time, and the program still has
+//! them. Uniform blocks and textures are always bound, since those bindings
+//! aren't kept in the program.
+//!
+//! This is only right for a batch that has its program to itself, since
+//! another batch drawn with the same program in between sets the uniforms to
+//! its own values. A batch sharing its program should call `invalidate`
+//! before each draw, or not be wrapped at all.
+//!
+//! `Dirty` dereferences to the parameters it wraps, so their fields are set
+//! the same way as without it.
+//!
+//! ```ignore
+//! let mut batch = context.make_batch(&program, Dirty::new(params), &mesh, slice, &state).unwrap();
+//! batch.params.view = view.into_fixed();
+//! renderer.draw(&(&batch, &context), &wrap).unwrap();
+//! let (uploaded, skipped) = batch.params.take_counts();
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::iter::repeat;
+use std::ops::{Deref, DerefMut};
+use gfx;
+use gfx::shade::{ParamStorage, ParameterError, ShaderParam, UniformValue};
+
+pub struct Dirty<P> {
+    params: P,
+    // what was sent at the last draw, for each uniform of the program
+    sent: RefCell<Vec<Option<UniformValue>>>,
+    uploaded: Cell<usize>,
+    skipped: Cell<usize>,
+}
+
+impl<P: ShaderParam> Dirty<P> {
+    pub fn new(params: P) -> Dirty<P> {
+        Dirty {
+            params: params,
+            sent: RefCell::new(Vec::new()),
+            uploaded: Cell::new(0),
+            skipped: Cell::new(0),
+        }
+    }
+
+    /// Makes the next draw upload every uniform.
+    pub fn invalidate(&self) {
+        self.sent.borrow_mut().clear();
+    }
+
+    /// The uniforms uploaded and skipped since the last call.
+    pub fn take_counts(&self) -> (usize, usize) {
+        let counts = (self.uploaded.get(), self.skipped.get());
+        self.uploaded.set(0);
+        self.skipped.set(0);
+        counts
+    }
+}
+
+impl<P> Deref for Dirty<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.params
+    }
+}
+
+impl<P> DerefMut for Dirty<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.params
+    }
+}
+
+impl<P: ShaderParam> ShaderParam for Dirty<P> {
+    type Resources = P::Resources;
+    type Link = P::Link;
+
+    fn create_link(params: Option<&Dirty<P>>, info: &gfx::ProgramInfo)
+                   -> Result<P::Link, ParameterError> {
+        P::create_link(params.map(|d| &d.params), info)
+    }
+
+    fn fill_params(&self, link: &P::Link, storage: &mut ParamStorage<P::Resources>) {
+        self.params.fill_params(link, storage);
+        let mut sent = self.sent.borrow_mut();
+        let missing = storage.uniforms.len().saturating_sub(sent.len());
+        sent.extend(repeat(None).take(missing));
+        for (value, last) in storage.uniforms.iter_mut().zip(sent.iter_mut()) {
+            let unchanged = match (&*value, &*last) {
+                (&Some(ref v), &Some(ref l)) => v == l,
+                _ => false,
+            };
+            if unchanged {
+                *value = None;
+                self.skipped.set(self.skipped.get() + 1);
+            } else if value.is_some() {
+                *last = value.clone();
+                self.uploaded.set(self.uploaded.get() + 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of displacement mapping in the vertex shader.
+//
+// A flat grid of 256 by 256 vertices is pushed up by a tileable height
+// texture, read in the vertex shader at coordinates that scroll over time.
+// As the surface moves under the vertices, the normals can't be stored with
+// the mesh: they come from finite differences of the height texture, sampled
+// around each vertex.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press Up and
+// Down to change the height of the displacement, N to switch between the
+// computed normals and flat ones, and Space to stop the scrolling.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// the grid has one more vertex than cells along each side, which just fits
+// in 16 bit indices
+const GRID_CELLS: usize = 255;
+const HEIGHT_SIZE: usize = 256;
+const PLANE_SIZE: f32 = 20.0;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    // position on the grid, from 0 to 1
+    #[name = "a_Coord"]
+    coord: [f32; 2],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Size"]
+    size: f32,
+    #[name = "u_Height"]
+    height: f32,
+    #[name = "u_Scroll"]
+    scroll: [f32; 2],
+    // 0 for flat normals, 1 for the computed ones
+    #[name = "u_Normals"]
+    normals: f32,
+    #[name = "t_Height"]
+    height_map: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform float u_Size;
+    uniform float u_Height;
+    uniform vec2 u_Scroll;
+    uniform float u_Normals;
+    uniform sampler2D t_Height;
+    in vec2 a_Coord;
+    out vec3 v_Normal;
+    out float v_Height;
+
+    float height(vec2 uv) {
+        return textureLod(t_Height, uv, 0.0).r;
+    }
+
+    void main() {
+        // the texture covers the plane twice
+        vec2 uv = 2.0 * a_Coord + u_Scroll;
+        v_Height = height(uv);
+
+        // central differences over one texel on each side
+        vec2 texel = 1.0 / vec2(textureSize(t_Height, 0));
+        float dx = height(uv + vec2(texel.x, 0.0)) - height(uv - vec2(texel.x, 0.0));
+        float dy = height(uv + vec2(0.0, texel.y)) - height(uv - vec2(0.0, texel.y));
+        // the two texels between the samples cover this much of the plane
+        vec2 span = 2.0 * texel * 0.5 * u_Size;
+        vec3 normal = normalize(vec3(-dx * u_Height / span.x, -dy * u_Height / span.y, 1.0));
+        v_Normal = mix(vec3(0.0, 0.0, 1.0), normal, u_Normals);
+
+        vec3 pos = vec3((a_Coord - 0.5) * u_Size, v_Height * u_Height);
+        gl_Position = u_ViewProj * vec4(pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    in float v_Height;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 light = normalize(vec3(0.4, 0.3, 0.8));
+        vec3 low = vec3(0.1, 0.25, 0.45);
+        vec3 high = vec3(0.9, 0.85, 0.75);
+        vec3 albedo = mix(low, high, smoothstep(0.2, 0.8, v_Height));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        o_Color = vec4(albedo * (0.15 + 0.85 * diffuse), 1.0);
+    }
+";
+
+fn hash(x: i32, y: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(73856093) ^ (y as u32).wrapping_mul(19349663);
+    h = (h ^ (h >> 13)).wrapping_mul(0x5bd1e995);
+    h ^ (h >> 15)
+}
+
+fn repeat(i: i32, period: i32) -> i32 {
+    ((i % period) + period) % period
+}
+
+// Value noise from 0 to 1, repeating every `period` units
+fn noise(x: f32, y: f32, period: i32) -> f32 {
+    let value = |i: i32, j: i32| {
+        (hash(repeat(i, period), repeat(j, period)) & 0xFFFF) as f32 / 65535.0
+    };
+    let fade = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (i, j) = (x.floor() as i32, y.floor() as i32);
+    let (u, v) = (fade(x - i as f32), fade(y - j as f32));
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    lerp(lerp(value(i, j), value(i + 1, j), u),
+         lerp(value(i, j + 1), value(i + 1, j + 1), u), v)
+}
+
+// Ridges of a few octaves of tileable noise, from 0 to 1
+fn create_heights() -> Vec<f32> {
+    let mut data = Vec::with_capacity(HEIGHT_SIZE * HEIGHT_SIZE);
+    for y in 0 .. HEIGHT_SIZE {
+        for x in 0 .. HEIGHT_SIZE {
+            let (mut sum, mut amplitude, mut total) = (0.0, 0.5, 0.0);
+            for octave in 0 .. 5 {
+                let period = 4 << octave;
+                let scale = period as f32 / HEIGHT_SIZE as f32;
+                let n = noise(x as f32 * scale, y as f32 * scale, period);
+                sum += amplitude * (1.0 - (2.0 * n - 1.0).abs());
+                total += amplitude;
+                amplitude *= 0.5;
+            }
+            data.push(sum / total);
+        }
+    }
+    data
+}
+
+fn create_grid() -> (Vec<Vertex>, Vec<u16>) {
+    let side = GRID_CELLS + 1;
+    let mut vertices = Vec::with_capacity(side * side);
+    for y in 0 .. side {
+        for x in 0 .. side {
+            vertices.push(Vertex {
+                coord: [x as f32 / GRID_CELLS as f32, y as f32 / GRID_CELLS as f32],
+            });
+        }
+    }
+    let mut indices = Vec::with_capacity(GRID_CELLS * GRID_CELLS * 6);
+    for y in 0 .. GRID_CELLS {
+        for x in 0 .. GRID_CELLS {
+            let i = (y * side + x) as u16;
+            let up = i + side as u16;
+            indices.extend([i, i + 1, up + 1, up + 1, up, i].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Displacement mapping example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut batch = {
+        let info = gfx::tex::TextureInfo {
+            width: HEIGHT_SIZE as u16,
+            height: HEIGHT_SIZE as u16,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::Format::Float(gfx::tex::Components::R,
+                                            gfx::attrib::FloatSize::F32),
+        };
+        let texture = factory.create_texture(info).unwrap();
+        factory.update_texture(&texture, &info.to_image_info(), &create_heights(), None)
+               .unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Tile)
+        );
+
+        let (vertex_data, index_data) = create_grid();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            size: PLANE_SIZE,
+            height: 2.0,
+            scroll: [0.0; 2],
+            normals: 1.0,
+            height_map: (texture, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 16.0, -1.2, 0.6);
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.7, 0.8, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut scrolling = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_scroll, mut scroll) = ([0.0f32; 2], [0.0f32; 2]);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let params = &mut batch.params;
+                    match key {
+                        VirtualKeyCode::Up => params.height = (params.height + 0.25).min(5.0),
+                        VirtualKeyCode::Down => params.height = (params.height - 0.25).max(0.0),
+                        VirtualKeyCode::N => params.normals = 1.0 - params.normals,
+                        VirtualKeyCode::Space => scrolling = !scrolling,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_scroll = scroll;
+            if scrolling {
+                // the texture repeats, so the offset can wrap around
+                scroll[0] = (scroll[0] + 0.02 * clock.step()) % 1.0;
+                scroll[1] = (scroll[1] + 0.01 * clock.step()) % 1.0;
+            }
+        }
+        let alpha = clock.alpha();
+        let mut offset = [0.0f32; 2];
+        for i in 0 .. 2 {
+            // interpolating across the wrap would jump back over the texture
+            let delta = scroll[i] - prev_scroll[i];
+            let delta = if delta < 0.0 { delta + 1.0 } else { delta };
+            offset[i] = prev_scroll[i] + delta * alpha;
+        }
+
+        batch.params.view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        batch.params.scroll = offset;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
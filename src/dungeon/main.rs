@@ -0,0 +1,476 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of walking through a procedural dungeon.
+//
+// The dungeon is a grid of cells. Rooms of random sizes are dug out of it
+// where they do not touch the ones before, and each of them is joined to
+// the previous one by a corridor bending once. All the floors, ceilings and
+// walls facing an open cell are then merged into a single mesh, drawn in one
+// call. Torches on the walls of the rooms are point lights sent in a uniform
+// block, with their flicker updated every frame.
+//
+// The free-fly camera is held at eye height, and every move it makes is
+// checked against the walls one axis at a time, so it slides along them
+// instead of stopping.
+//
+// Drag with the left mouse button to look around, press W, A, S and D to
+// walk, and hold Left Shift to run.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use rand::{Rng, SeedableRng};
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{capture, window, Clock, Thumbnail};
+
+// cells a side, and their size
+const SIZE: usize = 48;
+const CELL: f32 = 2.0;
+const WALL_HEIGHT: f32 = 3.0;
+const MAX_ROOMS: usize = 12;
+// Remember to also change the constant in the fragment shader
+const MAX_LIGHTS: usize = 32;
+const EYE_HEIGHT: f32 = 1.6;
+// how close the eye can get to a wall
+const RADIUS: f32 = 0.3;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_NumLights"]
+    num_lights: f32,
+    #[name = "u_LightBlock"]
+    lights: gfx::RawBufferHandle<R>,
+}
+
+#[shader_param]
+struct TorchParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        v_World = a_Pos;
+        v_Normal = a_Normal;
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const int MAX_LIGHTS = 32;
+
+    uniform float u_NumLights;
+    uniform u_LightBlock {
+        // the position and range of each light, then its color
+        vec4 u_Lights[2 * MAX_LIGHTS];
+    };
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    float hash(vec2 p) {
+        return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+    }
+
+    // 1 inside a block of a pattern of blocks, 0 in the mortar between them
+    float blocks(vec2 p, vec2 size, vec2 mortar) {
+        vec2 f = fract(p / size) * size;
+        vec2 inside = step(mortar, f) * step(f, size - mortar);
+        return inside.x * inside.y;
+    }
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 albedo;
+        if (n.z > 0.5) {
+            // flagstones
+            vec2 cell = floor(v_World.xy);
+            float stone = blocks(v_World.xy, vec2(1.0), vec2(0.03));
+            albedo = mix(vec3(0.12), vec3(0.35, 0.33, 0.3) * (0.7 + 0.3 * hash(cell)), stone);
+        } else if (n.z < -0.5) {
+            albedo = vec3(0.15, 0.14, 0.13);
+        } else {
+            // bricks, every other row moved by half a brick
+            vec2 p = vec2(dot(v_World.xy, vec2(-n.y, n.x)), v_World.z);
+            p.x += 0.25 * mod(floor(p.y / 0.25), 2.0);
+            vec2 brick = floor(p / vec2(0.5, 0.25));
+            float inside = blocks(p, vec2(0.5, 0.25), vec2(0.02));
+            albedo = mix(vec3(0.1), vec3(0.45, 0.28, 0.2) * (0.7 + 0.3 * hash(brick)), inside);
+        }
+
+        vec3 light = vec3(0.015);
+        for (int i = 0; i < int(u_NumLights); i++) {
+            vec3 to_light = u_Lights[2 * i].xyz - v_World;
+            float d = length(to_light);
+            // falls off with the square of the distance, and smoothly to
+            // nothing at the range of the light
+            float window = clamp(1.0 - pow(d / u_Lights[2 * i].w, 4.0), 0.0, 1.0);
+            float attenuation = window * window / (1.0 + d * d);
+            light += u_Lights[2 * i + 1].rgb * attenuation * max(dot(n, to_light / d), 0.0);
+        }
+        vec3 color = 1.0 - exp(-albedo * light);
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+static TORCH_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = u_ViewProj * vec4(0.08 * a_Pos + u_Offset, 1.0);
+    }
+";
+
+static TORCH_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(u_Color, 1.0);
+    }
+";
+
+// Adds a rectangle with the sides `u` and `v` from its middle, facing the
+// side `u` x `v` points to
+fn push_quad(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>, center: [f32; 3],
+             u: [f32; 3], v: [f32; 3], normal: [f32; 3]) {
+    let base = vertices.len() as u32;
+    for &(su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter() {
+        vertices.push(Vertex {
+            pos: [center[0] + su * u[0] + sv * v[0],
+                  center[1] + su * u[1] + sv * v[1],
+                  center[2] + su * u[2] + sv * v[2]],
+            normal: normal,
+        });
+    }
+    indices.extend([base, base + 1, base + 2, base, base + 2, base + 3].iter().cloned());
+}
+
+#[derive(Clone, Copy)]
+struct Room {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Room {
+    // whether the rooms overlap, or touch along a side
+    fn touches(&self, other: &Room) -> bool {
+        self.x <= other.x + other.w && other.x <= self.x + self.w &&
+        self.y <= other.y + other.h && other.y <= self.y + self.h
+    }
+
+    fn center(&self) -> (usize, usize) {
+        (self.x + self.w / 2, self.y + self.h / 2)
+    }
+}
+
+struct Dungeon {
+    // whether each cell is open, row by row
+    open: Vec<bool>,
+    rooms: Vec<Room>,
+}
+
+impl Dungeon {
+    fn generate<R: Rng>(rng: &mut R) -> Dungeon {
+        let mut dungeon = Dungeon {
+            open: vec![false; SIZE * SIZE],
+            rooms: Vec::new(),
+        };
+        for _ in 0 .. 200 {
+            if dungeon.rooms.len() == MAX_ROOMS {
+                break;
+            }
+            let (w, h) = (rng.gen_range(3, 8), rng.gen_range(3, 8));
+            let room = Room {
+                x: rng.gen_range(1, SIZE - w - 1),
+                y: rng.gen_range(1, SIZE - h - 1),
+                w: w,
+                h: h,
+            };
+            if dungeon.rooms.iter().any(|r| r.touches(&room)) {
+                continue;
+            }
+            for y in room.y .. room.y + room.h {
+                for x in room.x .. room.x + room.w {
+                    dungeon.open[y * SIZE + x] = true;
+                }
+            }
+            if let Some(&last) = dungeon.rooms.last() {
+                let horizontal_first = rng.gen();
+                dungeon.dig_corridor(last.center(), room.center(), horizontal_first);
+            }
+            dungeon.rooms.push(room);
+        }
+        dungeon
+    }
+
+    fn dig_corridor(&mut self, from: (usize, usize), to: (usize, usize),
+                    horizontal_first: bool) {
+        let corner = if horizontal_first { (to.0, from.1) } else { (from.0, to.1) };
+        for &(a, b) in [(from, corner), (corner, to)].iter() {
+            for y in a.1.min(b.1) .. a.1.max(b.1) + 1 {
+                for x in a.0.min(b.0) .. a.0.max(b.0) + 1 {
+                    self.open[y * SIZE + x] = true;
+                }
+            }
+        }
+    }
+
+    fn is_open(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && x < SIZE as isize && y < SIZE as isize &&
+        self.open[y as usize * SIZE + x as usize]
+    }
+
+    // whether a circle of `RADIUS` around the point overlaps a solid cell
+    fn blocked(&self, x: f32, y: f32) -> bool {
+        let cell = |v: f32| (v / CELL).floor() as isize;
+        for cy in cell(y - RADIUS) .. cell(y + RADIUS) + 1 {
+            for cx in cell(x - RADIUS) .. cell(x + RADIUS) + 1 {
+                if !self.is_open(cx, cy) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // The floor and ceiling of every open cell, and a wall on each side of
+    // it facing a solid cell, in one mesh
+    fn build_mesh(&self) -> (Vec<Vertex>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let (half, half_height) = (0.5 * CELL, 0.5 * WALL_HEIGHT);
+        for y in 0 .. SIZE as isize {
+            for x in 0 .. SIZE as isize {
+                if !self.is_open(x, y) {
+                    continue;
+                }
+                let (cx, cy) = ((x as f32 + 0.5) * CELL, (y as f32 + 0.5) * CELL);
+                push_quad(&mut vertices, &mut indices, [cx, cy, 0.0], [half, 0.0, 0.0],
+                          [0.0, half, 0.0], [0.0, 0.0, 1.0]);
+                push_quad(&mut vertices, &mut indices, [cx, cy, WALL_HEIGHT], [0.0, half, 0.0],
+                          [half, 0.0, 0.0], [0.0, 0.0, -1.0]);
+                for &(dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)].iter() {
+                    if self.is_open(x + dx, y + dy) {
+                        continue;
+                    }
+                    // facing back into the cell
+                    let center = [cx + dx as f32 * half, cy + dy as f32 * half, half_height];
+                    push_quad(&mut vertices, &mut indices, center,
+                              [dy as f32 * half, -dx as f32 * half, 0.0],
+                              [0.0, 0.0, half_height], [-dx as f32, -dy as f32, 0.0]);
+                }
+            }
+        }
+        (vertices, indices)
+    }
+
+    // A torch on the middle of the far and the near walls of every room,
+    // where no corridor comes in
+    fn torches(&self) -> Vec<[f32; 3]> {
+        let mut torches = Vec::new();
+        for room in self.rooms.iter() {
+            let x = (room.x + room.w / 2) as isize;
+            for &(y, dy) in [(room.y + room.h - 1, 1), (room.y, -1)].iter() {
+                let y = y as isize;
+                if torches.len() < MAX_LIGHTS && !self.is_open(x, y + dy) {
+                    torches.push([(x as f32 + 0.5) * CELL,
+                                  (y as f32 + 0.5 + 0.45 * dy as f32) * CELL, 2.2]);
+                }
+            }
+        }
+        torches
+    }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Dungeon example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let seed = match thumbnail {
+        Some(_) => capture::SEED,
+        None => rand::thread_rng().gen(),
+    };
+    let mut rng = rand::XorShiftRng::from_seed([seed, 0x1234, 0x5678, 0x9abc]);
+    let dungeon = Dungeon::generate(&mut rng);
+    let torches = dungeon.torches();
+    // a phase for the flicker of every torch
+    let phases: Vec<f32> = torches.iter().map(|_| rng.gen_range(0.0, 100.0)).collect();
+
+    let light_buffer = factory.create_buffer::<[f32; 4]>(2 * MAX_LIGHTS,
+                                                         gfx::BufferUsage::Stream);
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut batch = {
+        let (vertex_data, index_data) = dungeon.build_mesh();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            num_lights: torches.len() as f32,
+            lights: light_buffer.raw().clone(),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+    let mut meshes = MeshCache::new();
+    let mut torch = {
+        let (mesh, slice) = meshes.cube(&mut factory);
+        let program = factory.link_program(TORCH_VERTEX_SRC, TORCH_FRAGMENT_SRC).unwrap();
+        let data = TorchParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [0.0; 3],
+            color: [1.0; 3],
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(70.0f32), aspect, 0.05, 100.0);
+    let (start_x, start_y) = dungeon.rooms[0].center();
+    let mut camera = FlyCamera::new(Point3::new((start_x as f32 + 0.5) * CELL,
+                                                (start_y as f32 + 0.5) * CELL, EYE_HEIGHT),
+                                    0.0, 0.0, 3.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut time = 0.0f32;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            time = clock.time() as f32;
+            // walk, keeping each axis of the move the walls allow
+            let old = camera.position;
+            camera.update(clock.step());
+            let new = camera.position;
+            camera.position = old;
+            if !dungeon.blocked(new.x, old.y) {
+                camera.position.x = new.x;
+            }
+            if !dungeon.blocked(camera.position.x, new.y) {
+                camera.position.y = new.y;
+            }
+        }
+
+        let mut lights = [[0.0f32; 4]; 2 * MAX_LIGHTS];
+        for (i, (pos, &phase)) in torches.iter().zip(phases.iter()).enumerate() {
+            let t = time + phase;
+            let flicker = 0.8 + 0.12 * (7.0 * t).sin() + 0.08 * (17.0 * t).sin();
+            lights[2 * i] = [pos[0], pos[1], pos[2], 10.0];
+            lights[2 * i + 1] = [6.0 * flicker, 3.2 * flicker, 1.2 * flicker, 0.0];
+        }
+        factory.update_buffer(&light_buffer, &lights[..], 0);
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        batch.params.view_proj = view_proj;
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+        for pos in torches.iter() {
+            torch.params.view_proj = view_proj;
+            torch.params.offset = *pos;
+            torch.params.color = [1.0, 0.7, 0.3];
+            renderer.draw(&(&torch, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of environment mapping.
+//
+// An object reflects or refracts a cubemap, looked up with the direction
+// the view ray takes after bouncing off or bending through the surface, in
+// world space. The same cubemap is drawn behind it as a skybox. The cubemap
+// is loaded from the six images in the directory given as an argument, named
+// as in `gfx_examples::cubemap::FACE_NAMES`, or generated without one.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press M to
+// switch between chrome and glass, Tab to change the object, Plus and Minus
+// to change the exposure, and Space to stop the object from turning.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::env;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::cubemap::{self, Skybox};
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    // 0 for chrome, 1 for glass
+    #[name = "u_Glass"]
+    glass: f32,
+    #[name = "u_Exposure"]
+    exposure: f32,
+    #[name = "t_Env"]
+    env: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        vec4 pos = u_Model * vec4(a_Pos, 1.0);
+        v_Pos = pos.xyz;
+        // the model only rotates, so its matrix works for the normals too
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * pos;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_CamPos;
+    uniform float u_Glass;
+    uniform float u_Exposure;
+    uniform samplerCube t_Env;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 i = normalize(v_Pos - u_CamPos);
+        vec3 reflected = texture(t_Env, reflect(i, n)).rgb;
+        vec3 color;
+        if (u_Glass < 0.5) {
+            color = vec3(0.95, 0.93, 0.88) * reflected;
+        } else {
+            // each color bends a little differently, for some dispersion,
+            // and only the entry into the glass is accounted for
+            vec3 refracted = vec3(texture(t_Env, refract(i, n, 1.0 / 1.50)).r,
+                                  texture(t_Env, refract(i, n, 1.0 / 1.52)).g,
+                                  texture(t_Env, refract(i, n, 1.0 / 1.54)).b);
+            // Schlick's approximation of the Fresnel reflectance
+            float fresnel = 0.04 + 0.96 * pow(1.0 - max(dot(-i, n), 0.0), 5.0);
+            color = mix(refracted, reflected, fresnel);
+        }
+        o_Color = vec4(1.0 - exp(-u_Exposure * color), 1.0);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Environment mapping example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let cube = match env::args().nth(1) {
+        Some(dir) => cubemap::load(&mut factory, &dir).unwrap_or_else(|e| {
+            println!("{}, generating the environment", e);
+            cubemap::generate(&mut factory, 512, cubemap::environment)
+        }),
+        None => cubemap::generate(&mut factory, 512, cubemap::environment),
+    };
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+    let mut meshes = MeshCache::new();
+    let mut skybox = Skybox::new(&mut factory, &mut meshes, cube.clone());
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut batches = Vec::new();
+    for (mesh, slice) in vec![meshes.sphere(&mut factory, 48, 96),
+                              meshes.torus(&mut factory, 96, 48, 0.8, 0.35),
+                              meshes.cube(&mut factory)].into_iter() {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            cam_pos: [0.0; 3],
+            glass: 0.0,
+            exposure: 1.0,
+            env: (cube.clone(), Some(sampler.clone())),
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state)
+                            .unwrap());
+    }
+    let mut object = 0;
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 4.0, -1.2, 0.2);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut glass = false;
+    let mut exposure = 1.0f32;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::M => glass = !glass,
+                    VirtualKeyCode::Tab => object = (object + 1) % batches.len(),
+                    VirtualKeyCode::Space => turning = !turning,
+                    VirtualKeyCode::Add | VirtualKeyCode::Equals => exposure *= 1.25,
+                    VirtualKeyCode::Subtract | VirtualKeyCode::Minus => exposure *= 0.8,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.4 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let view = camera.view().mat;
+        let tilt = Matrix3::from_angle_x(cgmath::rad(0.6));
+        let model = Matrix3::from_angle_z(cgmath::rad(a)).mul_m(&tilt).to_matrix4();
+        let batch = &mut batches[object];
+        batch.params.view_proj = proj.mul_m(&view).into_fixed();
+        batch.params.model = model.into_fixed();
+        batch.params.cam_pos = camera.position().into_fixed();
+        batch.params.glass = if glass { 1.0 } else { 0.0 };
+        batch.params.exposure = exposure;
+        skybox.exposure = exposure;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        skybox.draw(&mut renderer, &wrap, &proj, &view);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,390 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of hydraulic erosion on a heightfield.
+//
+// The terrain starts as a few octaves of Perlin noise, then droplets of rain
+// are dropped on it one after the other. Each droplet runs downhill with a
+// bit of inertia, picks up sediment while it speeds up and has room for more,
+// and drops it again where it slows down, in pits, or as it evaporates.
+// After a couple hundred thousand droplets the noise has turned into
+// valleys, gullies and fans of sediment at the foot of the slopes.
+//
+// The simulation runs a batch of droplets every frame, and the terrain mesh
+// is built again from the heights and uploaded, so the erosion can be
+// watched as it happens. The title shows how far along it is.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press Space to
+// pause, O to show the terrain as it was before the erosion, R to start again
+// on a new terrain, and S to save the heights to `erosion.png`.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate image;
+extern crate noise;
+extern crate rand;
+
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::heightfield::Heightfield;
+use gfx_examples::{capture, shape, window, Thumbnail};
+use noise::{Seed, perlin2};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Scale"]
+    scale: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform float u_Scale;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+    out float v_Height;
+
+    void main() {
+        v_Normal = a_Normal;
+        v_Height = a_Pos.z;
+        gl_Position = u_ViewProj * vec4(u_Scale * a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    in float v_Height;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float rock = smoothstep(0.75, 0.6, n.z);
+        float snow = smoothstep(18.0, 22.0, v_Height + 4.0 * n.z) * (1.0 - rock);
+        vec3 albedo = mix(vec3(0.3, 0.42, 0.18), vec3(0.42, 0.36, 0.3), rock);
+        albedo = mix(albedo, vec3(0.9, 0.92, 0.95), snow);
+        float diffuse = max(dot(n, normalize(vec3(0.5, 0.3, 0.7))), 0.0);
+        vec3 color = albedo * (vec3(1.0, 0.95, 0.85) * diffuse + vec3(0.15, 0.18, 0.22));
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// Squares along each side of the terrain. The simulation works on squares
+// of one unit, and the terrain is drawn at `SCALE` meters a unit.
+const SEGMENTS: usize = 256;
+const SCALE: f32 = 0.5;
+
+// Droplets in a whole run, and in each frame
+const DROPLETS: usize = 200000;
+const DROPLETS_PER_FRAME: usize = 1000;
+
+// How much of its direction a droplet keeps from one step to the next,
+// instead of following the slope
+const INERTIA: f32 = 0.05;
+// How much sediment a droplet can carry, for its speed, its water and how
+// steeply it goes down
+const CAPACITY: f32 = 4.0;
+// Capacity left on flat ground, so droplets still carve a little there
+const MIN_CAPACITY: f32 = 0.01;
+// How fast a droplet picks up sediment it has room for, and drops sediment
+// it has no room for
+const EROSION: f32 = 0.3;
+const DEPOSITION: f32 = 0.3;
+// Water lost on every step
+const EVAPORATION: f32 = 0.01;
+const GRAVITY: f32 = 4.0;
+const MAX_STEPS: usize = 30;
+// Sediment is picked up from the corners within this radius, so droplets
+// wear down valleys instead of digging holes a square wide
+const RADIUS: isize = 3;
+
+// Hills from a few octaves of noise, in units of the terrain
+fn noise_height(seed: &Seed, x: f32, y: f32) -> f32 {
+    let mut height = 0.0;
+    let (mut frequency, mut amplitude) = (1.0 / 96.0, 16.0);
+    for _ in 0 .. 6 {
+        height += amplitude * perlin2(seed, &[x * frequency, y * frequency]);
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+    height + 6.0
+}
+
+// The height at a point inside the grid and the slope there, interpolated
+// between the four corners around it
+fn height_and_gradient(heights: &[f32], x: f32, y: f32) -> (f32, f32, f32) {
+    let row = SEGMENTS + 1;
+    let (i, j) = (x as usize, y as usize);
+    let (u, v) = (x - i as f32, y - j as f32);
+    let index = j * row + i;
+    let (h00, h10) = (heights[index], heights[index + 1]);
+    let (h01, h11) = (heights[index + row], heights[index + row + 1]);
+    let gx = (h10 - h00) * (1.0 - v) + (h11 - h01) * v;
+    let gy = (h01 - h00) * (1.0 - u) + (h11 - h10) * u;
+    let h = h00 * (1.0 - u) * (1.0 - v) + h10 * u * (1.0 - v) +
+            h01 * (1.0 - u) * v + h11 * u * v;
+    (h, gx, gy)
+}
+
+struct Erosion {
+    // offsets of the corners within `RADIUS`, and how much of the sediment
+    // picked up comes from each
+    brush: Vec<(isize, isize, f32)>,
+    rng: rand::XorShiftRng,
+    done: usize,
+}
+
+impl Erosion {
+    fn new(rng: rand::XorShiftRng) -> Erosion {
+        let mut brush = Vec::new();
+        for dy in -RADIUS .. RADIUS + 1 {
+            for dx in -RADIUS .. RADIUS + 1 {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance < RADIUS as f32 {
+                    brush.push((dx, dy, 1.0 - distance / RADIUS as f32));
+                }
+            }
+        }
+        let total = brush.iter().fold(0.0, |sum, &(_, _, w)| sum + w);
+        for b in brush.iter_mut() {
+            b.2 /= total;
+        }
+        Erosion { brush: brush, rng: rng, done: 0 }
+    }
+
+    // Runs one droplet from a random point until it stops, leaves the grid
+    // or runs out of steps
+    fn droplet(&mut self, heights: &mut [f32]) {
+        let (row, last) = (SEGMENTS + 1, (SEGMENTS - 1) as f32);
+        let (mut x, mut y) = (self.rng.gen_range(0.0, last), self.rng.gen_range(0.0, last));
+        let (mut dx, mut dy) = (0.0f32, 0.0f32);
+        let (mut speed, mut water, mut sediment) = (1.0f32, 1.0f32, 0.0f32);
+        for _ in 0 .. MAX_STEPS {
+            let (i, j) = (x as usize, y as usize);
+            let (u, v) = (x - i as f32, y - j as f32);
+            let (height, gx, gy) = height_and_gradient(heights, x, y);
+
+            dx = dx * INERTIA - gx * (1.0 - INERTIA);
+            dy = dy * INERTIA - gy * (1.0 - INERTIA);
+            let length = (dx * dx + dy * dy).sqrt();
+            if length < 1.0e-6 {
+                break;
+            }
+            dx /= length;
+            dy /= length;
+            x += dx;
+            y += dy;
+            if x < 0.0 || y < 0.0 || x >= last || y >= last {
+                break;
+            }
+
+            let (new_height, _, _) = height_and_gradient(heights, x, y);
+            let dh = new_height - height;
+            let capacity = (-dh * speed * water * CAPACITY).max(MIN_CAPACITY);
+            let index = j * row + i;
+            if sediment > capacity || dh > 0.0 {
+                // going uphill, fill the pit behind at most, and spread what
+                // is dropped over the corners around the old position
+                let amount = if dh > 0.0 { dh.min(sediment) }
+                             else { (sediment - capacity) * DEPOSITION };
+                sediment -= amount;
+                heights[index] += amount * (1.0 - u) * (1.0 - v);
+                heights[index + 1] += amount * u * (1.0 - v);
+                heights[index + row] += amount * (1.0 - u) * v;
+                heights[index + row + 1] += amount * u * v;
+            } else {
+                // never more than the drop, so it doesn't dig a pit behind
+                let amount = ((capacity - sediment) * EROSION).min(-dh);
+                for &(bx, by, weight) in self.brush.iter() {
+                    let (ci, cj) = (i as isize + bx, j as isize + by);
+                    if ci < 0 || cj < 0 || ci > SEGMENTS as isize || cj > SEGMENTS as isize {
+                        continue;
+                    }
+                    heights[cj as usize * row + ci as usize] -= amount * weight;
+                }
+                sediment += amount;
+            }
+
+            speed = (speed * speed - dh * GRAVITY).max(0.0).sqrt();
+            water *= 1.0 - EVAPORATION;
+        }
+        self.done += 1;
+    }
+}
+
+// Writes the heights as a grayscale image, from black at the lowest corner
+// to white at the highest
+fn save(terrain: &Heightfield, path: &str) {
+    let heights = terrain.heights();
+    let (low, high) = heights.iter().fold((std::f32::MAX, std::f32::MIN),
+                                          |(lo, hi), &h| (lo.min(h), hi.max(h)));
+    let row = SEGMENTS + 1;
+    // flipped, so the top of the image is the highest y
+    let mut data = Vec::with_capacity(row * row);
+    for j in (0 .. row).rev() {
+        for i in 0 .. row {
+            let h = heights[j * row + i];
+            data.push((255.0 * (h - low) / (high - low)) as u8);
+        }
+    }
+    match image::save_buffer(path, &data, row as u32, row as u32, image::Gray(8)) {
+        Ok(()) => println!("Saved {}, with heights from {:.1} to {:.1} m",
+                           path, SCALE * low, SCALE * high),
+        Err(e) => println!("Failed to save {}: {}", path, e),
+    }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Erosion example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let capturing = thumbnail.is_some();
+    let new_terrain = || {
+        let seed = Seed::new(if capturing { capture::SEED } else { rand::thread_rng().gen() });
+        let terrain = Heightfield::new(SEGMENTS, 1.0, |x, y| noise_height(&seed, x, y));
+        let original = Heightfield::new(SEGMENTS, 1.0, |x, y| noise_height(&seed, x, y));
+        (terrain, original)
+    };
+    let (mut terrain, mut original) = new_terrain();
+    let mut erosion = Erosion::new(if capturing { rand::XorShiftRng::new_unseeded() }
+                                   else { rand::weak_rng() });
+
+    // the corners don't move in x and y, so only the vertices are uploaded
+    // again, and the indices stay
+    let (vertex_data, index_data) = terrain.mesh();
+    let vertex_buffer = factory.create_buffer::<shape::Vertex>(vertex_data.len(),
+                                                               gfx::BufferUsage::Dynamic);
+    factory.update_buffer(&vertex_buffer, &vertex_data, 0);
+    let mut batch = {
+        let mesh = gfx::Mesh::from_format(vertex_buffer.clone(),
+                                          vertex_data.len() as gfx::VertexCount);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            scale: SCALE,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 1000.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 130.0, -2.2, 0.7);
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.72, 0.88, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // a thumbnail shows the end of the erosion, all run before the first frame
+    let per_frame = if capturing { DROPLETS } else { DROPLETS_PER_FRAME };
+    let mut paused = false;
+    let mut show_original = false;
+    let mut dirty = false;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    paused = !paused,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::O)) => {
+                    show_original = !show_original;
+                    dirty = true;
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::R)) => {
+                    let (t, o) = new_terrain();
+                    terrain = t;
+                    original = o;
+                    erosion.done = 0;
+                    dirty = true;
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::S)) =>
+                    save(&terrain, "erosion.png"),
+                _ => {},
+            }
+        }
+
+        if !paused && erosion.done < DROPLETS {
+            for _ in 0 .. per_frame.min(DROPLETS - erosion.done) {
+                erosion.droplet(terrain.heights_mut());
+            }
+            dirty |= !show_original;
+        }
+        if dirty {
+            let (vertex_data, _) = if show_original { original.mesh() } else { terrain.mesh() };
+            factory.update_buffer(&vertex_buffer, &vertex_data, 0);
+            dirty = false;
+        }
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        batch.params.view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let progress = if erosion.done < DROPLETS {
+            format!("{}%, {} of {} droplets{}", erosion.done * 100 / DROPLETS,
+                    erosion.done, DROPLETS, if paused { ", paused" } else { "" })
+        } else {
+            format!("done after {} droplets", DROPLETS)
+        };
+        wrap.window.set_title(&format!("Erosion example: {}, showing the {} terrain", progress,
+                                       if show_original { "original" } else { "eroded" }));
+    }
+}
@@ -0,0 +1,423 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of lens distortion and fisheye projections.
+//
+// The scene is rendered around the camera into the six faces of a cubemap,
+// so every direction is available to the final pass. That pass treats each
+// pixel as a point on the image plane, bends it with the radial polynomial
+// of Brown's lens model, and turns the distorted radius into an angle from
+// the view direction with one of the classic lens projections:
+// rectilinear, equidistant, stereographic, equisolid angle and
+// orthographic. The field of view spans the width of the window, and can go
+// up to a full sphere for the projections that cover one.
+//
+// Drag with the left mouse button to look around, and use W, A, S, D, E, Q
+// and Left Shift to fly. Press M to change the projection, Up and Down to
+// change the field of view, Left and Right to change the barrel or
+// pincushion distortion, Page Up and Page Down to change its fourth order
+// term, and R to reset the lens.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{cubemap, window, Clock, Thumbnail};
+
+const CUBE_SIZE: gfx::tex::Size = 1024;
+
+// names of the projections, and the widest field of view of each, in degrees
+static PROJECTIONS: [(&'static str, f32); 5] = [
+    ("rectilinear", 170.0),
+    ("equidistant", 360.0),
+    ("stereographic", 350.0),
+    ("equisolid angle", 360.0),
+    ("orthographic", 180.0),
+];
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct LensParams<R: gfx::Resources> {
+    // the camera axes in world space
+    #[name = "u_Right"]
+    right: [f32; 3],
+    #[name = "u_Up"]
+    up: [f32; 3],
+    #[name = "u_Forward"]
+    forward: [f32; 3],
+    #[name = "u_Aspect"]
+    aspect: f32,
+    // index into `PROJECTIONS`
+    #[name = "u_Projection"]
+    projection: f32,
+    // half of the horizontal field of view, in radians
+    #[name = "u_HalfFov"]
+    half_fov: f32,
+    // the coefficients of r^2 and r^4 in the radial distortion
+    #[name = "u_Distortion"]
+    distortion: [f32; 2],
+    #[name = "t_Cube"]
+    cube: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_World = world.xyz;
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.3, -0.5, 0.8))), 0.0);
+        vec3 color = u_Color * (2.5 * diffuse + vec3(0.35, 0.4, 0.5) * (0.6 + 0.4 * n.z));
+        // lines a unit apart, which show how straight lines bend
+        vec2 cell = abs(fract(v_World.xy + 0.5) - 0.5);
+        vec2 width = fwidth(v_World.xy);
+        float line = 1.0 - min(min(cell.x / width.x, cell.y / width.y), 1.0);
+        color *= 1.0 - 0.6 * line * step(0.5, abs(n.z));
+        // the same curve as the skybox
+        o_Color = vec4(1.0 - exp(-color), 1.0);
+    }
+";
+
+static LENS_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static LENS_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+
+    uniform vec3 u_Right;
+    uniform vec3 u_Up;
+    uniform vec3 u_Forward;
+    uniform float u_Aspect;
+    uniform float u_Projection;
+    uniform float u_HalfFov;
+    uniform vec2 u_Distortion;
+    uniform samplerCube t_Cube;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // the image plane, with a radius of 1 at the left and right edges
+        vec2 p = (2.0 * v_TexCoord - 1.0) * vec2(1.0, 1.0 / u_Aspect);
+        float r2 = dot(p, p);
+        // positive coefficients pull in more of the scene towards the edges
+        // for barrel distortion, negative ones less for pincushion
+        vec2 q = p * (1.0 + u_Distortion.x * r2 + u_Distortion.y * r2 * r2);
+        float r = length(q);
+
+        // the angle from the view direction, which reaches half of the field
+        // of view at a radius of 1
+        float f = u_HalfFov;
+        float theta = -1.0;
+        int projection = int(u_Projection);
+        if (projection == 0) {
+            theta = atan(r * tan(f));
+        } else if (projection == 1) {
+            theta = r * f;
+        } else if (projection == 2) {
+            theta = 2.0 * atan(r * tan(0.5 * f));
+        } else if (projection == 3) {
+            float s = r * sin(0.5 * f);
+            if (s <= 1.0) {
+                theta = 2.0 * asin(s);
+            }
+        } else {
+            float s = r * sin(f);
+            if (s <= 1.0) {
+                theta = asin(s);
+            }
+        }
+        // outside of the image circle of the lens
+        if (theta < 0.0 || theta > PI) {
+            o_Color = vec4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+
+        vec2 side = r > 0.0 ? q / r : vec2(0.0);
+        vec3 dir = cos(theta) * u_Forward + sin(theta) * (side.x * u_Right + side.y * u_Up);
+        o_Color = vec4(texture(t_Cube, dir).rgb, 1.0);
+    }
+";
+
+fn model_matrix(pos: [f32; 3], scale: [f32; 3]) -> Matrix4<f32> {
+    Matrix4::new(scale[0], 0.0, 0.0, 0.0,
+                 0.0, scale[1], 0.0, 0.0,
+                 0.0, 0.0, scale[2], 0.0,
+                 pos[0], pos[1], pos[2], 1.0)
+}
+
+// View direction and up vector for each cubemap face, in the order GL
+// numbers them: +X, -X, +Y, -Y, +Z, -Z
+fn face_views(eye: &Point3<f32>) -> Vec<AffineMatrix3<f32>> {
+    let faces = [
+        (Vector3::new( 1.0,  0.0,  0.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new(-1.0,  0.0,  0.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new( 0.0,  1.0,  0.0), Vector3::new(0.0,  0.0,  1.0)),
+        (Vector3::new( 0.0, -1.0,  0.0), Vector3::new(0.0,  0.0, -1.0)),
+        (Vector3::new( 0.0,  0.0,  1.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new( 0.0,  0.0, -1.0), Vector3::new(0.0, -1.0,  0.0)),
+    ];
+    faces.iter().map(|&(dir, up)| {
+        Transform::look_at(eye, &eye.add_v(&dir), &up)
+    }).collect()
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Fisheye example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let cube_tex = factory.create_texture(gfx::tex::TextureInfo {
+        width: CUBE_SIZE,
+        height: CUBE_SIZE,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::TextureCube,
+        format: gfx::tex::RGBA8,
+    }).unwrap();
+    // All six faces share one depth buffer, cleared before each of them
+    let depth_tex = factory.create_texture(gfx::tex::TextureInfo {
+        width: CUBE_SIZE,
+        height: CUBE_SIZE,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let face_frames: Vec<_> = (0 .. 6).map(|face| gfx::Frame {
+        colors: vec![Plane::Texture(cube_tex.clone(), 0, Some(face))],
+        depth: Some(Plane::Texture(depth_tex.clone(), 0, None)),
+        .. gfx::Frame::empty(CUBE_SIZE, CUBE_SIZE)
+    }).collect();
+
+    let mut cache = MeshCache::new();
+    let environment = cubemap::generate(&mut factory, 128, cubemap::environment);
+    let mut skybox = cubemap::Skybox::new(&mut factory, &mut cache, environment);
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let meshes = vec![cache.plane(&mut factory), cache.cube(&mut factory),
+                      cache.sphere(&mut factory, 24, 48)];
+
+    // mesh index, position, scale and color: a floor, a colonnade around
+    // the start, a street of blocks with a gate over it, and a few balls
+    let mut objects = vec![
+        (0, [0.0, 0.0, 0.0], [60.0, 60.0, 1.0], [0.55, 0.5, 0.45]),
+        (1, [-5.0, 14.0, 4.0], [1.0, 1.0, 4.0], [0.8, 0.75, 0.6]),
+        (1, [5.0, 14.0, 4.0], [1.0, 1.0, 4.0], [0.8, 0.75, 0.6]),
+        (1, [0.0, 14.0, 8.5], [6.0, 1.0, 0.5], [0.8, 0.75, 0.6]),
+        (2, [3.0, 3.0, 1.0], [1.0, 1.0, 1.0], [0.9, 0.2, 0.1]),
+        (2, [-2.0, 5.0, 0.6], [0.6, 0.6, 0.6], [0.2, 0.7, 0.2]),
+        (2, [0.0, -4.0, 1.5], [1.5, 1.5, 1.5], [0.2, 0.4, 0.9]),
+    ];
+    for i in 0 .. 16 {
+        let angle = i as f32 * PI / 8.0;
+        objects.push((1, [8.0 * angle.cos(), 8.0 * angle.sin(), 2.5], [0.4, 0.4, 2.5],
+                      [0.85, 0.85, 0.8]));
+    }
+    for i in 0 .. 8 {
+        let y = 20.0 + 6.0 * i as f32;
+        let height = 3.0 + 2.0 * (i % 3) as f32;
+        for &x in [-9.0f32, 9.0].iter() {
+            objects.push((1, [x, y, height], [2.5, 2.5, height],
+                          [0.5 + 0.05 * i as f32, 0.45, 0.6 - 0.05 * i as f32]));
+        }
+    }
+
+    let mut batches: Vec<_> = objects.iter().map(|&(mesh_id, pos, scale, color)| {
+        let (ref mesh, ref slice) = meshes[mesh_id];
+        let data = SceneParams {
+            model: model_matrix(pos, scale).into_fixed(),
+            view_proj: Matrix4::identity().into_fixed(),
+            color: color,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, mesh, slice.clone(), &state).unwrap()
+    }).collect();
+
+    let aspect = w as f32 / h as f32;
+    let mut lens = {
+        let (mesh, slice) = cache.quad(&mut factory);
+        let program = factory.link_program(LENS_VERTEX_SRC, LENS_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = LensParams {
+            right: [1.0, 0.0, 0.0],
+            up: [0.0, 0.0, 1.0],
+            forward: [0.0, 1.0, 0.0],
+            aspect: aspect,
+            projection: 1.0,
+            half_fov: 0.0,
+            distortion: [0.0; 2],
+            cube: (cube_tex, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    let face_proj = cgmath::perspective(cgmath::deg(90.0f32), 1.0, 0.1, 200.0);
+    let mut camera = FlyCamera::new(Point3::new(0.0, 0.0, 1.7), 0.5 * PI, 0.0, 5.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // the lens: a projection, a field of view in degrees and the distortion
+    let default_lens = (1, 180.0f32, [0.0f32; 2]);
+    let (mut projection, mut fov, mut distortion) = default_lens;
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::M => projection = (projection + 1) % PROJECTIONS.len(),
+                    VirtualKeyCode::Up => fov += 10.0,
+                    VirtualKeyCode::Down => fov -= 10.0,
+                    VirtualKeyCode::Right => distortion[0] += 0.05,
+                    VirtualKeyCode::Left => distortion[0] -= 0.05,
+                    VirtualKeyCode::PageUp => distortion[1] += 0.02,
+                    VirtualKeyCode::PageDown => distortion[1] -= 0.02,
+                    VirtualKeyCode::R => {
+                        let (p, f, d) = default_lens;
+                        projection = p;
+                        fov = f;
+                        distortion = d;
+                    },
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+        fov = fov.max(10.0).min(PROJECTIONS[projection].1);
+
+        clock.update();
+        while clock.tick() {
+            camera.update(clock.step());
+        }
+
+        // Render the scene around the camera into the faces of the cubemap
+        for (frame, face_view) in face_frames.iter().zip(face_views(&camera.position).iter()) {
+            let view_proj = face_proj.mul_m(&face_view.mat);
+            renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, frame);
+            for batch in batches.iter_mut() {
+                batch.params.view_proj = view_proj.into_fixed();
+                renderer.draw(&(&*batch, &context), frame).unwrap();
+            }
+            skybox.draw(&mut renderer, frame, &face_proj, &face_view.mat);
+        }
+
+        // Look it up through the lens
+        lens.params.right = camera.right().into_fixed();
+        lens.params.up = camera.up().into_fixed();
+        lens.params.forward = camera.forward().into_fixed();
+        lens.params.projection = projection as f32;
+        lens.params.half_fov = 0.5 * fov * PI / 180.0;
+        lens.params.distortion = distortion;
+        renderer.draw(&(&lens, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Fisheye example: {}, {} degrees, distortion {:.2} {:.2}",
+            PROJECTIONS[projection].0, fov, distortion[0], distortion[1]));
+    }
+}
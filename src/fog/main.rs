@@ -0,0 +1,378 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of the classic fog equations, side by side.
+//
+// The window is split into six panes looking down the same long corridor,
+// each drawn with its own scissor rectangle and a projection shifted into
+// it. The columns use the three fog modes of the old fixed function
+// pipeline, as a fraction of the color kept at a distance z:
+//
+//     linear              (end - z) / (end - start)
+//     exponential         exp(-density * z)
+//     exponential squared exp(-(density * z)^2)
+//
+// The top row computes the fog in the vertex shader and interpolates it
+// across the triangles, as the fixed function pipeline did; the bottom row
+// computes it for every pixel. Fog doesn't change linearly between two
+// vertices, so the top row gets it wrong inside large triangles, which the
+// coarse version of the corridor makes easy to see.
+//
+// Press Up and Down to change the density, T to switch between the fine and
+// the coarse corridor, and Space to stop walking.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx_examples::text::{Font, Span, TextRenderer};
+use gfx_examples::{window, Clock, Thumbnail};
+
+const CORRIDOR_LENGTH: f32 = 160.0;
+const FOG_COLOR: [f32; 3] = [0.6, 0.62, 0.66];
+const MODES: [&'static str; 3] = ["linear", "exponential", "exponential squared"];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+    #[name = "a_Color"]
+    color: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_View"]
+    view: [[f32; 4]; 4],
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    #[name = "u_FogColor"]
+    fog_color: [f32; 3],
+    // 0 linear, 1 exponential, 2 exponential squared
+    #[name = "u_FogMode"]
+    fog_mode: f32,
+    #[name = "u_FogDensity"]
+    fog_density: f32,
+    // the start and end distances of the linear fog
+    #[name = "u_FogRange"]
+    fog_range: [f32; 2],
+    #[name = "u_PerPixel"]
+    per_pixel: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_View;
+    uniform mat4 u_Proj;
+    uniform float u_FogMode;
+    uniform float u_FogDensity;
+    uniform vec2 u_FogRange;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec3 a_Color;
+    out vec3 v_ViewPos;
+    out vec3 v_Color;
+    out float v_Fog;
+
+    float fog(float z) {
+        if (u_FogMode < 0.5) {
+            return clamp((u_FogRange.y - z) / (u_FogRange.y - u_FogRange.x), 0.0, 1.0);
+        } else if (u_FogMode < 1.5) {
+            return exp(-u_FogDensity * z);
+        } else {
+            float d = u_FogDensity * z;
+            return exp(-d * d);
+        }
+    }
+
+    void main() {
+        vec4 pos = u_View * vec4(a_Pos, 1.0);
+        v_ViewPos = pos.xyz;
+        float light = 0.4 + 0.6 * max(dot(a_Normal, normalize(vec3(0.4, -0.3, 0.9))), 0.0);
+        v_Color = a_Color * light;
+        v_Fog = fog(length(pos.xyz));
+        gl_Position = u_Proj * pos;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_FogColor;
+    uniform float u_FogMode;
+    uniform float u_FogDensity;
+    uniform vec2 u_FogRange;
+    uniform float u_PerPixel;
+    in vec3 v_ViewPos;
+    in vec3 v_Color;
+    in float v_Fog;
+    out vec4 o_Color;
+
+    float fog(float z) {
+        if (u_FogMode < 0.5) {
+            return clamp((u_FogRange.y - z) / (u_FogRange.y - u_FogRange.x), 0.0, 1.0);
+        } else if (u_FogMode < 1.5) {
+            return exp(-u_FogDensity * z);
+        } else {
+            float d = u_FogDensity * z;
+            return exp(-d * d);
+        }
+    }
+
+    void main() {
+        float f = u_PerPixel > 0.5 ? fog(length(v_ViewPos)) : v_Fog;
+        o_Color = vec4(mix(u_FogColor, v_Color, f), 1.0);
+    }
+";
+
+// Adds the `nu` by `nv` cells of a rectangle, as separate quads so that
+// they can alternate between two colors
+fn add_rect(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>, origin: [f32; 3],
+            du: [f32; 3], dv: [f32; 3], (nu, nv): (usize, usize), colors: [[f32; 3]; 2]) {
+    let normal = {
+        let n = [du[1] * dv[2] - du[2] * dv[1], du[2] * dv[0] - du[0] * dv[2],
+                 du[0] * dv[1] - du[1] * dv[0]];
+        let l = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        [n[0] / l, n[1] / l, n[2] / l]
+    };
+    for j in 0 .. nv {
+        for i in 0 .. nu {
+            let base = vertices.len() as u32;
+            for &(s, t) in [(0, 0), (1, 0), (1, 1), (0, 1)].iter() {
+                let (s, t) = ((i + s) as f32 / nu as f32, (j + t) as f32 / nv as f32);
+                vertices.push(Vertex {
+                    pos: [origin[0] + du[0] * s + dv[0] * t,
+                          origin[1] + du[1] * s + dv[1] * t,
+                          origin[2] + du[2] * s + dv[2] * t],
+                    normal: normal,
+                    color: colors[(i + j) % 2],
+                });
+            }
+            indices.extend([0, 1, 2, 2, 3, 0].iter().map(|i| base + i));
+        }
+    }
+}
+
+// The floor, walls and ceiling of a corridor along Y, split into cells of
+// about `cell` units, with a pillar against the walls every 8 units
+fn make_corridor(cell: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let n = (CORRIDOR_LENGTH / cell) as usize;
+    let across = ((4.0 / cell).ceil() as usize).max(1);
+    let up = ((3.0 / cell).ceil() as usize).max(1);
+    let tiles = [[0.75, 0.7, 0.6], [0.45, 0.4, 0.35]];
+    let plaster = [[0.8, 0.78, 0.72], [0.72, 0.7, 0.65]];
+    let l = CORRIDOR_LENGTH;
+    add_rect(&mut vertices, &mut indices, [-2.0, 0.0, 0.0], [4.0, 0.0, 0.0], [0.0, l, 0.0],
+             (across, n), tiles);
+    add_rect(&mut vertices, &mut indices, [-2.0, 0.0, 3.0], [0.0, l, 0.0], [4.0, 0.0, 0.0],
+             (n, across), plaster);
+    add_rect(&mut vertices, &mut indices, [-2.0, 0.0, 0.0], [0.0, l, 0.0], [0.0, 0.0, 3.0],
+             (n, up), plaster);
+    add_rect(&mut vertices, &mut indices, [2.0, 0.0, 0.0], [0.0, 0.0, 3.0], [0.0, l, 0.0],
+             (up, n), plaster);
+
+    let stone = [[0.55, 0.5, 0.45], [0.55, 0.5, 0.45]];
+    let mut y = 4.0;
+    while y < CORRIDOR_LENGTH {
+        for &x in [-1.7, 1.7].iter() {
+            let (x0, x1, y0, y1) = (x - 0.3, x + 0.3, y - 0.3, y + 0.3);
+            // the four sides, facing -y, +x, +y and -x
+            add_rect(&mut vertices, &mut indices, [x0, y0, 0.0], [0.6, 0.0, 0.0],
+                     [0.0, 0.0, 3.0], (1, up), stone);
+            add_rect(&mut vertices, &mut indices, [x1, y0, 0.0], [0.0, 0.6, 0.0],
+                     [0.0, 0.0, 3.0], (1, up), stone);
+            add_rect(&mut vertices, &mut indices, [x1, y1, 0.0], [-0.6, 0.0, 0.0],
+                     [0.0, 0.0, 3.0], (1, up), stone);
+            add_rect(&mut vertices, &mut indices, [x0, y1, 0.0], [0.0, -0.6, 0.0],
+                     [0.0, 0.0, 3.0], (1, up), stone);
+        }
+        y += 8.0;
+    }
+    (vertices, indices)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Fog example with gfx-rs".to_string())
+            .with_dimensions(1200, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    // the corridor with cells of a unit, and of 20 units
+    let mut batches: Vec<_> = [1.0, 20.0].iter().map(|&cell| {
+        let (vertex_data, index_data) = make_corridor(cell);
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let data = Params {
+            view: Matrix4::identity().into_fixed(),
+            proj: Matrix4::identity().into_fixed(),
+            fog_color: FOG_COLOR,
+            fog_mode: 0.0,
+            fog_density: 0.0,
+            fog_range: [0.0, 1.0],
+            per_pixel: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    }).collect();
+
+    let mut hud = match Font::load_default(16) {
+        Ok(font) => Some(TextRenderer::new(&mut factory, font, 256)),
+        Err(e) => {
+            println!("{}, without labels: the columns are {}, {} and {}, \
+                      the top row per vertex and the bottom one per pixel",
+                     e, MODES[0], MODES[1], MODES[2]);
+            None
+        },
+    };
+
+    // three columns and two rows
+    let (pane_w, pane_h) = (w / 3, h / 2);
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), pane_w as f32 / pane_h as f32,
+                                   0.1, 200.0);
+
+    let clear_data = gfx::ClearData {
+        color: [FOG_COLOR[0], FOG_COLOR[1], FOG_COLOR[2], 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut density = 0.04f32;
+    let mut coarse = false;
+    let mut walking = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_y, mut y) = (2.0f32, 2.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => density = (density * 1.25).min(0.5),
+                    VirtualKeyCode::Down => density = (density / 1.25).max(0.005),
+                    VirtualKeyCode::T => coarse = !coarse,
+                    VirtualKeyCode::Space => walking = !walking,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_y = y;
+            if walking {
+                y += 1.5 * clock.step();
+                // back by a whole number of pillars and cells, before the end
+                // of the corridor comes into view
+                if y > 0.5 * CORRIDOR_LENGTH {
+                    y -= 40.0;
+                    prev_y -= 40.0;
+                }
+            }
+        }
+        let y = prev_y + (y - prev_y) * clock.alpha();
+
+        let sway = 0.4 * (0.5 * y).sin();
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(sway, y, 1.6),
+            &Point3::new(0.0, y + 10.0, 1.4),
+            &Vector3::unit_z(),
+        );
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        let batch = &mut batches[if coarse { 1 } else { 0 }];
+        batch.params.view = view.mat.into_fixed();
+        batch.params.fog_density = density;
+        // the linear fog ends where the exponential one keeps 5%
+        batch.params.fog_range = [0.0, 3.0 / density];
+        for row in 0 .. 2 {
+            for col in 0 .. 3 {
+                // squeezes the projection into the pane, whose bottom left
+                // corner is at (px, py) in pixels, counting from the bottom
+                let (px, py) = (col * pane_w, (1 - row) * pane_h);
+                let (sx, sy) = (pane_w as f32 / w as f32, pane_h as f32 / h as f32);
+                let pane = Matrix4::new(sx, 0.0, 0.0, 0.0,
+                                        0.0, sy, 0.0, 0.0,
+                                        0.0, 0.0, 1.0, 0.0,
+                                        (2.0 * px as f32 + pane_w as f32) / w as f32 - 1.0,
+                                        (2.0 * py as f32 + pane_h as f32) / h as f32 - 1.0,
+                                        0.0, 1.0);
+                batch.params.proj = pane.mul_m(&proj).into_fixed();
+                batch.params.fog_mode = col as f32;
+                batch.params.per_pixel = row as f32;
+                batch.state = state.clone().scissor(px, py, pane_w, pane_h);
+                renderer.draw(&(&*batch, &context), &wrap).unwrap();
+
+                if let Some(ref mut hud) = hud {
+                    let label = format!("{}, per {}", MODES[col as usize],
+                                        if row == 0 { "vertex" } else { "pixel" });
+                    hud.add(&[Span::new(&label, [0.1, 0.1, 0.1, 1.0])],
+                            [(col * pane_w) as f32 + 10.0, (row * pane_h) as f32 + 10.0], None);
+                }
+            }
+        }
+        if let Some(ref mut hud) = hud {
+            hud.draw(&mut factory, &mut renderer, &wrap);
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Fog example: density {:.3}, {} corridor",
+                                       density, if coarse { "coarse" } else { "fine" }));
+    }
+}
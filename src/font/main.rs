@@ -0,0 +1,161 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of text rendering with a glyph atlas.
+//
+// A TrueType font is rasterized into an atlas at startup, at two sizes, and
+// used to draw a heading, a paragraph with colored spans whose wrap width
+// keeps changing, and a line of text updated every frame.
+//
+// Run with `font [path to a .ttf file]`, some common system fonts are tried
+// otherwise. Press K to toggle kerning, Space to pause the wrapping.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::env;
+use std::process;
+use gfx::traits::*;
+use gfx_examples::text::{Font, Span, TextRenderer, WHITE};
+use gfx_examples::{window, Clock, Thumbnail};
+
+const GRAY: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+const YELLOW: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const CYAN: [f32; 4] = [0.4, 0.85, 1.0, 1.0];
+const RED: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+fn load_font(pixel_size: u32) -> Result<Font, String> {
+    match env::args().nth(1) {
+        Some(path) => Font::load(&path, pixel_size),
+        None => Font::load_default(pixel_size),
+    }
+}
+
+pub fn main() {
+    let (heading_font, body_font) = match (load_font(32), load_font(18)) {
+        (Ok(heading), Ok(body)) => (heading, body),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("{}, pass the path to a .ttf file", e);
+            // fail, so that the thumbnail binary doesn't expect a picture
+            process::exit(1);
+        },
+    };
+
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Font example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+
+    let mut heading = TextRenderer::new(&mut factory, heading_font, 256);
+    let mut body = TextRenderer::new(&mut factory, body_font, 4096);
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.15, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut kerning = true;
+    let mut paused = false;
+    let mut frame = 0u32;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::K)) => {
+                    kerning = !kerning;
+                    heading.font_mut().set_kerning(kerning);
+                    body.font_mut().set_kerning(kerning);
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    paused = !paused,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if !paused {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+        frame += 1;
+
+        let margin = 20.0;
+        let y = heading.add(&[Span::new("AVATAR Typeface, WAVE", WHITE)],
+                            [margin, margin], None);
+
+        let wrap_width = (w as f32 - 2.0 * margin) * (0.65 + 0.35 * (0.5 * t).sin());
+        let y = body.add(&[
+            Span::new("Text is rasterized by ", GRAY),
+            Span::new("FreeType", YELLOW),
+            Span::new(" into a glyph atlas when the font is loaded, then laid out on the \
+                       CPU into one quad per glyph. Words are wrapped to fit a width of ", GRAY),
+            Span::new(&format!("{:.0}", wrap_width), CYAN),
+            Span::new(" pixels, and the spacing of character pairs like \"AV\", \"To\" and \
+                       \"WA\" is adjusted by the kerning table of the font, which is ", GRAY),
+            Span::new(if kerning { "enabled" } else { "disabled" },
+                      if kerning { CYAN } else { RED }),
+            Span::new(".\nEvery span of text can have its own color, and all of them are \
+                       drawn with a single call.", GRAY),
+        ], [margin, y + margin], Some(wrap_width));
+
+        body.add(&[
+            Span::new("frame ", GRAY),
+            Span::new(&format!("{}", frame), YELLOW),
+            Span::new(", time ", GRAY),
+            Span::new(&format!("{:.2}s", t), YELLOW),
+        ], [margin, y + margin], None);
+
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        heading.draw(&mut factory, &mut renderer, &wrap);
+        body.draw(&mut factory, &mut renderer, &wrap);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,370 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of an escape-time fractal explorer.
+//
+// The Mandelbrot and Julia sets are computed per pixel in the fragment shader
+// of a full-screen quad, with the view sent as uniforms. The view is kept in
+// doubles on the CPU, and the shader can emulate doubles with pairs of floats
+// to zoom much further than single precision allows.
+//
+// Drag with the left mouse button to pan and use the mouse wheel to zoom at
+// the cursor. Press J to switch between the Mandelbrot set and the Julia set
+// of the point under the cursor, P to cycle palettes, D to toggle the double
+// emulation, Up and Down to change the number of iterations, and R to reset
+// the view.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use gfx::traits::*;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Thumbnail};
+
+const NUM_PALETTES: u32 = 3;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    // the view center as pairs of high and low floats
+    #[name = "u_CenterX"]
+    center_x: [f32; 2],
+    #[name = "u_CenterY"]
+    center_y: [f32; 2],
+    // size of a pixel in the complex plane
+    #[name = "u_Scale"]
+    scale: f32,
+    #[name = "u_HalfSize"]
+    half_size: [f32; 2],
+    #[name = "u_Iterations"]
+    iterations: f32,
+    #[name = "u_Julia"]
+    julia: f32,
+    #[name = "u_JuliaC"]
+    julia_c: [f32; 2],
+    #[name = "u_Precise"]
+    precise: f32,
+    #[name = "u_Palette"]
+    palette: f32,
+    // always 1.0, see the fragment shader
+    #[name = "u_One"]
+    one: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const int MAX_ITERATIONS = 4096;
+    const float BAILOUT = 256.0;
+
+    uniform vec2 u_CenterX;
+    uniform vec2 u_CenterY;
+    uniform float u_Scale;
+    uniform vec2 u_HalfSize;
+    uniform float u_Iterations;
+    uniform float u_Julia;
+    uniform vec2 u_JuliaC;
+    uniform float u_Precise;
+    uniform float u_Palette;
+    uniform float u_One;
+    out vec4 o_Color;
+
+    // Double-single arithmetic: a value is the unevaluated sum of a high and
+    // a low float, and the rounding error of each operation is kept in the
+    // low part. Compilers may simplify expressions like (a + b) - a to b,
+    // which would throw the errors away, so the key terms are multiplied by
+    // a uniform they can't see through.
+    vec2 ds_add(vec2 a, vec2 b) {
+        float s = a.x + b.x;
+        float v = (s - a.x) * u_One;
+        float e = (a.x - (s - v)) + (b.x - v) + a.y + b.y;
+        float hi = s + e;
+        return vec2(hi, e - (hi - s) * u_One);
+    }
+
+    vec2 ds_sub(vec2 a, vec2 b) {
+        return ds_add(a, -b);
+    }
+
+    // splits a float into two halves whose products are exact
+    vec2 split(float a) {
+        float t = a * 4097.0;
+        float hi = t - (t - a) * u_One;
+        return vec2(hi, a - hi);
+    }
+
+    vec2 ds_mul(vec2 a, vec2 b) {
+        float p = a.x * b.x;
+        vec2 sa = split(a.x);
+        vec2 sb = split(b.x);
+        float err = ((sa.x * sb.x - p) + sa.x * sb.y + sa.y * sb.x) + sa.y * sb.y;
+        err += a.x * b.y + a.y * b.x;
+        float hi = p + err;
+        return vec2(hi, err - (hi - p) * u_One);
+    }
+
+    // number of iterations before escaping, smoothed, or -1.0 inside the set
+    float escape_float(vec2 z, vec2 c) {
+        for (int i = 0; i < MAX_ITERATIONS; ++i) {
+            if (float(i) >= u_Iterations) break;
+            z = vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+            float r2 = dot(z, z);
+            if (r2 > BAILOUT) {
+                return float(i) + 1.0 - log(0.5 * log(r2)) / log(2.0);
+            }
+        }
+        return -1.0;
+    }
+
+    float escape_double(vec2 zx, vec2 zy, vec2 cx, vec2 cy) {
+        for (int i = 0; i < MAX_ITERATIONS; ++i) {
+            if (float(i) >= u_Iterations) break;
+            vec2 xy = ds_mul(zx, zy);
+            zx = ds_add(ds_sub(ds_mul(zx, zx), ds_mul(zy, zy)), cx);
+            zy = ds_add(ds_add(xy, xy), cy);
+            float r2 = zx.x * zx.x + zy.x * zy.x;
+            if (r2 > BAILOUT) {
+                return float(i) + 1.0 - log(0.5 * log(r2)) / log(2.0);
+            }
+        }
+        return -1.0;
+    }
+
+    // cosine palettes, a + b * cos(2 pi (c t + d))
+    vec3 palette(float t) {
+        vec3 a = vec3(0.5), b = vec3(0.5), c = vec3(1.0), d;
+        if (u_Palette < 0.5) {
+            d = vec3(0.0, 0.1, 0.2);
+        } else if (u_Palette < 1.5) {
+            c = vec3(1.0, 0.7, 0.4);
+            d = vec3(0.0, 0.15, 0.2);
+        } else {
+            a = vec3(0.8, 0.5, 0.4);
+            b = vec3(0.2, 0.4, 0.2);
+            c = vec3(2.0, 1.0, 1.0);
+            d = vec3(0.0, 0.25, 0.25);
+        }
+        return a + b * cos(6.28318 * (c * t + d));
+    }
+
+    void main() {
+        vec2 offset = (gl_FragCoord.xy - u_HalfSize) * u_Scale;
+        float n;
+        if (u_Precise > 0.5) {
+            vec2 px = ds_add(u_CenterX, vec2(offset.x, 0.0));
+            vec2 py = ds_add(u_CenterY, vec2(offset.y, 0.0));
+            if (u_Julia > 0.5) {
+                n = escape_double(px, py, vec2(u_JuliaC.x, 0.0), vec2(u_JuliaC.y, 0.0));
+            } else {
+                n = escape_double(vec2(0.0), vec2(0.0), px, py);
+            }
+        } else {
+            vec2 p = vec2(u_CenterX.x, u_CenterY.x) + offset;
+            n = u_Julia > 0.5 ? escape_float(p, u_JuliaC) : escape_float(vec2(0.0), p);
+        }
+        vec3 color = n < 0.0 ? vec3(0.0) : palette(0.02 * n);
+        o_Color = vec4(color, 1.0);
+    }
+";
+
+// A view of the complex plane
+#[derive(Clone, Copy)]
+struct View {
+    center: (f64, f64),
+    // size of a pixel
+    scale: f64,
+}
+
+impl View {
+    fn new(center: (f64, f64), height: f64, pixels: u16) -> View {
+        View { center: center, scale: height / pixels as f64 }
+    }
+
+    // Point of the complex plane at a pixel offset from the center
+    fn point(&self, offset: (f64, f64)) -> (f64, f64) {
+        (self.center.0 + offset.0 * self.scale, self.center.1 + offset.1 * self.scale)
+    }
+
+    // Scales the view, keeping the point at the offset in place
+    fn zoom(&mut self, offset: (f64, f64), factor: f64) {
+        let p = self.point(offset);
+        self.scale *= factor;
+        self.center = (p.0 - offset.0 * self.scale, p.1 - offset.1 * self.scale);
+    }
+}
+
+// Splits a double into a float and the float nearest to the remainder
+fn split(x: f64) -> [f32; 2] {
+    let hi = x as f32;
+    [hi, (x - hi as f64) as f32]
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Fractal example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let hidpi = window::scale_factor(&wrap.window) as f64;
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut meshes = MeshCache::new();
+    let (mesh, slice) = meshes.quad(&mut factory);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let data = Params {
+        center_x: [0.0; 2],
+        center_y: [0.0; 2],
+        scale: 1.0,
+        half_size: [0.5 * w as f32, 0.5 * h as f32],
+        iterations: 256.0,
+        julia: 0.0,
+        julia_c: [0.0; 2],
+        precise: 1.0,
+        palette: 0.0,
+        one: 1.0,
+        _dummy: std::marker::PhantomData,
+    };
+    let mut batch = context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new())
+                           .unwrap();
+
+    let default_views = [
+        View::new((-0.6, 0.0), 2.6, h),
+        View::new((0.0, 0.0), 3.2, h),
+    ];
+    // one view for the Mandelbrot set and one for the Julia set
+    let mut views = default_views;
+    let mut julia = false;
+    let mut palette = 0;
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // the cursor as a pixel offset from the center, y up
+    let mut cursor = (0.0f64, 0.0f64);
+    let mut dragging = false;
+    let mut title_changed = true;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode};
+            let mode = julia as usize;
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::MouseInput(state, MouseButton::Left) =>
+                    dragging = state == ElementState::Pressed,
+                Event::MouseMoved((x, y)) => {
+                    let pos = (x as f64 * hidpi - 0.5 * w as f64,
+                               0.5 * h as f64 - y as f64 * hidpi);
+                    if dragging {
+                        let view = &mut views[mode];
+                        view.center.0 -= (pos.0 - cursor.0) * view.scale;
+                        view.center.1 -= (pos.1 - cursor.1) * view.scale;
+                        title_changed = true;
+                    }
+                    cursor = pos;
+                },
+                Event::MouseWheel(delta) => {
+                    let lines = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y,
+                        MouseScrollDelta::PixelDelta(_, y) => y / 20.0,
+                    };
+                    views[mode].zoom(cursor, 0.8f64.powf(lines as f64));
+                    title_changed = true;
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let params = &mut batch.params;
+                    match key {
+                        VirtualKeyCode::J => {
+                            if !julia {
+                                let c = views[0].point(cursor);
+                                params.julia_c = [c.0 as f32, c.1 as f32];
+                                views[1] = default_views[1];
+                            }
+                            julia = !julia;
+                        },
+                        VirtualKeyCode::P => palette = (palette + 1) % NUM_PALETTES,
+                        VirtualKeyCode::D => params.precise = 1.0 - params.precise,
+                        VirtualKeyCode::Up =>
+                            params.iterations = (params.iterations * 1.5).min(4096.0),
+                        VirtualKeyCode::Down =>
+                            params.iterations = (params.iterations / 1.5).max(16.0),
+                        VirtualKeyCode::R => views[mode] = default_views[mode],
+                        _ => {},
+                    }
+                    title_changed = true;
+                },
+                _ => {},
+            }
+        }
+
+        let view = views[julia as usize];
+        batch.params.center_x = split(view.center.0);
+        batch.params.center_y = split(view.center.1);
+        batch.params.scale = view.scale as f32;
+        batch.params.julia = if julia { 1.0 } else { 0.0 };
+        batch.params.palette = palette as f32;
+
+        if title_changed {
+            let zoom = default_views[julia as usize].scale / view.scale;
+            wrap.window.set_title(&format!(
+                "{} set, zoom {:.3e}, {} iterations, {} precision",
+                if julia { "Julia" } else { "Mandelbrot" }, zoom,
+                batch.params.iterations as u32,
+                if batch.params.precise > 0.5 { "emulated double" } else { "single" }));
+            title_changed = false;
+        }
+
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of rendering fur with shells and fins.
+//
+// The same sphere is drawn many times in a frame, each time pushed a little
+// further out along its normals. A texture holds the length of a strand at
+// each texel, and every shell only keeps the texels whose strand reaches it,
+// so that the stack of shells looks like hairs standing on the surface. The
+// shells are alpha blended over each other from the inside out, get darker
+// towards the roots, and bend along a force made of gravity and gusts of
+// wind, more the further they are from the surface.
+//
+// At the silhouette the shells are seen edge on and the gaps between them
+// show. Fins fill them in: a quad stands on every edge of the mesh, textured
+// with a row of strands, and fades in as the surface turns away from the eye.
+//
+// Press Up and Down to change the number of shells, G to cycle through the
+// strengths of gravity, W through the strengths of the wind, F to toggle the
+// fins, and Space to stop the ball.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::collections::HashSet;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+const FUR_SIZE: u16 = 256;
+// of the longest strands, in the units of the sphere
+const FUR_LENGTH: f32 = 0.25;
+const FUR_DENSITY: f32 = 48.0;
+const MAX_SHELLS: usize = 64;
+const GRAVITY: [f32; 3] = [0.0, 0.4, 1.0];
+const WIND: [f32; 3] = [0.0, 0.5, 1.2];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct FinVertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+    // along the edge in x, from the root to the tip in y
+    #[name = "a_TexCoord"]
+    tex_coord: [f32; 2],
+    // the strand texture row the fin takes its strands from
+    #[name = "a_Row"]
+    row: f32,
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_CameraPos"]
+    camera_pos: [f32; 3],
+    // the bend at the tips, in world space
+    #[name = "u_Force"]
+    force: [f32; 3],
+    // from 0 at the surface to 1 at the tips
+    #[name = "u_Layer"]
+    layer: f32,
+    // of the strands at the tips, and how many strand texels cover the
+    // sphere once from pole to pole
+    #[name = "u_FurLength"]
+    fur_length: f32,
+    #[name = "u_FurDensity"]
+    fur_density: f32,
+    #[name = "t_Fur"]
+    fur: gfx::shade::TextureParam<R>,
+}
+
+static SHELL_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    uniform vec3 u_Force;
+    uniform float u_Layer;
+    uniform float u_FurLength;
+    uniform float u_FurDensity;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec2 a_TexCoord;
+    out vec3 v_Normal;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        // twice as long around the equator as from pole to pole
+        v_TexCoord = a_TexCoord * vec2(2.0, 1.0) * u_FurDensity;
+        vec3 pos = (u_Model * vec4(a_Pos, 1.0)).xyz;
+        // straight out at the roots, bending more towards the tips
+        pos += (normalize(v_Normal) + u_Force * u_Layer) * u_FurLength * u_Layer;
+        gl_Position = u_ViewProj * vec4(pos, 1.0);
+    }
+";
+
+static SHELL_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Layer;
+    uniform sampler2D t_Fur;
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 strand = texture(t_Fur, v_TexCoord);
+        // the surface itself is solid, above it only the strands that
+        // reach this high are left
+        if (u_Layer > 0.0 && strand.a < u_Layer) {
+            discard;
+        }
+        vec3 n = normalize(v_Normal);
+        float diffuse = 0.5 + 0.5 * dot(n, normalize(vec3(0.4, -0.5, 0.8)));
+        // less light gets down between the strands
+        float occlusion = mix(0.25, 1.0, u_Layer);
+        vec3 color = strand.rgb * diffuse * occlusion;
+        o_Color = vec4(color, u_Layer > 0.0 ? 1.0 - 0.5 * u_Layer : 1.0);
+    }
+";
+
+static FIN_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    uniform vec3 u_CameraPos;
+    uniform vec3 u_Force;
+    uniform float u_FurLength;
+    uniform float u_FurDensity;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec2 a_TexCoord;
+    in float a_Row;
+    out vec3 v_Normal;
+    out vec2 v_TexCoord;
+    out float v_Layer;
+    out float v_Fade;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_TexCoord = vec2(a_TexCoord.x * u_FurDensity, a_Row);
+        v_Layer = a_TexCoord.y;
+        vec3 n = normalize(v_Normal);
+        vec3 pos = (u_Model * vec4(a_Pos, 1.0)).xyz;
+        // only the fins on the silhouette are seen, the others are faded
+        // out while the shells still cover the gaps
+        float facing = abs(dot(n, normalize(u_CameraPos - pos)));
+        v_Fade = 1.0 - smoothstep(0.1, 0.4, facing);
+        pos += (n + u_Force * v_Layer) * u_FurLength * v_Layer;
+        gl_Position = u_ViewProj * vec4(pos, 1.0);
+    }
+";
+
+static FIN_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Fur;
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    in float v_Layer;
+    in float v_Fade;
+    out vec4 o_Color;
+
+    void main() {
+        // the same strands as the shells, seen from the side
+        vec4 strand = texture(t_Fur, v_TexCoord);
+        if (strand.a < v_Layer) {
+            discard;
+        }
+        vec3 n = normalize(v_Normal);
+        float diffuse = 0.5 + 0.5 * dot(n, normalize(vec3(0.4, -0.5, 0.8)));
+        float occlusion = mix(0.25, 1.0, v_Layer);
+        o_Color = vec4(strand.rgb * diffuse * occlusion, v_Fade * (1.0 - 0.5 * v_Layer));
+    }
+";
+
+// Random strand lengths in shades of brown, with a third of the texels
+// left without a strand
+fn make_fur<R: rand::Rng>(rng: &mut R) -> Vec<u8> {
+    let mut data = Vec::with_capacity(FUR_SIZE as usize * FUR_SIZE as usize * 4);
+    for _ in 0 .. FUR_SIZE as usize * FUR_SIZE as usize {
+        let length = if rng.gen::<f32>() < 0.33 { 0.0 } else { rng.gen_range(0.3f32, 1.0) };
+        let shade = rng.gen_range(0.6f32, 1.0);
+        data.extend([(shade * 0.75 * 255.0) as u8, (shade * 0.5 * 255.0) as u8,
+                     (shade * 0.3 * 255.0) as u8, (length * 255.0) as u8].iter().cloned());
+    }
+    data
+}
+
+// A fin standing on every edge of the mesh, as two triangles
+fn make_fins<R: rand::Rng>(vertices: &[shape::Vertex], indices: &[u16], rng: &mut R)
+                           -> Vec<FinVertex> {
+    let mut edges = HashSet::new();
+    for tri in indices.chunks(3) {
+        for k in 0 .. 3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            edges.insert((a.min(b), a.max(b)));
+        }
+    }
+    let mut fins = Vec::new();
+    for &(a, b) in edges.iter() {
+        let (va, vb) = (&vertices[a as usize], &vertices[b as usize]);
+        let d = [vb.pos[0] - va.pos[0], vb.pos[1] - va.pos[1], vb.pos[2] - va.pos[2]];
+        let length = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+        // the rings meet at the poles
+        if length < 1e-5 {
+            continue;
+        }
+        let row = rng.gen::<f32>();
+        // the shells have the density over the half circle between the poles
+        let u = length / std::f32::consts::PI;
+        let corner = |v: &shape::Vertex, x: f32, y: f32| FinVertex {
+            pos: v.pos,
+            normal: v.normal,
+            tex_coord: [x, y],
+            row: row,
+        };
+        fins.push(corner(va, 0.0, 0.0));
+        fins.push(corner(vb, u, 0.0));
+        fins.push(corner(vb, u, 1.0));
+        fins.push(corner(va, 0.0, 0.0));
+        fins.push(corner(vb, u, 1.0));
+        fins.push(corner(va, 0.0, 1.0));
+    }
+    fins
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Fur example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+    let mut rng = rand::XorShiftRng::new_unseeded();
+
+    let fur = factory.create_texture_rgba8(FUR_SIZE, FUR_SIZE, false).unwrap();
+    factory.update_texture(&fur, &fur.get_info().to_image_info(),
+                           &make_fur(&mut rng), None).unwrap();
+    // strands are one texel wide, so they are not filtered
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Tile)
+    );
+
+    let (vertex_data, index_data) = shape::sphere(32, 64);
+    let params = || Params {
+        view_proj: Matrix4::identity().into_fixed(),
+        model: Matrix4::identity().into_fixed(),
+        camera_pos: [0.0; 3],
+        force: [0.0; 3],
+        layer: 0.0,
+        fur_length: FUR_LENGTH,
+        fur_density: FUR_DENSITY,
+        fur: (fur.clone(), Some(sampler.clone())),
+    };
+
+    let mut shells = {
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(SHELL_VERTEX_SRC, SHELL_FRAGMENT_SRC).unwrap();
+        let mut state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        state.primitive.method = gfx::state::RasterMethod::Fill(gfx::state::CullMode::Back);
+        context.make_batch(&program, params(), &mesh, slice, &state).unwrap()
+    };
+    // the surface writes depth, the shells over it are only tested
+    let surface_state = shells.state.clone();
+    let shell_state = surface_state.clone()
+                                   .depth(gfx::state::Comparison::LessEqual, false)
+                                   .blend(gfx::BlendPreset::Alpha);
+
+    let mut fins = {
+        let fin_data = make_fins(&vertex_data, &index_data, &mut rng);
+        let mesh = factory.create_mesh(&fin_data);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(FIN_VERTEX_SRC, FIN_FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, false)
+                                         .blend(gfx::BlendPreset::Alpha);
+        context.make_batch(&program, params(), &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 4.0, -1.2, 0.3);
+
+    let clear_data = gfx::ClearData {
+        color: [0.45, 0.5, 0.55, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut num_shells = 24;
+    let mut gravity = 1;
+    let mut wind = 1;
+    let mut show_fins = true;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => num_shells = (num_shells + 4).min(MAX_SHELLS),
+                    VirtualKeyCode::Down => num_shells = (num_shells - 4).max(4),
+                    VirtualKeyCode::G => gravity = (gravity + 1) % GRAVITY.len(),
+                    VirtualKeyCode::W => wind = (wind + 1) % WIND.len(),
+                    VirtualKeyCode::F => show_fins = !show_fins,
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.3 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        // gusts from the side, on top of a steady pull down
+        let t = clock.time() as f32;
+        let gust = WIND[wind] * (0.6 + 0.4 * (1.3 * t).sin() * (0.7 * t).sin());
+        let force = [gust, 0.3 * gust * (0.9 * t).cos(), -GRAVITY[gravity]];
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        let model = Matrix3::from_angle_z(cgmath::rad(a)).to_matrix4().into_fixed();
+        let camera_pos = camera.position().into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+
+        shells.params.view_proj = view_proj;
+        shells.params.model = model;
+        shells.params.camera_pos = camera_pos;
+        shells.params.force = force;
+        // the surface, then each shell over the ones below it
+        for i in 0 .. num_shells + 1 {
+            shells.state = if i == 0 { surface_state.clone() } else { shell_state.clone() };
+            shells.params.layer = i as f32 / num_shells as f32;
+            renderer.draw(&(&shells, &context), &wrap).unwrap();
+        }
+
+        if show_fins {
+            fins.params.view_proj = view_proj;
+            fins.params.model = model;
+            fins.params.camera_pos = camera_pos;
+            fins.params.force = force;
+            renderer.draw(&(&fins, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Fur example: {} shells, fins {}",
+                                       num_shells, if show_fins { "on" } else { "off" }));
+    }
+}
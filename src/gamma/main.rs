@@ -0,0 +1,243 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of gamma correction.
+//
+// The window shows the same things twice. The left half treats color values
+// as amounts of light, as is easy to do by accident: the texture is lit as it
+// is stored, and the result is written out as it is. The right half decodes
+// the texture from sRGB to linear light, adds up the lighting there, and
+// encodes the result to sRGB for the display.
+//
+// From the top, each half has a ramp from no light to full light, a block of
+// black and white pixels next to a block meant to be as bright on average,
+// and a textured sphere lit by a red and a blue light. On the left, the
+// ramp is mostly dark, the block of half the light is too dark next to the
+// pixels, and the sphere has harsh shadow edges and overlapping lights that
+// don't add up.
+//
+// Press L to toggle the second light, and Space to stop the sphere.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use gfx::traits::*;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const TEXTURE_WIDTH: usize = 256;
+const TEXTURE_HEIGHT: usize = 128;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Resolution"]
+    resolution: [f32; 2],
+    // how far the sphere turned
+    #[name = "u_Rotation"]
+    rotation: f32,
+    #[name = "u_Lights"]
+    lights: [f32; 2],
+    #[name = "t_Texture"]
+    texture: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+
+    uniform vec2 u_Resolution;
+    uniform float u_Rotation;
+    uniform vec2 u_Lights;
+    uniform sampler2D t_Texture;
+    out vec4 o_Color;
+
+    // the exact sRGB curves, rather than a power of 2.2
+    vec3 to_linear(vec3 c) {
+        return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    }
+
+    vec3 to_srgb(vec3 c) {
+        return mix(12.92 * c, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+    }
+
+    // The light reaching each pixel of a half of the window, of `size` pixels
+    vec3 light(vec2 pos, vec2 size, bool correct) {
+        vec2 p = pos / size;
+        if (p.y > 0.85) {
+            return vec3(p.x);
+        }
+        if (p.y > 0.72) {
+            if (p.x < 0.5) {
+                // half of the pixels are fully lit
+                return vec3(mod(floor(pos.x) + floor(pos.y), 2.0));
+            }
+            return vec3(0.5);
+        }
+
+        vec2 center = vec2(0.5 * size.x, 0.36 * size.y);
+        float radius = 0.3 * size.y;
+        vec2 d = (pos - center) / radius;
+        float r2 = dot(d, d);
+        if (r2 > 1.0) {
+            return vec3(0.02);
+        }
+        vec3 n = vec3(d, sqrt(1.0 - r2));
+        // around the vertical axis, then from the bottom
+        vec2 uv = vec2((atan(n.x, n.z) + u_Rotation) / (2.0 * PI), acos(-n.y) / PI);
+        vec3 albedo = texture(t_Texture, uv).rgb;
+        if (correct) {
+            albedo = to_linear(albedo);
+        }
+        vec3 red = vec3(1.0, 0.25, 0.1) * max(dot(n, normalize(vec3(-0.8, 0.4, 0.6))), 0.0);
+        vec3 blue = vec3(0.1, 0.35, 1.0) * max(dot(n, normalize(vec3(0.8, 0.4, 0.6))), 0.0);
+        return albedo * (u_Lights.x * red + u_Lights.y * blue + 0.02);
+    }
+
+    void main() {
+        vec2 size = vec2(0.5 * u_Resolution.x, u_Resolution.y);
+        bool correct = gl_FragCoord.x >= size.x;
+        vec2 pos = gl_FragCoord.xy - vec2(correct ? size.x : 0.0, 0.0);
+        vec3 c = clamp(light(pos, size, correct), 0.0, 1.0);
+        if (correct) {
+            c = to_srgb(c);
+        }
+        // a line between the halves
+        if (abs(gl_FragCoord.x - size.x) < 1.0) {
+            c = vec3(0.5);
+        }
+        o_Color = vec4(c, 1.0);
+    }
+";
+
+// Tiles of two colors, picked in sRGB like colors from an image editor, with
+// light grout between them
+fn create_texture_data() -> Vec<u8> {
+    let mut data = Vec::with_capacity(TEXTURE_WIDTH * TEXTURE_HEIGHT * 4);
+    for y in 0 .. TEXTURE_HEIGHT {
+        for x in 0 .. TEXTURE_WIDTH {
+            let (tx, ty) = (x % 32, y % 32);
+            let color = if tx < 2 || ty < 2 {
+                [220, 215, 200]
+            } else if (x / 32 + y / 32) % 2 == 0 {
+                [230, 120, 40]
+            } else {
+                [40, 150, 160]
+            };
+            data.extend([color[0], color[1], color[2], 0xFF].iter().cloned());
+        }
+    }
+    data
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Gamma correction example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut batch = {
+        let texture = factory.create_texture_rgba8(TEXTURE_WIDTH as u16, TEXTURE_HEIGHT as u16,
+                                                   false).unwrap();
+        factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                               &create_texture_data(), None).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Tile)
+        );
+
+        let mut meshes = MeshCache::new();
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let data = Params {
+            resolution: [w as f32, h as f32],
+            rotation: 0.0,
+            lights: [1.0, 1.0],
+            texture: (texture, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::L =>
+                        batch.params.lights[1] = 1.0 - batch.params.lights[1],
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.3 * clock.step();
+            }
+        }
+        batch.params.rotation = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shader programs made with raw GL calls.
+//!
+//! The examples comparing gfx with plain GL, or using features that gfx
+//! doesn't wrap yet, like tessellation, transform feedback and image stores,
+//! build their programs with these instead of a `gfx::Factory`. Failing to
+//! compile or to link panics with the info log of the driver.
+//!
+//! ```ignore
+//! let vs = gl_program::compile_shader(&gl, VERTEX_SRC, gl::VERTEX_SHADER);
+//! let fs = gl_program::compile_shader(&gl, FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+//! let program = gl_program::link_program(&gl, &[vs, fs], &["a_Pos", "a_Normal"]);
+//! let u_transform = gl_program::uniform_location(&gl, program, "u_Transform");
+//! ```
+
+use std::ffi::CString;
+use std::iter::repeat;
+use std::ptr;
+use std::str;
+use gl;
+use gl::Gl;
+use gl::types::*;
+
+/// Compiles a shader of type `ty` from its source.
+pub fn compile_shader(gl: &Gl, src: &str, ty: GLenum) -> GLuint { unsafe {
+    let shader = gl.CreateShader(ty);
+    // Attempt to compile the shader
+    let src = CString::new(src).unwrap();
+    gl.ShaderSource(shader, 1, &(src.as_bytes_with_nul().as_ptr() as *const i8), ptr::null());
+    gl.CompileShader(shader);
+
+    // Get the compile status
+    let mut status = gl::FALSE as GLint;
+    gl.GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+
+    // Fail on error
+    if status != (gl::TRUE as GLint) {
+        let mut len: GLint = 0;
+        gl.GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+        // subtract 1 to skip the trailing null character
+        let mut buf: Vec<u8> = repeat(0u8).take((len as isize).saturating_sub(1) as usize)
+                                          .collect();
+        gl.GetShaderInfoLog(shader, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
+        panic!("{}", str::from_utf8(&buf).ok().expect("ShaderInfoLog not valid utf8"));
+    }
+    shader
+}}
+
+/// Links `shaders` into a program, binding `attributes` to the locations 0,
+/// 1 and so on in order, and `o_Color` to the first draw buffer.
+pub fn link_program(gl: &Gl, shaders: &[GLuint], attributes: &[&str]) -> GLuint {
+    link_feedback_program(gl, shaders, attributes, &[])
+}
+
+/// Like `link_program`, with `varyings` captured by transform feedback,
+/// interleaved into a single buffer.
+pub fn link_feedback_program(gl: &Gl, shaders: &[GLuint], attributes: &[&str],
+                             varyings: &[&str]) -> GLuint { unsafe {
+    let program = gl.CreateProgram();
+    for &shader in shaders.iter() {
+        gl.AttachShader(program, shader);
+    }
+    for (i, name) in attributes.iter().enumerate() {
+        let name = CString::new(*name).unwrap();
+        gl.BindAttribLocation(program, i as GLuint, name.as_ptr());
+    }
+    let o_color = CString::new("o_Color").unwrap();
+    gl.BindFragDataLocation(program, 0, o_color.as_ptr());
+
+    let names: Vec<CString> = varyings.iter().map(|v| CString::new(*v).unwrap()).collect();
+    let pointers: Vec<*const GLchar> = names.iter().map(|n| n.as_ptr()).collect();
+    if !pointers.is_empty() {
+        gl.TransformFeedbackVaryings(program, pointers.len() as GLsizei,
+                                     pointers.as_ptr(), gl::INTERLEAVED_ATTRIBS);
+    }
+    gl.LinkProgram(program);
+
+    // Get the link status
+    let mut status = gl::FALSE as GLint;
+    gl.GetProgramiv(program, gl::LINK_STATUS, &mut status);
+
+    // Fail on error
+    if status != (gl::TRUE as GLint) {
+        let mut len: GLint = 0;
+        gl.GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
+        // subtract 1 to skip the trailing null character
+        let mut buf: Vec<u8> = repeat(0u8).take((len as isize).saturating_sub(1) as usize)
+                                          .collect();
+        gl.GetProgramInfoLog(program, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
+        panic!("{}", str::from_utf8(&buf).ok().expect("ProgramInfoLog not valid utf8"));
+    }
+    program
+}}
+
+/// Location of the uniform called `name`, -1 if the program has none.
+pub fn uniform_location(gl: &Gl, program: GLuint, name: &str) -> GLint {
+    let name = CString::new(name).unwrap();
+    unsafe { gl.GetUniformLocation(program, name.as_ptr()) }
+}
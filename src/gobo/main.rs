@@ -0,0 +1,351 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of projecting a texture from a spotlight.
+//
+// A stage light shines a pattern, a gobo, onto the scene. The light has a
+// view and a perspective projection of its own, like a camera, and every
+// fragment is transformed by them into the light's clip space. After the
+// perspective division, the position on the light's image plane is the
+// texture coordinate to read the gobo at.
+//
+// Two things need care. A projection maps the points behind the light onto
+// the image plane too, mirrored, so the fragments with a negative W are left
+// dark, and so are the faces turned away from the light. And outside of the
+// image plane the texture coordinates go past the edges, where clamping
+// would smear the border texels over the whole scene, so these are left dark
+// as well.
+//
+// The same matrix is what the shadow example uses to find a fragment in its
+// shadow map, and what a decal uses to find its texture coordinates. The gobo
+// isn't blocked by anything here, since that would take the shadow map too.
+//
+// Press G to cycle through the gobos, Up and Down to change the field of view
+// of the light, R to toggle the rejection of what the light doesn't face,
+// and Space to stop the light.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const GOBO_SIZE: u16 = 256;
+const GOBOS: [&'static str; 3] = ["window", "stained glass", "leaves"];
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    // the projector matrix, from world space to the light's clip space
+    #[name = "u_LightViewProj"]
+    light_view_proj: [[f32; 4]; 4],
+    #[name = "u_LightPos"]
+    light_pos: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "u_Emissive"]
+    emissive: f32,
+    #[name = "u_Reject"]
+    reject: f32,
+    #[name = "t_Gobo"]
+    gobo: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    uniform mat4 u_LightViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+    out vec4 v_LightPos;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_Pos = world.xyz;
+        v_Normal = mat3(u_Model) * a_Normal;
+        // divided by W for each fragment, since it doesn't interpolate
+        // linearly after the division
+        v_LightPos = u_LightViewProj * world;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_LightPos;
+    uniform vec3 u_Color;
+    uniform float u_Emissive;
+    uniform float u_Reject;
+    uniform sampler2D t_Gobo;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    in vec4 v_LightPos;
+    out vec4 o_Color;
+
+    void main() {
+        if (u_Emissive > 0.5) {
+            o_Color = vec4(1.0, 0.95, 0.8, 1.0);
+            return;
+        }
+        vec3 n = normalize(v_Normal);
+        vec3 to_light = u_LightPos - v_Pos;
+        float facing = dot(n, normalize(to_light));
+
+        vec2 tc = v_LightPos.xy / v_LightPos.w * 0.5 + 0.5;
+        bool inside = all(greaterThanEqual(tc, vec2(0.0))) && all(lessThanEqual(tc, vec2(1.0)));
+        vec3 light = texture(t_Gobo, tc).rgb;
+        if (u_Reject > 0.5) {
+            if (v_LightPos.w <= 0.0 || !inside) {
+                light = vec3(0.0);
+            }
+            facing = max(facing, 0.0);
+        } else {
+            facing = abs(facing);
+        }
+        float falloff = 40.0 / dot(to_light, to_light);
+        vec3 lit = light * facing * falloff + vec3(0.04, 0.045, 0.06);
+        o_Color = vec4(u_Color * lit, 1.0);
+    }
+";
+
+// The gobos are drawn with a dark border, and a circular one for the lens
+fn make_gobo<R: rand::Rng>(kind: usize, rng: &mut R) -> Vec<u8> {
+    let size = GOBO_SIZE as usize;
+    // position and radius of the leaves, in the unit square
+    let leaves: Vec<_> = (0 .. 60).map(|_|
+        (rng.gen::<f32>(), rng.gen::<f32>(), rng.gen_range(0.03f32, 0.09))
+    ).collect();
+    // colors of the stained glass panes
+    let panes: Vec<[f32; 3]> = (0 .. 36).map(|_| {
+        let palette = [[0.9, 0.2, 0.15], [0.2, 0.35, 0.9], [0.95, 0.8, 0.2], [0.25, 0.8, 0.3]];
+        palette[rng.gen_range(0, palette.len())]
+    }).collect();
+
+    let mut data = Vec::with_capacity(size * size * 4);
+    for py in 0 .. size {
+        for px in 0 .. size {
+            let (x, y) = ((px as f32 + 0.5) / size as f32, (py as f32 + 0.5) / size as f32);
+            let (dx, dy) = (x - 0.5, y - 0.5);
+            let lens = (dx * dx + dy * dy).sqrt() < 0.48;
+            let color = match kind {
+                0 => {
+                    // four panes split by the bars of the frame
+                    let bar = (x - 0.5).abs() < 0.03 || (y - 0.5).abs() < 0.03;
+                    let frame = dx.abs() > 0.32 || dy.abs() > 0.32;
+                    if bar || frame { [0.0; 3] } else { [1.0, 0.92, 0.75] }
+                },
+                1 => {
+                    let (cx, cy) = ((x * 6.0) as usize, (y * 6.0) as usize);
+                    let (fx, fy) = ((x * 6.0).fract(), (y * 6.0).fract());
+                    let lead = fx < 0.06 || fx > 0.94 || fy < 0.06 || fy > 0.94;
+                    if lead { [0.0; 3] } else { panes[cy * 6 + cx] }
+                },
+                _ => {
+                    let shade = leaves.iter().any(|&(lx, ly, r)| {
+                        (x - lx) * (x - lx) + (y - ly) * (y - ly) < r * r
+                    });
+                    if shade { [0.05, 0.06, 0.03] } else { [0.95, 1.0, 0.8] }
+                },
+            };
+            let c = if lens { color } else { [0.0; 3] };
+            data.extend([(c[0] * 255.0) as u8, (c[1] * 255.0) as u8,
+                         (c[2] * 255.0) as u8, 0xFF].iter().cloned());
+        }
+    }
+    data
+}
+
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Gobo example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+    let mut rng = rand::XorShiftRng::new_unseeded();
+
+    let gobos: Vec<_> = (0 .. GOBOS.len()).map(|kind| {
+        let texture = factory.create_texture_rgba8(GOBO_SIZE, GOBO_SIZE, true).unwrap();
+        factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                               &make_gobo(kind, &mut rng), None).unwrap();
+        factory.generate_mipmap(&texture);
+        texture
+    }).collect();
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let scale = |s: f32| Matrix3::new(s, 0.0, 0.0, 0.0, s, 0.0, 0.0, 0.0, s);
+    let mut meshes = MeshCache::new();
+    let wall = Matrix3::from_angle_x(cgmath::rad(std::f32::consts::PI / 2.0)).mul_m(&scale(8.0));
+    // (mesh, model, color) of everything in the scene, with the ground and a
+    // wall behind the objects
+    let objects = vec![
+        (meshes.plane(&mut factory), place(scale(8.0), 0.0, 0.0, -1.0), [0.8, 0.8, 0.8]),
+        (meshes.plane(&mut factory), place(wall, 0.0, 6.0, 5.0), [0.7, 0.7, 0.75]),
+        (meshes.cube(&mut factory), place(scale(1.0), -2.5, 1.0, 0.0), [0.9, 0.5, 0.3]),
+        (meshes.sphere(&mut factory, 24, 48), place(scale(1.0), 0.0, -1.0, 0.0),
+         [0.4, 0.8, 0.5]),
+        (meshes.torus(&mut factory, 32, 64, 0.8, 0.35), place(scale(1.0), 2.5, 1.0, -0.3),
+         [0.4, 0.6, 0.9]),
+        (meshes.sphere(&mut factory, 12, 24), Matrix4::identity(), [1.0; 3]),
+    ];
+    let mut batches: Vec<_> = objects.into_iter().map(|((mesh, slice), model, color)| {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: model.into_fixed(),
+            light_view_proj: Matrix4::identity().into_fixed(),
+            light_pos: [0.0; 3],
+            color: color,
+            emissive: 0.0,
+            reject: 1.0,
+            gobo: (gobos[0].clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    }).collect();
+    // the last sphere marks the light
+    batches.last_mut().unwrap().params.emissive = 1.0;
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 14.0, -1.9, 0.5);
+
+    let clear_data = gfx::ClearData {
+        color: [0.02, 0.02, 0.03, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut gobo = 0;
+    let mut fov = 40.0f32;
+    let mut reject = true;
+    let mut moving = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::G => gobo = (gobo + 1) % GOBOS.len(),
+                    VirtualKeyCode::Up => fov = (fov + 5.0).min(90.0),
+                    VirtualKeyCode::Down => fov = (fov - 5.0).max(10.0),
+                    VirtualKeyCode::R => reject = !reject,
+                    VirtualKeyCode::Space => moving = !moving,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if moving {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // The light circles over the front of the scene, swaying its aim
+        // around the middle
+        let light_pos = Point3::new(5.0 * (0.4 * t).cos(), -5.0 + 2.0 * (0.4 * t).sin(), 4.0);
+        let target = Point3::new(1.5 * (0.9 * t).sin(), 1.0 * (0.7 * t).cos(), -0.5);
+        let light_view: AffineMatrix3<f32> = Transform::look_at(
+            &light_pos,
+            &target,
+            &Vector3::unit_z(),
+        );
+        let light_proj = cgmath::perspective(cgmath::deg(fov), 1.0, 0.5, 50.0);
+        let light_view_proj = light_proj.mul_m(&light_view.mat).into_fixed();
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        let count = batches.len();
+        for (i, batch) in batches.iter_mut().enumerate() {
+            if i == count - 1 {
+                batch.params.model = place(Matrix3::new(0.2, 0.0, 0.0,
+                                                        0.0, 0.2, 0.0,
+                                                        0.0, 0.0, 0.2),
+                                           light_pos.x, light_pos.y, light_pos.z).into_fixed();
+            }
+            batch.params.view_proj = view_proj;
+            batch.params.light_view_proj = light_view_proj;
+            batch.params.light_pos = light_pos.into_fixed();
+            batch.params.reject = if reject { 1.0 } else { 0.0 };
+            batch.params.gobo.0 = gobos[gobo].clone();
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Gobo example: {}, {} degrees, rejection {}",
+                                       GOBOS[gobo], fov, if reject { "on" } else { "off" }));
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a particle system simulated entirely on the GPU.
+//
+// The particles live in two vertex buffers. Every simulation step draws the
+// particles of one buffer as points through a vertex shader that integrates
+// them, and captures its outputs into the other buffer with transform
+// feedback, with rasterization turned off. The buffers then swap roles, and
+// the latest one is drawn as point sprites. The CPU never touches the
+// particle data after the first upload.
+//
+// gfx-rs doesn't expose transform feedback yet, so this example talks to GL
+// directly, like the "gl" mode of the performance example.
+//
+// Run with `gpu_particles [count]`, the default is a million particles.
+
+extern crate cgmath;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate glutin;
+extern crate rand;
+extern crate time;
+
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx_examples::{window, Clock, Thumbnail};
+use gfx_examples::gl_program::{compile_shader, link_program, link_feedback_program};
+use gfx_examples::gl_program::uniform_location;
+use gl::Gl;
+use gl::types::*;
+use std::env;
+use std::mem;
+use std::ptr;
+use std::str::FromStr;
+
+#[derive(Clone, Copy)]
+struct Particle {
+    // position, and age in seconds; negative until the particle is born
+    pos_age: [f32; 4],
+    // velocity, and the age at which the particle dies
+    vel_life: [f32; 4],
+}
+
+static UPDATE_SRC: &'static str = "
+    #version 150 core
+
+    uniform float u_Time;
+    uniform float u_Delta;
+    in vec4 a_PosAge;
+    in vec4 a_VelLife;
+    out vec4 v_PosAge;
+    out vec4 v_VelLife;
+
+    // Wang hash, turning the particle index and time into random numbers
+    uint hash(uint x) {
+        x = (x ^ 61u) ^ (x >> 16);
+        x *= 9u;
+        x ^= x >> 4;
+        x *= 0x27d4eb2du;
+        return x ^ (x >> 15);
+    }
+
+    float random(inout uint seed) {
+        seed = hash(seed);
+        return float(seed) / 4294967295.0;
+    }
+
+    void main() {
+        vec3 pos = a_PosAge.xyz;
+        float age = a_PosAge.w + u_Delta;
+        vec3 vel = a_VelLife.xyz;
+        float life = a_VelLife.w;
+
+        if (age >= life) {
+            // respawn at the fountain
+            uint seed = uint(gl_VertexID) * 1973u + uint(u_Time * 1000.0) * 9277u;
+            float angle = random(seed) * 6.2831853;
+            float spread = random(seed) * 2.5;
+            pos = vec3(0.0);
+            vel = vec3(spread * cos(angle), spread * sin(angle), 10.0 + 4.0 * random(seed));
+            age = 0.0;
+            life = 2.0 + 2.0 * random(seed);
+        } else if (age > 0.0) {
+            vel.z -= 9.81 * u_Delta;
+            pos += vel * u_Delta;
+            // bounce off the ground, losing some energy
+            if (pos.z < 0.0) {
+                pos.z = -pos.z;
+                vel = vec3(0.8, 0.8, -0.5) * vel;
+            }
+        }
+
+        v_PosAge = vec4(pos, age);
+        v_VelLife = vec4(vel, life);
+    }
+";
+
+static RENDER_VERTEX_SRC: &'static str = "
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec4 a_PosAge;
+    in vec4 a_VelLife;
+    out vec4 v_Color;
+
+    void main() {
+        float t = clamp(a_PosAge.w / a_VelLife.w, 0.0, 1.0);
+        v_Color = vec4(mix(vec3(0.3, 0.6, 1.0), vec3(1.0, 0.2, 0.6), t), 1.0) * (1.0 - t) * 0.25;
+        gl_PointSize = 3.0;
+        gl_Position = u_ViewProj * vec4(a_PosAge.xyz, 1.0);
+        if (a_PosAge.w < 0.0) {
+            // not born yet, move it out of the view volume
+            gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        }
+    }
+";
+
+static RENDER_FRAGMENT_SRC: &'static str = "
+    #version 150 core
+
+    in vec4 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        vec2 c = gl_PointCoord * 2.0 - 1.0;
+        o_Color = v_Color * max(0.0, 1.0 - dot(c, c));
+    }
+";
+
+pub fn main() {
+    let count = env::args().nth(1)
+                           .and_then(|s| FromStr::from_str(&s).ok())
+                           .unwrap_or(1_000_000usize);
+
+    let window = glutin::WindowBuilder::new()
+        .with_title("GPU particle example".to_string())
+        .with_dimensions(800, 600)
+        .with_gl(glutin::GlRequest::Latest)
+        .build().unwrap();
+    unsafe { window.make_current() };
+    let gl = Gl::load_with(|s| window.get_proc_address(s));
+
+    let (w, h) = window::physical_size(&window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+
+    let update_vs = compile_shader(&gl, UPDATE_SRC, gl::VERTEX_SHADER);
+    let update_program = link_feedback_program(&gl, &[update_vs], &["a_PosAge", "a_VelLife"],
+                                               &["v_PosAge", "v_VelLife"]);
+    let render_vs = compile_shader(&gl, RENDER_VERTEX_SRC, gl::VERTEX_SHADER);
+    let render_fs = compile_shader(&gl, RENDER_FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let render_program = link_program(&gl, &[render_vs, render_fs], &["a_PosAge", "a_VelLife"]);
+
+    let u_time = uniform_location(&gl, update_program, "u_Time");
+    let u_delta = uniform_location(&gl, update_program, "u_Delta");
+    let u_view_proj = uniform_location(&gl, render_program, "u_ViewProj");
+
+    // Stagger the births over the first seconds, so that the fountain starts
+    // out steady instead of pulsing
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let initial: Vec<Particle> = (0 .. count).map(|_| Particle {
+        pos_age: [0.0, 0.0, 0.0, -4.0 * rng.gen::<f32>()],
+        vel_life: [0.0, 0.0, 0.0, 0.0],
+    }).collect();
+
+    let mut buffers = [0; 2];
+    let mut vaos = [0; 2];
+    unsafe {
+        gl.GenBuffers(2, buffers.as_mut_ptr());
+        gl.GenVertexArrays(2, vaos.as_mut_ptr());
+        for (&buffer, &vao) in buffers.iter().zip(vaos.iter()) {
+            gl.BindVertexArray(vao);
+            gl.BindBuffer(gl::ARRAY_BUFFER, buffer);
+            gl.BufferData(gl::ARRAY_BUFFER,
+                          (count * mem::size_of::<Particle>()) as GLsizeiptr,
+                          mem::transmute(&initial[0]),
+                          gl::STREAM_COPY);
+            let stride = mem::size_of::<Particle>() as GLsizei;
+            gl.EnableVertexAttribArray(0);
+            gl.VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl.EnableVertexAttribArray(1);
+            gl.VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride,
+                                   mem::size_of::<[f32; 4]>() as *const GLvoid);
+        }
+        gl.BindVertexArray(0);
+    }
+    // the buffer holding the latest state
+    let mut current = 0;
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 100.0);
+
+    println!("simulating {} particles", count);
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+    let (mut frames, mut last_report) = (0u32, time::precise_time_s());
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in window.poll_events() {
+            use glutin::{Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            angle = 0.1 * clock.time() as f32;
+
+            let next = 1 - current;
+            unsafe {
+                gl.UseProgram(update_program);
+                gl.Uniform1f(u_time, clock.time() as f32);
+                gl.Uniform1f(u_delta, clock.step());
+                gl.Enable(gl::RASTERIZER_DISCARD);
+                gl.BindVertexArray(vaos[current]);
+                gl.BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, 0, buffers[next]);
+                gl.BeginTransformFeedback(gl::POINTS);
+                gl.DrawArrays(gl::POINTS, 0, count as GLsizei);
+                gl.EndTransformFeedback();
+                gl.BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, 0, 0);
+                gl.Disable(gl::RASTERIZER_DISCARD);
+            }
+            current = next;
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(20.0 * a.cos(), 20.0 * a.sin(), 6.0),
+            &Point3::new(0.0, 0.0, 4.0),
+            &Vector3::unit_z(),
+        );
+        let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+        unsafe {
+            gl.Viewport(0, 0, w as GLsizei, h as GLsizei);
+            gl.ClearColor(0.02, 0.02, 0.05, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT);
+
+            gl.Enable(gl::BLEND);
+            gl.BlendFunc(gl::ONE, gl::ONE);
+            gl.Enable(gl::PROGRAM_POINT_SIZE);
+            gl.UseProgram(render_program);
+            gl.UniformMatrix4fv(u_view_proj, 1, gl::FALSE, &view_proj[0][0]);
+            gl.BindVertexArray(vaos[current]);
+            gl.DrawArrays(gl::POINTS, 0, count as GLsizei);
+        }
+
+        let win = &window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        window.swap_buffers();
+
+        frames += 1;
+        let now = time::precise_time_s();
+        if now - last_report >= 1.0 {
+            window.set_title(&format!("GPU particle example: {} particles, {:.2}ms/frame",
+                                      count, 1000.0 * (now - last_report) / frames as f64));
+            frames = 0;
+            last_report = now;
+        }
+    }
+
+    // Cleanup
+    unsafe {
+        gl.DeleteProgram(update_program);
+        gl.DeleteProgram(render_program);
+        gl.DeleteShader(update_vs);
+        gl.DeleteShader(render_vs);
+        gl.DeleteShader(render_fs);
+        gl.DeleteBuffers(2, buffers.as_ptr());
+        gl.DeleteVertexArrays(2, vaos.as_ptr());
+    }
+}
@@ -0,0 +1,443 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of rendering grass with instancing.
+//
+// Every blade is an instance of the same strip of quads, placed on a
+// Perlin noise heightfield with a random facing, size and sway phase. The
+// vertex shader bends the blades in gusts of wind, and thins them out with
+// the distance to the camera, widening the remaining ones to keep the same
+// coverage. The shape of a blade comes from an alpha-tested texture.
+//
+// Run with a number to change the count of blades. Look around with the mouse
+// and fly with the keys of the free-fly camera. Press Up and Down to change
+// the strength of the wind, and F to toggle the density falloff.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate noise;
+extern crate rand;
+extern crate time;
+
+use std::env;
+use std::f32::consts::PI;
+use std::str::FromStr;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector3, EuclideanVector};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+use noise::{Seed, perlin2};
+
+const FIELD_SIZE: f32 = 200.0;
+const TERRAIN_CELLS: usize = 128;
+// Quads along a blade
+const BLADE_SEGMENTS: usize = 4;
+const TEXTURE_WIDTH: usize = 32;
+const TEXTURE_HEIGHT: usize = 128;
+// Blades are all drawn up to the first distance, and none past the second
+const DENSITY_RANGE: [f32; 2] = [15.0, 80.0];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct BladeVertex {
+    // across the blade from -0.5 to 0.5, and up from 0 to 1
+    #[name = "a_Blade"]
+    pos: [f32; 2],
+}
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Instance {
+    // position of the root and facing angle
+    #[name = "a_Root"]
+    root: [f32; 4],
+    // height, width, sway phase, and the density under which it is drawn
+    #[name = "a_Shape"]
+    shape: [f32; 4],
+}
+
+#[shader_param]
+struct GrassParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_Time"]
+    time: f32,
+    #[name = "u_Wind"]
+    wind: [f32; 2],
+    #[name = "u_Density"]
+    density: [f32; 2],
+    #[name = "t_Blade"]
+    blade: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct TerrainParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static GRASS_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_CamPos;
+    uniform float u_Time;
+    uniform vec2 u_Wind;
+    uniform vec2 u_Density;
+    in vec2 a_Blade;
+    in vec4 a_Root;
+    in vec4 a_Shape;
+    out vec2 v_TexCoord;
+    out vec3 v_Normal;
+    out float v_Distance;
+
+    void main() {
+        vec3 root = a_Root.xyz;
+        float dist = distance(root.xy, u_CamPos.xy);
+        float density = clamp((u_Density.y - dist) / (u_Density.y - u_Density.x), 0.0, 1.0);
+        if (a_Shape.w > density) {
+            // outside of the clip volume
+            gl_Position = vec4(0.0, 0.0, 2.0, 1.0);
+            return;
+        }
+
+        vec2 side = vec2(cos(a_Root.w), sin(a_Root.w));
+        vec3 normal = vec3(-side.y, side.x, 0.0);
+        // turn the blade towards the camera, so it never shows its back
+        if (dot(normal.xy, u_CamPos.xy - root.xy) < 0.0) {
+            side = -side;
+            normal = -normal;
+        }
+
+        float height = a_Shape.x;
+        float width = a_Shape.y / sqrt(max(density, 0.05));
+        float v = a_Blade.y;
+        // gusts travel along the wind, on top of a little sway of each blade
+        float along = dot(root.xy, normalize(u_Wind + vec2(1e-4)));
+        float gust = 0.5 + 0.5 * sin(0.3 * along - 2.0 * u_Time);
+        vec2 sway = 0.15 * vec2(cos(a_Shape.z), sin(a_Shape.z)) * sin(3.0 * u_Time + a_Shape.z);
+        vec2 bend = (u_Wind * (0.3 + 0.7 * gust) + sway) * height * v * v;
+
+        // bent blades get a little shorter
+        float up = height * v * (1.0 - 0.3 * dot(bend, bend));
+        vec3 pos = root + vec3(side * a_Blade.x * width + bend, up);
+        v_TexCoord = vec2(a_Blade.x + 0.5, v);
+        v_Normal = normalize(normal + vec3(-bend, 1.0));
+        v_Distance = distance(pos, u_CamPos);
+        gl_Position = u_ViewProj * vec4(pos, 1.0);
+    }
+";
+
+static GRASS_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const vec3 SKY = vec3(0.65, 0.75, 0.9);
+
+    uniform sampler2D t_Blade;
+    in vec2 v_TexCoord;
+    in vec3 v_Normal;
+    in float v_Distance;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 tex = texture(t_Blade, v_TexCoord);
+        if (tex.a < 0.5) {
+            discard;
+        }
+        vec3 l = normalize(vec3(0.4, -0.3, 0.8));
+        float light = 0.4 + 0.6 * max(0.0, dot(normalize(v_Normal), l));
+        // darker towards the root, where the blades shade each other
+        vec3 color = tex.rgb * light * (0.5 + 0.5 * v_TexCoord.y);
+        float fog = clamp(v_Distance / 150.0, 0.0, 1.0);
+        o_Color = vec4(mix(color, SKY, fog * fog), 1.0);
+    }
+";
+
+static TERRAIN_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_CamPos;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+    out float v_Distance;
+
+    void main() {
+        v_Normal = a_Normal;
+        v_Distance = distance(a_Pos, u_CamPos);
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static TERRAIN_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const vec3 SKY = vec3(0.65, 0.75, 0.9);
+
+    in vec3 v_Normal;
+    in float v_Distance;
+    out vec4 o_Color;
+
+    void main() {
+        float light = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.4, -0.3, 0.8))));
+        vec3 color = vec3(0.2, 0.25, 0.08) * (0.3 + 0.7 * light);
+        float fog = clamp(v_Distance / 150.0, 0.0, 1.0);
+        o_Color = vec4(mix(color, SKY, fog * fog), 1.0);
+    }
+";
+
+fn height(seed: &Seed, x: f32, y: f32) -> f32 {
+    6.0 * perlin2(seed, &[0.015 * x, 0.015 * y]) + 1.0 * perlin2(seed, &[0.06 * x, 0.06 * y])
+}
+
+fn create_terrain(seed: &Seed) -> (Vec<shape::Vertex>, Vec<u32>) {
+    let n = TERRAIN_CELLS + 1;
+    let cell = FIELD_SIZE / TERRAIN_CELLS as f32;
+    let mut vertices = Vec::with_capacity(n * n);
+    for j in 0 .. n {
+        for i in 0 .. n {
+            let x = i as f32 * cell - 0.5 * FIELD_SIZE;
+            let y = j as f32 * cell - 0.5 * FIELD_SIZE;
+            let e = 0.1;
+            let dx = (height(seed, x + e, y) - height(seed, x - e, y)) / (2.0 * e);
+            let dy = (height(seed, x, y + e) - height(seed, x, y - e)) / (2.0 * e);
+            vertices.push(shape::Vertex {
+                pos: [x, y, height(seed, x, y)],
+                normal: Vector3::new(-dx, -dy, 1.0).normalize().into_fixed(),
+                tex_coord: [i as f32 / TERRAIN_CELLS as f32, j as f32 / TERRAIN_CELLS as f32],
+            });
+        }
+    }
+    let mut indices = Vec::with_capacity(TERRAIN_CELLS * TERRAIN_CELLS * 6);
+    for j in 0 .. TERRAIN_CELLS {
+        for i in 0 .. TERRAIN_CELLS {
+            let a = (j * n + i) as u32;
+            let (b, c, d) = (a + 1, a + 1 + n as u32, a + n as u32);
+            indices.extend([a, b, c, a, c, d].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+fn create_blade() -> Vec<BladeVertex> {
+    let mut vertices = Vec::with_capacity(2 * (BLADE_SEGMENTS + 1));
+    for k in 0 .. BLADE_SEGMENTS + 1 {
+        let v = k as f32 / BLADE_SEGMENTS as f32;
+        vertices.push(BladeVertex { pos: [-0.5, v] });
+        vertices.push(BladeVertex { pos: [0.5, v] });
+    }
+    vertices
+}
+
+// A blade tapering to a point, with a lighter vein in the middle
+fn create_blade_texture() -> Vec<u8> {
+    let mut data = Vec::with_capacity(TEXTURE_WIDTH * TEXTURE_HEIGHT * 4);
+    for py in 0 .. TEXTURE_HEIGHT {
+        for px in 0 .. TEXTURE_WIDTH {
+            let u = ((px as f32 + 0.5) / TEXTURE_WIDTH as f32 - 0.5).abs() * 2.0;
+            let v = (py as f32 + 0.5) / TEXTURE_HEIGHT as f32;
+            let inside = u < (1.0 - v).powf(0.7);
+            let vein = 1.0 + 0.25 * (1.0 - (8.0 * u).min(1.0));
+            let r = (0.25 + 0.45 * v) * vein;
+            let g = (0.45 + 0.4 * v) * vein;
+            let b = 0.1 + 0.1 * v;
+            data.extend([(r.min(1.0) * 255.0) as u8, (g.min(1.0) * 255.0) as u8,
+                         (b * 255.0) as u8, if inside { 0xFF } else { 0 }].iter().cloned());
+        }
+    }
+    data
+}
+
+fn create_instances(seed: &Seed, count: usize) -> Vec<Instance> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    (0 .. count).map(|_| {
+        let x = FIELD_SIZE * (rng.gen::<f32>() - 0.5);
+        let y = FIELD_SIZE * (rng.gen::<f32>() - 0.5);
+        Instance {
+            root: [x, y, height(seed, x, y), 2.0 * PI * rng.gen::<f32>()],
+            shape: [0.3 + 0.5 * rng.gen::<f32>(), 0.05 + 0.04 * rng.gen::<f32>(),
+                    2.0 * PI * rng.gen::<f32>(), rng.gen::<f32>()],
+        }
+    }).collect()
+}
+
+pub fn main() {
+    let count = env::args().skip(1).filter_map(|s| FromStr::from_str(&s).ok())
+                           .next().unwrap_or(300_000usize);
+
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Grass example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let seed = Seed::new(7);
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let mut terrain = {
+        let (vertex_data, index_data) = create_terrain(&seed);
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(TERRAIN_VERTEX_SRC, TERRAIN_FRAGMENT_SRC)
+                             .unwrap();
+        let data = TerrainParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            cam_pos: [0.0; 3],
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let mut grass = {
+        let texture = factory.create_texture_rgba8(TEXTURE_WIDTH as u16, TEXTURE_HEIGHT as u16,
+                                                   false).unwrap();
+        factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                               &create_blade_texture(), None).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+
+        let instance_buffer = factory.create_buffer_static(&create_instances(&seed, count));
+        let vertex_data = create_blade();
+        let vertex_buffer = factory.create_buffer_static(&vertex_data);
+        let mesh = gfx::Mesh::from_format_instanced(vertex_buffer,
+                                                    vertex_data.len() as gfx::VertexCount,
+                                                    instance_buffer);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleStrip);
+        let program = factory.link_program(GRASS_VERTEX_SRC, GRASS_FRAGMENT_SRC).unwrap();
+        let data = GrassParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            cam_pos: [0.0; 3],
+            time: 0.0,
+            wind: [0.4, 0.2],
+            density: DENSITY_RANGE,
+            blade: (texture, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 300.0);
+    let start = Point3::new(0.0, -30.0, height(&seed, 0.0, -30.0) + 1.7);
+    let mut camera = FlyCamera::new(start, 0.5 * PI, -0.15, 5.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.65, 0.75, 0.9, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut wind_strength = 1.0f32;
+    let mut falloff = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut sim_time) = (0.0f32, 0.0f32);
+    let (mut frames, mut last_report, mut frame_time) = (0u32, time::precise_time_s(), 0.0);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) =>
+                    wind_strength = (wind_strength + 0.25).min(3.0),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) =>
+                    wind_strength = (wind_strength - 0.25).max(0.0),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::F)) =>
+                    falloff = !falloff,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = sim_time;
+            sim_time = clock.time() as f32;
+            camera.update(clock.step());
+            // stay above the ground
+            let ground = height(&seed, camera.position.x, camera.position.y) + 0.5;
+            camera.position.z = camera.position.z.max(ground);
+        }
+        let t = prev_time + (sim_time - prev_time) * clock.alpha();
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        let cam_pos = camera.position.into_fixed();
+        terrain.params.view_proj = view_proj;
+        terrain.params.cam_pos = cam_pos;
+        grass.params.view_proj = view_proj;
+        grass.params.cam_pos = cam_pos;
+        grass.params.time = t;
+        grass.params.wind = [0.4 * wind_strength, 0.2 * wind_strength];
+        // without the falloff, every blade is in the full density range
+        grass.params.density = if falloff { DENSITY_RANGE } else { [1e6, 2e6] };
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&terrain, &context), &wrap).unwrap();
+        renderer.draw_instanced(&(&grass, &context), count as u32, 0, &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        frames += 1;
+        let now = time::precise_time_s();
+        if now - last_report >= 0.5 {
+            frame_time = (now - last_report) / frames as f64;
+            frames = 0;
+            last_report = now;
+            wrap.window.set_title(&format!(
+                "Grass example: {} blades, falloff {}, wind {:.2}, {:.2} ms/frame",
+                count, if falloff { "on" } else { "off" }, wind_strength, 1000.0 * frame_time));
+        }
+    }
+}
@@ -0,0 +1,448 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of heat haze, bending the light above hot objects.
+//
+// Rendering happens in three passes:
+// First,  the scene is rendered to a texture.
+// Second, a billboard standing over each fire and chimney is rendered into
+//         a distortion texture, testing against the depth of the scene so
+//         that what stands in front of the hot air hides it. Each of them
+//         adds offsets made of noise rising with the air, fading out at the
+//         sides and on the way up, and getting smaller with the distance
+//         so that the shimmer keeps its size in the world.
+// Third,  a full-screen pass looks up the scene moved by those offsets.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press H to
+// toggle the haze, D to show the distortion texture, and Plus and Minus to
+// change its strength.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// position of the base, and width and height of the hot air over each
+// source: three fires and a chimney
+static SOURCES: [([f32; 3], [f32; 2]); 4] = [
+    ([-3.0, 0.0, 0.3], [1.2, 4.0]),
+    ([0.5, -1.5, 0.3], [1.0, 3.5]),
+    ([3.0, 1.0, 0.3], [1.4, 4.5]),
+    ([0.0, 5.0, 5.6], [1.2, 5.0]),
+];
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_Scale"]
+    scale: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 1 for a flame showing its color as it is, 0 for a lit surface
+    #[name = "u_Emissive"]
+    emissive: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct HazeParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Eye"]
+    eye: [f32; 3],
+    #[name = "u_Base"]
+    base: [f32; 3],
+    #[name = "u_Size"]
+    size: [f32; 2],
+    #[name = "u_Time"]
+    time: f32,
+    // offset in the noise, so the sources do not shimmer alike
+    #[name = "u_Seed"]
+    seed: f32,
+    #[name = "u_Strength"]
+    strength: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct CompositeParams<R: gfx::Resources> {
+    #[name = "t_Scene"]
+    scene: gfx::shade::TextureParam<R>,
+    #[name = "t_Distortion"]
+    distortion: gfx::shade::TextureParam<R>,
+    // 1 to show the distortion texture instead of the scene
+    #[name = "u_Show"]
+    show: f32,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    uniform vec3 u_Scale;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        v_World = u_Scale * a_Pos + u_Offset;
+        v_Normal = a_Normal / u_Scale;
+        gl_Position = u_ViewProj * vec4(v_World, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Emissive;
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.4, -0.6, 0.7))), 0.0);
+        // a checkerboard, with straight edges for the haze to bend
+        vec3 cell = floor(v_World * 2.0 + 0.01 * n);
+        float check = mod(cell.x + cell.y + cell.z, 2.0);
+        vec3 albedo = u_Color * (0.75 + 0.25 * check);
+        vec3 color = mix(albedo * (0.8 * diffuse + 0.3), u_Color, u_Emissive);
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+static HAZE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Eye;
+    uniform vec3 u_Base;
+    uniform vec2 u_Size;
+    in vec3 a_Pos;
+    out vec2 v_Local;
+    out float v_Distance;
+
+    void main() {
+        // turned around the vertical towards the eye
+        vec3 to_eye = u_Eye - u_Base;
+        vec3 right = normalize(vec3(-to_eye.y, to_eye.x, 0.0));
+        v_Local = vec2(a_Pos.x, a_Pos.y * 0.5 + 0.5);
+        vec3 world = u_Base + 0.5 * u_Size.x * v_Local.x * right
+                   + vec3(0.0, 0.0, u_Size.y * v_Local.y);
+        v_Distance = length(world - u_Eye);
+        gl_Position = u_ViewProj * vec4(world, 1.0);
+    }
+";
+
+static HAZE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec2 u_Size;
+    uniform float u_Time;
+    uniform float u_Seed;
+    uniform float u_Strength;
+    in vec2 v_Local;
+    in float v_Distance;
+    out vec4 o_Color;
+
+    float hash(vec2 p) {
+        return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+    }
+
+    float value_noise(vec2 p) {
+        vec2 i = floor(p), f = fract(p);
+        vec2 s = f * f * (3.0 - 2.0 * f);
+        return mix(mix(hash(i), hash(i + vec2(1.0, 0.0)), s.x),
+                   mix(hash(i + vec2(0.0, 1.0)), hash(i + vec2(1.0, 1.0)), s.x), s.y);
+    }
+
+    // two octaves, from -0.5 to 0.5
+    float noise(vec2 p) {
+        return 0.67 * value_noise(p) + 0.33 * value_noise(2.1 * p + 5.2) - 0.5;
+    }
+
+    void main() {
+        // the air rises, faster in the middle
+        vec2 p = 3.0 * vec2(0.5 * v_Local.x * u_Size.x, v_Local.y * u_Size.y);
+        p.y -= (2.0 + 1.5 * (1.0 - v_Local.x * v_Local.x)) * u_Time;
+        p += u_Seed;
+        vec2 offset = vec2(noise(p), noise(p + 31.7));
+        // strongest low in the middle, fading out at the sides and the top
+        float fade = (1.0 - v_Local.x * v_Local.x) * smoothstep(0.0, 0.1, v_Local.y)
+                   * (1.0 - v_Local.y);
+        o_Color = vec4(u_Strength * fade / v_Distance * offset, 0.0, 0.0);
+    }
+";
+
+static COMPOSITE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static COMPOSITE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Scene;
+    uniform sampler2D t_Distortion;
+    uniform float u_Show;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec2 offset = texture(t_Distortion, v_TexCoord).rg;
+        if (u_Show > 0.5) {
+            o_Color = vec4(0.5 + 20.0 * offset, 0.5, 1.0);
+        } else {
+            o_Color = vec4(texture(t_Scene, v_TexCoord + offset).rgb, 1.0);
+        }
+    }
+";
+
+fn create_target<R: gfx::Resources, F: Factory<R>>(
+                 width: gfx::tex::Size, height: gfx::tex::Size, format: gfx::tex::Format,
+                 factory: &mut F) -> gfx::TextureHandle<R> {
+    factory.create_texture(gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: format,
+    }).unwrap()
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Heat haze example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let scene_tex = create_target(w, h, gfx::tex::RGBA8, &mut factory);
+    // the offsets are signed, so they need a float target to add up
+    let distortion_tex = create_target(w, h, gfx::tex::Format::Float(
+        gfx::tex::Components::RG, gfx::attrib::FloatSize::F16), &mut factory);
+    let depth_tex = create_target(w, h, gfx::tex::Format::DEPTH24_STENCIL8, &mut factory);
+    let scene_frame = gfx::Frame {
+        colors: vec![Plane::Texture(scene_tex.clone(), 0, None)],
+        depth: Some(Plane::Texture(depth_tex.clone(), 0, None)),
+        .. gfx::Frame::empty(w, h)
+    };
+    let distortion_frame = gfx::Frame {
+        colors: vec![Plane::Texture(distortion_tex.clone(), 0, None)],
+        depth: Some(Plane::Texture(depth_tex, 0, None)),
+        .. gfx::Frame::empty(w, h)
+    };
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    // the shapes and the quad of the two full-screen passes, uploaded once
+    let mut meshes = MeshCache::new();
+    let mut batches = Vec::new();
+    for (mesh, slice) in vec![meshes.sphere(&mut factory, 16, 32), meshes.cube(&mut factory),
+                              meshes.plane(&mut factory)].into_iter() {
+        let data = SceneParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [0.0; 3],
+            scale: [1.0; 3],
+            color: [1.0; 3],
+            emissive: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+    }
+    let mut floor = batches.pop().unwrap();
+    floor.params.scale = [12.0, 12.0, 1.0];
+    floor.params.color = [0.45, 0.4, 0.35];
+    let mut cube = batches.pop().unwrap();
+    let mut flame = batches.pop().unwrap();
+    flame.params.emissive = 1.0;
+
+    // offset, scale and color of the boxes: a wall behind the fires, and a
+    // chimney under the last source
+    let boxes = [
+        ([0.0, 8.0, 3.0], [8.0, 0.3, 3.0], [0.7, 0.7, 0.75]),
+        ([0.0, 5.0, 2.8], [0.6, 0.6, 2.8], [0.55, 0.3, 0.25]),
+        ([-5.0, -3.0, 1.0], [1.0, 1.0, 1.0], [0.2, 0.4, 0.7]),
+    ];
+
+    let mut haze = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(HAZE_VERTEX_SRC, HAZE_FRAGMENT_SRC).unwrap();
+        let data = HazeParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            eye: [0.0; 3],
+            base: [0.0; 3],
+            size: [1.0; 2],
+            time: 0.0,
+            seed: 0.0,
+            strength: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        // added up over the sources, behind what the scene has in front
+        let state = gfx::DrawState::new()
+            .depth(gfx::state::Comparison::LessEqual, false)
+            .blend(gfx::BlendPreset::Add);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let mut composite = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(COMPOSITE_VERTEX_SRC, COMPOSITE_FRAGMENT_SRC)
+                             .unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = CompositeParams {
+            scene: (scene_tex, Some(sampler.clone())),
+            distortion: (distortion_tex, Some(sampler)),
+            show: 0.0,
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 1.0, 2.0), 12.0, -1.5, 0.2);
+
+    let clear_data = gfx::ClearData {
+        color: [0.55, 0.65, 0.8, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+    let distortion_clear = gfx::ClearData {
+        color: [0.0; 4],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut enabled = true;
+    let mut strength = 0.4f32;
+    let mut time = 0.0f32;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::H => enabled = !enabled,
+                    VirtualKeyCode::D => composite.params.show = 1.0 - composite.params.show,
+                    VirtualKeyCode::Add | VirtualKeyCode::Equals => strength *= 1.25,
+                    VirtualKeyCode::Subtract | VirtualKeyCode::Minus => strength *= 0.8,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            time = clock.time() as f32;
+        }
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &scene_frame);
+        floor.params.view_proj = view_proj;
+        renderer.draw(&(&floor, &context), &scene_frame).unwrap();
+        for &(offset, scale, color) in boxes.iter() {
+            cube.params.view_proj = view_proj;
+            cube.params.offset = offset;
+            cube.params.scale = scale;
+            cube.params.color = color;
+            renderer.draw(&(&cube, &context), &scene_frame).unwrap();
+        }
+        // the flames of the fires flicker
+        for (i, &(base, size)) in SOURCES.iter().take(3).enumerate() {
+            let flicker = 0.85 + 0.15 * (11.0 * time + 2.0 * i as f32).sin();
+            flame.params.view_proj = view_proj;
+            flame.params.offset = base;
+            flame.params.scale = [0.35 * size[0], 0.35 * size[0], 0.6 * size[0] * flicker];
+            flame.params.color = [1.0 * flicker, 0.55 * flicker, 0.15];
+            renderer.draw(&(&flame, &context), &scene_frame).unwrap();
+        }
+
+        renderer.clear(distortion_clear, gfx::COLOR, &distortion_frame);
+        if enabled {
+            haze.params.view_proj = view_proj;
+            haze.params.eye = camera.position().into_fixed();
+            haze.params.time = time;
+            haze.params.strength = strength;
+            for (i, &(base, size)) in SOURCES.iter().enumerate() {
+                haze.params.base = base;
+                haze.params.size = size;
+                haze.params.seed = 17.0 * i as f32;
+                renderer.draw(&(&haze, &context), &distortion_frame).unwrap();
+            }
+        }
+
+        renderer.draw(&(&composite, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Heat haze example: {}, strength {:.2}",
+                                       if enabled { "on" } else { "off" }, strength));
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Terrain as a grid of heights.
+//!
+//! A `Heightfield` samples a function of x and y at the corners of a square
+//! grid centered on the origin. `mesh` splits each square of the grid into two
+//! triangles along the same diagonal, and `height` interpolates over those
+//! exact triangles, so whatever stands on the terrain, like a
+//! `walk::Walker`, touches the surface that is drawn. Outside of the grid,
+//! `height` is a wall too high to walk over.
+//!
+//! ```ignore
+//! let terrain = Heightfield::new(256, 1.0, |x, y| 8.0 * perlin2(&seed, &[x / 40.0, y / 40.0]));
+//! let (vertex_data, index_data) = terrain.mesh();
+//! let z = terrain.height(10.0, -3.5);
+//! ```
+
+use shape::Vertex;
+
+/// Height returned outside of the grid.
+pub const OUTSIDE: f32 = 1.0e4;
+
+pub struct Heightfield {
+    /// Squares along each side.
+    pub segments: usize,
+    /// Size of a square.
+    pub spacing: f32,
+    heights: Vec<f32>,
+}
+
+impl Heightfield {
+    /// Samples `f` on a grid of `segments` squares of `spacing` a side.
+    pub fn new<F: Fn(f32, f32) -> f32>(segments: usize, spacing: f32, f: F) -> Heightfield {
+        let mut field = Heightfield {
+            segments: segments,
+            spacing: spacing,
+            heights: Vec::with_capacity((segments + 1) * (segments + 1)),
+        };
+        for j in 0 .. segments + 1 {
+            for i in 0 .. segments + 1 {
+                let (x, y) = field.corner(i, j);
+                field.heights.push(f(x, y));
+            }
+        }
+        field
+    }
+
+    /// Width of the whole grid.
+    pub fn size(&self) -> f32 {
+        self.segments as f32 * self.spacing
+    }
+
+    /// The heights at the corners, row by row from the lowest y, with
+    /// `segments + 1` corners in each row.
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    /// The heights at the corners, to change the terrain in place. `mesh`
+    /// has to be called again to see the changes.
+    pub fn heights_mut(&mut self) -> &mut [f32] {
+        &mut self.heights
+    }
+
+    // position of a corner
+    fn corner(&self, i: usize, j: usize) -> (f32, f32) {
+        let half = 0.5 * self.segments as f32;
+        ((i as f32 - half) * self.spacing, (j as f32 - half) * self.spacing)
+    }
+
+    fn at(&self, i: usize, j: usize) -> f32 {
+        self.heights[j * (self.segments + 1) + i]
+    }
+
+    /// The height of the triangle under a point, or `OUTSIDE`.
+    pub fn height(&self, x: f32, y: f32) -> f32 {
+        let n = self.segments as f32;
+        let (u, v) = (x / self.spacing + 0.5 * n, y / self.spacing + 0.5 * n);
+        if u < 0.0 || v < 0.0 || u >= n || v >= n {
+            return OUTSIDE;
+        }
+        let (i, j) = (u as usize, v as usize);
+        let (fx, fy) = (u - i as f32, v - j as f32);
+        // split along the diagonal from (i, j) to (i + 1, j + 1)
+        let (h00, h11) = (self.at(i, j), self.at(i + 1, j + 1));
+        if fx > fy {
+            let h10 = self.at(i + 1, j);
+            h00 + fx * (h10 - h00) + fy * (h11 - h10)
+        } else {
+            let h01 = self.at(i, j + 1);
+            h00 + fy * (h01 - h00) + fx * (h11 - h01)
+        }
+    }
+
+    /// The triangles of the grid, with smooth normals and texture coordinates
+    /// from 0 to 1 across it.
+    pub fn mesh(&self) -> (Vec<Vertex>, Vec<u32>) {
+        let (n, row) = (self.segments, self.segments + 1);
+        let mut vertices = Vec::with_capacity(row * row);
+        for j in 0 .. row {
+            for i in 0 .. row {
+                let (x, y) = self.corner(i, j);
+                let (left, right) = (self.at(i.saturating_sub(1), j), self.at((i + 1).min(n), j));
+                let (down, up) = (self.at(i, j.saturating_sub(1)), self.at(i, (j + 1).min(n)));
+                let normal = [left - right, down - up, 2.0 * self.spacing];
+                let length = (normal[0] * normal[0] + normal[1] * normal[1] +
+                              normal[2] * normal[2]).sqrt();
+                vertices.push(Vertex {
+                    pos: [x, y, self.at(i, j)],
+                    normal: [normal[0] / length, normal[1] / length, normal[2] / length],
+                    tex_coord: [i as f32 / n as f32, j as f32 / n as f32],
+                });
+            }
+        }
+        let mut indices = Vec::with_capacity(6 * n * n);
+        for j in 0 .. n {
+            for i in 0 .. n {
+                let (a, r) = ((j * row + i) as u32, row as u32);
+                indices.extend([a, a + 1, a + r + 1, a, a + r + 1, a + r].iter().cloned());
+            }
+        }
+        (vertices, indices)
+    }
+}
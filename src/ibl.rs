@@ -0,0 +1,311 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Image based lighting.
+//!
+//! `Ibl::new` prefilters an environment cubemap for the split sum
+//! approximation of the GGX specular lobe, with one mipmap level of
+//! `specular` per roughness, and convolves it with the cosine lobe into
+//! `irradiance` for the diffuse light. `brdf` holds the scale and bias to
+//! apply to the Fresnel reflectance at normal incidence, indexed by the
+//! cosine of the view angle and the roughness. A shader combines them as:
+//!
+//! ```ignore
+//! vec2 ab = texture(t_Brdf, vec2(n_dot_v, roughness)).rg;
+//! vec3 spec = textureLod(t_Specular, r, roughness * u_MaxLod).rgb * (f0 * ab.x + ab.y);
+//! vec3 diffuse = texture(t_Irradiance, n).rgb * albedo;
+//! ```
+//!
+//! Every pass draws the quad of the `mesh_cache::MeshCache` given to
+//! `Ibl::new`. All the passes are only recorded in the renderer given to it,
+//! and run when it is submitted, which has to happen before the next
+//! `factory.cleanup()` frees their programs.
+
+use gfx;
+use gfx::traits::*;
+use gfx::Plane;
+use cubemap;
+use mesh_cache::MeshCache;
+
+/// Size of the most detailed face of `specular`.
+pub const SPECULAR_SIZE: u16 = 128;
+/// Number of levels of `specular`, from a mirror to a roughness of one.
+pub const SPECULAR_LEVELS: u8 = 6;
+const IRRADIANCE_SIZE: u16 = 32;
+const BRDF_SIZE: u16 = 128;
+
+#[shader_param]
+struct FilterParams<R: gfx::Resources> {
+    #[name = "u_Face"]
+    face: f32,
+    #[name = "u_Roughness"]
+    roughness: f32,
+    // texels across a face of the environment
+    #[name = "u_EnvSize"]
+    env_size: f32,
+    #[name = "t_Env"]
+    env: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct BrdfParams<R: gfx::Resources> {
+    _dummy: ::std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_Ndc;
+
+    void main() {
+        v_Ndc = a_Pos.xy;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+// The direction through a point of a face, matching `cubemap::direction`,
+// and the GGX sampling shared by the two specular passes
+macro_rules! common_src {
+    () => ("
+    #version 150 core
+
+    const float PI = 3.14159265;
+    const uint SAMPLES = 1024u;
+
+    vec3 face_direction(int face, vec2 st) {
+        if (face == 0) return vec3(1.0, -st.y, -st.x);
+        if (face == 1) return vec3(-1.0, -st.y, st.x);
+        if (face == 2) return vec3(st.x, 1.0, st.y);
+        if (face == 3) return vec3(st.x, -1.0, -st.y);
+        if (face == 4) return vec3(st.x, -st.y, 1.0);
+        return vec3(-st.x, -st.y, -1.0);
+    }
+
+    // a low discrepancy sequence, covering the square more evenly than
+    // random points
+    vec2 hammersley(uint i) {
+        uint bits = (i << 16u) | (i >> 16u);
+        bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+        bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+        bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+        bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+        return vec2(float(i) / float(SAMPLES), float(bits) * 2.3283064365386963e-10);
+    }
+
+    // a half vector around `n`, distributed as the GGX lobe of roughness `a`
+    vec3 sample_ggx(vec2 xi, vec3 n, float a) {
+        float phi = 2.0 * PI * xi.x;
+        float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+        float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+        vec3 t = normalize(cross(up, n));
+        vec3 b = cross(n, t);
+        return normalize(sin_theta * cos(phi) * t + sin_theta * sin(phi) * b + cos_theta * n);
+    }
+    ")
+}
+
+static SPECULAR_FRAGMENT_SRC: &'static str = concat!(common_src!(), "
+    uniform float u_Face;
+    uniform float u_Roughness;
+    uniform float u_EnvSize;
+    uniform samplerCube t_Env;
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    void main() {
+        // the view is assumed to come along the normal, as the split sum
+        // approximation has it
+        vec3 n = normalize(face_direction(int(u_Face + 0.5), v_Ndc));
+        float a = u_Roughness * u_Roughness;
+        vec3 sum = vec3(0.0);
+        float weight = 0.0;
+        for (uint i = 0u; i < SAMPLES; ++i) {
+            vec3 h = sample_ggx(hammersley(i), n, a);
+            vec3 l = 2.0 * dot(n, h) * h - n;
+            float n_dot_l = dot(n, l);
+            if (n_dot_l > 0.0) {
+                // read from a blurrier level when a sample stands for a larger
+                // solid angle than a texel, instead of aliasing
+                float n_dot_h = max(dot(n, h), 0.0);
+                float d = (a * a) / (PI * pow(n_dot_h * n_dot_h * (a * a - 1.0) + 1.0, 2.0));
+                float sample_angle = 4.0 / (float(SAMPLES) * d + 1e-4);
+                float texel_angle = 4.0 * PI / (6.0 * u_EnvSize * u_EnvSize);
+                float lod = u_Roughness == 0.0 ? 0.0
+                          : 0.5 * log2(sample_angle / texel_angle) + 1.0;
+                sum += textureLod(t_Env, l, lod).rgb * n_dot_l;
+                weight += n_dot_l;
+            }
+        }
+        o_Color = vec4(sum / weight, 1.0);
+    }
+");
+
+static IRRADIANCE_FRAGMENT_SRC: &'static str = concat!(common_src!(), "
+    const float STEP = 0.04;
+
+    uniform float u_Face;
+    uniform float u_EnvSize;
+    uniform samplerCube t_Env;
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(face_direction(int(u_Face + 0.5), v_Ndc));
+        vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+        vec3 t = normalize(cross(up, n));
+        vec3 b = cross(n, t);
+        // a level with about as many texels as the steps over a face
+        float lod = max(log2(u_EnvSize * STEP), 0.0);
+        vec3 sum = vec3(0.0);
+        float count = 0.0;
+        for (float phi = 0.0; phi < 2.0 * PI; phi += STEP) {
+            for (float theta = 0.0; theta < 0.5 * PI; theta += STEP) {
+                vec3 l = sin(theta) * (cos(phi) * t + sin(phi) * b) + cos(theta) * n;
+                // the cosine law, and the sine for the smaller rings near the pole
+                sum += textureLod(t_Env, l, lod).rgb * cos(theta) * sin(theta);
+                count += 1.0;
+            }
+        }
+        o_Color = vec4(PI * sum / count, 1.0);
+    }
+");
+
+static BRDF_FRAGMENT_SRC: &'static str = concat!(common_src!(), "
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    void main() {
+        vec2 uv = 0.5 + 0.5 * v_Ndc;
+        float n_dot_v = max(uv.x, 1e-3);
+        float roughness = uv.y;
+        float a = roughness * roughness;
+        // Smith's geometry term, with the remapping of k for image based light
+        float k = 0.5 * a;
+        vec3 n = vec3(0.0, 0.0, 1.0);
+        vec3 v = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+        vec2 sum = vec2(0.0);
+        for (uint i = 0u; i < SAMPLES; ++i) {
+            vec3 h = sample_ggx(hammersley(i), n, a);
+            vec3 l = 2.0 * dot(v, h) * h - v;
+            float n_dot_l = max(l.z, 0.0);
+            if (n_dot_l > 0.0) {
+                float n_dot_h = max(h.z, 0.0);
+                float v_dot_h = max(dot(v, h), 0.0);
+                float g = n_dot_v / (n_dot_v * (1.0 - k) + k)
+                        * n_dot_l / (n_dot_l * (1.0 - k) + k);
+                float visibility = g * v_dot_h / (n_dot_h * n_dot_v);
+                float fc = pow(1.0 - v_dot_h, 5.0);
+                sum += vec2(1.0 - fc, fc) * visibility;
+            }
+        }
+        o_Color = vec4(sum / float(SAMPLES), 0.0, 1.0);
+    }
+");
+
+/// The lighting of an environment, prefiltered for each kind of surface.
+pub struct Ibl<R: gfx::Resources> {
+    /// The environment blurred by the specular lobe of a roughness going
+    /// from 0 at level 0 to 1 at the last of `SPECULAR_LEVELS`.
+    pub specular: gfx::TextureHandle<R>,
+    /// The light reaching a diffuse surface facing each direction.
+    pub irradiance: gfx::TextureHandle<R>,
+    /// The scale and bias of the Fresnel reflectance, in red and green.
+    pub brdf: gfx::TextureHandle<R>,
+}
+
+// Draws `batch` into every level and face of a cube, with the roughness of
+// each level
+fn filter<R, C>(renderer: &mut gfx::Renderer<R, C>,
+                batch: &mut gfx::batch::OwnedBatch<FilterParams<R>>,
+                cube: &gfx::TextureHandle<R>, size: u16, levels: u8)
+                where R: gfx::Resources, C: gfx::CommandBuffer<R> {
+    for level in 0 .. levels {
+        let level_size = size >> level;
+        batch.params.roughness = level as f32 / (levels - 1).max(1) as f32;
+        for face in 0 .. 6 {
+            let frame = gfx::Frame {
+                colors: vec![Plane::Texture(cube.clone(), level, Some(face))],
+                .. gfx::Frame::empty(level_size, level_size)
+            };
+            batch.params.face = face as f32;
+            renderer.draw(batch, &frame).unwrap();
+        }
+    }
+}
+
+impl<R: gfx::Resources> Ibl<R> {
+    pub fn new<F, C>(factory: &mut F, meshes: &mut MeshCache<R>,
+                     renderer: &mut gfx::Renderer<R, C>,
+                     env: &gfx::TextureHandle<R>) -> Ibl<R>
+                     where F: gfx::Factory<R>, C: gfx::CommandBuffer<R> {
+        let env_size = env.get_info().width as f32;
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+
+        let specular = cubemap::create(factory, SPECULAR_SIZE, SPECULAR_LEVELS);
+        let irradiance = cubemap::create(factory, IRRADIANCE_SIZE, 1);
+        for &(cube, size, levels, src) in [(&specular, SPECULAR_SIZE, SPECULAR_LEVELS,
+                                            SPECULAR_FRAGMENT_SRC),
+                                           (&irradiance, IRRADIANCE_SIZE, 1,
+                                            IRRADIANCE_FRAGMENT_SRC)].iter() {
+            let (mesh, _) = meshes.quad(factory);
+            let program = factory.link_program(VERTEX_SRC, src.as_bytes()).unwrap();
+            let data = FilterParams {
+                face: 0.0,
+                roughness: 0.0,
+                env_size: env_size,
+                env: (env.clone(), Some(sampler.clone())),
+            };
+            let mut batch = gfx::batch::OwnedBatch::new(mesh, program, data).unwrap();
+            filter(renderer, &mut batch, cube, size, levels);
+        }
+
+        let brdf = factory.create_texture(gfx::tex::TextureInfo {
+            width: BRDF_SIZE,
+            height: BRDF_SIZE,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::Format::Float(gfx::tex::Components::RGBA,
+                                            gfx::attrib::FloatSize::F32),
+        }).unwrap();
+        {
+            let (mesh, _) = meshes.quad(factory);
+            let program = factory.link_program(VERTEX_SRC, BRDF_FRAGMENT_SRC.as_bytes())
+                                 .unwrap();
+            let data = BrdfParams { _dummy: ::std::marker::PhantomData };
+            let batch = gfx::batch::OwnedBatch::new(mesh, program, data).unwrap();
+            let frame = gfx::Frame {
+                colors: vec![Plane::Texture(brdf.clone(), 0, None)],
+                .. gfx::Frame::empty(BRDF_SIZE, BRDF_SIZE)
+            };
+            renderer.draw(&batch, &frame).unwrap();
+        }
+
+        Ibl {
+            specular: specular,
+            irradiance: irradiance,
+            brdf: brdf,
+        }
+    }
+
+    /// The highest level of `specular`, for a roughness of one.
+    pub fn max_lod(&self) -> f32 {
+        (SPECULAR_LEVELS - 1) as f32
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of image based lighting.
+//
+// At startup, `gfx_examples::ibl` prefilters a high dynamic range
+// environment: a cubemap with a level for each roughness of the specular
+// reflection, a small cubemap of the diffuse irradiance, and a table of the
+// specular response. The test objects then take all of their light from
+// these, with a few lookups per pixel.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press Left and
+// Right to select an object, Up and Down to change its roughness, M to make
+// it metallic or not, B to show the environment, the prefiltered level of the
+// selected object or the irradiance as the background, and Plus and Minus to
+// change the exposure.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::env;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::cubemap::{self, Skybox};
+use gfx_examples::ibl::Ibl;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_Albedo"]
+    albedo: [f32; 3],
+    #[name = "u_Roughness"]
+    roughness: f32,
+    #[name = "u_Metalness"]
+    metalness: f32,
+    #[name = "u_MaxLod"]
+    max_lod: f32,
+    #[name = "u_Exposure"]
+    exposure: f32,
+    #[name = "t_Specular"]
+    specular: gfx::shade::TextureParam<R>,
+    #[name = "t_Irradiance"]
+    irradiance: gfx::shade::TextureParam<R>,
+    #[name = "t_Brdf"]
+    brdf: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Pos = a_Pos + u_Offset;
+        v_Normal = a_Normal;
+        gl_Position = u_ViewProj * vec4(v_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_CamPos;
+    uniform vec3 u_Albedo;
+    uniform float u_Roughness;
+    uniform float u_Metalness;
+    uniform float u_MaxLod;
+    uniform float u_Exposure;
+    uniform samplerCube t_Specular;
+    uniform samplerCube t_Irradiance;
+    uniform sampler2D t_Brdf;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 v = normalize(u_CamPos - v_Pos);
+        float n_dot_v = max(dot(n, v), 1e-3);
+        // metals tint their reflection and have no diffuse light
+        vec3 f0 = mix(vec3(0.04), u_Albedo, u_Metalness);
+        // rough surfaces don't turn into mirrors as much at grazing angles
+        vec3 fresnel = f0 + (max(vec3(1.0 - u_Roughness), f0) - f0) * pow(1.0 - n_dot_v, 5.0);
+        vec3 diffuse = (1.0 - fresnel) * (1.0 - u_Metalness) * u_Albedo
+                     * texture(t_Irradiance, n).rgb;
+        vec2 brdf = texture(t_Brdf, vec2(n_dot_v, u_Roughness)).rg;
+        vec3 specular = textureLod(t_Specular, reflect(-v, n), u_Roughness * u_MaxLod).rgb
+                      * (f0 * brdf.x + brdf.y);
+        o_Color = vec4(1.0 - exp(-u_Exposure * (diffuse + specular)), 1.0);
+    }
+";
+
+struct Material {
+    albedo: [f32; 3],
+    roughness: f32,
+    metal: bool,
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Image based lighting example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let env = match env::args().nth(1) {
+        Some(dir) => cubemap::load(&mut factory, &dir).unwrap_or_else(|e| {
+            println!("{}, generating the environment", e);
+            cubemap::generate(&mut factory, 512, cubemap::environment)
+        }),
+        None => cubemap::generate(&mut factory, 512, cubemap::environment),
+    };
+    let mut meshes = MeshCache::new();
+    let ibl = Ibl::new(&mut factory, &mut meshes, &mut renderer, &env);
+    device.submit(renderer.as_buffer());
+    renderer.reset();
+
+    let mut skybox = Skybox::new(&mut factory, &mut meshes, env.clone());
+    let trilinear = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+    let bilinear = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let mut materials = vec![
+        Material { albedo: [1.0, 0.78, 0.34], roughness: 0.3, metal: true },
+        Material { albedo: [0.7, 0.1, 0.08], roughness: 0.45, metal: false },
+        Material { albedo: [0.95, 0.95, 0.95], roughness: 0.05, metal: true },
+        Material { albedo: [0.15, 0.3, 0.6], roughness: 0.9, metal: false },
+    ];
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut batches = Vec::new();
+    // the two spheres share their buffers
+    let shapes = vec![meshes.sphere(&mut factory, 48, 96),
+                      meshes.torus(&mut factory, 96, 48, 0.65, 0.3),
+                      meshes.cube(&mut factory), meshes.sphere(&mut factory, 48, 96)];
+    for (i, (mesh, slice)) in shapes.into_iter().enumerate() {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [2.5 * i as f32 - 3.75, 0.0, 0.0],
+            cam_pos: [0.0; 3],
+            albedo: [1.0; 3],
+            roughness: 0.5,
+            metalness: 0.0,
+            max_lod: ibl.max_lod(),
+            exposure: 1.0,
+            specular: (ibl.specular.clone(), Some(trilinear.clone())),
+            irradiance: (ibl.irradiance.clone(), Some(bilinear.clone())),
+            brdf: (ibl.brdf.clone(), Some(bilinear.clone())),
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state)
+                            .unwrap());
+    }
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 8.0, -1.4, 0.25);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut selected = 0;
+    // 0 for the environment, 1 for the prefiltered level and 2 for the irradiance
+    let mut background = 0;
+    let mut exposure = 1.0f32;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let material = &mut materials[selected];
+                    match key {
+                        VirtualKeyCode::Right => selected = (selected + 1) % batches.len(),
+                        VirtualKeyCode::Left =>
+                            selected = (selected + batches.len() - 1) % batches.len(),
+                        VirtualKeyCode::Up =>
+                            material.roughness = (material.roughness + 0.05).min(1.0),
+                        VirtualKeyCode::Down =>
+                            material.roughness = (material.roughness - 0.05).max(0.0),
+                        VirtualKeyCode::M => material.metal = !material.metal,
+                        VirtualKeyCode::B => background = (background + 1) % 3,
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals => exposure *= 1.25,
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus => exposure *= 0.8,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let view = camera.view().mat;
+        let view_proj = proj.mul_m(&view).into_fixed();
+        let cam_pos = camera.position().into_fixed();
+        for (batch, material) in batches.iter_mut().zip(materials.iter()) {
+            batch.params.view_proj = view_proj;
+            batch.params.cam_pos = cam_pos;
+            batch.params.albedo = material.albedo;
+            batch.params.roughness = material.roughness;
+            batch.params.metalness = if material.metal { 1.0 } else { 0.0 };
+            batch.params.exposure = exposure;
+        }
+
+        let roughness = materials[selected].roughness;
+        match background {
+            0 => {
+                skybox.set_cubemap(env.clone());
+                skybox.lod = 0.0;
+            },
+            1 => {
+                skybox.set_cubemap(ibl.specular.clone());
+                skybox.lod = roughness * ibl.max_lod();
+            },
+            _ => {
+                skybox.set_cubemap(ibl.irradiance.clone());
+                skybox.lod = 0.0;
+            },
+        }
+        skybox.exposure = exposure;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for batch in batches.iter() {
+            renderer.draw(&(batch, &context), &wrap).unwrap();
+        }
+        skybox.draw(&mut renderer, &wrap, &proj, &view);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Image based lighting example: object {} of {}, roughness {:.2}, {}",
+            selected + 1, batches.len(), roughness,
+            if materials[selected].metal { "metal" } else { "dielectric" }));
+    }
+}
@@ -0,0 +1,530 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of rendering a forest with billboards and impostors.
+//
+// At startup a tree mesh is rendered from eight sides into an impostor atlas,
+// and from eight sides again at each of a few heights above it. Trees far
+// from the camera are then drawn as billboards textured with the tile of the
+// atlas closest to their viewing angle, while trees close to the camera are
+// drawn with the real mesh.
+//
+// Press B to switch between axis-aligned and camera-facing billboards, Up and
+// Down to move the distance at which trees switch to the mesh.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// Remember to also change the constants in the billboard shader
+const NUM_VIEWS: usize = 8;
+// rows of views, from the side up to MAX_ELEVATION above the tree
+const NUM_ELEVATIONS: usize = 3;
+const MAX_ELEVATION: f32 = PI / 3.0;
+const TILE_SIZE: gfx::tex::Size = 256;
+// Extent of the tree in the impostor tiles, around the middle of its height
+const IMPOSTOR_WIDTH: f32 = 6.4;
+const IMPOSTOR_CENTER: f32 = 2.9;
+
+const FOREST_SIZE: usize = 64;
+const TREE_SPACING: f32 = 3.0;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+    #[name = "a_Color"]
+    color: [f32; 3],
+}
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct BillboardVertex {
+    #[name = "a_Center"]
+    center: [f32; 3],
+    #[name = "a_Corner"]
+    corner: [f32; 2],
+    // scale and rotation around Z of the tree
+    #[name = "a_ScaleYaw"]
+    scale_yaw: [f32; 2],
+}
+
+#[shader_param]
+struct MeshParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    // scale and offset of clip space X, then of Y, to render into an atlas tile
+    #[name = "u_Tile"]
+    tile: [f32; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct BillboardParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_CameraPos"]
+    camera_pos: [f32; 3],
+    #[name = "u_Spherical"]
+    spherical: f32,
+    #[name = "u_MeshDistance"]
+    mesh_distance: f32,
+    #[name = "t_Atlas"]
+    atlas: gfx::shade::TextureParam<R>,
+}
+
+static MESH_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    uniform vec4 u_Tile;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec3 a_Color;
+    out vec3 v_Normal;
+    out vec3 v_Color;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_Color = a_Color;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+        gl_Position.xy = gl_Position.xy * u_Tile.xz + u_Tile.yw * gl_Position.w;
+    }
+";
+
+static MESH_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    in vec3 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.4, -0.3, 0.9))));
+        o_Color = vec4(v_Color * (0.3 + 0.7 * d), 1.0);
+    }
+";
+
+static BILLBOARD_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float NUM_VIEWS = 8.0;
+    const float NUM_ELEVATIONS = 3.0;
+    const float MAX_ELEVATION = 1.0471976;
+    const float WIDTH = 6.4;
+    const float CENTER = 2.9;
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_CameraPos;
+    uniform float u_Spherical;
+    uniform float u_MeshDistance;
+    in vec3 a_Center;
+    in vec2 a_Corner;
+    in vec2 a_ScaleYaw;
+    out vec2 v_TexCoord;
+
+    void main() {
+        vec3 center = a_Center + vec3(0.0, 0.0, a_ScaleYaw.x * CENTER);
+        vec3 to_camera = u_CameraPos - center;
+
+        // pick the atlas tile baked from the closest direction, only from the
+        // side for upright billboards
+        float angle = atan(to_camera.y, to_camera.x) - a_ScaleYaw.y;
+        float view = mod(floor(angle / 6.2831853 * NUM_VIEWS + 0.5), NUM_VIEWS);
+        float elevation = atan(to_camera.z, length(to_camera.xy));
+        float row = clamp(floor(elevation / MAX_ELEVATION * (NUM_ELEVATIONS - 1.0) + 0.5),
+                          0.0, NUM_ELEVATIONS - 1.0) * u_Spherical;
+        v_TexCoord = vec2((view + 0.5 + 0.5 * a_Corner.x) / NUM_VIEWS,
+                          (row + 0.5 + 0.5 * a_Corner.y) / NUM_ELEVATIONS);
+
+        // face the camera like the baking camera faced the tree, upright
+        // unless the billboards face it fully
+        vec3 right = normalize(vec3(-to_camera.y, to_camera.x, 0.0));
+        vec3 up = vec3(0.0, 0.0, 1.0);
+        if (u_Spherical > 0.5) {
+            up = normalize(cross(normalize(to_camera), right));
+        }
+        vec2 offset = 0.5 * WIDTH * a_Corner;
+        vec3 pos = center + a_ScaleYaw.x * (right * offset.x + up * offset.y);
+        gl_Position = u_ViewProj * vec4(pos, 1.0);
+
+        // close trees are drawn with the mesh instead
+        if (length(to_camera.xy) < u_MeshDistance) {
+            gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        }
+    }
+";
+
+static BILLBOARD_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Atlas;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 color = texture(t_Atlas, v_TexCoord);
+        if (color.a < 0.5) {
+            discard;
+        }
+        o_Color = color;
+    }
+";
+
+fn cylinder(z: f32, radius: f32, height: f32, color: [f32; 3],
+            vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>) {
+    let segments = 8;
+    let start = vertices.len() as u16;
+    for s in 0 .. segments {
+        let phi = (s as f32) / (segments as f32) * 2.0 * PI;
+        let (x, y) = (phi.cos(), phi.sin());
+        for &dz in [0.0, height].iter() {
+            vertices.push(Vertex {
+                pos: [radius * x, radius * y, z + dz],
+                normal: [x, y, 0.0],
+                color: color,
+            });
+        }
+    }
+    for s in 0 .. segments {
+        let i0 = start + 2 * s;
+        let i1 = start + 2 * ((s + 1) % segments);
+        indices.extend([i0, i1, i1 + 1, i1 + 1, i0 + 1, i0].iter().cloned());
+    }
+}
+
+fn cone(z: f32, radius: f32, height: f32, color: [f32; 3],
+        vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>) {
+    let segments = 12;
+    let start = vertices.len() as u16;
+    for s in 0 .. segments + 1 {
+        let phi = (s as f32) / (segments as f32) * 2.0 * PI;
+        let (x, y) = (phi.cos(), phi.sin());
+        let normal = Vector3::new(x * height, y * height, radius).normalize().into_fixed();
+        vertices.push(Vertex { pos: [radius * x, radius * y, z], normal: normal, color: color });
+        vertices.push(Vertex { pos: [0.0, 0.0, z + height], normal: normal, color: color });
+    }
+    for s in 0 .. segments {
+        let i = start + 2 * s;
+        indices.extend([i, i + 2, i + 1].iter().cloned());
+    }
+}
+
+// A fir tree: a trunk and three stacked cones
+fn create_tree() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    cylinder(0.0, 0.25, 1.5, [0.4, 0.25, 0.1], &mut vertices, &mut indices);
+    cone(1.2, 2.0, 2.2, [0.1, 0.4, 0.15], &mut vertices, &mut indices);
+    cone(2.4, 1.6, 2.2, [0.15, 0.45, 0.15], &mut vertices, &mut indices);
+    cone(3.6, 1.2, 2.2, [0.2, 0.5, 0.2], &mut vertices, &mut indices);
+    (vertices, indices)
+}
+
+fn create_ground() -> (Vec<Vertex>, Vec<u16>) {
+    let half = FOREST_SIZE as f32 * TREE_SPACING;
+    let color = [0.3, 0.35, 0.15];
+    let vertices = [(-half, -half), (half, -half), (half, half), (-half, half)]
+        .iter().map(|&(x, y)| Vertex {
+            pos: [x, y, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            color: color,
+        }).collect();
+    (vertices, vec![0, 1, 2, 2, 3, 0])
+}
+
+struct Tree {
+    pos: [f32; 3],
+    scale: f32,
+    yaw: f32,
+}
+
+fn create_forest() -> Vec<Tree> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let offset = 0.5 * (FOREST_SIZE - 1) as f32 * TREE_SPACING;
+    let mut trees = Vec::new();
+    for i in 0 .. FOREST_SIZE {
+        for j in 0 .. FOREST_SIZE {
+            let jx = 0.8 * TREE_SPACING * (rng.gen::<f32>() - 0.5);
+            let jy = 0.8 * TREE_SPACING * (rng.gen::<f32>() - 0.5);
+            trees.push(Tree {
+                pos: [i as f32 * TREE_SPACING - offset + jx,
+                      j as f32 * TREE_SPACING - offset + jy,
+                      0.0],
+                scale: 0.7 + 0.6 * rng.gen::<f32>(),
+                yaw: 2.0 * PI * rng.gen::<f32>(),
+            });
+        }
+    }
+    trees
+}
+
+fn model_matrix(tree: &Tree) -> Matrix4<f32> {
+    let mut model = Matrix3::from_angle_z(cgmath::rad(tree.yaw)).mul_s(tree.scale).to_matrix4();
+    model.w = Vector4::new(tree.pos[0], tree.pos[1], tree.pos[2], 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Impostor example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mesh_program = factory.link_program(MESH_VERTEX_SRC, MESH_FRAGMENT_SRC)
+                              .unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let mut tree_batch = {
+        let (vertex_data, index_data) = create_tree();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let data = MeshParams {
+            model: Matrix4::identity().into_fixed(),
+            view_proj: Matrix4::identity().into_fixed(),
+            tile: [1.0, 0.0, 1.0, 0.0],
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&mesh_program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let mut ground_batch = {
+        let (vertex_data, index_data) = create_ground();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let data = MeshParams {
+            model: Matrix4::identity().into_fixed(),
+            view_proj: Matrix4::identity().into_fixed(),
+            tile: [1.0, 0.0, 1.0, 0.0],
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&mesh_program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    // Bake the impostor atlas, a row of tiles around the tree per elevation
+    let texture_atlas = factory.create_texture(gfx::tex::TextureInfo {
+        width: TILE_SIZE * NUM_VIEWS as gfx::tex::Size,
+        height: TILE_SIZE * NUM_ELEVATIONS as gfx::tex::Size,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::RGBA8,
+    }).unwrap();
+    let texture_atlas_depth = factory.create_texture(gfx::tex::TextureInfo {
+        width: TILE_SIZE * NUM_VIEWS as gfx::tex::Size,
+        height: TILE_SIZE * NUM_ELEVATIONS as gfx::tex::Size,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    {
+        let frame = gfx::Frame {
+            colors: vec![Plane::Texture(texture_atlas.clone(), 0, None)],
+            depth: Some(Plane::Texture(texture_atlas_depth.clone(), 0, None)),
+            .. gfx::Frame::empty(TILE_SIZE * NUM_VIEWS as gfx::tex::Size,
+                                 TILE_SIZE * NUM_ELEVATIONS as gfx::tex::Size)
+        };
+        let clear_data = gfx::ClearData {
+            color: [0.0, 0.0, 0.0, 0.0],
+            depth: 1.0,
+            stencil: 0,
+        };
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &frame);
+
+        let half = 0.5 * IMPOSTOR_WIDTH;
+        let proj = cgmath::ortho(-half, half, -half, half, 0.1, 20.0);
+        let (sx, sy) = (1.0 / NUM_VIEWS as f32, 1.0 / NUM_ELEVATIONS as f32);
+        for j in 0 .. NUM_ELEVATIONS {
+            let elevation = j as f32 / (NUM_ELEVATIONS - 1) as f32 * MAX_ELEVATION;
+            for i in 0 .. NUM_VIEWS {
+                let angle = (i as f32) / (NUM_VIEWS as f32) * 2.0 * PI;
+                let view: AffineMatrix3<f32> = Transform::look_at(
+                    &Point3::new(10.0 * elevation.cos() * angle.cos(),
+                                 10.0 * elevation.cos() * angle.sin(),
+                                 IMPOSTOR_CENTER + 10.0 * elevation.sin()),
+                    &Point3::new(0.0, 0.0, IMPOSTOR_CENTER),
+                    &Vector3::unit_z(),
+                );
+                tree_batch.params.view_proj = proj.mul_m(&view.mat).into_fixed();
+                tree_batch.params.tile = [sx, -1.0 + (2 * i + 1) as f32 * sx,
+                                          sy, -1.0 + (2 * j + 1) as f32 * sy];
+                renderer.draw(&(&tree_batch, &context), &frame).unwrap();
+            }
+        }
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+        tree_batch.params.tile = [1.0, 0.0, 1.0, 0.0];
+    }
+
+    let forest = create_forest();
+
+    let mut billboard_batch = {
+        let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0],
+                       [1.0, 1.0], [-1.0, 1.0], [-1.0, -1.0]];
+        let vertex_data: Vec<BillboardVertex> = forest.iter().flat_map(|tree| {
+            corners.iter().map(move |&corner| BillboardVertex {
+                center: tree.pos,
+                corner: corner,
+                scale_yaw: [tree.scale, tree.yaw],
+            })
+        }).collect();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(BILLBOARD_VERTEX_SRC, BILLBOARD_FRAGMENT_SRC)
+                             .unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = BillboardParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            camera_pos: [0.0; 3],
+            spherical: 1.0,
+            mesh_distance: 20.0,
+            atlas: (texture_atlas.clone(), Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 300.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.7, 0.8, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::B)) => {
+                    let params = &mut billboard_batch.params;
+                    params.spherical = 1.0 - params.spherical;
+                    println!("{} billboards",
+                             if params.spherical > 0.5 { "camera-facing" } else { "axis-aligned" });
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) => {
+                    billboard_batch.params.mesh_distance += 5.0;
+                    println!("mesh distance: {}", billboard_batch.params.mesh_distance);
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) => {
+                    let distance = (billboard_batch.params.mesh_distance - 5.0).max(0.0);
+                    billboard_batch.params.mesh_distance = distance;
+                    println!("mesh distance: {}", distance);
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            angle = 0.05 * clock.time() as f32;
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        // circle the forest, rising now and then to look down on it
+        let height = 6.0 + 20.0 * (0.5 - 0.5 * (3.0 * a).cos());
+        let eye = Point3::new(60.0 * a.cos(), 60.0 * a.sin(), height);
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &eye,
+            &Point3::new(0.0, 0.0, 2.0),
+            &Vector3::unit_z(),
+        );
+        let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+
+        ground_batch.params.view_proj = view_proj;
+        renderer.draw(&(&ground_batch, &context), &wrap).unwrap();
+
+        // Draw the close trees with the mesh
+        let mesh_distance = billboard_batch.params.mesh_distance;
+        tree_batch.params.view_proj = view_proj;
+        for tree in forest.iter() {
+            let (dx, dy) = (eye.x - tree.pos[0], eye.y - tree.pos[1]);
+            if (dx * dx + dy * dy).sqrt() < mesh_distance {
+                tree_batch.params.model = model_matrix(tree).into_fixed();
+                renderer.draw(&(&tree_batch, &context), &wrap).unwrap();
+            }
+        }
+
+        // And all the others as billboards
+        {
+            let params = &mut billboard_batch.params;
+            params.view_proj = view_proj;
+            params.camera_pos = [eye.x, eye.y, eye.z];
+        }
+        renderer.draw(&(&billboard_batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Index buffers no wider than the mesh needs.
+//!
+//! Meshes are built and loaded with 32 bit indices, which can count any
+//! number of vertices. `slice` uploads them as 8 bit indices when the mesh
+//! has no more than 256 vertices and as 16 bit indices when it has no more
+//! than 65536, which already halves the index data of most meshes, and only
+//! keeps 32 bits for the ones that need them.
+//!
+//! ```ignore
+//! let (vertex_data, index_data) = terrain.mesh();
+//! let slice = index::slice(&mut factory, &index_data, gfx::PrimitiveType::TriangleList);
+//! ```
+
+use gfx;
+use gfx::traits::*;
+
+/// Size of each index in an index buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexWidth {
+    U8,
+    U16,
+    U32,
+}
+
+impl IndexWidth {
+    /// The narrowest width that can hold every one of `indices`.
+    pub fn of(indices: &[u32]) -> IndexWidth {
+        match indices.iter().fold(0, |max, &i| max.max(i)) {
+            0 ... 0xff => IndexWidth::U8,
+            0x100 ... 0xffff => IndexWidth::U16,
+            _ => IndexWidth::U32,
+        }
+    }
+
+    /// Bytes taken by each index.
+    pub fn size(&self) -> usize {
+        match *self {
+            IndexWidth::U8 => 1,
+            IndexWidth::U16 => 2,
+            IndexWidth::U32 => 4,
+        }
+    }
+}
+
+/// Uploads `indices` with the narrowest `IndexWidth` that holds them, and
+/// returns the slice drawing all of them.
+pub fn slice<R, F>(factory: &mut F, indices: &[u32], prim_type: gfx::PrimitiveType)
+                   -> gfx::Slice<R> where R: gfx::Resources, F: gfx::Factory<R> {
+    match IndexWidth::of(indices) {
+        IndexWidth::U8 => {
+            let narrow: Vec<u8> = indices.iter().map(|&i| i as u8).collect();
+            factory.create_buffer_index::<u8>(&narrow).to_slice(prim_type)
+        },
+        IndexWidth::U16 => {
+            let narrow: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+            factory.create_buffer_index::<u16>(&narrow).to_slice(prim_type)
+        },
+        IndexWidth::U32 => factory.create_buffer_index::<u32>(indices).to_slice(prim_type),
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is a stress test of hardware instancing.
+//
+// A grid of spinning cubes is drawn with a single instanced draw call. The
+// model matrix and color of every cube are per-instance vertex attributes,
+// read from a second vertex buffer that is refilled every frame.
+//
+// Run with `instancing [count] [--static]`, the default count is 100000.
+// With `--static` the instance buffer is filled once and the cubes stop
+// spinning, which leaves only the cost of the draw itself.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate time;
+
+use std::env;
+use std::str::FromStr;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Point3, Vector3, Vector4, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3, Rotation3, Basis3, ToMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::text::{Font, Span, TextRenderer};
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Instance {
+    #[name = "a_Model0"]
+    model0: [f32; 4],
+    #[name = "a_Model1"]
+    model1: [f32; 4],
+    #[name = "a_Model2"]
+    model2: [f32; 4],
+    #[name = "a_Model3"]
+    model3: [f32; 4],
+    #[name = "a_Color"]
+    color: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec4 a_Model0;
+    in vec4 a_Model1;
+    in vec4 a_Model2;
+    in vec4 a_Model3;
+    in vec3 a_Color;
+    out vec3 v_Normal;
+    out vec3 v_Color;
+
+    void main() {
+        mat4 model = mat4(a_Model0, a_Model1, a_Model2, a_Model3);
+        v_Normal = mat3(model) * a_Normal;
+        v_Color = a_Color;
+        gl_Position = u_ViewProj * model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    in vec3 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(v_Color * (0.2 + 0.8 * d), 1.0);
+    }
+";
+
+// Cubes fill a grid of `side` cubes along each axis
+fn instance(i: usize, side: usize, time: f32) -> Instance {
+    let (x, y, z) = (i % side, (i / side) % side, i / (side * side));
+    let offset = 0.5 * (side - 1) as f32;
+    let pos = Vector3::new(x as f32 - offset, y as f32 - offset, z as f32 - offset).mul_s(3.0);
+
+    let axis = Vector3::new(1.0 + x as f32, 1.0 + y as f32, 1.0 + z as f32).normalize();
+    let rotation: Basis3<f32> = Rotation3::from_axis_angle(&axis, cgmath::rad(time + i as f32));
+    let mut model = rotation.to_matrix3().mul_s(0.8).to_matrix4();
+    model.w = Vector4::new(pos.x, pos.y, pos.z, 1.0);
+
+    let s = 1.0 / side as f32;
+    Instance {
+        model0: model.x.into_fixed(),
+        model1: model.y.into_fixed(),
+        model2: model.z.into_fixed(),
+        model3: model.w.into_fixed(),
+        color: [x as f32 * s, y as f32 * s, z as f32 * s],
+    }
+}
+
+pub fn main() {
+    let count = env::args().skip(1).filter_map(|s| FromStr::from_str(&s).ok())
+                           .next().unwrap_or(100_000usize);
+    let animate = !env::args().any(|s| s == "--static");
+    let side = (count as f64).cbrt().ceil() as usize;
+
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Instancing example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut instances: Vec<Instance> = (0 .. count).map(|i| instance(i, side, 0.0)).collect();
+    let instance_buffer = factory.create_buffer(count, gfx::BufferUsage::Stream);
+    factory.update_buffer(&instance_buffer, &instances, 0);
+
+    let (vertex_data, index_data) = shape::cube();
+    let vertex_buffer = factory.create_buffer_static(&vertex_data);
+    let mesh = gfx::Mesh::from_format_instanced(vertex_buffer, vertex_data.len() as gfx::VertexCount,
+                                                instance_buffer.clone());
+    let slice = factory.create_buffer_index::<u16>(&index_data)
+                       .to_slice(gfx::PrimitiveType::TriangleList);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let data = Params {
+        view_proj: Matrix3::identity().to_matrix4().into_fixed(),
+        _dummy: std::marker::PhantomData,
+    };
+    let mut batch = context.make_batch(&program, data, &mesh, slice, &state)
+                           .unwrap();
+
+    // Without a font, the statistics go into the window title
+    let mut hud = match Font::load_default(16) {
+        Ok(font) => Some(TextRenderer::new(&mut factory, font, 256)),
+        Err(e) => {
+            println!("{}, showing statistics in the window title", e);
+            None
+        },
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 1.0, 10.0 * side as f32);
+    let distance = 2.5 * side as f32;
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.1, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    println!("drawing {} instances", count);
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+    let (mut frames, mut last_report) = (0u32, time::precise_time_s());
+    let mut stats = String::from("measuring...");
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            time = clock.time() as f32;
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        if animate {
+            for (i, inst) in instances.iter_mut().enumerate() {
+                *inst = instance(i, side, t);
+            }
+            factory.update_buffer(&instance_buffer, &instances, 0);
+        }
+
+        let a = 0.1 * t;
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(distance * a.cos(), distance * a.sin(), 0.5 * distance),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::unit_z(),
+        );
+        batch.params.view_proj = proj.mul_m(&view.mat).into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw_instanced(&(&batch, &context), count as u32, 0, &wrap)
+                .unwrap();
+        if let Some(ref mut hud) = hud {
+            hud.add(&[Span::new(&stats, [1.0, 1.0, 1.0, 1.0])], [10.0, 10.0], None);
+            hud.draw(&mut factory, &mut renderer, &wrap);
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        frames += 1;
+        let now = time::precise_time_s();
+        if now - last_report >= 1.0 {
+            let frame_time = (now - last_report) / frames as f64;
+            stats = format!("{} instances, {:.1} fps, {:.2} ms", count,
+                            1.0 / frame_time, 1000.0 * frame_time);
+            if hud.is_none() {
+                wrap.window.set_title(&format!("Instancing example with gfx-rs: {}", stats));
+            }
+            frames = 0;
+            last_report = now;
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the examples.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate freetype;
+extern crate gfx;
+extern crate gfx_gl as gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate image;
+extern crate time;
+
+pub use capture::Thumbnail;
+pub use clock::Clock;
+
+pub mod camera;
+pub mod camera_path;
+pub mod capture;
+pub mod clock;
+pub mod cubemap;
+pub mod depth_pyramid;
+pub mod dirty;
+pub mod gl_program;
+pub mod heightfield;
+pub mod ibl;
+pub mod index;
+pub mod md5;
+pub mod mesh_cache;
+pub mod obj;
+pub mod outline;
+pub mod post;
+pub mod queue;
+pub mod retained;
+pub mod shape;
+pub mod sky;
+pub mod text;
+pub mod vertex_cache;
+pub mod walk;
+pub mod window;
+pub mod writer;
@@ -0,0 +1,515 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of lighting a static scene with a baked lightmap.
+//
+// The scene is a room made of boxes, open at the front, with a lamp under
+// the ceiling. Each vertex has two sets of texture coordinates: the first
+// tiles the albedo texture, the second places the face in a lightmap shared
+// by the whole scene, where every face has a rectangle of its own. The
+// lightmap holds the light reaching each texel, from the lamp with its soft
+// shadows and from the sky through the opening, traced against the boxes,
+// which is much too slow to do every frame. Light doesn't depend on where it
+// is seen from, so it can be stored like this. Reflections do, so the
+// specular highlight of the lamp is added at runtime, hidden where the
+// lightmap says the lamp is blocked.
+//
+// The lightmap is loaded from lightmap.png in the working directory. If
+// there is none, it is baked at startup and saved there, the way a content
+// pipeline would bake it offline. Delete it to bake again.
+//
+// Press M to cycle through the lit scene, the lightmap, the albedo and the
+// specular term, and Up and Down to change the shininess.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate image;
+extern crate rand;
+extern crate time;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{window, Thumbnail};
+
+const LIGHTMAP_PATH: &'static str = "lightmap.png";
+const LIGHTMAP_SIZE: usize = 512;
+// lightmap texels per unit of length
+const TEXEL_DENSITY: f32 = 12.0;
+const LAMP_POS: [f32; 3] = [0.5, 1.0, 3.2];
+const LAMP_RADIUS: f32 = 0.25;
+// matching the constants of the fragment shader
+const LAMP_POWER: f32 = 14.0;
+const SKY: [f32; 3] = [0.55, 0.65, 0.9];
+const SKY_RAYS: usize = 24;
+const LAMP_RAYS: usize = 8;
+// the brightest light the texels can hold
+const MAX_LIGHT: f32 = 2.0;
+const MODES: [&'static str; 4] = ["lit", "lightmap", "albedo", "specular"];
+
+// (min, max, color) of the boxes making up the scene
+static BLOCKS: [([f32; 3], [f32; 3], [f32; 3]); 8] = [
+    // the floor, the ceiling and three walls
+    ([-5.0, -5.0, -0.2], [5.0, 5.0, 0.0], [0.8, 0.8, 0.8]),
+    ([-5.0, -5.0, 4.0], [5.0, 5.0, 4.2], [0.9, 0.9, 0.9]),
+    ([-5.0, 5.0, 0.0], [5.0, 5.2, 4.0], [0.9, 0.85, 0.7]),
+    ([-5.2, -5.0, 0.0], [-5.0, 5.0, 4.0], [0.85, 0.4, 0.35]),
+    ([5.0, -5.0, 0.0], [5.2, 5.0, 4.0], [0.35, 0.5, 0.8]),
+    // a pillar and two crates
+    ([-2.5, 0.5, 0.0], [-1.7, 1.3, 4.0], [0.9, 0.9, 0.9]),
+    ([1.5, 1.5, 0.0], [3.0, 3.0, 1.2], [0.8, 0.6, 0.4]),
+    ([0.3, -2.0, 0.0], [1.1, -1.2, 0.8], [0.8, 0.6, 0.4]),
+];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+    #[name = "a_Color"]
+    color: [f32; 3],
+    // tiling the albedo texture, in world units
+    #[name = "a_TexCoord"]
+    tex_coord: [f32; 2],
+    // the place of the vertex in the lightmap
+    #[name = "a_LightmapCoord"]
+    lightmap_coord: [f32; 2],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_CameraPos"]
+    camera_pos: [f32; 3],
+    #[name = "u_LampPos"]
+    lamp_pos: [f32; 3],
+    #[name = "u_Shininess"]
+    shininess: f32,
+    #[name = "u_Mode"]
+    mode: f32,
+    #[name = "t_Albedo"]
+    albedo: gfx::shade::TextureParam<R>,
+    #[name = "t_Lightmap"]
+    lightmap: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec3 a_Color;
+    in vec2 a_TexCoord;
+    in vec2 a_LightmapCoord;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+    out vec3 v_Color;
+    out vec2 v_TexCoord;
+    out vec2 v_LightmapCoord;
+
+    void main() {
+        v_Pos = a_Pos;
+        v_Normal = a_Normal;
+        v_Color = a_Color;
+        v_TexCoord = a_TexCoord;
+        v_LightmapCoord = a_LightmapCoord;
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float MAX_LIGHT = 2.0;
+    const vec3 LAMP = vec3(1.0, 0.9, 0.75);
+    const float LAMP_POWER = 14.0;
+    // the reflectance of the surfaces seen straight on
+    const float F0 = 0.04;
+
+    uniform vec3 u_CameraPos;
+    uniform vec3 u_LampPos;
+    uniform float u_Shininess;
+    uniform float u_Mode;
+    uniform sampler2D t_Albedo;
+    uniform sampler2D t_Lightmap;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    in vec3 v_Color;
+    in vec2 v_TexCoord;
+    in vec2 v_LightmapCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 albedo = v_Color * texture(t_Albedo, v_TexCoord).rgb;
+        // the light is stored as the square root of its fraction of the
+        // maximum, to spend more of the 8 bits on the dark parts
+        vec4 baked = texture(t_Lightmap, v_LightmapCoord);
+        vec3 light = baked.rgb * baked.rgb * MAX_LIGHT;
+
+        // Blinn-Phong from the lamp, hidden where it is in shadow
+        vec3 n = normalize(v_Normal);
+        vec3 to_lamp = u_LampPos - v_Pos;
+        vec3 l = normalize(to_lamp);
+        vec3 v = normalize(u_CameraPos - v_Pos);
+        float spec = pow(max(dot(n, normalize(l + v)), 0.0), u_Shininess)
+                   * step(0.0, dot(n, l)) * (u_Shininess + 8.0) / (8.0 * 3.14159);
+        vec3 specular = F0 * LAMP * LAMP_POWER * spec * baked.a / dot(to_lamp, to_lamp);
+
+        vec3 color;
+        if (u_Mode < 0.5) {
+            color = albedo * light + specular;
+        } else if (u_Mode < 1.5) {
+            color = light;
+        } else if (u_Mode < 2.5) {
+            color = albedo;
+        } else {
+            color = specular;
+        }
+        o_Color = vec4(pow(1.0 - exp(-color), vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// A face of a box, with its rectangle in the lightmap
+struct Face {
+    origin: [f32; 3],
+    du: [f32; 3],
+    dv: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+    // in texels, without the border around it
+    size: (usize, usize),
+    offset: (usize, usize),
+}
+
+fn add(a: [f32; 3], b: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] + b[0] * s, a[1] + b[1] * s, a[2] + b[2] * s]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+// The six faces of every box, packed into rows of the lightmap with a
+// texel of border around each, from the tallest to the shortest
+fn make_faces() -> Vec<Face> {
+    let mut faces = Vec::new();
+    for &(min, max, color) in BLOCKS.iter() {
+        for k in 0 .. 3 {
+            let (a, b) = ((k + 1) % 3, (k + 2) % 3);
+            let mut ea = [0.0; 3];
+            let mut eb = [0.0; 3];
+            ea[a] = max[a] - min[a];
+            eb[b] = max[b] - min[b];
+            for &positive in [false, true].iter() {
+                let mut origin = min;
+                let mut normal = [0.0; 3];
+                if positive {
+                    origin[k] = max[k];
+                    normal[k] = 1.0;
+                } else {
+                    normal[k] = -1.0;
+                }
+                // counter-clockwise seen from the outside
+                let (du, dv) = if positive { (ea, eb) } else { (eb, ea) };
+                let size = ((length(du) * TEXEL_DENSITY).ceil() as usize,
+                            (length(dv) * TEXEL_DENSITY).ceil() as usize);
+                faces.push(Face {
+                    origin: origin, du: du, dv: dv, normal: normal, color: color,
+                    size: size, offset: (0, 0),
+                });
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0 .. faces.len()).collect();
+    order.sort_by(|&a, &b| faces[b].size.1.cmp(&faces[a].size.1));
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for &i in order.iter() {
+        let (w, h) = (faces[i].size.0 + 2, faces[i].size.1 + 2);
+        if x + w > LIGHTMAP_SIZE {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        assert!(y + h <= LIGHTMAP_SIZE, "the scene doesn't fit in the lightmap");
+        faces[i].offset = (x + 1, y + 1);
+        x += w;
+        row_height = row_height.max(h);
+    }
+    faces
+}
+
+fn make_mesh(faces: &[Face]) -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for face in faces.iter() {
+        let base = vertices.len() as u16;
+        for &(s, t) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+            let (x, y) = (face.offset.0 as f32 + s * face.size.0 as f32,
+                          face.offset.1 as f32 + t * face.size.1 as f32);
+            vertices.push(Vertex {
+                pos: add(add(face.origin, face.du, s), face.dv, t),
+                normal: face.normal,
+                color: face.color,
+                tex_coord: [s * length(face.du) * 0.5, t * length(face.dv) * 0.5],
+                lightmap_coord: [x / LIGHTMAP_SIZE as f32, y / LIGHTMAP_SIZE as f32],
+            });
+        }
+        indices.extend([0, 1, 2, 2, 3, 0].iter().map(|i| base + i));
+    }
+    (vertices, indices)
+}
+
+// Whether the ray hits any of the boxes before `max_t`
+fn occluded(origin: [f32; 3], dir: [f32; 3], max_t: f32) -> bool {
+    BLOCKS.iter().any(|&(min, max, _)| {
+        let (mut t0, mut t1) = (0.0f32, max_t);
+        for k in 0 .. 3 {
+            let inv = 1.0 / dir[k];
+            let (a, b) = ((min[k] - origin[k]) * inv, (max[k] - origin[k]) * inv);
+            t0 = t0.max(a.min(b));
+            t1 = t1.min(a.max(b));
+        }
+        t0 < t1
+    })
+}
+
+// Traces the light reaching every texel of every face, writing the square
+// root of its fraction of `MAX_LIGHT` in RGB and how much of the lamp is
+// seen in alpha
+fn bake(faces: &[Face]) -> Vec<u8> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut data = vec![0u8; LIGHTMAP_SIZE * LIGHTMAP_SIZE * 4];
+    for face in faces.iter() {
+        let n = face.normal;
+        // any two directions across the normal, for the sky rays
+        let k = (0 .. 3).position(|k| n[k] != 0.0).unwrap();
+        let (mut t1, mut t2) = ([0.0; 3], [0.0; 3]);
+        t1[(k + 1) % 3] = 1.0;
+        t2[(k + 2) % 3] = 1.0;
+
+        let (w, h) = (face.size.0 as isize, face.size.1 as isize);
+        // the border takes the texels at the edge, so that filtering
+        // doesn't blend in the neighbouring rectangles
+        for j in -1 .. h + 1 {
+            for i in -1 .. w + 1 {
+                let s = ((i.max(0).min(w - 1)) as f32 + 0.5) / w as f32;
+                let t = ((j.max(0).min(h - 1)) as f32 + 0.5) / h as f32;
+                let p = add(add(add(face.origin, face.du, s), face.dv, t), n, 1e-3);
+
+                let mut light = [0.0f32; 3];
+                let mut seen = 0;
+                for _ in 0 .. LAMP_RAYS {
+                    // a random point of the lamp, a small sphere
+                    let mut offset = [0.0f32; 3];
+                    loop {
+                        for c in offset.iter_mut() {
+                            *c = rng.gen_range(-1.0f32, 1.0);
+                        }
+                        if dot(offset, offset) <= 1.0 {
+                            break;
+                        }
+                    }
+                    let target = add(LAMP_POS, offset, LAMP_RADIUS);
+                    let d = add(target, p, -1.0);
+                    let distance = length(d);
+                    let dir = [d[0] / distance, d[1] / distance, d[2] / distance];
+                    let cos = dot(dir, n);
+                    if cos > 0.0 && !occluded(p, dir, distance) {
+                        let e = LAMP_POWER * cos / (distance * distance) / LAMP_RAYS as f32;
+                        light = add(light, [1.0, 0.9, 0.75], e);
+                        seen += 1;
+                    }
+                }
+                for _ in 0 .. SKY_RAYS {
+                    // cosine weighted over the hemisphere, so that the
+                    // escaping rays only have to be counted
+                    let (r, a) = (rng.gen::<f32>().sqrt(), 2.0 * PI * rng.gen::<f32>());
+                    let z = (1.0 - r * r).sqrt();
+                    let dir = add(add([n[0] * z, n[1] * z, n[2] * z], t1, r * a.cos()),
+                                  t2, r * a.sin());
+                    if !occluded(p, dir, 100.0) {
+                        light = add(light, SKY, 1.0 / SKY_RAYS as f32);
+                    }
+                }
+
+                let (x, y) = ((face.offset.0 as isize + i) as usize,
+                              (face.offset.1 as isize + j) as usize);
+                let texel = &mut data[(y * LIGHTMAP_SIZE + x) * 4 ..][.. 4];
+                for c in 0 .. 3 {
+                    texel[c] = ((light[c] / MAX_LIGHT).min(1.0).sqrt() * 255.0) as u8;
+                }
+                texel[3] = (seen * 255 / LAMP_RAYS) as u8;
+            }
+        }
+    }
+    data
+}
+
+fn load_or_bake(faces: &[Face]) -> Vec<u8> {
+    if let Ok(img) = image::open(LIGHTMAP_PATH) {
+        let img = img.to_rgba();
+        if img.dimensions() == (LIGHTMAP_SIZE as u32, LIGHTMAP_SIZE as u32) {
+            return img.into_raw();
+        }
+        println!("{} has the wrong size, baking again", LIGHTMAP_PATH);
+    }
+    let start = time::precise_time_s();
+    let data = bake(faces);
+    println!("Baked the lightmap in {:.1} s", time::precise_time_s() - start);
+    if let Err(e) = image::save_buffer(LIGHTMAP_PATH, &data, LIGHTMAP_SIZE as u32,
+                                       LIGHTMAP_SIZE as u32, image::RGBA(8)) {
+        println!("Failed to save {}: {}", LIGHTMAP_PATH, e);
+    }
+    data
+}
+
+// Large light tiles with dark seams, at two tiles per unit
+fn make_albedo() -> Vec<u8> {
+    let size = 64;
+    let mut data = Vec::with_capacity(size * size * 4);
+    for y in 0 .. size {
+        for x in 0 .. size {
+            let seam = x < 2 || y < 2;
+            let checker = ((x / 32) + (y / 32)) % 2 == 0;
+            let v = if seam { 0.45 } else if checker { 1.0 } else { 0.85 };
+            let b = (v * 255.0) as u8;
+            data.extend([b, b, b, 0xFF].iter().cloned());
+        }
+    }
+    data
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Lightmap example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let faces = make_faces();
+    let lightmap = factory.create_texture_rgba8(LIGHTMAP_SIZE as u16, LIGHTMAP_SIZE as u16,
+                                                false).unwrap();
+    factory.update_texture(&lightmap, &lightmap.get_info().to_image_info(),
+                           &load_or_bake(&faces), None).unwrap();
+    let albedo = factory.create_texture_rgba8(64, 64, true).unwrap();
+    factory.update_texture(&albedo, &albedo.get_info().to_image_info(),
+                           &make_albedo(), None).unwrap();
+    factory.generate_mipmap(&albedo);
+
+    let mut batch = {
+        let (vertex_data, index_data) = make_mesh(&faces);
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            camera_pos: [0.0; 3],
+            lamp_pos: LAMP_POS,
+            shininess: 32.0,
+            mode: 0.0,
+            albedo: (albedo, Some(factory.create_sampler(
+                gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                           gfx::tex::WrapMode::Tile)
+            ))),
+            // no mipmaps, since the rectangles only have a texel of border
+            lightmap: (lightmap, Some(factory.create_sampler(
+                gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                           gfx::tex::WrapMode::Clamp)
+            ))),
+        };
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(50.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 1.5), 9.0, -1.7, 0.2);
+
+    let clear_data = gfx::ClearData {
+        color: [SKY[0], SKY[1], SKY[2], 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut mode = 0;
+    let mut shininess = 32.0f32;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::M => mode = (mode + 1) % MODES.len(),
+                    VirtualKeyCode::Up => shininess = (shininess * 2.0).min(512.0),
+                    VirtualKeyCode::Down => shininess = (shininess / 2.0).max(4.0),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        batch.params.view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        batch.params.camera_pos = camera.position().into_fixed();
+        batch.params.shininess = shininess;
+        batch.params.mode = mode as f32;
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Lightmap example: {}, shininess {}",
+                                       MODES[mode], shininess));
+    }
+}
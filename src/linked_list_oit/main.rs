@@ -0,0 +1,541 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of order-independent transparency with per-pixel
+// linked lists, also known as an A-buffer.
+//
+// Instead of peeling the layers of glass one pass at a time like the depth
+// peeling example, every transparent fragment is kept in a single pass:
+//
+// 1. The opaque shapes are drawn as usual, filling the depth buffer.
+// 2. The glass shapes are drawn with the depth test, but without writing
+//    color or depth. Each fragment that passes takes the next free node of a
+//    large buffer from an atomic counter, stores its color, its depth, and
+//    the head of the list of its pixel in it, and becomes the new head.
+// 3. A full-screen pass walks the list of every pixel, sorts the fragments
+//    by depth, blends them from front to back over what is already there,
+//    and resets the head of the list for the next frame.
+//
+// This needs atomic counters and image load and store, which came with GL
+// 4.2 and which gfx-rs doesn't expose, so the example talks to GL directly,
+// and stops right away when the context is older.
+//
+// Drag with the left mouse button to turn around the shapes, and use the
+// mouse wheel to zoom. Press H to show the number of fragments of each pixel
+// instead, and Space to stop the shapes. The window title shows how many
+// fragments were stored.
+
+extern crate cgmath;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+use gfx_examples::gl_program::{compile_shader, link_program, uniform_location};
+use gl::Gl;
+use gl::types::*;
+use std::mem;
+use std::ptr;
+use std::iter::repeat;
+
+// How many fragments the node buffer has room for, on average per pixel
+const NODES_PER_PIXEL: usize = 8;
+// The end of a list
+const END: u32 = 0xFFFFFFFF;
+
+static VERTEX_SRC: &'static str = "
+    #version 420 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_View;
+    uniform mat4 u_Proj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_View) * mat3(u_Model) * a_Normal;
+        gl_Position = u_Proj * u_View * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static OPAQUE_FRAGMENT_SRC: &'static str = "
+    #version 420 core
+
+    uniform vec4 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float light = 0.2 + 0.8 * max(0.0, dot(n, normalize(vec3(0.3, 0.5, 0.8))));
+        o_Color = vec4(u_Color.rgb * light, 1.0);
+    }
+";
+
+// Adds the fragment to the list of its pixel. The depth test runs before the
+// shader, so that fragments behind the opaque shapes are never stored.
+static STORE_FRAGMENT_SRC: &'static str = "
+    #version 420 core
+
+    layout(early_fragment_tests) in;
+
+    uniform vec4 u_Color;
+    uniform uint u_MaxNodes;
+    layout(binding = 0, offset = 0) uniform atomic_uint u_Counter;
+    layout(binding = 0, r32ui) uniform coherent uimage2D u_Heads;
+    layout(binding = 1, rgba32ui) uniform writeonly uimageBuffer u_Nodes;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float facing = abs(n.z);
+        float light = 0.4 + 0.6 * abs(dot(n, normalize(vec3(0.3, 0.5, 0.8))));
+        // glass lets less through when seen at a grazing angle
+        float alpha = mix(1.0, u_Color.a, facing);
+
+        uint node = atomicCounterIncrement(u_Counter);
+        // when the buffer is full, the fragment is lost
+        if (node < u_MaxNodes) {
+            uint next = imageAtomicExchange(u_Heads, ivec2(gl_FragCoord.xy), node);
+            uint color = packUnorm4x8(vec4(u_Color.rgb * light, alpha));
+            imageStore(u_Nodes, int(node), uvec4(color, floatBitsToUint(gl_FragCoord.z), next, 0));
+        }
+        o_Color = vec4(0.0);
+    }
+";
+
+static RESOLVE_VERTEX_SRC: &'static str = "
+    #version 420 core
+
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+// Sorts the list of the pixel front to back and blends it, giving a
+// premultiplied color to be blended over the opaque shapes
+static RESOLVE_FRAGMENT_SRC: &'static str = "
+    #version 420 core
+
+    // fragments past this many are left out of a pixel
+    const int MAX_FRAGMENTS = 32;
+    const uint END = 0xFFFFFFFFu;
+
+    uniform float u_Heat;
+    layout(binding = 0, r32ui) uniform uimage2D u_Heads;
+    layout(binding = 1, rgba32ui) uniform readonly uimageBuffer u_Nodes;
+    out vec4 o_Color;
+
+    void main() {
+        ivec2 pixel = ivec2(gl_FragCoord.xy);
+        uint node = imageLoad(u_Heads, pixel).r;
+        // ready for the next frame
+        imageStore(u_Heads, pixel, uvec4(END));
+
+        // color and depth of each fragment
+        uvec2 fragments[MAX_FRAGMENTS];
+        int count = 0;
+        int total = 0;
+        while (node != END) {
+            uvec4 n = imageLoad(u_Nodes, int(node));
+            if (count < MAX_FRAGMENTS) {
+                fragments[count++] = n.xy;
+            }
+            total++;
+            node = n.z;
+        }
+        if (total == 0) {
+            discard;
+        }
+
+        if (u_Heat > 0.5) {
+            float k = min(float(total) / 16.0, 1.0);
+            o_Color = vec4(k, 1.0 - abs(2.0 * k - 1.0), 1.0 - k, 1.0);
+            return;
+        }
+
+        // insertion sort, the lists are short
+        for (int i = 1; i < count; ++i) {
+            uvec2 f = fragments[i];
+            float depth = uintBitsToFloat(f.y);
+            int j = i - 1;
+            while (j >= 0 && uintBitsToFloat(fragments[j].y) > depth) {
+                fragments[j + 1] = fragments[j];
+                --j;
+            }
+            fragments[j + 1] = f;
+        }
+
+        vec3 color = vec3(0.0);
+        float transmittance = 1.0;
+        for (int i = 0; i < count; ++i) {
+            vec4 f = unpackUnorm4x8(fragments[i].x);
+            color += transmittance * f.a * f.rgb;
+            transmittance *= 1.0 - f.a;
+        }
+        o_Color = vec4(color, 1.0 - transmittance);
+    }
+";
+
+/// A vertex array with its buffers, drawn with indices.
+struct Mesh {
+    vao: GLuint,
+    buffers: [GLuint; 2],
+    num_indices: GLsizei,
+}
+
+impl Mesh {
+    fn new(gl: &Gl, vertices: &[shape::Vertex], indices: &[u16]) -> Mesh {
+        let mut vao = 0;
+        let mut buffers = [0; 2];
+        unsafe {
+            gl.GenVertexArrays(1, &mut vao);
+            gl.BindVertexArray(vao);
+            gl.GenBuffers(2, buffers.as_mut_ptr());
+
+            gl.BindBuffer(gl::ARRAY_BUFFER, buffers[0]);
+            gl.BufferData(gl::ARRAY_BUFFER,
+                          (vertices.len() * mem::size_of::<shape::Vertex>()) as GLsizeiptr,
+                          mem::transmute(&vertices[0]),
+                          gl::STATIC_DRAW);
+            let stride = mem::size_of::<shape::Vertex>() as GLsizei;
+            gl.EnableVertexAttribArray(0);
+            gl.VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl.EnableVertexAttribArray(1);
+            gl.VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride,
+                                   mem::size_of::<[f32; 3]>() as *const GLvoid);
+
+            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffers[1]);
+            gl.BufferData(gl::ELEMENT_ARRAY_BUFFER,
+                          (indices.len() * mem::size_of::<u16>()) as GLsizeiptr,
+                          mem::transmute(&indices[0]),
+                          gl::STATIC_DRAW);
+            gl.BindVertexArray(0);
+        }
+        Mesh {
+            vao: vao,
+            buffers: buffers,
+            num_indices: indices.len() as GLsizei,
+        }
+    }
+
+    fn draw(&self, gl: &Gl) {
+        unsafe {
+            gl.BindVertexArray(self.vao);
+            gl.DrawElements(gl::TRIANGLES, self.num_indices, gl::UNSIGNED_SHORT, ptr::null());
+        }
+    }
+
+    fn delete(&self, gl: &Gl) {
+        unsafe {
+            gl.DeleteBuffers(2, self.buffers.as_ptr());
+            gl.DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// The heads of the lists, one per pixel, the nodes they point to, and the
+/// counter handing out the nodes.
+struct Lists {
+    heads: GLuint,
+    node_buffer: GLuint,
+    nodes: GLuint,
+    counter: GLuint,
+    max_nodes: usize,
+}
+
+impl Lists {
+    fn new(gl: &Gl, width: usize, height: usize) -> Lists {
+        let max_nodes = width * height * NODES_PER_PIXEL;
+        let (mut heads, mut node_buffer, mut nodes, mut counter) = (0, 0, 0, 0);
+        let empty: Vec<u32> = repeat(END).take(width * height).collect();
+        unsafe {
+            gl.GenTextures(1, &mut heads);
+            gl.BindTexture(gl::TEXTURE_2D, heads);
+            gl.TexImage2D(gl::TEXTURE_2D, 0, gl::R32UI as GLint, width as GLsizei,
+                          height as GLsizei, 0, gl::RED_INTEGER, gl::UNSIGNED_INT,
+                          mem::transmute(&empty[0]));
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+
+            // a node is the color, the depth and the next node, with one
+            // more word to keep them aligned
+            gl.GenBuffers(1, &mut node_buffer);
+            gl.BindBuffer(gl::TEXTURE_BUFFER, node_buffer);
+            gl.BufferData(gl::TEXTURE_BUFFER,
+                          (max_nodes * mem::size_of::<[u32; 4]>()) as GLsizeiptr,
+                          ptr::null(), gl::DYNAMIC_COPY);
+            gl.BindBuffer(gl::TEXTURE_BUFFER, 0);
+            gl.GenTextures(1, &mut nodes);
+            gl.BindTexture(gl::TEXTURE_BUFFER, nodes);
+            gl.TexBuffer(gl::TEXTURE_BUFFER, gl::RGBA32UI, node_buffer);
+            gl.BindTexture(gl::TEXTURE_BUFFER, 0);
+
+            gl.GenBuffers(1, &mut counter);
+            gl.BindBuffer(gl::ATOMIC_COUNTER_BUFFER, counter);
+            gl.BufferData(gl::ATOMIC_COUNTER_BUFFER, mem::size_of::<u32>() as GLsizeiptr,
+                          mem::transmute(&0u32), gl::DYNAMIC_COPY);
+            gl.BindBuffer(gl::ATOMIC_COUNTER_BUFFER, 0);
+        }
+        Lists {
+            heads: heads,
+            node_buffer: node_buffer,
+            nodes: nodes,
+            counter: counter,
+            max_nodes: max_nodes,
+        }
+    }
+
+    // Reads how many nodes were handed out since the last reset, and resets
+    // the counter. This waits until the GPU is done with the counter.
+    fn reset_counter(&self, gl: &Gl) -> u32 {
+        let mut count = 0u32;
+        let zero = 0u32;
+        unsafe {
+            gl.BindBuffer(gl::ATOMIC_COUNTER_BUFFER, self.counter);
+            gl.GetBufferSubData(gl::ATOMIC_COUNTER_BUFFER, 0, mem::size_of::<u32>() as GLsizeiptr,
+                                mem::transmute(&mut count));
+            gl.BufferSubData(gl::ATOMIC_COUNTER_BUFFER, 0, mem::size_of::<u32>() as GLsizeiptr,
+                             mem::transmute(&zero));
+            gl.BindBuffer(gl::ATOMIC_COUNTER_BUFFER, 0);
+        }
+        count
+    }
+
+    fn bind(&self, gl: &Gl) {
+        unsafe {
+            gl.BindBufferBase(gl::ATOMIC_COUNTER_BUFFER, 0, self.counter);
+            gl.BindImageTexture(0, self.heads, 0, gl::FALSE, 0, gl::READ_WRITE, gl::R32UI);
+            gl.BindImageTexture(1, self.nodes, 0, gl::FALSE, 0, gl::READ_WRITE, gl::RGBA32UI);
+        }
+    }
+
+    fn delete(&self, gl: &Gl) {
+        unsafe {
+            gl.DeleteBuffers(1, &self.counter);
+            gl.DeleteTextures(1, &self.nodes);
+            gl.DeleteBuffers(1, &self.node_buffer);
+            gl.DeleteTextures(1, &self.heads);
+        }
+    }
+}
+
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let window = glutin::WindowBuilder::new()
+        .with_title("Linked list OIT example".to_string())
+        .with_dimensions(800, 600)
+        .with_gl(glutin::GlRequest::Latest)
+        .build().unwrap();
+    unsafe { window.make_current() };
+    let gl = Gl::load_with(|s| window.get_proc_address(s));
+
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl.GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl.GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    if (major, minor) < (4, 2) {
+        println!("This example needs GL-4.2 for atomic counters and image load and store, \
+                  but the context is GL-{}.{}", major, minor);
+        return;
+    }
+
+    let (w, h) = window::physical_size(&window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+
+    let vs = compile_shader(&gl, VERTEX_SRC, gl::VERTEX_SHADER);
+    let opaque_fs = compile_shader(&gl, OPAQUE_FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let opaque_program = link_program(&gl, &[vs, opaque_fs], &["a_Pos", "a_Normal"]);
+    let store_fs = compile_shader(&gl, STORE_FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let store_program = link_program(&gl, &[vs, store_fs], &["a_Pos", "a_Normal"]);
+    let resolve_vs = compile_shader(&gl, RESOLVE_VERTEX_SRC, gl::VERTEX_SHADER);
+    let resolve_fs = compile_shader(&gl, RESOLVE_FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let resolve_program = link_program(&gl, &[resolve_vs, resolve_fs], &["a_Pos", "a_Normal"]);
+    let u_heat = uniform_location(&gl, resolve_program, "u_Heat");
+
+    let lists = Lists::new(&gl, w as usize, h as usize);
+    unsafe {
+        gl.UseProgram(store_program);
+        gl.Uniform1ui(uniform_location(&gl, store_program, "u_MaxNodes"),
+                      lists.max_nodes as GLuint);
+    }
+
+    // one per shape
+    let meshes: Vec<Mesh> = vec![
+        shape::cube(), shape::sphere(32, 64), shape::torus(64, 32, 0.8, 0.3),
+    ].into_iter().map(|(vertices, indices)| Mesh::new(&gl, &vertices, &indices)).collect();
+    let quad = {
+        let vertices = shape::quad();
+        let indices: Vec<u16> = (0 .. vertices.len() as u16).collect();
+        Mesh::new(&gl, &vertices, &indices)
+    };
+
+    // (shape, position, color) of the glass objects, crossing each other
+    let glass = [
+        (0, [-0.8, 0.0, 0.0], [0.9, 0.2, 0.2, 0.4]),
+        (1, [0.8, 0.3, 0.0], [0.2, 0.8, 0.3, 0.35]),
+        (2, [0.0, -0.2, 0.3], [0.2, 0.4, 0.9, 0.45]),
+        (2, [0.2, 0.6, -0.4], [0.9, 0.8, 0.2, 0.4]),
+        (1, [-0.3, -0.8, 0.6], [0.8, 0.3, 0.9, 0.3]),
+        (0, [0.5, -0.6, -0.6], [0.2, 0.8, 0.9, 0.35]),
+    ];
+    // and the opaque ones, hiding the glass behind them
+    let opaque = [
+        (1, [0.0, 0.0, -0.1], [0.9, 0.9, 0.85, 1.0]),
+    ];
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.5, 30.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 7.0, -0.6, 0.4);
+
+    let mut heat = false;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::H => heat = !heat,
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.3 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+        let view = camera.view().mat.into_fixed();
+
+        // How many nodes the last frame used, which it has finished with by
+        // now, after the swap
+        let stored = lists.reset_counter(&gl);
+        lists.bind(&gl);
+
+        let draw_objects = |program: GLuint, objects: &[(usize, [f32; 3], [f32; 4])]| unsafe {
+            gl.UseProgram(program);
+            gl.UniformMatrix4fv(uniform_location(&gl, program, "u_View"), 1, gl::FALSE,
+                                view[0].as_ptr());
+            gl.UniformMatrix4fv(uniform_location(&gl, program, "u_Proj"), 1, gl::FALSE,
+                                proj.into_fixed()[0].as_ptr());
+            let (u_model, u_color) = (uniform_location(&gl, program, "u_Model"),
+                                      uniform_location(&gl, program, "u_Color"));
+            for (j, &(kind, pos, color)) in objects.iter().enumerate() {
+                let (z, x) = (a + j as f32, 0.5 * a + j as f32);
+                let spin = Matrix3::from_angle_z(cgmath::rad(z))
+                                   .mul_m(&Matrix3::from_angle_x(cgmath::rad(x)));
+                let model = place(spin.mul_s(0.8), pos[0], pos[1], pos[2]).into_fixed();
+                gl.UniformMatrix4fv(u_model, 1, gl::FALSE, model[0].as_ptr());
+                gl.Uniform4f(u_color, color[0], color[1], color[2], color[3]);
+                meshes[kind].draw(&gl);
+            }
+        };
+
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl.Viewport(0, 0, w as GLsizei, h as GLsizei);
+            gl.ClearColor(0.15, 0.15, 0.2, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            gl.Enable(gl::DEPTH_TEST);
+            gl.DepthFunc(gl::LEQUAL);
+        }
+        draw_objects(opaque_program, &opaque);
+
+        // Store the glass, testing against the opaque depth but not
+        // writing anything to the frame buffer
+        unsafe {
+            gl.DepthMask(gl::FALSE);
+            gl.ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        }
+        draw_objects(store_program, &glass);
+
+        // Sort and blend every list over the opaque shapes, once the stores
+        // are visible to the loads
+        unsafe {
+            gl.ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl.Disable(gl::DEPTH_TEST);
+            gl.MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+            gl.Enable(gl::BLEND);
+            gl.BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+            gl.UseProgram(resolve_program);
+            gl.Uniform1f(u_heat, if heat { 1.0 } else { 0.0 });
+        }
+        quad.draw(&gl);
+        unsafe {
+            gl.Disable(gl::BLEND);
+            gl.DepthMask(gl::TRUE);
+            // the heads reset by the resolve have to be visible to the next
+            // frame's stores
+            gl.MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+        }
+
+        let win = &window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        window.swap_buffers();
+
+        let dropped = (stored as usize).saturating_sub(lists.max_nodes);
+        window.set_title(&format!("Linked list OIT example: {} fragments, {:.1} per pixel, {} lost",
+                                  stored, stored as f32 / (w as f32 * h as f32), dropped));
+    }
+
+    // Cleanup
+    lists.delete(&gl);
+    unsafe {
+        gl.DeleteProgram(resolve_program);
+        gl.DeleteShader(resolve_fs);
+        gl.DeleteShader(resolve_vs);
+        gl.DeleteProgram(store_program);
+        gl.DeleteShader(store_fs);
+        gl.DeleteProgram(opaque_program);
+        gl.DeleteShader(opaque_fs);
+        gl.DeleteShader(vs);
+    }
+    for mesh in meshes.iter() {
+        mesh.delete(&gl);
+    }
+    quad.delete(&gl);
+}
@@ -0,0 +1,334 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of switching between levels of detail by distance.
+//
+// Every object of a field of knots has four meshes, each with about a
+// quarter of the triangles of the one before, made separately like an artist
+// would make them. Each frame, every object picks the level for its distance
+// to the camera. Switching happens a little further out when moving away than
+// when coming back, so that an object sitting right at the threshold doesn't
+// flicker between two levels.
+//
+// A switch would still pop. With cross-fading on, the object draws both
+// levels for a moment, the new one over a growing share of its pixels and the
+// old one over the rest, picked by an ordered dither pattern. Both are drawn
+// opaque with depth, so no sorting is needed.
+//
+// Press F to toggle cross-fading, C to color the objects by level, and Up and
+// Down to move the distances between levels.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point, Point3, Vector4, EuclideanVector};
+use cgmath::ToMatrix4;
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::text::{Font, Span, TextRenderer};
+use gfx_examples::{window, Clock, Thumbnail};
+
+const GRID_SIZE: usize = 24;
+const SPACING: f32 = 4.0;
+// distances at which each level gives way to the next
+const DISTANCES: [f32; 3] = [10.0, 20.0, 40.0];
+// how far past a distance an object has to go to switch
+const HYSTERESIS: f32 = 0.1;
+// seconds a cross-fade lasts
+const FADE_TIME: f32 = 0.4;
+// colors of the levels when coloring is on
+const COLORS: [[f32; 4]; 4] = [
+    [0.9, 0.3, 0.2, 1.0],
+    [0.9, 0.8, 0.2, 1.0],
+    [0.3, 0.8, 0.3, 1.0],
+    [0.3, 0.5, 0.9, 1.0],
+];
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // share of the pixels drawn, from the start of the pattern or its end
+    #[name = "u_Fade"]
+    fade: f32,
+    #[name = "u_Outgoing"]
+    outgoing: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Fade;
+    uniform float u_Outgoing;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    const float BAYER[16] = float[16](
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0);
+
+    void main() {
+        ivec2 p = ivec2(gl_FragCoord.xy) % 4;
+        float threshold = (BAYER[p.y * 4 + p.x] + 0.5) / 16.0;
+        // the outgoing level keeps exactly the pixels the incoming one skips
+        if ((threshold < u_Fade) == (u_Outgoing > 0.5)) {
+            discard;
+        }
+        vec3 light = normalize(vec3(0.3, -0.5, 0.8));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        o_Color = vec4(u_Color * (0.2 + 0.8 * diffuse), 1.0);
+    }
+";
+
+struct Object {
+    model: [[f32; 4]; 4],
+    pos: Point3<f32>,
+    color: [f32; 3],
+    level: usize,
+    // the level faded out of, and how far along the fade is
+    previous: usize,
+    fade: f32,
+}
+
+// The level for `distance`, staying at `current` within the hysteresis
+fn select_level(current: usize, distance: f32, scale: f32) -> usize {
+    let mut level = current;
+    while level < DISTANCES.len() && distance > DISTANCES[level] * scale * (1.0 + HYSTERESIS) {
+        level += 1;
+    }
+    while level > 0 && distance < DISTANCES[level - 1] * scale * (1.0 - HYSTERESIS) {
+        level -= 1;
+    }
+    level
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("LOD example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    // One mesh per level, from the closest to the furthest
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut meshes = MeshCache::new();
+    let levels = vec![
+        meshes.torus(&mut factory, 96, 48, 0.6, 0.25),
+        meshes.torus(&mut factory, 48, 24, 0.6, 0.25),
+        meshes.torus(&mut factory, 24, 12, 0.6, 0.25),
+        meshes.torus(&mut factory, 12, 6, 0.6, 0.25),
+    ];
+    let triangles: Vec<usize> = levels.iter().map(|&(_, ref slice)| {
+        (slice.end - slice.start) as usize / 3
+    }).collect();
+    let mut batches: Vec<_> = levels.into_iter().map(|(mesh, slice)| {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: [0.0; 3],
+            fade: 1.0,
+            outgoing: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    }).collect();
+
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut objects: Vec<Object> = (0 .. GRID_SIZE * GRID_SIZE).map(|i| {
+        let offset = 0.5 * (GRID_SIZE - 1) as f32;
+        let x = ((i % GRID_SIZE) as f32 - offset) * SPACING;
+        let y = ((i / GRID_SIZE) as f32 - offset) * SPACING;
+        let rotation = Matrix3::from_angle_z(cgmath::rad(2.0 * PI * rng.gen::<f32>()))
+                               .mul_m(&Matrix3::from_angle_x(cgmath::rad(0.5 * PI)));
+        let mut model = rotation.mul_s(1.5).to_matrix4();
+        model.w = Vector4::new(x, y, 1.2, 1.0);
+        Object {
+            model: model.into_fixed(),
+            pos: Point3::new(x, y, 1.2),
+            color: [0.5 + 0.4 * rng.gen::<f32>(), 0.5, 0.5 + 0.4 * rng.gen::<f32>()],
+            level: 0,
+            previous: 0,
+            fade: 1.0,
+        }
+    }).collect();
+
+    // Without a font, the statistics go into the window title
+    let mut hud = match Font::load_default(16) {
+        Ok(font) => Some(TextRenderer::new(&mut factory, font, 512)),
+        Err(e) => {
+            println!("{}, showing statistics in the window title", e);
+            None
+        },
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 200.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 20.0, -1.0, 0.3);
+
+    let clear_data = gfx::ClearData {
+        color: [0.5, 0.6, 0.7, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut scale = 1.0f32;
+    let mut cross_fade = true;
+    let mut show_levels = false;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::F => cross_fade = !cross_fade,
+                    VirtualKeyCode::C => show_levels = !show_levels,
+                    VirtualKeyCode::Up => scale = (scale * 1.25).min(8.0),
+                    VirtualKeyCode::Down => scale = (scale / 1.25).max(0.25),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        let mut step = 0.0;
+        clock.update();
+        while clock.tick() {
+            step += clock.step();
+        }
+
+        // Pick the levels, and carry on with the fades
+        let eye = camera.position();
+        for object in objects.iter_mut() {
+            let distance = object.pos.sub_p(&eye).length();
+            let level = select_level(object.level, distance, scale);
+            if level != object.level {
+                object.previous = object.level;
+                object.level = level;
+                object.fade = if cross_fade { 0.0 } else { 1.0 };
+            }
+            object.fade = (object.fade + step / FADE_TIME).min(1.0);
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        let mut counts = [0usize; 4];
+        let mut drawn = 0;
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for object in objects.iter() {
+            let fading = object.fade < 1.0;
+            let parts = if fading {
+                vec![(object.level, object.fade, 0.0), (object.previous, object.fade, 1.0)]
+            } else {
+                vec![(object.level, 1.0, 0.0)]
+            };
+            for &(level, fade, outgoing) in parts.iter() {
+                let batch = &mut batches[level];
+                batch.params.view_proj = view_proj;
+                batch.params.model = object.model;
+                batch.params.color = if show_levels {
+                    [COLORS[level][0], COLORS[level][1], COLORS[level][2]]
+                } else {
+                    object.color
+                };
+                batch.params.fade = fade;
+                batch.params.outgoing = outgoing;
+                renderer.draw(&(&*batch, &context), &wrap).unwrap();
+                drawn += triangles[level];
+            }
+            counts[object.level] += 1;
+        }
+
+        let all_finest = objects.len() * triangles[0];
+        let stats = format!("{} triangles, {:.1}% of {} at the finest level",
+                            drawn, 100.0 * drawn as f32 / all_finest as f32, all_finest);
+        match hud {
+            Some(ref mut hud) => {
+                let mut spans = vec![Span::new(&stats, [1.0, 1.0, 1.0, 1.0])];
+                let lines: Vec<String> = (0 .. 4).map(|i|
+                    format!("\nlevel {}: {} objects of {} triangles", i, counts[i], triangles[i])
+                ).collect();
+                for (i, line) in lines.iter().enumerate() {
+                    let color = if show_levels { COLORS[i] } else { [1.0, 1.0, 1.0, 1.0] };
+                    spans.push(Span::new(line, color));
+                }
+                hud.add(&spans, [10.0, 10.0], None);
+                hud.draw(&mut factory, &mut renderer, &wrap);
+            },
+            None => wrap.window.set_title(&format!(
+                "LOD example: {}, {:?} objects per level", stats, counts)),
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
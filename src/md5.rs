@@ -0,0 +1,555 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loading rigged meshes and their animation clips from MD5 files.
+//!
+//! MD5 is the text format of Doom 3, which most modelling tools can export.
+//! A `.md5mesh` file has the joints of a skeleton in their bind pose and the
+//! meshes skinned to them, and each `.md5anim` file has one clip, the local
+//! pose of every joint at a fixed frame rate. Both are Z-up like the
+//! examples, so nothing is turned.
+//!
+//! The vertices of an MD5 mesh are made of weights, each a position in the
+//! space of its joint. They are put together into bind pose positions, with
+//! the four heaviest joints of each vertex kept for skinning on the GPU, and
+//! normals are averaged from the faces since the format has none. MD5 faces
+//! are clockwise, so they are turned around to match the other meshes.
+//!
+//! ```ignore
+//! let model = md5::parse_mesh("tentacle", TENTACLE_SRC).unwrap();
+//! let clip = md5::load_anim("walk.md5anim").unwrap();
+//! clip.sample(time, &mut poses);
+//! md5::skin(&model.joints, &poses, &mut matrices);
+//! ```
+
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+/// Joints a vertex is skinned to, at most.
+pub const MAX_WEIGHTS: usize = 4;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+pub struct Vertex {
+    #[name = "a_Pos"]
+    pub pos: [f32; 3],
+    #[name = "a_Normal"]
+    pub normal: [f32; 3],
+    #[name = "a_TexCoord"]
+    pub tex_coord: [f32; 2],
+    #[as_float]
+    #[name = "a_Joints"]
+    pub joints: [u8; 4],
+    /// Summing up to 1, the unused ones being 0.
+    #[name = "a_Weights"]
+    pub weights: [f32; 4],
+}
+
+/// A position and an orientation, the quaternion being `[x, y, z, w]`.
+#[derive(Clone, Copy, Debug)]
+pub struct Pose {
+    pub pos: [f32; 3],
+    pub orient: [f32; 4],
+}
+
+pub struct Joint {
+    pub name: String,
+    /// Always before the joint itself.
+    pub parent: Option<usize>,
+    /// In model space.
+    pub bind: Pose,
+}
+
+pub struct Model {
+    pub joints: Vec<Joint>,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+/// The local poses of the joints, relative to their parents, at every frame.
+pub struct Clip {
+    pub frame_rate: f32,
+    frames: Vec<Vec<Pose>>,
+}
+
+// The words of a file, with the parentheses and braces on their own and the
+// comments left out
+struct Tokens<'a> {
+    name: &'a str,
+    words: Vec<&'a str>,
+    next: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(name: &'a str, src: &'a str) -> Tokens<'a> {
+        let mut words = Vec::new();
+        for line in src.lines() {
+            let line = match line.find("//") {
+                Some(i) => &line[.. i],
+                None => line,
+            };
+            let mut start = None;
+            let mut quoted = false;
+            for (i, c) in line.char_indices() {
+                if quoted {
+                    if c == '"' {
+                        words.push(&line[start.unwrap() .. i]);
+                        start = None;
+                        quoted = false;
+                    }
+                    continue;
+                }
+                let single = c == '(' || c == ')' || c == '{' || c == '}';
+                if c.is_whitespace() || single || c == '"' {
+                    if let Some(s) = start.take() {
+                        words.push(&line[s .. i]);
+                    }
+                }
+                if single {
+                    words.push(&line[i .. i + 1]);
+                } else if c == '"' {
+                    start = Some(i + 1);
+                    quoted = true;
+                } else if !c.is_whitespace() && start.is_none() {
+                    start = Some(i);
+                }
+            }
+            if let Some(s) = start {
+                words.push(&line[s ..]);
+            }
+        }
+        Tokens { name: name, words: words, next: 0 }
+    }
+
+    fn error(&self, what: &str) -> String {
+        format!("Malformed {}: expected {} at word {}", self.name, what, self.next)
+    }
+
+    fn word(&mut self) -> Option<&'a str> {
+        let word = self.words.get(self.next).map(|w| *w);
+        self.next += 1;
+        word
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), String> {
+        match self.word() {
+            Some(w) if w == word => Ok(()),
+            _ => Err(self.error(word)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.word().map(|w| w.to_string()).ok_or(self.error("a string"))
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, String> {
+        match self.word().and_then(|w| w.parse().ok()) {
+            Some(n) => Ok(n),
+            None => Err(self.error("a number")),
+        }
+    }
+
+    // `( x y z )`
+    fn vector(&mut self) -> Result<[f32; 3], String> {
+        try!(self.expect("("));
+        let v = [try!(self.number()), try!(self.number()), try!(self.number())];
+        try!(self.expect(")"));
+        Ok(v)
+    }
+
+    fn parent(&mut self) -> Result<Option<usize>, String> {
+        let parent: i32 = try!(self.number());
+        Ok(if parent < 0 { None } else { Some(parent as usize) })
+    }
+
+    fn header(&mut self) -> Result<(), String> {
+        try!(self.expect("MD5Version"));
+        if try!(self.number::<u32>()) != 10 {
+            return Err(format!("Unsupported version of {}", self.name));
+        }
+        try!(self.expect("commandline"));
+        self.string().map(|_| ())
+    }
+}
+
+// The unit quaternion of `[x, y, z]`, its w being negative by convention
+fn orientation(v: [f32; 3]) -> [f32; 4] {
+    let t = 1.0 - v[0] * v[0] - v[1] * v[1] - v[2] * v[2];
+    [v[0], v[1], v[2], -t.max(0.0).sqrt()]
+}
+
+fn mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    [a[3] * b[0] + a[0] * b[3] + a[1] * b[2] - a[2] * b[1],
+     a[3] * b[1] - a[0] * b[2] + a[1] * b[3] + a[2] * b[0],
+     a[3] * b[2] + a[0] * b[1] - a[1] * b[0] + a[2] * b[3],
+     a[3] * b[3] - a[0] * b[0] - a[1] * b[1] - a[2] * b[2]]
+}
+
+fn conjugate(q: [f32; 4]) -> [f32; 4] {
+    [-q[0], -q[1], -q[2], q[3]]
+}
+
+fn rotate(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    let r = mul(mul(q, [v[0], v[1], v[2], 0.0]), conjugate(q));
+    [r[0], r[1], r[2]]
+}
+
+impl Pose {
+    /// The pose `amount` of the way to `other`, the orientations taking the
+    /// shortest way around.
+    pub fn blend(&self, other: &Pose, amount: f32) -> Pose {
+        let p = &self.pos;
+        let q = &self.orient;
+        let dot = q[0] * other.orient[0] + q[1] * other.orient[1] +
+                  q[2] * other.orient[2] + q[3] * other.orient[3];
+        let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+        let mut orient = [0.0; 4];
+        for k in 0 .. 4 {
+            orient[k] = q[k] + (sign * other.orient[k] - q[k]) * amount;
+        }
+        let length = orient.iter().fold(0.0, |l, c| l + c * c).sqrt();
+        for c in orient.iter_mut() {
+            *c /= length;
+        }
+        Pose {
+            pos: [p[0] + (other.pos[0] - p[0]) * amount,
+                  p[1] + (other.pos[1] - p[1]) * amount,
+                  p[2] + (other.pos[2] - p[2]) * amount],
+            orient: orient,
+        }
+    }
+
+    // `local` relative to this one
+    fn then(&self, local: &Pose) -> Pose {
+        let r = rotate(self.orient, local.pos);
+        Pose {
+            pos: [self.pos[0] + r[0], self.pos[1] + r[1], self.pos[2] + r[2]],
+            orient: mul(self.orient, local.orient),
+        }
+    }
+
+    /// The column-major matrix of the pose.
+    pub fn to_matrix(&self) -> [[f32; 4]; 4] {
+        let (x, y, z, w) = (self.orient[0], self.orient[1], self.orient[2], self.orient[3]);
+        [[1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w), 0.0],
+         [2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w), 0.0],
+         [2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+         [self.pos[0], self.pos[1], self.pos[2], 1.0]]
+    }
+}
+
+fn read(path: &str) -> Result<String, String> {
+    let mut src = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut src))
+                         .map_err(|e| format!("Failed to read {}: {}", path, e)));
+    Ok(src)
+}
+
+/// Loads the skeleton and all the meshes of a `.md5mesh` file, as one mesh.
+pub fn load_mesh(path: &str) -> Result<Model, String> {
+    let src = try!(read(path));
+    parse_mesh(path, &src)
+}
+
+/// Loads the clip of a `.md5anim` file.
+pub fn load_anim(path: &str) -> Result<Clip, String> {
+    let src = try!(read(path));
+    parse_anim(path, &src)
+}
+
+/// Reads the contents of a `.md5mesh` file, `name` only telling it apart in
+/// the errors.
+pub fn parse_mesh(name: &str, src: &str) -> Result<Model, String> {
+    let mut tokens = Tokens::new(name, src);
+    try!(tokens.header());
+    let mut model = Model { joints: Vec::new(), vertices: Vec::new(), indices: Vec::new() };
+
+    while let Some(word) = tokens.word() {
+        match word {
+            "numJoints" | "numMeshes" => { try!(tokens.number::<usize>()); },
+            "joints" => {
+                try!(tokens.expect("{"));
+                while tokens.words.get(tokens.next) != Some(&"}") {
+                    let name = try!(tokens.string());
+                    let parent = try!(tokens.parent());
+                    let pos = try!(tokens.vector());
+                    let orient = orientation(try!(tokens.vector()));
+                    if parent.map_or(false, |p| p >= model.joints.len()) {
+                        return Err(tokens.error("a parent before its children"));
+                    }
+                    model.joints.push(Joint {
+                        name: name,
+                        parent: parent,
+                        bind: Pose { pos: pos, orient: orient },
+                    });
+                }
+                try!(tokens.expect("}"));
+            },
+            "mesh" => try!(parse_submesh(&mut tokens, &mut model)),
+            _ => return Err(tokens.error("joints or a mesh")),
+        }
+    }
+    if model.joints.len() > 256 {
+        return Err(format!("{} has more joints than 8 bit indices can count", name));
+    }
+    Ok(model)
+}
+
+// One `mesh { }` block, appended to the model
+fn parse_submesh(tokens: &mut Tokens, model: &mut Model) -> Result<(), String> {
+    // texture coordinate, first weight and weight count of each vertex
+    let mut verts = Vec::new();
+    let mut tris = Vec::new();
+    // joint, bias and position of each weight
+    let mut weights = Vec::new();
+
+    try!(tokens.expect("{"));
+    loop {
+        match tokens.word() {
+            Some("}") => break,
+            Some("shader") => { try!(tokens.string()); },
+            Some("numverts") | Some("numtris") | Some("numweights") => {
+                try!(tokens.number::<usize>());
+            },
+            Some("vert") => {
+                try!(tokens.number::<usize>());
+                try!(tokens.expect("("));
+                let s: f32 = try!(tokens.number());
+                let t: f32 = try!(tokens.number());
+                try!(tokens.expect(")"));
+                let start: usize = try!(tokens.number());
+                let count: usize = try!(tokens.number());
+                verts.push(([s, 1.0 - t], start, count));
+            },
+            Some("tri") => {
+                try!(tokens.number::<usize>());
+                let a: u32 = try!(tokens.number());
+                let b: u32 = try!(tokens.number());
+                let c: u32 = try!(tokens.number());
+                tris.push([a, c, b]);
+            },
+            Some("weight") => {
+                try!(tokens.number::<usize>());
+                let joint: usize = try!(tokens.number());
+                let bias: f32 = try!(tokens.number());
+                let pos = try!(tokens.vector());
+                if joint >= model.joints.len() {
+                    return Err(tokens.error("a joint of the skeleton"));
+                }
+                weights.push((joint, bias, pos));
+            },
+            _ => return Err(tokens.error("a vertex, a triangle or a weight")),
+        }
+    }
+
+    let base = model.vertices.len() as u32;
+    for &(tex_coord, start, count) in verts.iter() {
+        if count == 0 || start + count > weights.len() {
+            return Err(tokens.error("weights of a vertex"));
+        }
+        let mut bound = weights[start .. start + count].to_vec();
+        let mut pos = [0.0; 3];
+        for &(joint, bias, offset) in bound.iter() {
+            let bind = &model.joints[joint].bind;
+            let p = rotate(bind.orient, offset);
+            for k in 0 .. 3 {
+                pos[k] += bias * (bind.pos[k] + p[k]);
+            }
+        }
+        // the heaviest ones, weighing 1 together
+        bound.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        bound.truncate(MAX_WEIGHTS);
+        let total = bound.iter().fold(0.0, |t, w| t + w.1);
+        let mut vertex = Vertex {
+            pos: pos,
+            normal: [0.0; 3],
+            tex_coord: tex_coord,
+            joints: [0; MAX_WEIGHTS],
+            weights: [0.0; MAX_WEIGHTS],
+        };
+        for (k, &(joint, bias, _)) in bound.iter().enumerate() {
+            vertex.joints[k] = joint as u8;
+            vertex.weights[k] = bias / total;
+        }
+        model.vertices.push(vertex);
+    }
+
+    for tri in tris.iter() {
+        if tri.iter().any(|&i| i as usize >= verts.len()) {
+            return Err(tokens.error("a triangle of the mesh's vertices"));
+        }
+        // the normal of the face, as long as it is large
+        let (a, b, c) = (model.vertices[(base + tri[0]) as usize].pos,
+                         model.vertices[(base + tri[1]) as usize].pos,
+                         model.vertices[(base + tri[2]) as usize].pos);
+        let e1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let e2 = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [e1[1] * e2[2] - e1[2] * e2[1],
+                 e1[2] * e2[0] - e1[0] * e2[2],
+                 e1[0] * e2[1] - e1[1] * e2[0]];
+        for &i in tri.iter() {
+            let normal = &mut model.vertices[(base + i) as usize].normal;
+            for k in 0 .. 3 {
+                normal[k] += n[k];
+            }
+            model.indices.push(base + i);
+        }
+    }
+    for v in model.vertices[base as usize ..].iter_mut() {
+        let n = v.normal;
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        if length > 0.0 {
+            v.normal = [n[0] / length, n[1] / length, n[2] / length];
+        }
+    }
+    Ok(())
+}
+
+/// Reads the contents of a `.md5anim` file, `name` only telling it apart in
+/// the errors.
+pub fn parse_anim(name: &str, src: &str) -> Result<Clip, String> {
+    let mut tokens = Tokens::new(name, src);
+    try!(tokens.header());
+    let mut frame_rate = 24.0;
+    // parent, animated components and first of them in a frame, of each joint
+    let mut hierarchy = Vec::new();
+    let mut base = Vec::new();
+    let mut frames = Vec::new();
+
+    while let Some(word) = tokens.word() {
+        match word {
+            "numFrames" | "numJoints" | "numAnimatedComponents" => {
+                try!(tokens.number::<usize>());
+            },
+            "frameRate" => frame_rate = try!(tokens.number()),
+            "hierarchy" => {
+                try!(tokens.expect("{"));
+                while tokens.words.get(tokens.next) != Some(&"}") {
+                    try!(tokens.string());
+                    let parent = try!(tokens.parent());
+                    let flags: u32 = try!(tokens.number());
+                    let start: usize = try!(tokens.number());
+                    hierarchy.push((parent, flags, start));
+                }
+                try!(tokens.expect("}"));
+            },
+            "bounds" => {
+                // only used for culling, which the examples do themselves
+                try!(tokens.expect("{"));
+                while tokens.words.get(tokens.next) != Some(&"}") {
+                    try!(tokens.vector());
+                    try!(tokens.vector());
+                }
+                try!(tokens.expect("}"));
+            },
+            "baseframe" => {
+                try!(tokens.expect("{"));
+                while tokens.words.get(tokens.next) != Some(&"}") {
+                    let pos = try!(tokens.vector());
+                    let orient = try!(tokens.vector());
+                    base.push((pos, orient));
+                }
+                try!(tokens.expect("}"));
+            },
+            "frame" => {
+                try!(tokens.number::<usize>());
+                try!(tokens.expect("{"));
+                let mut components = Vec::new();
+                while tokens.words.get(tokens.next) != Some(&"}") {
+                    components.push(try!(tokens.number::<f32>()));
+                }
+                try!(tokens.expect("}"));
+                if base.len() != hierarchy.len() {
+                    return Err(tokens.error("a base pose for every joint"));
+                }
+                // the base pose, with the components that are animated
+                // replaced in the order tx, ty, tz, qx, qy, qz
+                let mut poses = Vec::with_capacity(hierarchy.len());
+                for (&(_, flags, start), &(pos, orient)) in hierarchy.iter().zip(base.iter()) {
+                    let mut values = [pos[0], pos[1], pos[2], orient[0], orient[1], orient[2]];
+                    let mut next = start;
+                    for k in 0 .. 6 {
+                        if flags & (1 << k) != 0 {
+                            match components.get(next) {
+                                Some(&c) => values[k] = c,
+                                None => return Err(tokens.error("more components")),
+                            }
+                            next += 1;
+                        }
+                    }
+                    poses.push(Pose {
+                        pos: [values[0], values[1], values[2]],
+                        orient: orientation([values[3], values[4], values[5]]),
+                    });
+                }
+                frames.push(poses);
+            },
+            _ => return Err(tokens.error("the hierarchy, the base pose or a frame")),
+        }
+    }
+    if frames.is_empty() {
+        return Err(format!("{} has no frames", name));
+    }
+    Ok(Clip { frame_rate: frame_rate, frames: frames })
+}
+
+impl Clip {
+    /// Length of the clip in seconds, looping back to the first frame.
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 / self.frame_rate
+    }
+
+    /// Number of joints the clip animates.
+    pub fn joints(&self) -> usize {
+        self.frames[0].len()
+    }
+
+    /// Fills `poses` with the local pose of every joint at `time`, blending
+    /// the two frames around it and looping from the last to the first.
+    pub fn sample(&self, time: f32, poses: &mut [Pose]) {
+        let position = ((time / self.duration()).fract() + 1.0).fract() * self.frames.len() as f32;
+        let a = (position as usize).min(self.frames.len() - 1);
+        let b = (a + 1) % self.frames.len();
+        let amount = position - a as f32;
+        for (pose, (pa, pb)) in poses.iter_mut()
+                                     .zip(self.frames[a].iter().zip(self.frames[b].iter())) {
+            *pose = pa.blend(pb, amount);
+        }
+    }
+}
+
+/// Walks the skeleton from the root with the local `poses` of its joints,
+/// and fills `matrices` with the skinning matrix of each joint, taking the
+/// vertices from the bind pose to where the joint is now.
+pub fn skin(joints: &[Joint], poses: &[Pose], matrices: &mut [[[f32; 4]; 4]]) {
+    let mut world: Vec<Pose> = Vec::with_capacity(joints.len());
+    for (joint, local) in joints.iter().zip(poses.iter()) {
+        let pose = match joint.parent {
+            Some(p) => world[p].then(local),
+            None => *local,
+        };
+        world.push(pose);
+    }
+    for ((joint, pose), matrix) in joints.iter().zip(world.iter()).zip(matrices.iter_mut()) {
+        // the pose after the inverse of the bind pose
+        let orient = mul(pose.orient, conjugate(joint.bind.orient));
+        let r = rotate(orient, joint.bind.pos);
+        let skinning = Pose {
+            pos: [pose.pos[0] - r[0], pose.pos[1] - r[1], pose.pos[2] - r[2]],
+            orient: orient,
+        };
+        *matrix = skinning.to_matrix();
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Meshes uploaded once and shared by every batch drawing them.
+//!
+//! A `gfx::Mesh` and a `gfx::Slice` only hold handles to their buffers, so
+//! any number of batches can draw from the same ones. A `MeshCache` keeps the
+//! meshes of `shape` by name, uploading each the first time it is asked for
+//! and handing out the same buffers afterwards, instead of every batch
+//! uploading a full-screen quad or a cube of its own. Meshes of other shapes
+//! go in with `get` under a name of their own.
+//!
+//! ```ignore
+//! let mut meshes = MeshCache::new();
+//! let (mesh, slice) = meshes.cube(&mut factory);
+//! let cube = context.make_batch(&program, data, &mesh, slice, &state).unwrap();
+//! // the same buffers again
+//! let (mesh, slice) = meshes.cube(&mut factory);
+//! ```
+
+use std::collections::HashMap;
+use gfx;
+use gfx::traits::*;
+use index;
+use shape::{self, Vertex};
+
+pub struct MeshCache<R: gfx::Resources> {
+    meshes: HashMap<String, (gfx::Mesh<R>, gfx::Slice<R>)>,
+}
+
+fn widen(indices: Vec<u16>) -> Vec<u32> {
+    indices.into_iter().map(|i| i as u32).collect()
+}
+
+impl<R: gfx::Resources> MeshCache<R> {
+    pub fn new() -> MeshCache<R> {
+        MeshCache { meshes: HashMap::new() }
+    }
+
+    /// The mesh called `name`, and the slice drawing all of its triangles.
+    /// `build` makes its vertices and indices the first time only, no
+    /// indices meaning that the vertices are drawn in order.
+    pub fn get<F, B>(&mut self, factory: &mut F, name: &str, build: B)
+                     -> (gfx::Mesh<R>, gfx::Slice<R>)
+                     where F: gfx::Factory<R>, B: FnOnce() -> (Vec<Vertex>, Vec<u32>) {
+        if let Some(&(ref mesh, ref slice)) = self.meshes.get(name) {
+            return (mesh.clone(), slice.clone());
+        }
+        let (vertices, indices) = build();
+        let mesh = factory.create_mesh(&vertices);
+        let slice = if indices.is_empty() {
+            mesh.to_slice(gfx::PrimitiveType::TriangleList)
+        } else {
+            index::slice(factory, &indices, gfx::PrimitiveType::TriangleList)
+        };
+        self.meshes.insert(name.to_string(), (mesh.clone(), slice.clone()));
+        (mesh, slice)
+    }
+
+    /// Number of meshes uploaded so far.
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    /// `shape::quad`.
+    pub fn quad<F: gfx::Factory<R>>(&mut self, factory: &mut F) -> (gfx::Mesh<R>, gfx::Slice<R>) {
+        self.get(factory, "quad", || (shape::quad(), Vec::new()))
+    }
+
+    /// `shape::cube`.
+    pub fn cube<F: gfx::Factory<R>>(&mut self, factory: &mut F) -> (gfx::Mesh<R>, gfx::Slice<R>) {
+        self.get(factory, "cube", || {
+            let (vertices, indices) = shape::cube();
+            (vertices, widen(indices))
+        })
+    }
+
+    /// `shape::plane`.
+    pub fn plane<F: gfx::Factory<R>>(&mut self, factory: &mut F) -> (gfx::Mesh<R>, gfx::Slice<R>) {
+        self.get(factory, "plane", || {
+            let (vertices, indices) = shape::plane();
+            (vertices, widen(indices))
+        })
+    }
+
+    /// `shape::sphere`, one for each number of rings and segments.
+    pub fn sphere<F>(&mut self, factory: &mut F, rings: u16, segments: u16)
+                     -> (gfx::Mesh<R>, gfx::Slice<R>) where F: gfx::Factory<R> {
+        self.get(factory, &format!("sphere {}x{}", rings, segments), || {
+            let (vertices, indices) = shape::sphere(rings, segments);
+            (vertices, widen(indices))
+        })
+    }
+
+    /// `shape::torus`, one for each size and number of rings and segments.
+    pub fn torus<F>(&mut self, factory: &mut F, rings: u16, segments: u16,
+                    radius: f32, thickness: f32) -> (gfx::Mesh<R>, gfx::Slice<R>)
+                    where F: gfx::Factory<R> {
+        let name = format!("torus {}x{} {} {}", rings, segments, radius, thickness);
+        self.get(factory, &name, || {
+            let (vertices, indices) = shape::torus(rings, segments, radius, thickness);
+            (vertices, widen(indices))
+        })
+    }
+
+    /// `shape::capsule`, one for each size and number of rings and segments.
+    pub fn capsule<F>(&mut self, factory: &mut F, rings: u16, segments: u16,
+                      radius: f32, half_length: f32) -> (gfx::Mesh<R>, gfx::Slice<R>)
+                      where F: gfx::Factory<R> {
+        let name = format!("capsule {}x{} {} {}", rings, segments, radius, half_length);
+        self.get(factory, &name, || {
+            let (vertices, indices) = shape::capsule(rings, segments, radius, half_length);
+            (vertices, widen(indices))
+        })
+    }
+}
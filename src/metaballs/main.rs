@@ -0,0 +1,423 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of marching cubes over metaballs.
+//
+// Every frame, the field of a few moving balls is sampled on a grid, and
+// marching cubes turns every cell crossed by the surface into triangles on
+// the CPU. They are streamed into a vertex buffer created once with room for
+// the largest mesh, and drawn with the slice covering the vertices of the
+// frame.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press M to
+// switch between matcap and Phong shading, Up and Down to change the
+// resolution of the grid, and Space to pause the balls.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate time;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const MAX_VERTICES: usize = 600000;
+const BOUND: f32 = 1.5;
+const BALLS: usize = 7;
+// The surface is where the field crosses this value
+const THRESHOLD: f32 = 1.0;
+const MATCAP_SIZE: usize = 256;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    #[name = "u_View"]
+    view: [[f32; 4]; 4],
+    // 1 for the matcap, 0 for Phong
+    #[name = "u_Matcap"]
+    matcap: f32,
+    #[name = "t_Matcap"]
+    matcap_texture: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Proj;
+    uniform mat4 u_View;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_ViewPos;
+    out vec3 v_ViewNormal;
+
+    void main() {
+        vec4 pos = u_View * vec4(a_Pos, 1.0);
+        v_ViewPos = pos.xyz;
+        v_ViewNormal = mat3(u_View) * a_Normal;
+        gl_Position = u_Proj * pos;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Matcap;
+    uniform sampler2D t_Matcap;
+    in vec3 v_ViewPos;
+    in vec3 v_ViewNormal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_ViewNormal);
+        if (u_Matcap > 0.5) {
+            // the sphere of the matcap faces the eye, so the view space
+            // normal picks the point of the same orientation
+            o_Color = texture(t_Matcap, 0.5 + 0.5 * n.xy);
+        } else {
+            vec3 l = normalize(vec3(-0.4, 0.6, 0.7));
+            vec3 h = normalize(l + normalize(-v_ViewPos));
+            vec3 color = vec3(0.9, 0.35, 0.2) * (0.15 + max(dot(n, l), 0.0))
+                       + vec3(0.5) * pow(max(dot(n, h), 0.0), 60.0);
+            o_Color = vec4(color, 1.0);
+        }
+    }
+";
+
+// Corners of a cell are numbered by their offsets as bits, with X the lowest
+static EDGES: [(usize, usize); 12] = [
+    (0, 1), (0, 2), (0, 4), (1, 3), (1, 5), (2, 3),
+    (2, 6), (3, 7), (4, 5), (4, 6), (5, 7), (6, 7),
+];
+
+// Corners around each face, counter-clockwise seen from outside the cell
+static FACES: [[usize; 4]; 6] = [
+    [4, 6, 2, 0], [1, 3, 7, 5], [1, 5, 4, 0],
+    [2, 6, 7, 3], [2, 3, 1, 0], [4, 5, 7, 6],
+];
+
+fn edge_between(a: usize, b: usize) -> usize {
+    EDGES.iter().position(|&(p, q)| (p, q) == (a, b) || (p, q) == (b, a)).unwrap()
+}
+
+/// Builds the triangles for each of the 256 sets of corners inside the
+/// surface, as the edges their vertices lie on.
+///
+/// Instead of the usual hand-written table, the surface is traced on the
+/// faces of the cell. Each run of inside corners along a face is cut off by
+/// a segment, so a face with two opposite inside corners gets two segments,
+/// the same way from the cells on both of its sides, and the mesh has no
+/// holes. Around the cell, the segments link up into loops, which are cut
+/// into fans facing the outside.
+fn create_table() -> Vec<Vec<[usize; 3]>> {
+    (0 .. 256).map(|mask: usize| {
+        let inside = |corner: usize| mask & (1 << corner) != 0;
+        let mut next = [None; 12];
+        for face in FACES.iter() {
+            for k in 0 .. 4 {
+                if inside(face[k]) || !inside(face[(k + 1) % 4]) {
+                    continue;
+                }
+                let mut j = (k + 1) % 4;
+                while inside(face[(j + 1) % 4]) {
+                    j = (j + 1) % 4;
+                }
+                let enter = edge_between(face[k], face[(k + 1) % 4]);
+                next[enter] = Some(edge_between(face[j], face[(j + 1) % 4]));
+            }
+        }
+        let mut triangles = Vec::new();
+        for start in 0 .. 12 {
+            let mut edges = Vec::new();
+            let mut edge = start;
+            while let Some(n) = next[edge].take() {
+                edges.push(edge);
+                edge = n;
+            }
+            for i in 2 .. edges.len() {
+                triangles.push([edges[0], edges[i - 1], edges[i]]);
+            }
+        }
+        triangles
+    }).collect()
+}
+
+struct Ball {
+    radius: f32,
+    // of the motion along each axis
+    freq: [f32; 3],
+    phase: [f32; 3],
+}
+
+impl Ball {
+    fn center(&self, t: f32) -> [f32; 3] {
+        let at = |k: usize| 0.8 * (self.freq[k] * t + self.phase[k]).sin();
+        [at(0), at(1), at(2)]
+    }
+}
+
+// The sum of r² / d² over the balls, and its gradient
+fn field(centers: &[([f32; 3], f32)], p: [f32; 3]) -> (f32, [f32; 3]) {
+    let mut value = 0.0;
+    let mut gradient = [0.0; 3];
+    for &(c, radius) in centers.iter() {
+        let d = [p[0] - c[0], p[1] - c[1], p[2] - c[2]];
+        let d2 = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).max(1e-6);
+        let f = radius * radius / d2;
+        value += f;
+        for k in 0 .. 3 {
+            gradient[k] -= 2.0 * f / d2 * d[k];
+        }
+    }
+    (value, gradient)
+}
+
+/// Marches the cells of a grid with `cells` on each side over the field of
+/// the balls, filling `vertices` with triangles. `values` holds the samples
+/// between calls, so neither vector is allocated again once large enough.
+fn polygonise(table: &[Vec<[usize; 3]>], centers: &[([f32; 3], f32)], cells: usize,
+              values: &mut Vec<f32>, vertices: &mut Vec<Vertex>) {
+    let n = cells + 1;
+    let size = 2.0 * BOUND / cells as f32;
+    let position = |x: usize, y: usize, z: usize| {
+        [x as f32 * size - BOUND, y as f32 * size - BOUND, z as f32 * size - BOUND]
+    };
+    values.clear();
+    for z in 0 .. n {
+        for y in 0 .. n {
+            for x in 0 .. n {
+                values.push(field(centers, position(x, y, z)).0);
+            }
+        }
+    }
+
+    vertices.clear();
+    for z in 0 .. cells {
+        for y in 0 .. cells {
+            for x in 0 .. cells {
+                let corner = |i: usize| (x + (i & 1), y + ((i >> 1) & 1), z + (i >> 2));
+                let value = |i: usize| {
+                    let (cx, cy, cz) = corner(i);
+                    values[(cz * n + cy) * n + cx]
+                };
+                let mask = (0 .. 8).filter(|&i| value(i) > THRESHOLD)
+                                   .fold(0, |mask, i| mask | (1 << i));
+                for triangle in table[mask].iter() {
+                    if vertices.len() + 3 > MAX_VERTICES {
+                        return;
+                    }
+                    for &edge in triangle.iter() {
+                        let (a, b) = EDGES[edge];
+                        // where the field crosses the threshold along the edge
+                        let t = (THRESHOLD - value(a)) / (value(b) - value(a));
+                        let (ax, ay, az) = corner(a);
+                        let (bx, by, bz) = corner(b);
+                        let (pa, pb) = (position(ax, ay, az), position(bx, by, bz));
+                        let pos = [pa[0] + t * (pb[0] - pa[0]),
+                                   pa[1] + t * (pb[1] - pa[1]),
+                                   pa[2] + t * (pb[2] - pa[2])];
+                        // the field grows towards the inside
+                        let g = field(centers, pos).1;
+                        let len = (g[0] * g[0] + g[1] * g[1] + g[2] * g[2]).sqrt().max(1e-6);
+                        vertices.push(Vertex {
+                            pos: pos,
+                            normal: [-g[0] / len, -g[1] / len, -g[2] / len],
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+// A shiny sphere facing the eye, lit from the top left
+fn create_matcap_data() -> Vec<u8> {
+    let mut data = Vec::with_capacity(MATCAP_SIZE * MATCAP_SIZE * 4);
+    let light = [-0.4f32, 0.6, 0.7];
+    let len = (light[0] * light[0] + light[1] * light[1] + light[2] * light[2]).sqrt();
+    for py in 0 .. MATCAP_SIZE {
+        for px in 0 .. MATCAP_SIZE {
+            let x = (px as f32 + 0.5) / MATCAP_SIZE as f32 * 2.0 - 1.0;
+            let y = (py as f32 + 0.5) / MATCAP_SIZE as f32 * 2.0 - 1.0;
+            let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+            let diffuse = ((x * light[0] + y * light[1] + z * light[2]) / len).max(0.0);
+            // the eye is along +Z, and the highlight halfway to the light
+            let (hx, hy, hz) = (light[0] / len, light[1] / len, light[2] / len + 1.0);
+            let hlen = (hx * hx + hy * hy + hz * hz).sqrt();
+            let specular = ((x * hx + y * hy + z * hz) / hlen).max(0.0).powf(40.0);
+            let rim = (1.0 - z).powf(3.0);
+            let channel = |base: f32, rim_tint: f32| {
+                let v = base * (0.1 + 0.9 * diffuse) + 0.8 * specular + rim_tint * rim;
+                (v.min(1.0) * 255.0) as u8
+            };
+            data.extend([channel(0.2, 0.3), channel(0.45, 0.6), channel(0.85, 0.9), 0xFF]
+                        .iter().cloned());
+        }
+    }
+    data
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Metaballs example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let texture = factory.create_texture_rgba8(MATCAP_SIZE as u16, MATCAP_SIZE as u16, false)
+                         .unwrap();
+    factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                           &create_matcap_data(), None).unwrap();
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let vertex_buffer = factory.create_buffer::<Vertex>(MAX_VERTICES, gfx::BufferUsage::Stream);
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+
+    let mut batch = {
+        let mesh = gfx::Mesh::from_format(vertex_buffer.clone(),
+                                          MAX_VERTICES as gfx::VertexCount);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = Params {
+            proj: proj.into_fixed(),
+            view: Matrix4::identity().into_fixed(),
+            matcap: 1.0,
+            matcap_texture: (texture, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let balls: Vec<Ball> = (0 .. BALLS).map(|i| {
+        let k = i as f32;
+        Ball {
+            radius: 0.25 + 0.05 * (k % 3.0),
+            freq: [0.5 + 0.13 * k, 0.7 - 0.07 * k, 0.4 + 0.11 * k],
+            phase: [1.7 * k, 2.9 * k + 1.0, 0.6 * k + 2.0],
+        }
+    }).collect();
+    let table = create_table();
+    let mut cells = 48;
+    let mut values = Vec::new();
+    let mut vertices = Vec::with_capacity(MAX_VERTICES);
+    let mut centers = Vec::with_capacity(BALLS);
+
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 5.0, -1.0, 0.4);
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.12, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut paused = false;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let mut sim_time = 0.0f32;
+    let mut last_report = time::precise_time_s();
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::M)) =>
+                    batch.params.matcap = 1.0 - batch.params.matcap,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) =>
+                    cells = (cells + 8).min(96),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) =>
+                    cells = (cells - 8).max(16),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    paused = !paused,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            if !paused {
+                sim_time += clock.step();
+            }
+        }
+
+        let start = time::precise_time_s();
+        centers.clear();
+        centers.extend(balls.iter().map(|b| (b.center(sim_time), b.radius)));
+        polygonise(&table, &centers, cells, &mut values, &mut vertices);
+        factory.update_buffer(&vertex_buffer, &vertices, 0);
+        batch.slice.end = vertices.len() as gfx::VertexCount;
+        let mesh_time = time::precise_time_s() - start;
+
+        batch.params.view = camera.view().mat.into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let now = time::precise_time_s();
+        if now - last_report >= 0.5 {
+            last_report = now;
+            wrap.window.set_title(&format!(
+                "Metaballs example: {} cells, {} triangles in {:.2} ms, {}",
+                cells * cells * cells, vertices.len() / 3, 1000.0 * mesh_time,
+                if batch.params.matcap > 0.5 { "matcap" } else { "Phong" }));
+        }
+    }
+}
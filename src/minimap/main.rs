@@ -0,0 +1,305 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a minimap drawn over the scene.
+//
+// The city is drawn from a flying camera over the whole window, then again
+// from straight above, with an orthographic projection centered on the
+// camera, into a small square in the top right corner. gfx sets the
+// viewport to the whole of the output, so the minimap sits in its corner as
+// the fog example splits the window: its projection is squeezed into the
+// square, and the scissor rectangle of its draw state cuts off whatever
+// falls outside of it. The scissor also draws the border, as a quad over
+// the whole window cut down to a slightly larger square. An arrow on the
+// minimap shows where the camera is and which way it looks.
+//
+// Drag with the left mouse button to look around, and press W, A, S and D
+// to move, E and Q to go up and down, and hold Left Shift to go faster.
+// Press R to turn the minimap with the camera or keep north up, and Plus
+// and Minus to zoom it.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 1 to show the color without lighting
+    #[name = "u_Flat"]
+    flat: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Flat;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 color = u_Color;
+        if (u_Flat < 0.5) {
+            vec3 n = normalize(v_Normal);
+            color *= 0.75 * max(dot(n, normalize(vec3(0.4, 0.3, 0.8))), 0.0) + 0.25;
+        }
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// Blocks along each side of the city, and the distance between them
+const BLOCKS: i32 = 12;
+const SPACING: f32 = 10.0;
+// Side of the minimap and of its border, and its margin from the corner, in
+// pixels
+const MAP_SIZE: u16 = 200;
+const BORDER: u16 = 3;
+const MARGIN: u16 = 12;
+
+fn model_matrix(pos: [f32; 3], scale: [f32; 3], yaw: f32) -> Matrix4<f32> {
+    let (c, s) = (yaw.cos(), yaw.sin());
+    Matrix4::new(c * scale[0], s * scale[0], 0.0, 0.0,
+                 -s * scale[1], c * scale[1], 0.0, 0.0,
+                 0.0, 0.0, scale[2], 0.0,
+                 pos[0], pos[1], pos[2], 1.0)
+}
+
+// An arrow pointing along +X, flat on the ground
+fn arrow() -> Vec<shape::Vertex> {
+    let vertex = |x: f32, y: f32| shape::Vertex {
+        pos: [x, y, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tex_coord: [0.0, 0.0],
+    };
+    vec![vertex(1.0, 0.0), vertex(-0.7, 0.6), vertex(-0.3, 0.0),
+         vertex(1.0, 0.0), vertex(-0.3, 0.0), vertex(-0.7, -0.6)]
+}
+
+// Squeezes a projection into the rectangle of `size` pixels whose bottom
+// left corner is at (x, y), in a window of (w, h)
+fn squeeze(x: u16, y: u16, size: u16, w: u16, h: u16) -> Matrix4<f32> {
+    let (sx, sy) = (size as f32 / w as f32, size as f32 / h as f32);
+    Matrix4::new(sx, 0.0, 0.0, 0.0,
+                 0.0, sy, 0.0, 0.0,
+                 0.0, 0.0, 1.0, 0.0,
+                 (2.0 * x as f32 + size as f32) / w as f32 - 1.0,
+                 (2.0 * y as f32 + size as f32) / h as f32 - 1.0,
+                 0.0, 1.0)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Minimap example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let data = |color: [f32; 3], flat: f32| Params {
+        view_proj: Matrix4::identity().into_fixed(),
+        model: Matrix4::identity().into_fixed(),
+        color: color,
+        flat: flat,
+        _dummy: std::marker::PhantomData,
+    };
+
+    // a floor and a grid of buildings of a few heights
+    let half = 0.5 * BLOCKS as f32 * SPACING;
+    let mut objects = vec![(0, [0.0, 0.0, 0.0], [half + 10.0, half + 10.0, 1.0],
+                            [0.35, 0.37, 0.35])];
+    for i in 0 .. BLOCKS {
+        for j in 0 .. BLOCKS {
+            let height = 3.0 + 3.0 * ((i * 7 + j * 13) % 5) as f32;
+            let shade = 0.5 + 0.08 * ((i * 3 + j * 5) % 4) as f32;
+            objects.push((1, [(i as f32 + 0.5) * SPACING - half,
+                              (j as f32 + 0.5) * SPACING - half, 0.5 * height],
+                          [3.0, 3.0, 0.5 * height], [shade, shade * 0.95, shade * 0.85]));
+        }
+    }
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.plane(&mut factory), meshes.cube(&mut factory)];
+    let mut batches: Vec<_> = shapes.into_iter().map(|(mesh, slice)| {
+        context.make_batch(&program, data([1.0; 3], 0.0), &mesh, slice, &state).unwrap()
+    }).collect();
+    let mut marker = {
+        let mesh = factory.create_mesh(&arrow());
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        context.make_batch(&program, data([1.0, 0.3, 0.1], 1.0), &mesh, slice,
+                           &gfx::DrawState::new()).unwrap()
+    };
+    let mut panel = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        context.make_batch(&program, data([0.9, 0.9, 0.85], 1.0), &mesh, slice,
+                           &gfx::DrawState::new()).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 500.0);
+    let mut camera = FlyCamera::new(Point3::new(-half, -half, 25.0), 0.25 * PI, -0.4, 10.0);
+    // meters across the minimap
+    let mut extent = 60.0f32;
+    let mut rotate = true;
+    // the corner of the minimap, and of its border
+    let map = (w - MARGIN - MAP_SIZE, h - MARGIN - MAP_SIZE);
+    let border = (map.0 - BORDER, map.1 - BORDER, MAP_SIZE + 2 * BORDER);
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.7, 0.85, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::R => rotate = !rotate,
+                    VirtualKeyCode::Equals | VirtualKeyCode::Add =>
+                        extent = (extent / 1.25).max(15.0),
+                    VirtualKeyCode::Minus | VirtualKeyCode::Subtract =>
+                        extent = (extent * 1.25).min(300.0),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            camera.update(clock.step());
+        }
+
+        // The scene, over the whole window
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for &(mesh_id, pos, scale, color) in objects.iter() {
+            let batch = &mut batches[mesh_id];
+            batch.params.view_proj = view_proj;
+            batch.params.model = model_matrix(pos, scale, 0.0).into_fixed();
+            batch.params.color = color;
+            batch.state = state.clone();
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        // The border, and the ground under the minimap, from quads over the
+        // whole window cut down by the scissor
+        panel.params.color = [0.9, 0.9, 0.85];
+        panel.state = gfx::DrawState::new().scissor(border.0, border.1, border.2, border.2);
+        renderer.draw(&(&panel, &context), &wrap).unwrap();
+        panel.params.color = [0.2, 0.22, 0.2];
+        panel.state = gfx::DrawState::new().scissor(map.0, map.1, MAP_SIZE, MAP_SIZE);
+        renderer.draw(&(&panel, &context), &wrap).unwrap();
+
+        // The minimap, from above the camera and squeezed into its corner
+        renderer.clear(clear_data, gfx::DEPTH, &wrap);
+        let p = camera.position;
+        let up = if rotate {
+            Vector3::new(camera.yaw.cos(), camera.yaw.sin(), 0.0)
+        } else {
+            Vector3::unit_y()
+        };
+        let top: AffineMatrix3<f32> = Transform::look_at(&Point3::new(p.x, p.y, 200.0),
+                                                         &Point3::new(p.x, p.y, 0.0), &up);
+        let r = 0.5 * extent;
+        let map_proj = squeeze(map.0, map.1, MAP_SIZE, w, h)
+                           .mul_m(&cgmath::ortho(-r, r, -r, r, 1.0, 400.0));
+        let map_view_proj = map_proj.mul_m(&top.mat).into_fixed();
+        let map_state = state.clone().scissor(map.0, map.1, MAP_SIZE, MAP_SIZE);
+        for &(mesh_id, pos, scale, color) in objects.iter() {
+            let batch = &mut batches[mesh_id];
+            batch.params.view_proj = map_view_proj;
+            batch.params.model = model_matrix(pos, scale, 0.0).into_fixed();
+            batch.params.color = color;
+            batch.state = map_state.clone();
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+        let size = 0.04 * extent;
+        marker.params.view_proj = map_view_proj;
+        marker.params.model = model_matrix([p.x, p.y, 100.0], [size; 3], camera.yaw)
+                                  .into_fixed();
+        marker.state = gfx::DrawState::new().scissor(map.0, map.1, MAP_SIZE, MAP_SIZE);
+        renderer.draw(&(&marker, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Minimap example: {:.0} m across, {}", extent,
+                                       if rotate { "turning with the camera" }
+                                       else { "north up" }));
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a mirror masked with the stencil buffer.
+//
+// A few objects turn on a floor in front of a mirror on the wall. Each frame
+// first marks the pixels of the mirror in the stencil buffer, without
+// touching the colors. The scene is then drawn a second time, reflected
+// through the plane of the mirror and only where the stencil is marked. As
+// the reflection turns the triangles inside out, these draws take clockwise
+// triangles as front faces. The mirror itself is blended over the reflection
+// with a slight tint, and the scene is drawn one last time as it is.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press M to
+// toggle the reflection, Up and Down to change the tint of the mirror, and
+// Space to stop the objects from turning.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// the mirror stands on the far side of the floor, facing the objects, so
+// nothing is behind it to come out in front of it once reflected
+const MIRROR_Y: f32 = 3.0;
+
+static COLORS: [[f32; 3]; 4] = [
+    [0.8, 0.25, 0.2],
+    [0.25, 0.6, 0.3],
+    [0.9, 0.7, 0.3],
+    [0.7, 0.7, 0.7],
+];
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 1 for the checkered floor
+    #[name = "u_Checker"]
+    checker: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct MirrorParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Tint"]
+    tint: [f32; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        vec4 pos = u_Model * vec4(a_Pos, 1.0);
+        v_Pos = pos.xyz;
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * pos;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Checker;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        // the light is not reflected: the diffuse light doesn't depend on
+        // where the surface is seen from, so the reflection matches
+        vec3 light = normalize(vec3(0.3, -0.5, 0.8));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        float checker = mod(floor(v_Pos.x) + floor(v_Pos.y), 2.0);
+        vec3 color = u_Color * mix(1.0, 0.6 + 0.4 * checker, u_Checker);
+        o_Color = vec4(color * (0.25 + 0.75 * diffuse), 1.0);
+    }
+";
+
+static MIRROR_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Tint;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = u_Tint;
+    }
+";
+
+// Places an object, made of a rotation and scale, at a position
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Stencil mirror example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .with_stencil_buffer(8)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    state.primitive.method = gfx::state::RasterMethod::Fill(gfx::state::CullMode::Back);
+    // only inside the mirror, and with the winding flipped by the reflection
+    let mut reflected_state = state.clone().stencil(gfx::state::Comparison::Equal, 1);
+    reflected_state.primitive.front_face = gfx::state::WindingOrder::Clockwise;
+    // sets the stencil to 1 on the mirror, without writing colors or depth
+    let mut mark_state = state.clone().depth(gfx::state::Comparison::LessEqual, false)
+                              .stencil(gfx::state::Comparison::Always, 1);
+    if let Some(ref mut stencil) = mark_state.stencil {
+        stencil.front.op_pass = gfx::state::StencilOp::Replace;
+        stencil.back.op_pass = gfx::state::StencilOp::Replace;
+    }
+    mark_state.color_mask = gfx::state::ColorMask::empty();
+    let tint_state = state.clone().blend(gfx::BlendPreset::Alpha);
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let mut batches = Vec::new();
+    let mut reflected_batches = Vec::new();
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.cube(&mut factory), meshes.sphere(&mut factory, 32, 64),
+                      meshes.torus(&mut factory, 64, 32, 0.7, 0.3), meshes.plane(&mut factory)];
+    for (i, (mesh, slice)) in shapes.into_iter().enumerate() {
+        // the floor is the last shape
+        let checker = if i == COLORS.len() - 1 { 1.0 } else { 0.0 };
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: COLORS[i],
+            checker: checker,
+            _dummy: std::marker::PhantomData,
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice.clone(), &state)
+                            .unwrap());
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: COLORS[i],
+            checker: checker,
+            _dummy: std::marker::PhantomData,
+        };
+        reflected_batches.push(context.make_batch(&program, data, &mesh, slice,
+                                                  &reflected_state).unwrap());
+    }
+
+    // a 6 by 4 rectangle facing the objects, its bottom edge on the floor
+    let mirror_model = place(Matrix3::from_angle_x(cgmath::rad(0.5 * PI))
+                                     .mul_m(&Matrix3::new(3.0, 0.0, 0.0,
+                                                          0.0, 2.0, 0.0,
+                                                          0.0, 0.0, 1.0)),
+                             0.0, MIRROR_Y, 1.0);
+    let (mut mark, mut tint) = {
+        let (mesh, slice) = meshes.plane(&mut factory);
+        let program = factory.link_program(VERTEX_SRC, MIRROR_FRAGMENT_SRC).unwrap();
+        let data = MirrorParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: mirror_model.into_fixed(),
+            tint: [0.0; 4],
+            _dummy: std::marker::PhantomData,
+        };
+        let mark = context.make_batch(&program, data, &mesh, slice.clone(), &mark_state)
+                          .unwrap();
+        let data = MirrorParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: mirror_model.into_fixed(),
+            tint: [0.75, 0.85, 0.9, 0.25],
+            _dummy: std::marker::PhantomData,
+        };
+        let tint = context.make_batch(&program, data, &mesh, slice, &tint_state)
+                          .unwrap();
+        (mark, tint)
+    };
+
+    // mirrors the Y axis around the plane of the mirror
+    let mut reflection = Matrix4::identity();
+    reflection.y.y = -1.0;
+    reflection.w.y = 2.0 * MIRROR_Y;
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.5), 9.0, -2.0, 0.3);
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.12, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut reflecting = true;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let alpha = &mut tint.params.tint[3];
+                    match key {
+                        VirtualKeyCode::M => reflecting = !reflecting,
+                        VirtualKeyCode::Up => *alpha = (*alpha + 0.05).min(1.0),
+                        VirtualKeyCode::Down => *alpha = (*alpha - 0.05).max(0.0),
+                        VirtualKeyCode::Space => turning = !turning,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.6 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let spin = Matrix3::from_angle_z(cgmath::rad(a));
+        let models = [
+            place(spin.mul_s(0.6), -2.0, 0.0, 0.0),
+            place(Matrix3::identity().mul_s(0.8), 0.0, 1.0, 0.0),
+            place(spin.mul_m(&Matrix3::from_angle_x(cgmath::rad(1.0))), 2.0, 0.0, 0.0),
+            // the floor ends at the mirror
+            place(Matrix3::new(6.0, 0.0, 0.0, 0.0, 4.5, 0.0, 0.0, 0.0, 1.0),
+                  0.0, MIRROR_Y - 4.5, -1.0),
+        ];
+
+        let view_proj = proj.mul_m(&camera.view().mat);
+        let reflected_view_proj = view_proj.mul_m(&reflection).into_fixed();
+        let view_proj = view_proj.into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH | gfx::STENCIL, &wrap);
+
+        mark.params.view_proj = view_proj;
+        renderer.draw(&(&mark, &context), &wrap).unwrap();
+        if reflecting {
+            for (batch, model) in reflected_batches.iter_mut().zip(models.iter()) {
+                batch.params.view_proj = reflected_view_proj;
+                batch.params.model = model.into_fixed();
+                renderer.draw(&(&*batch, &context), &wrap).unwrap();
+            }
+        }
+        tint.params.view_proj = view_proj;
+        renderer.draw(&(&tint, &context), &wrap).unwrap();
+
+        for (batch, model) in batches.iter_mut().zip(models.iter()) {
+            batch.params.view_proj = view_proj;
+            batch.params.model = model.into_fixed();
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of showing a render to texture inside the scene.
+//
+// A security camera in a corner pans over the room. Every frame, its view is
+// rendered into a texture first, which is then shown on the screen of a
+// monitor standing in the room, when the scene is drawn to the window.
+//
+// The security camera sees the monitor as well. Drawing the screen into its
+// own texture would read the texture while writing it, a feedback loop that
+// GL leaves undefined and gfx-rs doesn't catch. So by default the screen is
+// left out of the camera's view. With feedback on, there are two textures
+// instead, taking turns: the camera renders into one while its view of the
+// screen shows the other, the frame before, which makes a tunnel of screens
+// lagging one frame more at every step. Likewise, the camera doesn't draw its
+// own body, that it sits inside of.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press F to
+// toggle the feedback and Space to stop everything.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point, Point3, Vector3, Vector4, ToMatrix4};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const SCREEN_WIDTH: u16 = 512;
+const SCREEN_HEIGHT: u16 = 384;
+// the index of the security camera in the objects
+const CAMERA: usize = 6;
+
+static COLORS: [[f32; 3]; 7] = [
+    [0.8, 0.25, 0.2],
+    [0.25, 0.6, 0.3],
+    [0.9, 0.7, 0.3],
+    // the floor
+    [0.4, 0.4, 0.42],
+    // the monitor and its stand
+    [0.15, 0.15, 0.17],
+    [0.15, 0.15, 0.17],
+    // the security camera
+    [0.85, 0.85, 0.8],
+];
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct ScreenParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "t_Screen"]
+    screen: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 light = normalize(vec3(0.3, -0.5, 0.8));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        o_Color = vec4(u_Color * (0.25 + 0.75 * diffuse), 1.0);
+    }
+";
+
+static SCREEN_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static SCREEN_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+
+    uniform sampler2D t_Screen;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 color = texture(t_Screen, v_TexCoord).rgb;
+        // faint scan lines, and darker corners
+        float lines = 0.9 + 0.1 * sin(v_TexCoord.y * 192.0 * 2.0 * PI);
+        vec2 d = v_TexCoord - 0.5;
+        float vignette = 1.0 - 0.8 * dot(d, d);
+        o_Color = vec4(color * lines * vignette, 1.0);
+    }
+";
+
+// Places an object, rotated and scaled, at a position
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+fn scale(x: f32, y: f32, z: f32) -> Matrix3<f32> {
+    Matrix3::new(x, 0.0, 0.0,
+                 0.0, y, 0.0,
+                 0.0, 0.0, z)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Monitor example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    // Two textures to take turns with, sharing a depth buffer
+    let screen_depth = factory.create_texture(gfx::tex::TextureInfo {
+        width: SCREEN_WIDTH,
+        height: SCREEN_HEIGHT,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let screens: Vec<_> = (0 .. 2).map(|_| {
+        let texture = factory.create_texture_rgba8(SCREEN_WIDTH, SCREEN_HEIGHT, false)
+                             .unwrap();
+        let frame = gfx::Frame {
+            colors: vec![Plane::Texture(texture.clone(), 0, None)],
+            depth: Some(Plane::Texture(screen_depth.clone(), 0, None)),
+            .. gfx::Frame::empty(SCREEN_WIDTH, SCREEN_HEIGHT)
+        };
+        (frame, texture)
+    }).collect();
+
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let mut meshes = MeshCache::new();
+    let mut batches = Vec::new();
+    let shapes = vec![meshes.cube(&mut factory), meshes.torus(&mut factory, 64, 32, 0.7, 0.3),
+                      meshes.sphere(&mut factory, 32, 64), meshes.plane(&mut factory),
+                      meshes.cube(&mut factory), meshes.cube(&mut factory),
+                      meshes.cube(&mut factory)];
+    for (i, (mesh, slice)) in shapes.into_iter().enumerate() {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: COLORS[i],
+            _dummy: std::marker::PhantomData,
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+    }
+
+    let mut screen = {
+        let (mesh, slice) = meshes.plane(&mut factory);
+        let program = factory.link_program(SCREEN_VERTEX_SRC, SCREEN_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        // standing up in front of the monitor, facing the room
+        let facing = Matrix3::from_angle_x(cgmath::rad(0.5 * PI));
+        let model = place(facing.mul_m(&scale(1.6, 1.2, 1.0)), 0.0, 3.88, 1.8);
+        let data = ScreenParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: model.into_fixed(),
+            screen: (screens[0].1.clone(), Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 1.0, 1.0), 11.0, -1.9, 0.35);
+
+    let camera_aspect = SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32;
+    let camera_proj = cgmath::perspective(cgmath::deg(50.0f32), camera_aspect, 0.1, 100.0);
+    let camera_pos = Point3::new(4.0, -4.0, 3.5);
+
+    let clear_data = gfx::ClearData {
+        color: [0.35, 0.45, 0.6, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut feedback = false;
+    let mut current = 0;
+    let mut moving = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::F => feedback = !feedback,
+                    VirtualKeyCode::Space => moving = !moving,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if moving {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // pan back and forth around the center of the room
+        let yaw = 0.75 * PI + 0.5 * (0.4 * t).sin();
+        let camera_dir = Vector3::new(yaw.cos(), yaw.sin(), -0.35);
+        let camera_view: AffineMatrix3<f32> = Transform::look_at(
+            &camera_pos,
+            &camera_pos.add_v(&camera_dir),
+            &Vector3::unit_z(),
+        );
+
+        let spin = Matrix3::from_angle_z(cgmath::rad(0.6 * t));
+        let models = [
+            place(spin.mul_s(0.6), -2.0, 0.0, 1.0),
+            place(spin.mul_m(&Matrix3::from_angle_x(cgmath::rad(1.5))), 2.0, 0.5, 1.2),
+            place(Matrix3::identity().mul_s(0.8), 1.5 * (0.5 * t).sin(), -1.5, 0.8),
+            place(scale(8.0, 8.0, 1.0), 0.0, 0.0, 0.0),
+            place(scale(1.8, 0.1, 1.4), 0.0, 4.0, 1.8),
+            place(scale(0.15, 0.15, 0.2), 0.0, 4.0, 0.2),
+            // pointing where it looks
+            place(Matrix3::from_angle_z(cgmath::rad(yaw)).mul_m(&scale(0.3, 0.15, 0.15)),
+                  camera_pos.x, camera_pos.y, camera_pos.z),
+        ];
+
+        // The camera's view, with itself left out
+        if feedback {
+            current = 1 - current;
+        }
+        let camera_view_proj = camera_proj.mul_m(&camera_view.mat).into_fixed();
+        let target = &screens[current].0;
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, target);
+        for (i, (batch, model)) in batches.iter_mut().zip(models.iter()).enumerate() {
+            if i != CAMERA {
+                batch.params.view_proj = camera_view_proj;
+                batch.params.model = model.into_fixed();
+                renderer.draw(&(&*batch, &context), target).unwrap();
+            }
+        }
+        if feedback {
+            // the other texture, done by the previous frame
+            screen.params.view_proj = camera_view_proj;
+            screen.params.screen.0 = screens[1 - current].1.clone();
+            renderer.draw(&(&screen, &context), target).unwrap();
+        }
+
+        // The room, with the new view on the monitor
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for (batch, model) in batches.iter_mut().zip(models.iter()) {
+            batch.params.view_proj = view_proj;
+            batch.params.model = model.into_fixed();
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+        screen.params.view_proj = view_proj;
+        screen.params.screen.0 = screens[current].1.clone();
+        renderer.draw(&(&screen, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Monitor example: feedback {}",
+                                       if feedback { "on" } else { "off" }));
+    }
+}
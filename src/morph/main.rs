@@ -0,0 +1,262 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of morph target (blend shape) animation.
+//
+// Next to its own position and normal, every vertex of a sphere stores how
+// far it moves for each of three targets: a cube, a spiky ball and a twisted
+// squashed shape. The vertex shader adds the offsets scaled by animated
+// weights, so the blending costs nothing on the CPU.
+//
+// Press Space to pause the animation.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Point3, Vector, Vector3, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+    #[name = "a_PosDelta0"]
+    pos_delta0: [f32; 3],
+    #[name = "a_NormalDelta0"]
+    normal_delta0: [f32; 3],
+    #[name = "a_PosDelta1"]
+    pos_delta1: [f32; 3],
+    #[name = "a_NormalDelta1"]
+    normal_delta1: [f32; 3],
+    #[name = "a_PosDelta2"]
+    pos_delta2: [f32; 3],
+    #[name = "a_NormalDelta2"]
+    normal_delta2: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Weights"]
+    weights: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Weights;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec3 a_PosDelta0;
+    in vec3 a_NormalDelta0;
+    in vec3 a_PosDelta1;
+    in vec3 a_NormalDelta1;
+    in vec3 a_PosDelta2;
+    in vec3 a_NormalDelta2;
+    out vec3 v_Normal;
+
+    void main() {
+        vec3 pos = a_Pos + u_Weights.x * a_PosDelta0
+                         + u_Weights.y * a_PosDelta1
+                         + u_Weights.z * a_PosDelta2;
+        v_Normal = a_Normal + u_Weights.x * a_NormalDelta0
+                            + u_Weights.y * a_NormalDelta1
+                            + u_Weights.z * a_NormalDelta2;
+        gl_Position = u_ViewProj * vec4(pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float d = max(0.0, dot(n, normalize(vec3(0.4, -0.6, 0.7))));
+        o_Color = vec4(vec3(0.3, 0.7, 0.9) * (0.2 + 0.8 * d), 1.0);
+    }
+";
+
+// Position of a morph target in the direction given by the spherical
+// coordinates of a point of the base sphere
+fn target_position(target: usize, theta: f32, phi: f32) -> Vector3<f32> {
+    let d = Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+    match target {
+        // cube
+        0 => d.mul_s(1.0 / d.x.abs().max(d.y.abs()).max(d.z.abs())),
+        // spiky ball
+        1 => d.mul_s(1.0 + 0.25 * (5.0 * theta).sin() * (5.0 * phi).cos()),
+        // twisted and squashed
+        _ => {
+            let a = 1.5 * d.z;
+            Vector3::new(d.x * a.cos() - d.y * a.sin(),
+                         d.x * a.sin() + d.y * a.cos(),
+                         0.6 * d.z)
+        },
+    }
+}
+
+// Normal of a morph target, from the tangents along theta and phi
+fn target_normal(target: usize, theta: f32, phi: f32) -> Vector3<f32> {
+    let e = 0.001;
+    let dt = target_position(target, theta + e, phi).sub_v(&target_position(target, theta - e, phi));
+    let dp = target_position(target, theta, phi + e).sub_v(&target_position(target, theta, phi - e));
+    let n = dt.cross(&dp);
+    if n.length() < 1e-6 {
+        // the tangents vanish at the poles
+        Vector3::new(0.0, 0.0, theta.cos().signum())
+    } else {
+        n.normalize()
+    }
+}
+
+fn create_mesh_data() -> (Vec<Vertex>, Vec<u16>) {
+    let (sphere, indices) = shape::sphere(48, 96);
+    let vertices = sphere.iter().map(|v| {
+        let theta = v.pos[2].max(-1.0).min(1.0).acos();
+        let phi = v.pos[1].atan2(v.pos[0]);
+        let base_pos = Vector3::new(v.pos[0], v.pos[1], v.pos[2]);
+        let base_normal = Vector3::new(v.normal[0], v.normal[1], v.normal[2]);
+        let delta = |target| (
+            target_position(target, theta, phi).sub_v(&base_pos).into_fixed(),
+            target_normal(target, theta, phi).sub_v(&base_normal).into_fixed(),
+        );
+        let (p0, n0) = delta(0);
+        let (p1, n1) = delta(1);
+        let (p2, n2) = delta(2);
+        Vertex {
+            pos: v.pos,
+            normal: v.normal,
+            pos_delta0: p0,
+            normal_delta0: n0,
+            pos_delta1: p1,
+            normal_delta1: n1,
+            pos_delta2: p2,
+            normal_delta2: n2,
+        }
+    }).collect();
+    (vertices, indices)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Morph target example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let (vertex_data, index_data) = create_mesh_data();
+    let mesh = factory.create_mesh(&vertex_data);
+    let slice = factory.create_buffer_index::<u16>(&index_data)
+                       .to_slice(gfx::PrimitiveType::TriangleList);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.5, 20.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(2.5, -3.5, 2.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_z(),
+    );
+
+    let data = Params {
+        view_proj: proj.mul_m(&view.mat).into_fixed(),
+        weights: [0.0, 0.0, 0.0],
+        _dummy: std::marker::PhantomData,
+    };
+    let mut batch = context.make_batch(&program, data, &mesh, slice, &state)
+                           .unwrap();
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut paused = false;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    paused = !paused,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if !paused {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // Fade each target in and out, overlapping with the others
+        for (i, weight) in batch.params.weights.iter_mut().enumerate() {
+            *weight = (0.7 * t - 2.1 * i as f32).sin().max(0.0);
+        }
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
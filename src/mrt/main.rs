@@ -0,0 +1,249 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of rendering to multiple render targets at once.
+//
+// A turning torus is drawn once into a frame with three color planes. The
+// fragment shader has an output for each of them, in the same order: the
+// color, the normal and the depth. The three textures are then shown side by
+// side in the window.
+//
+// This is the first pass of the deferred example, without anything else.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct TileParams<R: gfx::Resources> {
+    #[name = "u_Rect"]
+    rect: [f32; 4],
+    #[name = "t_Plane"]
+    plane: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec2 a_TexCoord;
+    out vec3 v_Normal;
+    out vec2 v_TexCoord;
+    out float v_Distance;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_TexCoord = a_TexCoord;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+        v_Distance = gl_Position.w;
+    }
+";
+
+// One output for each color plane of the frame
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    in float v_Distance;
+    out vec4 o_Color;
+    out vec4 o_Normal;
+    out vec4 o_Depth;
+
+    void main() {
+        float stripe = step(0.5, fract(16.0 * v_TexCoord.x));
+        o_Color = vec4(mix(vec3(0.9, 0.5, 0.1), vec3(0.2, 0.5, 0.8), stripe), 1.0);
+        o_Normal = vec4(normalize(v_Normal) * 0.5 + 0.5, 1.0);
+        // white up close, black at the back of the torus
+        o_Depth = vec4(vec3(2.0 - v_Distance / 3.0), 1.0);
+    }
+";
+
+static TILE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Rect;
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        // the tile center is in xy, its half size in zw
+        gl_Position = vec4(a_Pos.xy * u_Rect.zw + u_Rect.xy, 0.0, 1.0);
+    }
+";
+
+static TILE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Plane;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = texture(t_Plane, v_TexCoord);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Multiple render targets example with gfx-rs".to_string())
+            .with_dimensions(1200, 400)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    // A third of the window for each plane
+    let (width, height) = (w / 3, h);
+    let planes: Vec<_> = (0 .. 3).map(|_|
+        factory.create_texture_rgba8(width, height, false).unwrap()
+    ).collect();
+    let depth = factory.create_texture(gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let frame = gfx::Frame {
+        colors: planes.iter().map(|tex| Plane::Texture(tex.clone(), 0, None)).collect(),
+        depth: Some(Plane::Texture(depth, 0, None)),
+        .. gfx::Frame::empty(width, height)
+    };
+
+    let mut meshes = MeshCache::new();
+    let mut torus = {
+        let (mesh, slice) = meshes.torus(&mut factory, 32, 64, 1.0, 0.4);
+        let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+        let aspect = width as f32 / height as f32;
+        let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 1.0, 10.0);
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(0.0, -4.0, 2.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::unit_z(),
+        );
+        let data = SceneParams {
+            model: Matrix4::identity().into_fixed(),
+            view_proj: proj.mul_m(&view.mat).into_fixed(),
+            _dummy: std::marker::PhantomData,
+        };
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let tiles: Vec<_> = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(TILE_VERTEX_SRC, TILE_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        planes.iter().enumerate().map(|(i, tex)| {
+            let data = TileParams {
+                rect: [(i as f32 - 1.0) * 2.0 / 3.0, 0.0, 1.0 / 3.0, 1.0],
+                plane: (tex.clone(), Some(sampler.clone())),
+            };
+            context.make_batch(&program, data, &mesh, slice.clone(), &gfx::DrawState::new())
+                   .unwrap()
+        }).collect()
+    };
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            angle += 0.5 * clock.step();
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+        torus.params.model = Matrix3::from_angle_x(cgmath::rad(a))
+                                     .mul_m(&Matrix3::from_angle_z(cgmath::rad(0.7 * a)))
+                                     .to_matrix4().into_fixed();
+
+        // One draw, filling all three planes
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &frame);
+        renderer.draw(&(&torus, &context), &frame).unwrap();
+
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        for tile in tiles.iter() {
+            renderer.draw(&(tile, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
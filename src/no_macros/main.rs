@@ -0,0 +1,278 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of using gfx-rs without its macros, so that it builds
+// on stable Rust.
+//
+// Every other example derives the vertex format and the shader parameters
+// with `#[vertex_format]` and `#[shader_param]`, which are compiler plugins
+// and need a nightly compiler. Here both traits are implemented by hand,
+// doing what the plugins would generate:
+//
+// - `VertexFormat` describes each attribute of the vertex struct: its name
+//   in the shader, how many components of which type it has, and where it
+//   is in the struct.
+// - `ShaderParam` first links the struct to a program, by finding each
+//   uniform and texture of the program among its fields, and then fills
+//   in the values of those fields every time the batch is drawn.
+//
+// It is a package of its own, so that nothing it builds needs the plugins.
+// It doesn't use the `gfx_examples` library either, which does, and only
+// compiles in the clock, the window size and the thumbnail capture, which
+// don't.
+//
+// A checkered triangle turns on itself, tinted by a uniform.
+
+extern crate gfx;
+extern crate gfx_gl as gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate image;
+extern crate time;
+
+use std::mem;
+use gfx::traits::*;
+use gfx::attrib::{Format, Type, FloatSubType, FloatSize};
+use gfx::shade::{ParamStorage, ParameterError, ShaderParam, TextureParam, ToUniform};
+use gfx::shade::{VarUniform, VarTexture};
+use clock::Clock;
+use capture::Thumbnail;
+
+// The same files as in `gfx_examples`, which use no plugins, not all of
+// their items being needed here
+#[path = "../clock.rs"]
+#[allow(dead_code)]
+mod clock;
+#[path = "../capture.rs"]
+#[allow(dead_code)]
+mod capture;
+#[path = "../window.rs"]
+#[allow(dead_code)]
+mod window;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Vertex {
+    pos: [f32; 2],
+    tex_coord: [f32; 2],
+}
+
+// What `#[vertex_format]` would generate: one attribute per field, all read
+// from the same buffer
+impl gfx::VertexFormat for Vertex {
+    fn generate<R: gfx::Resources>(buffer: &gfx::BufferHandle<R, Vertex>)
+                                   -> Vec<gfx::Attribute<R>> {
+        let stride = mem::size_of::<Vertex>() as gfx::attrib::Stride;
+        let float2 = |offset: usize| Format {
+            elem_count: 2,
+            elem_type: Type::Float(FloatSubType::Default, FloatSize::F32),
+            offset: offset as gfx::attrib::Offset,
+            stride: stride,
+            instance_rate: 0,
+        };
+        vec![
+            gfx::Attribute {
+                name: "a_Pos".to_string(),
+                buffer: buffer.raw().clone(),
+                format: float2(0),
+            },
+            // right after the position, the struct being `#[repr(C)]`
+            gfx::Attribute {
+                name: "a_TexCoord".to_string(),
+                buffer: buffer.raw().clone(),
+                format: float2(mem::size_of::<[f32; 2]>()),
+            },
+        ]
+    }
+}
+
+struct Params<R: gfx::Resources> {
+    angle: f32,
+    tint: [f32; 3],
+    checker: TextureParam<R>,
+}
+
+// Where each field goes in the program, found when the batch is made
+struct ParamsLink {
+    angle: Option<VarUniform>,
+    tint: Option<VarUniform>,
+    checker: Option<VarTexture>,
+}
+
+// What `#[shader_param]` would generate
+impl<R: gfx::Resources> ShaderParam for Params<R> {
+    type Resources = R;
+    type Link = ParamsLink;
+
+    fn create_link(_: Option<&Params<R>>, info: &gfx::ProgramInfo)
+                   -> Result<ParamsLink, ParameterError> {
+        let mut link = ParamsLink {
+            angle: None,
+            tint: None,
+            checker: None,
+        };
+        // everything the program uses has to be provided
+        for (i, u) in info.uniforms.iter().enumerate() {
+            match &u.name[..] {
+                "u_Angle" => link.angle = Some(i as VarUniform),
+                "u_Tint" => link.tint = Some(i as VarUniform),
+                _ => return Err(ParameterError::MissingUniform(u.name.clone())),
+            }
+        }
+        // and this one has no blocks
+        if let Some(b) = info.blocks.first() {
+            return Err(ParameterError::MissingBlock(b.name.clone()));
+        }
+        for (i, t) in info.textures.iter().enumerate() {
+            match &t.name[..] {
+                "t_Checker" => link.checker = Some(i as VarTexture),
+                _ => return Err(ParameterError::MissingTexture(t.name.clone())),
+            }
+        }
+        Ok(link)
+    }
+
+    fn fill_params(&self, link: &ParamsLink, storage: &mut ParamStorage<R>) {
+        if let Some(id) = link.angle {
+            storage.uniforms[id as usize] = Some(self.angle.to_uniform());
+        }
+        if let Some(id) = link.tint {
+            storage.uniforms[id as usize] = Some(self.tint.to_uniform());
+        }
+        if let Some(id) = link.checker {
+            storage.textures[id as usize] = Some(self.checker.clone());
+        }
+    }
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Angle;
+    in vec2 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        mat2 rotation = mat2(cos(u_Angle), sin(u_Angle), -sin(u_Angle), cos(u_Angle));
+        v_TexCoord = a_TexCoord;
+        gl_Position = vec4(rotation * a_Pos, 0.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Tint;
+    uniform sampler2D t_Checker;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(texture(t_Checker, v_TexCoord).rgb * u_Tint, 1.0);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("No macros example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+
+    let vertex_data = [
+        Vertex { pos: [ -0.5, -0.4 ], tex_coord: [0.0, 0.0] },
+        Vertex { pos: [  0.5, -0.4 ], tex_coord: [1.0, 0.0] },
+        Vertex { pos: [  0.0,  0.6 ], tex_coord: [0.5, 1.0] },
+    ];
+    let mesh = factory.create_mesh(&vertex_data);
+    let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+
+    // an 8 by 8 checkerboard
+    let mut texels = Vec::new();
+    for y in 0 .. 8 {
+        for x in 0 .. 8 {
+            let c = if (x + y) % 2 == 0 { 0xFF } else { 0x60 };
+            texels.extend([c, c, c, 0xFFu8].iter().cloned());
+        }
+    }
+    let texture = factory.create_texture_rgba8(8, 8, false).unwrap();
+    factory.update_texture(&texture, &texture.get_info().to_image_info(), &texels, None)
+           .unwrap();
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let data = Params {
+        angle: 0.0,
+        tint: [1.0, 0.6, 0.3],
+        checker: (texture, Some(sampler)),
+    };
+    // this calls `create_link`, and fails if the parameters don't match the
+    // program
+    let mut batch = gfx::batch::OwnedBatch::new(mesh, program, data).unwrap();
+    batch.slice = slice;
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            match event {
+                glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape)) => break 'main,
+                glutin::Event::Closed => break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            angle += 0.6 * clock.step();
+        }
+        batch.params.angle = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        renderer.draw(&batch, &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of visualizing normals with a geometry shader.
+//
+// A torus is drawn shaded, then drawn a second time with a program whose
+// geometry shader replaces each triangle with lines: one along the normal of
+// each of its vertices, and one along the normal of the face from its center.
+//
+// Press V to toggle the vertex normals, F to toggle the face normals, and Up
+// and Down to change the length of the lines.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector3, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3, Rotation3, Quaternion, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct NormalParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Length"]
+    length: f32,
+    // whether to show the vertex and face normals
+    #[name = "u_Show"]
+    show: [f32; 2],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(vec3(0.6) * (0.2 + 0.8 * d), 1.0);
+    }
+";
+
+static NORMAL_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Pos = (u_Model * vec4(a_Pos, 1.0)).xyz;
+        v_Normal = normalize(mat3(u_Model) * a_Normal);
+    }
+";
+
+static NORMAL_GEOMETRY_SRC: &'static [u8] = b"
+    #version 150 core
+
+    layout(triangles) in;
+    layout(line_strip, max_vertices = 8) out;
+
+    uniform mat4 u_ViewProj;
+    uniform float u_Length;
+    uniform vec2 u_Show;
+    in vec3 v_Pos[];
+    in vec3 v_Normal[];
+    out vec3 g_Color;
+
+    void line(vec3 from, vec3 dir, vec3 color) {
+        g_Color = color;
+        gl_Position = u_ViewProj * vec4(from, 1.0);
+        EmitVertex();
+        gl_Position = u_ViewProj * vec4(from + u_Length * dir, 1.0);
+        EmitVertex();
+        EndPrimitive();
+    }
+
+    void main() {
+        if (u_Show.x > 0.5) {
+            for (int i = 0; i < 3; ++i) {
+                line(v_Pos[i], v_Normal[i], vec3(0.2, 0.6, 1.0));
+            }
+        }
+        if (u_Show.y > 0.5) {
+            vec3 center = (v_Pos[0] + v_Pos[1] + v_Pos[2]) / 3.0;
+            vec3 normal = normalize(cross(v_Pos[1] - v_Pos[0], v_Pos[2] - v_Pos[0]));
+            line(center, normal, vec3(1.0, 0.8, 0.2));
+        }
+    }
+";
+
+static NORMAL_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 g_Color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(g_Color, 1.0);
+    }
+";
+
+// A torus around Z, with smooth normals
+fn create_torus(radius: f32, thickness: f32, rings: u16, segments: u16)
+                -> (Vec<shape::Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    for r in 0 .. rings + 1 {
+        let theta = (r as f32) / (rings as f32) * 2.0 * PI;
+        for s in 0 .. segments + 1 {
+            let phi = (s as f32) / (segments as f32) * 2.0 * PI;
+            let normal = [phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin()];
+            let d = radius + thickness * phi.cos();
+            vertices.push(shape::Vertex {
+                pos: [d * theta.cos(), d * theta.sin(), thickness * phi.sin()],
+                normal: normal,
+                tex_coord: [r as f32 / rings as f32, s as f32 / segments as f32],
+            });
+        }
+    }
+    let mut indices = Vec::new();
+    for r in 0 .. rings {
+        for s in 0 .. segments {
+            let i0 = r * (segments + 1) + s;
+            let i1 = i0 + segments + 1;
+            indices.extend([i0, i1, i1 + 1, i1 + 1, i0 + 1, i0].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Normals example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let (vertex_data, index_data) = create_torus(1.5, 0.6, 32, 16);
+    let mesh = factory.create_mesh(&vertex_data);
+    let slice = factory.create_buffer_index::<u16>(&index_data)
+                       .to_slice(gfx::PrimitiveType::TriangleList);
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let data = Params {
+        model: Matrix4::identity().into_fixed(),
+        view_proj: Matrix4::identity().into_fixed(),
+        _dummy: std::marker::PhantomData,
+    };
+    let mut batch = context.make_batch(&program, data, &mesh, slice.clone(), &state)
+                           .unwrap();
+
+    // link_program only takes a vertex and a fragment shader, so the
+    // program with the geometry stage is put together by hand
+    let normal_program = {
+        use gfx::shade::Stage;
+        let shaders = [
+            factory.create_shader(Stage::Vertex, NORMAL_VERTEX_SRC).unwrap(),
+            factory.create_shader(Stage::Geometry, NORMAL_GEOMETRY_SRC).unwrap(),
+            factory.create_shader(Stage::Fragment, NORMAL_FRAGMENT_SRC).unwrap(),
+        ];
+        factory.create_program(&shaders, None).unwrap()
+    };
+    let data = NormalParams {
+        model: Matrix4::identity().into_fixed(),
+        view_proj: Matrix4::identity().into_fixed(),
+        length: 0.3,
+        show: [1.0, 1.0],
+        _dummy: std::marker::PhantomData,
+    };
+    let mut normal_batch = context.make_batch(&normal_program, data, &mesh, slice, &state)
+                                  .unwrap();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.5, 50.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(3.0, -5.0, 3.5),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_z(),
+    );
+    let view_proj = proj.mul_m(&view.mat).into_fixed();
+    batch.params.view_proj = view_proj;
+    normal_batch.params.view_proj = view_proj;
+
+    let clear_data = gfx::ClearData {
+        color: [0.15, 0.15, 0.15, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            let params = &mut normal_batch.params;
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::V)) =>
+                    params.show[0] = 1.0 - params.show[0],
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::F)) =>
+                    params.show[1] = 1.0 - params.show[1],
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) =>
+                    params.length *= 1.25,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) =>
+                    params.length *= 0.8,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            time = clock.time() as f32;
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        let rotation: Quaternion<f32> = Rotation3::from_axis_angle(
+            &Vector3::new(0.3, 0.2, 1.0).normalize(), cgmath::rad(0.4 * t));
+        let model = rotation.to_matrix4().into_fixed();
+        batch.params.model = model;
+        normal_batch.params.model = model;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+        renderer.draw(&(&normal_batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loading meshes from Wavefront OBJ files.
+//!
+//! Only the geometry is read: the positions, the texture coordinates and the
+//! normals of the faces, with polygons split into fans of triangles. The
+//! corners sharing all three of their indices become a single vertex.
+//! Materials, groups and everything else in the file are skipped.
+//!
+//! OBJ files are usually Y-up, so the positions and normals are turned to
+//! the Z-up space of the examples. Faces without normals get the average
+//! of the normals of the faces around their vertices.
+//!
+//! ```ignore
+//! let (vertices, indices) = obj::load("head.obj").unwrap();
+//! let (center, radius) = obj::bounds(&vertices);
+//! ```
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use shape::Vertex;
+
+/// Loads the triangles of an OBJ file, with 32 bit indices since scanned
+/// models often have more vertices than 16 bits can count. `index::slice`
+/// uploads them narrower when they fit.
+pub fn load(path: &str) -> Result<(Vec<Vertex>, Vec<u32>), String> {
+    let file = try!(File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e)));
+    let mut positions = Vec::new();
+    let mut tex_coords = Vec::new();
+    let mut normals = Vec::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    // of the vertex made of each (position, texture coordinate, normal)
+    let mut corners = HashMap::new();
+    let mut missing_normals = false;
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = try!(line.map_err(|e| format!("Failed to read {}: {}", path, e)));
+        let error = || format!("Malformed line {} of {}: {}", number + 1, path, line);
+        let mut words = line.split_whitespace();
+        let values = |words: &mut ::std::str::SplitWhitespace| -> Result<Vec<f32>, String> {
+            words.map(|w| w.parse().map_err(|_| error())).collect()
+        };
+        match words.next() {
+            Some("v") => {
+                let v = try!(values(&mut words));
+                if v.len() < 3 {
+                    return Err(error());
+                }
+                positions.push([v[0], -v[2], v[1]]);
+            },
+            Some("vt") => {
+                let v = try!(values(&mut words));
+                if v.len() < 2 {
+                    return Err(error());
+                }
+                tex_coords.push([v[0], v[1]]);
+            },
+            Some("vn") => {
+                let v = try!(values(&mut words));
+                if v.len() < 3 {
+                    return Err(error());
+                }
+                normals.push([v[0], -v[2], v[1]]);
+            },
+            Some("f") => {
+                let mut face = Vec::new();
+                for word in words {
+                    // v, v/vt, v//vn or v/vt/vn, counting from 1, or from
+                    // the end when negative
+                    let mut refs = [None; 3];
+                    let counts = [positions.len(), tex_coords.len(), normals.len()];
+                    for (k, part) in word.split('/').take(3).enumerate() {
+                        if part.is_empty() {
+                            continue;
+                        }
+                        let i: isize = try!(part.parse().map_err(|_| error()));
+                        let i = if i < 0 { counts[k] as isize + i } else { i - 1 };
+                        if i < 0 || i >= counts[k] as isize {
+                            return Err(error());
+                        }
+                        refs[k] = Some(i as usize);
+                    }
+                    let key = match refs {
+                        [Some(v), vt, vn] => (v, vt, vn),
+                        _ => return Err(error()),
+                    };
+                    missing_normals |= key.2.is_none();
+                    let index = *corners.entry(key).or_insert_with(|| {
+                        vertices.push(Vertex {
+                            pos: positions[key.0],
+                            normal: key.2.map_or([0.0; 3], |i| normals[i]),
+                            tex_coord: key.1.map_or([0.0; 2], |i| tex_coords[i]),
+                        });
+                        vertices.len() as u32 - 1
+                    });
+                    face.push(index);
+                }
+                if face.len() < 3 {
+                    return Err(error());
+                }
+                for k in 1 .. face.len() - 1 {
+                    indices.extend([face[0], face[k], face[k + 1]].iter().cloned());
+                }
+            },
+            _ => {},
+        }
+    }
+
+    if missing_normals {
+        smooth_normals(&mut vertices, &indices);
+    }
+    Ok((vertices, indices))
+}
+
+// Sums up the face normals, weighted by the face areas, at every vertex
+// of the faces given without normals
+fn smooth_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut sums = vec![[0.0f32; 3]; vertices.len()];
+    for tri in indices.chunks(3) {
+        let (a, b, c) = (vertices[tri[0] as usize].pos, vertices[tri[1] as usize].pos,
+                         vertices[tri[2] as usize].pos);
+        let e1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let e2 = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [e1[1] * e2[2] - e1[2] * e2[1],
+                 e1[2] * e2[0] - e1[0] * e2[2],
+                 e1[0] * e2[1] - e1[1] * e2[0]];
+        for &i in tri.iter() {
+            for k in 0 .. 3 {
+                sums[i as usize][k] += n[k];
+            }
+        }
+    }
+    for (v, n) in vertices.iter_mut().zip(sums.iter()) {
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        if v.normal == [0.0; 3] && length > 0.0 {
+            v.normal = [n[0] / length, n[1] / length, n[2] / length];
+        }
+    }
+}
+
+/// The center of the bounding box of the vertices, and the radius of the
+/// sphere around it that holds them all, to fit a model of any size.
+pub fn bounds(vertices: &[Vertex]) -> ([f32; 3], f32) {
+    let mut min = [::std::f32::INFINITY; 3];
+    let mut max = [::std::f32::NEG_INFINITY; 3];
+    for v in vertices.iter() {
+        for k in 0 .. 3 {
+            min[k] = min[k].min(v.pos[k]);
+            max[k] = max[k].max(v.pos[k]);
+        }
+    }
+    let center = [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5, (min[2] + max[2]) * 0.5];
+    let radius = vertices.iter().fold(0.0f32, |r, v| {
+        let d = [v.pos[0] - center[0], v.pos[1] - center[1], v.pos[2] - center[2]];
+        r.max((d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt())
+    });
+    (center, radius)
+}
@@ -0,0 +1,341 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of occlusion culling with hardware occlusion queries.
+//
+// A field of spheres stands behind a wall. Every frame, after drawing the wall,
+// the bounding box of each sphere is drawn with color and depth writes turned
+// off inside an occlusion query, counting the samples that pass the depth
+// test. Spheres whose box had no visible samples are not drawn.
+//
+// The results are read a frame later, when they are ready, so that the CPU
+// never waits for the GPU. The box of each sphere is outlined in green when
+// its last query passed, and in red when it was occluded.
+//
+// gfx-rs doesn't expose queries yet, so this example talks to GL directly,
+// like the "gl" mode of the performance example.
+//
+// Press Space to toggle occlusion culling. The window title shows the number
+// of drawn and occluded spheres.
+
+extern crate cgmath;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx_examples::{shape, window, Clock, Thumbnail};
+use gfx_examples::gl_program::{compile_shader, link_program, uniform_location};
+use gl::Gl;
+use gl::types::*;
+use std::mem;
+use std::ptr;
+
+const GRID_SIZE: usize = 9;
+
+static VERTEX_SRC: &'static str = "
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static str = "
+    #version 150 core
+
+    uniform vec3 u_Color;
+    // 1 to skip the lighting, for the outlines
+    uniform float u_Flat;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(u_Color * mix(0.2 + 0.8 * d, 1.0, u_Flat), 1.0);
+    }
+";
+
+/// A vertex array with its buffers, drawn with indices.
+struct Mesh {
+    vao: GLuint,
+    buffers: [GLuint; 2],
+    num_indices: GLsizei,
+    primitive: GLenum,
+}
+
+impl Mesh {
+    fn new(gl: &Gl, vertices: &[shape::Vertex], indices: &[u16], primitive: GLenum) -> Mesh {
+        let mut vao = 0;
+        let mut buffers = [0; 2];
+        unsafe {
+            gl.GenVertexArrays(1, &mut vao);
+            gl.BindVertexArray(vao);
+            gl.GenBuffers(2, buffers.as_mut_ptr());
+
+            gl.BindBuffer(gl::ARRAY_BUFFER, buffers[0]);
+            gl.BufferData(gl::ARRAY_BUFFER,
+                          (vertices.len() * mem::size_of::<shape::Vertex>()) as GLsizeiptr,
+                          mem::transmute(&vertices[0]),
+                          gl::STATIC_DRAW);
+            let stride = mem::size_of::<shape::Vertex>() as GLsizei;
+            gl.EnableVertexAttribArray(0);
+            gl.VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl.EnableVertexAttribArray(1);
+            gl.VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride,
+                                   mem::size_of::<[f32; 3]>() as *const GLvoid);
+
+            gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffers[1]);
+            gl.BufferData(gl::ELEMENT_ARRAY_BUFFER,
+                          (indices.len() * mem::size_of::<u16>()) as GLsizeiptr,
+                          mem::transmute(&indices[0]),
+                          gl::STATIC_DRAW);
+            gl.BindVertexArray(0);
+        }
+        Mesh {
+            vao: vao,
+            buffers: buffers,
+            num_indices: indices.len() as GLsizei,
+            primitive: primitive,
+        }
+    }
+
+    fn draw(&self, gl: &Gl) {
+        unsafe {
+            gl.BindVertexArray(self.vao);
+            gl.DrawElements(self.primitive, self.num_indices, gl::UNSIGNED_SHORT, ptr::null());
+        }
+    }
+
+    fn delete(&self, gl: &Gl) {
+        unsafe {
+            gl.DeleteBuffers(2, self.buffers.as_ptr());
+            gl.DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+// The 12 edges of the -1 to 1 cube
+fn create_box_edges() -> (Vec<shape::Vertex>, Vec<u16>) {
+    let vertices = (0 .. 8).map(|i| shape::Vertex {
+        pos: [if i & 1 != 0 { 1.0 } else { -1.0 },
+              if i & 2 != 0 { 1.0 } else { -1.0 },
+              if i & 4 != 0 { 1.0 } else { -1.0 }],
+        normal: [0.0, 0.0, 0.0],
+        tex_coord: [0.0, 0.0],
+    }).collect();
+    let indices = vec![
+        0, 1, 2, 3, 4, 5, 6, 7, // along X
+        0, 2, 1, 3, 4, 6, 5, 7, // along Y
+        0, 4, 1, 5, 2, 6, 3, 7, // along Z
+    ];
+    (vertices, indices)
+}
+
+fn model_matrix(pos: [f32; 3], scale: [f32; 3]) -> Matrix4<f32> {
+    let mut model = Matrix3::new(scale[0], 0.0, 0.0,
+                                 0.0, scale[1], 0.0,
+                                 0.0, 0.0, scale[2]).to_matrix4();
+    model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+    model
+}
+
+pub fn main() {
+    let window = glutin::WindowBuilder::new()
+        .with_title("Occlusion query example".to_string())
+        .with_dimensions(800, 600)
+        .with_gl(glutin::GlRequest::Latest)
+        .build().unwrap();
+    unsafe { window.make_current() };
+    let gl = Gl::load_with(|s| window.get_proc_address(s));
+
+    let (w, h) = window::physical_size(&window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+
+    let vs = compile_shader(&gl, VERTEX_SRC, gl::VERTEX_SHADER);
+    let fs = compile_shader(&gl, FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let program = link_program(&gl, &[vs, fs], &["a_Pos", "a_Normal"]);
+    let u_model = uniform_location(&gl, program, "u_Model");
+    let u_view_proj = uniform_location(&gl, program, "u_ViewProj");
+    let u_color = uniform_location(&gl, program, "u_Color");
+    let u_flat = uniform_location(&gl, program, "u_Flat");
+
+    let cube = {
+        let (vertices, indices) = shape::cube();
+        Mesh::new(&gl, &vertices, &indices, gl::TRIANGLES)
+    };
+    let sphere = {
+        let (vertices, indices) = shape::sphere(16, 32);
+        Mesh::new(&gl, &vertices, &indices, gl::TRIANGLES)
+    };
+    let edges = {
+        let (vertices, indices) = create_box_edges();
+        Mesh::new(&gl, &vertices, &indices, gl::LINES)
+    };
+
+    let wall = model_matrix([0.0, 0.0, 4.0], [8.0, 0.5, 4.0]);
+    let ground = model_matrix([0.0, 20.0, -0.1], [40.0, 40.0, 0.1]);
+    // A grid of spheres behind the wall, with their bounding boxes
+    let spheres: Vec<(Matrix4<f32>, [f32; 3])> = (0 .. GRID_SIZE * GRID_SIZE).map(|i| {
+        let (x, y) = (i % GRID_SIZE, i / GRID_SIZE);
+        let pos = [(x as f32 - 0.5 * (GRID_SIZE - 1) as f32) * 3.0, 5.0 + y as f32 * 3.0, 1.0];
+        let color = [x as f32 / GRID_SIZE as f32, 0.5, y as f32 / GRID_SIZE as f32];
+        (model_matrix(pos, [1.0; 3]), color)
+    }).collect();
+
+    let mut queries = vec![0; spheres.len()];
+    unsafe { gl.GenQueries(queries.len() as GLsizei, queries.as_mut_ptr()) };
+    // Visible until a query says otherwise
+    let mut visible = vec![true; spheres.len()];
+    let mut issued = false;
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 100.0);
+
+    let mut culling = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    culling = !culling,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            time = clock.time() as f32;
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // Collect the results of the previous frame
+        if issued {
+            for (query, visible) in queries.iter().zip(visible.iter_mut()) {
+                let mut available = 0;
+                unsafe { gl.GetQueryObjectuiv(*query, gl::QUERY_RESULT_AVAILABLE, &mut available) };
+                if available != 0 {
+                    let mut samples = 0;
+                    unsafe { gl.GetQueryObjectuiv(*query, gl::QUERY_RESULT, &mut samples) };
+                    *visible = samples > 0;
+                }
+            }
+        }
+
+        // Slide along the wall, looking past it
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(18.0 * (0.3 * t).sin(), -25.0, 5.0),
+            &Point3::new(0.0, 15.0, 2.0),
+            &Vector3::unit_z(),
+        );
+        let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+        let set_object = |model: &Matrix4<f32>, color: [f32; 3], flat: bool| unsafe {
+            gl.UniformMatrix4fv(u_model, 1, gl::FALSE, &model.into_fixed()[0][0]);
+            gl.Uniform3f(u_color, color[0], color[1], color[2]);
+            gl.Uniform1f(u_flat, if flat { 1.0 } else { 0.0 });
+        };
+
+        unsafe {
+            gl.Viewport(0, 0, w as GLsizei, h as GLsizei);
+            gl.ClearColor(0.3, 0.3, 0.35, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            gl.Enable(gl::DEPTH_TEST);
+            gl.DepthFunc(gl::LEQUAL);
+            gl.UseProgram(program);
+            gl.UniformMatrix4fv(u_view_proj, 1, gl::FALSE, &view_proj[0][0]);
+        }
+
+        // The occluders
+        set_object(&wall, [0.7, 0.6, 0.5], false);
+        cube.draw(&gl);
+        set_object(&ground, [0.4, 0.45, 0.4], false);
+        cube.draw(&gl);
+
+        // Query the bounding boxes against them, without touching the frame
+        unsafe {
+            gl.ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            gl.DepthMask(gl::FALSE);
+        }
+        for (&(ref model, _), &query) in spheres.iter().zip(queries.iter()) {
+            set_object(model, [0.0; 3], true);
+            unsafe { gl.BeginQuery(gl::SAMPLES_PASSED, query) };
+            cube.draw(&gl);
+            unsafe { gl.EndQuery(gl::SAMPLES_PASSED) };
+        }
+        issued = true;
+        unsafe {
+            gl.ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl.DepthMask(gl::TRUE);
+        }
+
+        let mut drawn = 0;
+        for (&(ref model, color), &is_visible) in spheres.iter().zip(visible.iter()) {
+            if is_visible || !culling {
+                set_object(model, color, false);
+                sphere.draw(&gl);
+                drawn += 1;
+            }
+        }
+
+        // Outline the boxes through the wall
+        unsafe { gl.Disable(gl::DEPTH_TEST) };
+        for (&(ref model, _), &is_visible) in spheres.iter().zip(visible.iter()) {
+            set_object(model, if is_visible { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] }, true);
+            edges.draw(&gl);
+        }
+
+        let win = &window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        window.swap_buffers();
+
+        let occluded = visible.iter().filter(|&&v| !v).count();
+        window.set_title(&format!("Occlusion query example: culling {}, {} drawn, {} occluded",
+                                  if culling { "on" } else { "off" }, drawn, occluded));
+    }
+
+    // Cleanup
+    unsafe {
+        gl.DeleteQueries(queries.len() as GLsizei, queries.as_ptr());
+        gl.DeleteProgram(program);
+        gl.DeleteShader(fs);
+        gl.DeleteShader(vs);
+    }
+    cube.delete(&gl);
+    sphere.delete(&gl);
+    edges.delete(&gl);
+}
@@ -0,0 +1,192 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Outlines found from the depth and normal buffers.
+//!
+//! `Outline` draws a full-screen pass that looks at the neighbours of each
+//! pixel in a depth texture and a normal texture, and blends an outline
+//! color over the output where either of them changes abruptly. Depth edges
+//! are where an object ends in front of another, normal edges are the creases
+//! inside an object. It only needs the two textures, so it can follow any
+//! pass that leaves them behind, like the geometry pass of deferred shading.
+//!
+//! The normals can be in any space, and a normal of zero length is taken for
+//! the background.
+//!
+//! ```ignore
+//! let mut outline = outline::Outline::new(&mut factory, &mut meshes, &normal_tex, &depth_tex);
+//! outline.width = 2.0;
+//! outline.draw(&mut renderer, &wrap, &proj);
+//! ```
+
+use cgmath::Matrix4;
+use gfx;
+use gfx::traits::*;
+use mesh_cache::MeshCache;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "t_Normal"]
+    normal: gfx::shade::TextureParam<R>,
+    #[name = "t_Depth"]
+    depth: gfx::shade::TextureParam<R>,
+    // the terms of the projection that map view depth to window depth
+    #[name = "u_DepthParams"]
+    depth_params: [f32; 2],
+    #[name = "u_Color"]
+    color: [f32; 4],
+    #[name = "u_Width"]
+    width: f32,
+    #[name = "u_Thresholds"]
+    thresholds: [f32; 2],
+    #[name = "u_Sources"]
+    sources: [f32; 2],
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Normal;
+    uniform sampler2D t_Depth;
+    uniform vec2 u_DepthParams;
+    uniform vec4 u_Color;
+    uniform float u_Width;
+    uniform vec2 u_Thresholds;
+    uniform vec2 u_Sources;
+    out vec4 o_Color;
+
+    ivec2 clamped(ivec2 p) {
+        return clamp(p, ivec2(0), textureSize(t_Depth, 0) - 1);
+    }
+
+    // distance from the eye, undoing the perspective division
+    float view_distance(ivec2 p) {
+        float z = texelFetch(t_Depth, clamped(p), 0).r * 2.0 - 1.0;
+        return u_DepthParams.y / (z + u_DepthParams.x);
+    }
+
+    vec3 normal(ivec2 p) {
+        return texelFetch(t_Normal, clamped(p), 0).xyz;
+    }
+
+    void main() {
+        ivec2 p = ivec2(gl_FragCoord.xy);
+        int r = max(int(u_Width + 0.5), 1);
+        ivec2 dx = ivec2(r, 0), dy = ivec2(0, r);
+
+        // The second difference of the distance is zero over any plane, even
+        // one seen at a grazing angle, and large across a silhouette. It is
+        // relative to the distance, so that far objects keep their outlines.
+        float d = view_distance(p);
+        float ddx = abs(view_distance(p - dx) + view_distance(p + dx) - 2.0 * d);
+        float ddy = abs(view_distance(p - dy) + view_distance(p + dy) - 2.0 * d);
+        float depth_edge = step(u_Thresholds.x, max(ddx, ddy) / d);
+
+        // how far apart the normals across the pixel point
+        vec3 n = normal(p);
+        vec3 left = normal(p - dx), right = normal(p + dx);
+        vec3 down = normal(p - dy), up = normal(p + dy);
+        float normal_edge = 0.0;
+        if (dot(n, n) > 0.0) {
+            float spread = max(max(1.0 - dot(n, left), 1.0 - dot(n, right)),
+                               max(1.0 - dot(n, down), 1.0 - dot(n, up)));
+            normal_edge = step(u_Thresholds.y, spread);
+        }
+
+        float edge = max(depth_edge * u_Sources.x, normal_edge * u_Sources.y);
+        if (edge < 0.5) {
+            discard;
+        }
+        o_Color = u_Color;
+    }
+";
+
+/// Draws outlines over the output, from a depth texture and a normal texture
+/// of the same size.
+pub struct Outline<R: gfx::Resources> {
+    /// Color of the outlines, with the alpha to blend them with.
+    pub color: [f32; 4],
+    /// How far apart the compared pixels are, which sets the line width.
+    pub width: f32,
+    /// Relative change in distance that makes a depth edge.
+    pub depth_threshold: f32,
+    /// One minus the cosine of the angle between the normals that makes a
+    /// normal edge.
+    pub normal_threshold: f32,
+    /// Whether to look for edges in the depth texture.
+    pub use_depth: bool,
+    /// Whether to look for edges in the normal texture.
+    pub use_normals: bool,
+    batch: gfx::batch::OwnedBatch<Params<R>>,
+}
+
+impl<R: gfx::Resources> Outline<R> {
+    pub fn new<F: gfx::Factory<R>>(factory: &mut F, meshes: &mut MeshCache<R>,
+                                   normal: &gfx::TextureHandle<R>,
+                                   depth: &gfx::TextureHandle<R>) -> Outline<R> {
+        let (mesh, _) = meshes.quad(factory);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = Params {
+            normal: (normal.clone(), Some(sampler.clone())),
+            depth: (depth.clone(), Some(sampler)),
+            depth_params: [-1.0, -1.0],
+            color: [0.0, 0.0, 0.0, 1.0],
+            width: 1.0,
+            thresholds: [0.0; 2],
+            sources: [1.0, 1.0],
+        };
+        let mut batch = gfx::batch::OwnedBatch::new(mesh, program, data).unwrap();
+        batch.state = gfx::DrawState::new().blend(gfx::BlendPreset::Alpha);
+
+        Outline {
+            color: [0.0, 0.0, 0.0, 1.0],
+            width: 1.0,
+            depth_threshold: 0.05,
+            normal_threshold: 0.3,
+            use_depth: true,
+            use_normals: true,
+            batch: batch,
+        }
+    }
+
+    /// Draws the outlines over what `output` already holds. `proj` is the
+    /// perspective projection the depth texture was rendered with.
+    pub fn draw<C, O>(&mut self, renderer: &mut gfx::Renderer<R, C>, output: &O,
+                      proj: &Matrix4<f32>)
+                      where C: gfx::CommandBuffer<R>, O: gfx::Output<R> {
+        self.batch.params.depth_params = [proj.z.z, proj.w.z];
+        self.batch.params.color = self.color;
+        self.batch.params.width = self.width;
+        self.batch.params.thresholds = [self.depth_threshold, self.normal_threshold];
+        self.batch.params.sources = [
+            if self.use_depth { 1.0 } else { 0.0 },
+            if self.use_normals { 1.0 } else { 0.0 },
+        ];
+        renderer.draw(&self.batch, output).unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of outlining a scene in a post pass.
+//
+// The scene is drawn with banded toon shading into a frame with two color
+// planes, the color and the world space normal, and a depth texture. The
+// color is shown in the window, and `gfx_examples::outline` then draws lines
+// over it wherever the depth or the normal changes abruptly between nearby
+// pixels: around the silhouettes, where objects overlap and along the sharp
+// creases of the cube.
+//
+// Unlike drawing each mesh again inflated along its normals, this doesn't
+// care what was drawn or how, and costs the same for any number of objects.
+// It only needs the depth and the normals, which a deferred renderer already
+// has, see --outline in the deferred example.
+//
+// Press E to cycle through the edges from both buffers, from the depth only
+// and from the normals only, O to show the outlines alone, Up and Down to
+// change their width, and Space to stop the shapes.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::outline::Outline;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const SOURCES: [&'static str; 3] = ["depth and normals", "depth only", "normals only"];
+const BACKGROUND: [f32; 3] = [0.95, 0.92, 0.85];
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct BlitParams<R: gfx::Resources> {
+    #[name = "t_Color"]
+    color: gfx::shade::TextureParam<R>,
+    #[name = "u_Background"]
+    background: [f32; 3],
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+    out vec4 o_Normal;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        // three flat bands of light
+        float diffuse = max(0.0, dot(n, normalize(vec3(0.4, -0.3, 0.9))));
+        float band = diffuse > 0.6 ? 1.0 : diffuse > 0.2 ? 0.7 : 0.45;
+        o_Color = vec4(u_Color * band, 1.0);
+        o_Normal = vec4(n, 0.0);
+    }
+";
+
+static BLIT_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static BLIT_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Color;
+    uniform vec3 u_Background;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // the frame is cleared to zero, so that the normals of the
+        // background are zero as well
+        vec4 color = texture(t_Color, v_TexCoord);
+        o_Color = vec4(mix(u_Background, color.rgb, color.a), 1.0);
+    }
+";
+
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Outline example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let color = factory.create_texture_rgba8(w, h, false).unwrap();
+    let normal = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA,
+                                        gfx::attrib::FloatSize::F16),
+    }).unwrap();
+    let depth = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let frame = gfx::Frame {
+        colors: vec![
+            Plane::Texture(color.clone(), 0, None),
+            Plane::Texture(normal.clone(), 0, None),
+        ],
+        depth: Some(Plane::Texture(depth.clone(), 0, None)),
+        .. gfx::Frame::empty(w, h)
+    };
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    // (mesh, position, color) of each shape, on a ground below them, the two
+    // spheres sharing their buffers with each other and the quad with the
+    // outline pass
+    let mut meshes = MeshCache::new();
+    let shapes = vec![
+        (meshes.cube(&mut factory), [-3.0, 0.0, 0.0], [0.9, 0.45, 0.3]),
+        (meshes.sphere(&mut factory, 24, 48), [0.0, 1.5, 0.0], [0.35, 0.7, 0.45]),
+        (meshes.torus(&mut factory, 32, 64, 0.8, 0.35), [3.0, 0.0, 0.0], [0.35, 0.55, 0.9]),
+        (meshes.sphere(&mut factory, 24, 48), [0.5, -2.0, 0.0], [0.9, 0.8, 0.35]),
+    ];
+    let mut batches: Vec<_> = shapes.into_iter().map(|((mesh, slice), pos, color)| {
+        let data = SceneParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: color,
+            _dummy: std::marker::PhantomData,
+        };
+        (context.make_batch(&program, data, &mesh, slice, &state).unwrap(), pos)
+    }).collect();
+    let mut ground = {
+        let (mesh, slice) = meshes.plane(&mut factory);
+        let data = SceneParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: place(Matrix3::new(8.0, 0.0, 0.0,
+                                      0.0, 8.0, 0.0,
+                                      0.0, 0.0, 1.0), 0.0, 0.0, -1.2).into_fixed(),
+            color: [0.7, 0.7, 0.65],
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let blit = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(BLIT_VERTEX_SRC, BLIT_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = BlitParams {
+            color: (color.clone(), Some(sampler)),
+            background: BACKGROUND,
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    let mut outline = Outline::new(&mut factory, &mut meshes, &normal, &depth);
+    outline.color = [0.1, 0.08, 0.06, 1.0];
+    outline.width = window::scale_factor(&wrap.window);
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 11.0, -1.2, 0.5);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 0.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+    let paper = gfx::ClearData {
+        color: [BACKGROUND[0], BACKGROUND[1], BACKGROUND[2], 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut sources = 0;
+    let mut outlines_only = false;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::E => sources = (sources + 1) % SOURCES.len(),
+                    VirtualKeyCode::O => outlines_only = !outlines_only,
+                    VirtualKeyCode::Up => outline.width = (outline.width + 1.0).min(6.0),
+                    VirtualKeyCode::Down => outline.width = (outline.width - 1.0).max(1.0),
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.4 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &frame);
+        ground.params.view_proj = view_proj;
+        renderer.draw(&(&ground, &context), &frame).unwrap();
+        for (i, &mut (ref mut batch, pos)) in batches.iter_mut().enumerate() {
+            let b = a + i as f32;
+            let spin = Matrix3::from_angle_z(cgmath::rad(b))
+                               .mul_m(&Matrix3::from_angle_x(cgmath::rad(0.6 * b)));
+            batch.params.view_proj = view_proj;
+            batch.params.model = place(spin, pos[0], pos[1], pos[2]).into_fixed();
+            renderer.draw(&(&*batch, &context), &frame).unwrap();
+        }
+
+        if outlines_only {
+            renderer.clear(paper, gfx::COLOR, &wrap);
+        } else {
+            renderer.draw(&(&blit, &context), &wrap).unwrap();
+        }
+        outline.use_depth = sources != 2;
+        outline.use_normals = sources != 1;
+        outline.draw(&mut renderer, &wrap, &proj);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Outline example: edges from {}, width {}",
+                                       SOURCES[sources], outline.width));
+    }
+}
@@ -0,0 +1,454 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a CPU particle system drawn with instancing.
+//
+// Three fountains emit 100k particles, simulated on the CPU with gravity and
+// a limited lifetime. Every frame their position, size and color are written
+// into a float texture, and a single instanced draw of a quad renders all of
+// them, each instance fetching its own texels by gl_InstanceID.
+//
+// Rendering happens in three passes:
+// First,  the scene is rendered into a color and a depth texture.
+// Second, the particles are blended additively over the scene color. They
+//         fade out where they get close to the scene depth, instead of
+//         cutting hard edges into the geometry.
+// Third,  the result is displayed.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const NUM_PARTICLES: usize = 100_000;
+// Each particle takes two texels: position and size, then color
+const DATA_WIDTH: gfx::tex::Size = 1024;
+const DATA_HEIGHT: gfx::tex::Size = ((NUM_PARTICLES * 2 + 1023) / 1024) as gfx::tex::Size;
+const NEAR: f32 = 0.5;
+const FAR: f32 = 100.0;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct CornerVertex {
+    #[name = "a_Corner"]
+    corner: [f32; 2],
+}
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct ParticleParams<R: gfx::Resources> {
+    #[name = "u_View"]
+    view: [[f32; 4]; 4],
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    #[name = "u_FrameRes"]
+    frame_res: [f32; 2],
+    #[name = "u_NearFar"]
+    near_far: [f32; 2],
+    #[name = "t_Data"]
+    data: gfx::shade::TextureParam<R>,
+    #[name = "t_Depth"]
+    depth: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct BlitParams<R: gfx::Resources> {
+    #[name = "u_Tex"]
+    tex: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.4, 1.0))));
+        o_Color = vec4(u_Color * (0.2 + 0.6 * d), 1.0);
+    }
+";
+
+static PARTICLE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_View;
+    uniform mat4 u_Proj;
+    uniform sampler2D t_Data;
+    in vec2 a_Corner;
+    out vec2 v_Corner;
+    out vec4 v_Color;
+    out float v_Depth;
+
+    void main() {
+        int i = gl_InstanceID * 2;
+        int width = textureSize(t_Data, 0).x;
+        vec4 pos_size = texelFetch(t_Data, ivec2(i % width, i / width), 0);
+        v_Color = texelFetch(t_Data, ivec2((i + 1) % width, (i + 1) / width), 0);
+        v_Corner = a_Corner;
+
+        // expand the quad in view space, so that it always faces the camera
+        vec4 center = u_View * vec4(pos_size.xyz, 1.0);
+        center.xy += a_Corner * pos_size.w;
+        v_Depth = -center.z;
+        gl_Position = u_Proj * center;
+    }
+";
+
+static PARTICLE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec2 u_FrameRes;
+    uniform vec2 u_NearFar;
+    uniform sampler2D t_Depth;
+    in vec2 v_Corner;
+    in vec4 v_Color;
+    in float v_Depth;
+    out vec4 o_Color;
+
+    float linear_depth(float d) {
+        float n = u_NearFar.x, f = u_NearFar.y;
+        return 2.0 * n * f / (f + n - (2.0 * d - 1.0) * (f - n));
+    }
+
+    void main() {
+        float scene = linear_depth(texture(t_Depth, gl_FragCoord.xy / u_FrameRes).r);
+        // fade out over the last 0.5 units in front of the scene, which
+        // also hides the particles behind it
+        float soft = clamp((scene - v_Depth) / 0.5, 0.0, 1.0);
+        float round = max(0.0, 1.0 - dot(v_Corner, v_Corner));
+        o_Color = v_Color * (round * soft);
+    }
+";
+
+static BLIT_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        gl_Position = vec4(a_Pos, 1.0);
+    }
+";
+
+static BLIT_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D u_Tex;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = texture(u_Tex, v_TexCoord);
+    }
+";
+
+#[derive(Clone, Copy)]
+struct Particle {
+    pos: [f32; 3],
+    vel: [f32; 3],
+    age: f32,
+    life: f32,
+}
+
+// Fountain positions, particle i belongs to EMITTERS[i % 3]
+static EMITTERS: [[f32; 3]; 3] = [
+    [-6.0, 0.0, 0.0],
+    [ 0.0, 4.0, 2.0],
+    [ 6.0, 0.0, 0.0],
+];
+
+fn respawn<R: Rng>(p: &mut Particle, emitter: &[f32; 3], rng: &mut R) {
+    let angle = rng.gen::<f32>() * 2.0 * std::f32::consts::PI;
+    let spread = rng.gen::<f32>() * 1.5;
+    p.pos = *emitter;
+    p.vel = [spread * angle.cos(), spread * angle.sin(), 8.0 + 3.0 * rng.gen::<f32>()];
+    p.age = 0.0;
+    p.life = 1.5 + rng.gen::<f32>();
+}
+
+// Yellow when young, then red, then fading to nothing
+fn color_over_life(t: f32) -> [f32; 4] {
+    let fade = 1.0 - t;
+    [fade, fade * (1.0 - t) * 0.8, 0.2 * fade * fade, fade]
+}
+
+fn model_matrix(pos: [f32; 3], scale: f32) -> Matrix4<f32> {
+    let mut model = Matrix3::identity().mul_s(scale).to_matrix4();
+    model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Particle example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let texture_color = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F16),
+    }).unwrap();
+    let texture_depth = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let scene_frame = gfx::Frame {
+        colors: vec![Plane::Texture(texture_color.clone(), 0, None)],
+        depth: Some(Plane::Texture(texture_depth.clone(), 0, None)),
+        .. gfx::Frame::empty(w, h)
+    };
+    // The particles read the scene depth, so it can't be attached here
+    let particle_frame = gfx::Frame {
+        colors: vec![Plane::Texture(texture_color.clone(), 0, None)],
+        .. gfx::Frame::empty(w, h)
+    };
+
+    let texture_data = factory.create_texture(gfx::tex::TextureInfo {
+        width: DATA_WIDTH,
+        height: DATA_HEIGHT,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F32),
+    }).unwrap();
+    let data_info = texture_data.get_info().to_image_info();
+
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, NEAR, FAR);
+
+    let scene_program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC)
+                               .unwrap();
+    let scene_state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut meshes = MeshCache::new();
+    let mut scene: Vec<_> = {
+        let (plane, plane_slice) = meshes.plane(&mut factory);
+        let (cube, cube_slice) = meshes.cube(&mut factory);
+
+        let objects = [
+            (&plane, &plane_slice, model_matrix([0.0, 0.0, 0.0], 15.0), [0.4, 0.4, 0.4]),
+            (&cube, &cube_slice, model_matrix([-6.0, 0.0, 0.5], 0.5), [0.3, 0.3, 0.6]),
+            (&cube, &cube_slice, model_matrix([ 0.0, 4.0, 1.0], 1.0), [0.3, 0.3, 0.6]),
+            (&cube, &cube_slice, model_matrix([ 6.0, 0.0, 0.5], 0.5), [0.3, 0.3, 0.6]),
+            (&cube, &cube_slice, model_matrix([ 2.0, -2.0, 1.5], 1.5), [0.5, 0.3, 0.3]),
+        ];
+        objects.iter().map(|&(mesh, slice, model, color)| {
+            let data = SceneParams {
+                model: model.into_fixed(),
+                view_proj: Matrix4::identity().into_fixed(),
+                color: color,
+                _dummy: std::marker::PhantomData,
+            };
+            context.make_batch(&scene_program, data, mesh, slice.clone(), &scene_state)
+                   .unwrap()
+        }).collect()
+    };
+
+    let mut particle_batch = {
+        let vertex_data = [
+            CornerVertex { corner: [-1.0, -1.0] },
+            CornerVertex { corner: [ 1.0, -1.0] },
+            CornerVertex { corner: [-1.0,  1.0] },
+            CornerVertex { corner: [ 1.0,  1.0] },
+        ];
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleStrip);
+        let program = factory.link_program(PARTICLE_VERTEX_SRC, PARTICLE_FRAGMENT_SRC)
+                             .unwrap();
+        let state = gfx::DrawState::new().blend(gfx::BlendPreset::Add);
+        let data = ParticleParams {
+            view: Matrix4::identity().into_fixed(),
+            proj: proj.into_fixed(),
+            frame_res: [w as f32, h as f32],
+            near_far: [NEAR, FAR],
+            data: (texture_data.clone(), Some(sampler.clone())),
+            depth: (texture_depth.clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let blit = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(BLIT_VERTEX_SRC, BLIT_FRAGMENT_SRC)
+                             .unwrap();
+        let data = BlitParams {
+            tex: (texture_color.clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new())
+               .unwrap()
+    };
+
+    // Start with random ages, so that the fountains don't pulse
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut particles: Vec<Particle> = (0 .. NUM_PARTICLES).map(|i| {
+        let mut p = Particle { pos: [0.0; 3], vel: [0.0; 3], age: 0.0, life: 0.0 };
+        respawn(&mut p, &EMITTERS[i % 3], &mut rng);
+        p.age = rng.gen::<f32>() * p.life;
+        p
+    }).collect();
+    let mut particle_data: Vec<[f32; 4]> = (0 .. (DATA_WIDTH as usize * DATA_HEIGHT as usize))
+        .map(|_| [0.0; 4]).collect();
+
+    let clear_data = gfx::ClearData {
+        color: [0.05, 0.05, 0.1, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            let dt = clock.step();
+            prev_angle = angle;
+            angle = 0.1 * clock.time() as f32;
+
+            for (i, p) in particles.iter_mut().enumerate() {
+                p.age += dt;
+                if p.age > p.life {
+                    respawn(p, &EMITTERS[i % 3], &mut rng);
+                }
+                p.vel[2] -= 9.81 * dt;
+                for k in 0 .. 3 {
+                    p.pos[k] += p.vel[k] * dt;
+                }
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        // Write the particles into the data texture
+        for (p, texels) in particles.iter().zip(particle_data.chunks_mut(2)) {
+            let t = p.age / p.life;
+            texels[0] = [p.pos[0], p.pos[1], p.pos[2], 0.05 + 0.1 * t];
+            texels[1] = color_over_life(t);
+        }
+        factory.update_texture(&texture_data, &data_info, &particle_data, None)
+               .unwrap();
+
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(20.0 * a.cos(), 20.0 * a.sin(), 8.0),
+            &Point3::new(0.0, 0.0, 3.0),
+            &Vector3::unit_z(),
+        );
+        for batch in scene.iter_mut() {
+            batch.params.view_proj = proj.mul_m(&view.mat).into_fixed();
+        }
+        particle_batch.params.view = view.mat.into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &scene_frame);
+        for batch in scene.iter() {
+            renderer.draw(&(batch, &context), &scene_frame).unwrap();
+        }
+
+        renderer.draw_instanced(&(&particle_batch, &context),
+                                NUM_PARTICLES as u32, 0, &particle_frame)
+                .unwrap();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&blit, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
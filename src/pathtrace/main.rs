@@ -0,0 +1,411 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of progressive path tracing in a fragment shader.
+//
+// A full-screen pass traces one path per pixel through a small room of
+// spheres and a box, adding it to the sum of the previous samples. The sums
+// live in two float textures used in turn: each pass reads one and writes the
+// other, since a texture can't be sampled while it is being rendered to. A
+// second pass shows the average of the last one written. The sums start over
+// whenever the camera moves.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press Up and
+// Down to change the number of samples per frame, and R to start over. The
+// sample count is shown in a text overlay, or in the window title if no font
+// is found.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate time;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::text::{Font, Span, TextRenderer};
+use gfx_examples::{window, Thumbnail};
+
+#[shader_param]
+struct TraceParams<R: gfx::Resources> {
+    #[name = "u_InvViewProj"]
+    inv_view_proj: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_Resolution"]
+    resolution: [f32; 2],
+    // number of samples already in t_Accum
+    #[name = "u_Samples"]
+    samples: f32,
+    #[name = "t_Accum"]
+    accum: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct DisplayParams<R: gfx::Resources> {
+    #[name = "t_Accum"]
+    accum: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_Ndc;
+
+    void main() {
+        v_Ndc = a_Pos.xy;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static TRACE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+    const float EPSILON = 1e-4;
+    const int BOUNCES = 6;
+    const vec3 LIGHT = vec3(12.0, 11.0, 9.5);
+    const vec3 SKY = vec3(0.04, 0.05, 0.07);
+    const int WHITE = 0;
+    const int RED = 1;
+    const int GREEN = 2;
+    const int BLUE = 3;
+    const int MIRROR = 4;
+    const int EMITTER = 5;
+
+    uniform mat4 u_InvViewProj;
+    uniform vec3 u_CamPos;
+    uniform vec2 u_Resolution;
+    uniform float u_Samples;
+    uniform sampler2D t_Accum;
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    struct Hit {
+        float t;
+        vec3 normal;
+        int material;
+    };
+
+    uint hash(uint x) {
+        x ^= x >> 16;
+        x *= 0x7feb352du;
+        x ^= x >> 15;
+        x *= 0x846ca68bu;
+        x ^= x >> 16;
+        return x;
+    }
+
+    float random(inout uint state) {
+        state = hash(state);
+        return float(state >> 8) / 16777216.0;
+    }
+
+    // an axis-aligned rectangle at `center`, across `axis`, seen from both sides
+    void rect(vec3 ro, vec3 rd, int axis, vec3 center, vec3 half_size, int material,
+              inout Hit hit) {
+        float t = (center[axis] - ro[axis]) / rd[axis];
+        vec3 p = ro + t * rd;
+        if (t > EPSILON && t < hit.t && all(lessThanEqual(abs(p - center), half_size))) {
+            hit.t = t;
+            hit.normal = vec3(0.0);
+            hit.normal[axis] = -sign(rd[axis]);
+            hit.material = material;
+        }
+    }
+
+    void sphere(vec3 ro, vec3 rd, vec3 center, float radius, int material, inout Hit hit) {
+        vec3 oc = ro - center;
+        float b = dot(oc, rd);
+        float d = b * b - dot(oc, oc) + radius * radius;
+        if (d < 0.0) return;
+        float t = -b - sqrt(d);
+        if (t > EPSILON && t < hit.t) {
+            hit.t = t;
+            hit.normal = (oc + t * rd) / radius;
+            hit.material = material;
+        }
+    }
+
+    void box(vec3 ro, vec3 rd, vec3 center, vec3 half_size, int material, inout Hit hit) {
+        vec3 t0 = (center - half_size - ro) / rd;
+        vec3 t1 = (center + half_size - ro) / rd;
+        vec3 near = min(t0, t1), far = max(t0, t1);
+        float t = max(max(near.x, near.y), near.z);
+        if (t > EPSILON && t < hit.t && t < min(min(far.x, far.y), far.z)) {
+            hit.t = t;
+            hit.normal = -sign(rd) * step(near.yzx, near) * step(near.zxy, near);
+            hit.material = material;
+        }
+    }
+
+    // A room from -1 to 1, open towards -Y, with a light in the ceiling
+    bool trace(vec3 ro, vec3 rd, out Hit hit) {
+        hit.t = 1e20;
+        vec3 wall = vec3(1.0, 1.0, 0.0) + EPSILON;
+        rect(ro, rd, 0, vec3(-1.0, 0.0, 0.0), wall.zxy, RED, hit);
+        rect(ro, rd, 0, vec3(1.0, 0.0, 0.0), wall.zxy, GREEN, hit);
+        rect(ro, rd, 1, vec3(0.0, 1.0, 0.0), wall.xzy, WHITE, hit);
+        rect(ro, rd, 2, vec3(0.0, 0.0, -1.0), wall, WHITE, hit);
+        rect(ro, rd, 2, vec3(0.0, 0.0, 1.0), wall, WHITE, hit);
+        rect(ro, rd, 2, vec3(0.0, 0.0, 0.999), vec3(0.3, 0.3, 1.0), EMITTER, hit);
+        sphere(ro, rd, vec3(-0.45, -0.2, -0.6), 0.4, MIRROR, hit);
+        sphere(ro, rd, vec3(0.45, -0.45, -0.75), 0.25, BLUE, hit);
+        box(ro, rd, vec3(0.4, 0.35, -0.45), vec3(0.3, 0.3, 0.55), WHITE, hit);
+        return hit.t < 1e20;
+    }
+
+    vec3 albedo(int material) {
+        if (material == RED) return vec3(0.65, 0.08, 0.06);
+        if (material == GREEN) return vec3(0.12, 0.5, 0.1);
+        if (material == BLUE) return vec3(0.15, 0.25, 0.7);
+        return vec3(0.75);
+    }
+
+    // a direction around the normal, more likely near it as the cosine law
+    // of diffuse surfaces, so no weight is needed
+    vec3 cosine_direction(vec3 n, inout uint state) {
+        float u = random(state), v = random(state);
+        float r = sqrt(u), phi = 2.0 * PI * v;
+        vec3 t = normalize(cross(abs(n.x) > 0.5 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0), n));
+        vec3 b = cross(n, t);
+        return normalize(r * cos(phi) * t + r * sin(phi) * b + sqrt(1.0 - u) * n);
+    }
+
+    vec3 radiance(vec3 ro, vec3 rd, inout uint state) {
+        vec3 throughput = vec3(1.0);
+        for (int bounce = 0; bounce < BOUNCES; ++bounce) {
+            Hit hit;
+            if (!trace(ro, rd, hit)) {
+                return throughput * SKY;
+            }
+            if (hit.material == EMITTER) {
+                return throughput * LIGHT;
+            }
+            ro += hit.t * rd + EPSILON * hit.normal;
+            if (hit.material == MIRROR) {
+                rd = reflect(rd, hit.normal);
+                throughput *= 0.9;
+            } else {
+                rd = cosine_direction(hit.normal, state);
+                throughput *= albedo(hit.material);
+            }
+        }
+        return vec3(0.0);
+    }
+
+    void main() {
+        uint state = hash(uint(gl_FragCoord.x) + 4096u * uint(gl_FragCoord.y))
+                   ^ hash(uint(u_Samples) * 26699u + 1u);
+        // a different point of the pixel for every sample smooths the edges
+        vec2 jitter = vec2(random(state), random(state)) - 0.5;
+        vec4 far = u_InvViewProj * vec4(v_Ndc + 2.0 * jitter / u_Resolution, 1.0, 1.0);
+        vec3 rd = normalize(far.xyz / far.w);
+
+        vec4 sum = u_Samples > 0.5 ? texelFetch(t_Accum, ivec2(gl_FragCoord.xy), 0) : vec4(0.0);
+        o_Color = sum + vec4(radiance(u_CamPos, rd, state), 1.0);
+    }
+";
+
+static DISPLAY_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Accum;
+    out vec4 o_Color;
+
+    void main() {
+        // the sample count is in alpha
+        vec4 sum = texelFetch(t_Accum, ivec2(gl_FragCoord.xy), 0);
+        vec3 color = sum.rgb / max(sum.a, 1.0);
+        color = 1.0 - exp(-color);
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+fn create_target<R: gfx::Resources, F: Factory<R>>(
+                 width: gfx::tex::Size, height: gfx::tex::Size, factory: &mut F)
+                 -> (gfx::Frame<R>, gfx::TextureHandle<R>) {
+    let texture_info = gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F32),
+    };
+    let texture = factory.create_texture(texture_info).unwrap();
+    let frame = gfx::Frame {
+        colors: vec![Plane::Texture(texture.clone(), 0, None)],
+        .. gfx::Frame::empty(width, height)
+    };
+    (frame, texture)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Path tracing example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let targets = [create_target(w as u16, h as u16, &mut factory),
+                   create_target(w as u16, h as u16, &mut factory)];
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let mut meshes = MeshCache::new();
+    let (mesh, slice) = meshes.quad(&mut factory);
+    let state = gfx::DrawState::new();
+
+    let mut trace = {
+        let program = factory.link_program(VERTEX_SRC, TRACE_FRAGMENT_SRC).unwrap();
+        let data = TraceParams {
+            inv_view_proj: Matrix4::identity().into_fixed(),
+            cam_pos: [0.0; 3],
+            resolution: [w as f32, h as f32],
+            samples: 0.0,
+            accum: (targets[0].1.clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice.clone(), &state)
+               .unwrap()
+    };
+
+    let mut display = {
+        let program = factory.link_program(VERTEX_SRC, DISPLAY_FRAGMENT_SRC).unwrap();
+        let data = DisplayParams {
+            accum: (targets[0].1.clone(), Some(sampler.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let mut hud = match Font::load_default(16) {
+        Ok(font) => Some(TextRenderer::new(&mut factory, font, 256)),
+        Err(e) => {
+            println!("{}, showing statistics in the window title", e);
+            None
+        },
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+    // looking into the open side of the room
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 4.0, -0.5 * PI, 0.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // the target last written to
+    let mut current = 0;
+    let mut samples = 0u32;
+    let mut per_frame = 1u32;
+    let mut last_view = [[0.0f32; 4]; 4];
+    let (mut traced, mut last_report, mut rate) = (0u32, time::precise_time_s(), 0.0);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) =>
+                    per_frame = (per_frame * 2).min(32),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) =>
+                    per_frame = (per_frame / 2).max(1),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::R)) =>
+                    samples = 0,
+                _ => {},
+            }
+        }
+
+        let view = camera.view().mat;
+        // any motion makes the samples so far wrong
+        if view.into_fixed() != last_view {
+            last_view = view.into_fixed();
+            samples = 0;
+        }
+        let mut rotation = view;
+        rotation.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        trace.params.inv_view_proj = proj.mul_m(&rotation).invert().unwrap().into_fixed();
+        trace.params.cam_pos = camera.position().into_fixed();
+
+        for _ in 0 .. per_frame {
+            let next = 1 - current;
+            trace.params.samples = samples as f32;
+            trace.params.accum.0 = targets[current].1.clone();
+            renderer.draw(&(&trace, &context), &targets[next].0).unwrap();
+            current = next;
+            samples += 1;
+            traced += 1;
+        }
+
+        display.params.accum.0 = targets[current].1.clone();
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        renderer.draw(&(&display, &context), &wrap).unwrap();
+
+        let stats = format!("{} samples per pixel, {} per frame, {:.0} samples/s",
+                            samples, per_frame, rate);
+        match hud {
+            Some(ref mut hud) => {
+                hud.add(&[Span::new(&stats, [1.0, 1.0, 1.0, 1.0])], [10.0, 10.0], None);
+                hud.draw(&mut factory, &mut renderer, &wrap);
+            },
+            None => wrap.window.set_title(&stats),
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let now = time::precise_time_s();
+        if now - last_report >= 0.5 {
+            rate = traced as f64 / (now - last_report);
+            traced = 0;
+            last_report = now;
+        }
+    }
+}
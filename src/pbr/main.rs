@@ -0,0 +1,319 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of physically based shading, on a grid of spheres.
+//
+// The roughness grows from left to right and the metalness from bottom to
+// top. Every sphere is lit by the prefiltered environment of
+// `gfx_examples::ibl` and by a few point lights, both with the same GGX
+// microfacet model, so a mistake in either shows as a sphere that doesn't fit
+// in with its neighbors.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press I to
+// toggle the image based light, L to toggle the point lights, C to change
+// the albedo, and Plus and Minus to change the exposure. Run with a number
+// for another size of the grid.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::env;
+use std::str::FromStr;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::cubemap::{self, Skybox};
+use gfx_examples::ibl::Ibl;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Thumbnail};
+
+const NUM_LIGHTS: usize = 4;
+const SPACING: f32 = 1.2;
+
+static ALBEDOS: [[f32; 3]; 4] = [
+    [0.9, 0.1, 0.1],
+    [1.0, 0.78, 0.34],
+    [0.95, 0.95, 0.95],
+    [0.1, 0.3, 0.8],
+];
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_Albedo"]
+    albedo: [f32; 3],
+    #[name = "u_Roughness"]
+    roughness: f32,
+    #[name = "u_Metalness"]
+    metalness: f32,
+    #[name = "u_MaxLod"]
+    max_lod: f32,
+    // how much of each kind of light, 0 or 1
+    #[name = "u_Sources"]
+    sources: [f32; 2],
+    #[name = "u_Exposure"]
+    exposure: f32,
+    #[name = "u_LightBlock"]
+    lights: gfx::RawBufferHandle<R>,
+    #[name = "t_Specular"]
+    specular: gfx::shade::TextureParam<R>,
+    #[name = "t_Irradiance"]
+    irradiance: gfx::shade::TextureParam<R>,
+    #[name = "t_Brdf"]
+    brdf: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Pos = 0.45 * a_Pos + u_Offset;
+        v_Normal = a_Normal;
+        gl_Position = u_ViewProj * vec4(v_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+    const int NUM_LIGHTS = 4;
+
+    uniform vec3 u_CamPos;
+    uniform vec3 u_Albedo;
+    uniform float u_Roughness;
+    uniform float u_Metalness;
+    uniform float u_MaxLod;
+    uniform vec2 u_Sources;
+    uniform float u_Exposure;
+    uniform samplerCube t_Specular;
+    uniform samplerCube t_Irradiance;
+    uniform sampler2D t_Brdf;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    // the position and the color of each light
+    layout(std140)
+    uniform u_LightBlock {
+        vec4 u_Lights[2 * NUM_LIGHTS];
+    };
+
+    float distribution_ggx(float n_dot_h, float a) {
+        float a2 = a * a;
+        float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+        return a2 / (PI * d * d);
+    }
+
+    float geometry_smith(float n_dot_v, float n_dot_l, float k) {
+        return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+    }
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 v = normalize(u_CamPos - v_Pos);
+        float n_dot_v = max(dot(n, v), 1e-3);
+        vec3 f0 = mix(vec3(0.04), u_Albedo, u_Metalness);
+        vec3 color = vec3(0.0);
+
+        // a perfectly smooth sphere would reflect points of light as points
+        float a = max(u_Roughness * u_Roughness, 0.002);
+        // Smith's geometry term, with the remapping of k for direct light
+        float k = (u_Roughness + 1.0) * (u_Roughness + 1.0) / 8.0;
+        for (int i = 0; i < NUM_LIGHTS; ++i) {
+            vec3 to_light = u_Lights[2 * i].xyz - v_Pos;
+            vec3 l = normalize(to_light);
+            vec3 h = normalize(v + l);
+            float n_dot_l = max(dot(n, l), 0.0);
+            vec3 fresnel = f0 + (1.0 - f0) * pow(1.0 - max(dot(h, v), 0.0), 5.0);
+            vec3 specular = distribution_ggx(max(dot(n, h), 0.0), a)
+                          * geometry_smith(n_dot_v, n_dot_l, k) * fresnel
+                          / (4.0 * n_dot_v * n_dot_l + 1e-4);
+            vec3 diffuse = (1.0 - fresnel) * (1.0 - u_Metalness) * u_Albedo / PI;
+            vec3 radiance = u_Lights[2 * i + 1].rgb / dot(to_light, to_light);
+            color += u_Sources.y * (diffuse + specular) * radiance * n_dot_l;
+        }
+
+        vec3 fresnel = f0 + (max(vec3(1.0 - u_Roughness), f0) - f0) * pow(1.0 - n_dot_v, 5.0);
+        vec3 diffuse = (1.0 - fresnel) * (1.0 - u_Metalness) * u_Albedo
+                     * texture(t_Irradiance, n).rgb;
+        vec2 brdf = texture(t_Brdf, vec2(n_dot_v, u_Roughness)).rg;
+        vec3 specular = textureLod(t_Specular, reflect(-v, n), u_Roughness * u_MaxLod).rgb
+                      * (f0 * brdf.x + brdf.y);
+        color += u_Sources.x * (diffuse + specular);
+
+        o_Color = vec4(1.0 - exp(-u_Exposure * color), 1.0);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("PBR example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let n = env::args().skip(1).filter_map(|s| FromStr::from_str(&s).ok())
+                   .next().unwrap_or(7usize).max(2);
+
+    let env = cubemap::generate(&mut factory, 512, cubemap::environment);
+    let mut meshes = MeshCache::new();
+    let ibl = Ibl::new(&mut factory, &mut meshes, &mut renderer, &env);
+    device.submit(renderer.as_buffer());
+    renderer.reset();
+    let mut skybox = Skybox::new(&mut factory, &mut meshes, env.clone());
+    // a blurred background keeps the eye on the spheres
+    skybox.lod = 2.0;
+
+    let half = 0.5 * SPACING * (n - 1) as f32;
+    let lights = [
+        [-half - 2.0, -4.0, half + 2.0, 1.0], [60.0, 60.0, 60.0, 1.0],
+        [half + 2.0, -4.0, half + 2.0, 1.0], [60.0, 60.0, 60.0, 1.0],
+        [-half - 2.0, -4.0, -half - 2.0, 1.0], [40.0, 50.0, 70.0, 1.0],
+        [half + 2.0, -4.0, -half - 2.0, 1.0], [70.0, 50.0, 40.0, 1.0],
+    ];
+    let light_buffer = factory.create_buffer::<[f32; 4]>(2 * NUM_LIGHTS,
+                                                         gfx::BufferUsage::Static);
+    factory.update_buffer(&light_buffer, &lights, 0);
+
+    let trilinear = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+    let bilinear = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let mut batch = {
+        let (mesh, slice) = meshes.sphere(&mut factory, 32, 64);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [0.0; 3],
+            cam_pos: [0.0; 3],
+            albedo: ALBEDOS[0],
+            roughness: 0.0,
+            metalness: 0.0,
+            max_lod: ibl.max_lod(),
+            sources: [1.0, 1.0],
+            exposure: 1.0,
+            lights: light_buffer.raw().clone(),
+            specular: (ibl.specular.clone(), Some(trilinear)),
+            irradiance: (ibl.irradiance.clone(), Some(bilinear.clone())),
+            brdf: (ibl.brdf.clone(), Some(bilinear)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0),
+                                      3.0 * half + 4.0, -0.5 * std::f32::consts::PI, 0.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut albedo = 0;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let params = &mut batch.params;
+                    match key {
+                        VirtualKeyCode::I => params.sources[0] = 1.0 - params.sources[0],
+                        VirtualKeyCode::L => params.sources[1] = 1.0 - params.sources[1],
+                        VirtualKeyCode::C => albedo = (albedo + 1) % ALBEDOS.len(),
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals =>
+                            params.exposure *= 1.25,
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus =>
+                            params.exposure *= 0.8,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let view = camera.view().mat;
+        batch.params.view_proj = proj.mul_m(&view).into_fixed();
+        batch.params.cam_pos = camera.position().into_fixed();
+        batch.params.albedo = ALBEDOS[albedo];
+        // the background only shows the environment when it lights the spheres
+        skybox.exposure = batch.params.exposure * batch.params.sources[0];
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        let last = (n - 1) as f32;
+        for row in 0 .. n {
+            for column in 0 .. n {
+                batch.params.offset = [SPACING * column as f32 - half, 0.0,
+                                       SPACING * row as f32 - half];
+                batch.params.roughness = column as f32 / last;
+                batch.params.metalness = row as f32 / last;
+                renderer.draw(&(&batch, &context), &wrap).unwrap();
+            }
+        }
+        skybox.draw(&mut renderer, &wrap, &proj, &view);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -17,6 +17,7 @@
 
 extern crate cgmath;
 extern crate gfx;
+extern crate gfx_examples;
 extern crate gfx_window_glfw;
 extern crate glfw;
 extern crate time;
@@ -27,16 +28,16 @@ use cgmath::FixedArray;
 use cgmath::{Matrix, Point3, Vector3, Matrix3, ToMatrix4};
 use cgmath::{Transform, AffineMatrix3, Vector4, Array1};
 use gfx::traits::*;
+use gfx_examples::Thumbnail;
+use gfx_examples::gl_program::{compile_shader, link_program};
 use glfw::Context;
 use gl::Gl;
 use gl::types::*;
 use std::mem;
 use std::ptr;
-use std::str;
 use std::env;
 use std::str::FromStr;
 use std::sync::mpsc::Receiver;
-use std::iter::repeat;
 use std::ffi::CString;
 
 #[vertex_format]
@@ -82,6 +83,7 @@ fn gfx_main(mut glfw: glfw::Glfw,
             events: Receiver<(f64, glfw::WindowEvent)>,
             dimension: i16) {
     let (mut wrap, device, mut factory) = gfx_window_glfw::init(window);
+    let mut thumbnail = Thumbnail::from_env();
     let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
 
     let vertex_data = [
@@ -149,6 +151,16 @@ fn gfx_main(mut glfw: glfw::Glfw,
         let pre_submit = precise_time_s() * 1000.;
         graphics.end_frame();
         let post_submit = precise_time_s() * 1000.;
+
+        {
+            let size = wrap.get_size();
+            let window = &mut wrap.window;
+            if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| window.get_proc_address(s),
+                                                                   size)) {
+                window.set_should_close(true);
+            }
+        }
+
         wrap.window.swap_buffers();
         graphics.cleanup();
         let swap = precise_time_s() * 1000.;
@@ -156,7 +168,7 @@ fn gfx_main(mut glfw: glfw::Glfw,
         println!("total time:\t\t{0:4.2}ms", swap - start);
         println!("\tcreate list:\t{0:4.2}ms", pre_submit - start);
         println!("\tsubmit:\t\t{0:4.2}ms", post_submit - pre_submit);
-        println!("\tgpu wait:\t{0:4.2}ms", swap - post_submit)
+        println!("\tgpu wait:\t{0:4.2}ms", swap - post_submit);
     }
 }
 
@@ -181,48 +193,6 @@ static FS_SRC: &'static str = "
 ";
 
 
-fn compile_shader(gl: &Gl, src: &str, ty: GLenum) -> GLuint { unsafe {
-    let shader = gl.CreateShader(ty);
-    // Attempt to compile the shader
-    let src = CString::new(src).unwrap();
-    gl.ShaderSource(shader, 1, &(src.as_bytes_with_nul().as_ptr() as *const i8), ptr::null());
-    gl.CompileShader(shader);
-
-    // Get the compile status
-    let mut status = gl::FALSE as GLint;
-    gl.GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
-
-    // Fail on error
-    if status != (gl::TRUE as GLint) {
-        let mut len: GLint = 0;
-        gl.GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-        let mut buf: Vec<u8> = repeat(0u8).take((len as isize).saturating_sub(1) as usize).collect();     // subtract 1 to skip the trailing null character
-        gl.GetShaderInfoLog(shader, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
-        panic!("{}", str::from_utf8(buf.as_slice()).ok().expect("ShaderInfoLog not valid utf8"));
-    }
-    shader
-}}
-
-fn link_program(gl: &Gl, vs: GLuint, fs: GLuint) -> GLuint { unsafe {
-    let program = gl.CreateProgram();
-    gl.AttachShader(program, vs);
-    gl.AttachShader(program, fs);
-    gl.LinkProgram(program);
-    // Get the link status
-    let mut status = gl::FALSE as GLint;
-    gl.GetProgramiv(program, gl::LINK_STATUS, &mut status);
-
-    // Fail on error
-    if status != (gl::TRUE as GLint) {
-        let mut len: GLint = 0;
-        gl.GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-        let mut buf: Vec<u8> = repeat(0u8).take((len as isize).saturating_sub(1) as usize).collect();     // subtract 1 to skip the trailing null character
-        gl.GetProgramInfoLog(program, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
-        panic!("{}", str::from_utf8(buf.as_slice()).ok().expect("ProgramInfoLog not valid utf8"));
-    }
-    program
-}}
-
 fn gl_main(mut glfw: glfw::Glfw,
            mut window: glfw::Window,
            _: Receiver<(f64, glfw::WindowEvent),>,
@@ -232,7 +202,7 @@ fn gl_main(mut glfw: glfw::Glfw,
     // Create GLSL shaders
     let vs = compile_shader(&gl, VS_SRC, gl::VERTEX_SHADER);
     let fs = compile_shader(&gl, FS_SRC, gl::FRAGMENT_SHADER);
-    let program = link_program(&gl, vs, fs);
+    let program = link_program(&gl, &[vs, fs], &[]);
 
     let mut vao = 0;
     let mut vbo = 0;
@@ -0,0 +1,319 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of picking objects with an ID buffer.
+//
+// Before each frame, every object is drawn in a flat color that encodes its
+// index plus one in its red, green and blue bytes, with black for nothing.
+// The pixel under the mouse is read back, decoded into the object it shows,
+// and the frame is then drawn over it with that object highlighted. Unlike
+// casting a ray against bounding shapes, this is exact to the pixel for any
+// geometry, at the cost of a draw of the scene.
+//
+// gfx-rs can't read render targets back yet, so the IDs go into the back
+// buffer of the window and the pixel is read with GL directly, as the
+// thumbnail capture does. Reading right after drawing makes the CPU wait for
+// the GPU to finish; the readback example shows how to avoid that.
+//
+// Move the mouse to highlight an object and click to select it. Press Space
+// to stop the objects, and I to show the ID buffer itself.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4, ToMatrix4};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+use gl::types::*;
+
+const GRID_SIZE: usize = 5;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 0 for nothing, 1 under the mouse, 2 selected
+    #[name = "u_Highlight"]
+    highlight: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct IdParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Id"]
+    id: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Highlight;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 light = normalize(vec3(0.3, -0.5, 0.8));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        vec3 color = u_Color * (0.25 + 0.75 * diffuse);
+        if (u_Highlight == 1.0) {
+            color = mix(color, vec3(1.0), 0.4);
+        } else if (u_Highlight == 2.0) {
+            color = mix(color, vec3(1.0, 0.9, 0.2), 0.6);
+        }
+        o_Color = vec4(color, 1.0);
+    }
+";
+
+static ID_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Id;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(u_Id, 1.0);
+    }
+";
+
+// The bytes of `index + 1`, as colors that convert back to them exactly
+fn encode_id(index: usize) -> [f32; 3] {
+    let id = index + 1;
+    [(id & 0xFF) as f32 / 255.0,
+     ((id >> 8) & 0xFF) as f32 / 255.0,
+     ((id >> 16) & 0xFF) as f32 / 255.0]
+}
+
+fn decode_id(pixel: [u8; 4]) -> Option<usize> {
+    let id = pixel[0] as usize | (pixel[1] as usize) << 8 | (pixel[2] as usize) << 16;
+    if id == 0 { None } else { Some(id - 1) }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Picking example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+    let gl = gl::Gl::load_with(|s| wrap.window.get_proc_address(s));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let hidpi = window::scale_factor(&wrap.window);
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let id_program = factory.link_program(VERTEX_SRC, ID_FRAGMENT_SRC).unwrap();
+    // a batch of each kind for every shape
+    let mut meshes = MeshCache::new();
+    let mut batches = Vec::new();
+    let mut id_batches = Vec::new();
+    for (mesh, slice) in vec![meshes.cube(&mut factory), meshes.sphere(&mut factory, 24, 48),
+                              meshes.torus(&mut factory, 48, 24, 0.7, 0.3)] {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: [0.0; 3],
+            highlight: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice.clone(), &state).unwrap());
+        let data = IdParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            id: [0.0; 3],
+            _dummy: std::marker::PhantomData,
+        };
+        id_batches.push(context.make_batch(&id_program, data, &mesh, slice, &state).unwrap());
+    }
+
+    // (shape, position, color) of each object
+    let objects: Vec<(usize, [f32; 3], [f32; 3])> = (0 .. GRID_SIZE * GRID_SIZE).map(|i| {
+        let (x, y) = (i % GRID_SIZE, i / GRID_SIZE);
+        let offset = 0.5 * (GRID_SIZE - 1) as f32;
+        let pos = [(x as f32 - offset) * 2.5, (y as f32 - offset) * 2.5, 0.0];
+        let color = [0.3 + 0.6 * x as f32 / GRID_SIZE as f32, 0.4,
+                     0.3 + 0.6 * y as f32 / GRID_SIZE as f32];
+        (i % 3, pos, color)
+    }).collect();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(0.0, -10.0, 9.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_z(),
+    );
+    let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.35, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+    let clear_id = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // the cursor in pixels from the bottom left, as GL counts them
+    let mut cursor: Option<(i32, i32)> = None;
+    let mut hovered = None;
+    let mut selected = None;
+    let mut show_ids = false;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, MouseButton, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::MouseMoved((x, y)) => {
+                    let (x, y) = ((x as f32 * hidpi) as i32, (y as f32 * hidpi) as i32);
+                    cursor = Some((x, h as i32 - 1 - y));
+                },
+                Event::MouseInput(ElementState::Pressed, MouseButton::Left) =>
+                    selected = hovered,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::I => show_ids = !show_ids,
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.5 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let models: Vec<_> = objects.iter().enumerate().map(|(i, &(_, pos, _))| {
+            let spin = Matrix3::from_angle_z(cgmath::rad(a + i as f32))
+                               .mul_m(&Matrix3::from_angle_x(cgmath::rad(0.7 * a)));
+            let mut model = spin.mul_s(0.8).to_matrix4();
+            model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+            model.into_fixed()
+        }).collect();
+
+        // The IDs, then the one under the cursor
+        renderer.clear(clear_id, gfx::COLOR | gfx::DEPTH, &wrap);
+        for (i, &(kind, _, _)) in objects.iter().enumerate() {
+            let batch = &mut id_batches[kind];
+            batch.params.view_proj = view_proj;
+            batch.params.model = models[i];
+            batch.params.id = encode_id(i);
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        hovered = match cursor {
+            Some((x, y)) if x >= 0 && y >= 0 && x < w as i32 && y < h as i32 => {
+                let mut pixel = [0u8; 4];
+                unsafe {
+                    gl.ReadBuffer(gl::BACK);
+                    gl.ReadPixels(x, y, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE,
+                                  pixel.as_mut_ptr() as *mut GLvoid);
+                }
+                decode_id(pixel)
+            },
+            _ => None,
+        };
+
+        // The frame itself, over the IDs
+        if !show_ids {
+            renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+            for (i, &(kind, _, color)) in objects.iter().enumerate() {
+                let batch = &mut batches[kind];
+                batch.params.view_proj = view_proj;
+                batch.params.model = models[i];
+                batch.params.color = color;
+                batch.params.highlight = if selected == Some(i) { 2.0 }
+                                         else if hovered == Some(i) { 1.0 }
+                                         else { 0.0 };
+                renderer.draw(&(&*batch, &context), &wrap).unwrap();
+            }
+            device.submit(renderer.as_buffer());
+            renderer.reset();
+        }
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let describe = |object: Option<usize>| match object {
+            Some(i) => format!("object {}", i),
+            None => "nothing".to_string(),
+        };
+        wrap.window.set_title(&format!("Picking example: hovering {}, selected {}",
+                                       describe(hovered), describe(selected)));
+    }
+}
@@ -0,0 +1,527 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a procedural planet with a level of detail.
+//
+// The planet is a cube whose points are pushed out onto a sphere, then
+// displaced by 3D Perlin noise sampled at their direction, so the surface has
+// no seams or pinched poles. Each face of the cube is a quadtree of patches
+// that are split as the camera comes closer and merged back as it leaves.
+// Patches are generated on the CPU when first needed, a few per frame, and
+// hang skirts from their edges to hide the cracks between levels.
+//
+// Fly with the mouse and the keys of `gfx_examples::camera::FlyCamera`, whose
+// speed follows the altitude. Press L to tint patches by their level and
+// Space to freeze the level of detail.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point, Point3, Vector, Vector3, EuclideanVector};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const RADIUS: f32 = 100.0;
+// Height of the highest mountains, relative to the radius
+const AMPLITUDE: f32 = 0.04;
+const MAX_DEPTH: u8 = 8;
+// Quads along the edge of a patch
+const GRID: usize = 16;
+// Split patches closer than this many times their size
+const SPLIT_DISTANCE: f32 = 2.5;
+const MAX_BUILDS_PER_FRAME: usize = 8;
+// Frames an unused patch stays cached
+const CACHE_FRAMES: u64 = 600;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+    // elevation before the oceans are flattened
+    #[name = "a_Height"]
+    height: f32,
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_LightDir"]
+    light_dir: [f32; 3],
+    #[name = "u_Tint"]
+    tint: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in float a_Height;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+    out float v_Height;
+
+    void main() {
+        v_Pos = a_Pos;
+        v_Normal = a_Normal;
+        v_Height = a_Height;
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_CamPos;
+    uniform vec3 u_LightDir;
+    uniform vec3 u_Tint;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    in float v_Height;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 up = normalize(v_Pos);
+        float slope = 1.0 - dot(n, up);
+        vec3 color;
+        float shininess = 0.0;
+        if (v_Height < 0.0) {
+            float depth = clamp(1.0 + 6.0 * v_Height, 0.0, 1.0);
+            color = mix(vec3(0.02, 0.08, 0.25), vec3(0.1, 0.35, 0.5), depth);
+            shininess = 1.0;
+        } else if (v_Height < 0.03) {
+            color = vec3(0.76, 0.7, 0.5);
+        } else if (v_Height < 0.3) {
+            color = mix(vec3(0.2, 0.45, 0.15), vec3(0.3, 0.35, 0.15), v_Height / 0.3);
+        } else if (v_Height < 0.5) {
+            color = vec3(0.4, 0.35, 0.3);
+        } else {
+            color = vec3(0.95);
+        }
+        // steep land is bare rock
+        if (v_Height >= 0.03) {
+            color = mix(color, vec3(0.35, 0.3, 0.28), smoothstep(0.15, 0.3, slope));
+        }
+
+        vec3 l = normalize(u_LightDir);
+        float diffuse = max(0.0, dot(n, l));
+        vec3 h = normalize(l + normalize(u_CamPos - v_Pos));
+        float spec = shininess * pow(max(0.0, dot(n, h)), 64.0);
+        o_Color = vec4(u_Tint * (color * (0.05 + 0.95 * diffuse) + 0.5 * spec), 1.0);
+    }
+";
+
+// Ken Perlin's improved noise
+struct Noise {
+    perm: Vec<usize>,
+}
+
+impl Noise {
+    fn new<R: Rng>(rng: &mut R) -> Noise {
+        let mut p: Vec<usize> = (0 .. 256).collect();
+        rng.shuffle(&mut p);
+        let perm = p.iter().chain(p.iter()).cloned().collect();
+        Noise { perm: perm }
+    }
+
+    fn get(&self, x: f32, y: f32, z: f32) -> f32 {
+        fn fade(t: f32) -> f32 {
+            t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+        }
+        fn lerp(t: f32, a: f32, b: f32) -> f32 {
+            a + t * (b - a)
+        }
+        fn grad(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+            let h = hash & 15;
+            let u = if h < 8 { x } else { y };
+            let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+            (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+        }
+
+        let (fx, fy, fz) = (x.floor(), y.floor(), z.floor());
+        let xi = (fx as i32 & 255) as usize;
+        let yi = (fy as i32 & 255) as usize;
+        let zi = (fz as i32 & 255) as usize;
+        let (x, y, z) = (x - fx, y - fy, z - fz);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let p = &self.perm;
+        let a = p[xi] + yi;
+        let (aa, ab) = (p[a] + zi, p[a + 1] + zi);
+        let b = p[xi + 1] + yi;
+        let (ba, bb) = (p[b] + zi, p[b + 1] + zi);
+
+        lerp(w, lerp(v, lerp(u, grad(p[aa], x, y, z),
+                                grad(p[ba], x - 1.0, y, z)),
+                        lerp(u, grad(p[ab], x, y - 1.0, z),
+                                grad(p[bb], x - 1.0, y - 1.0, z))),
+                lerp(v, lerp(u, grad(p[aa + 1], x, y, z - 1.0),
+                                grad(p[ba + 1], x - 1.0, y, z - 1.0)),
+                        lerp(u, grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                                grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0))))
+    }
+
+    // Fractal sum of octaves, each twice the frequency and half the weight
+    fn fbm(&self, p: Vector3<f32>, octaves: u32) -> f32 {
+        let (mut sum, mut freq, mut weight) = (0.0, 1.0, 1.0);
+        for _ in 0 .. octaves {
+            sum += weight * self.get(p.x * freq, p.y * freq, p.z * freq);
+            freq *= 2.0;
+            weight *= 0.5;
+        }
+        sum
+    }
+}
+
+struct Planet {
+    noise: Noise,
+}
+
+impl Planet {
+    // Elevation at a direction from the center, roughly in -1 .. 1
+    fn elevation(&self, dir: Vector3<f32>) -> f32 {
+        1.6 * self.noise.fbm(dir.mul_s(1.5), 12) - 0.05
+    }
+
+    // Distance from the center to the surface, with flat oceans
+    fn radius(&self, elevation: f32) -> f32 {
+        RADIUS * (1.0 + AMPLITUDE * elevation.max(0.0))
+    }
+}
+
+// A patch of the quadtree of a cube face
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct Node {
+    face: u8,
+    depth: u8,
+    x: u32,
+    y: u32,
+}
+
+impl Node {
+    fn children(&self) -> [Node; 4] {
+        let child = |dx, dy| Node {
+            face: self.face,
+            depth: self.depth + 1,
+            x: 2 * self.x + dx,
+            y: 2 * self.y + dy,
+        };
+        [child(0, 0), child(1, 0), child(0, 1), child(1, 1)]
+    }
+
+    // Direction through a point of the patch, at grid coordinates i and j,
+    // which may go past the edges
+    fn direction(&self, i: f32, j: f32) -> Vector3<f32> {
+        // normal, u, v of each face, with u x v = normal
+        let axes = [
+            ([ 1.0,  0.0,  0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+            ([-1.0,  0.0,  0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            ([ 0.0,  1.0,  0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+            ([ 0.0, -1.0,  0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            ([ 0.0,  0.0,  1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([ 0.0,  0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+        ];
+        let (n, u, v) = axes[self.face as usize];
+        let cells = (1u32 << self.depth) as f32;
+        let s = 2.0 * (self.x as f32 + i / GRID as f32) / cells - 1.0;
+        let t = 2.0 * (self.y as f32 + j / GRID as f32) / cells - 1.0;
+        Vector3::new(n[0] + s * u[0] + t * v[0],
+                     n[1] + s * u[1] + t * v[1],
+                     n[2] + s * u[2] + t * v[2]).normalize()
+    }
+
+    // Rough size of the patch on the surface
+    fn size(&self) -> f32 {
+        0.5 * PI * RADIUS / (1u32 << self.depth) as f32
+    }
+
+    fn center(&self) -> Point3<f32> {
+        let h = 0.5 * GRID as f32;
+        Point3::from_vec(&self.direction(h, h).mul_s(RADIUS))
+    }
+}
+
+fn build_patch(planet: &Planet, node: &Node) -> (Vec<Vertex>, Vec<u16>) {
+    // positions with an extra ring around the patch, for the normals
+    let n = GRID + 3;
+    let mut positions = Vec::with_capacity(n * n);
+    let mut heights = Vec::with_capacity(n * n);
+    for j in 0 .. n {
+        for i in 0 .. n {
+            let dir = node.direction(i as f32 - 1.0, j as f32 - 1.0);
+            let e = planet.elevation(dir);
+            positions.push(dir.mul_s(planet.radius(e)));
+            heights.push(e);
+        }
+    }
+
+    let mut vertices = Vec::with_capacity((GRID + 1) * (GRID + 5));
+    for j in 1 .. GRID + 2 {
+        for i in 1 .. GRID + 2 {
+            let at = |i: usize, j: usize| positions[j * n + i];
+            let du = at(i + 1, j).sub_v(&at(i - 1, j));
+            let dv = at(i, j + 1).sub_v(&at(i, j - 1));
+            vertices.push(Vertex {
+                pos: at(i, j).into_fixed(),
+                normal: du.cross(&dv).normalize().into_fixed(),
+                height: heights[j * n + i],
+            });
+        }
+    }
+
+    let index = |i: usize, j: usize| (j * (GRID + 1) + i) as u16;
+    let mut indices = Vec::new();
+    for j in 0 .. GRID {
+        for i in 0 .. GRID {
+            let (a, b, c, d) = (index(i, j), index(i + 1, j), index(i + 1, j + 1), index(i, j + 1));
+            indices.extend([a, b, c, a, c, d].iter().cloned());
+        }
+    }
+
+    // The skirt hangs below the edge, which is walked counter-clockwise
+    let mut edge = Vec::with_capacity(4 * GRID);
+    for i in 0 .. GRID { edge.push(index(i, 0)); }
+    for j in 0 .. GRID { edge.push(index(GRID, j)); }
+    for i in 0 .. GRID { edge.push(index(GRID - i, GRID)); }
+    for j in 0 .. GRID { edge.push(index(0, GRID - j)); }
+    let depth = 0.1 * node.size();
+    let first = vertices.len() as u16;
+    for &e in edge.iter() {
+        let mut v = vertices[e as usize];
+        let pos = Vector3::new(v.pos[0], v.pos[1], v.pos[2]);
+        v.pos = pos.sub_v(&pos.normalize().mul_s(depth)).into_fixed();
+        vertices.push(v);
+    }
+    for k in 0 .. edge.len() {
+        let next = (k + 1) % edge.len();
+        let (a, b) = (edge[k], edge[next]);
+        let (sa, sb) = (first + k as u16, first + next as u16);
+        indices.extend([a, sa, sb, a, sb, b].iter().cloned());
+    }
+
+    (vertices, indices)
+}
+
+struct Patch<R: gfx::Resources> {
+    batch: gfx::batch::OwnedBatch<Params<R>>,
+    last_used: u64,
+}
+
+fn create_patch<R, F>(factory: &mut F, planet: &Planet, node: &Node,
+                      program: &gfx::ProgramHandle<R>, state: &gfx::DrawState)
+                      -> Patch<R> where R: gfx::Resources, F: gfx::Factory<R> {
+    let (vertex_data, index_data) = build_patch(planet, node);
+    let mesh = factory.create_mesh(&vertex_data);
+    let slice = factory.create_buffer_index::<u16>(&index_data)
+                       .to_slice(gfx::PrimitiveType::TriangleList);
+    let data = Params {
+        view_proj: Matrix4::identity().into_fixed(),
+        cam_pos: [0.0; 3],
+        light_dir: [0.6, -0.5, 0.4],
+        tint: [1.0; 3],
+        _dummy: std::marker::PhantomData,
+    };
+    let mut batch = gfx::batch::OwnedBatch::new(mesh, program.clone(), data).unwrap();
+    batch.slice = slice;
+    batch.state = state.clone();
+    Patch { batch: batch, last_used: 0 }
+}
+
+// Walks down the quadtree, splitting nodes close to the eye when all their
+// children are built. The nodes to draw go to `selected`, and the children
+// still missing to `missing`.
+fn select<R: gfx::Resources>(cache: &mut HashMap<Node, Patch<R>>, node: Node,
+                             eye: &Point3<f32>, frame: u64,
+                             selected: &mut Vec<Node>, missing: &mut Vec<Node>) {
+    if let Some(patch) = cache.get_mut(&node) {
+        patch.last_used = frame;
+    }
+    let close = node.center().sub_p(eye).length() < SPLIT_DISTANCE * node.size();
+    if node.depth < MAX_DEPTH && close {
+        let children = node.children();
+        let mut ready = true;
+        for child in children.iter() {
+            if !cache.contains_key(child) {
+                missing.push(*child);
+                ready = false;
+            }
+        }
+        if ready {
+            for child in children.iter() {
+                select(cache, *child, eye, frame, selected, missing);
+            }
+            return;
+        }
+    }
+    selected.push(node);
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Planet example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let planet = Planet { noise: Noise::new(&mut rng) };
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let roots: Vec<Node> = (0 .. 6).map(|face| Node { face: face, depth: 0, x: 0, y: 0 })
+                                  .collect();
+    let mut cache = HashMap::new();
+    for root in roots.iter() {
+        let patch = create_patch(&mut factory, &planet, root, &program, &state);
+        cache.insert(*root, patch);
+    }
+
+    let aspect = w as f32 / h as f32;
+    let mut camera = FlyCamera::new(Point3::new(0.0, -3.0 * RADIUS, 0.0), 0.5 * PI, 0.0, 1.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.02, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut show_levels = false;
+    let mut frozen = false;
+    let mut altitude = 3.0 * RADIUS;
+    let mut frame = 0u64;
+    let mut eye = camera.position;
+    let mut selected = Vec::new();
+    let mut missing = Vec::new();
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::L)) =>
+                    show_levels = !show_levels,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    frozen = !frozen,
+                _ => {},
+            }
+        }
+
+        // Move slower closer to the ground, and never go under it
+        clock.update();
+        while clock.tick() {
+            camera.speed = 0.5 * altitude.max(0.2);
+            camera.update(clock.step());
+            let dir = camera.position.to_vec().normalize();
+            let ground = planet.radius(planet.elevation(dir));
+            if camera.position.to_vec().length() < ground + 0.05 {
+                camera.position = Point3::from_vec(&dir.mul_s(ground + 0.05));
+            }
+            altitude = camera.position.to_vec().length() - ground;
+        }
+
+        frame += 1;
+        if !frozen {
+            eye = camera.position;
+        }
+        selected.clear();
+        missing.clear();
+        for root in roots.iter() {
+            select(&mut cache, *root, &eye, frame, &mut selected, &mut missing);
+        }
+        // Build the coarsest missing patches first, a few at a time
+        missing.sort_by(|a, b| a.depth.cmp(&b.depth));
+        for node in missing.iter().take(MAX_BUILDS_PER_FRAME) {
+            let patch = create_patch(&mut factory, &planet, node, &program, &state);
+            cache.insert(*node, patch);
+        }
+        let stale: Vec<Node> = cache.iter().filter(|&(node, patch)| {
+            node.depth > 0 && patch.last_used + CACHE_FRAMES < frame
+        }).map(|(node, _)| *node).collect();
+        for node in stale.iter() {
+            cache.remove(node);
+        }
+
+        // Keep the depth precision around the altitude
+        let dist = camera.position.to_vec().length();
+        let near = (0.1 * altitude).max(0.01).min(10.0);
+        let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, near, dist + 2.0 * RADIUS);
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for node in selected.iter() {
+            let patch = cache.get_mut(node).unwrap();
+            let params = &mut patch.batch.params;
+            params.view_proj = view_proj;
+            params.cam_pos = camera.position.into_fixed();
+            params.tint = if show_levels {
+                let k = node.depth as f32 / MAX_DEPTH as f32;
+                [1.0 - 0.6 * k, 0.4 + 0.6 * (2.0 * k - 1.0).abs(), 0.4 + 0.6 * k]
+            } else {
+                [1.0; 3]
+            };
+            renderer.draw(&patch.batch, &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Planet example: {} patches drawn, {} cached, altitude {:.2}",
+            selected.len(), cache.len(), altitude));
+    }
+}
@@ -0,0 +1,386 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of omnidirectional shadows for a point light.
+//
+// Rendering happens in two passes:
+// First,  the scene is rendered six times, once for each face of a cubemap
+//         centered on the light. Every face stores the distance from the
+//         light to the closest surface.
+// Second, the scene is rendered from the camera. Each fragment looks up the
+//         cubemap in the direction from the light to itself, and is in
+//         shadow if it is further away than the stored distance.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+const CUBE_SIZE: gfx::tex::Size = 512;
+// Distances are stored divided by this, so they fit into [0, 1]
+const FAR: f32 = 30.0;
+
+#[shader_param]
+struct DistanceParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_LightPos"]
+    light_pos: [f32; 3],
+    #[name = "u_Far"]
+    far: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_LightPos"]
+    light_pos: [f32; 3],
+    #[name = "u_Far"]
+    far: f32,
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "t_Distance"]
+    distance: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct LampParams<R: gfx::Resources> {
+    #[name = "u_Transform"]
+    transform: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static DISTANCE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    out vec3 v_World;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_World = world.xyz;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static DISTANCE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_LightPos;
+    uniform float u_Far;
+    in vec3 v_World;
+    out vec4 o_Distance;
+
+    void main() {
+        o_Distance = vec4(length(v_World - u_LightPos) / u_Far);
+    }
+";
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_World = world.xyz;
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_LightPos;
+    uniform float u_Far;
+    uniform vec3 u_Color;
+    uniform samplerCube t_Distance;
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 from_light = v_World - u_LightPos;
+        float dist = length(from_light);
+        float closest = texture(t_Distance, from_light).r * u_Far;
+        float lit = dist - 0.05 > closest ? 0.0 : 1.0;
+
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(0.0, dot(n, -from_light / dist));
+        float falloff = 1.0 / (1.0 + 0.02 * dist * dist);
+
+        o_Color = vec4(u_Color * (0.1 + diffuse * falloff * lit), 1.0);
+    }
+";
+
+static LAMP_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Transform;
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = u_Transform * vec4(a_Pos, 1.0);
+    }
+";
+
+static LAMP_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(1.0, 1.0, 0.8, 1.0);
+    }
+";
+
+fn model_matrix(pos: [f32; 3], scale: f32) -> Matrix4<f32> {
+    let mut model = Matrix3::identity().mul_s(scale).to_matrix4();
+    model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+    model
+}
+
+// View direction and up vector for each cubemap face, in the order GL
+// numbers them: +X, -X, +Y, -Y, +Z, -Z
+fn face_views(eye: &Point3<f32>) -> Vec<AffineMatrix3<f32>> {
+    let faces = [
+        (Vector3::new( 1.0,  0.0,  0.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new(-1.0,  0.0,  0.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new( 0.0,  1.0,  0.0), Vector3::new(0.0,  0.0,  1.0)),
+        (Vector3::new( 0.0, -1.0,  0.0), Vector3::new(0.0,  0.0, -1.0)),
+        (Vector3::new( 0.0,  0.0,  1.0), Vector3::new(0.0, -1.0,  0.0)),
+        (Vector3::new( 0.0,  0.0, -1.0), Vector3::new(0.0, -1.0,  0.0)),
+    ];
+    faces.iter().map(|&(dir, up)| {
+        Transform::look_at(eye, &eye.add_v(&dir), &up)
+    }).collect()
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Point light shadow example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let distance_tex = factory.create_texture(gfx::tex::TextureInfo {
+        width: CUBE_SIZE,
+        height: CUBE_SIZE,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::TextureCube,
+        format: gfx::tex::Format::Float(gfx::tex::Components::R, gfx::attrib::FloatSize::F32),
+    }).unwrap();
+    // All six faces share one depth buffer, cleared before each of them
+    let depth_tex = factory.create_texture(gfx::tex::TextureInfo {
+        width: CUBE_SIZE,
+        height: CUBE_SIZE,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let face_frames: Vec<_> = (0 .. 6).map(|face| gfx::Frame {
+        colors: vec![Plane::Texture(distance_tex.clone(), 0, Some(face))],
+        depth: Some(Plane::Texture(depth_tex.clone(), 0, None)),
+        .. gfx::Frame::empty(CUBE_SIZE, CUBE_SIZE)
+    }).collect();
+
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let distance_program = factory.link_program(DISTANCE_VERTEX_SRC, DISTANCE_FRAGMENT_SRC)
+                                  .unwrap();
+    let scene_program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC)
+                               .unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    // The room is a cube seen from the inside, so flip its normals and winding
+    let mut meshes = MeshCache::new();
+    let room = meshes.get(&mut factory, "room", || {
+        let (mut vertex_data, index_data) = shape::cube();
+        for v in vertex_data.iter_mut() {
+            v.normal = [-v.normal[0], -v.normal[1], -v.normal[2]];
+        }
+        let mut index_data: Vec<u32> = index_data.into_iter().map(|i| i as u32).collect();
+        for tri in index_data.chunks_mut(3) {
+            tri.swap(1, 2);
+        }
+        (vertex_data, index_data)
+    });
+    let shapes = vec![room, meshes.cube(&mut factory), meshes.sphere(&mut factory, 16, 32)];
+
+    // mesh index, position, scale and color of everything in the scene
+    let objects = [
+        (0, [ 0.0,  0.0, 6.0],  6.0, [0.8, 0.8, 0.8]),
+        (1, [-4.0, -3.0, 1.0],  1.0, [0.8, 0.3, 0.2]),
+        (1, [ 3.0, -4.0, 3.0],  0.8, [0.2, 0.6, 0.3]),
+        (1, [ 4.0,  4.0, 2.0],  2.0, [0.3, 0.4, 0.8]),
+        (2, [-3.0,  3.0, 4.0],  1.5, [0.9, 0.8, 0.3]),
+        (1, [ 0.0,  0.0, 7.0],  0.7, [0.7, 0.3, 0.7]),
+    ];
+
+    let mut batches: Vec<_> = objects.iter().map(|&(mesh_id, pos, scale, color)| {
+        let (ref mesh, ref slice) = shapes[mesh_id];
+        let model = model_matrix(pos, scale).into_fixed();
+
+        let distance_data = DistanceParams {
+            model: model,
+            view_proj: Matrix4::identity().into_fixed(),
+            light_pos: [0.0, 0.0, 0.0],
+            far: FAR,
+            _dummy: std::marker::PhantomData,
+        };
+        let scene_data = SceneParams {
+            model: model,
+            view_proj: Matrix4::identity().into_fixed(),
+            light_pos: [0.0, 0.0, 0.0],
+            far: FAR,
+            color: color,
+            distance: (distance_tex.clone(), Some(sampler.clone())),
+        };
+
+        let distance = context.make_batch(&distance_program, distance_data,
+                                          mesh, slice.clone(), &state).unwrap();
+        let scene = context.make_batch(&scene_program, scene_data,
+                                       mesh, slice.clone(), &state).unwrap();
+        (distance, scene)
+    }).collect();
+
+    let mut lamp = {
+        let (ref mesh, ref slice) = shapes[2];
+        let program = factory.link_program(LAMP_VERTEX_SRC, LAMP_FRAGMENT_SRC)
+                             .unwrap();
+        let data = LampParams {
+            transform: Matrix4::identity().into_fixed(),
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, mesh, slice.clone(), &state)
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 100.0);
+    let face_proj = cgmath::perspective(cgmath::deg(90.0f32), 1.0, 0.1, FAR);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(-5.0, -5.5, 8.0),
+        &Point3::new(0.0, 0.0, 3.0),
+        &Vector3::unit_z(),
+    );
+    let view_proj = proj.mul_m(&view.mat);
+
+    let clear_data = gfx::ClearData {
+        color: [1.0, 1.0, 1.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            time = clock.time() as f32;
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // The light wanders around the middle of the room
+        let light_pos = Point3::new(5.0 * (0.5 * t).cos(), 5.0 * (0.7 * t).sin(),
+                                    4.0 + 2.0 * (0.3 * t).sin());
+
+        // Render the distances for each face of the cubemap
+        for (frame, face_view) in face_frames.iter().zip(face_views(&light_pos).iter()) {
+            renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, frame);
+            for &mut (ref mut distance, _) in batches.iter_mut() {
+                distance.params.view_proj = face_proj.mul_m(&face_view.mat).into_fixed();
+                distance.params.light_pos = light_pos.into_fixed();
+                renderer.draw(&(&*distance, &context), frame).unwrap();
+            }
+        }
+
+        // Render the lit scene from the camera
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for &mut (_, ref mut scene) in batches.iter_mut() {
+            scene.params.view_proj = view_proj.into_fixed();
+            scene.params.light_pos = light_pos.into_fixed();
+            renderer.draw(&(&*scene, &context), &wrap).unwrap();
+        }
+        lamp.params.transform = view_proj.mul_m(
+            &model_matrix(light_pos.into_fixed(), 0.2)).into_fixed();
+        renderer.draw(&(&lamp, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
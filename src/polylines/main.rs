@@ -0,0 +1,309 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of wide, anti-aliased lines with joins and caps.
+//
+// Line widths other than one pixel are not reliably supported by GL, and
+// core profiles may not draw wide lines at all, so every segment of a
+// polyline is drawn as a quad of two triangles instead. All six corners of
+// the quad carry both ends of the segment. The vertex shader projects the
+// ends to pixels, and moves each corner away from them by half the width of
+// the line, plus a pixel for the anti-aliasing, along the segment and
+// across it. The width stays the same however far away the line is.
+//
+// The fragment shader knows where it is relative to the segment in pixels,
+// and computes the distance to the outline of the line, which it turns into
+// coverage for a smooth edge. Along the segment, that outline is two
+// straight sides. Past its ends, it closes around a half disc, which makes a
+// round join where the next segment starts, or a cap at the ends of the
+// polyline, which can also be square or flat.
+//
+// Drag with the left mouse button to turn around the lines, and use the
+// mouse wheel to zoom. Press Up and Down to change the widths, C to change
+// the caps, and Q to show the quads.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{window, Thumbnail};
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Start"]
+    start: [f32; 3],
+    #[name = "a_End"]
+    end: [f32; 3],
+    // along the segment from 0 to 1, and across it from -1 to 1
+    #[name = "a_Corner"]
+    corner: [f32; 2],
+    // 1 where the start or the end of the segment is an end of the polyline
+    #[name = "a_Ends"]
+    ends: [f32; 2],
+    #[name = "a_Color"]
+    color: [f32; 3],
+    // in pixels
+    #[name = "a_Width"]
+    width: f32,
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Viewport"]
+    viewport: [f32; 2],
+    #[name = "u_WidthScale"]
+    width_scale: f32,
+    // index into `CAPS`
+    #[name = "u_Cap"]
+    cap: f32,
+    #[name = "u_ShowQuads"]
+    show_quads: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec2 u_Viewport;
+    uniform float u_WidthScale;
+    in vec3 a_Start;
+    in vec3 a_End;
+    in vec2 a_Corner;
+    in vec2 a_Ends;
+    in vec3 a_Color;
+    in float a_Width;
+    // where the corner is from the start of the segment, in pixels along and
+    // across it, which has to be interpolated linearly on the screen
+    noperspective out vec2 v_Local;
+    flat out float v_Length;
+    flat out float v_HalfWidth;
+    flat out vec2 v_Ends;
+    out vec3 v_Color;
+
+    void main() {
+        vec4 start = u_ViewProj * vec4(a_Start, 1.0);
+        vec4 end = u_ViewProj * vec4(a_End, 1.0);
+        vec2 p0 = start.xy / start.w * 0.5 * u_Viewport;
+        vec2 p1 = end.xy / end.w * 0.5 * u_Viewport;
+        float len = length(p1 - p0);
+        vec2 dir = len > 1e-4 ? (p1 - p0) / len : vec2(1.0, 0.0);
+        vec2 normal = vec2(-dir.y, dir.x);
+
+        float half_width = 0.5 * a_Width * u_WidthScale;
+        // the outline, and a pixel more for the smooth edge
+        float reach = half_width + 1.0;
+        float along = mix(-reach, len + reach, a_Corner.x);
+        float across = a_Corner.y * reach;
+        vec4 clip = mix(start, end, a_Corner.x);
+        vec2 pixel = p0 + dir * along + normal * across;
+        gl_Position = vec4(pixel / (0.5 * u_Viewport) * clip.w, clip.z, clip.w);
+
+        v_Local = vec2(along, across);
+        v_Length = len;
+        v_HalfWidth = half_width;
+        v_Ends = a_Ends;
+        v_Color = a_Color;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Cap;
+    uniform float u_ShowQuads;
+    noperspective in vec2 v_Local;
+    flat in float v_Length;
+    flat in float v_HalfWidth;
+    flat in vec2 v_Ends;
+    in vec3 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        float along = v_Local.x;
+        float across = abs(v_Local.y);
+        // how far past the start or the end, and whether that is an end of
+        // the polyline
+        float beyond = max(-along, along - v_Length);
+        bool polyline_end = along < 0.0 ? v_Ends.x > 0.5 : along > v_Length && v_Ends.y > 0.5;
+
+        // the distance to the outline, negative inside
+        float d;
+        if (!polyline_end || u_Cap < 0.5) {
+            d = length(vec2(max(beyond, 0.0), across)) - v_HalfWidth;
+        } else if (u_Cap < 1.5) {
+            d = max(beyond, across) - v_HalfWidth;
+        } else {
+            d = max(beyond, across - v_HalfWidth);
+        }
+        float coverage = clamp(0.5 - d, 0.0, 1.0);
+        float quad = u_ShowQuads * 0.25;
+        o_Color = vec4(v_Color, max(coverage, quad));
+    }
+";
+
+static CAPS: [&'static str; 3] = ["round", "square", "flat"];
+
+// Adds a quad for every segment of a polyline through `points`
+fn push_polyline(vertices: &mut Vec<Vertex>, points: &[[f32; 3]], color: [f32; 3],
+                 width: f32) {
+    let last = points.len() - 1;
+    for i in 0 .. last {
+        let ends = [(i == 0) as i32 as f32, (i + 1 == last) as i32 as f32];
+        for &corner in [[0.0, -1.0], [1.0, -1.0], [1.0, 1.0],
+                        [0.0, -1.0], [1.0, 1.0], [0.0, 1.0]].iter() {
+            vertices.push(Vertex {
+                start: points[i],
+                end: points[i + 1],
+                corner: corner,
+                ends: ends,
+                color: color,
+                width: width,
+            });
+        }
+    }
+}
+
+// A helix, a wave, a star with sharp corners, a zigzag and a Lissajous
+// curve, from thin to thick
+fn create_lines() -> Vec<Vertex> {
+    let mut vertices = Vec::new();
+    let helix: Vec<_> = (0 .. 241).map(|i| {
+        let t = i as f32 / 240.0;
+        let a = 8.0 * PI * t;
+        [1.5 * a.cos(), 1.5 * a.sin(), 4.0 * t - 2.0]
+    }).collect();
+    push_polyline(&mut vertices, &helix, [0.95, 0.5, 0.2], 3.0);
+    let wave: Vec<_> = (0 .. 161).map(|i| {
+        let x = i as f32 / 160.0 * 8.0 - 4.0;
+        [x, -3.0, 0.5 * (2.0 * x).sin()]
+    }).collect();
+    push_polyline(&mut vertices, &wave, [0.3, 0.8, 0.4], 6.0);
+    let star: Vec<_> = (0 .. 11).map(|i| {
+        let a = i as f32 * 4.0 * PI / 5.0 + 0.5 * PI;
+        [3.5 + 1.2 * a.cos(), 0.0, 1.2 * a.sin()]
+    }).collect();
+    push_polyline(&mut vertices, &star, [0.9, 0.85, 0.2], 10.0);
+    let zigzag: Vec<_> = (0 .. 9).map(|i| {
+        [-4.0 + 0.2 * (i % 2) as f32, 0.8 * i as f32 - 3.2, 0.0]
+    }).collect();
+    push_polyline(&mut vertices, &zigzag, [0.3, 0.6, 1.0], 16.0);
+    let lissajous: Vec<_> = (0 .. 301).map(|i| {
+        let t = i as f32 / 300.0 * 2.0 * PI;
+        [2.0 * (3.0 * t).sin(), 3.0 + 0.8 * (2.0 * t).sin(), 1.2 * (5.0 * t).cos()]
+    }).collect();
+    push_polyline(&mut vertices, &lissajous, [0.85, 0.35, 0.85], 24.0);
+    vertices
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Polylines example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    // later segments are blended over the earlier ones, without a depth test
+    let state = gfx::DrawState::new().blend(gfx::BlendPreset::Alpha);
+    let mut batch = {
+        let mesh = factory.create_mesh(&create_lines());
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            viewport: [w as f32, h as f32],
+            width_scale: 1.0,
+            cap: 0.0,
+            show_quads: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 12.0, -0.5 * PI, 0.5);
+    let mut width_scale = 1.0f32;
+    let mut cap = 0;
+    let mut show_quads = false;
+
+    let clear_data = gfx::ClearData {
+        color: [0.08, 0.08, 0.1, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => width_scale = (width_scale * 1.25).min(8.0),
+                    VirtualKeyCode::Down => width_scale = (width_scale / 1.25).max(0.125),
+                    VirtualKeyCode::C => cap = (cap + 1) % CAPS.len(),
+                    VirtualKeyCode::Q => show_quads = !show_quads,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        batch.params.view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        batch.params.width_scale = width_scale;
+        batch.params.cap = cap as f32;
+        batch.params.show_quads = if show_quads { 1.0 } else { 0.0 };
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Polylines example: widths x{:.2}, {} caps",
+                                       width_scale, CAPS[cap]));
+    }
+}
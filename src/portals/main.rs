@@ -0,0 +1,414 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of portals rendered to textures.
+//
+// Two portals are linked: looking into one shows the scene beyond the other.
+// Before the scene is drawn, the view through each portal facing the camera
+// is rendered into a texture, from a camera moved by the offset between the
+// portals. Its near plane is tilted onto the exit portal, so that nothing
+// behind the exit gets in the way. The portals then show these textures at
+// the screen position of their pixels. The portals seen through a portal are
+// rendered the same way first, down to a given depth, where they are closed.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press Up and
+// Down to change the depth of the recursion, and Space to stop the objects
+// from turning.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector, Vector4, ToMatrix4};
+use cgmath::EuclideanVector;
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::{oblique, OrbitCamera};
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// past the first level, only the portal that isn't the exit can recurse, so
+// this takes two render targets per level
+const MAX_DEPTH: usize = 4;
+
+static COLORS: [[f32; 3]; 5] = [
+    [0.8, 0.25, 0.2],
+    [0.25, 0.6, 0.3],
+    [0.9, 0.7, 0.3],
+    [0.6, 0.3, 0.7],
+    [0.7, 0.7, 0.7],
+];
+
+static PORTAL_COLORS: [[f32; 3]; 2] = [[1.0, 0.5, 0.1], [0.1, 0.5, 1.0]];
+
+static CLEAR_DATA: gfx::ClearData = gfx::ClearData {
+    color: [0.35, 0.45, 0.6, 1.0],
+    depth: 1.0,
+    stencil: 0,
+};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 1 for the checkered floor
+    #[name = "u_Checker"]
+    checker: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct PortalParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "u_Resolution"]
+    resolution: [f32; 2],
+    // 0 for a closed portal, at the end of the recursion
+    #[name = "u_Open"]
+    open: f32,
+    #[name = "t_View"]
+    view: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec2 a_TexCoord;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+    out vec2 v_TexCoord;
+
+    void main() {
+        vec4 pos = u_Model * vec4(a_Pos, 1.0);
+        v_Pos = pos.xyz;
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_TexCoord = a_TexCoord;
+        gl_Position = u_ViewProj * pos;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Checker;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 light = normalize(vec3(0.3, -0.5, 0.8));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        float checker = mod(floor(v_Pos.x) + floor(v_Pos.y), 2.0);
+        vec3 color = u_Color * mix(1.0, 0.6 + 0.4 * checker, u_Checker);
+        o_Color = vec4(color * (0.25 + 0.75 * diffuse), 1.0);
+    }
+";
+
+static PORTAL_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform vec2 u_Resolution;
+    uniform float u_Open;
+    uniform sampler2D t_View;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        if (!gl_FrontFacing) {
+            o_Color = vec4(0.2, 0.2, 0.2, 1.0);
+            return;
+        }
+        // a rim, as thick on all sides of the 2 by 3 rectangle
+        vec2 edge = min(v_TexCoord, 1.0 - v_TexCoord) * vec2(2.0, 3.0);
+        if (min(edge.x, edge.y) < 0.08) {
+            o_Color = vec4(u_Color, 1.0);
+            return;
+        }
+        // the camera behind the portal looks through the same pixels
+        vec3 view = texture(t_View, gl_FragCoord.xy / u_Resolution).rgb;
+        o_Color = vec4(mix(0.3 * u_Color, view, u_Open), 1.0);
+    }
+";
+
+// Places an object, rotated and scaled, at a position
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+// The plane of a portal, with its normal out of the front
+fn portal_plane(placement: &Matrix4<f32>) -> Vector4<f32> {
+    let normal = placement.z.truncate().normalize();
+    let pos = placement.w.truncate();
+    Vector4::new(normal.x, normal.y, normal.z, -normal.dot(&pos))
+}
+
+struct Scene<R: gfx::Resources> {
+    context: gfx::batch::Context<R>,
+    objects: Vec<gfx::batch::RefBatch<Params<R>>>,
+    models: Vec<Matrix4<f32>>,
+    portal: gfx::batch::RefBatch<PortalParams<R>>,
+    placements: [Matrix4<f32>; 2],
+    // the planes of the portals, and the change of view through each
+    planes: [Vector4<f32>; 2],
+    passes: [Matrix4<f32>; 2],
+    proj: Matrix4<f32>,
+}
+
+impl<R: gfx::Resources> Scene<R> {
+    // Draws the scene seen through `view` to `output`, with `depth` more
+    // levels of portals taking their render targets from `targets`. A
+    // camera behind a portal clips at the plane of its exit, and doesn't
+    // draw the exit, which it sees from behind.
+    fn draw<C, O>(&mut self, renderer: &mut gfx::Renderer<R, C>, targets: &[(gfx::Frame<R>,
+                  gfx::TextureHandle<R>)], view: &Matrix4<f32>, exit: Option<usize>,
+                  depth: usize, output: &O)
+                  where C: gfx::CommandBuffer<R>, O: gfx::Output<R> {
+        let eye = view.invert().unwrap().w;
+        let mut through = [None, None];
+        let mut next = 0;
+        for p in 0 .. 2 {
+            if depth == 0 || exit == Some(p) || self.planes[p].dot(&eye) <= 0.0 {
+                continue
+            }
+            let (ref frame, ref texture) = targets[next];
+            let pass = view.mul_m(&self.passes[p]);
+            self.draw(renderer, &targets[next + 1 ..], &pass, Some(1 - p), depth - 1, frame);
+            through[p] = Some(texture.clone());
+            // below the first level only one portal can recurse, so this one
+            // and what it sees take a target per level
+            next += depth;
+        }
+
+        let proj = match exit {
+            Some(q) => {
+                let plane = view.invert().unwrap().transpose().mul_v(&self.planes[q]);
+                oblique(&self.proj, plane)
+            },
+            None => self.proj,
+        };
+        let view_proj = proj.mul_m(view).into_fixed();
+
+        renderer.clear(CLEAR_DATA, gfx::COLOR | gfx::DEPTH, output);
+        for (batch, model) in self.objects.iter_mut().zip(self.models.iter()) {
+            batch.params.view_proj = view_proj;
+            batch.params.model = model.into_fixed();
+            renderer.draw(&(&*batch, &self.context), output).unwrap();
+        }
+        for p in 0 .. 2 {
+            if exit == Some(p) {
+                continue
+            }
+            {
+                let params = &mut self.portal.params;
+                params.view_proj = view_proj;
+                params.model = self.placements[p].into_fixed();
+                params.color = PORTAL_COLORS[p];
+                match through[p] {
+                    Some(ref texture) => {
+                        params.view.0 = texture.clone();
+                        params.open = 1.0;
+                    },
+                    None => params.open = 0.0,
+                }
+            }
+            renderer.draw(&(&self.portal, &self.context), output).unwrap();
+        }
+    }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Portal example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    // every target is drawn entirely before the next, so they can share depth
+    let depth_texture = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let mut targets = Vec::new();
+    for _ in 0 .. 2 * MAX_DEPTH {
+        let texture = factory.create_texture_rgba8(w, h, false).unwrap();
+        let frame = gfx::Frame {
+            colors: vec![Plane::Texture(texture.clone(), 0, None)],
+            depth: Some(Plane::Texture(depth_texture.clone(), 0, None)),
+            .. gfx::Frame::empty(w, h)
+        };
+        targets.push((frame, texture));
+    }
+
+    // nothing is culled, for the back of the portals to show
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let mut objects = Vec::new();
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.cube(&mut factory), meshes.torus(&mut factory, 64, 32, 0.7, 0.3),
+                      meshes.sphere(&mut factory, 32, 64), meshes.cube(&mut factory),
+                      meshes.plane(&mut factory)];
+    for (i, (mesh, slice)) in shapes.into_iter().enumerate() {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: COLORS[i],
+            // the floor is the last shape
+            checker: if i == COLORS.len() - 1 { 1.0 } else { 0.0 },
+            _dummy: std::marker::PhantomData,
+        };
+        objects.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+    }
+
+    let portal = {
+        let (mesh, slice) = meshes.plane(&mut factory);
+        let program = factory.link_program(VERTEX_SRC, PORTAL_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = PortalParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: [1.0; 3],
+            resolution: [w as f32, h as f32],
+            open: 0.0,
+            view: (targets[0].1.clone(), Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    // 2 by 3 rectangles standing on the floor, the first one facing +X and
+    // the second one -Y
+    let upright = Matrix3::from_angle_x(cgmath::rad(0.5 * PI))
+                          .mul_m(&Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.5, 0.0, 0.0, 0.0, 1.0));
+    let placements = [
+        place(Matrix3::from_angle_z(cgmath::rad(0.5 * PI)).mul_m(&upright), -5.0, 0.0, 1.5),
+        place(upright, 3.0, 5.0, 1.5),
+    ];
+    // going in through the front of one portal and out through the front of
+    // the other turns around their vertical axis
+    let turn = Matrix3::from_angle_y(cgmath::rad(PI)).to_matrix4();
+    let pass = |from: usize, to: usize| {
+        placements[from].mul_m(&turn).mul_m(&placements[to].invert().unwrap())
+    };
+
+    let aspect = w as f32 / h as f32;
+    let mut scene = Scene {
+        context: context,
+        objects: objects,
+        models: Vec::new(),
+        portal: portal,
+        placements: placements,
+        planes: [portal_plane(&placements[0]), portal_plane(&placements[1])],
+        passes: [pass(0, 1), pass(1, 0)],
+        proj: cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0),
+    };
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 1.0), 12.0, -0.9, 0.35);
+
+    let mut depth = 2;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => depth = (depth + 1).min(MAX_DEPTH),
+                    VirtualKeyCode::Down => if depth > 0 { depth -= 1 },
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.6 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let spin = Matrix3::from_angle_z(cgmath::rad(a));
+        scene.models = vec![
+            place(spin.mul_s(0.6), 2.0, -2.0, 0.6),
+            place(spin.mul_m(&Matrix3::from_angle_x(cgmath::rad(1.5))), 0.0, 0.0, 1.2),
+            place(Matrix3::identity().mul_s(0.8), -2.0, 3.0, 0.8),
+            place(Matrix3::new(0.3, 0.0, 0.0, 0.0, 0.3, 0.0, 0.0, 0.0, 1.5), 5.0, 2.0, 1.5),
+            place(Matrix3::identity().mul_s(8.0), 0.0, 0.0, 0.0),
+        ];
+
+        scene.draw(&mut renderer, &targets, &camera.view().mat, None, depth, &wrap);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Portal example: depth {}", depth));
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chains of full-screen post-processing passes.
+//!
+//! A `PostChain` owns a target the scene is rendered to, through its
+//! `frame`, and a list of passes that each read the result of the pass
+//! before them and write the next one, back and forth between two more
+//! targets. The last pass that is enabled writes straight to the output,
+//! so turning passes on and off costs nothing else.
+//!
+//! A pass is only the body of a fragment shader. It is put after a header
+//! declaring what every pass gets: the `t_Source` texture, the
+//! `u_Resolution` of the targets in pixels, the `u_Time` in seconds, four
+//! numbers of its own in `u_Params`, the `v_TexCoord` of the pixel and the
+//! `o_Color` output.
+//!
+//! ```ignore
+//! let mut post = post::PostChain::new(&mut factory, &mut meshes, w, h);
+//! let invert = post.add(&mut factory, "invert", b"
+//!     void main() {
+//!         o_Color = vec4(1.0 - texture(t_Source, v_TexCoord).rgb, 1.0);
+//!     }
+//! ", [0.0; 4]);
+//! // every frame
+//! renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &post.frame);
+//! renderer.draw(&batch, &post.frame).unwrap();
+//! post.draw(&mut renderer, &wrap, time);
+//! ```
+
+use gfx;
+use gfx::traits::*;
+use gfx::Plane;
+use mesh_cache::MeshCache;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "t_Source"]
+    source: gfx::shade::TextureParam<R>,
+    #[name = "u_Resolution"]
+    resolution: [f32; 2],
+    #[name = "u_Time"]
+    time: f32,
+    #[name = "u_Params"]
+    params: [f32; 4],
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+/// What the fragment shader of every pass starts with.
+pub static HEADER_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Source;
+    uniform vec2 u_Resolution;
+    uniform float u_Time;
+    uniform vec4 u_Params;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+";
+
+static COPY_SRC: &'static [u8] = b"
+    void main() {
+        o_Color = texture(t_Source, v_TexCoord);
+    }
+";
+
+/// A full-screen pass of a chain.
+pub struct Pass<R: gfx::Resources> {
+    pub name: &'static str,
+    /// Whether the pass runs, or the one before it goes on to the next.
+    pub enabled: bool,
+    /// The `u_Params` of the shader.
+    pub params: [f32; 4],
+    batch: gfx::batch::OwnedBatch<Params<R>>,
+}
+
+impl<R: gfx::Resources> Pass<R> {
+    fn new<F: gfx::Factory<R>>(factory: &mut F, mesh: &gfx::Mesh<R>,
+                               name: &'static str, fragment_src: &[u8],
+                               params: [f32; 4], source: &gfx::TextureHandle<R>,
+                               sampler: &gfx::SamplerHandle<R>) -> Pass<R> {
+        let src = [HEADER_SRC, fragment_src].concat();
+        let program = match factory.link_program(VERTEX_SRC, &src) {
+            Ok(program) => program,
+            Err(e) => panic!("Failed to link the {} pass: {:?}", name, e),
+        };
+        let data = Params {
+            source: (source.clone(), Some(sampler.clone())),
+            resolution: [0.0; 2],
+            time: 0.0,
+            params: params,
+        };
+        Pass {
+            name: name,
+            enabled: true,
+            params: params,
+            batch: gfx::batch::OwnedBatch::new(mesh.clone(), program, data).unwrap(),
+        }
+    }
+}
+
+fn create_target<R, F>(factory: &mut F, width: gfx::tex::Size, height: gfx::tex::Size)
+                 -> gfx::TextureHandle<R> where R: gfx::Resources, F: gfx::Factory<R> {
+    factory.create_texture(gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::RGBA8,
+    }).unwrap()
+}
+
+/// A scene target followed by post-processing passes.
+pub struct PostChain<R: gfx::Resources> {
+    /// The passes, run in order.
+    pub passes: Vec<Pass<R>>,
+    /// Where to render the scene, with a depth buffer.
+    pub frame: gfx::Frame<R>,
+    scene: gfx::TextureHandle<R>,
+    // the textures passes write to in turn, and their frames
+    targets: Vec<(gfx::TextureHandle<R>, gfx::Frame<R>)>,
+    sampler: gfx::SamplerHandle<R>,
+    // the quad every pass draws
+    quad: gfx::Mesh<R>,
+    // draws the scene as it is when no pass is enabled
+    copy: Pass<R>,
+}
+
+impl<R: gfx::Resources> PostChain<R> {
+    /// Creates a chain with no passes and targets of `width` by `height`,
+    /// which should be the size of the output. Every pass draws the quad of
+    /// `meshes`.
+    pub fn new<F: gfx::Factory<R>>(factory: &mut F, meshes: &mut MeshCache<R>,
+                                   width: gfx::tex::Size,
+                                   height: gfx::tex::Size) -> PostChain<R> {
+        let scene = create_target(factory, width, height);
+        let depth = factory.create_texture(gfx::tex::TextureInfo {
+            width: width,
+            height: height,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::Format::DEPTH24_STENCIL8,
+        }).unwrap();
+        let frame = gfx::Frame {
+            colors: vec![Plane::Texture(scene.clone(), 0, None)],
+            depth: Some(Plane::Texture(depth, 0, None)),
+            .. gfx::Frame::empty(width, height)
+        };
+        let targets = (0 .. 2).map(|_| {
+            let texture = create_target(factory, width, height);
+            let frame = gfx::Frame {
+                colors: vec![Plane::Texture(texture.clone(), 0, None)],
+                .. gfx::Frame::empty(width, height)
+            };
+            (texture, frame)
+        }).collect();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let (quad, _) = meshes.quad(factory);
+        let copy = Pass::new(factory, &quad, "copy", COPY_SRC, [0.0; 4], &scene, &sampler);
+
+        PostChain {
+            passes: Vec::new(),
+            frame: frame,
+            scene: scene,
+            targets: targets,
+            sampler: sampler,
+            quad: quad,
+            copy: copy,
+        }
+    }
+
+    /// Adds an enabled pass at the end, from the body of its fragment shader,
+    /// and returns its index in `passes`.
+    pub fn add<F: gfx::Factory<R>>(&mut self, factory: &mut F, name: &'static str,
+                                   fragment_src: &[u8], params: [f32; 4]) -> usize {
+        let pass = Pass::new(factory, &self.quad, name, fragment_src, params,
+                             &self.scene, &self.sampler);
+        self.passes.push(pass);
+        self.passes.len() - 1
+    }
+
+    /// Runs the enabled passes over the scene, the last of them into `output`.
+    pub fn draw<C, O>(&mut self, renderer: &mut gfx::Renderer<R, C>, output: &O, time: f32)
+                      where C: gfx::CommandBuffer<R>, O: gfx::Output<R> {
+        let (width, height) = (self.frame.width, self.frame.height);
+        let last = self.passes.iter().rposition(|p| p.enabled);
+        let mut source = self.scene.clone();
+        let mut target = 0;
+        for (i, pass) in self.passes.iter_mut().enumerate() {
+            if !pass.enabled {
+                continue;
+            }
+            {
+                let params = &mut pass.batch.params;
+                params.source.0 = source.clone();
+                params.resolution = [width as f32, height as f32];
+                params.time = time;
+                params.params = pass.params;
+            }
+            if Some(i) == last {
+                renderer.draw(&pass.batch, output).unwrap();
+            } else {
+                let (ref texture, ref frame) = self.targets[target];
+                renderer.draw(&pass.batch, frame).unwrap();
+                source = texture.clone();
+                target = 1 - target;
+            }
+        }
+        if last.is_none() {
+            renderer.draw(&self.copy.batch, output).unwrap();
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a stack of small post effects.
+//
+// The scene is rendered to a texture of a `post::PostChain`, which runs it
+// through four passes in turn: chromatic aberration, which samples the red
+// and blue channels further out and further in towards the corners, like a
+// lens bending colors differently; a vignette darkening the corners; film
+// grain, noise stronger in the mid tones and new on every frame; and
+// scanlines rolling slowly down the screen. Each pass is only the body of a
+// fragment shader, and a pass that is off is skipped without a copy.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press 1 to 4
+// to toggle the effects, Tab to select one, and Plus and Minus to change the
+// strength of the selected effect.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::post::PostChain;
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_Scale"]
+    scale: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    uniform vec3 u_Scale;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = a_Normal / u_Scale;
+        gl_Position = u_ViewProj * vec4(u_Scale * a_Pos + u_Offset, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.4, -0.6, 0.7))), 0.0);
+        vec3 color = u_Color * (0.8 * diffuse + 0.25 + 0.1 * n.z);
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// u_Params.x: how far the red and blue channels are moved at the corners,
+// in pixels
+static CHROMATIC_ABERRATION_SRC: &'static [u8] = b"
+    void main() {
+        vec2 from_center = v_TexCoord - 0.5;
+        // grows with the square of the distance, like a lens
+        vec2 offset = 4.0 * dot(from_center, from_center) * from_center
+                    * u_Params.x / u_Resolution;
+        vec4 color = texture(t_Source, v_TexCoord);
+        color.r = texture(t_Source, v_TexCoord - offset).r;
+        color.b = texture(t_Source, v_TexCoord + offset).b;
+        o_Color = color;
+    }
+";
+
+// u_Params.x: how dark the corners get, y: the distance from the center
+// where it begins, in heights of the screen, z: the width of the transition
+static VIGNETTE_SRC: &'static [u8] = b"
+    void main() {
+        vec2 from_center = (v_TexCoord - 0.5) * vec2(u_Resolution.x / u_Resolution.y, 1.0);
+        float v = smoothstep(u_Params.y, u_Params.y + u_Params.z, length(from_center));
+        vec4 color = texture(t_Source, v_TexCoord);
+        o_Color = vec4(color.rgb * (1.0 - u_Params.x * v), color.a);
+    }
+";
+
+// u_Params.x: the amount of noise, y: the size of a grain, in pixels
+static GRAIN_SRC: &'static [u8] = b"
+    float hash(vec2 p) {
+        return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+    }
+
+    void main() {
+        vec4 color = texture(t_Source, v_TexCoord);
+        vec2 cell = floor(gl_FragCoord.xy / u_Params.y);
+        float noise = hash(cell + 100.0 * fract(13.7 * u_Time)) - 0.5;
+        // strongest in the mid tones, like on film
+        float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+        color.rgb += 4.0 * u_Params.x * luma * (1.0 - luma) * noise;
+        o_Color = color;
+    }
+";
+
+// u_Params.x: how dark the gaps between lines get, y: the height of a line,
+// in pixels, z: how many lines they roll by in a second
+static SCANLINES_SRC: &'static [u8] = b"
+    const float PI = 3.14159265;
+
+    void main() {
+        vec4 color = texture(t_Source, v_TexCoord);
+        float phase = (gl_FragCoord.y + u_Params.z * u_Params.y * u_Time) / u_Params.y;
+        float gap = 0.5 + 0.5 * cos(2.0 * PI * phase);
+        o_Color = vec4(color.rgb * (1.0 - u_Params.x * gap), color.a);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Post stack example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut meshes = MeshCache::new();
+    let mut post = PostChain::new(&mut factory, &mut meshes, w, h);
+    post.add(&mut factory, "chromatic aberration", CHROMATIC_ABERRATION_SRC,
+             [6.0, 0.0, 0.0, 0.0]);
+    post.add(&mut factory, "vignette", VIGNETTE_SRC, [0.6, 0.35, 0.5, 0.0]);
+    post.add(&mut factory, "film grain", GRAIN_SRC, [0.15, 1.5, 0.0, 0.0]);
+    post.add(&mut factory, "scanlines", SCANLINES_SRC, [0.25, 3.0, 2.0, 0.0]);
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut batches = Vec::new();
+    for (mesh, slice) in vec![meshes.sphere(&mut factory, 32, 64), meshes.cube(&mut factory),
+                              meshes.plane(&mut factory)].into_iter() {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [0.0; 3],
+            scale: [1.0; 3],
+            color: [1.0; 3],
+            _dummy: std::marker::PhantomData,
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+    }
+    let mut floor = batches.pop().unwrap();
+    floor.params.offset = [0.0, 0.0, -1.0];
+    floor.params.scale = [10.0, 10.0, 1.0];
+    floor.params.color = [0.5, 0.5, 0.55];
+    let mut cube = batches.pop().unwrap();
+    let mut sphere = batches.pop().unwrap();
+
+    // white, black and saturated shapes, which show the fringes of the
+    // aberration and the grain in the mid tones
+    let spheres = [
+        ([-3.0, 0.0, 0.0], [0.95, 0.95, 0.95]),
+        ([0.0, 0.0, 0.0], [0.9, 0.15, 0.1]),
+        ([3.0, 0.0, 0.0], [0.1, 0.5, 0.9]),
+    ];
+    let cubes = [
+        ([-4.5, 4.0, 0.0], [0.03, 0.03, 0.03]),
+        ([0.0, 4.5, 0.5], [0.95, 0.8, 0.2]),
+        ([4.5, 4.0, 0.0], [0.2, 0.8, 0.3]),
+    ];
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 1.5, 0.0), 10.0, -1.6, 0.4);
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.7, 0.85, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut selected = 0;
+    let mut time = 0.0f32;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let toggle = match key {
+                        VirtualKeyCode::Key1 => Some(0),
+                        VirtualKeyCode::Key2 => Some(1),
+                        VirtualKeyCode::Key3 => Some(2),
+                        VirtualKeyCode::Key4 => Some(3),
+                        _ => None,
+                    };
+                    if let Some(i) = toggle {
+                        post.passes[i].enabled = !post.passes[i].enabled;
+                    }
+                    match key {
+                        VirtualKeyCode::Tab => selected = (selected + 1) % post.passes.len(),
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals =>
+                            post.passes[selected].params[0] *= 1.25,
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus =>
+                            post.passes[selected].params[0] *= 0.8,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            time = clock.time() as f32;
+        }
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &post.frame);
+        floor.params.view_proj = view_proj;
+        renderer.draw(&(&floor, &context), &post.frame).unwrap();
+        for &(offset, color) in spheres.iter() {
+            sphere.params.view_proj = view_proj;
+            sphere.params.offset = offset;
+            sphere.params.color = color;
+            renderer.draw(&(&sphere, &context), &post.frame).unwrap();
+        }
+        for &(offset, color) in cubes.iter() {
+            cube.params.view_proj = view_proj;
+            cube.params.offset = offset;
+            cube.params.color = color;
+            renderer.draw(&(&cube, &context), &post.frame).unwrap();
+        }
+        post.draw(&mut renderer, &wrap, time);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let enabled: Vec<_> = post.passes.iter().filter(|p| p.enabled).map(|p| p.name)
+                                         .collect();
+        let effects = if enabled.is_empty() { "no effects".to_string() }
+                      else { enabled.join(", ") };
+        let pass = &post.passes[selected];
+        wrap.window.set_title(&format!("Post stack example: {} | selected {} at {:.2}",
+                                       effects, pass.name, pass.params[0]));
+    }
+}
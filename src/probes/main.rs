@@ -0,0 +1,446 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of lighting moving objects with a grid of light probes.
+//
+// The room is open at the front, lit by a lamp and by the sky, with a red
+// and a blue wall. A lightmap can't light the balls flying around in it, since
+// they move, but the light bouncing off the walls can still be recorded ahead
+// of time at points in the empty space. At startup each probe of a regular
+// grid traces rays in all directions, finds the walls they hit and how
+// brightly those are lit by the lamp, and projects what it sees onto the
+// nine coefficients of the spherical harmonics up to the second band, which
+// can tell apart the light coming from every side. The coefficients of all
+// the probes go in a uniform block, and the shader blends the eight probes
+// around a point by their distances and evaluates the result for its normal.
+//
+// The point can be the center of each ball, which is how a game would pick
+// the probes of an object, or every pixel, which also shows the change of
+// light across the ball. The direct light of the lamp is added at runtime,
+// without shadows.
+//
+// Press M to cycle through the lit scene, the light of the probes and the
+// direct light, P to switch between blending per ball and per pixel, V to
+// show the probes, and Space to stop the balls.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::clock::Clock;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Thumbnail};
+
+// matching the constants of the shaders
+const GRID: [usize; 3] = [5, 5, 3];
+const GRID_ORIGIN: [f32; 3] = [-4.0, -4.0, 0.5];
+const GRID_SPACING: [f32; 3] = [2.0, 2.0, 1.5];
+const NUM_PROBES: usize = 75;
+const LAMP_POS: [f32; 3] = [0.5, 1.0, 3.2];
+const LAMP: [f32; 3] = [14.0, 12.6, 10.5];
+const SKY: [f32; 3] = [0.55, 0.65, 0.9];
+const PROBE_RAYS: usize = 1024;
+const NUM_BALLS: usize = 3;
+const MODES: [&'static str; 3] = ["lit", "probes", "direct"];
+
+// (min, max, color) of the boxes making up the room
+static BLOCKS: [([f32; 3], [f32; 3], [f32; 3]); 8] = [
+    // the floor, the ceiling and three walls
+    ([-5.0, -5.0, -0.2], [5.0, 5.0, 0.0], [0.8, 0.8, 0.8]),
+    ([-5.0, -5.0, 4.0], [5.0, 5.0, 4.2], [0.9, 0.9, 0.9]),
+    ([-5.0, 5.0, 0.0], [5.0, 5.2, 4.0], [0.9, 0.85, 0.7]),
+    ([-5.2, -5.0, 0.0], [-5.0, 5.0, 4.0], [0.9, 0.2, 0.15]),
+    ([5.0, -5.0, 0.0], [5.2, 5.0, 4.0], [0.15, 0.3, 0.9]),
+    // a pillar and two crates, kept clear of the probes
+    ([-2.5, 0.5, 0.0], [-1.7, 1.3, 4.0], [0.9, 0.9, 0.9]),
+    ([0.6, 2.6, 0.0], [1.6, 3.6, 1.2], [0.8, 0.6, 0.4]),
+    ([-3.6, -3.4, 0.0], [-2.8, -2.6, 0.8], [0.8, 0.6, 0.4]),
+];
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // where to blend the probes when not per pixel
+    #[name = "u_Center"]
+    center: [f32; 3],
+    #[name = "u_PerPixel"]
+    per_pixel: f32,
+    #[name = "u_Mode"]
+    mode: f32,
+    #[name = "u_ProbeBlock"]
+    probes: gfx::RawBufferHandle<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        vec4 pos = u_Model * vec4(a_Pos, 1.0);
+        v_Pos = pos.xyz;
+        // the models are only scaled along their axes, which keeps the
+        // normals of the boxes and the spheres pointing the right way
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * pos;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const ivec3 GRID = ivec3(5, 5, 3);
+    const vec3 GRID_ORIGIN = vec3(-4.0, -4.0, 0.5);
+    const vec3 GRID_SPACING = vec3(2.0, 2.0, 1.5);
+    const int NUM_PROBES = 75;
+    const vec3 LAMP_POS = vec3(0.5, 1.0, 3.2);
+    const vec3 LAMP = vec3(14.0, 12.6, 10.5);
+
+    uniform vec3 u_Color;
+    uniform vec3 u_Center;
+    uniform float u_PerPixel;
+    uniform float u_Mode;
+    uniform u_ProbeBlock {
+        // nine coefficients per probe, already turned into irradiance
+        vec4 u_Probes[NUM_PROBES * 9];
+    };
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    // the light reaching a surface facing n at p, divided by pi
+    vec3 probe_light(vec3 p, vec3 n) {
+        float basis[9] = float[9](
+            0.282095,
+            0.488603 * n.y, 0.488603 * n.z, 0.488603 * n.x,
+            1.092548 * n.x * n.y, 1.092548 * n.y * n.z,
+            0.315392 * (3.0 * n.z * n.z - 1.0),
+            1.092548 * n.x * n.z, 0.546274 * (n.x * n.x - n.y * n.y));
+
+        vec3 g = clamp((p - GRID_ORIGIN) / GRID_SPACING, vec3(0.0), vec3(GRID - 1));
+        ivec3 cell = min(ivec3(g), GRID - 2);
+        vec3 f = g - vec3(cell);
+        vec3 sum = vec3(0.0);
+        for (int c = 0; c < 8; c++) {
+            ivec3 corner = ivec3(c & 1, (c >> 1) & 1, c >> 2);
+            vec3 w = mix(1.0 - f, f, vec3(corner));
+            ivec3 i = cell + corner;
+            int probe = (i.z * GRID.y + i.y) * GRID.x + i.x;
+            for (int k = 0; k < 9; k++) {
+                sum += w.x * w.y * w.z * basis[k] * u_Probes[probe * 9 + k].rgb;
+            }
+        }
+        return max(sum, vec3(0.0));
+    }
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 indirect = probe_light(mix(u_Center, v_Pos, u_PerPixel), n);
+        vec3 to_lamp = LAMP_POS - v_Pos;
+        vec3 direct = LAMP * max(dot(n, normalize(to_lamp)), 0.0)
+                    / (dot(to_lamp, to_lamp) * 3.14159);
+
+        vec3 light = u_Mode < 0.5 ? indirect + direct : u_Mode < 1.5 ? indirect : direct;
+        vec3 color = u_Color * light;
+        o_Color = vec4(pow(1.0 - exp(-color), vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+fn add(a: [f32; 3], b: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] + b[0] * s, a[1] + b[1] * s, a[2] + b[2] * s]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// The nearest box the ray hits, as the distance, the normal of the face
+// and the color of the box
+fn trace(origin: [f32; 3], dir: [f32; 3]) -> Option<(f32, [f32; 3], [f32; 3])> {
+    let mut nearest = None;
+    for &(min, max, color) in BLOCKS.iter() {
+        let (mut t0, mut t1) = (0.0f32, ::std::f32::INFINITY);
+        let mut axis = 0;
+        for k in 0 .. 3 {
+            let inv = 1.0 / dir[k];
+            let (a, b) = ((min[k] - origin[k]) * inv, (max[k] - origin[k]) * inv);
+            if a.min(b) > t0 {
+                t0 = a.min(b);
+                axis = k;
+            }
+            t1 = t1.min(a.max(b));
+        }
+        if t0 < t1 && nearest.map_or(true, |(t, _, _)| t0 < t) {
+            // the face the ray entered through
+            let mut normal = [0.0; 3];
+            normal[axis] = -dir[axis].signum();
+            nearest = Some((t0, normal, color));
+        }
+    }
+    nearest
+}
+
+// The light of the lamp reaching a point of a surface
+fn direct(p: [f32; 3], n: [f32; 3]) -> [f32; 3] {
+    let d = add(LAMP_POS, p, -1.0);
+    let distance = dot(d, d).sqrt();
+    let dir = [d[0] / distance, d[1] / distance, d[2] / distance];
+    let cos = dot(dir, n);
+    match trace(p, dir) {
+        Some((t, _, _)) if t < distance => [0.0; 3],
+        _ if cos <= 0.0 => [0.0; 3],
+        _ => [LAMP[0] * cos / (distance * distance),
+              LAMP[1] * cos / (distance * distance),
+              LAMP[2] * cos / (distance * distance)],
+    }
+}
+
+fn sh_basis(d: [f32; 3]) -> [f32; 9] {
+    let (x, y, z) = (d[0], d[1], d[2]);
+    [0.282095,
+     0.488603 * y, 0.488603 * z, 0.488603 * x,
+     1.092548 * x * y, 1.092548 * y * z,
+     0.315392 * (3.0 * z * z - 1.0),
+     1.092548 * x * z, 0.546274 * (x * x - y * y)]
+}
+
+// Projects the light seen from every probe onto the spherical harmonics,
+// with a single bounce off the walls, and convolves it with the cosine lobe
+// so that the shader gets the light reaching a surface straight away
+fn bake_probes() -> Vec<[f32; 4]> {
+    // the convolution of each band, divided by pi
+    let bands = [1.0, 2.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0, 0.25, 0.25, 0.25, 0.25, 0.25];
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut coefficients = Vec::with_capacity(NUM_PROBES * 9);
+    for z in 0 .. GRID[2] {
+        for y in 0 .. GRID[1] {
+            for x in 0 .. GRID[0] {
+                let probe = [GRID_ORIGIN[0] + x as f32 * GRID_SPACING[0],
+                             GRID_ORIGIN[1] + y as f32 * GRID_SPACING[1],
+                             GRID_ORIGIN[2] + z as f32 * GRID_SPACING[2]];
+                let mut sh = [[0.0f32; 3]; 9];
+                for _ in 0 .. PROBE_RAYS {
+                    // uniform over the sphere
+                    let dz = rng.gen_range(-1.0f32, 1.0);
+                    let a = 2.0 * PI * rng.gen::<f32>();
+                    let r = (1.0 - dz * dz).sqrt();
+                    let dir = [r * a.cos(), r * a.sin(), dz];
+                    let radiance = match trace(probe, dir) {
+                        Some((t, n, color)) => {
+                            let e = direct(add(add(probe, dir, t), n, 1e-3), n);
+                            [color[0] * e[0] / PI, color[1] * e[1] / PI, color[2] * e[2] / PI]
+                        },
+                        None => SKY,
+                    };
+                    let weight = 4.0 * PI / PROBE_RAYS as f32;
+                    for (k, b) in sh_basis(dir).iter().enumerate() {
+                        for c in 0 .. 3 {
+                            sh[k][c] += radiance[c] * b * weight;
+                        }
+                    }
+                }
+                for k in 0 .. 9 {
+                    coefficients.push([sh[k][0] * bands[k], sh[k][1] * bands[k],
+                                       sh[k][2] * bands[k], 0.0]);
+                }
+            }
+        }
+    }
+    coefficients
+}
+
+// Scales the shape to the half sizes and moves it to the center
+fn place(center: [f32; 3], size: [f32; 3]) -> [[f32; 4]; 4] {
+    Matrix4::new(size[0], 0.0, 0.0, 0.0,
+                 0.0, size[1], 0.0, 0.0,
+                 0.0, 0.0, size[2], 0.0,
+                 center[0], center[1], center[2], 1.0).into_fixed()
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Light probes example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let coefficients = bake_probes();
+    let probe_buffer = factory.create_buffer::<[f32; 4]>(NUM_PROBES * 9,
+                                                         gfx::BufferUsage::Static);
+    factory.update_buffer(&probe_buffer, &coefficients, 0);
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let params = |model, color, center, per_pixel| Params {
+        view_proj: Matrix4::identity().into_fixed(),
+        model: model,
+        color: color,
+        center: center,
+        per_pixel: per_pixel,
+        mode: 0.0,
+        probes: probe_buffer.raw().clone(),
+    };
+
+    let mut meshes = MeshCache::new();
+    // the room is too large to take its light from a single point
+    let mut blocks: Vec<_> = {
+        let (mesh, slice) = meshes.cube(&mut factory);
+        BLOCKS.iter().map(|&(min, max, color)| {
+            let center = [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5,
+                          (min[2] + max[2]) * 0.5];
+            let size = [(max[0] - min[0]) * 0.5, (max[1] - min[1]) * 0.5,
+                        (max[2] - min[2]) * 0.5];
+            let data = params(place(center, size), color, center, 1.0);
+            context.make_batch(&program, data, &mesh, slice.clone(), &state).unwrap()
+        }).collect()
+    };
+
+    let mut spheres = {
+        let (mesh, slice) = meshes.sphere(&mut factory, 24, 48);
+        let data = params(place([0.0; 3], [1.0; 3]), [0.9; 3], [0.0; 3], 0.0);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(50.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 1.5), 9.0, -1.7, 0.2);
+
+    let clear_data = gfx::ClearData {
+        color: [SKY[0], SKY[1], SKY[2], 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut mode = 0;
+    let mut per_pixel = false;
+    let mut show_probes = false;
+    let mut moving = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::M => mode = (mode + 1) % MODES.len(),
+                    VirtualKeyCode::P => per_pixel = !per_pixel,
+                    VirtualKeyCode::V => show_probes = !show_probes,
+                    VirtualKeyCode::Space => moving = !moving,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if moving {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+
+        for batch in blocks.iter_mut() {
+            batch.params.view_proj = view_proj;
+            batch.params.mode = mode as f32;
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        // the balls fly around the room, from the red wall to the blue one
+        // and from the floor to the lamp
+        spheres.params.view_proj = view_proj;
+        spheres.params.mode = mode as f32;
+        spheres.params.per_pixel = if per_pixel { 1.0 } else { 0.0 };
+        for i in 0 .. NUM_BALLS {
+            let a = 0.4 * t + i as f32 * 2.0 * PI / NUM_BALLS as f32;
+            let center = [3.2 * a.cos(), 2.5 * a.sin(),
+                          1.6 + 1.1 * (0.7 * t + i as f32 * 2.0).sin()];
+            spheres.params.model = place(center, [0.45; 3]);
+            spheres.params.center = center;
+            renderer.draw(&(&spheres, &context), &wrap).unwrap();
+        }
+
+        // a small ball at each probe, lit by that probe alone
+        if show_probes {
+            spheres.params.mode = 1.0;
+            spheres.params.per_pixel = 0.0;
+            for i in 0 .. NUM_PROBES {
+                let (x, y, z) = (i % GRID[0], i / GRID[0] % GRID[1], i / (GRID[0] * GRID[1]));
+                let center = [GRID_ORIGIN[0] + x as f32 * GRID_SPACING[0],
+                              GRID_ORIGIN[1] + y as f32 * GRID_SPACING[1],
+                              GRID_ORIGIN[2] + z as f32 * GRID_SPACING[2]];
+                spheres.params.model = place(center, [0.12; 3]);
+                spheres.params.center = center;
+                renderer.draw(&(&spheres, &context), &wrap).unwrap();
+            }
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Light probes example: {}, blended per {}",
+                                       MODES[mode], if per_pixel { "pixel" } else { "ball" }));
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Drawing batches sorted by the state they need.
+//!
+//! Every draw that uses another program than the one before makes the
+//! driver switch programs, which is the most expensive change, then come
+//! changes of the rest of the draw state, then rebinding textures. A
+//! `RenderQueue` is given the batches of a frame in any order, and draws
+//! them sorted by program, then by draw state, then by a key standing for
+//! the textures each one binds, so that batches needing the same things
+//! follow each other. `switches` counts the changes between one draw and
+//! the next, to see what sorting saves.
+//!
+//! The queue only holds indices, so that the batches stay where they are
+//! and it can be kept from one frame to the next without allocating.
+//!
+//! ```ignore
+//! for (i, object) in objects.iter().enumerate() {
+//!     queue.push(i, object.material);
+//! }
+//! queue.flush(&batches, &context, &mut renderer, &wrap);
+//! println!("{} program switches", queue.switches.programs);
+//! ```
+
+use std::cmp::Ordering;
+use gfx;
+use gfx::batch::{Context, RefBatch};
+use gfx::shade::ShaderParam;
+
+/// Changes between consecutive draws, the first draw counting as a change
+/// of everything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Switches {
+    pub programs: usize,
+    pub states: usize,
+    pub textures: usize,
+}
+
+pub struct RenderQueue {
+    /// Whether to sort, or to draw in the order the batches were pushed.
+    pub sorted: bool,
+    /// The changes between the draws of the last `flush`.
+    pub switches: Switches,
+    // index of each batch, and its texture key
+    items: Vec<(usize, u32)>,
+}
+
+impl RenderQueue {
+    pub fn new() -> RenderQueue {
+        RenderQueue {
+            sorted: true,
+            switches: Switches::default(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Queues the batch at `index` of the slice given to `flush`. Batches
+    /// with the same `texture` key should bind the same textures.
+    pub fn push(&mut self, index: usize, texture: u32) {
+        self.items.push((index, texture));
+    }
+
+    /// Draws the queued batches out of `batches`, and empties the queue.
+    pub fn flush<R, C, O, T>(&mut self, batches: &[RefBatch<T>], context: &Context<R>,
+                             renderer: &mut gfx::Renderer<R, C>, output: &O)
+                             where R: gfx::Resources, C: gfx::CommandBuffer<R>,
+                                   O: gfx::Output<R>, T: ShaderParam<Resources = R> {
+        if self.sorted {
+            self.items.sort_by(|&(a, ta), &(b, tb)| {
+                let (a, b) = (&batches[a], &batches[b]);
+                match a.cmp_program(b) {
+                    Ordering::Equal => match a.cmp_state(b) {
+                        Ordering::Equal => ta.cmp(&tb),
+                        order => order,
+                    },
+                    order => order,
+                }
+            });
+        }
+
+        self.switches = Switches::default();
+        let mut previous: Option<(usize, u32)> = None;
+        for &(index, texture) in self.items.iter() {
+            let batch = &batches[index];
+            match previous {
+                Some((p, t)) => {
+                    let p = &batches[p];
+                    if batch.cmp_program(p) != Ordering::Equal {
+                        self.switches.programs += 1;
+                    }
+                    if batch.cmp_state(p) != Ordering::Equal {
+                        self.switches.states += 1;
+                    }
+                    if texture != t {
+                        self.switches.textures += 1;
+                    }
+                },
+                None => self.switches = Switches { programs: 1, states: 1, textures: 1 },
+            }
+            renderer.draw(&(batch, context), output).unwrap();
+            previous = Some((index, texture));
+        }
+        self.items.clear();
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of raymarching signed distance functions.
+//
+// There is no geometry besides a full-screen quad: the fragment shader casts a
+// ray per pixel and marches it through a scene described by distance
+// functions of spheres, boxes and a torus, some of them blended together with
+// a smooth union. The hit points are lit with soft shadows, marched towards
+// the light, and ambient occlusion, sampled along the normal.
+//
+// Drag with the left mouse button to turn around the scene and use the mouse
+// wheel to zoom. Press Space to pause the animation, H to toggle the soft
+// shadows and O to toggle the ambient occlusion.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_InvViewProj"]
+    inv_view_proj: [[f32; 4]; 4],
+    #[name = "u_CamPos"]
+    cam_pos: [f32; 3],
+    #[name = "u_Time"]
+    time: f32,
+    // whether to compute the soft shadows and the ambient occlusion
+    #[name = "u_Features"]
+    features: [f32; 2],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_Ndc;
+
+    void main() {
+        v_Ndc = a_Pos.xy;
+        gl_Position = vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const int MAX_STEPS = 128;
+    const float MAX_DIST = 60.0;
+    const float EPSILON = 0.001;
+    const vec3 LIGHT_DIR = vec3(0.45, -0.35, 0.82);
+
+    uniform mat4 u_InvViewProj;
+    uniform vec3 u_CamPos;
+    uniform float u_Time;
+    uniform vec2 u_Features;
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    float sd_sphere(vec3 p, float r) {
+        return length(p) - r;
+    }
+
+    float sd_box(vec3 p, vec3 b, float r) {
+        vec3 q = abs(p) - b;
+        return length(max(q, 0.0)) + min(max(q.x, max(q.y, q.z)), 0.0) - r;
+    }
+
+    float sd_torus(vec3 p, vec2 t) {
+        vec2 q = vec2(length(p.xy) - t.x, p.z);
+        return length(q) - t.y;
+    }
+
+    // polynomial smooth minimum, blending within a distance of k
+    float smin(float a, float b, float k) {
+        float h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+        return mix(b, a, h) - k * h * (1.0 - h);
+    }
+
+    // distance to the scene in x, material in y
+    vec2 map(vec3 p) {
+        vec2 res = vec2(p.z, 0.0);
+        // a box melting into two orbiting spheres
+        vec3 c = p - vec3(0.0, 0.0, 1.0);
+        float blob = sd_box(c, vec3(0.6), 0.1);
+        float a = 1.3 * u_Time;
+        vec3 orbit = vec3(1.1 * cos(a), 1.1 * sin(a), 0.3 * sin(2.0 * a));
+        blob = smin(blob, sd_sphere(c - orbit, 0.5), 0.4);
+        blob = smin(blob, sd_sphere(c + orbit, 0.4), 0.4);
+        if (blob < res.x) res = vec2(blob, 1.0);
+        float sphere = sd_sphere(p - vec3(-3.0, 1.5, 0.8), 0.8);
+        if (sphere < res.x) res = vec2(sphere, 2.0);
+        float torus = sd_torus(p - vec3(2.8, 2.0, 0.35), vec2(0.8, 0.35));
+        if (torus < res.x) res = vec2(torus, 3.0);
+        // a box with a sphere carved out of it
+        vec3 q = p - vec3(1.0, -3.0, 0.7);
+        float carved = max(sd_box(q, vec3(0.7), 0.0), -sd_sphere(q, 0.9));
+        if (carved < res.x) res = vec2(carved, 4.0);
+        return res;
+    }
+
+    vec2 march(vec3 ro, vec3 rd) {
+        float t = 0.0;
+        for (int i = 0; i < MAX_STEPS; ++i) {
+            vec2 h = map(ro + t * rd);
+            if (h.x < EPSILON * t) return vec2(t, h.y);
+            t += h.x;
+            if (t > MAX_DIST) break;
+        }
+        return vec2(MAX_DIST, -1.0);
+    }
+
+    vec3 normal(vec3 p) {
+        vec2 e = vec2(EPSILON, 0.0);
+        return normalize(vec3(
+            map(p + e.xyy).x - map(p - e.xyy).x,
+            map(p + e.yxy).x - map(p - e.yxy).x,
+            map(p + e.yyx).x - map(p - e.yyx).x));
+    }
+
+    // the closer a ray towards the light passes by the scene, relative to
+    // how far it went, the darker the shadow
+    float soft_shadow(vec3 ro, vec3 rd, float k) {
+        float res = 1.0;
+        float t = 0.02;
+        for (int i = 0; i < 64; ++i) {
+            float h = map(ro + t * rd).x;
+            if (h < EPSILON) return 0.0;
+            res = min(res, k * h / t);
+            t += clamp(h, 0.02, 0.5);
+            if (t > 20.0) break;
+        }
+        return clamp(res, 0.0, 1.0);
+    }
+
+    // compares the distance to the scene with the distance along the normal
+    float ambient_occlusion(vec3 p, vec3 n) {
+        float occlusion = 0.0;
+        float weight = 1.0;
+        for (int i = 1; i <= 5; ++i) {
+            float d = 0.06 * float(i);
+            occlusion += weight * (d - map(p + d * n).x);
+            weight *= 0.7;
+        }
+        return clamp(1.0 - 3.0 * occlusion, 0.0, 1.0);
+    }
+
+    vec3 material(float id, vec3 p) {
+        if (id < 0.5) {
+            float checker = mod(floor(p.x) + floor(p.y), 2.0);
+            return mix(vec3(0.35), vec3(0.55), checker);
+        }
+        if (id < 1.5) return vec3(0.9, 0.4, 0.2);
+        if (id < 2.5) return vec3(0.2, 0.6, 0.9);
+        if (id < 3.5) return vec3(0.3, 0.8, 0.3);
+        return vec3(0.8, 0.75, 0.3);
+    }
+
+    void main() {
+        vec4 far = u_InvViewProj * vec4(v_Ndc, 1.0, 1.0);
+        vec3 rd = normalize(far.xyz / far.w - u_CamPos);
+        vec3 sky = mix(vec3(0.75, 0.8, 0.9), vec3(0.35, 0.5, 0.8), clamp(rd.z, 0.0, 1.0));
+
+        vec2 hit = march(u_CamPos, rd);
+        if (hit.y < 0.0) {
+            o_Color = vec4(sky, 1.0);
+            return;
+        }
+
+        vec3 p = u_CamPos + hit.x * rd;
+        vec3 n = normal(p);
+        vec3 l = normalize(LIGHT_DIR);
+        float shadow = u_Features.x > 0.5 ? soft_shadow(p + 0.01 * n, l, 16.0) : 1.0;
+        float ao = u_Features.y > 0.5 ? ambient_occlusion(p, n) : 1.0;
+
+        float diffuse = max(0.0, dot(n, l)) * shadow;
+        float spec = pow(max(0.0, dot(reflect(rd, n), l)), 32.0) * shadow;
+        float sky_light = 0.5 + 0.5 * n.z;
+        vec3 color = material(hit.y, p) * (vec3(1.0, 0.95, 0.85) * diffuse
+                                         + 0.3 * sky * sky_light * ao)
+                   + 0.3 * spec;
+        // fade into the sky in the distance
+        color = mix(color, sky, 1.0 - exp(-0.002 * hit.x * hit.x));
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Raymarching example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut meshes = MeshCache::new();
+    let (mesh, slice) = meshes.quad(&mut factory);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let data = Params {
+        inv_view_proj: Matrix4::identity().into_fixed(),
+        cam_pos: [0.0; 3],
+        time: 0.0,
+        features: [1.0, 1.0],
+        _dummy: std::marker::PhantomData,
+    };
+    let mut batch = context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new())
+                           .unwrap();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(50.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.8), 9.0, -1.2, 0.35);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut paused = false;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            let features = &mut batch.params.features;
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    paused = !paused,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::H)) =>
+                    features[0] = 1.0 - features[0],
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::O)) =>
+                    features[1] = 1.0 - features[1],
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if !paused {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        let view = camera.view();
+        let inv_view_proj = proj.mul_m(&view.mat).invert().unwrap();
+        batch.params.inv_view_proj = inv_view_proj.into_fixed();
+        batch.params.cam_pos = camera.position().into_fixed();
+        batch.params.time = t;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of reading every frame back to the CPU without waiting
+// for it.
+//
+// Reading pixels into memory makes the CPU wait until the GPU has finished
+// everything before the read and copied the image over. Reading them into a
+// pixel buffer object instead only queues the copy, and a fence after it
+// tells when the copy is done. The example keeps a ring of such buffers, one
+// for each frame in flight, and maps each one when its fence has passed, a
+// couple of frames after it was filled. Only when the ring is full and the
+// oldest copy still isn't done does it have to wait.
+//
+// Each image that comes back is averaged on the CPU, so that the whole of it
+// gets touched. The window title shows how far behind the images arrive, the
+// bandwidth of the transfers, and the average color.
+//
+// gfx-rs can't read render targets back yet, so the reads and the buffers use
+// GL directly, like the thumbnail capture.
+//
+// Press S to switch between reading through the buffers and reading straight
+// into memory, Up and Down to change the number of buffers, and Space to stop
+// the scene.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate time;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+use gl::Gl;
+use gl::types::*;
+use std::iter::repeat;
+use std::ptr;
+use std::slice;
+use time::precise_time_s;
+
+const MAX_BUFFERS: usize = 6;
+// how much a new measure counts in the displayed average
+const SMOOTHING: f64 = 0.05;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 light = normalize(vec3(0.3, -0.5, 0.8));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        o_Color = vec4(u_Color * (0.2 + 0.8 * diffuse), 1.0);
+    }
+";
+
+// A copy of a frame on its way back
+struct Transfer {
+    fence: GLsync,
+    frame: u64,
+    issued: f64,
+}
+
+// The ring of pixel buffers, with the copy each one is waiting for
+struct Ring {
+    buffers: Vec<GLuint>,
+    transfers: Vec<Option<Transfer>>,
+}
+
+impl Ring {
+    fn new(gl: &Gl, count: usize, size: usize) -> Ring {
+        let mut buffers: Vec<GLuint> = repeat(0).take(count).collect();
+        unsafe {
+            gl.GenBuffers(count as GLsizei, buffers.as_mut_ptr());
+            for &buffer in buffers.iter() {
+                gl.BindBuffer(gl::PIXEL_PACK_BUFFER, buffer);
+                gl.BufferData(gl::PIXEL_PACK_BUFFER, size as GLsizeiptr,
+                              ptr::null(), gl::STREAM_READ);
+            }
+            gl.BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+        Ring {
+            buffers: buffers,
+            transfers: (0 .. count).map(|_| None).collect(),
+        }
+    }
+
+    fn delete(&mut self, gl: &Gl) {
+        unsafe {
+            for transfer in self.transfers.iter_mut() {
+                if let Some(t) = transfer.take() {
+                    gl.DeleteSync(t.fence);
+                }
+            }
+            gl.DeleteBuffers(self.buffers.len() as GLsizei, self.buffers.as_ptr());
+        }
+    }
+}
+
+// The average color of an image of RGBA bytes
+fn average(pixels: &[u8]) -> [f32; 3] {
+    let mut sum = [0u64; 3];
+    for pixel in pixels.chunks(4) {
+        for (s, &p) in sum.iter_mut().zip(pixel.iter()) {
+            *s += p as u64;
+        }
+    }
+    let count = (pixels.len() / 4) as f32 * 255.0;
+    [sum[0] as f32 / count, sum[1] as f32 / count, sum[2] as f32 / count]
+}
+
+fn smooth(average: &mut f64, value: f64) {
+    *average += (value - *average) * SMOOTHING;
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Readback example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+    let gl = Gl::load_with(|s| wrap.window.get_proc_address(s));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let mut meshes = MeshCache::new();
+    let mut batch = {
+        let (mesh, slice) = meshes.torus(&mut factory, 48, 96, 1.0, 0.35);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let aspect = w as f32 / h as f32;
+        let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 1.0, 50.0);
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(0.0, -12.0, 8.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::unit_z(),
+        );
+        let data = Params {
+            view_proj: proj.mul_m(&view.mat).into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: [0.0; 3],
+            _dummy: std::marker::PhantomData,
+        };
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.15, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let size = w as usize * h as usize * 4;
+    let mut pixels: Vec<u8> = repeat(0u8).take(size).collect();
+    let mut num_buffers = 3;
+    let mut ring = Ring::new(&gl, num_buffers, size);
+
+    let mut asynchronous = true;
+    let mut turning = true;
+    let mut frame = 0u64;
+    let (mut latency, mut frames_behind, mut stall) = (0.0f64, 0.0f64, 0.0f64);
+    let (mut bandwidth, mut bytes, mut since) = (0.0f64, 0usize, precise_time_s());
+    let mut color = [0.0f32; 3];
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::S => asynchronous = !asynchronous,
+                    VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                        num_buffers = if key == VirtualKeyCode::Up {
+                            std::cmp::min(num_buffers + 1, MAX_BUFFERS)
+                        } else {
+                            std::cmp::max(num_buffers - 1, 1)
+                        };
+                        ring.delete(&gl);
+                        ring = Ring::new(&gl, num_buffers, size);
+                    },
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.5 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for i in 0 .. 9 {
+            let spin = Matrix3::from_angle_z(cgmath::rad(a + i as f32))
+                               .mul_m(&Matrix3::from_angle_x(cgmath::rad(0.7 * a)));
+            let mut model = spin.to_matrix4();
+            model.w = Vector4::new((i % 3) as f32 * 3.0 - 3.0,
+                                   (i / 3) as f32 * 3.0 - 3.0, 0.0, 1.0);
+            batch.params.model = model.into_fixed();
+            let t = 0.5 + 0.5 * (a + i as f32).sin();
+            batch.params.color = [0.9 * t, 0.4, 0.9 * (1.0 - t)];
+            renderer.draw(&(&batch, &context), &wrap).unwrap();
+        }
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        if asynchronous {
+            let slot = (frame % num_buffers as u64) as usize;
+            let buffer = ring.buffers[slot];
+            unsafe {
+                // The ring is full, so wait for the copy that owns this slot
+                let start = precise_time_s();
+                if let Some(t) = ring.transfers[slot].take() {
+                    gl.ClientWaitSync(t.fence, gl::SYNC_FLUSH_COMMANDS_BIT, !0);
+                    gl.DeleteSync(t.fence);
+                }
+                smooth(&mut stall, (precise_time_s() - start) * 1000.0);
+
+                gl.BindBuffer(gl::PIXEL_PACK_BUFFER, buffer);
+                gl.ReadBuffer(gl::BACK);
+                gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+                // with a buffer bound the pointer is an offset into it
+                gl.ReadPixels(0, 0, w as GLsizei, h as GLsizei, gl::RGBA, gl::UNSIGNED_BYTE,
+                              ptr::null_mut());
+                gl.BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                ring.transfers[slot] = Some(Transfer {
+                    fence: gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0),
+                    frame: frame,
+                    issued: precise_time_s(),
+                });
+            }
+
+            // Collect the copies that are done, oldest first, without waiting
+            for i in 1 .. num_buffers + 1 {
+                let slot = (slot + i) % num_buffers;
+                let done = match ring.transfers[slot] {
+                    Some(ref t) => unsafe {
+                        let status = gl.ClientWaitSync(t.fence, 0, 0);
+                        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+                    },
+                    None => false,
+                };
+                if !done {
+                    continue;
+                }
+                let t = ring.transfers[slot].take().unwrap();
+                unsafe {
+                    gl.DeleteSync(t.fence);
+                    gl.BindBuffer(gl::PIXEL_PACK_BUFFER, ring.buffers[slot]);
+                    let data = gl.MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, size as GLsizeiptr,
+                                                 gl::MAP_READ_BIT);
+                    if !data.is_null() {
+                        color = average(slice::from_raw_parts(data as *const u8, size));
+                        gl.UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+                    }
+                    gl.BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                }
+                smooth(&mut latency, (precise_time_s() - t.issued) * 1000.0);
+                smooth(&mut frames_behind, (frame - t.frame) as f64);
+                bytes += size;
+            }
+        } else {
+            // Straight into memory: the call returns once the copy is done
+            let start = precise_time_s();
+            unsafe {
+                gl.ReadBuffer(gl::BACK);
+                gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+                gl.ReadPixels(0, 0, w as GLsizei, h as GLsizei, gl::RGBA, gl::UNSIGNED_BYTE,
+                              pixels.as_mut_ptr() as *mut GLvoid);
+            }
+            let elapsed = (precise_time_s() - start) * 1000.0;
+            color = average(&pixels);
+            smooth(&mut latency, elapsed);
+            smooth(&mut stall, elapsed);
+            smooth(&mut frames_behind, 0.0);
+            bytes += size;
+        }
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+        frame += 1;
+
+        let now = precise_time_s();
+        if now - since >= 1.0 {
+            bandwidth = bytes as f64 / (now - since) / (1024.0 * 1024.0);
+            bytes = 0;
+            since = now;
+        }
+        let mode = if asynchronous {
+            format!("{} buffers", num_buffers)
+        } else {
+            "direct".to_string()
+        };
+        wrap.window.set_title(&format!(
+            "Readback example: {}, {:.1} frames / {:.2} ms behind, {:.2} ms stalled, \
+             {:.0} MB/s, average ({:.2}, {:.2}, {:.2})",
+            mode, frames_behind, latency, stall, bandwidth, color[0], color[1], color[2]));
+    }
+
+    ring.delete(&gl);
+}
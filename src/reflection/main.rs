@@ -0,0 +1,328 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of planar reflections rendered to a texture.
+//
+// The objects are first drawn into a reflection texture, from a camera
+// mirrored below the floor. The near plane of its projection is tilted onto
+// the floor, so that nothing below the floor, like the bottom of the sunken
+// sphere, gets into the reflection. The floor then reads the texture at the
+// screen position of each of its pixels, where the mirrored camera saw the
+// reflection. For rough floors, the texture gets mipmaps and the floor reads
+// blurrier levels.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press Up and
+// Down to change the roughness of the floor, C to toggle the clipping at the
+// floor, and Space to stop the objects from turning.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::cmp;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::{oblique, OrbitCamera};
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+static COLORS: [[f32; 3]; 4] = [
+    [0.8, 0.25, 0.2],
+    [0.25, 0.6, 0.3],
+    [0.9, 0.7, 0.3],
+    [0.3, 0.4, 0.8],
+];
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct FloorParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Roughness"]
+    roughness: f32,
+    #[name = "u_MaxLod"]
+    max_lod: f32,
+    #[name = "t_Reflection"]
+    reflection: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 light = normalize(vec3(0.3, -0.5, 0.8));
+        float diffuse = max(dot(normalize(v_Normal), light), 0.0);
+        o_Color = vec4(u_Color * (0.25 + 0.75 * diffuse), 1.0);
+    }
+";
+
+static FLOOR_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    out vec2 v_Pos;
+    out vec4 v_Clip;
+
+    void main() {
+        v_Pos = 10.0 * a_Pos.xy;
+        v_Clip = u_ViewProj * vec4(v_Pos, 0.0, 1.0);
+        gl_Position = v_Clip;
+    }
+";
+
+static FLOOR_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Roughness;
+    uniform float u_MaxLod;
+    uniform sampler2D t_Reflection;
+    in vec2 v_Pos;
+    in vec4 v_Clip;
+    out vec4 o_Color;
+
+    void main() {
+        // the mirrored camera looks through the same pixels
+        vec2 uv = v_Clip.xy / v_Clip.w * 0.5 + 0.5;
+        vec3 reflection = textureLod(t_Reflection, uv, u_Roughness * u_MaxLod).rgb;
+        float checker = mod(floor(v_Pos.x) + floor(v_Pos.y), 2.0);
+        vec3 floor_color = vec3(0.1 + 0.05 * checker);
+        o_Color = vec4(floor_color + 0.6 * reflection, 1.0);
+    }
+";
+
+// Places an object, rotated and scaled, at a position
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Planar reflection example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let levels = (16 - cmp::max(w, h).leading_zeros()) as u8;
+    let reflection = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: levels,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::RGBA8,
+    }).unwrap();
+    let reflection_depth = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let reflection_frame = gfx::Frame {
+        colors: vec![Plane::Texture(reflection.clone(), 0, None)],
+        depth: Some(Plane::Texture(reflection_depth, 0, None)),
+        .. gfx::Frame::empty(w, h)
+    };
+
+    // the reflection reverses the winding of the triangles, so nothing is culled
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let mut batches = Vec::new();
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.cube(&mut factory), meshes.torus(&mut factory, 64, 32, 0.7, 0.3),
+                      meshes.sphere(&mut factory, 32, 64), meshes.cube(&mut factory)];
+    for (i, (mesh, slice)) in shapes.into_iter().enumerate() {
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: COLORS[i],
+            _dummy: std::marker::PhantomData,
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+    }
+
+    let mut floor = {
+        let (mesh, slice) = meshes.plane(&mut factory);
+        let program = factory.link_program(FLOOR_VERTEX_SRC, FLOOR_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Trilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = FloorParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            roughness: 0.0,
+            max_lod: (levels - 1) as f32,
+            reflection: (reflection.clone(), Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    // mirrors the Z axis through the floor
+    let mut mirror = Matrix4::identity();
+    mirror.z.z = -1.0;
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.5), 10.0, -1.9, 0.35);
+
+    let clear_data = gfx::ClearData {
+        color: [0.35, 0.45, 0.6, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clipping = true;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let roughness = &mut floor.params.roughness;
+                    match key {
+                        VirtualKeyCode::Up => *roughness = (*roughness + 0.05).min(1.0),
+                        VirtualKeyCode::Down => *roughness = (*roughness - 0.05).max(0.0),
+                        VirtualKeyCode::C => clipping = !clipping,
+                        VirtualKeyCode::Space => turning = !turning,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.6 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let spin = Matrix3::from_angle_z(cgmath::rad(a));
+        let models = [
+            place(spin.mul_s(0.6), -2.5, 0.0, 1.0),
+            place(spin.mul_m(&Matrix3::from_angle_x(cgmath::rad(1.5))), 2.5, 0.0, 1.2),
+            // sunk halfway
+            place(Matrix3::identity(), 0.0, 2.0, 0.0),
+            place(spin.mul_s(0.5), 0.0, -2.0, 0.5),
+        ];
+
+        let view = camera.view().mat;
+        let view_proj = proj.mul_m(&view);
+        let mirrored_view = view.mul_m(&mirror);
+        let mirrored_proj = if clipping {
+            // the floor in view space, facing down since the mirror moved
+            // what stands on the floor below it
+            let plane = view.invert().unwrap().transpose()
+                            .mul_v(&Vector4::new(0.0, 0.0, -1.0, 0.0));
+            oblique(&proj, plane)
+        } else {
+            proj
+        };
+        let mirrored_view_proj = mirrored_proj.mul_m(&mirrored_view);
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &reflection_frame);
+        for (batch, model) in batches.iter_mut().zip(models.iter()) {
+            batch.params.view_proj = mirrored_view_proj.into_fixed();
+            batch.params.model = model.into_fixed();
+            renderer.draw(&(&*batch, &context), &reflection_frame).unwrap();
+        }
+        // the mipmaps need the reflection to be rendered already
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+        if floor.params.roughness > 0.0 {
+            factory.generate_mipmap(&reflection);
+        }
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for batch in batches.iter_mut() {
+            batch.params.view_proj = view_proj.into_fixed();
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+        floor.params.view_proj = view_proj.into_fixed();
+        renderer.draw(&(&floor, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of sorting draws by state with a render queue.
+//
+// A grid of 1600 objects is drawn each frame, every one with its own batch
+// made from one of four programs, three draw states and four textures,
+// picked at random. Pushed to a `gfx_examples::queue::RenderQueue` in the
+// order they were made, they switch programs, states or textures on almost
+// every draw. Sorted by the queue, each program is switched to once, and
+// the states and textures only change within a program. The title shows
+// the switches of the last frame, and the CPU time it took to build and
+// submit.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press S to
+// switch sorting on and off.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+extern crate time;
+
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::queue::RenderQueue;
+use gfx_examples::{window, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "t_Pattern"]
+    pattern: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec2 a_TexCoord;
+    out vec3 v_Normal;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_TexCoord = a_TexCoord;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+// Four ways of shading, so that the objects need different programs
+static FRAGMENT_SRCS: [&'static [u8]; 4] = [b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform sampler2D t_Pattern;
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // plain diffuse
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.4, 0.3, 0.8))), 0.0);
+        vec3 albedo = u_Color * texture(t_Pattern, v_TexCoord).rgb;
+        o_Color = vec4(albedo * (0.8 * diffuse + 0.2), 1.0);
+    }
+", b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform sampler2D t_Pattern;
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // diffuse in three bands
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.4, 0.3, 0.8))), 0.0);
+        float band = floor(diffuse * 3.0 + 0.5) / 3.0;
+        vec3 albedo = u_Color * texture(t_Pattern, v_TexCoord).rgb;
+        o_Color = vec4(albedo * (0.8 * band + 0.2), 1.0);
+    }
+", b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform sampler2D t_Pattern;
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // lit from the sky above and the ground below
+        vec3 n = normalize(v_Normal);
+        vec3 light = mix(vec3(0.25, 0.2, 0.15), vec3(0.7, 0.8, 1.0), 0.5 + 0.5 * n.z);
+        vec3 albedo = u_Color * texture(t_Pattern, v_TexCoord).rgb;
+        o_Color = vec4(albedo * light, 1.0);
+    }
+", b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform sampler2D t_Pattern;
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // the normal as a color, tinted
+        vec3 n = normalize(v_Normal);
+        vec3 albedo = u_Color * texture(t_Pattern, v_TexCoord).rgb;
+        o_Color = vec4(albedo * (0.5 + 0.5 * n), 1.0);
+    }
+"];
+
+// Objects along each side of the grid, and the distance between them
+const GRID: usize = 40;
+const SPACING: f32 = 3.0;
+const PATTERN_SIZE: usize = 64;
+
+// A checker, stripes, dots and a grid, all light enough to tint
+fn make_pattern(kind: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(PATTERN_SIZE * PATTERN_SIZE * 4);
+    for y in 0 .. PATTERN_SIZE {
+        for x in 0 .. PATTERN_SIZE {
+            let (u, v) = (x as f32 / PATTERN_SIZE as f32, y as f32 / PATTERN_SIZE as f32);
+            let light = match kind {
+                0 => (x / 16 + y / 16) % 2 == 0,
+                1 => (x + y) / 8 % 2 == 0,
+                2 => {
+                    let (du, dv) = ((u * 4.0).fract() - 0.5, (v * 4.0).fract() - 0.5);
+                    du * du + dv * dv > 0.09
+                },
+                _ => x % 16 > 1 && y % 16 > 1,
+            };
+            let value = if light { 255 } else { 140 };
+            data.extend([value, value, value, 255].iter().cloned());
+        }
+    }
+    data
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Render queue example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let programs: Vec<_> = FRAGMENT_SRCS.iter().map(|src| {
+        factory.link_program(VERTEX_SRC, src).unwrap()
+    }).collect();
+
+    let depth = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut culled = depth.clone();
+    culled.primitive.method = gfx::state::RasterMethod::Fill(gfx::state::CullMode::Back);
+    let mut lines = depth.clone();
+    lines.primitive.method = gfx::state::RasterMethod::Line(1.0);
+    let states = [depth, culled, lines];
+
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Tile)
+    );
+    let textures: Vec<_> = (0 .. 4).map(|kind| {
+        let size = PATTERN_SIZE as u16;
+        let texture = factory.create_texture_rgba8(size, size, false).unwrap();
+        factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                               &make_pattern(kind), None).unwrap();
+        texture
+    }).collect();
+
+    let mut cache = MeshCache::new();
+    let meshes = vec![cache.cube(&mut factory), cache.sphere(&mut factory, 12, 24)];
+
+    // Everything picked at random, with the texture kept as the key of
+    // each object for the queue
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut batches = Vec::with_capacity(GRID * GRID);
+    let mut texture_keys = Vec::with_capacity(GRID * GRID);
+    for y in 0 .. GRID {
+        for x in 0 .. GRID {
+            let (program, state, texture) = (rng.gen_range(0, programs.len()),
+                                             rng.gen_range(0, states.len()),
+                                             rng.gen_range(0, textures.len()));
+            let (ref mesh, ref slice) = meshes[rng.gen_range(0, meshes.len())];
+            let half = 0.5 * (GRID - 1) as f32 * SPACING;
+            let mut model = Matrix4::identity();
+            model.w = Vector4::new(x as f32 * SPACING - half, y as f32 * SPACING - half, 0.0, 1.0);
+            let data = Params {
+                view_proj: Matrix4::identity().into_fixed(),
+                model: model.into_fixed(),
+                color: [rng.gen_range(0.4, 1.0), rng.gen_range(0.4, 1.0), rng.gen_range(0.4, 1.0)],
+                pattern: (textures[texture].clone(), Some(sampler.clone())),
+            };
+            batches.push(context.make_batch(&programs[program], data, mesh, slice.clone(),
+                                            &states[state]).unwrap());
+            texture_keys.push(texture as u32);
+        }
+    }
+    let mut queue = RenderQueue::new();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 500.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 110.0, -0.8, 0.9);
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.12, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // CPU time from the start of a frame until it is submitted, smoothed
+    let mut cpu_time = 0.0;
+
+    'main: loop {
+        let frame_start = time::precise_time_s();
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::S)) =>
+                    queue.sorted = !queue.sorted,
+                _ => {},
+            }
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for (i, batch) in batches.iter_mut().enumerate() {
+            batch.params.view_proj = view_proj;
+            queue.push(i, texture_keys[i]);
+        }
+        queue.flush(&batches, &context, &mut renderer, &wrap);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+        cpu_time += (time::precise_time_s() - frame_start - cpu_time) * 0.05;
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let switches = queue.switches;
+        wrap.window.set_title(&format!("Render queue example: {}, {} program, {} state and {} \
+                                        texture switches over {} draws, {:.2} ms of CPU",
+                                       if queue.sorted { "sorted" } else { "unsorted" },
+                                       switches.programs, switches.states, switches.textures,
+                                       batches.len(), cpu_time * 1000.0));
+    }
+}
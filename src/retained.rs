@@ -0,0 +1,73 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Passes recorded once and submitted again every frame.
+//!
+//! A `gfx::Renderer` only records commands, they run when its buffer is
+//! submitted to the device, and the same buffer can be submitted any number
+//! of times. A `RetainedPass` keeps a renderer of its own for a pass whose
+//! commands don't change from one frame to the next, like a blit of a render
+//! target to the window, and only records it again after `invalidate`,
+//! when the parameters of its batches have changed. Everything else about
+//! the frame, like the contents of the textures it reads, can change in
+//! between.
+//!
+//! ```ignore
+//! let mut show = RetainedPass::new(factory.create_renderer());
+//! // every frame
+//! device.submit(show.record(|renderer| {
+//!     renderer.draw(&(&blit, &context), &wrap).unwrap();
+//! }).as_buffer());
+//! ```
+
+use gfx;
+
+pub struct RetainedPass<R: gfx::Resources, C: gfx::CommandBuffer<R>> {
+    renderer: gfx::Renderer<R, C>,
+    valid: bool,
+    recordings: usize,
+}
+
+impl<R: gfx::Resources, C: gfx::CommandBuffer<R>> RetainedPass<R, C> {
+    pub fn new(renderer: gfx::Renderer<R, C>) -> RetainedPass<R, C> {
+        RetainedPass {
+            renderer: renderer,
+            valid: false,
+            recordings: 0,
+        }
+    }
+
+    /// Makes the next `record` record the pass again.
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    /// Records the pass with `f`, unless what was recorded last time is
+    /// still valid, and returns the renderer holding it, to submit.
+    pub fn record<F>(&mut self, f: F) -> &gfx::Renderer<R, C>
+                     where F: FnOnce(&mut gfx::Renderer<R, C>) {
+        if !self.valid {
+            self.renderer.reset();
+            f(&mut self.renderer);
+            self.valid = true;
+            self.recordings += 1;
+        }
+        &self.renderer
+    }
+
+    /// How many times the pass has been recorded.
+    pub fn recordings(&self) -> usize {
+        self.recordings
+    }
+}
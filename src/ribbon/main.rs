@@ -0,0 +1,291 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of streaming new vertices to the GPU every frame.
+//
+// A ribbon trails behind a point moving along a path, or behind the mouse
+// once it moves. Each frame the head moves, a pair of vertices is added
+// there, and the oldest ones fade out and drop off the tail.
+//
+// The vertices live in a buffer used as a ring. New ones are written after
+// the last ones, never over anything a frame still in flight could be
+// reading, so the driver never has to wait for the GPU before the upload.
+// The ribbon drawn is the range between its tail and the write position.
+// When the buffer is full, writing starts over at the beginning of fresh
+// storage, with the living part of the ribbon copied there first.
+//
+// Getting fresh storage is what orphaning a buffer does: the driver hands
+// out new memory under the same buffer, and frees the old one once the GPU
+// is done with it. gfx-rs can't orphan a buffer yet, so the example keeps a
+// few buffers and moves on to the next one instead. By the time it comes
+// back to one, the frames that read it are long finished.
+//
+// Press A to follow the path again after moving the mouse, and Up and Down
+// to change the width of the ribbon.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate time;
+
+use cgmath::FixedArray;
+use gfx::traits::*;
+use gfx_examples::{window, Clock, Thumbnail};
+use std::collections::VecDeque;
+
+// vertices in each buffer
+const CAPACITY: usize = 2048;
+const NUM_BUFFERS: usize = 3;
+// seconds before a vertex has faded out
+const LIFETIME: f32 = 2.0;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct RibbonVertex {
+    #[name = "a_Pos"]
+    pos: [f32; 2],
+    // -1 on one edge of the ribbon, 1 on the other
+    #[name = "a_Side"]
+    side: f32,
+    #[name = "a_Time"]
+    time: f32,
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    #[name = "u_Time"]
+    time: f32,
+    #[name = "u_Lifetime"]
+    lifetime: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Proj;
+    uniform float u_Time;
+    uniform float u_Lifetime;
+    in vec2 a_Pos;
+    in float a_Side;
+    in float a_Time;
+    out float v_Side;
+    out float v_Age;
+
+    void main() {
+        v_Side = a_Side;
+        v_Age = clamp((u_Time - a_Time) / u_Lifetime, 0.0, 1.0);
+        gl_Position = u_Proj * vec4(a_Pos, 0.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in float v_Side;
+    in float v_Age;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 color = mix(vec3(1.0, 0.9, 0.4), vec3(0.8, 0.1, 0.5), v_Age);
+        float edge = 1.0 - v_Side * v_Side;
+        o_Color = vec4(color, edge * (1.0 - v_Age));
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Ribbon example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let hidpi = window::scale_factor(&wrap.window);
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().blend(gfx::BlendPreset::Alpha);
+    let proj = cgmath::ortho(0.0, w as f32, 0.0, h as f32, -1.0, 1.0);
+    // a buffer and a batch drawing from it for each turn
+    let buffers: Vec<_> = (0 .. NUM_BUFFERS).map(|_|
+        factory.create_buffer::<RibbonVertex>(CAPACITY, gfx::BufferUsage::Stream)
+    ).collect();
+    let mut batches: Vec<_> = buffers.iter().map(|buffer| {
+        let mesh = gfx::Mesh::from_format(buffer.clone(), CAPACITY as gfx::VertexCount);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleStrip);
+        let data = Params {
+            proj: proj.into_fixed(),
+            time: 0.0,
+            lifetime: LIFETIME,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    }).collect();
+
+    let clear_data = gfx::ClearData {
+        color: [0.05, 0.05, 0.1, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    // the living part of the ribbon, kept to copy into fresh storage
+    let mut ribbon: VecDeque<RibbonVertex> = VecDeque::new();
+    let (mut current, mut cursor) = (0, 0);
+    let mut scratch = Vec::with_capacity(CAPACITY);
+    let mut head = [0.5 * w as f32, 0.5 * h as f32];
+    let mut last: Option<[f32; 2]> = None;
+    let mut mouse = None;
+    let mut width = 24.0f32;
+    let (mut orphaned, mut uploaded) = (0, 0usize);
+    let mut last_report = time::precise_time_s();
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let mut t = 0.0f32;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::MouseMoved((x, y)) =>
+                    mouse = Some([x as f32 * hidpi, h as f32 - y as f32 * hidpi]),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::A => mouse = None,
+                    VirtualKeyCode::Up => width = (width * 1.25).min(96.0),
+                    VirtualKeyCode::Down => width = (width / 1.25).max(2.0),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            t += clock.step();
+            head = match mouse {
+                Some(pos) => pos,
+                None => [(0.5 + 0.4 * (1.3 * t).sin()) * w as f32,
+                         (0.5 + 0.35 * (1.7 * t + 0.5).sin()) * h as f32],
+            };
+        }
+
+        // Drop the faded vertices off the tail, a pair at a time, keeping the
+        // ribbon to half a buffer so that it always fits in fresh storage
+        while ribbon.len() > CAPACITY / 2 ||
+              ribbon.front().map_or(false, |v| v.time < t - LIFETIME) {
+            ribbon.pop_front();
+            ribbon.pop_front();
+        }
+        if ribbon.is_empty() {
+            last = None;
+        }
+
+        // The new pair, across the direction the head moved in
+        let moved = last.map_or(true, |p| {
+            let (dx, dy) = (head[0] - p[0], head[1] - p[1]);
+            dx * dx + dy * dy > 1.0
+        });
+        if moved {
+            let (nx, ny) = match last {
+                Some(p) => {
+                    let (dx, dy) = (head[0] - p[0], head[1] - p[1]);
+                    let length = (dx * dx + dy * dy).sqrt();
+                    (-dy / length, dx / length)
+                },
+                None => (0.0, 1.0),
+            };
+            let half = 0.5 * width;
+            let pair = [
+                RibbonVertex {
+                    pos: [head[0] - nx * half, head[1] - ny * half],
+                    side: -1.0,
+                    time: t,
+                },
+                RibbonVertex {
+                    pos: [head[0] + nx * half, head[1] + ny * half],
+                    side: 1.0,
+                    time: t,
+                },
+            ];
+            ribbon.extend(pair.iter().cloned());
+            last = Some(head);
+
+            if cursor + pair.len() <= CAPACITY {
+                // Append after what was written before
+                factory.update_buffer(&buffers[current], &pair, cursor);
+                cursor += pair.len();
+                uploaded += pair.len();
+            } else {
+                // Full: start over in fresh storage with the whole ribbon
+                current = (current + 1) % NUM_BUFFERS;
+                scratch.clear();
+                scratch.extend(ribbon.iter().cloned());
+                factory.update_buffer(&buffers[current], &scratch, 0);
+                cursor = scratch.len();
+                uploaded += scratch.len();
+                orphaned += 1;
+            }
+        }
+
+        let batch = &mut batches[current];
+        batch.slice.start = (cursor - ribbon.len()) as gfx::VertexCount;
+        batch.slice.end = cursor as gfx::VertexCount;
+        batch.params.time = t;
+
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        renderer.draw(&(&*batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let now = time::precise_time_s();
+        if now - last_report >= 0.5 {
+            let rate = uploaded as f64 * std::mem::size_of::<RibbonVertex>() as f64
+                     / (now - last_report) / 1024.0;
+            wrap.window.set_title(&format!(
+                "Ribbon example: {} vertices, written at {} of {} in buffer {}, \
+                 {:.1} KB/s uploaded, {} restarts",
+                ribbon.len(), cursor, CAPACITY, current, rate, orphaned));
+            last_report = now;
+            uploaded = 0;
+        }
+    }
+}
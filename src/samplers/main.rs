@@ -0,0 +1,294 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of sampler states, side by side.
+//
+// The window is split into twelve panes, each drawn with its own scissor
+// rectangle and a transform shifted into it, and each sampling the same
+// texture through a different sampler. The texture is a checkerboard with a
+// bar in one corner, and every level of its mip chain is tinted with its own
+// color, so the levels a sampler picks show directly:
+//
+//     top row      the wrap modes, on texture coordinates from -1 to 2
+//     middle row   LOD biases, on a floor going away from the camera
+//     bottom row   ranges the LOD is clamped to, on the same floor
+//
+// Press Space to move the floors.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx::tex::{FilterMethod, SamplerInfo, WrapMode};
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::text::{Font, Span, TextRenderer};
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Transform"]
+    transform: [[f32; 4]; 4],
+    // texture coordinates are scaled by the first, then offset by the others
+    #[name = "u_TexTransform"]
+    tex_transform: [f32; 3],
+    #[name = "t_Texture"]
+    texture: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Transform;
+    uniform vec3 u_TexTransform;
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord * u_TexTransform.x + u_TexTransform.yz;
+        gl_Position = u_Transform * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Texture;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = texture(t_Texture, v_TexCoord);
+    }
+";
+
+const TEXTURE_SIZE: u16 = 256;
+// The tint of each mip level
+const TINTS: [[u8; 3]; 9] = [
+    [255, 255, 255], [255, 80, 80], [255, 200, 60], [120, 230, 80], [60, 200, 230],
+    [90, 110, 255], [200, 90, 255], [255, 120, 200], [160, 160, 160],
+];
+
+// A checkerboard of 8 by 8 squares at every level, with the squares of a
+// bar across one corner darker, so mirroring shows
+fn create_texture<R: gfx::Resources, F: gfx::Factory<R>>(factory: &mut F)
+                                                         -> gfx::TextureHandle<R> {
+    let texture = factory.create_texture(gfx::tex::TextureInfo {
+        width: TEXTURE_SIZE,
+        height: TEXTURE_SIZE,
+        depth: 1,
+        levels: TINTS.len() as u8,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::RGBA8,
+    }).unwrap();
+    for (level, tint) in TINTS.iter().enumerate() {
+        let size = TEXTURE_SIZE as usize >> level;
+        let square = (size / 8).max(1);
+        let mut data = Vec::with_capacity(size * size * 4);
+        for y in 0 .. size {
+            for x in 0 .. size {
+                let light = (x / square + y / square) % 2 == 0;
+                let bar = x < size / 2 && y < square;
+                let shade = match (light, bar) {
+                    (true, false) => 1.0,
+                    (false, false) => 0.55,
+                    (_, true) => 0.15,
+                };
+                for &c in tint.iter() {
+                    data.push((c as f32 * shade) as u8);
+                }
+                data.push(0xFF);
+            }
+        }
+        let mut image = texture.get_info().to_image_info();
+        image.width = size as u16;
+        image.height = size as u16;
+        image.mipmap = level as u8;
+        factory.update_texture(&texture, &image, &data, None).unwrap();
+    }
+    texture
+}
+
+// The label of each pane, its sampler, and whether it shows the floor
+fn panes() -> Vec<(&'static str, SamplerInfo, bool)> {
+    let flat = |wrap: WrapMode| SamplerInfo::new(FilterMethod::Bilinear, wrap);
+    let floor = SamplerInfo::new(FilterMethod::Trilinear, WrapMode::Tile);
+    let bias = |lod_bias: f32| SamplerInfo { lod_bias: lod_bias, .. floor };
+    let range = |min: f32, max: f32| SamplerInfo { lod_range: (min, max), .. floor };
+    vec![
+        ("tile", flat(WrapMode::Tile), false),
+        ("mirror", flat(WrapMode::Mirror), false),
+        ("clamp", flat(WrapMode::Clamp), false),
+        ("border", flat(WrapMode::Border), false),
+        ("bias -1", bias(-1.0), true),
+        ("bias 0", bias(0.0), true),
+        ("bias 1", bias(1.0), true),
+        ("bias 2", bias(2.0), true),
+        ("LOD 0 to 1000", range(0.0, 1000.0), true),
+        ("LOD 2 to 1000", range(2.0, 1000.0), true),
+        ("LOD 0 to 1", range(0.0, 1.0), true),
+        ("LOD 3 to 3", range(3.0, 3.0), true),
+    ]
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Samplers example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let texture = create_texture(&mut factory);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new();
+    let mut meshes = MeshCache::new();
+    let (mesh, slice) = meshes.plane(&mut factory);
+    let panes = panes();
+    let mut batches: Vec<_> = panes.iter().map(|&(_, info, _)| {
+        let data = Params {
+            transform: Matrix4::identity().into_fixed(),
+            tex_transform: [1.0, 0.0, 0.0],
+            texture: (texture.clone(), Some(factory.create_sampler(info))),
+        };
+        context.make_batch(&program, data, &mesh, slice.clone(), &state).unwrap()
+    }).collect();
+
+    let mut hud = match Font::load_default(16) {
+        Ok(font) => Some(TextRenderer::new(&mut factory, font, 512)),
+        Err(e) => {
+            println!("{}, without labels: the panes are, row by row, {}", e,
+                     panes.iter().map(|p| p.0).collect::<Vec<_>>().join(", "));
+            None
+        },
+    };
+
+    // four columns and three rows
+    let (pane_w, pane_h) = (w / 4, h / 3);
+    let aspect = pane_w as f32 / pane_h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 200.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(0.0, -20.0, 1.0),
+        &Point3::new(0.0, -14.0, 0.0),
+        &Vector3::unit_z(),
+    );
+    // the plane spans 40 units with a square every unit, and the flat panes
+    // keep their squares square
+    let floor = proj.mul_m(&view.mat).mul_m(&Matrix4::new(20.0, 0.0, 0.0, 0.0,
+                                                          0.0, 20.0, 0.0, 0.0,
+                                                          0.0, 0.0, 1.0, 0.0,
+                                                          0.0, 0.0, 0.0, 1.0));
+    let flat = Matrix4::new(0.8 / aspect, 0.0, 0.0, 0.0,
+                            0.0, 0.8, 0.0, 0.0,
+                            0.0, 0.0, 1.0, 0.0,
+                            0.0, 0.0, 0.0, 1.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut moving = false;
+    let (mut prev_offset, mut offset) = (0.0f32, 0.0f32);
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    moving = !moving,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_offset = offset;
+            if moving {
+                offset += 0.25 * clock.step();
+            }
+        }
+        let offset = prev_offset + (offset - prev_offset) * clock.alpha();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for (i, (batch, &(label, _, on_floor))) in batches.iter_mut().zip(panes.iter())
+                                                          .enumerate() {
+            let (col, row) = (i as u16 % 4, i as u16 / 4);
+            // squeezes the transform into the pane, whose bottom left corner
+            // is at (px, py) in pixels, counting from the bottom
+            let (px, py) = (col * pane_w, (2 - row) * pane_h);
+            let (sx, sy) = (pane_w as f32 / w as f32, pane_h as f32 / h as f32);
+            let pane = Matrix4::new(sx, 0.0, 0.0, 0.0,
+                                    0.0, sy, 0.0, 0.0,
+                                    0.0, 0.0, 1.0, 0.0,
+                                    (2.0 * px as f32 + pane_w as f32) / w as f32 - 1.0,
+                                    (2.0 * py as f32 + pane_h as f32) / h as f32 - 1.0,
+                                    0.0, 1.0);
+            if on_floor {
+                batch.params.transform = pane.mul_m(&floor).into_fixed();
+                batch.params.tex_transform = [40.0, 0.0, offset];
+            } else {
+                batch.params.transform = pane.mul_m(&flat).into_fixed();
+                batch.params.tex_transform = [3.0, -1.0, -1.0];
+            }
+            batch.state = state.clone().scissor(px, py, pane_w, pane_h);
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+
+            if let Some(ref mut hud) = hud {
+                hud.add(&[Span::new(label, [1.0, 1.0, 1.0, 1.0])],
+                        [(col * pane_w) as f32 + 10.0, (row * pane_h) as f32 + 10.0], None);
+            }
+        }
+        if let Some(ref mut hud) = hud {
+            hud.draw(&mut factory, &mut renderer, &wrap);
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of shadow mapping with gfx-rs.
+//
+// Rendering happens in two passes:
+// First,  the scene is rendered from the light into a depth texture.
+// Second, the scene is rendered from the camera. Each fragment is projected
+//         into the light's view and compared against the stored depth with
+//         a comparison sampler to find out whether it is lit.
+//
+// Press Up/Down to change the depth bias, Space to show the shadow map.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const SHADOW_SIZE: gfx::tex::Size = 1024;
+
+#[shader_param]
+struct ShadowParams<R: gfx::Resources> {
+    #[name = "u_Transform"]
+    transform: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_LightViewProj"]
+    light_view_proj: [[f32; 4]; 4],
+    #[name = "u_LightDir"]
+    light_dir: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "u_Bias"]
+    bias: f32,
+    #[name = "t_Shadow"]
+    shadow: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct DebugParams<R: gfx::Resources> {
+    #[name = "t_Depth"]
+    depth: gfx::shade::TextureParam<R>,
+}
+
+static SHADOW_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Transform;
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = u_Transform * vec4(a_Pos, 1.0);
+    }
+";
+
+static SHADOW_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    void main() {
+    }
+";
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_LightViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+    out vec4 v_LightPos;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_LightPos = u_LightViewProj * world;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_LightDir;
+    uniform vec3 u_Color;
+    uniform float u_Bias;
+    uniform sampler2DShadow t_Shadow;
+    in vec3 v_Normal;
+    in vec4 v_LightPos;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(0.0, dot(n, -u_LightDir));
+
+        // from the light's clip space to shadow map coordinates
+        vec3 coord = v_LightPos.xyz / v_LightPos.w * 0.5 + 0.5;
+        float lit = texture(t_Shadow, vec3(coord.xy, coord.z - u_Bias));
+
+        o_Color = vec4(u_Color * (0.2 + 0.8 * diffuse * lit), 1.0);
+    }
+";
+
+static DEBUG_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        // lower left corner of the screen
+        gl_Position = vec4(a_Pos.xy * 0.3 - 0.65, 0.0, 1.0);
+    }
+";
+
+static DEBUG_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Depth;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        float depth = texture(t_Depth, v_TexCoord).r;
+        o_Color = vec4(vec3(depth), 1.0);
+    }
+";
+
+fn model_matrix(pos: [f32; 3], scale: f32) -> Matrix4<f32> {
+    let mut model = Matrix3::identity().mul_s(scale).to_matrix4();
+    model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Shadow mapping example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let shadow_tex = factory.create_texture(gfx::tex::TextureInfo {
+        width: SHADOW_SIZE,
+        height: SHADOW_SIZE,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let shadow_frame = gfx::Frame {
+        depth: Some(Plane::Texture(shadow_tex.clone(), 0, None)),
+        .. gfx::Frame::empty(SHADOW_SIZE, SHADOW_SIZE)
+    };
+
+    // Returns 1.0 where the stored depth passes the comparison, 0.0 where it
+    // fails, and filters the results of the four nearest texels
+    let shadow_sampler = factory.create_sampler(gfx::tex::SamplerInfo {
+        comparison: Some(gfx::state::Comparison::LessEqual),
+        .. gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                      gfx::tex::WrapMode::Clamp)
+    });
+    let debug_sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let shadow_program = factory.link_program(SHADOW_VERTEX_SRC, SHADOW_FRAGMENT_SRC)
+                                .unwrap();
+    let scene_program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC)
+                               .unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.plane(&mut factory), meshes.cube(&mut factory),
+                      meshes.sphere(&mut factory, 16, 32)];
+
+    // mesh index, position, scale and color of everything in the scene
+    let objects = [
+        (0, [ 0.0,  0.0, 0.0], 10.0, [0.8, 0.8, 0.8]),
+        (1, [-3.0, -2.0, 1.0],  1.0, [0.8, 0.3, 0.2]),
+        (1, [ 2.5, -3.0, 0.5],  0.5, [0.2, 0.6, 0.3]),
+        (1, [ 3.0,  3.0, 1.5],  1.5, [0.3, 0.4, 0.8]),
+        (2, [ 0.0,  1.0, 2.0],  1.2, [0.9, 0.8, 0.3]),
+    ];
+
+    let mut batches: Vec<_> = objects.iter().map(|&(mesh_id, pos, scale, color)| {
+        let (ref mesh, ref slice) = shapes[mesh_id];
+        let model = model_matrix(pos, scale);
+
+        let shadow_data = ShadowParams {
+            transform: Matrix4::identity().into_fixed(),
+            _dummy: std::marker::PhantomData,
+        };
+        let scene_data = SceneParams {
+            model: model.into_fixed(),
+            view_proj: Matrix4::identity().into_fixed(),
+            light_view_proj: Matrix4::identity().into_fixed(),
+            light_dir: [0.0, 0.0, -1.0],
+            color: color,
+            bias: 0.0,
+            shadow: (shadow_tex.clone(), Some(shadow_sampler.clone())),
+        };
+
+        let shadow = context.make_batch(&shadow_program, shadow_data, mesh, slice.clone(), &state)
+                            .unwrap();
+        let scene = context.make_batch(&scene_program, scene_data, mesh, slice.clone(), &state)
+                           .unwrap();
+        (model, shadow, scene)
+    }).collect();
+
+    let debug = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(DEBUG_VERTEX_SRC, DEBUG_FRAGMENT_SRC)
+                             .unwrap();
+        let data = DebugParams {
+            depth: (shadow_tex.clone(), Some(debug_sampler.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new())
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 1.0, 100.0);
+    let light_proj = cgmath::ortho(-12.0f32, 12.0, -12.0, 12.0, 1.0, 40.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut bias = 0.002f32;
+    let mut show_shadow_map = false;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        let old_bias = bias;
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) =>
+                    bias *= 2.0,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) =>
+                    bias /= 2.0,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    show_shadow_map = !show_shadow_map,
+                _ => {},
+            }
+        }
+        if bias != old_bias {
+            println!("bias: {}", bias);
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            time = clock.time() as f32;
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // The light slowly circles above the scene
+        let light_dir = Vector3::new(0.5 * (0.3 * t).cos(), 0.5 * (0.3 * t).sin(), -1.0)
+                                .normalize();
+        let light_view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(-20.0 * light_dir.x, -20.0 * light_dir.y, -20.0 * light_dir.z),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::unit_y(),
+        );
+        let light_view_proj = light_proj.mul_m(&light_view.mat);
+
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(15.0 * (0.1 * t).cos(), 15.0 * (0.1 * t).sin(), 10.0),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::unit_z(),
+        );
+        let view_proj = proj.mul_m(&view.mat);
+
+        for &mut (ref model, ref mut shadow, ref mut scene) in batches.iter_mut() {
+            shadow.params.transform = light_view_proj.mul_m(model).into_fixed();
+            scene.params.view_proj = view_proj.into_fixed();
+            scene.params.light_view_proj = light_view_proj.into_fixed();
+            scene.params.light_dir = light_dir.into_fixed();
+            scene.params.bias = bias;
+        }
+
+        // Render depth from the light
+        renderer.clear(clear_data, gfx::DEPTH, &shadow_frame);
+        for &(_, ref shadow, _) in batches.iter() {
+            renderer.draw(&(shadow, &context), &shadow_frame).unwrap();
+        }
+
+        // Render the lit scene from the camera
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for &(_, _, ref scene) in batches.iter() {
+            renderer.draw(&(scene, &context), &wrap).unwrap();
+        }
+        if show_shadow_map {
+            renderer.draw(&(&debug, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
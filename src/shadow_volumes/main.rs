@@ -0,0 +1,551 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of stencil shadow volumes with gfx-rs.
+//
+// Every frame the CPU finds the silhouette of each object as seen from the
+// light, the edges between a triangle facing the light and one facing away,
+// and extrudes them to infinity along the light. Together with the
+// triangles facing away, which close the volume on the side of the object,
+// they bound the space the object keeps in the dark.
+//
+// Rendering happens in three passes:
+// First,  the scene is drawn with the ambient light only, filling the depth
+//         buffer.
+// Second, the volumes are drawn without writing colors or depth, with the
+//         front and back faces changing the stencil differently. Where a
+//         face is behind the scene, back faces add one and front faces
+//         take one away, so the stencil ends up counting the volumes the
+//         visible surface is in. This is the z-fail method, which unlike
+//         counting the faces in front still works with the camera inside a
+//         volume, at the cost of the volumes having to be closed. The far
+//         end of the volumes is at infinity, so the projection has no far
+//         plane.
+// Third,  the scene is drawn again with the direct light, added only where
+//         the stencil is zero.
+//
+// The result has the exact, hard edges of the geometry, where a shadow map
+// of the same scene, drawn for comparison, is limited by its resolution.
+//
+// Press S to switch between shadow volumes and the shadow map, V to show
+// the volumes, and Space to stop the light.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector, Vector3, Vector4, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+const SHADOW_SIZE: gfx::tex::Size = 1024;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct VolumeVertex {
+    // with w set to 0 for the ends at infinity
+    #[name = "a_Pos"]
+    pos: [f32; 4],
+}
+
+#[shader_param]
+struct ShadowParams<R: gfx::Resources> {
+    #[name = "u_Transform"]
+    transform: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_LightViewProj"]
+    light_view_proj: [[f32; 4]; 4],
+    #[name = "u_LightDir"]
+    light_dir: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 0 for the ambient light, 1 for the direct light, 2 for both with the
+    // shadow map
+    #[name = "u_Pass"]
+    pass: f32,
+    #[name = "t_Shadow"]
+    shadow: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct VolumeParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static SHADOW_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Transform;
+    in vec3 a_Pos;
+
+    void main() {
+        gl_Position = u_Transform * vec4(a_Pos, 1.0);
+    }
+";
+
+static SHADOW_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    void main() {
+    }
+";
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_LightViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+    out vec4 v_LightPos;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_LightPos = u_LightViewProj * world;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const vec3 AMBIENT = vec3(0.2, 0.22, 0.28);
+    const float BIAS = 0.002;
+
+    uniform vec3 u_LightDir;
+    uniform vec3 u_Color;
+    uniform float u_Pass;
+    uniform sampler2DShadow t_Shadow;
+    in vec3 v_Normal;
+    in vec4 v_LightPos;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(0.0, dot(n, -u_LightDir));
+        vec3 light;
+        if (u_Pass < 0.5) {
+            light = AMBIENT;
+        } else if (u_Pass < 1.5) {
+            light = vec3(diffuse);
+        } else {
+            vec3 coord = v_LightPos.xyz / v_LightPos.w * 0.5 + 0.5;
+            float lit = texture(t_Shadow, vec3(coord.xy, coord.z - BIAS));
+            light = AMBIENT + diffuse * lit;
+        }
+        o_Color = vec4(u_Color * light, 1.0);
+    }
+";
+
+static VOLUME_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec4 a_Pos;
+
+    void main() {
+        gl_Position = u_ViewProj * a_Pos;
+    }
+";
+
+static VOLUME_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = u_Color;
+    }
+";
+
+// A closed mesh casting a shadow volume
+struct Caster {
+    positions: Vec<[f32; 3]>,
+    triangles: Vec<[usize; 3]>,
+    // the ends of each edge, in the order of the first of the two triangles
+    // sharing it, and the two triangles
+    edges: Vec<(usize, usize, usize, usize)>,
+}
+
+impl Caster {
+    fn new((vertices, indices): (Vec<shape::Vertex>, Vec<u16>)) -> Caster {
+        // the shapes split their vertices along the seams of the texture
+        // coordinates and the edges of the normals, which have to be welded
+        // back together for the triangles to find their neighbours
+        let mut positions = Vec::new();
+        let mut welded = HashMap::new();
+        let remap: Vec<usize> = vertices.iter().map(|v| {
+            let key = [(v.pos[0] * 1e4).round() as i32, (v.pos[1] * 1e4).round() as i32,
+                       (v.pos[2] * 1e4).round() as i32];
+            *welded.entry(key).or_insert_with(|| {
+                positions.push(v.pos);
+                positions.len() - 1
+            })
+        }).collect();
+
+        let mut triangles = Vec::new();
+        let mut edges = Vec::new();
+        let mut open = HashMap::new();
+        for tri in indices.chunks(3) {
+            let t = [remap[tri[0] as usize], remap[tri[1] as usize], remap[tri[2] as usize]];
+            // the ones collapsed around the poles of the sphere
+            if t[0] == t[1] || t[1] == t[2] || t[2] == t[0] {
+                continue;
+            }
+            let id = triangles.len();
+            triangles.push(t);
+            for k in 0 .. 3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                match open.remove(&(b, a)) {
+                    Some(first) => edges.push((b, a, first, id)),
+                    None => { open.insert((a, b), id); },
+                }
+            }
+        }
+        assert!(open.is_empty(), "the mesh of a shadow caster has to be closed");
+
+        Caster {
+            positions: positions,
+            triangles: triangles,
+            edges: edges,
+        }
+    }
+
+    fn max_volume_vertices(&self) -> usize {
+        3 * (self.triangles.len() + self.edges.len())
+    }
+
+    // Appends the triangles of the shadow volume of the mesh placed by
+    // `model`, for a light shining along `light_dir`
+    fn extrude(&self, model: &Matrix4<f32>, light_dir: Vector3<f32>,
+               out: &mut Vec<VolumeVertex>) {
+        let world: Vec<Vector3<f32>> = self.positions.iter().map(|p| {
+            model.mul_v(&Vector4::new(p[0], p[1], p[2], 1.0)).truncate()
+        }).collect();
+        let facing: Vec<bool> = self.triangles.iter().map(|t| {
+            let n = world[t[1]].sub_v(&world[t[0]]).cross(&world[t[2]].sub_v(&world[t[0]]));
+            n.dot(&light_dir) < 0.0
+        }).collect();
+        let vertex = |p: Vector3<f32>| VolumeVertex { pos: [p.x, p.y, p.z, 1.0] };
+
+        // The triangles facing away close the volume, turned around so that
+        // they face out of it, towards the light. Closing it with the ones
+        // facing the light works too, but puts the volume right on top of
+        // the lit surfaces, where depth fighting would leave holes in the
+        // light; behind the triangles facing away, it can only touch
+        // surfaces the light doesn't reach anyway.
+        for (t, &lit) in self.triangles.iter().zip(facing.iter()) {
+            if !lit {
+                out.extend([t[0], t[2], t[1]].iter().map(|&i| vertex(world[i])));
+            }
+        }
+
+        // every point at infinity along a directional light is the same
+        let end = VolumeVertex { pos: [light_dir.x, light_dir.y, light_dir.z, 0.0] };
+        for &(p, q, first, second) in self.edges.iter() {
+            if facing[first] == facing[second] {
+                continue;
+            }
+            // in the order of the triangle facing the light
+            let (a, b) = if facing[first] { (p, q) } else { (q, p) };
+            out.extend([vertex(world[b]), vertex(world[a]), end].iter().cloned());
+        }
+    }
+}
+
+fn model_matrix(rotation: Matrix3<f32>, pos: [f32; 3], scale: f32) -> Matrix4<f32> {
+    let mut model = rotation.mul_s(scale).to_matrix4();
+    model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Shadow volumes example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .with_stencil_buffer(8)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let shadow_tex = factory.create_texture(gfx::tex::TextureInfo {
+        width: SHADOW_SIZE,
+        height: SHADOW_SIZE,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let shadow_frame = gfx::Frame {
+        depth: Some(Plane::Texture(shadow_tex.clone(), 0, None)),
+        .. gfx::Frame::empty(SHADOW_SIZE, SHADOW_SIZE)
+    };
+    let shadow_sampler = factory.create_sampler(gfx::tex::SamplerInfo {
+        comparison: Some(gfx::state::Comparison::LessEqual),
+        .. gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                      gfx::tex::WrapMode::Clamp)
+    });
+
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    // counts the volumes in front of the surface, with both faces drawn
+    let mut volume_state = gfx::DrawState::new()
+        .depth(gfx::state::Comparison::LessEqual, false)
+        .stencil(gfx::state::Comparison::Always, 0);
+    if let Some(ref mut stencil) = volume_state.stencil {
+        stencil.front.op_depth_fail = gfx::state::StencilOp::DecrementWrap;
+        stencil.back.op_depth_fail = gfx::state::StencilOp::IncrementWrap;
+    }
+    volume_state.color_mask = gfx::state::ColorMask::empty();
+    // adds the direct light where the surface is in no volume
+    let lit_state = gfx::DrawState::new()
+        .depth(gfx::state::Comparison::LessEqual, false)
+        .stencil(gfx::state::Comparison::Equal, 0)
+        .blend(gfx::BlendPreset::Add);
+    let show_state = gfx::DrawState::new()
+        .depth(gfx::state::Comparison::LessEqual, false)
+        .blend(gfx::BlendPreset::Alpha);
+
+    let shadow_program = factory.link_program(SHADOW_VERTEX_SRC, SHADOW_FRAGMENT_SRC)
+                                .unwrap();
+    let scene_program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC)
+                               .unwrap();
+
+    let shapes = vec![shape::plane(), shape::cube(), shape::sphere(16, 32),
+                      shape::torus(32, 16, 0.7, 0.3)];
+    let mut meshes = Vec::new();
+    for &(ref vertex_data, ref index_data) in shapes.iter() {
+        let mesh = factory.create_mesh(vertex_data);
+        let slice = factory.create_buffer_index::<u16>(index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        meshes.push((mesh, slice));
+    }
+    // the floor doesn't cast shadows, and isn't closed
+    let casters: Vec<Option<Caster>> = shapes.into_iter().enumerate().map(|(i, shape)| {
+        if i == 0 { None } else { Some(Caster::new(shape)) }
+    }).collect();
+
+    // mesh index, position, scale and color of everything in the scene
+    let objects = [
+        (0, [ 0.0,  0.0, 0.0], 10.0, [0.8, 0.8, 0.8]),
+        (1, [-3.0, -2.0, 1.0],  1.0, [0.8, 0.3, 0.2]),
+        (1, [ 2.5, -3.0, 0.5],  0.5, [0.2, 0.6, 0.3]),
+        (2, [ 0.0,  1.0, 2.0],  1.2, [0.9, 0.8, 0.3]),
+        (3, [ 3.0,  3.0, 2.0],  1.5, [0.3, 0.4, 0.8]),
+    ];
+
+    let mut batches: Vec<_> = objects.iter().map(|&(mesh_id, _, _, color)| {
+        let (ref mesh, ref slice) = meshes[mesh_id];
+        let shadow_data = ShadowParams {
+            transform: Matrix4::identity().into_fixed(),
+            _dummy: std::marker::PhantomData,
+        };
+        let scene_data = SceneParams {
+            model: Matrix4::identity().into_fixed(),
+            view_proj: Matrix4::identity().into_fixed(),
+            light_view_proj: Matrix4::identity().into_fixed(),
+            light_dir: [0.0, 0.0, -1.0],
+            color: color,
+            pass: 0.0,
+            shadow: (shadow_tex.clone(), Some(shadow_sampler.clone())),
+        };
+        let shadow = context.make_batch(&shadow_program, shadow_data, mesh, slice.clone(), &state)
+                            .unwrap();
+        let scene = context.make_batch(&scene_program, scene_data, mesh, slice.clone(), &state)
+                           .unwrap();
+        (shadow, scene)
+    }).collect();
+
+    let capacity = objects.iter().fold(0, |sum, &(mesh_id, _, _, _)| {
+        sum + casters[mesh_id].as_ref().map_or(0, |c| c.max_volume_vertices())
+    });
+    let volume_buffer = factory.create_buffer::<VolumeVertex>(capacity, gfx::BufferUsage::Stream);
+    let mut volumes = {
+        let mesh = gfx::Mesh::from_format(volume_buffer.clone(), capacity as gfx::VertexCount);
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VOLUME_VERTEX_SRC, VOLUME_FRAGMENT_SRC).unwrap();
+        let data = VolumeParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            color: [1.0, 0.4, 0.2, 0.12],
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &volume_state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    // moves the far plane to infinity, where the volumes end, leaving the
+    // near plane at 0.5
+    let mut proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 100.0);
+    proj.z.z = -1.0;
+    proj.w.z = -2.0 * 0.5;
+    let light_proj = cgmath::ortho(-12.0f32, 12.0, -12.0, 12.0, 1.0, 40.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 1.0), 16.0, -2.0, 0.5);
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut use_volumes = true;
+    let mut show_volumes = false;
+    let mut moving = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+    let mut volume_data = Vec::with_capacity(capacity);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::S => use_volumes = !use_volumes,
+                    VirtualKeyCode::V => show_volumes = !show_volumes,
+                    VirtualKeyCode::Space => moving = !moving,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if moving {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // The light slowly circles above the scene, and the torus turns
+        let light_dir = Vector3::new(0.6 * (0.3 * t).cos(), 0.6 * (0.3 * t).sin(), -1.0)
+                                .normalize();
+        let light_view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(-20.0 * light_dir.x, -20.0 * light_dir.y, -20.0 * light_dir.z),
+            &Point3::new(0.0, 0.0, 0.0),
+            &Vector3::unit_y(),
+        );
+        let light_view_proj = light_proj.mul_m(&light_view.mat);
+        let view_proj = proj.mul_m(&camera.view().mat);
+
+        volume_data.clear();
+        for (i, &(mesh_id, pos, scale, _)) in objects.iter().enumerate() {
+            let rotation = if mesh_id == 3 {
+                Matrix3::from_angle_x(cgmath::rad(0.25 * PI + 0.5 * t))
+            } else {
+                Matrix3::identity()
+            };
+            let model = model_matrix(rotation, pos, scale);
+            if let Some(ref caster) = casters[mesh_id] {
+                caster.extrude(&model, light_dir, &mut volume_data);
+            }
+            let (ref mut shadow, ref mut scene) = batches[i];
+            shadow.params.transform = light_view_proj.mul_m(&model).into_fixed();
+            scene.params.model = model.into_fixed();
+            scene.params.view_proj = view_proj.into_fixed();
+            scene.params.light_view_proj = light_view_proj.into_fixed();
+            scene.params.light_dir = light_dir.into_fixed();
+        }
+        factory.update_buffer(&volume_buffer, &volume_data, 0);
+        volumes.slice.end = volume_data.len() as gfx::VertexCount;
+        volumes.params.view_proj = view_proj.into_fixed();
+
+        if use_volumes {
+            renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH | gfx::STENCIL, &wrap);
+            for &mut (_, ref mut scene) in batches.iter_mut() {
+                scene.state = state.clone();
+                scene.params.pass = 0.0;
+                renderer.draw(&(&*scene, &context), &wrap).unwrap();
+            }
+            volumes.state = volume_state.clone();
+            renderer.draw(&(&volumes, &context), &wrap).unwrap();
+            for &mut (_, ref mut scene) in batches.iter_mut() {
+                scene.state = lit_state.clone();
+                scene.params.pass = 1.0;
+                renderer.draw(&(&*scene, &context), &wrap).unwrap();
+            }
+        } else {
+            renderer.clear(clear_data, gfx::DEPTH, &shadow_frame);
+            for &(ref shadow, _) in batches.iter() {
+                renderer.draw(&(shadow, &context), &shadow_frame).unwrap();
+            }
+            renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+            for &mut (_, ref mut scene) in batches.iter_mut() {
+                scene.state = state.clone();
+                scene.params.pass = 2.0;
+                renderer.draw(&(&*scene, &context), &wrap).unwrap();
+            }
+        }
+        if show_volumes {
+            volumes.state = show_state.clone();
+            renderer.draw(&(&volumes, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Shadow volumes example: {}, {} volume triangles",
+                                       if use_volumes { "shadow volumes" } else { "shadow map" },
+                                       volume_data.len() / 3));
+    }
+}
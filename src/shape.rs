@@ -0,0 +1,178 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Basic meshes used by several examples.
+//!
+//! Everything is built around the origin in a Z-up space, with
+//! counter-clockwise front faces.
+
+use std::f32::consts::PI;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+pub struct Vertex {
+    #[name = "a_Pos"]
+    pub pos: [f32; 3],
+    #[name = "a_Normal"]
+    pub normal: [f32; 3],
+    #[name = "a_TexCoord"]
+    pub tex_coord: [f32; 2],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+    Vertex { pos: pos, normal: normal, tex_coord: tex_coord }
+}
+
+/// A cube spanning -1 to 1 on every axis, with a normal per face.
+pub fn cube() -> (Vec<Vertex>, Vec<u16>) {
+    // normal, and the two axes spanning the face
+    let faces = [
+        ([ 0.0,  0.0,  1.0], [ 1.0,  0.0, 0.0], [0.0, 1.0, 0.0]), // top
+        ([ 0.0,  0.0, -1.0], [-1.0,  0.0, 0.0], [0.0, 1.0, 0.0]), // bottom
+        ([ 1.0,  0.0,  0.0], [ 0.0,  1.0, 0.0], [0.0, 0.0, 1.0]), // right
+        ([-1.0,  0.0,  0.0], [ 0.0, -1.0, 0.0], [0.0, 0.0, 1.0]), // left
+        ([ 0.0,  1.0,  0.0], [-1.0,  0.0, 0.0], [0.0, 0.0, 1.0]), // front
+        ([ 0.0, -1.0,  0.0], [ 1.0,  0.0, 0.0], [0.0, 0.0, 1.0]), // back
+    ];
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for &(n, u, v) in faces.iter() {
+        let base = vertices.len() as u16;
+        for &(s, t) in corners.iter() {
+            let pos = [n[0] + s * u[0] + t * v[0],
+                       n[1] + s * u[1] + t * v[1],
+                       n[2] + s * u[2] + t * v[2]];
+            vertices.push(vertex(pos, n, [(s + 1.0) / 2.0, (t + 1.0) / 2.0]));
+        }
+        indices.extend([0, 1, 2, 2, 3, 0].iter().map(|i| base + i));
+    }
+    (vertices, indices)
+}
+
+/// A square on the XY plane spanning -1 to 1, facing up.
+pub fn plane() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        vertex([-1.0, -1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+        vertex([ 1.0, -1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
+        vertex([ 1.0,  1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
+        vertex([-1.0,  1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
+    ];
+    (vertices, vec![0, 1, 2, 2, 3, 0])
+}
+
+/// A unit sphere made of `rings` bands of `segments` quads each. The texture
+/// coordinates wrap around once horizontally and go from the north pole to
+/// the south pole vertically.
+pub fn sphere(rings: u16, segments: u16) -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    for r in 0 .. rings + 1 {
+        let v = r as f32 / rings as f32;
+        let theta = v * PI;
+        for s in 0 .. segments + 1 {
+            let u = s as f32 / segments as f32;
+            let phi = u * 2.0 * PI;
+            let n = [theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()];
+            vertices.push(vertex(n, n, [u, v]));
+        }
+    }
+
+    let mut indices = Vec::new();
+    let stride = segments + 1;
+    for r in 0 .. rings {
+        for s in 0 .. segments {
+            let a = r * stride + s;
+            let b = a + stride;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+/// A capsule along the Z axis: a cylinder of `radius` from `-half_length` to
+/// `half_length`, capped by half spheres, made of `rings` bands of
+/// `segments` quads each over the caps and one more band for the cylinder.
+/// `rings` should be even.
+pub fn capsule(rings: u16, segments: u16, radius: f32, half_length: f32)
+               -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    // the ring at the equator is there twice, once on each cap
+    for r in 0 .. rings + 2 {
+        let (ring, offset) = if r <= rings / 2 { (r, half_length) } else { (r - 1, -half_length) };
+        let theta = ring as f32 / rings as f32 * PI;
+        let v = r as f32 / (rings + 1) as f32;
+        for s in 0 .. segments + 1 {
+            let u = s as f32 / segments as f32;
+            let phi = u * 2.0 * PI;
+            let n = [theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()];
+            let pos = [radius * n[0], radius * n[1], radius * n[2] + offset];
+            vertices.push(vertex(pos, n, [u, v]));
+        }
+    }
+
+    let mut indices = Vec::new();
+    let stride = segments + 1;
+    for r in 0 .. rings + 1 {
+        for s in 0 .. segments {
+            let a = r * stride + s;
+            let b = a + stride;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+/// A torus around the Z axis, with `radius` from the center to the middle of
+/// the tube and `thickness` the radius of the tube, made of `rings` tube
+/// sections of `segments` quads each.
+pub fn torus(rings: u16, segments: u16, radius: f32, thickness: f32)
+             -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    for r in 0 .. rings + 1 {
+        let u = r as f32 / rings as f32;
+        let (sin_phi, cos_phi) = (u * 2.0 * PI).sin_cos();
+        for s in 0 .. segments + 1 {
+            let v = s as f32 / segments as f32;
+            let (sin_theta, cos_theta) = (v * 2.0 * PI).sin_cos();
+            let n = [cos_theta * cos_phi, cos_theta * sin_phi, sin_theta];
+            let d = radius + thickness * cos_theta;
+            vertices.push(vertex([d * cos_phi, d * sin_phi, thickness * sin_theta], n, [u, v]));
+        }
+    }
+
+    let mut indices = Vec::new();
+    let stride = segments + 1;
+    for r in 0 .. rings {
+        for s in 0 .. segments {
+            let a = r * stride + s;
+            let b = a + stride;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+/// Two triangles covering the whole clip space, for full-screen passes.
+pub fn quad() -> Vec<Vertex> {
+    let n = [0.0, 0.0, 1.0];
+    vec![
+        vertex([-1.0, -1.0, 0.0], n, [0.0, 0.0]),
+        vertex([ 1.0, -1.0, 0.0], n, [1.0, 0.0]),
+        vertex([ 1.0,  1.0, 0.0], n, [1.0, 1.0]),
+        vertex([-1.0, -1.0, 0.0], n, [0.0, 0.0]),
+        vertex([ 1.0,  1.0, 0.0], n, [1.0, 1.0]),
+        vertex([-1.0,  1.0, 0.0], n, [0.0, 1.0]),
+    ]
+}
@@ -0,0 +1,243 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of skeletal animation with linear blend skinning on the
+// GPU.
+//
+// A rigged tentacle and two of its animation clips are loaded from MD5 files
+// by `gfx_examples::md5`. Every frame the pose of both clips is sampled, the
+// two poses are blended per joint, and the resulting skinning matrices are
+// uploaded into a uniform buffer. The vertex shader then moves each vertex by
+// the weighted matrices of its joints.
+//
+// Run with the paths of a .md5mesh file and two .md5anim files of it to load
+// another model, like `skinning guard.md5mesh walk.md5anim run.md5anim`.
+//
+// Press Left/Right to change the blend between the clips, Space to let the
+// blend animate by itself.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::env;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx_examples::md5::{self, Pose};
+use gfx_examples::{index, window, Clock, Thumbnail};
+
+// Remember to also change the constant in the shader
+const MAX_JOINTS: usize = 32;
+
+// Made by `tentacle.py`
+static TENTACLE_SRC: &'static str = include_str!("tentacle.md5mesh");
+static WAVE_SRC: &'static str = include_str!("wave.md5anim");
+static CURL_SRC: &'static str = include_str!("curl.md5anim");
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_BoneBlock"]
+    bones: gfx::RawBufferHandle<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec4 a_Joints;
+    in vec4 a_Weights;
+    out vec3 v_Normal;
+
+    const int MAX_JOINTS = 32;
+    layout(std140)
+    uniform u_BoneBlock {
+        mat4 u_Bones[MAX_JOINTS];
+    };
+
+    void main() {
+        mat4 skin = u_Bones[int(a_Joints.x)] * a_Weights.x +
+                    u_Bones[int(a_Joints.y)] * a_Weights.y +
+                    u_Bones[int(a_Joints.z)] * a_Weights.z +
+                    u_Bones[int(a_Joints.w)] * a_Weights.w;
+        v_Normal = mat3(skin) * a_Normal;
+        gl_Position = u_ViewProj * skin * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float d = max(0.0, dot(n, normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(vec3(0.9, 0.5, 0.6) * (0.2 + 0.8 * d), 1.0);
+    }
+";
+
+// The clip at `path`, or the embedded one
+fn clip(path: Option<String>, name: &str, src: &str) -> md5::Clip {
+    match path {
+        Some(path) => md5::load_anim(&path),
+        None => md5::parse_anim(name, src),
+    }.unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn main() {
+    let mut args = env::args().skip(1);
+    let model = match args.next() {
+        Some(path) => md5::load_mesh(&path),
+        None => md5::parse_mesh("tentacle.md5mesh", TENTACLE_SRC),
+    }.unwrap_or_else(|e| panic!("{}", e));
+    let clips = [clip(args.next(), "wave.md5anim", WAVE_SRC),
+                 clip(args.next(), "curl.md5anim", CURL_SRC)];
+    let num_joints = model.joints.len();
+    assert!(num_joints <= MAX_JOINTS, "the model has more than {} joints", MAX_JOINTS);
+    for clip in clips.iter() {
+        assert!(clip.joints() == num_joints, "a clip doesn't animate the joints of the model");
+    }
+
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Skinning example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let bone_buffer = factory.create_buffer::<[[f32; 4]; 4]>(MAX_JOINTS, gfx::BufferUsage::Stream);
+
+    let mesh = factory.create_mesh(&model.vertices);
+    let slice = index::slice(&mut factory, &model.indices, gfx::PrimitiveType::TriangleList);
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 100.0);
+    // looking at the middle of the model, from far enough to see all of it
+    let (mut min, mut max) = ([std::f32::INFINITY; 3], [std::f32::NEG_INFINITY; 3]);
+    for v in model.vertices.iter() {
+        for k in 0 .. 3 {
+            min[k] = min[k].min(v.pos[k]);
+            max[k] = max[k].max(v.pos[k]);
+        }
+    }
+    let center = Point3::new((min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5,
+                             (min[2] + max[2]) * 0.5);
+    let size = (max[2] - min[2]).max(max[0] - min[0]).max(max[1] - min[1]);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(center.x + 1.25 * size, center.y - size, center.z + 0.25 * size),
+        &center,
+        &Vector3::unit_z(),
+    );
+
+    let data = Params {
+        view_proj: proj.mul_m(&view.mat).into_fixed(),
+        bones: bone_buffer.raw().clone(),
+    };
+    let batch = context.make_batch(&program, data, &mesh, slice, &state)
+                       .unwrap();
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let rest = Pose { pos: [0.0; 3], orient: [0.0, 0.0, 0.0, 1.0] };
+    let (mut poses_a, mut poses_b) = (vec![rest; num_joints], vec![rest; num_joints]);
+    let mut bones = vec![[[0.0f32; 4]; 4]; num_joints];
+    let mut blend = 0.5f32;
+    let mut auto_blend = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Left)) => {
+                    auto_blend = false;
+                    blend = (blend - 0.1).max(0.0);
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Right)) => {
+                    auto_blend = false;
+                    blend = (blend + 0.1).min(1.0);
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Space)) =>
+                    auto_blend = !auto_blend,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            time = clock.time() as f32;
+            if auto_blend {
+                blend = 0.5 + 0.5 * (0.25 * time).sin();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        // the local pose of every joint, blended between the clips
+        clips[0].sample(t, &mut poses_a);
+        clips[1].sample(t, &mut poses_b);
+        for (a, b) in poses_a.iter_mut().zip(poses_b.iter()) {
+            *a = a.blend(b, blend);
+        }
+        md5::skin(&model.joints, &poses_a, &mut bones);
+        factory.update_buffer(&bone_buffer, &bones, 0);
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
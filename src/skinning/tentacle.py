@@ -0,0 +1,114 @@
+# Copyright 2014 The Gfx-rs Developers.
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+# Generates the rigged tentacle of the skinning example as MD5 files, the
+# format of Doom 3 that most modelling tools can export.
+#
+# tentacle.md5mesh is a tapered tube along Z with a chain of eight joints, one
+# per unit of length, every vertex weighted between the two joints nearest to
+# it. wave.md5anim is a wave travelling up the chain, three seconds long, and
+# curl.md5anim a sideways curl of six seconds, both looping and sampled at
+# 24 frames per second.
+#
+# Run it from this directory with `python3 tentacle.py`.
+
+import math
+
+NUM_JOINTS = 8
+RINGS_PER_JOINT = 4
+SEGMENTS = 16
+FRAME_RATE = 24
+
+
+def write_mesh(path):
+    rings = NUM_JOINTS * RINGS_PER_JOINT
+    verts, weights, tris = [], [], []
+    for r in range(rings + 1):
+        z = r / RINGS_PER_JOINT
+        # taper towards the tip
+        radius = 0.5 - 0.4 * z / NUM_JOINTS
+        a = min(max(math.floor(z - 0.5), 0), NUM_JOINTS - 1)
+        b = min(a + 1, NUM_JOINTS - 1)
+        wb = min(max(z - 0.5 - a, 0.0), 1.0)
+        for s in range(SEGMENTS):
+            phi = s / SEGMENTS * 2.0 * math.pi
+            x, y = radius * math.cos(phi), radius * math.sin(phi)
+            # the joints have no rotation in the bind pose, so the weights
+            # are only offset by the position of their joint
+            bound = [(j, w) for j, w in ((a, 1.0 - wb), (b, wb)) if w > 0.0]
+            if a == b:
+                bound = [(a, 1.0)]
+            verts.append((s / SEGMENTS, 1.0 - z / NUM_JOINTS, len(weights), len(bound)))
+            for j, w in bound:
+                weights.append((j, w, (x, y, z - j)))
+    for r in range(rings):
+        for s in range(SEGMENTS):
+            i0 = r * SEGMENTS + s
+            i1 = r * SEGMENTS + (s + 1) % SEGMENTS
+            j0, j1 = i0 + SEGMENTS, i1 + SEGMENTS
+            # MD5 faces are clockwise
+            tris.append((i0, j1, i1))
+            tris.append((j1, i0, j0))
+
+    with open(path, 'w') as f:
+        f.write('MD5Version 10\ncommandline "tentacle.py"\n\n')
+        f.write('numJoints %d\nnumMeshes 1\n\njoints {\n' % NUM_JOINTS)
+        for j in range(NUM_JOINTS):
+            f.write('\t"bone%d"\t%d ( 0 0 %d ) ( 0 0 0 )\n' % (j, j - 1, j))
+        f.write('}\n\nmesh {\n\tshader "tentacle"\n\n')
+        f.write('\tnumverts %d\n' % len(verts))
+        for i, (s, t, start, count) in enumerate(verts):
+            f.write('\tvert %d ( %.6f %.6f ) %d %d\n' % (i, s, t, start, count))
+        f.write('\n\tnumtris %d\n' % len(tris))
+        for i, (a, b, c) in enumerate(tris):
+            f.write('\ttri %d %d %d %d\n' % (i, a, b, c))
+        f.write('\n\tnumweights %d\n' % len(weights))
+        for i, (j, w, (x, y, z)) in enumerate(weights):
+            f.write('\tweight %d %d %.6f ( %.6f %.6f %.6f )\n' % (i, j, w, x, y, z))
+        f.write('}\n')
+
+
+# Writes a clip rotating every joint about one axis, `angle(t, j)` radians at
+# `t` seconds. Only that component of the orientations is animated.
+def write_anim(path, frames, axis, angle):
+    flag = {0: 8, 1: 16}[axis]
+    with open(path, 'w') as f:
+        f.write('MD5Version 10\ncommandline "tentacle.py"\n\n')
+        f.write('numFrames %d\nnumJoints %d\nframeRate %d\nnumAnimatedComponents %d\n\n'
+                % (frames, NUM_JOINTS, FRAME_RATE, NUM_JOINTS))
+        f.write('hierarchy {\n')
+        for j in range(NUM_JOINTS):
+            f.write('\t"bone%d"\t%d %d %d\n' % (j, j - 1, flag, j))
+        f.write('}\n\nbounds {\n')
+        for _ in range(frames):
+            f.write('\t( -%d -%d -1 ) ( %d %d %d )\n'
+                    % (NUM_JOINTS, NUM_JOINTS, NUM_JOINTS, NUM_JOINTS, NUM_JOINTS))
+        f.write('}\n\nbaseframe {\n')
+        for j in range(NUM_JOINTS):
+            # each joint at the end of its parent
+            f.write('\t( 0 0 %d ) ( 0 0 0 )\n' % (0 if j == 0 else 1))
+        f.write('}\n')
+        for k in range(frames):
+            t = k / FRAME_RATE
+            # the loader takes the w of the quaternions as negative, so the
+            # other components are negated too to keep the same rotation
+            values = [-math.sin(0.5 * angle(t, j)) for j in range(NUM_JOINTS)]
+            f.write('\nframe %d {\n\t%s\n}\n' % (k, ' '.join('%.6f' % v for v in values)))
+
+
+write_mesh('tentacle.md5mesh')
+write_anim('wave.md5anim', 3 * FRAME_RATE, 0,
+           lambda t, j: 0.35 * math.sin(2.0 * math.pi * t / 3.0 - 0.8 * j))
+write_anim('curl.md5anim', 6 * FRAME_RATE, 1,
+           lambda t, j: 0.3 * (1.0 + math.sin(2.0 * math.pi * t / 6.0)))
@@ -0,0 +1,220 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A physically based sky.
+//!
+//! `Sky` draws a full-screen pass computing single scattering of sunlight
+//! through an Earth-like atmosphere, with Rayleigh scattering by air
+//! molecules and Mie scattering by aerosols, integrated along each view ray.
+//! The pass is drawn at the far plane without writing depth, so it only
+//! covers the pixels where nothing was drawn yet.
+//!
+//! ```ignore
+//! let mut sky = sky::Sky::new(&mut factory, &mut meshes);
+//! sky.sun_dir = [0.0, 1.0, 0.1];
+//! sky.draw(&mut renderer, &wrap, &proj, &view.mat);
+//! ```
+
+use cgmath::{Matrix, Matrix4, Vector4};
+use cgmath::FixedArray;
+use gfx;
+use gfx::traits::*;
+use mesh_cache::MeshCache;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_InvViewProj"]
+    inv_view_proj: [[f32; 4]; 4],
+    #[name = "u_SunDir"]
+    sun_dir: [f32; 3],
+    #[name = "u_Altitude"]
+    altitude: f32,
+    #[name = "u_Exposure"]
+    exposure: f32,
+    _dummy: ::std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_Ndc;
+
+    void main() {
+        v_Ndc = a_Pos.xy;
+        gl_Position = vec4(a_Pos.xy, 1.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PI = 3.14159265;
+    // all distances in meters
+    const float PLANET_RADIUS = 6371e3;
+    const float ATMOSPHERE_RADIUS = 6471e3;
+    const vec3 RAYLEIGH_BETA = vec3(5.5e-6, 13.0e-6, 22.4e-6);
+    const float MIE_BETA = 21e-6;
+    const float RAYLEIGH_HEIGHT = 8e3;
+    const float MIE_HEIGHT = 1.2e3;
+    const float MIE_G = 0.76;
+    const float SUN_INTENSITY = 22.0;
+    const int VIEW_STEPS = 16;
+    const int LIGHT_STEPS = 8;
+
+    uniform mat4 u_InvViewProj;
+    uniform vec3 u_SunDir;
+    uniform float u_Altitude;
+    uniform float u_Exposure;
+    in vec2 v_Ndc;
+    out vec4 o_Color;
+
+    // distances along the ray to a sphere around the planet center, with
+    // the first greater than the second when the ray misses it
+    vec2 intersect(vec3 ro, vec3 rd, float radius) {
+        float b = dot(ro, rd);
+        float d = b * b - dot(ro, ro) + radius * radius;
+        if (d < 0.0) return vec2(1e20, -1e20);
+        d = sqrt(d);
+        return vec2(-b - d, -b + d);
+    }
+
+    // light scattered towards the eye along a ray, and the fraction of the
+    // light behind the atmosphere that makes it through
+    vec3 scatter(vec3 ro, vec3 rd, vec3 sun, out vec3 transmittance) {
+        transmittance = vec3(0.0);
+        vec2 atmosphere = intersect(ro, rd, ATMOSPHERE_RADIUS);
+        if (atmosphere.x > atmosphere.y || atmosphere.y < 0.0) {
+            transmittance = vec3(1.0);
+            return vec3(0.0);
+        }
+        float start = max(atmosphere.x, 0.0);
+        float end = atmosphere.y;
+        vec2 ground = intersect(ro, rd, PLANET_RADIUS);
+        bool hits_ground = ground.x < ground.y && ground.x > 0.0;
+        if (hits_ground) end = min(end, ground.x);
+
+        float ds = (end - start) / float(VIEW_STEPS);
+        vec3 sum_rayleigh = vec3(0.0), sum_mie = vec3(0.0);
+        float depth_rayleigh = 0.0, depth_mie = 0.0;
+        for (int i = 0; i < VIEW_STEPS; ++i) {
+            vec3 p = ro + rd * (start + (float(i) + 0.5) * ds);
+            float height = length(p) - PLANET_RADIUS;
+            float density_rayleigh = exp(-height / RAYLEIGH_HEIGHT) * ds;
+            float density_mie = exp(-height / MIE_HEIGHT) * ds;
+            depth_rayleigh += density_rayleigh;
+            depth_mie += density_mie;
+
+            // optical depth from the sample towards the sun, unless the
+            // planet is in the way
+            float dl = intersect(p, sun, ATMOSPHERE_RADIUS).y / float(LIGHT_STEPS);
+            float light_rayleigh = 0.0, light_mie = 0.0;
+            bool lit = true;
+            for (int j = 0; j < LIGHT_STEPS; ++j) {
+                vec3 q = p + sun * ((float(j) + 0.5) * dl);
+                float light_height = length(q) - PLANET_RADIUS;
+                if (light_height < 0.0) {
+                    lit = false;
+                    break;
+                }
+                light_rayleigh += exp(-light_height / RAYLEIGH_HEIGHT) * dl;
+                light_mie += exp(-light_height / MIE_HEIGHT) * dl;
+            }
+            if (lit) {
+                vec3 tau = RAYLEIGH_BETA * (depth_rayleigh + light_rayleigh)
+                         + 1.1 * MIE_BETA * (depth_mie + light_mie);
+                vec3 attenuation = exp(-tau);
+                sum_rayleigh += density_rayleigh * attenuation;
+                sum_mie += density_mie * attenuation;
+            }
+        }
+        if (!hits_ground) {
+            transmittance = exp(-RAYLEIGH_BETA * depth_rayleigh - 1.1 * MIE_BETA * depth_mie);
+        }
+
+        float mu = dot(rd, sun);
+        float phase_rayleigh = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+        float g2 = MIE_G * MIE_G;
+        float phase_mie = 3.0 / (8.0 * PI) * (1.0 - g2) * (1.0 + mu * mu)
+                        / ((2.0 + g2) * pow(1.0 + g2 - 2.0 * MIE_G * mu, 1.5));
+        return SUN_INTENSITY * (phase_rayleigh * RAYLEIGH_BETA * sum_rayleigh
+                              + phase_mie * MIE_BETA * sum_mie);
+    }
+
+    void main() {
+        // the view has no translation, so this is a direction
+        vec4 far = u_InvViewProj * vec4(v_Ndc, 1.0, 1.0);
+        vec3 rd = normalize(far.xyz / far.w);
+        vec3 ro = vec3(0.0, 0.0, PLANET_RADIUS + max(u_Altitude, 1.0));
+        vec3 sun = normalize(u_SunDir);
+
+        vec3 transmittance;
+        vec3 color = scatter(ro, rd, sun, transmittance);
+        // the sun disk, about half a degree wide
+        if (dot(rd, sun) > 0.99996) {
+            color += SUN_INTENSITY * transmittance;
+        }
+        o_Color = vec4(1.0 - exp(-u_Exposure * color), 1.0);
+    }
+";
+
+/// Draws the sky seen from a point above the ground, in a Z-up space.
+pub struct Sky<R: gfx::Resources> {
+    /// Direction towards the sun, which doesn't have to be normalized.
+    pub sun_dir: [f32; 3],
+    /// Height of the eye above the ground, in meters.
+    pub altitude: f32,
+    /// Scale of the light before it is mapped to the displayable range.
+    pub exposure: f32,
+    batch: gfx::batch::OwnedBatch<Params<R>>,
+}
+
+impl<R: gfx::Resources> Sky<R> {
+    /// Creates the pass, drawing the quad of `meshes`.
+    pub fn new<F: gfx::Factory<R>>(factory: &mut F, meshes: &mut MeshCache<R>) -> Sky<R> {
+        let (mesh, _) = meshes.quad(factory);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let data = Params {
+            inv_view_proj: Matrix4::identity().into_fixed(),
+            sun_dir: [0.0, 0.0, 1.0],
+            altitude: 1.0,
+            exposure: 1.0,
+            _dummy: ::std::marker::PhantomData,
+        };
+        let mut batch = gfx::batch::OwnedBatch::new(mesh, program, data).unwrap();
+        batch.state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, false);
+
+        Sky {
+            sun_dir: [0.0, 0.0, 1.0],
+            altitude: 1.0,
+            exposure: 1.0,
+            batch: batch,
+        }
+    }
+
+    /// Draws the sky behind what `output` already holds, seen through the
+    /// `proj` and `view` matrices. Only the rotation of the view is used.
+    pub fn draw<C, O>(&mut self, renderer: &mut gfx::Renderer<R, C>, output: &O,
+                      proj: &Matrix4<f32>, view: &Matrix4<f32>)
+                      where C: gfx::CommandBuffer<R>, O: gfx::Output<R> {
+        let mut rotation = *view;
+        rotation.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let inv_view_proj = proj.mul_m(&rotation).invert().unwrap();
+        self.batch.params.inv_view_proj = inv_view_proj.into_fixed();
+        self.batch.params.sun_dir = self.sun_dir;
+        self.batch.params.altitude = self.altitude;
+        self.batch.params.exposure = self.exposure;
+        renderer.draw(&self.batch, output).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a skybox.
+//
+// A ring of cubes stands in front of a cubemap drawn by
+// `gfx_examples::cubemap::Skybox`. The skybox is drawn last, as a full-screen
+// quad at the far plane that only passes the depth test where nothing else
+// was drawn, so the sky costs nothing behind the scene. The cubemap is loaded
+// from the six images in the directory given as an argument, named as in
+// `gfx_examples::cubemap::FACE_NAMES`, or generated without one.
+//
+// The camera turns slowly by itself. Drag with the left mouse button to look
+// around and scroll to zoom. Press Space to stop the camera, and Plus and
+// Minus to change the exposure.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::env;
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::cubemap::{self, Skybox};
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const NUM_CUBES: usize = 8;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_Exposure"]
+    exposure: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = a_Normal;
+        gl_Position = u_ViewProj * vec4(0.5 * a_Pos + u_Offset, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Exposure;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        // the sun of the generated environment, and some light from the sky
+        vec3 sun = normalize(vec3(0.5, 0.6, 0.5));
+        float light = 2.0 * max(dot(v_Normal, sun), 0.0) + 0.4 + 0.2 * v_Normal.z;
+        vec3 color = vec3(0.6, 0.55, 0.5) * light;
+        o_Color = vec4(1.0 - exp(-u_Exposure * color), 1.0);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Skybox example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let cube = match env::args().nth(1) {
+        Some(dir) => cubemap::load(&mut factory, &dir).unwrap_or_else(|e| {
+            println!("{}, generating the environment", e);
+            cubemap::generate(&mut factory, 512, cubemap::environment)
+        }),
+        None => cubemap::generate(&mut factory, 512, cubemap::environment),
+    };
+    let mut meshes = MeshCache::new();
+    let mut skybox = Skybox::new(&mut factory, &mut meshes, cube);
+
+    let mut batch = {
+        let (mesh, slice) = meshes.cube(&mut factory);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [0.0; 3],
+            exposure: 1.0,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 8.0, 0.0, 0.1);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_yaw, mut yaw) = (0.0f32, 0.0f32);
+    // how much of the turn the camera was given already
+    let mut applied = 0.0f32;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Space => turning = !turning,
+                    VirtualKeyCode::Add | VirtualKeyCode::Equals =>
+                        skybox.exposure *= 1.25,
+                    VirtualKeyCode::Subtract | VirtualKeyCode::Minus =>
+                        skybox.exposure *= 0.8,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_yaw = yaw;
+            if turning {
+                yaw += 0.1 * clock.step();
+            }
+        }
+        // the turn adds to what the mouse does
+        let turn = prev_yaw + (yaw - prev_yaw) * clock.alpha();
+        camera.yaw += turn - applied;
+        applied = turn;
+
+        let view = camera.view().mat;
+        batch.params.view_proj = proj.mul_m(&view).into_fixed();
+        batch.params.exposure = skybox.exposure;
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for i in 0 .. NUM_CUBES {
+            let angle = 2.0 * PI * i as f32 / NUM_CUBES as f32;
+            batch.params.offset = [3.0 * angle.cos(), 3.0 * angle.sin(), 0.0];
+            renderer.draw(&(&batch, &context), &wrap).unwrap();
+        }
+        // last, where the cubes left the far depth
+        skybox.draw(&mut renderer, &wrap, &proj, &view);
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
@@ -0,0 +1,551 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of SMAA 1x, morphological antialiasing in three passes.
+//
+// The scene is rendered to a texture. The first pass finds the edges between
+// pixels from the difference of their luminance. The second pass follows
+// each edge to both of its ends, looks at how the edges crossing it there
+// bend, and reads from a precomputed area texture how much of each pixel the
+// line through the bends covers. The last pass blends each pixel with its
+// neighbours by these weights.
+//
+// Following an edge reads two pixels at once with a bilinear fetch, and a
+// small search texture tells from the blended value how far the edge really
+// went. Both lookup textures are made by `textures.py`, and embedded in the
+// example.
+//
+// Press M to go through the antialiased image, the original, the edges and
+// the blending weights, and Space to stop the cubes.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector3, Vector4};
+use cgmath::{Transform, AffineMatrix3, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const GRID_SIZE: usize = 5;
+const AREA_SIZE: u16 = 80;
+const SEARCH_SIZE: [u16; 2] = [66, 33];
+
+static MODES: [&'static str; 4] = ["SMAA", "no antialiasing", "edges", "blending weights"];
+
+// two bytes a texel
+static AREA_DATA: &'static [u8] = include_bytes!("area.bin");
+// one byte a texel
+static SEARCH_DATA: &'static [u8] = include_bytes!("search.bin");
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct EdgeParams<R: gfx::Resources> {
+    // the size of a pixel in texture coordinates, then of the window in pixels
+    #[name = "u_Metrics"]
+    metrics: [f32; 4],
+    #[name = "t_Color"]
+    color: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct WeightParams<R: gfx::Resources> {
+    #[name = "u_Metrics"]
+    metrics: [f32; 4],
+    #[name = "t_Edges"]
+    edges: gfx::shade::TextureParam<R>,
+    #[name = "t_Area"]
+    area: gfx::shade::TextureParam<R>,
+    #[name = "t_Search"]
+    search: gfx::shade::TextureParam<R>,
+}
+
+#[shader_param]
+struct BlendParams<R: gfx::Resources> {
+    #[name = "u_Metrics"]
+    metrics: [f32; 4],
+    // an index in MODES
+    #[name = "u_Mode"]
+    mode: f32,
+    #[name = "t_Color"]
+    color: gfx::shade::TextureParam<R>,
+    #[name = "t_Edges"]
+    edges: gfx::shade::TextureParam<R>,
+    #[name = "t_Weights"]
+    weights: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(u_Color * (0.2 + 0.8 * d), 1.0);
+    }
+";
+
+static QUAD_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+// Marks an edge to the left of a pixel in red and below it in green, where
+// the luminance changes enough compared to the other edges around
+static EDGE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float THRESHOLD = 0.1;
+    const vec3 LUMA = vec3(0.2126, 0.7152, 0.0722);
+
+    uniform vec4 u_Metrics;
+    uniform sampler2D t_Color;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    float luma(vec2 offset) {
+        return dot(texture(t_Color, v_TexCoord + offset * u_Metrics.xy).rgb, LUMA);
+    }
+
+    void main() {
+        float l = luma(vec2(0.0, 0.0));
+        float left = luma(vec2(-1.0, 0.0));
+        float below = luma(vec2(0.0, -1.0));
+        vec4 delta = vec4(abs(l - vec2(left, below)), 0.0, 0.0);
+        vec2 edges = step(THRESHOLD, delta.xy);
+        if (dot(edges, vec2(1.0)) == 0.0) {
+            discard;
+        }
+
+        delta.zw = abs(l - vec2(luma(vec2(1.0, 0.0)), luma(vec2(0.0, 1.0))));
+        vec2 max_delta = max(delta.xy, delta.zw);
+        delta.zw = abs(vec2(left, below) - vec2(luma(vec2(-2.0, 0.0)), luma(vec2(0.0, -2.0))));
+        max_delta = max(max_delta, delta.zw);
+        // drop the edges much weaker than the strongest one nearby
+        edges *= step(max(max_delta.x, max_delta.y), 2.0 * delta.xy);
+        o_Color = vec4(edges, 0.0, 1.0);
+    }
+";
+
+// For the edge below a pixel, red is how much the pixel takes from the one
+// below and green how much the one below takes from it. Blue and alpha are
+// the same for the edge to the left.
+static WEIGHT_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float MAX_SEARCH_STEPS = 16.0;
+    const float MAX_DISTANCE = 16.0;
+    const vec2 AREA_SIZE = vec2(80.0, 80.0);
+    const vec2 SEARCH_SIZE = vec2(66.0, 33.0);
+    // any less and the two pixels of a fetch don't both have an edge
+    const float BOTH_EDGES = 0.8281;
+
+    uniform vec4 u_Metrics;
+    uniform sampler2D t_Edges;
+    uniform sampler2D t_Area;
+    uniform sampler2D t_Search;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    // How many pixels the last fetch of a search was past the end of the
+    // edge, less than 3.25, for fetched edges `e` searching left or right
+    float search_offset(vec2 e, float right) {
+        vec2 texel = round(32.0 * e) + vec2(33.0 * right, 0.0) + 0.5;
+        float steps = textureLod(t_Search, texel / SEARCH_SIZE, 0.0).r;
+        return 3.25 - (255.0 / 127.0) * steps;
+    }
+
+    float search_left(vec2 uv, float end) {
+        vec2 e = vec2(0.0, 1.0);
+        while (uv.x > end && e.g > BOTH_EDGES && e.r == 0.0) {
+            e = textureLod(t_Edges, uv, 0.0).rg;
+            uv.x -= 2.0 * u_Metrics.x;
+        }
+        return uv.x + search_offset(e, 0.0) * u_Metrics.x;
+    }
+
+    float search_right(vec2 uv, float end) {
+        vec2 e = vec2(0.0, 1.0);
+        while (uv.x < end && e.g > BOTH_EDGES && e.r == 0.0) {
+            e = textureLod(t_Edges, uv, 0.0).rg;
+            uv.x += 2.0 * u_Metrics.x;
+        }
+        return uv.x - search_offset(e, 1.0) * u_Metrics.x;
+    }
+
+    float search_down(vec2 uv, float end) {
+        vec2 e = vec2(1.0, 0.0);
+        while (uv.y > end && e.r > BOTH_EDGES && e.g == 0.0) {
+            e = textureLod(t_Edges, uv, 0.0).rg;
+            uv.y -= 2.0 * u_Metrics.y;
+        }
+        return uv.y + search_offset(e.gr, 0.0) * u_Metrics.y;
+    }
+
+    float search_up(vec2 uv, float end) {
+        vec2 e = vec2(1.0, 0.0);
+        while (uv.y < end && e.r > BOTH_EDGES && e.g == 0.0) {
+            e = textureLod(t_Edges, uv, 0.0).rg;
+            uv.y += 2.0 * u_Metrics.y;
+        }
+        return uv.y - search_offset(e.gr, 1.0) * u_Metrics.y;
+    }
+
+    // The crossing edges, fetched between two rows, are 0 for none, 1 for one
+    // on the other side of the edge, 3 for one on this side and 4 for both
+    vec2 area(vec2 sqrt_distance, float e1, float e2) {
+        vec2 texel = MAX_DISTANCE * round(4.0 * vec2(e1, e2)) + sqrt_distance + 0.5;
+        return textureLod(t_Area, texel / AREA_SIZE, 0.0).rg;
+    }
+
+    void main() {
+        vec2 uv = v_TexCoord;
+        vec2 pixel = uv * u_Metrics.zw;
+        vec4 offset0 = uv.xyxy + u_Metrics.xyxy * vec4(-0.25, -0.125, 1.25, -0.125);
+        vec4 offset1 = uv.xyxy + u_Metrics.xyxy * vec4(-0.125, -0.25, -0.125, 1.25);
+        vec4 ends = vec4(offset0.xz, offset1.yw) +
+                    vec4(-2.0, 2.0, -2.0, 2.0) * u_Metrics.xxyy * MAX_SEARCH_STEPS;
+
+        vec4 weights = vec4(0.0);
+        vec2 e = texture(t_Edges, uv).rg;
+        if (e.g > 0.0) {
+            float left = search_left(offset0.xy, ends.x);
+            float right = search_right(offset0.zw, ends.y);
+            float e1 = textureLod(t_Edges, vec2(left, offset1.y), 0.0).r;
+            float e2 = textureLodOffset(t_Edges, vec2(right, offset1.y), 0.0, ivec2(1, 0)).r;
+            vec2 d = abs(round(u_Metrics.zz * vec2(left, right) - pixel.xx));
+            weights.rg = area(sqrt(d), e1, e2);
+        }
+        if (e.r > 0.0) {
+            float down = search_down(offset1.xy, ends.z);
+            float up = search_up(offset1.zw, ends.w);
+            float e1 = textureLod(t_Edges, vec2(offset0.x, down), 0.0).g;
+            float e2 = textureLodOffset(t_Edges, vec2(offset0.x, up), 0.0, ivec2(0, 1)).g;
+            vec2 d = abs(round(u_Metrics.ww * vec2(down, up) - pixel.yy));
+            weights.ba = area(sqrt(d), e1, e2);
+        }
+        o_Color = weights;
+    }
+";
+
+static BLEND_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Metrics;
+    uniform float u_Mode;
+    uniform sampler2D t_Color;
+    uniform sampler2D t_Edges;
+    uniform sampler2D t_Weights;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec2 uv = v_TexCoord;
+        if (u_Mode == 2.0) {
+            o_Color = vec4(texture(t_Edges, uv).rg, 0.0, 1.0);
+            return;
+        }
+        if (u_Mode == 3.0) {
+            vec4 w = texture(t_Weights, uv);
+            o_Color = vec4(2.0 * (w.rb + w.ga), 0.0, 1.0);
+            return;
+        }
+
+        // how much to take from the right, above, the left and below
+        vec4 a;
+        a.x = texture(t_Weights, uv + vec2(u_Metrics.x, 0.0)).a;
+        a.y = texture(t_Weights, uv + vec2(0.0, u_Metrics.y)).g;
+        a.wz = texture(t_Weights, uv).rb;
+        if (u_Mode == 1.0 || dot(a, vec4(1.0)) < 1e-5) {
+            o_Color = textureLod(t_Color, uv, 0.0);
+            return;
+        }
+
+        // blend along one axis only, with bilinear fetches partway to the
+        // neighbours
+        bool horizontal = max(a.x, a.z) > max(a.y, a.w);
+        vec4 offset = horizontal ? vec4(a.x, 0.0, a.z, 0.0) : vec4(0.0, a.y, 0.0, a.w);
+        vec2 weight = horizontal ? a.xz : a.yw;
+        weight /= dot(weight, vec2(1.0));
+        vec4 coord = uv.xyxy + offset * vec4(u_Metrics.xy, -u_Metrics.xy);
+        o_Color = weight.x * textureLod(t_Color, coord.xy, 0.0) +
+                  weight.y * textureLod(t_Color, coord.zw, 0.0);
+    }
+";
+
+fn create_target<R: gfx::Resources, F: Factory<R>>(
+                 width: gfx::tex::Size, height: gfx::tex::Size, factory: &mut F)
+                 -> (gfx::Frame<R>, gfx::TextureHandle<R>) {
+    let texture = factory.create_texture_rgba8(width, height, false).unwrap();
+    let frame = gfx::Frame {
+        colors: vec![Plane::Texture(texture.clone(), 0, None)],
+        .. gfx::Frame::empty(width, height)
+    };
+    (frame, texture)
+}
+
+// A texture from the first `channels` bytes of each RGBA texel
+fn create_lookup<R: gfx::Resources, F: Factory<R>>(width: u16, height: u16, channels: usize,
+                                                   data: &[u8], factory: &mut F)
+                                                   -> gfx::TextureHandle<R> {
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for texel in data.chunks(channels) {
+        let mut color = [0, 0, 0, 0xFF];
+        for (c, &v) in color.iter_mut().zip(texel.iter()) {
+            *c = v;
+        }
+        rgba.extend(color.iter().cloned());
+    }
+    let texture = factory.create_texture_rgba8(width, height, false).unwrap();
+    factory.update_texture(&texture, &texture.get_info().to_image_info(), &rgba, None)
+           .unwrap();
+    texture
+}
+
+fn model_matrix(angle: f32, pos: [f32; 3], size: f32) -> Matrix4<f32> {
+    let mut model = Matrix3::from_angle_z(cgmath::rad(angle)).mul_s(size).to_matrix4();
+    model.w = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("SMAA example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+    let metrics = [1.0 / w as f32, 1.0 / h as f32, w as f32, h as f32];
+
+    let (mut scene_frame, scene_texture) = create_target(w, h, &mut factory);
+    scene_frame.depth = Some(Plane::Texture(factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap(), 0, None));
+    let (edge_frame, edge_texture) = create_target(w, h, &mut factory);
+    let (weight_frame, weight_texture) = create_target(w, h, &mut factory);
+
+    let area_texture = create_lookup(AREA_SIZE, AREA_SIZE, 2, AREA_DATA, &mut factory);
+    let search_texture = create_lookup(SEARCH_SIZE[0], SEARCH_SIZE[1], 1, SEARCH_DATA,
+                                       &mut factory);
+
+    // the searches read two pixels at once, and the blending between them
+    let bilinear = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+    let nearest = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let mut meshes = MeshCache::new();
+    let (mut cube, mut floor) = {
+        let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let mut batches = Vec::new();
+        for (mesh, slice) in vec![meshes.cube(&mut factory), meshes.plane(&mut factory)] {
+            let data = SceneParams {
+                model: Matrix4::identity().into_fixed(),
+                view_proj: Matrix4::identity().into_fixed(),
+                color: [0.9, 0.9, 0.9],
+                _dummy: std::marker::PhantomData,
+            };
+            batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+        }
+        let floor = batches.pop().unwrap();
+        (batches.pop().unwrap(), floor)
+    };
+
+    let (mesh, slice) = meshes.quad(&mut factory);
+    let edge = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, EDGE_FRAGMENT_SRC).unwrap();
+        let data = EdgeParams {
+            metrics: metrics,
+            color: (scene_texture.clone(), Some(nearest.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice.clone(), &gfx::DrawState::new())
+               .unwrap()
+    };
+    let weight = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, WEIGHT_FRAGMENT_SRC).unwrap();
+        let data = WeightParams {
+            metrics: metrics,
+            edges: (edge_texture.clone(), Some(bilinear.clone())),
+            area: (area_texture, Some(bilinear.clone())),
+            search: (search_texture, Some(nearest.clone())),
+        };
+        context.make_batch(&program, data, &mesh, slice.clone(), &gfx::DrawState::new())
+               .unwrap()
+    };
+    let mut blend = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, BLEND_FRAGMENT_SRC).unwrap();
+        let data = BlendParams {
+            metrics: metrics,
+            mode: 0.0,
+            color: (scene_texture, Some(bilinear.clone())),
+            edges: (edge_texture, Some(nearest.clone())),
+            weights: (weight_texture, Some(nearest)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(0.0, -8.0, 5.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_z(),
+    );
+    let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.35, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+    let clear_black = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 0.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut mode = 0;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::M => mode = (mode + 1) % MODES.len(),
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.2 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &scene_frame);
+        floor.params.model = model_matrix(0.0, [0.0, 0.0, 0.0], 6.0).into_fixed();
+        floor.params.view_proj = view_proj;
+        floor.params.color = [0.45, 0.5, 0.45];
+        renderer.draw(&(&floor, &context), &scene_frame).unwrap();
+        // each cube turning at its own speed, to cover all the slopes
+        for i in 0 .. GRID_SIZE * GRID_SIZE {
+            let (x, y) = (i % GRID_SIZE, i / GRID_SIZE);
+            let offset = 0.5 * (GRID_SIZE - 1) as f32;
+            // standing on the floor
+            let pos = [(x as f32 - offset) * 2.0, (y as f32 - offset) * 2.0, 0.5];
+            cube.params.model = model_matrix(a * (1.0 + 0.3 * i as f32), pos, 0.5).into_fixed();
+            cube.params.view_proj = view_proj;
+            cube.params.color = [0.3 + 0.6 * x as f32 / GRID_SIZE as f32, 0.4,
+                                 0.3 + 0.6 * y as f32 / GRID_SIZE as f32];
+            renderer.draw(&(&cube, &context), &scene_frame).unwrap();
+        }
+
+        // the edge pass only writes edges, and weights are only found on them
+        renderer.clear(clear_black, gfx::COLOR, &edge_frame);
+        renderer.draw(&(&edge, &context), &edge_frame).unwrap();
+        renderer.clear(clear_black, gfx::COLOR, &weight_frame);
+        renderer.draw(&(&weight, &context), &weight_frame).unwrap();
+        blend.params.mode = mode as f32;
+        renderer.draw(&(&blend, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("SMAA example: {}", MODES[mode]));
+    }
+}
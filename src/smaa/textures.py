@@ -0,0 +1,133 @@
+# Copyright 2014 The Gfx-rs Developers.
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+# Generates the lookup textures of the SMAA example, following AreaTex.py and
+# SearchTex.py of the SMAA reference, without the diagonal patterns and the
+# subpixel offsets of the other SMAA modes.
+#
+# area.bin is 80x80 texels of two bytes. It is made of 5x5 blocks of 16x16,
+# one for each pair of crossing edges at the ends of a line, where texel
+# (x, y) is for a pixel at x^2 pixels from the left end and y^2 from the right.
+#
+# search.bin is 66x33 texels of one byte, the left search in the first 33
+# columns and the right search in the last 33, indexed by the bilinearly
+# fetched edges times 32.
+#
+# Run it from this directory with `python3 textures.py`.
+
+import struct
+
+MAX_DISTANCE = 16
+
+# How the crossing edge of each end bends the line: a half pixel up, a half
+# pixel down or not at all. Edges on both sides don't bend it either.
+BEND = {0: None, 1: -0.5, 3: 0.5, 4: None}
+
+
+def segment_area(p1, p2, x):
+    """Areas below and above zero between the segment and the line y = 0,
+    for the pixel from x to x + 1."""
+    a, b = max(x, p1[0]), min(x + 1.0, p2[0])
+    if a >= b:
+        return 0.0, 0.0
+    slope = (p2[1] - p1[1]) / (p2[0] - p1[0])
+    y1 = p1[1] + slope * (a - p1[0])
+    y2 = p1[1] + slope * (b - p1[0])
+    if y1 * y2 >= 0.0:
+        area = 0.5 * (y1 + y2) * (b - a)
+        return (-area, 0.0) if area < 0.0 else (0.0, area)
+    # two triangles, on both sides of the crossing
+    c = a - y1 / slope
+    t1 = 0.5 * y1 * (c - a)
+    t2 = 0.5 * y2 * (b - c)
+    return (-min(t1, t2), max(t1, t2))
+
+
+def area(e1, e2, left, right):
+    """The fractions of the pixel at `left` from the left end of a line of
+    `left + right + 1` pixels to blend across the edge. The first goes to
+    the pixel itself, the second to its neighbour across the edge."""
+    d = left + right + 1.0
+    b1, b2 = BEND[e1], BEND[e2]
+    if b1 is None and b2 is None:
+        segments = []
+    elif b1 is None:
+        segments = [((0.5 * d, 0.0), (d, b2))]
+    elif b2 is None:
+        segments = [((0.0, b1), (0.5 * d, 0.0))]
+    elif b1 != b2:
+        segments = [((0.0, b1), (d, b2))]
+    else:
+        segments = [((0.0, b1), (0.5 * d, 0.0)), ((0.5 * d, 0.0), (d, b2))]
+    up, down = 0.0, 0.0
+    for p1, p2 in segments:
+        u, v = segment_area(p1, p2, float(left))
+        up, down = up + u, down + v
+    # blending both ways would cancel out, so keep the larger side
+    if up > down:
+        return 0.0, up
+    return down, 0.0
+
+
+def bilinear(e):
+    a = e[0] + (e[1] - e[0]) * 0.75
+    b = e[2] + (e[3] - e[2]) * 0.75
+    return a + (b - a) * 0.875
+
+
+def delta_left(left, top):
+    d = 0
+    # an edge, carry on
+    if top[3] == 1:
+        d += 1
+    # another one, with no crossing edges in between
+    if d == 1 and top[2] == 1 and left[1] != 1 and left[3] != 1:
+        d += 1
+    return d
+
+
+def delta_right(left, top):
+    d = 0
+    if top[3] == 1 and left[1] != 1 and left[3] != 1:
+        d += 1
+    if d == 1 and top[2] == 1 and left[0] != 1 and left[2] != 1:
+        d += 1
+    return d
+
+
+def main():
+    with open('area.bin', 'wb') as f:
+        for y in range(5 * MAX_DISTANCE):
+            for x in range(5 * MAX_DISTANCE):
+                e1, e2 = x // MAX_DISTANCE, y // MAX_DISTANCE
+                if e1 == 2 or e2 == 2:
+                    a = (0.0, 0.0)
+                else:
+                    a = area(e1, e2, (x % MAX_DISTANCE) ** 2, (y % MAX_DISTANCE) ** 2)
+                f.write(struct.pack('BB', *(int(round(255.0 * v)) for v in a)))
+
+    patterns = [[(i >> b) & 1 for b in range(4)] for i in range(16)]
+    edges = dict((int(round(32.0 * bilinear(p))), p) for p in patterns)
+    search = bytearray(66 * 33)
+    for y in range(33):
+        for x in range(33):
+            if x in edges and y in edges:
+                search[y * 66 + x] = 127 * delta_left(edges[x], edges[y])
+                search[y * 66 + 33 + x] = 127 * delta_right(edges[x], edges[y])
+    with open('search.bin', 'wb') as f:
+        f.write(search)
+
+
+if __name__ == '__main__':
+    main()
@@ -0,0 +1,345 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of 2D rendering with a sprite batch.
+//
+// Thousands of sprites bounce around the window, each with its own rotation,
+// scale, tint and image from a texture atlas. Every frame they are pushed
+// into a `SpriteBatch`, which expands them into quads on the CPU, uploads all
+// of them into one stream buffer and draws them with a single call.
+//
+// Press Up and Down to double or halve the number of sprites. The window title
+// shows the number of sprites submitted per second.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+extern crate time;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use gfx::traits::*;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// The atlas is a grid of square images
+const ATLAS_COLUMNS: usize = 4;
+const ATLAS_ROWS: usize = 2;
+const TILE_SIZE: usize = 64;
+const MAX_SPRITES: usize = 1 << 18;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct SpriteVertex {
+    #[name = "a_Pos"]
+    pos: [f32; 2],
+    #[name = "a_TexCoord"]
+    tex_coord: [f32; 2],
+    #[name = "a_Color"]
+    color: [f32; 4],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    #[name = "t_Atlas"]
+    atlas: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Proj;
+    in vec2 a_Pos;
+    in vec2 a_TexCoord;
+    in vec4 a_Color;
+    out vec2 v_TexCoord;
+    out vec4 v_Color;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        v_Color = a_Color;
+        gl_Position = u_Proj * vec4(a_Pos, 0.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Atlas;
+    in vec2 v_TexCoord;
+    in vec4 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = texture(t_Atlas, v_TexCoord) * v_Color;
+    }
+";
+
+/// A sprite to draw, in window coordinates.
+#[derive(Clone, Copy)]
+struct Sprite {
+    pos: [f32; 2],
+    size: f32,
+    rotation: f32,
+    color: [f32; 4],
+    tile: usize,
+}
+
+/// Collects sprites into vertices and draws them all at once.
+struct SpriteBatch<R: gfx::Resources> {
+    vertices: Vec<SpriteVertex>,
+    buffer: gfx::BufferHandle<R, SpriteVertex>,
+    capacity: usize,
+}
+
+impl<R: gfx::Resources> SpriteBatch<R> {
+    fn new<F: gfx::Factory<R>>(factory: &mut F, capacity: usize) -> SpriteBatch<R> {
+        SpriteBatch {
+            vertices: Vec::with_capacity(capacity * 6),
+            buffer: factory.create_buffer(capacity * 6, gfx::BufferUsage::Stream),
+            capacity: capacity,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Adds a sprite, unless the batch is full.
+    fn push(&mut self, sprite: &Sprite) {
+        if self.vertices.len() >= self.capacity * 6 {
+            return;
+        }
+        let (s, c) = sprite.rotation.sin_cos();
+        let half = 0.5 * sprite.size;
+        let u = (sprite.tile % ATLAS_COLUMNS) as f32 / ATLAS_COLUMNS as f32;
+        let v = (sprite.tile / ATLAS_COLUMNS) as f32 / ATLAS_ROWS as f32;
+        let (du, dv) = (1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+        let corner = |x: f32, y: f32| SpriteVertex {
+            pos: [sprite.pos[0] + half * (c * x - s * y),
+                  sprite.pos[1] + half * (s * x + c * y)],
+            tex_coord: [u + du * (0.5 + 0.5 * x), v + dv * (0.5 + 0.5 * y)],
+            color: sprite.color,
+        };
+        let quad = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+        self.vertices.extend([0, 1, 2, 2, 3, 0].iter().map(|&i| quad[i]));
+    }
+
+    /// Returns a mesh over the whole vertex buffer.
+    fn mesh(&self) -> gfx::Mesh<R> {
+        gfx::Mesh::from_format(self.buffer.clone(), (self.capacity * 6) as gfx::VertexCount)
+    }
+
+    /// Uploads the collected vertices and returns the slice covering them.
+    fn flush<F: gfx::Factory<R>>(&mut self, factory: &mut F) -> gfx::Slice<R> {
+        factory.update_buffer(&self.buffer, &self.vertices, 0);
+        gfx::Slice {
+            start: 0,
+            end: self.vertices.len() as gfx::VertexCount,
+            prim_type: gfx::PrimitiveType::TriangleList,
+            kind: gfx::SliceKind::Vertex,
+        }
+    }
+}
+
+// Coverage of the white shape drawn in an atlas tile, at a point in [-1, 1]
+fn tile_shape(tile: usize, x: f32, y: f32) -> bool {
+    let r = (x * x + y * y).sqrt();
+    let a = y.atan2(x);
+    match tile {
+        0 => r < 0.9,
+        1 => r < 0.9 && r > 0.6,
+        2 => x.abs() < 0.8 && y.abs() < 0.8,
+        3 => x.abs() + y.abs() < 0.9,
+        4 => y > -0.7 && y < 0.9 - 2.0 * x.abs(),
+        5 => r < 0.5 + 0.4 * (5.0 * a).cos().max(0.0),
+        6 => (x.abs() < 0.25 || y.abs() < 0.25) && x.abs() < 0.9 && y.abs() < 0.9,
+        _ => r < 0.9 && (6.0 * a).sin() > 0.0,
+    }
+}
+
+fn create_atlas_data() -> Vec<u8> {
+    let (width, height) = (ATLAS_COLUMNS * TILE_SIZE, ATLAS_ROWS * TILE_SIZE);
+    let mut data = Vec::with_capacity(width * height * 4);
+    for py in 0 .. height {
+        for px in 0 .. width {
+            let tile = (py / TILE_SIZE) * ATLAS_COLUMNS + px / TILE_SIZE;
+            let x = ((px % TILE_SIZE) as f32 + 0.5) / TILE_SIZE as f32 * 2.0 - 1.0;
+            let y = ((py % TILE_SIZE) as f32 + 0.5) / TILE_SIZE as f32 * 2.0 - 1.0;
+            let alpha = if tile_shape(tile, x, y) { 0xFF } else { 0 };
+            data.extend([0xFF, 0xFF, 0xFF, alpha].iter().cloned());
+        }
+    }
+    data
+}
+
+struct Body {
+    sprite: Sprite,
+    vel: [f32; 2],
+    spin: f32,
+}
+
+fn create_body<R: Rng>(rng: &mut R, (w, h): (f32, f32)) -> Body {
+    let angle = rng.gen::<f32>() * 2.0 * PI;
+    let speed = 50.0 + 150.0 * rng.gen::<f32>();
+    Body {
+        sprite: Sprite {
+            pos: [w * rng.gen::<f32>(), h * rng.gen::<f32>()],
+            size: 8.0 + 24.0 * rng.gen::<f32>(),
+            rotation: 0.0,
+            color: [0.3 + 0.7 * rng.gen::<f32>(),
+                    0.3 + 0.7 * rng.gen::<f32>(),
+                    0.3 + 0.7 * rng.gen::<f32>(),
+                    0.8],
+            tile: rng.gen_range(0, ATLAS_COLUMNS * ATLAS_ROWS),
+        },
+        vel: [speed * angle.cos(), speed * angle.sin()],
+        spin: 4.0 * (rng.gen::<f32>() - 0.5),
+    }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Sprite example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let texture = factory.create_texture_rgba8((ATLAS_COLUMNS * TILE_SIZE) as u16,
+                                               (ATLAS_ROWS * TILE_SIZE) as u16,
+                                               false).unwrap();
+    factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                           &create_atlas_data(), None).unwrap();
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().blend(gfx::BlendPreset::Alpha);
+    // One pixel per unit, with the origin at the bottom left
+    let proj = cgmath::ortho(0.0, w as f32, 0.0, h as f32, -1.0, 1.0);
+
+    let size = (w as f32, h as f32);
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let mut bodies: Vec<Body> = (0 .. 10000).map(|_| create_body(&mut rng, size)).collect();
+    let mut sprite_batch = SpriteBatch::new(&mut factory, MAX_SPRITES);
+
+    let mut batch = {
+        let mesh = sprite_batch.mesh();
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let data = Params {
+            proj: proj.into_fixed(),
+            atlas: (texture, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state)
+               .unwrap()
+    };
+
+    let clear_data = gfx::ClearData {
+        color: [0.1, 0.1, 0.15, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut submitted, mut last_report) = (0usize, time::precise_time_s());
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) => {
+                    let count = bodies.len().min(MAX_SPRITES - bodies.len());
+                    bodies.extend((0 .. count).map(|_| create_body(&mut rng, size)));
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) => {
+                    let count = (bodies.len() / 2).max(1);
+                    bodies.truncate(count);
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            let dt = clock.step();
+            for body in bodies.iter_mut() {
+                let sprite = &mut body.sprite;
+                for k in 0 .. 2 {
+                    sprite.pos[k] += body.vel[k] * dt;
+                    // bounce off the window edges
+                    let limit = [size.0, size.1][k];
+                    if (sprite.pos[k] < 0.0 && body.vel[k] < 0.0) ||
+                       (sprite.pos[k] > limit && body.vel[k] > 0.0) {
+                        body.vel[k] = -body.vel[k];
+                    }
+                }
+                sprite.rotation += body.spin * dt;
+            }
+        }
+
+        sprite_batch.clear();
+        for body in bodies.iter() {
+            sprite_batch.push(&body.sprite);
+        }
+        batch.slice = sprite_batch.flush(&mut factory);
+
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        submitted += bodies.len();
+        let now = time::precise_time_s();
+        if now - last_report >= 1.0 {
+            wrap.window.set_title(&format!("Sprite example with gfx-rs: {} sprites, {:.2}M sprites/s",
+                                           bodies.len(), submitted as f64 / (now - last_report) / 1e6));
+            submitted = 0;
+            last_report = now;
+        }
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a million stars drawn as point sprites.
+//
+// The stars make up a spiral galaxy: a bulge of old, yellowish stars in the
+// middle, and a thin disc of bluer ones gathering along logarithmic spiral
+// arms. Each star is a single vertex with a position, a color and a size,
+// all generated once on the CPU and drawn as points with additive blending.
+// The vertex shader sets the size of every point from the size of its star
+// over its distance, and when that is less than a pixel keeps the point a
+// pixel wide but dims it by the area it lost, so distant stars fade out
+// instead of flickering. The fragment shader turns each square point into a
+// round glow with a soft falloff from its center.
+//
+// gfx-rs doesn't enable point sizes set by the vertex shader, so this
+// example turns them on once with a direct GL call.
+//
+// Drag with the left mouse button to turn around the galaxy, and use the
+// mouse wheel to zoom. Press Up and Down to make the stars larger or
+// smaller, and Space to stop the galaxy from turning.
+//
+// Run with `starfield [count]`, the default is a million stars.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::env;
+use std::f32::consts::PI;
+use std::str::FromStr;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Star {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Color"]
+    color: [f32; 3],
+    #[name = "a_Size"]
+    size: f32,
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_View"]
+    view: [[f32; 4]; 4],
+    #[name = "u_Proj"]
+    proj: [[f32; 4]; 4],
+    // pixels a star of size 1 covers a unit away
+    #[name = "u_PointScale"]
+    point_scale: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_View;
+    uniform mat4 u_Proj;
+    uniform float u_PointScale;
+    in vec3 a_Pos;
+    in vec3 a_Color;
+    in float a_Size;
+    out vec3 v_Color;
+
+    void main() {
+        vec4 pos = u_View * u_Model * vec4(a_Pos, 1.0);
+        float size = u_PointScale * a_Size / max(-pos.z, 1e-3);
+        // below a pixel, keep the brightness it would have had
+        v_Color = a_Color * min(size * size, 1.0);
+        gl_PointSize = clamp(size, 1.0, 64.0);
+        gl_Position = u_Proj * pos;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        vec2 c = gl_PointCoord * 2.0 - 1.0;
+        float r2 = dot(c, c);
+        if (r2 > 1.0) {
+            discard;
+        }
+        // a bright core with a soft halo, reaching zero at the edge
+        float glow = exp(-6.0 * r2) + 0.15 * (1.0 - r2);
+        o_Color = vec4(v_Color * glow, 1.0);
+    }
+";
+
+// Radius of the disc, of the bulge, and the thickness of the disc
+const RADIUS: f32 = 30.0;
+const BULGE: f32 = 4.0;
+const THICKNESS: f32 = 0.6;
+// Spiral arms, and how tightly they wind
+const ARMS: usize = 4;
+const PITCH: f32 = 0.22;
+// Share of the stars in the bulge
+const BULGE_SHARE: f32 = 0.2;
+
+// A sample of the standard normal distribution, by the Box-Muller transform
+fn gaussian<R: Rng>(rng: &mut R) -> f32 {
+    let (u, v) = (rng.gen::<f32>().max(1e-7), rng.gen::<f32>());
+    (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+}
+
+fn generate_stars(count: usize) -> Vec<Star> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    (0 .. count).map(|_| {
+        // most stars are small, a few are much larger
+        let size = 0.02 / (0.05 + rng.gen::<f32>()).powf(1.5);
+        if rng.gen::<f32>() < BULGE_SHARE {
+            let (x, y, z) = (gaussian(&mut rng), gaussian(&mut rng), gaussian(&mut rng));
+            let warm = rng.gen::<f32>();
+            Star {
+                pos: [BULGE * 0.5 * x, BULGE * 0.5 * y, BULGE * 0.3 * z],
+                color: [1.0, 0.8 + 0.1 * warm, 0.55 + 0.2 * warm],
+                size: size,
+            }
+        } else {
+            // denser towards the middle, along one of the arms
+            let r = BULGE * 0.5 + -(1.0 - rng.gen::<f32>() * 0.98).ln() * RADIUS / 4.0;
+            let arm = (rng.gen::<usize>() % ARMS) as f32 * 2.0 * PI / ARMS as f32;
+            let spread = 0.35 * gaussian(&mut rng) * (1.0 - (-r / RADIUS).exp());
+            let angle = arm + (r / BULGE).ln() / PITCH.tan() + spread;
+            let z = THICKNESS * gaussian(&mut rng) * (-r / RADIUS).exp();
+            // young blue stars are in the arms, a few red giants anywhere
+            let color = match rng.gen::<f32>() {
+                t if t < 0.05 => [1.0, 0.45, 0.3],
+                t if t < 0.6 => [0.6, 0.75, 1.0],
+                _ => [0.95, 0.92, 0.85],
+            };
+            Star {
+                pos: [r * angle.cos(), r * angle.sin(), z],
+                color: color,
+                size: size,
+            }
+        }
+    }).collect()
+}
+
+pub fn main() {
+    let count = env::args().nth(1)
+                           .and_then(|s| FromStr::from_str(&s).ok())
+                           .unwrap_or(1_000_000usize);
+
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Starfield example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+    let gl = gl::Gl::load_with(|s| wrap.window.get_proc_address(s));
+    unsafe { gl.Enable(gl::PROGRAM_POINT_SIZE) };
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().blend(gfx::BlendPreset::Add);
+    let aspect = w as f32 / h as f32;
+    let fov = 60.0f32;
+    let proj = cgmath::perspective(cgmath::deg(fov), aspect, 0.1, 1000.0);
+    let pixels = 0.5 * h as f32 / (0.5 * fov * PI / 180.0).tan();
+    let mut batch = {
+        let mesh = factory.create_mesh(&generate_stars(count));
+        let slice = mesh.to_slice(gfx::PrimitiveType::Point);
+        let data = Params {
+            model: Matrix4::identity().into_fixed(),
+            view: Matrix4::identity().into_fixed(),
+            proj: proj.into_fixed(),
+            point_scale: pixels,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 60.0, 0.0, 0.5);
+    let mut star_scale = 1.0f32;
+    let mut turning = true;
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.01, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => star_scale = (star_scale * 1.25).min(10.0),
+                    VirtualKeyCode::Down => star_scale = (star_scale / 1.25).max(0.1),
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.02 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        batch.params.model = Matrix3::from_angle_z(cgmath::rad(a)).to_matrix4().into_fixed();
+        batch.params.view = camera.view().mat.into_fixed();
+        batch.params.point_scale = pixels * star_scale;
+        renderer.clear(clear_data, gfx::COLOR, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Starfield example: {} stars, size {:.2}",
+                                       count, star_scale));
+    }
+}
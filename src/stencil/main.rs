@@ -0,0 +1,447 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of stencil functions and operations.
+//
+// The scene is drawn into a texture with a depth and stencil buffer of its
+// own, in one of three ways:
+//
+//     masking      a disc moving over the screen replaces the stencil with 1,
+//                  without drawing any color, then the scene is drawn lit
+//                  where the stencil is not 1 and with its normals where it
+//                  is, like through a lens
+//     counting     once the scene is drawn, it is drawn again without depth
+//                  test or colors, incrementing the stencil for every
+//                  fragment, which counts the surfaces under each pixel
+//     outlines     the objects replace the stencil with 1 as they are drawn,
+//                  then are drawn again pushed out along their normals, in a
+//                  flat color and only where the stencil is not 1, which
+//                  leaves an outline around them
+//
+// The inset in the corner shows the stencil buffer. It is drawn into a
+// second texture sharing the same depth and stencil buffer, with a full
+// screen quad for every value, each passing the stencil test only where the
+// stencil holds that value.
+//
+// Drag with the left mouse button to turn around the scene, and use the
+// mouse wheel to zoom. Press Tab to switch between the three ways, Up and
+// Down to change the width of the outlines, and I to hide the inset.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx::state::{Comparison, StencilOp};
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 0 to light the color, 1 to show it flat, 2 to show the normals
+    #[name = "u_Style"]
+    style: f32,
+    // how far the surface is pushed out along the normals
+    #[name = "u_Extrude"]
+    extrude: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct OverlayParams<R: gfx::Resources> {
+    // the corners of the quad, in normalized device coordinates
+    #[name = "u_Rect"]
+    rect: [f32; 4],
+    #[name = "u_Color"]
+    color: [f32; 4],
+    // 1 to cut a disc out of the quad
+    #[name = "u_Disc"]
+    disc: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct BlitParams<R: gfx::Resources> {
+    #[name = "u_Rect"]
+    rect: [f32; 4],
+    #[name = "t_Source"]
+    source: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    uniform float u_Extrude;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = normalize(mat3(u_Model) * a_Normal);
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        world.xyz += v_Normal * u_Extrude;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Style;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 color = u_Color;
+        if (u_Style < 0.5) {
+            float diffuse = max(dot(n, normalize(vec3(0.4, 0.3, 0.8))), 0.0);
+            color *= 0.8 * diffuse + 0.2;
+        } else if (u_Style > 1.5) {
+            color = 0.5 + 0.5 * n;
+        }
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+static OVERLAY_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Rect;
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(mix(u_Rect.xy, u_Rect.zw, v_TexCoord), 0.0, 1.0);
+    }
+";
+
+static OVERLAY_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec4 u_Color;
+    uniform float u_Disc;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // a discarded fragment leaves the stencil as it is
+        if (u_Disc > 0.5 && length(v_TexCoord - 0.5) > 0.5) {
+            discard;
+        }
+        o_Color = u_Color;
+    }
+";
+
+static BLIT_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Source;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = texture(t_Source, v_TexCoord);
+    }
+";
+
+static MODES: [&'static str; 3] = ["masking", "counting", "outlines"];
+
+// The color shown in the inset for each stencil value, the last one for
+// that value and above
+const PALETTE: [[f32; 4]; 9] = [
+    [0.0, 0.0, 0.0, 1.0], [0.8, 0.1, 0.1, 1.0], [0.95, 0.5, 0.1, 1.0],
+    [0.95, 0.9, 0.2, 1.0], [0.2, 0.8, 0.2, 1.0], [0.2, 0.8, 0.9, 1.0],
+    [0.2, 0.3, 0.95, 1.0], [0.6, 0.2, 0.9, 1.0], [1.0, 1.0, 1.0, 1.0],
+];
+
+// A stencil test of `fun` against `value`, doing `op` when both it and the
+// depth test pass
+fn stencil_state(state: &gfx::DrawState, fun: Comparison, value: u8, op: StencilOp)
+                 -> gfx::DrawState {
+    let mut state = state.clone().stencil(fun, value);
+    if let Some(ref mut stencil) = state.stencil {
+        stencil.front.op_pass = op;
+        stencil.back.op_pass = op;
+    }
+    state
+}
+
+fn model_matrix(pos: [f32; 3], scale: f32) -> Matrix4<f32> {
+    Matrix4::new(scale, 0.0, 0.0, 0.0,
+                 0.0, scale, 0.0, 0.0,
+                 0.0, 0.0, scale, 0.0,
+                 pos[0], pos[1], pos[2], 1.0)
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Stencil example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    // The scene and the inset have colors of their own, and share the depth
+    // and stencil
+    let scene_tex = factory.create_texture_rgba8(w, h, false).unwrap();
+    let inset_tex = factory.create_texture_rgba8(w, h, false).unwrap();
+    let depth_stencil = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let frames: Vec<_> = [&scene_tex, &inset_tex].iter().map(|&texture| gfx::Frame {
+        colors: vec![Plane::Texture(texture.clone(), 0, None)],
+        depth: Some(Plane::Texture(depth_stencil.clone(), 0, None)),
+        stencil: Some(Plane::Texture(depth_stencil.clone(), 0, None)),
+        .. gfx::Frame::empty(w, h)
+    }).collect();
+    let (scene_frame, inset_frame) = (&frames[0], &frames[1]);
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(Comparison::LessEqual, true);
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.plane(&mut factory), meshes.sphere(&mut factory, 32, 64),
+                      meshes.torus(&mut factory, 64, 32, 0.7, 0.3),
+                      meshes.capsule(&mut factory, 16, 32, 0.5, 0.5)];
+    // mesh index, position, scale and color, the floor first
+    let objects = [
+        (0, [0.0, 0.0, 0.0], 6.0, [0.5, 0.5, 0.5]),
+        (1, [0.0, 0.0, 1.0], 1.0, [0.9, 0.3, 0.2]),
+        (2, [2.5, 1.0, 0.3], 1.0, [0.2, 0.7, 0.3]),
+        (3, [-2.0, 1.5, 1.0], 1.0, [0.2, 0.4, 0.9]),
+        (1, [1.0, -2.5, 0.6], 0.6, [0.9, 0.8, 0.2]),
+        (2, [-2.5, -2.0, 1.2], 0.8, [0.7, 0.3, 0.8]),
+    ];
+    let mut batches: Vec<_> = objects.iter().map(|&(mesh_id, pos, scale, color)| {
+        let (ref mesh, ref slice) = shapes[mesh_id];
+        let data = SceneParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: model_matrix(pos, scale).into_fixed(),
+            color: color,
+            style: 0.0,
+            extrude: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, mesh, slice.clone(), &state).unwrap()
+    }).collect();
+
+    let (quad, quad_slice) = meshes.quad(&mut factory);
+    let mut overlay = {
+        let program = factory.link_program(OVERLAY_VERTEX_SRC, OVERLAY_FRAGMENT_SRC).unwrap();
+        let data = OverlayParams {
+            rect: [-1.0, -1.0, 1.0, 1.0],
+            color: [1.0; 4],
+            disc: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &quad, quad_slice.clone(), &gfx::DrawState::new())
+               .unwrap()
+    };
+    let mut blits: Vec<_> = {
+        let program = factory.link_program(OVERLAY_VERTEX_SRC, BLIT_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        [&scene_tex, &inset_tex].iter().map(|&texture| {
+            let data = BlitParams {
+                rect: [-1.0, -1.0, 1.0, 1.0],
+                source: (texture.clone(), Some(sampler.clone())),
+            };
+            context.make_batch(&program, data, &quad, quad_slice.clone(),
+                               &gfx::DrawState::new()).unwrap()
+        }).collect()
+    };
+    // the inset in the top right corner, with a frame around it
+    blits[1].params.rect = [0.45, 0.45, 0.95, 0.95];
+
+    // sets the stencil to 1, without writing colors or depth
+    let mut mark_state = stencil_state(&gfx::DrawState::new(), Comparison::Always, 1,
+                                       StencilOp::Replace);
+    mark_state.color_mask = gfx::state::ColorMask::empty();
+    let inside_state = stencil_state(&state, Comparison::Equal, 1, StencilOp::Keep);
+    let outside_state = stencil_state(&state, Comparison::NotEqual, 1, StencilOp::Keep);
+    // adds one for every fragment, hidden or not
+    let mut count_state = stencil_state(&gfx::DrawState::new(), Comparison::Always, 0,
+                                        StencilOp::IncrementClamp);
+    count_state.color_mask = gfx::state::ColorMask::empty();
+    let marked_state = stencil_state(&state, Comparison::Always, 1, StencilOp::Replace);
+    let outline_state = stencil_state(&gfx::DrawState::new(), Comparison::NotEqual, 1,
+                                      StencilOp::Keep);
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.5), 10.0, 0.8, 0.6);
+    let mut mode = 0;
+    let mut outline = 0.06f32;
+    let mut show_inset = true;
+
+    let clear_data = gfx::ClearData {
+        color: [0.15, 0.15, 0.18, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let mut time = 0.0f32;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Tab => mode = (mode + 1) % MODES.len(),
+                    VirtualKeyCode::Up => outline = (outline + 0.02).min(0.3),
+                    VirtualKeyCode::Down => outline = (outline - 0.02).max(0.02),
+                    VirtualKeyCode::I => show_inset = !show_inset,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            time = clock.time() as f32;
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        for batch in batches.iter_mut() {
+            batch.params.view_proj = view_proj;
+            batch.params.style = 0.0;
+            batch.params.extrude = 0.0;
+            batch.state = state.clone();
+        }
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH | gfx::STENCIL, scene_frame);
+        match mode {
+            0 => {
+                let (x, y, r) = (0.4 * (0.7 * time).cos(), 0.3 * time.sin(), 0.35);
+                overlay.params.rect = [x - r / aspect, y - r, x + r / aspect, y + r];
+                overlay.params.disc = 1.0;
+                overlay.state = mark_state.clone();
+                renderer.draw(&(&overlay, &context), scene_frame).unwrap();
+                for batch in batches.iter_mut() {
+                    batch.state = outside_state.clone();
+                    renderer.draw(&(&*batch, &context), scene_frame).unwrap();
+                    batch.params.style = 2.0;
+                    batch.state = inside_state.clone();
+                    renderer.draw(&(&*batch, &context), scene_frame).unwrap();
+                }
+            },
+            1 => {
+                for batch in batches.iter() {
+                    renderer.draw(&(batch, &context), scene_frame).unwrap();
+                }
+                for batch in batches.iter_mut() {
+                    batch.state = count_state.clone();
+                    renderer.draw(&(&*batch, &context), scene_frame).unwrap();
+                }
+            },
+            _ => {
+                renderer.draw(&(&batches[0], &context), scene_frame).unwrap();
+                for batch in batches[1 ..].iter_mut() {
+                    batch.state = marked_state.clone();
+                    renderer.draw(&(&*batch, &context), scene_frame).unwrap();
+                }
+                for batch in batches[1 ..].iter_mut() {
+                    batch.params.style = 1.0;
+                    batch.params.extrude = outline;
+                    batch.state = outline_state.clone();
+                    let color = batch.params.color;
+                    batch.params.color = [1.0, 0.75, 0.2];
+                    renderer.draw(&(&*batch, &context), scene_frame).unwrap();
+                    batch.params.color = color;
+                }
+            },
+        }
+
+        // Show every value of the stencil in its own color
+        if show_inset {
+            renderer.clear(clear_data, gfx::COLOR, inset_frame);
+            overlay.params.rect = [-1.0, -1.0, 1.0, 1.0];
+            overlay.params.disc = 0.0;
+            for (value, &color) in PALETTE.iter().enumerate() {
+                // the last color for all the values from there up
+                let fun = if value + 1 == PALETTE.len() { Comparison::LessEqual }
+                          else { Comparison::Equal };
+                overlay.params.color = color;
+                overlay.state = stencil_state(&gfx::DrawState::new(), fun, value as u8,
+                                              StencilOp::Keep);
+                renderer.draw(&(&overlay, &context), inset_frame).unwrap();
+            }
+        }
+
+        renderer.draw(&(&blits[0], &context), &wrap).unwrap();
+        if show_inset {
+            overlay.params.rect = [0.44, 0.44 - 0.01 * aspect, 0.96, 0.96 + 0.01 * aspect];
+            overlay.params.color = [1.0; 4];
+            overlay.state = gfx::DrawState::new();
+            renderer.draw(&(&overlay, &context), &wrap).unwrap();
+            renderer.draw(&(&blits[1], &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Stencil example: {}", MODES[mode]));
+    }
+}
@@ -0,0 +1,419 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of streaming a large texture from the CPU every frame.
+//
+// A plasma is generated on the CPU every frame, like a software renderer or
+// a video decoder would, into a square texture of up to 4096 pixels. The
+// simplest way to get it there is to upload it straight from memory, which
+// makes the driver copy the whole image before the call returns. Writing it
+// into a pixel buffer object instead lets the GPU copy it into the texture
+// on its own time, but mapping that buffer again the next frame has to wait
+// until the previous copy out of it is done. With two buffers, each frame
+// fills one while the other is copied into the texture, and a buffer is only
+// mapped again a whole frame after its copy was queued.
+//
+// The pixels are written in BGRA order, which is what most drivers store
+// internally, so that they don't have to be swizzled on the way. The window
+// title shows the time spent generating and uploading each frame, and how
+// much data goes to the GPU per second.
+//
+// gfx-rs doesn't expose pixel buffer objects yet, so this example talks to GL
+// directly, like the antialiasing example.
+//
+// Press S to switch between uploading from memory, from one buffer and from
+// two buffers, Up and Down to change the size of the texture, and Space to
+// stop the plasma.
+
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate glutin;
+extern crate time;
+
+use gfx_examples::{window, Clock, Thumbnail};
+use gfx_examples::gl_program::{compile_shader, link_program, uniform_location};
+use gl::Gl;
+use gl::types::*;
+use std::f32::consts::PI;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::iter::repeat;
+use time::precise_time_s;
+
+static SIZES: [usize; 4] = [512, 1024, 2048, 4096];
+// how much a new timing counts in the displayed average
+const SMOOTHING: f64 = 0.05;
+
+static VERTEX_SRC: &'static str = "
+    #version 150 core
+
+    uniform vec2 u_Scale;
+    in vec2 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos * u_Scale, 0.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static str = "
+    #version 150 core
+
+    uniform sampler2D t_Plasma;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = texture(t_Plasma, v_TexCoord);
+    }
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Memory,
+    OneBuffer,
+    TwoBuffers,
+}
+
+impl Mode {
+    fn next(self) -> Mode {
+        match self {
+            Mode::Memory => Mode::OneBuffer,
+            Mode::OneBuffer => Mode::TwoBuffers,
+            Mode::TwoBuffers => Mode::Memory,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Memory => "from memory",
+            Mode::OneBuffer => "one buffer",
+            Mode::TwoBuffers => "two buffers",
+        }
+    }
+}
+
+/// The texture and the pixel buffers it is uploaded from, for one size.
+struct Stream {
+    size: usize,
+    texture: GLuint,
+    buffers: [GLuint; 2],
+    // the buffer that was filled last, and is copied into the texture next
+    filled: Option<usize>,
+}
+
+impl Stream {
+    fn new(gl: &Gl, size: usize) -> Stream {
+        let mut texture = 0;
+        let mut buffers = [0; 2];
+        unsafe {
+            gl.GenTextures(1, &mut texture);
+            gl.BindTexture(gl::TEXTURE_2D, texture);
+            gl.TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as GLint, size as GLsizei,
+                          size as GLsizei, 0, gl::BGRA, gl::UNSIGNED_BYTE, ptr::null());
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+
+            gl.GenBuffers(2, buffers.as_mut_ptr());
+            for &buffer in buffers.iter() {
+                gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, buffer);
+                gl.BufferData(gl::PIXEL_UNPACK_BUFFER, (size * size * 4) as GLsizeiptr,
+                              ptr::null(), gl::STREAM_DRAW);
+            }
+            gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+        }
+        Stream {
+            size: size,
+            texture: texture,
+            buffers: buffers,
+            filled: None,
+        }
+    }
+
+    // Copies the buffer filled last into the texture, if there is one
+    fn upload_filled(&mut self, gl: &Gl) {
+        if let Some(index) = self.filled.take() {
+            unsafe {
+                gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, self.buffers[index]);
+                self.upload(gl, ptr::null());
+                gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+            }
+        }
+    }
+
+    // Uploads the whole texture from `data`, which is an offset into the
+    // bound pixel buffer if there is one
+    unsafe fn upload(&self, gl: &Gl, data: *const GLvoid) {
+        gl.BindTexture(gl::TEXTURE_2D, self.texture);
+        gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        gl.TexSubImage2D(gl::TEXTURE_2D, 0, 0, 0, self.size as GLsizei, self.size as GLsizei,
+                         gl::BGRA, gl::UNSIGNED_BYTE, data);
+    }
+
+    fn delete(&self, gl: &Gl) {
+        unsafe {
+            gl.DeleteBuffers(2, self.buffers.as_ptr());
+            gl.DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+/// An old school plasma made of sine waves added together, looked up in
+/// tables to keep it fast enough for 16 million pixels a frame.
+struct Plasma {
+    sine: [u8; 256],
+    // in BGRA order
+    palette: [[u8; 4]; 256],
+    columns: Vec<u8>,
+    rows: Vec<u8>,
+    diagonals: Vec<u8>,
+}
+
+impl Plasma {
+    fn new() -> Plasma {
+        let mut sine = [0u8; 256];
+        let mut palette = [[0u8; 4]; 256];
+        for i in 0 .. 256 {
+            let a = i as f32 / 256.0 * 2.0 * PI;
+            // three of these add up to a full byte
+            sine[i] = (42.0 + 42.0 * a.sin()) as u8;
+            let channel = |phase: f32| (127.5 + 127.5 * (a + phase).sin()) as u8;
+            palette[i] = [channel(4.0), channel(2.0), channel(0.0), 255];
+        }
+        Plasma {
+            sine: sine,
+            palette: palette,
+            columns: Vec::new(),
+            rows: Vec::new(),
+            diagonals: Vec::new(),
+        }
+    }
+
+    // Writes a `size` by `size` image of BGRA pixels at time `t`
+    fn generate(&mut self, pixels: &mut [u8], size: usize, t: f32) {
+        let sine = self.sine;
+        // the waves look the same at every size
+        let step = 512.0 / size as f32;
+        let wave = |i: usize, frequency: f32, speed: f32| {
+            sine[(i as f32 * step * frequency + t * speed) as usize & 255]
+        };
+        self.columns = (0 .. size).map(|x| wave(x, 1.0, 60.0)).collect();
+        self.rows = (0 .. size).map(|y| wave(y, 1.5, 45.0)).collect();
+        self.diagonals = (0 .. 2 * size).map(|d| wave(d, 0.7, 75.0)).collect();
+        let shift = (t * 20.0) as usize;
+
+        for (y, row) in pixels.chunks_mut(size * 4).enumerate() {
+            let r = self.rows[y];
+            let diagonals = &self.diagonals[y ..];
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                let v = self.columns[x] as usize + r as usize + diagonals[x] as usize;
+                let color = self.palette[(v + shift) & 255];
+                pixel[0] = color[0];
+                pixel[1] = color[1];
+                pixel[2] = color[2];
+                pixel[3] = color[3];
+            }
+        }
+    }
+}
+
+fn smooth(average: &mut f64, value: f64) {
+    *average += (value - *average) * SMOOTHING;
+}
+
+pub fn main() {
+    let window = glutin::WindowBuilder::new()
+        .with_title("Streaming example".to_string())
+        .with_dimensions(800, 600)
+        .with_gl(glutin::GlRequest::Latest)
+        .build().unwrap();
+    unsafe { window.make_current() };
+    let gl = Gl::load_with(|s| window.get_proc_address(s));
+
+    let (w, h) = window::physical_size(&window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+
+    let vs = compile_shader(&gl, VERTEX_SRC, gl::VERTEX_SHADER);
+    let fs = compile_shader(&gl, FRAGMENT_SRC, gl::FRAGMENT_SHADER);
+    let program = link_program(&gl, &[vs, fs], &["a_Pos"]);
+    unsafe {
+        gl.UseProgram(program);
+        gl.Uniform1i(uniform_location(&gl, program, "t_Plasma"), 0);
+        // a square in the middle of the window
+        gl.Uniform2f(uniform_location(&gl, program, "u_Scale"),
+                     0.9 * (h as f32 / w as f32).min(1.0), 0.9 * (w as f32 / h as f32).min(1.0));
+    }
+
+    // Two triangles, drawn without indices
+    let (mut vao, mut vbo) = (0, 0);
+    let corners: [f32; 12] = [-1.0, -1.0, 1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, 1.0];
+    unsafe {
+        gl.GenVertexArrays(1, &mut vao);
+        gl.BindVertexArray(vao);
+        gl.GenBuffers(1, &mut vbo);
+        gl.BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl.BufferData(gl::ARRAY_BUFFER, mem::size_of::<[f32; 12]>() as GLsizeiptr,
+                      mem::transmute(&corners[0]), gl::STATIC_DRAW);
+        gl.EnableVertexAttribArray(0);
+        gl.VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+        gl.BindVertexArray(0);
+    }
+
+    let mut size_index = 2;
+    let mut stream = Stream::new(&gl, SIZES[size_index]);
+    let mut pixels: Vec<u8> = repeat(0u8).take(SIZES[SIZES.len() - 1].pow(2) * 4).collect();
+    let mut plasma = Plasma::new();
+
+    let mut mode = Mode::TwoBuffers;
+    let mut moving = true;
+    let (mut generating, mut uploading) = (0.0f64, 0.0f64);
+    let (mut bandwidth, mut bytes, mut since) = (0.0f64, 0usize, precise_time_s());
+    let mut next_buffer = 0;
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let mut t = 0.0f32;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::S => {
+                        mode = mode.next();
+                        stream.filled = None;
+                    },
+                    VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                        let new_index = match key {
+                            VirtualKeyCode::Up => (size_index + 1).min(SIZES.len() - 1),
+                            _ => size_index.saturating_sub(1),
+                        };
+                        if new_index != size_index {
+                            size_index = new_index;
+                            stream.delete(&gl);
+                            stream = Stream::new(&gl, SIZES[size_index]);
+                        }
+                    },
+                    VirtualKeyCode::Space => moving = !moving,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            if moving {
+                t += clock.step();
+            }
+        }
+
+        let size = stream.size;
+        let length = size * size * 4;
+        match mode {
+            Mode::Memory => {
+                let start = precise_time_s();
+                plasma.generate(&mut pixels[.. length], size, t);
+                let generated = precise_time_s();
+                unsafe { stream.upload(&gl, mem::transmute(&pixels[0])) };
+                smooth(&mut generating, (generated - start) * 1000.0);
+                smooth(&mut uploading, (precise_time_s() - generated) * 1000.0);
+            },
+            Mode::OneBuffer | Mode::TwoBuffers => {
+                let start = precise_time_s();
+                // with two buffers, the one filled last frame goes first, so
+                // that its copy can run while the other one is being filled
+                if mode == Mode::TwoBuffers {
+                    stream.upload_filled(&gl);
+                }
+                let buffer = if mode == Mode::TwoBuffers { next_buffer } else { 0 };
+                let data = unsafe {
+                    gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, stream.buffers[buffer]);
+                    // waits until the last copy out of this buffer is done
+                    gl.MapBufferRange(gl::PIXEL_UNPACK_BUFFER, 0, length as GLsizeiptr,
+                                      gl::MAP_WRITE_BIT)
+                };
+                let mapped = precise_time_s();
+                if !data.is_null() {
+                    let pixels = unsafe { slice::from_raw_parts_mut(data as *mut u8, length) };
+                    plasma.generate(pixels, size, t);
+                }
+                let generated = precise_time_s();
+                unsafe {
+                    gl.UnmapBuffer(gl::PIXEL_UNPACK_BUFFER);
+                    gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+                }
+                stream.filled = Some(buffer);
+                if mode == Mode::OneBuffer {
+                    stream.upload_filled(&gl);
+                } else {
+                    next_buffer = 1 - buffer;
+                }
+                smooth(&mut generating, (generated - mapped) * 1000.0);
+                smooth(&mut uploading, (mapped - start + precise_time_s() - generated) * 1000.0);
+            },
+        }
+        bytes += length;
+
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl.Viewport(0, 0, w as GLsizei, h as GLsizei);
+            gl.ClearColor(0.1, 0.1, 0.1, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT);
+            gl.UseProgram(program);
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, stream.texture);
+            gl.BindVertexArray(vao);
+            gl.DrawArrays(gl::TRIANGLES, 0, 6);
+            gl.BindVertexArray(0);
+        }
+
+        let win = &window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        window.swap_buffers();
+
+        let now = precise_time_s();
+        if now - since >= 1.0 {
+            bandwidth = bytes as f64 / (now - since) / (1024.0 * 1024.0);
+            bytes = 0;
+            since = now;
+        }
+        window.set_title(&format!(
+            "Streaming example: {}x{} {}, {:.2} ms generating, {:.2} ms uploading, {:.0} MB/s",
+            size, size, mode.name(), generating, uploading, bandwidth));
+    }
+
+    // Cleanup
+    stream.delete(&gl);
+    unsafe {
+        gl.DeleteBuffers(1, &vbo);
+        gl.DeleteVertexArrays(1, &vao);
+        gl.DeleteProgram(program);
+        gl.DeleteShader(fs);
+        gl.DeleteShader(vs);
+    }
+}
@@ -0,0 +1,535 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of approximating subsurface scattering in screen space.
+//
+// Light entering skin comes out again a little further, and red light much
+// further than blue, which softens the shading and leaves a red glow where
+// light turns into shadow. Here the head is lit as usual, but its diffuse
+// light goes into its own target, with a material ID in the alpha channel,
+// and the specular light into another, since it's reflected at the surface.
+// The diffuse light is then blurred twice, horizontally and vertically, with
+// a wider kernel for red than for green and blue. The kernel is scaled by the
+// distance, so that it covers the same width on the head however far it is,
+// and it only takes samples of the same material that aren't far behind or
+// in front, so the head doesn't bleed into the pedestal or the nose into the
+// cheek behind it. The specular light is added back at the end.
+//
+// The head is loaded from the OBJ file given as the first argument. Without
+// one, a rough stand-in head is made from a sphere.
+//
+// Press S to cycle through scattering, no scattering and the material IDs,
+// Up and Down to change the scattering width, and Space to stop the light.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::env;
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{index, obj, shape, vertex_cache, window, Clock, Thumbnail};
+
+const MODES: [&'static str; 3] = ["scattering", "no scattering", "material IDs"];
+// material IDs, in the alpha channel of the diffuse light
+const OPAQUE: f32 = 0.0;
+const SKIN: f32 = 1.0;
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_CameraPos"]
+    camera_pos: [f32; 3],
+    #[name = "u_LightDir"]
+    light_dir: [f32; 3],
+    #[name = "u_Albedo"]
+    albedo: [f32; 3],
+    #[name = "u_Gloss"]
+    gloss: f32,
+    #[name = "u_Material"]
+    material: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct BlurParams<R: gfx::Resources> {
+    #[name = "t_Source"]
+    source: gfx::shade::TextureParam<R>,
+    #[name = "t_Depth"]
+    depth: gfx::shade::TextureParam<R>,
+    // one texel along the blur
+    #[name = "u_Step"]
+    step: [f32; 2],
+    // of the scattering, in world units
+    #[name = "u_Width"]
+    width: f32,
+    // how many pixels a world unit covers at a distance of one
+    #[name = "u_PixelScale"]
+    pixel_scale: f32,
+    #[name = "u_DepthParams"]
+    depth_params: [f32; 2],
+}
+
+#[shader_param]
+struct CompositeParams<R: gfx::Resources> {
+    #[name = "t_Diffuse"]
+    diffuse: gfx::shade::TextureParam<R>,
+    #[name = "t_Scattered"]
+    scattered: gfx::shade::TextureParam<R>,
+    #[name = "t_Specular"]
+    specular: gfx::shade::TextureParam<R>,
+    #[name = "t_Depth"]
+    depth: gfx::shade::TextureParam<R>,
+    #[name = "u_Mode"]
+    mode: f32,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Pos;
+    out vec3 v_Normal;
+
+    void main() {
+        // the models are only scaled uniformly
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_Pos = (u_Model * vec4(a_Pos, 1.0)).xyz;
+        gl_Position = u_ViewProj * vec4(v_Pos, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const vec3 LIGHT = vec3(1.6, 1.5, 1.4);
+    const vec3 AMBIENT = vec3(0.08, 0.09, 0.12);
+
+    uniform vec3 u_CameraPos;
+    uniform vec3 u_LightDir;
+    uniform vec3 u_Albedo;
+    uniform float u_Gloss;
+    uniform float u_Material;
+    in vec3 v_Pos;
+    in vec3 v_Normal;
+    out vec4 o_Diffuse;
+    out vec4 o_Specular;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 l = normalize(u_LightDir);
+        vec3 v = normalize(u_CameraPos - v_Pos);
+        float lambert = max(dot(n, l), 0.0);
+        vec3 irradiance = LIGHT * lambert + AMBIENT * (0.6 + 0.4 * n.z);
+        o_Diffuse = vec4(u_Albedo * irradiance, u_Material);
+
+        // Blinn-Phong, brighter at grazing angles like an oily skin
+        float fresnel = 0.04 + 0.96 * pow(1.0 - max(dot(n, v), 0.0), 5.0);
+        float spec = pow(max(dot(n, normalize(l + v)), 0.0), 60.0) * step(0.0, lambert);
+        o_Specular = vec4(LIGHT * spec * u_Gloss * (0.3 + fresnel), 1.0);
+    }
+";
+
+static QUAD_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static BLUR_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const int TAPS = 8;
+    // the standard deviations of each color, relative to the width, with
+    // red scattering furthest in skin
+    const vec3 SPREAD = vec3(0.5, 0.22, 0.12);
+
+    uniform sampler2D t_Source;
+    uniform sampler2D t_Depth;
+    uniform vec2 u_Step;
+    uniform float u_Width;
+    uniform float u_PixelScale;
+    uniform vec2 u_DepthParams;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    float view_distance(vec2 tc) {
+        float z = texture(t_Depth, tc).r * 2.0 - 1.0;
+        return u_DepthParams.y / (z + u_DepthParams.x);
+    }
+
+    void main() {
+        vec4 center = texture(t_Source, v_TexCoord);
+        if (center.a == 0.0) {
+            o_Color = center;
+            return;
+        }
+        float d = view_distance(v_TexCoord);
+        vec2 stride = u_Step * u_Width * u_PixelScale / d / float(TAPS);
+
+        vec3 sum = center.rgb, total = vec3(1.0);
+        for (int i = -TAPS; i <= TAPS; ++i) {
+            if (i == 0) continue;
+            vec2 tc = v_TexCoord + stride * float(i);
+            vec4 s = texture(t_Source, tc);
+            // none of it from other materials, or across a gap in depth
+            float gap = abs(view_distance(tc) - d) / u_Width;
+            float keep = float(s.a == center.a) * max(1.0 - gap, 0.0);
+            float x = float(i) / float(TAPS);
+            vec3 weight = exp(-x * x / (2.0 * SPREAD * SPREAD)) * keep;
+            sum += s.rgb * weight;
+            total += weight;
+        }
+        o_Color = vec4(sum / total, center.a);
+    }
+";
+
+static COMPOSITE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const vec3 BACKGROUND = vec3(0.1, 0.1, 0.12);
+
+    uniform sampler2D t_Diffuse;
+    uniform sampler2D t_Scattered;
+    uniform sampler2D t_Specular;
+    uniform sampler2D t_Depth;
+    uniform float u_Mode;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        vec4 diffuse = texture(t_Diffuse, v_TexCoord);
+        vec3 color;
+        if (texture(t_Depth, v_TexCoord).r == 1.0) {
+            color = BACKGROUND;
+        } else if (u_Mode < 0.5) {
+            color = texture(t_Scattered, v_TexCoord).rgb + texture(t_Specular, v_TexCoord).rgb;
+        } else if (u_Mode < 1.5) {
+            color = diffuse.rgb + texture(t_Specular, v_TexCoord).rgb;
+        } else {
+            color = diffuse.a > 0.5 ? vec3(0.9, 0.3, 0.3) : vec3(0.4);
+        }
+        // to the display's gamma
+        o_Color = vec4(pow(clamp(color, 0.0, 1.0), vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// A sphere pushed into the rough shape of a head, facing -Y, with the
+// normals of the new shape
+fn stand_in_head() -> (Vec<shape::Vertex>, Vec<u32>) {
+    let bump = |n: [f32; 3], center: [f32; 3], size: f32| {
+        let d = [n[0] - center[0], n[1] - center[1], n[2] - center[2]];
+        (-(d[0] * d[0] + d[1] * d[1] + d[2] * d[2]) / (size * size)).exp()
+    };
+    let surface = |theta: f32, phi: f32| {
+        let n = [theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()];
+        let r = 1.0 + 0.22 * bump(n, [0.0, -0.95, -0.1], 0.15)
+                    + 0.08 * bump(n, [0.0, -0.85, 0.35], 0.3)
+                    + 0.12 * bump(n, [0.97, 0.0, -0.05], 0.15)
+                    + 0.12 * bump(n, [-0.97, 0.0, -0.05], 0.15)
+                    // the chin
+                    + 0.1 * bump(n, [0.0, -0.7, -0.7], 0.25);
+        // narrower at the jaw than at the skull
+        let jaw = 1.0 - 0.2 * (-n[2]).max(0.0);
+        [n[0] * r * 0.78 * jaw, n[1] * r * 0.9 * jaw, n[2] * r]
+    };
+
+    let (rings, segments) = (96, 192);
+    let mut vertices = Vec::new();
+    for ring in 0 .. rings + 1 {
+        let v = ring as f32 / rings as f32;
+        for segment in 0 .. segments + 1 {
+            let u = segment as f32 / segments as f32;
+            let (theta, phi) = (v * PI, u * 2.0 * PI);
+            let p = surface(theta, phi);
+            // across the rings and along them, keeping clear of the poles
+            let e = 1e-3;
+            let (th, ph) = (theta.max(e).min(PI - e), phi);
+            let (a, b) = (surface(th + e, ph), surface(th - e, ph));
+            let (c, d) = (surface(th, ph + e), surface(th, ph - e));
+            let t1 = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+            let t2 = [c[0] - d[0], c[1] - d[1], c[2] - d[2]];
+            let n = [t1[1] * t2[2] - t1[2] * t2[1],
+                     t1[2] * t2[0] - t1[0] * t2[2],
+                     t1[0] * t2[1] - t1[1] * t2[0]];
+            let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            vertices.push(shape::Vertex {
+                pos: p,
+                normal: [n[0] / length, n[1] / length, n[2] / length],
+                tex_coord: [u, v],
+            });
+        }
+    }
+    let mut indices = Vec::new();
+    let stride = segments + 1;
+    for ring in 0 .. rings {
+        for segment in 0 .. segments {
+            let a = ring * stride + segment;
+            let b = a + stride;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1].iter().cloned());
+        }
+    }
+    (vertices, indices)
+}
+
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+fn create_target<R: gfx::Resources, F: Factory<R>>(factory: &mut F, width: u16, height: u16)
+                 -> gfx::TextureHandle<R> {
+    factory.create_texture(gfx::tex::TextureInfo {
+        width: width,
+        height: height,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA,
+                                        gfx::attrib::FloatSize::F16),
+    }).unwrap()
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Subsurface scattering example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let diffuse = create_target(&mut factory, w, h);
+    let specular = create_target(&mut factory, w, h);
+    let blurred = create_target(&mut factory, w, h);
+    let scattered = create_target(&mut factory, w, h);
+    let depth = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let scene_frame = gfx::Frame {
+        colors: vec![
+            Plane::Texture(diffuse.clone(), 0, None),
+            Plane::Texture(specular.clone(), 0, None),
+        ],
+        depth: Some(Plane::Texture(depth.clone(), 0, None)),
+        .. gfx::Frame::empty(w, h)
+    };
+    let blurred_frame = gfx::Frame {
+        colors: vec![Plane::Texture(blurred.clone(), 0, None)],
+        .. gfx::Frame::empty(w, h)
+    };
+    let scattered_frame = gfx::Frame {
+        colors: vec![Plane::Texture(scattered.clone(), 0, None)],
+        .. gfx::Frame::empty(w, h)
+    };
+
+    let (vertex_data, index_data) = match env::args().nth(1) {
+        Some(path) => obj::load(&path).unwrap_or_else(|e| {
+            println!("{}, making a stand-in head", e);
+            stand_in_head()
+        }),
+        None => stand_in_head(),
+    };
+    // fitted into the unit sphere, whatever the size of the model
+    let (center, radius) = obj::bounds(&vertex_data);
+    let s = 1.0 / radius;
+    let head_model = place(Matrix3::new(s, 0.0, 0.0,
+                                        0.0, s, 0.0,
+                                        0.0, 0.0, s),
+                           -center[0] * s, -center[1] * s, -center[2] * s);
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let scene_params = |model: Matrix4<f32>, albedo, gloss, material| SceneParams {
+        view_proj: Matrix4::identity().into_fixed(),
+        model: model.into_fixed(),
+        camera_pos: [0.0; 3],
+        light_dir: [0.0, 0.0, 1.0],
+        albedo: albedo,
+        gloss: gloss,
+        material: material,
+        _dummy: std::marker::PhantomData,
+    };
+    let head = {
+        let mesh = factory.create_mesh(&vertex_data);
+        let index_data = vertex_cache::optimize(&index_data);
+        let slice = index::slice(&mut factory, &index_data, gfx::PrimitiveType::TriangleList);
+        let data = scene_params(head_model, [0.85, 0.6, 0.5], 1.0, SKIN);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+    let mut meshes = MeshCache::new();
+    let pedestal = {
+        let (mesh, slice) = meshes.cube(&mut factory);
+        let model = place(Matrix3::new(0.5, 0.0, 0.0,
+                                       0.0, 0.5, 0.0,
+                                       0.0, 0.0, 0.5), 0.0, 0.0, -1.45);
+        let data = scene_params(model, [0.5, 0.5, 0.52], 0.2, OPAQUE);
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+    let mut objects = vec![head, pedestal];
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(40.0f32), aspect, 0.1, 50.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, -0.2), 4.0, -PI / 2.0, 0.1);
+
+    let (quad, quad_slice) = meshes.quad(&mut factory);
+    let nearest = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let (mut horizontal, mut vertical) = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, BLUR_FRAGMENT_SRC).unwrap();
+        let mut blur_pass = |source: &gfx::TextureHandle<_>, step| {
+            let data = BlurParams {
+                source: (source.clone(), Some(nearest.clone())),
+                depth: (depth.clone(), Some(nearest.clone())),
+                step: step,
+                width: 0.1,
+                // half the window height over the tangent of half the field
+                // of view
+                pixel_scale: 0.5 * h as f32 * proj.y.y,
+                depth_params: [proj.z.z, proj.w.z],
+            };
+            context.make_batch(&program, data, &quad, quad_slice.clone(),
+                               &gfx::DrawState::new()).unwrap()
+        };
+        (blur_pass(&diffuse, [1.0 / w as f32, 0.0]), blur_pass(&blurred, [0.0, 1.0 / h as f32]))
+    };
+
+    let mut composite = {
+        let program = factory.link_program(QUAD_VERTEX_SRC, COMPOSITE_FRAGMENT_SRC).unwrap();
+        let data = CompositeParams {
+            diffuse: (diffuse.clone(), Some(nearest.clone())),
+            scattered: (scattered.clone(), Some(nearest.clone())),
+            specular: (specular.clone(), Some(nearest.clone())),
+            depth: (depth.clone(), Some(nearest.clone())),
+            mode: 0.0,
+        };
+        context.make_batch(&program, data, &quad, quad_slice.clone(), &gfx::DrawState::new())
+               .unwrap()
+    };
+
+    let clear_data = gfx::ClearData {
+        color: [0.0, 0.0, 0.0, 0.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut mode = 0;
+    let mut width = 0.1f32;
+    let mut moving = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    // from the side, so that the shadow line crosses the face
+    let (mut prev_angle, mut angle) = (-0.3f32, -0.3f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::S => mode = (mode + 1) % MODES.len(),
+                    VirtualKeyCode::Up => width = (width * 1.25).min(0.4),
+                    VirtualKeyCode::Down => width = (width / 1.25).max(0.01),
+                    VirtualKeyCode::Space => moving = !moving,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if moving {
+                angle += 0.5 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+        let light_dir = [a.cos(), -a.sin() - 0.3, 0.5];
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        let camera_pos = camera.position().into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &scene_frame);
+        for batch in objects.iter_mut() {
+            batch.params.view_proj = view_proj;
+            batch.params.camera_pos = camera_pos;
+            batch.params.light_dir = light_dir;
+            renderer.draw(&(&*batch, &context), &scene_frame).unwrap();
+        }
+
+        horizontal.params.width = width;
+        vertical.params.width = width;
+        renderer.draw(&(&horizontal, &context), &blurred_frame).unwrap();
+        renderer.draw(&(&vertical, &context), &scattered_frame).unwrap();
+        composite.params.mode = mode as f32;
+        renderer.draw(&(&composite, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Subsurface scattering example: {}, width {:.3}",
+                                       MODES[mode], width));
+    }
+}
@@ -17,9 +17,9 @@
 
 extern crate cgmath;
 extern crate gfx;
+extern crate gfx_examples;
 extern crate gfx_window_glutin;
 extern crate glutin;
-extern crate time;
 extern crate rand;
 extern crate genmesh;
 extern crate noise;
@@ -30,9 +30,9 @@ use cgmath::FixedArray;
 use cgmath::{Matrix4, Point3, Vector3};
 use cgmath::{Transform, AffineMatrix3};
 use gfx::traits::*;
+use gfx_examples::{capture, window, Clock, Thumbnail};
 use genmesh::{Vertices, Triangulate};
 use genmesh::generators::{Plane, SharedVertex, IndexedPolygon};
-use time::precise_time_s;
 
 use noise::{Seed, perlin2};
 
@@ -108,11 +108,15 @@ fn calculate_color(height: f32) -> [f32; 3] {
 }
 
 pub fn main() {
-    let mut canvas = gfx_window_glutin::init(glutin::Window::new().unwrap())
-                                       .into_canvas();
+    let mut canvas = window::init(gfx_window_glutin::init(glutin::Window::new().unwrap()))
+                     .into_canvas();
     canvas.output.window.set_title("Terrain example");
+    let mut thumbnail = Thumbnail::from_env();
 
-    let rand_seed = rand::thread_rng().gen();
+    let rand_seed = match thumbnail {
+        Some(_) => capture::SEED,
+        None => rand::thread_rng().gen(),
+    };
     let seed = Seed::new(rand_seed);
     let plane = Plane::subdivide(256, 256);
     let vertex_data: Vec<Vertex> = plane.shared_vertex_iter()
@@ -152,6 +156,13 @@ pub fn main() {
     let mut batch = context.make_batch(&program, data, &mesh, slice, &state)
                            .unwrap();
 
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    // Camera angle around the center, at the previous and the latest tick
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
     'main: loop {
         // quit when Esc is pressed.
         for event in canvas.output.window.poll_events() {
@@ -162,9 +173,15 @@ pub fn main() {
             }
         }
 
-        let time = precise_time_s() as f32;
-        let x = time.sin();
-        let y = time.cos();
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            angle = clock.time() as f32;
+        }
+
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+        let x = a.sin();
+        let y = a.cos();
         let view: AffineMatrix3<f32> = Transform::look_at(
             &Point3::new(x * 32.0, y * 32.0, 16.0),
             &Point3::new(0.0, 0.0, 0.0),
@@ -178,6 +195,18 @@ pub fn main() {
             stencil: 0,
         });
         canvas.draw(&(&batch, &context)).unwrap();
+
+        if thumbnail.is_some() {
+            // the frame has to be in the back buffer before `present` swaps it
+            canvas.device.submit(canvas.renderer.as_buffer());
+            canvas.renderer.reset();
+            let win = &canvas.output.window;
+            if let Some(size) = window::physical_size(win) {
+                if thumbnail.as_mut().unwrap().end_frame(|s| win.get_proc_address(s), size) {
+                    break 'main;
+                }
+            }
+        }
         canvas.present();
     }
 }
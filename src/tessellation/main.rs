@@ -0,0 +1,309 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of adaptive terrain tessellation on the GPU.
+//
+// The terrain is a coarse grid of quad patches. The tessellation control
+// shader projects the edges of each patch to the screen and subdivides them
+// in proportion to their length in pixels, so that triangles keep about the
+// same size on screen whatever their distance. The evaluation shader then
+// places the generated vertices on a procedural height field.
+//
+// Tessellation shaders need GL-4.0, which is checked at startup. gfx-rs
+// doesn't support them yet, so this example talks to GL directly, like the
+// "gl" mode of the performance example.
+//
+// Press W to toggle wireframe, Up and Down to change the target size of the
+// triangles in pixels.
+
+extern crate cgmath;
+extern crate gfx_examples;
+extern crate gfx_gl as gl;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Point3, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx_examples::{window, Clock, Thumbnail};
+use gfx_examples::gl_program::{compile_shader, link_program, uniform_location};
+use gl::Gl;
+use gl::types::*;
+use std::mem;
+use std::ptr;
+use std::ffi::CStr;
+
+const PATCHES: usize = 16;
+const PATCH_SIZE: f32 = 16.0;
+
+static VERTEX_SRC: &'static str = "
+    #version 400 core
+
+    in vec2 a_Pos;
+    out vec2 v_Pos;
+
+    void main() {
+        v_Pos = a_Pos;
+    }
+";
+
+static CONTROL_SRC: &'static str = "
+    #version 400 core
+
+    layout(vertices = 4) out;
+
+    uniform mat4 u_ViewProj;
+    uniform vec2 u_Viewport;
+    uniform float u_PixelsPerEdge;
+    in vec2 v_Pos[];
+    out vec2 tc_Pos[];
+
+    vec2 to_screen(vec2 pos) {
+        vec4 clip = u_ViewProj * vec4(pos, 0.0, 1.0);
+        // clamp behind the camera, where the projection flips
+        return clip.xy / max(clip.w, 0.1) * 0.5 * u_Viewport;
+    }
+
+    float edge_level(vec2 a, vec2 b) {
+        float pixels = distance(to_screen(a), to_screen(b));
+        return clamp(pixels / u_PixelsPerEdge, 1.0, 64.0);
+    }
+
+    void main() {
+        tc_Pos[gl_InvocationID] = v_Pos[gl_InvocationID];
+        if (gl_InvocationID == 0) {
+            // the outer levels follow the edges of the patch, shared with
+            // the neighbours, so that no cracks open between them
+            gl_TessLevelOuter[0] = edge_level(v_Pos[3], v_Pos[0]);
+            gl_TessLevelOuter[1] = edge_level(v_Pos[0], v_Pos[1]);
+            gl_TessLevelOuter[2] = edge_level(v_Pos[1], v_Pos[2]);
+            gl_TessLevelOuter[3] = edge_level(v_Pos[2], v_Pos[3]);
+            float inner = max(max(gl_TessLevelOuter[0], gl_TessLevelOuter[1]),
+                              max(gl_TessLevelOuter[2], gl_TessLevelOuter[3]));
+            gl_TessLevelInner[0] = inner;
+            gl_TessLevelInner[1] = inner;
+        }
+    }
+";
+
+static EVALUATION_SRC: &'static str = "
+    #version 400 core
+
+    layout(quads, fractional_odd_spacing, ccw) in;
+
+    uniform mat4 u_ViewProj;
+    in vec2 tc_Pos[];
+    out vec3 te_Normal;
+    out float te_Height;
+
+    float hash(vec2 p) {
+        return fract(sin(dot(p, vec2(127.1, 311.7))) * 43758.5453);
+    }
+
+    float noise(vec2 p) {
+        vec2 i = floor(p);
+        vec2 f = fract(p);
+        vec2 u = f * f * (3.0 - 2.0 * f);
+        return mix(mix(hash(i), hash(i + vec2(1.0, 0.0)), u.x),
+                   mix(hash(i + vec2(0.0, 1.0)), hash(i + vec2(1.0, 1.0)), u.x), u.y);
+    }
+
+    float height(vec2 p) {
+        float h = 0.0;
+        float amplitude = 12.0;
+        p *= 0.02;
+        for (int i = 0; i < 6; ++i) {
+            h += amplitude * noise(p);
+            amplitude *= 0.45;
+            p *= 2.1;
+        }
+        return h;
+    }
+
+    void main() {
+        vec2 p = mix(mix(tc_Pos[0], tc_Pos[1], gl_TessCoord.x),
+                     mix(tc_Pos[3], tc_Pos[2], gl_TessCoord.x), gl_TessCoord.y);
+        float h = height(p);
+        float e = 0.2;
+        te_Normal = normalize(vec3(height(p - vec2(e, 0.0)) - height(p + vec2(e, 0.0)),
+                                   height(p - vec2(0.0, e)) - height(p + vec2(0.0, e)),
+                                   2.0 * e));
+        te_Height = h;
+        gl_Position = u_ViewProj * vec4(p, h, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static str = "
+    #version 400 core
+
+    in vec3 te_Normal;
+    in float te_Height;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(te_Normal);
+        vec3 color = mix(vec3(0.2, 0.45, 0.15), vec3(0.45, 0.4, 0.35), smoothstep(6.0, 12.0, te_Height));
+        color = mix(color, vec3(0.95), smoothstep(15.0, 17.0, te_Height));
+        // steep slopes are rock
+        color = mix(vec3(0.4, 0.38, 0.35), color, smoothstep(0.6, 0.8, n.z));
+        float d = max(0.0, dot(n, normalize(vec3(0.5, 0.3, 0.8))));
+        o_Color = vec4(color * (0.25 + 0.75 * d), 1.0);
+    }
+";
+
+// Corners of every patch, counter-clockwise
+fn create_patches() -> Vec<[f32; 2]> {
+    let offset = 0.5 * PATCHES as f32 * PATCH_SIZE;
+    let mut vertices = Vec::new();
+    for y in 0 .. PATCHES {
+        for x in 0 .. PATCHES {
+            let (x0, y0) = (x as f32 * PATCH_SIZE - offset, y as f32 * PATCH_SIZE - offset);
+            let (x1, y1) = (x0 + PATCH_SIZE, y0 + PATCH_SIZE);
+            vertices.extend([[x0, y0], [x1, y0], [x1, y1], [x0, y1]].iter().cloned());
+        }
+    }
+    vertices
+}
+
+pub fn main() {
+    let window = glutin::WindowBuilder::new()
+        .with_title("Tessellation example".to_string())
+        .with_dimensions(800, 600)
+        .with_gl(glutin::GlRequest::Latest)
+        .build().unwrap();
+    unsafe { window.make_current() };
+    let gl = Gl::load_with(|s| window.get_proc_address(s));
+
+    // Check the capability before touching any tessellation state
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl.GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl.GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    if major < 4 {
+        let version = unsafe { CStr::from_ptr(gl.GetString(gl::VERSION) as *const i8) };
+        println!("Tessellation shaders need GL-4.0, this context is {}.{} ({})",
+                 major, minor, String::from_utf8_lossy(version.to_bytes()));
+        return;
+    }
+
+    let (w, h) = window::physical_size(&window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+
+    let shaders = [
+        compile_shader(&gl, VERTEX_SRC, gl::VERTEX_SHADER),
+        compile_shader(&gl, CONTROL_SRC, gl::TESS_CONTROL_SHADER),
+        compile_shader(&gl, EVALUATION_SRC, gl::TESS_EVALUATION_SHADER),
+        compile_shader(&gl, FRAGMENT_SRC, gl::FRAGMENT_SHADER),
+    ];
+    let program = link_program(&gl, &shaders, &["a_Pos"]);
+    let u_view_proj = uniform_location(&gl, program, "u_ViewProj");
+    let u_viewport = uniform_location(&gl, program, "u_Viewport");
+    let u_pixels_per_edge = uniform_location(&gl, program, "u_PixelsPerEdge");
+
+    let patches = create_patches();
+    let mut vao = 0;
+    let mut vbo = 0;
+    unsafe {
+        gl.GenVertexArrays(1, &mut vao);
+        gl.BindVertexArray(vao);
+        gl.GenBuffers(1, &mut vbo);
+        gl.BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl.BufferData(gl::ARRAY_BUFFER,
+                      (patches.len() * mem::size_of::<[f32; 2]>()) as GLsizeiptr,
+                      mem::transmute(&patches[0]),
+                      gl::STATIC_DRAW);
+        gl.EnableVertexAttribArray(0);
+        gl.VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+        gl.PatchParameteri(gl::PATCH_VERTICES, 4);
+    }
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.5, 500.0);
+
+    let mut wireframe = false;
+    let mut pixels_per_edge = 8.0f32;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::W)) =>
+                    wireframe = !wireframe,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Up)) =>
+                    pixels_per_edge = (pixels_per_edge * 2.0).min(64.0),
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Down)) =>
+                    pixels_per_edge = (pixels_per_edge * 0.5).max(1.0),
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            angle = 0.05 * clock.time() as f32;
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        // Circle low over the terrain, looking at its center
+        let view: AffineMatrix3<f32> = Transform::look_at(
+            &Point3::new(90.0 * a.cos(), 90.0 * a.sin(), 35.0),
+            &Point3::new(0.0, 0.0, 5.0),
+            &Vector3::unit_z(),
+        );
+        let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+        unsafe {
+            gl.Viewport(0, 0, w as GLsizei, h as GLsizei);
+            gl.ClearColor(0.6, 0.7, 0.85, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            gl.Enable(gl::DEPTH_TEST);
+            gl.PolygonMode(gl::FRONT_AND_BACK, if wireframe { gl::LINE } else { gl::FILL });
+
+            gl.UseProgram(program);
+            gl.UniformMatrix4fv(u_view_proj, 1, gl::FALSE, &view_proj[0][0]);
+            gl.Uniform2f(u_viewport, w as f32, h as f32);
+            gl.Uniform1f(u_pixels_per_edge, pixels_per_edge);
+            gl.BindVertexArray(vao);
+            gl.DrawArrays(gl::PATCHES, 0, patches.len() as GLsizei);
+            gl.PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+        }
+
+        let win = &window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        window.swap_buffers();
+        window.set_title(&format!("Tessellation example: {} pixels per edge", pixels_per_edge));
+    }
+
+    // Cleanup
+    unsafe {
+        gl.DeleteProgram(program);
+        for &shader in shaders.iter() {
+            gl.DeleteShader(shader);
+        }
+        gl.DeleteBuffers(1, &vbo);
+        gl.DeleteVertexArrays(1, &vao);
+    }
+}
@@ -0,0 +1,415 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Text rendering from a glyph atlas.
+//!
+//! A `Font` rasterizes the printable ASCII characters of a TrueType font with
+//! FreeType at a fixed pixel size, packing them into a single texture. A
+//! `TextRenderer` lays out spans of colored text on the CPU, with kerning and
+//! word wrapping, and draws all of it with one call, in window coordinates
+//! with the origin at the top left.
+//!
+//! ```ignore
+//! let font = text::Font::load("DejaVuSans.ttf", 18).unwrap();
+//! let mut text = text::TextRenderer::new(&mut factory, font, 1024);
+//! text.add(&[text::Span::new("Hello", text::WHITE)], [10.0, 10.0], None);
+//! text.draw(&mut factory, &mut renderer, &wrap);
+//! ```
+
+use std::collections::HashMap;
+use std::cmp;
+use std::f32;
+use std::path::Path;
+use freetype;
+use gfx;
+use gfx::traits::*;
+
+const FIRST_CHAR: u8 = 32;
+const LAST_CHAR: u8 = 126;
+const ATLAS_WIDTH: usize = 512;
+
+static DEFAULT_PATHS: &'static [&'static str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+pub const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+pub struct Vertex {
+    #[name = "a_Pos"]
+    pub pos: [f32; 2],
+    #[name = "a_TexCoord"]
+    pub tex_coord: [f32; 2],
+    #[name = "a_Color"]
+    pub color: [f32; 4],
+}
+
+#[shader_param]
+pub struct Params<R: gfx::Resources> {
+    #[name = "u_Proj"]
+    pub proj: [[f32; 4]; 4],
+    #[name = "t_Atlas"]
+    pub atlas: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Proj;
+    in vec2 a_Pos;
+    in vec2 a_TexCoord;
+    in vec4 a_Color;
+    out vec2 v_TexCoord;
+    out vec4 v_Color;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        v_Color = a_Color;
+        gl_Position = u_Proj * vec4(a_Pos, 0.0, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Atlas;
+    in vec2 v_TexCoord;
+    in vec4 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = vec4(v_Color.rgb, v_Color.a * texture(t_Atlas, v_TexCoord).a);
+    }
+";
+
+/// A run of text sharing a color.
+#[derive(Clone, Copy)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub color: [f32; 4],
+}
+
+impl<'a> Span<'a> {
+    pub fn new(text: &'a str, color: [f32; 4]) -> Span<'a> {
+        Span { text: text, color: color }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Glyph {
+    advance: f32,
+    // offset from the pen position on the baseline to the top left corner
+    offset: [f32; 2],
+    size: [f32; 2],
+    // top left and bottom right corners in the atlas
+    tex_coords: [f32; 4],
+}
+
+/// A font rasterized into a glyph atlas.
+pub struct Font {
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+    use_kerning: bool,
+    ascent: f32,
+    line_height: f32,
+    atlas_size: (usize, usize),
+    atlas: Vec<u8>,
+}
+
+impl Font {
+    /// Rasterizes the font at `path` with glyphs `pixel_size` pixels high.
+    pub fn load(path: &str, pixel_size: u32) -> Result<Font, String> {
+        let library = try!(freetype::Library::init()
+                                     .map_err(|e| format!("FreeType: {:?}", e)));
+        let face = try!(library.new_face(path, 0)
+                               .map_err(|e| format!("{}: {:?}", path, e)));
+        try!(face.set_pixel_sizes(0, pixel_size)
+                 .map_err(|e| format!("{}: {:?}", path, e)));
+
+        let chars: Vec<char> = (FIRST_CHAR .. LAST_CHAR + 1).map(|c| c as char).collect();
+
+        // Rasterize every glyph, then pack them in rows
+        let mut bitmaps = Vec::new();
+        for &c in chars.iter() {
+            try!(face.load_char(c as usize, freetype::face::RENDER)
+                     .map_err(|e| format!("{}: {:?}", path, e)));
+            let glyph = face.glyph();
+            let bitmap = glyph.bitmap();
+            let (width, rows, pitch) = (bitmap.width() as usize, bitmap.rows() as usize,
+                                        bitmap.pitch() as usize);
+            let pixels: Vec<u8> = (0 .. rows).flat_map(|y| {
+                bitmap.buffer()[y * pitch .. y * pitch + width].iter().cloned()
+            }).collect();
+            bitmaps.push((c, width, rows, pixels, glyph.bitmap_left(), glyph.bitmap_top(),
+                          (glyph.advance().x >> 6) as f32));
+        }
+
+        let mut placements = Vec::new();
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for &(_, width, rows, _, _, _, _) in bitmaps.iter() {
+            // leave a pixel between glyphs, so that filtering doesn't bleed
+            if x + width + 1 > ATLAS_WIDTH {
+                x = 0;
+                y += row_height + 1;
+                row_height = 0;
+            }
+            placements.push((x, y));
+            x += width + 1;
+            row_height = cmp::max(row_height, rows);
+        }
+        let atlas_height = (y + row_height + 1).next_power_of_two();
+
+        let mut atlas = vec![0u8; ATLAS_WIDTH * atlas_height * 4];
+        let mut glyphs = HashMap::new();
+        let (aw, ah) = (ATLAS_WIDTH as f32, atlas_height as f32);
+        for (&(c, width, rows, ref pixels, left, top, advance), &(x, y)) in
+                bitmaps.iter().zip(placements.iter()) {
+            for row in 0 .. rows {
+                for col in 0 .. width {
+                    let i = ((y + row) * ATLAS_WIDTH + x + col) * 4;
+                    atlas[i] = 0xFF;
+                    atlas[i + 1] = 0xFF;
+                    atlas[i + 2] = 0xFF;
+                    atlas[i + 3] = pixels[row * width + col];
+                }
+            }
+            glyphs.insert(c, Glyph {
+                advance: advance,
+                offset: [left as f32, -top as f32],
+                size: [width as f32, rows as f32],
+                tex_coords: [x as f32 / aw, y as f32 / ah,
+                             (x + width) as f32 / aw, (y + rows) as f32 / ah],
+            });
+        }
+
+        let mut kerning = HashMap::new();
+        for &left in chars.iter() {
+            for &right in chars.iter() {
+                let delta = face.get_kerning(face.get_char_index(left as usize),
+                                             face.get_char_index(right as usize),
+                                             freetype::face::KerningMode::KerningDefault);
+                if let Ok(delta) = delta {
+                    if delta.x != 0 {
+                        kerning.insert((left, right), (delta.x >> 6) as f32);
+                    }
+                }
+            }
+        }
+
+        let (ascent, line_height) = match face.size_metrics() {
+            Some(metrics) => ((metrics.ascender >> 6) as f32, (metrics.height >> 6) as f32),
+            None => (pixel_size as f32, 1.2 * pixel_size as f32),
+        };
+
+        Ok(Font {
+            glyphs: glyphs,
+            kerning: kerning,
+            use_kerning: true,
+            ascent: ascent,
+            line_height: line_height,
+            atlas_size: (ATLAS_WIDTH, atlas_height),
+            atlas: atlas,
+        })
+    }
+
+    /// Loads the first font found in some common system locations.
+    pub fn load_default(pixel_size: u32) -> Result<Font, String> {
+        match DEFAULT_PATHS.iter().find(|p| Path::new(p).exists()) {
+            Some(path) => Font::load(path, pixel_size),
+            None => Err("no default font found".to_string()),
+        }
+    }
+
+    /// Returns the distance between two baselines.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Enables or disables the kerning between pairs of characters.
+    pub fn set_kerning(&mut self, enabled: bool) {
+        self.use_kerning = enabled;
+    }
+
+    fn kerning(&self, left: Option<char>, right: char) -> f32 {
+        match left {
+            Some(left) if self.use_kerning =>
+                self.kerning.get(&(left, right)).cloned().unwrap_or(0.0),
+            _ => 0.0,
+        }
+    }
+
+    fn word_width(&self, word: &[(char, [f32; 4])], prev: Option<char>) -> f32 {
+        let mut width = 0.0;
+        let mut prev = prev;
+        for &(c, _) in word.iter() {
+            if let Some(glyph) = self.glyphs.get(&c) {
+                width += self.kerning(prev, c) + glyph.advance;
+            }
+            prev = Some(c);
+        }
+        width
+    }
+
+    /// Lays out the spans starting with the top left corner at `origin`,
+    /// appending two triangles per glyph to `vertices`. Lines are broken at
+    /// newlines, and between words that would go past `wrap_width`. Returns
+    /// the position of the top of the line after the text.
+    pub fn layout(&self, spans: &[Span], origin: [f32; 2], wrap_width: Option<f32>,
+                  vertices: &mut Vec<Vertex>) -> f32 {
+        let chars: Vec<(char, [f32; 4])> = spans.iter().flat_map(|span| {
+            span.text.chars().map(move |c| (c, span.color))
+        }).collect();
+        let max_x = origin[0] + wrap_width.unwrap_or(f32::INFINITY);
+        let (mut x, mut y) = (origin[0], origin[1] + self.ascent);
+        let mut prev = None;
+
+        let mut start = 0;
+        while start < chars.len() {
+            let (c, _) = chars[start];
+            if c == '\n' {
+                x = origin[0];
+                y += self.line_height;
+                prev = None;
+                start += 1;
+                continue;
+            }
+            if c.is_whitespace() {
+                // spaces at the start of a wrapped line are dropped
+                if x > origin[0] {
+                    x += self.word_width(&chars[start .. start + 1], prev);
+                    prev = Some(c);
+                }
+                start += 1;
+                continue;
+            }
+
+            let end = chars[start ..].iter().position(|&(c, _)| c.is_whitespace())
+                                     .map_or(chars.len(), |i| start + i);
+            let word = &chars[start .. end];
+            if x > origin[0] && x + self.word_width(word, prev) > max_x {
+                x = origin[0];
+                y += self.line_height;
+                prev = None;
+            }
+            for &(c, color) in word.iter() {
+                let glyph = match self.glyphs.get(&c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                x += self.kerning(prev, c);
+                let (x0, y0) = (x + glyph.offset[0], y + glyph.offset[1]);
+                let (x1, y1) = (x0 + glyph.size[0], y0 + glyph.size[1]);
+                let t = &glyph.tex_coords;
+                let (u0, v0, u1, v1) = (t[0], t[1], t[2], t[3]);
+                let corner = |x, y, u, v| Vertex { pos: [x, y], tex_coord: [u, v], color: color };
+                let quad = [corner(x0, y0, u0, v0), corner(x1, y0, u1, v0),
+                            corner(x1, y1, u1, v1), corner(x0, y1, u0, v1)];
+                vertices.extend([0, 1, 2, 2, 3, 0].iter().map(|&i| quad[i]));
+                x += glyph.advance;
+                prev = Some(c);
+            }
+            start = end;
+        }
+        y - self.ascent + self.line_height
+    }
+}
+
+/// Draws text laid out with a `Font`.
+pub struct TextRenderer<R: gfx::Resources> {
+    font: Font,
+    vertices: Vec<Vertex>,
+    buffer: gfx::BufferHandle<R, Vertex>,
+    capacity: usize,
+    batch: gfx::batch::OwnedBatch<Params<R>>,
+}
+
+impl<R: gfx::Resources> TextRenderer<R> {
+    /// Uploads the glyph atlas of `font`, and makes room for drawing up to
+    /// `capacity` glyphs at once.
+    pub fn new<F: gfx::Factory<R>>(factory: &mut F, font: Font, capacity: usize)
+               -> TextRenderer<R> {
+        let (width, height) = font.atlas_size;
+        let texture = factory.create_texture_rgba8(width as u16, height as u16, false)
+                             .unwrap();
+        factory.update_texture(&texture, &texture.get_info().to_image_info(),
+                               &font.atlas, None).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+
+        let buffer = factory.create_buffer(capacity * 6, gfx::BufferUsage::Stream);
+        let mesh = gfx::Mesh::from_format(buffer.clone(), (capacity * 6) as gfx::VertexCount);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let data = Params {
+            proj: [[0.0; 4]; 4],
+            atlas: (texture, Some(sampler)),
+        };
+        let mut batch = gfx::batch::OwnedBatch::new(mesh, program, data).unwrap();
+        batch.state = gfx::DrawState::new().blend(gfx::BlendPreset::Alpha);
+
+        TextRenderer {
+            font: font,
+            vertices: Vec::with_capacity(capacity * 6),
+            buffer: buffer,
+            capacity: capacity,
+            batch: batch,
+        }
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    pub fn font_mut(&mut self) -> &mut Font {
+        &mut self.font
+    }
+
+    /// Queues the spans for drawing, see `Font::layout`.
+    pub fn add(&mut self, spans: &[Span], origin: [f32; 2], wrap_width: Option<f32>) -> f32 {
+        self.font.layout(spans, origin, wrap_width, &mut self.vertices)
+    }
+
+    /// Draws the queued text on top of `output`, and clears the queue.
+    /// Glyphs past the capacity are dropped.
+    pub fn draw<F, C, O>(&mut self, factory: &mut F, renderer: &mut gfx::Renderer<R, C>,
+                         output: &O)
+               where F: gfx::Factory<R>, C: gfx::CommandBuffer<R>, O: gfx::Output<R> {
+        self.vertices.truncate(self.capacity * 6);
+        factory.update_buffer(&self.buffer, &self.vertices, 0);
+
+        let (w, h) = output.get_size();
+        let (w, h) = (w as f32, h as f32);
+        // cgmath::ortho(0, w, h, 0, -1, 1), flipping Y so that it points down
+        self.batch.params.proj = [
+            [2.0 / w, 0.0, 0.0, 0.0],
+            [0.0, -2.0 / h, 0.0, 0.0],
+            [0.0, 0.0, -1.0, 0.0],
+            [-1.0, 1.0, 0.0, 1.0],
+        ];
+        self.batch.slice = gfx::Slice {
+            start: 0,
+            end: self.vertices.len() as gfx::VertexCount,
+            prim_type: gfx::PrimitiveType::TriangleList,
+            kind: gfx::SliceKind::Vertex,
+        };
+        renderer.draw(&self.batch, output).unwrap();
+        self.vertices.clear();
+    }
+}
@@ -0,0 +1,322 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a third person camera following a character.
+//
+// The character is a capsule standing on a `walk::Walker`, which walks over
+// a Perlin noise terrain among pillars and walls. It moves relative to the
+// camera and turns smoothly to face where it goes. The camera is a
+// `FollowCamera` on a spring arm behind the character: the arm is cast
+// against the same ground the character walks on, pillars and walls
+// included, and pulls in at once to stay in front of whatever would come
+// between the camera and the character, then springs back out once the view
+// is clear.
+//
+// Drag with the left mouse button to turn the camera around the character,
+// and use the mouse wheel to lengthen or shorten the arm. Press W, A, S and
+// D to walk, Space to jump, and hold Left Shift to run.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate noise;
+extern crate rand;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point, Point3, Vector, Vector2, Vector3, Vector4};
+use cgmath::{EuclideanVector, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::FollowCamera;
+use gfx_examples::heightfield::Heightfield;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::walk::Walker;
+use gfx_examples::{capture, window, Clock, Thumbnail};
+use noise::{Seed, perlin2};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    #[name = "u_Eye"]
+    eye: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        v_World = (u_Model * vec4(a_Pos, 1.0)).xyz;
+        v_Normal = transpose(inverse(mat3(u_Model))) * a_Normal;
+        gl_Position = u_ViewProj * vec4(v_World, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const vec3 SKY = vec3(0.6, 0.72, 0.88);
+
+    uniform vec3 u_Color;
+    uniform vec3 u_Eye;
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.4, 0.3, 0.8))), 0.0);
+        vec3 color = u_Color * (vec3(1.0, 0.95, 0.85) * diffuse + 0.35 * SKY);
+        float fog = 1.0 - exp(-0.008 * length(v_World - u_Eye));
+        o_Color = vec4(pow(mix(color, SKY, fog), vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// Radius of the capsule, and half the length of its straight part, so it
+// stands 1.8 high
+const RADIUS: f32 = 0.35;
+const HALF_LENGTH: f32 = 0.55;
+// Height above the feet the camera looks at
+const TARGET_HEIGHT: f32 = 1.5;
+// How fast the character turns to where it walks, per second
+const TURN_RATE: f32 = 10.0;
+
+// Gentle hills
+fn terrain_height(seed: &Seed, x: f32, y: f32) -> f32 {
+    6.0 * perlin2(seed, &[x / 40.0, y / 40.0]) + perlin2(seed, &[x / 9.0, y / 9.0])
+}
+
+// A box standing on the terrain, from its corners in x and y, and the height
+// of its top
+struct Block {
+    min: [f32; 2],
+    max: [f32; 2],
+    top: f32,
+}
+
+impl Block {
+    fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.min[0] && x <= self.max[0] && y >= self.min[1] && y <= self.max[1]
+    }
+}
+
+// A ring of pillars around the start, and walls across it to walk behind
+fn blocks(terrain: &Heightfield) -> Vec<Block> {
+    let block = |x: f32, y: f32, half_x: f32, half_y: f32, height: f32| Block {
+        min: [x - half_x, y - half_y],
+        max: [x + half_x, y + half_y],
+        top: terrain.height(x, y) + height,
+    };
+    let mut blocks: Vec<_> = (0 .. 8).map(|i| {
+        let a = i as f32 * PI / 4.0;
+        block(9.0 * a.cos(), 9.0 * a.sin(), 0.6, 0.6, 5.0)
+    }).collect();
+    blocks.push(block(0.0, -15.0, 4.0, 0.4, 3.5));
+    blocks.push(block(15.0, 3.0, 0.4, 5.0, 3.5));
+    blocks.push(block(-14.0, 10.0, 3.0, 3.0, 2.0));
+    blocks
+}
+
+// Moves an angle by whole turns to between -PI and PI
+fn wrap_angle(a: f32) -> f32 {
+    let r = (a + PI) % (2.0 * PI);
+    if r < 0.0 { r + PI } else { r - PI }
+}
+
+// Scales by `scale`, turns by `facing` around Z, and moves to `position`
+fn model(position: Point3<f32>, facing: f32, scale: [f32; 3]) -> Matrix4<f32> {
+    let scale = Matrix3::new(scale[0], 0.0, 0.0, 0.0, scale[1], 0.0, 0.0, 0.0, scale[2]);
+    let mut model = Matrix3::from_angle_z(cgmath::rad(facing)).mul_m(&scale).to_matrix4();
+    model.w = Vector4::new(position.x, position.y, position.z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Third person example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let seed = Seed::new(match thumbnail {
+        Some(_) => capture::SEED,
+        None => rand::thread_rng().gen(),
+    });
+    let terrain = Heightfield::new(256, 1.0, |x, y| terrain_height(&seed, x, y));
+    let blocks = blocks(&terrain);
+    let ground = |x: f32, y: f32| {
+        blocks.iter().filter(|b| b.contains(x, y))
+              .fold(terrain.height(x, y), |h, b| h.max(b.top))
+    };
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let data = |color: [f32; 3]| Params {
+        view_proj: Matrix4::identity().into_fixed(),
+        model: Matrix4::identity().into_fixed(),
+        color: color,
+        eye: [0.0; 3],
+        _dummy: std::marker::PhantomData,
+    };
+    let mut terrain_batch = {
+        let (vertex_data, index_data) = terrain.mesh();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        context.make_batch(&program, data([0.25, 0.45, 0.15]), &mesh, slice, &state).unwrap()
+    };
+    let mut meshes = MeshCache::new();
+    let mut character = {
+        let (mesh, slice) = meshes.capsule(&mut factory, 16, 24, RADIUS, HALF_LENGTH);
+        context.make_batch(&program, data([0.8, 0.3, 0.2]), &mesh, slice, &state).unwrap()
+    };
+    let mut cube = {
+        let (mesh, slice) = meshes.cube(&mut factory);
+        context.make_batch(&program, data([0.6, 0.58, 0.55]), &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.05, 400.0);
+    let mut walker = Walker::new(Point3::new(0.0, 0.0, ground(0.0, 0.0)));
+    let target = |walker: &Walker| walker.position.add_v(&Vector3::new(0.0, 0.0, TARGET_HEIGHT));
+    let mut camera = FollowCamera::new(target(&walker), 6.0, -0.5 * PI, 0.35);
+    let mut facing = 0.5 * PI;
+    // held W, S, D, A and Left Shift
+    let mut keys = [false; 5];
+    let mut jump = false;
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.72, 0.88, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(state, _, Some(key)) => {
+                    let pressed = state == ElementState::Pressed;
+                    match key {
+                        VirtualKeyCode::W => keys[0] = pressed,
+                        VirtualKeyCode::S => keys[1] = pressed,
+                        VirtualKeyCode::D => keys[2] = pressed,
+                        VirtualKeyCode::A => keys[3] = pressed,
+                        VirtualKeyCode::LShift => keys[4] = pressed,
+                        VirtualKeyCode::Space => jump |= pressed,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            let dt = clock.step();
+            let axis = |plus: bool, minus: bool| (plus as i32 - minus as i32) as f32;
+            let motion = camera.forward().mul_s(axis(keys[0], keys[1]))
+                               .add_v(&camera.right().mul_s(axis(keys[2], keys[3])));
+            let mut heading = Vector2::new(motion.x, motion.y);
+            if heading.length2() > 0.0 {
+                heading = heading.normalize().mul_s(if keys[4] { 2.0 } else { 1.0 });
+                let turn = wrap_angle(heading.y.atan2(heading.x) - facing);
+                facing = wrap_angle(facing + turn * (1.0 - (-TURN_RATE * dt).exp()));
+            }
+            walker.update(dt, heading, jump, &ground);
+            jump = false;
+            camera.update(dt, target(&walker), &ground);
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        let eye = camera.position().into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        terrain_batch.params.view_proj = view_proj;
+        terrain_batch.params.eye = eye;
+        renderer.draw(&(&terrain_batch, &context), &wrap).unwrap();
+
+        let center = walker.position.add_v(&Vector3::new(0.0, 0.0, RADIUS + HALF_LENGTH));
+        character.params.view_proj = view_proj;
+        character.params.eye = eye;
+        character.params.model = model(center, facing, [1.0; 3]).into_fixed();
+        renderer.draw(&(&character, &context), &wrap).unwrap();
+
+        cube.params.view_proj = view_proj;
+        cube.params.eye = eye;
+        // a visor on the front of the capsule, to show where it faces
+        let visor = center.add_v(&Vector3::new(RADIUS * facing.cos(), RADIUS * facing.sin(),
+                                               0.55));
+        cube.params.model = model(visor, facing, [0.08, 0.2, 0.06]).into_fixed();
+        renderer.draw(&(&cube, &context), &wrap).unwrap();
+        for block in blocks.iter() {
+            // down into the terrain, below where it is lowest
+            let bottom = block.top - 10.0;
+            let position = Point3::new(0.5 * (block.min[0] + block.max[0]),
+                                       0.5 * (block.min[1] + block.max[1]),
+                                       0.5 * (block.top + bottom));
+            let scale = [0.5 * (block.max[0] - block.min[0]),
+                         0.5 * (block.max[1] - block.min[1]),
+                         0.5 * (block.top - bottom)];
+            cube.params.model = model(position, 0.0, scale).into_fixed();
+            renderer.draw(&(&cube, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+    }
+}
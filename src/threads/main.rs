@@ -0,0 +1,415 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of preparing a scene on worker threads.
+//
+// Tens of thousands of creatures crawl around in circles, each a chain of
+// boxes wiggling behind its head. Every frame, each creature is animated,
+// culled against the view, and the matrices of its boxes are computed by
+// walking the chain from the head. None of that needs the GPU, so it is
+// split between worker threads, each filling the instances of its share of
+// the creatures into a staging vector of its own.
+//
+// The renderer, the factory and the device stay on the main thread. It hands
+// every worker its range of creatures and its staging vector through a
+// channel, waits until they all send them back, uploads each vector into its
+// place in a single instance buffer, and draws everything with one call. The
+// vectors go back and forth between the threads without being reallocated,
+// and the instances always end up in the same order, however many threads
+// there are.
+//
+// Drag with the left mouse button to look around, and move with W, A, S, D,
+// E and Q. Press Up and Down to change the number of threads, zero doing
+// everything on the main thread, C to toggle culling and Space to stop the
+// creatures.
+//
+// Run with `threads [count]`, the default is 20000 creatures.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+extern crate time;
+
+use std::env;
+use std::f32::consts::PI;
+use std::mem;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+use time::precise_time_s;
+
+const MAX_THREADS: usize = 8;
+const SEGMENTS: usize = 6;
+const SEGMENT_LENGTH: f32 = 0.5;
+const FIELD_SIZE: f32 = 400.0;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Instance {
+    // the rows of the model matrix, without the last one
+    #[name = "a_Row0"]
+    row0: [f32; 4],
+    #[name = "a_Row1"]
+    row1: [f32; 4],
+    #[name = "a_Row2"]
+    row2: [f32; 4],
+    #[name = "a_Color"]
+    color: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec4 a_Row0;
+    in vec4 a_Row1;
+    in vec4 a_Row2;
+    in vec3 a_Color;
+    out vec3 v_Normal;
+    out vec3 v_Color;
+
+    void main() {
+        vec4 pos = vec4(a_Pos, 1.0);
+        vec4 normal = vec4(a_Normal, 0.0);
+        v_Normal = vec3(dot(a_Row0, normal), dot(a_Row1, normal), dot(a_Row2, normal));
+        v_Color = a_Color;
+        gl_Position = u_ViewProj * vec4(dot(a_Row0, pos), dot(a_Row1, pos), dot(a_Row2, pos), 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    in vec3 v_Color;
+    out vec4 o_Color;
+
+    void main() {
+        float d = max(0.0, dot(normalize(v_Normal), normalize(vec3(0.3, -0.5, 0.8))));
+        o_Color = vec4(v_Color * (0.25 + 0.75 * d), 1.0);
+    }
+";
+
+// A creature crawling around its circle
+#[derive(Clone, Copy)]
+struct Creature {
+    center: [f32; 2],
+    radius: f32,
+    // in radians per second, negative clockwise
+    speed: f32,
+    phase: f32,
+    // how fast its body wiggles
+    wiggle: f32,
+    color: [f32; 3],
+}
+
+fn create_creatures(count: usize) -> Vec<Creature> {
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    (0 .. count).map(|_| {
+        let radius = rng.gen_range(2.0, 10.0);
+        let sign = if rng.gen() { 1.0 } else { -1.0 };
+        let hue = rng.gen_range(0.0, 2.0 * PI);
+        Creature {
+            center: [rng.gen_range(-0.5, 0.5) * FIELD_SIZE, rng.gen_range(-0.5, 0.5) * FIELD_SIZE],
+            radius: radius,
+            speed: sign * rng.gen_range(1.0, 2.0) / radius,
+            phase: rng.gen_range(0.0, 2.0 * PI),
+            wiggle: rng.gen_range(3.0, 6.0),
+            color: [0.6 + 0.4 * hue.cos(), 0.6 + 0.4 * (hue + 2.1).cos(),
+                    0.6 + 0.4 * (hue + 4.2).cos()],
+        }
+    }).collect()
+}
+
+// The planes bounding the clip volume, as (normal, distance) with the normal
+// pointing inside and of unit length, taken from the rows of the
+// view-projection matrix
+fn frustum_planes(m: &Matrix4<f32>) -> [[f32; 4]; 6] {
+    let row = |i: usize| [m.x[i], m.y[i], m.z[i], m.w[i]];
+    let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+    let add = |a: [f32; 4], b: [f32; 4], s: f32| {
+        let p = [a[0] + s * b[0], a[1] + s * b[1], a[2] + s * b[2], a[3] + s * b[3]];
+        let length = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        [p[0] / length, p[1] / length, p[2] / length, p[3] / length]
+    };
+    [
+        add(r3, r0, 1.0), add(r3, r0, -1.0), // left, right
+        add(r3, r1, 1.0), add(r3, r1, -1.0), // bottom, top
+        add(r3, r2, 1.0), add(r3, r2, -1.0), // near, far
+    ]
+}
+
+fn is_visible(planes: &[[f32; 4]; 6], center: [f32; 3], radius: f32) -> bool {
+    planes.iter().all(|p| {
+        p[0] * center[0] + p[1] * center[1] + p[2] * center[2] + p[3] >= -radius
+    })
+}
+
+// What a thread does with its share of the creatures, in any thread:
+// animates them at time `t`, culls them unless `planes` is `None`, and fills
+// `staging` with the boxes of the ones left. Returns how many were culled.
+fn prepare(creatures: &[Creature], planes: Option<&[[f32; 4]; 6]>, t: f32,
+           staging: &mut Vec<Instance>) -> usize {
+    staging.clear();
+    let mut culled = 0;
+    for c in creatures.iter() {
+        let angle = c.phase + t * c.speed;
+        let mut pos = [c.center[0] + c.radius * angle.cos(),
+                       c.center[1] + c.radius * angle.sin(), 0.0];
+        // the whole body is within its length of the head
+        if let Some(planes) = planes {
+            if !is_visible(planes, pos, SEGMENTS as f32 * SEGMENT_LENGTH) {
+                culled += 1;
+                continue;
+            }
+        }
+
+        // walk the chain from the head, each box turned a little more than
+        // the one before it and trailing behind it
+        let mut heading = angle + c.speed.signum() * 0.5 * PI;
+        for i in 0 .. SEGMENTS {
+            heading += 0.25 * (c.wiggle * t - i as f32).sin();
+            let (sin, cos) = heading.sin_cos();
+            let scale = 0.3 * (1.0 - 0.08 * i as f32);
+            let z = scale + 0.05 * (2.0 * c.wiggle * t - i as f32).sin().abs();
+            staging.push(Instance {
+                row0: [cos * scale, -sin * scale, 0.0, pos[0]],
+                row1: [sin * scale, cos * scale, 0.0, pos[1]],
+                row2: [0.0, 0.0, scale, z],
+                color: c.color,
+            });
+            pos[0] -= cos * SEGMENT_LENGTH;
+            pos[1] -= sin * SEGMENT_LENGTH;
+        }
+    }
+    culled
+}
+
+// A share of the creatures, sent to a worker along with the vector to fill
+struct Job {
+    start: usize,
+    end: usize,
+    planes: Option<[[f32; 4]; 6]>,
+    time: f32,
+    staging: Vec<Instance>,
+}
+
+// A finished job, sent back to the main thread
+struct Done {
+    worker: usize,
+    staging: Vec<Instance>,
+    culled: usize,
+}
+
+// Starts a worker, which runs jobs until the returned sender is dropped
+fn spawn_worker(index: usize, creatures: Arc<Vec<Creature>>, done: Sender<Done>)
+                -> Sender<Job> {
+    let (sender, jobs) = channel::<Job>();
+    thread::spawn(move || {
+        for mut job in jobs.iter() {
+            let culled = prepare(&creatures[job.start .. job.end], job.planes.as_ref(),
+                                 job.time, &mut job.staging);
+            let result = Done {
+                worker: index,
+                staging: job.staging,
+                culled: culled,
+            };
+            if done.send(result).is_err() {
+                break;
+            }
+        }
+    });
+    sender
+}
+
+pub fn main() {
+    let count = env::args().nth(1)
+                           .and_then(|arg| usize::from_str(&arg).ok())
+                           .unwrap_or(20000);
+
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Threads example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let creatures = Arc::new(create_creatures(count));
+    let (done_sender, done) = channel();
+    let workers: Vec<Sender<Job>> = (0 .. MAX_THREADS).map(|i| {
+        spawn_worker(i, creatures.clone(), done_sender.clone())
+    }).collect();
+    // one more for the main thread, used when there are no workers
+    let mut stagings: Vec<Vec<Instance>> = (0 .. MAX_THREADS + 1).map(|_| Vec::new()).collect();
+
+    let instance_buffer = factory.create_buffer::<Instance>(count * SEGMENTS,
+                                                            gfx::BufferUsage::Stream);
+    let mut batch = {
+        let (vertex_data, index_data) = shape::cube();
+        let vertex_buffer = factory.create_buffer_static(&vertex_data);
+        let mesh = gfx::Mesh::from_format_instanced(vertex_buffer,
+                                                    vertex_data.len() as gfx::VertexCount,
+                                                    instance_buffer.clone());
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 1000.0);
+    let mut camera = FlyCamera::new(Point3::new(-30.0, -30.0, 12.0), 0.25 * PI, -0.3, 10.0);
+
+    let clear_data = gfx::ClearData {
+        color: [0.35, 0.5, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut num_threads = 4;
+    let mut culling = true;
+    let mut crawling = true;
+    let mut preparing = 0.0f64;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => num_threads = (num_threads + 1).min(MAX_THREADS),
+                    VirtualKeyCode::Down => num_threads = num_threads.saturating_sub(1),
+                    VirtualKeyCode::C => culling = !culling,
+                    VirtualKeyCode::Space => crawling = !crawling,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            camera.update(clock.step());
+            prev_time = time;
+            if crawling {
+                time += clock.step();
+            }
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        let view_proj = proj.mul_m(&camera.view().mat);
+        let planes = if culling { Some(frustum_planes(&view_proj)) } else { None };
+
+        // Hand out the creatures and wait for all the shares to come back
+        let start = precise_time_s();
+        let mut culled = 0;
+        if num_threads == 0 {
+            culled = prepare(&creatures, planes.as_ref(), t, &mut stagings[MAX_THREADS]);
+        } else {
+            for (i, worker) in workers[.. num_threads].iter().enumerate() {
+                worker.send(Job {
+                    start: count * i / num_threads,
+                    end: count * (i + 1) / num_threads,
+                    planes: planes,
+                    time: t,
+                    staging: mem::replace(&mut stagings[i], Vec::new()),
+                }).unwrap();
+            }
+            for _ in 0 .. num_threads {
+                let result = done.recv().unwrap();
+                stagings[result.worker] = result.staging;
+                culled += result.culled;
+            }
+        }
+        preparing += ((precise_time_s() - start) * 1000.0 - preparing) * 0.05;
+
+        // Upload each share after the previous one, in the order of the
+        // creatures
+        let shares = if num_threads == 0 {
+            &stagings[MAX_THREADS ..]
+        } else {
+            &stagings[.. num_threads]
+        };
+        let mut instances = 0;
+        for staging in shares.iter().filter(|s| !s.is_empty()) {
+            factory.update_buffer(&instance_buffer, staging, instances);
+            instances += staging.len();
+        }
+
+        batch.params.view_proj = view_proj.into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        if instances > 0 {
+            renderer.draw_instanced(&(&batch, &context), instances as u32, 0, &wrap)
+                    .unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Threads example: {} threads, {:.2} ms preparing, {} of {} creatures culled",
+            num_threads, preparing, culled, count));
+    }
+}
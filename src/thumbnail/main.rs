@@ -0,0 +1,154 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Runs every example for a fixed frame with a fixed seed and writes a PNG
+// thumbnail of each one into an output directory.
+//
+// It doesn't run headless: the examples open their windows and the frames are
+// read back from them, so a display is needed, which `xvfb-run` can provide.
+//
+// Usage: thumbnail [output directory] [frame]
+
+extern crate gfx_examples;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+use gfx_examples::capture;
+
+// Binary name and the arguments it needs to show something
+static EXAMPLES: &'static [(&'static str, &'static [&'static str])] = &[
+    ("aerial", &[]),
+    ("alpha_to_coverage", &[]),
+    ("anaglyph", &[]),
+    ("antialiasing", &[]),
+    ("atmosphere", &[]),
+    ("blending", &[]),
+    ("bloom", &[]),
+    ("camera_path", &[]),
+    ("caustics", &[]),
+    ("churn", &[]),
+    ("clouds", &[]),
+    ("cube", &[]),
+    ("culling", &[]),
+    ("decals", &[]),
+    ("deferred", &[]),
+    ("depth_peeling", &[]),
+    ("displacement", &[]),
+    ("dungeon", &[]),
+    ("envmap", &[]),
+    ("erosion", &[]),
+    ("fisheye", &[]),
+    ("fog", &[]),
+    ("font", &[]),
+    ("fractal", &[]),
+    ("fur", &[]),
+    ("gamma", &[]),
+    ("gobo", &[]),
+    ("gpu_particles", &[]),
+    ("grass", &[]),
+    ("heat_haze", &[]),
+    ("ibl", &[]),
+    ("impostors", &[]),
+    ("instancing", &[]),
+    ("lightmap", &[]),
+    ("linked_list_oit", &[]),
+    ("lod", &[]),
+    ("metaballs", &[]),
+    ("minimap", &[]),
+    ("mirror", &[]),
+    ("monitor", &[]),
+    ("morph", &[]),
+    ("mrt", &[]),
+    ("no_macros", &[]),
+    ("normals", &[]),
+    ("occlusion", &[]),
+    ("outline", &[]),
+    ("particles", &[]),
+    ("pathtrace", &[]),
+    ("pbr", &[]),
+    ("performance", &["gfx", "1000"]),
+    ("picking", &[]),
+    ("planet", &[]),
+    ("point_shadow", &[]),
+    ("polylines", &[]),
+    ("portals", &[]),
+    ("post_stack", &[]),
+    ("probes", &[]),
+    ("raymarch", &[]),
+    ("readback", &[]),
+    ("reflection", &[]),
+    ("render_queue", &[]),
+    ("ribbon", &[]),
+    ("samplers", &[]),
+    ("shadow", &[]),
+    ("shadow_volumes", &[]),
+    ("skinning", &[]),
+    ("skybox", &[]),
+    ("smaa", &[]),
+    ("sprites", &[]),
+    ("starfield", &[]),
+    ("stencil", &[]),
+    ("streaming", &[]),
+    ("subsurface", &[]),
+    ("terrain", &[]),
+    ("tessellation", &[]),
+    ("third_person", &[]),
+    ("threads", &[]),
+    ("tonemap", &[]),
+    ("triangle", &[]),
+    ("uniform_block", &[]),
+    ("vertex_animation", &[]),
+    ("virtual_texture", &[]),
+    ("vr", &[]),
+    ("walk", &[]),
+    ("wireframe", &[]),
+];
+
+pub fn main() {
+    let mut args = env::args().skip(1);
+    let out_dir = args.next().unwrap_or("thumbnails".to_string());
+    let frame = args.next().unwrap_or(capture::DEFAULT_FRAME.to_string());
+
+    fs::create_dir_all(&out_dir).ok().expect("Failed to create the output directory");
+    // The examples are built next to this binary
+    let exe = env::current_exe().ok().expect("Failed to locate the examples");
+    let bin_dir = exe.parent().unwrap();
+
+    let mut failed = 0;
+    for &(name, example_args) in EXAMPLES.iter() {
+        let path = Path::new(&out_dir).join(&format!("{}.png", name));
+        let status = Command::new(&bin_dir.join(name))
+            .args(example_args)
+            .env(capture::PATH_VAR, &path)
+            .env(capture::FRAME_VAR, &frame)
+            .status();
+        match status {
+            Ok(ref s) if s.success() => println!("{}:\t{}", name, path.display()),
+            Ok(s) => {
+                println!("{}:\texited with {}", name, s);
+                failed += 1;
+            },
+            Err(e) => {
+                println!("{}:\tfailed to start: {}", name, e);
+                failed += 1;
+            },
+        }
+    }
+
+    if failed > 0 {
+        process::exit(1);
+    }
+}
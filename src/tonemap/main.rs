@@ -0,0 +1,344 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example comparing tone mapping operators.
+//
+// A high dynamic range scene, with spheres in a strong sun and a row of
+// lights doubling in brightness from one to the next, is rendered to a float
+// target. A full-screen pass then maps it to the window with one of four
+// operators: the exposure alone, clipped at white, Reinhard's, the filmic
+// curve of John Hable, and a fit of the ACES curve by Krzysztof Narkowicz.
+// In the split mode, the left and the right halves of the screen use two
+// different operators.
+//
+// Drag with the left mouse button to orbit and scroll to zoom. Press Tab to
+// change the operator of the left side, B to change the one of the right
+// side, S to toggle the split, and Plus and Minus to change the exposure.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Thumbnail};
+
+static OPERATORS: [&'static str; 4] = ["exposure only", "Reinhard", "Hable filmic", "ACES"];
+
+// albedo of the spheres, and brightness of the first of the lights, which
+// double from one to the next
+static ALBEDOS: [[f32; 3]; 5] = [
+    [0.9, 0.9, 0.9],
+    [0.5, 0.5, 0.5],
+    [0.1, 0.1, 0.1],
+    [0.8, 0.1, 0.1],
+    [0.1, 0.3, 0.9],
+];
+const NUM_LIGHTS: usize = 10;
+const FIRST_LIGHT: f32 = 0.125;
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_Scale"]
+    scale: [f32; 3],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // 1 for a light showing its color as it is, 0 for a lit surface
+    #[name = "u_Emissive"]
+    emissive: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct ToneParams<R: gfx::Resources> {
+    #[name = "u_Exposure"]
+    exposure: f32,
+    // the operators of the left and the right side, as indices into
+    // `OPERATORS`
+    #[name = "u_Operators"]
+    operators: [f32; 2],
+    // where the right side begins, from 0 to 1
+    #[name = "u_Split"]
+    split: f32,
+    #[name = "t_Scene"]
+    scene: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    uniform vec3 u_Scale;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+
+    void main() {
+        v_Normal = a_Normal / u_Scale;
+        gl_Position = u_ViewProj * vec4(u_Scale * a_Pos + u_Offset, 1.0);
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_Emissive;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 sun = 6.0 * vec3(1.0, 0.95, 0.85)
+                 * max(dot(n, normalize(vec3(0.4, -0.6, 0.7))), 0.0);
+        vec3 sky = vec3(0.3, 0.4, 0.6) * (0.5 + 0.5 * n.z);
+        vec3 color = mix(u_Color * (sun + sky), u_Color, u_Emissive);
+        o_Color = vec4(color, 1.0);
+    }
+";
+
+static TONE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static TONE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Exposure;
+    uniform vec2 u_Operators;
+    uniform float u_Split;
+    uniform sampler2D t_Scene;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    vec3 hable(vec3 x) {
+        const float A = 0.15, B = 0.50, C = 0.10, D = 0.20, E = 0.02, F = 0.30;
+        return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+    }
+
+    vec3 tone_map(vec3 c, int op) {
+        if (op == 1) {
+            return c / (1.0 + c);
+        } else if (op == 2) {
+            // the curve is brighter at the same exposure, and the white
+            // point is where it reaches 1
+            const float WHITE = 11.2;
+            return hable(2.0 * c) / hable(vec3(WHITE));
+        } else if (op == 3) {
+            const float A = 2.51, B = 0.03, C = 2.43, D = 0.59, E = 0.14;
+            c *= 0.6;
+            return (c * (A * c + B)) / (c * (C * c + D) + E);
+        }
+        return c;
+    }
+
+    void main() {
+        vec3 c = u_Exposure * texture(t_Scene, v_TexCoord).rgb;
+        bool right = v_TexCoord.x >= u_Split;
+        int op = int(right ? u_Operators.y : u_Operators.x);
+        vec3 color = clamp(tone_map(c, op), 0.0, 1.0);
+        // a line between the sides
+        if (abs(v_TexCoord.x - u_Split) < 0.001) {
+            color = vec3(1.0);
+        }
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Tone mapping example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let scene_texture = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F16),
+    }).unwrap();
+    let depth_texture = factory.create_texture(gfx::tex::TextureInfo {
+        width: w,
+        height: h,
+        depth: 1,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::DEPTH24_STENCIL8,
+    }).unwrap();
+    let scene_frame = gfx::Frame {
+        colors: vec![Plane::Texture(scene_texture.clone(), 0, None)],
+        depth: Some(Plane::Texture(depth_texture, 0, None)),
+        .. gfx::Frame::empty(w, h)
+    };
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut meshes = MeshCache::new();
+    let mut batches = Vec::new();
+    for (mesh, slice) in vec![meshes.sphere(&mut factory, 32, 64), meshes.cube(&mut factory),
+                              meshes.plane(&mut factory)].into_iter() {
+        let data = SceneParams {
+            view_proj: Matrix4::identity().into_fixed(),
+            offset: [0.0; 3],
+            scale: [1.0; 3],
+            color: [1.0; 3],
+            emissive: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        batches.push(context.make_batch(&program, data, &mesh, slice, &state).unwrap());
+    }
+    let mut floor = batches.pop().unwrap();
+    floor.params.offset = [0.0, 0.0, -0.8];
+    floor.params.scale = [8.0, 8.0, 1.0];
+    floor.params.color = [0.4, 0.35, 0.3];
+    let mut light = batches.pop().unwrap();
+    light.params.emissive = 1.0;
+    let mut sphere = batches.pop().unwrap();
+
+    let mut tone = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(TONE_VERTEX_SRC, TONE_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = ToneParams {
+            exposure: 1.0,
+            operators: [0.0, 3.0],
+            split: 0.5,
+            scene: (scene_texture, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 1.0, 0.0), 10.0, -1.7, 0.35);
+
+    // a bright sky, which the operators map differently too
+    let clear_data = gfx::ClearData {
+        color: [1.2, 1.6, 2.4, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut split = true;
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    let params = &mut tone.params;
+                    let count = OPERATORS.len() as f32;
+                    match key {
+                        VirtualKeyCode::Tab =>
+                            params.operators[0] = (params.operators[0] + 1.0) % count,
+                        VirtualKeyCode::B =>
+                            params.operators[1] = (params.operators[1] + 1.0) % count,
+                        VirtualKeyCode::S => split = !split,
+                        VirtualKeyCode::Add | VirtualKeyCode::Equals => params.exposure *= 1.25,
+                        VirtualKeyCode::Subtract | VirtualKeyCode::Minus =>
+                            params.exposure *= 0.8,
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &scene_frame);
+        floor.params.view_proj = view_proj;
+        renderer.draw(&(&floor, &context), &scene_frame).unwrap();
+        for (i, albedo) in ALBEDOS.iter().enumerate() {
+            sphere.params.view_proj = view_proj;
+            sphere.params.offset = [2.0 * i as f32 - 4.0, 0.0, 0.0];
+            sphere.params.scale = [0.8; 3];
+            sphere.params.color = *albedo;
+            renderer.draw(&(&sphere, &context), &scene_frame).unwrap();
+        }
+        for i in 0 .. NUM_LIGHTS {
+            let brightness = FIRST_LIGHT * (1 << i) as f32;
+            light.params.view_proj = view_proj;
+            light.params.offset = [i as f32 - 0.5 * (NUM_LIGHTS - 1) as f32, 3.0, 0.2];
+            light.params.scale = [0.4, 0.1, 1.0];
+            light.params.color = [brightness; 3];
+            renderer.draw(&(&light, &context), &scene_frame).unwrap();
+        }
+
+        tone.params.split = if split { 0.5 } else { 2.0 };
+        renderer.draw(&(&tone, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let operators = tone.params.operators;
+        wrap.window.set_title(&if split {
+            format!("Tone mapping example: {} | {}, exposure {:.2}",
+                    OPERATORS[operators[0] as usize], OPERATORS[operators[1] as usize],
+                    tone.params.exposure)
+        } else {
+            format!("Tone mapping example: {}, exposure {:.2}",
+                    OPERATORS[operators[0] as usize], tone.params.exposure)
+        });
+    }
+}
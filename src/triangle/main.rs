@@ -16,10 +16,12 @@
 #![plugin(gfx_macros)]
 
 extern crate gfx;
+extern crate gfx_examples;
 extern crate gfx_window_glutin;
 extern crate glutin;
 
 use gfx::traits::*;
+use gfx_examples::{window, Thumbnail};
 
 #[vertex_format]
 #[derive(Clone, Copy)]
@@ -55,9 +57,10 @@ static FRAGMENT_SRC: &'static [u8] = b"
 ";
 
 pub fn main() {
-    let mut canvas = gfx_window_glutin::init(glutin::Window::new().unwrap())
-                                       .into_canvas();
+    let mut canvas = window::init(gfx_window_glutin::init(glutin::Window::new().unwrap()))
+                     .into_canvas();
     canvas.output.window.set_title("Triangle example");
+    let mut thumbnail = Thumbnail::from_env();
 
     let vertex_data = [
         Vertex { pos: [ -0.5, -0.5 ], color: [1.0, 0.0, 0.0] },
@@ -88,6 +91,18 @@ pub fn main() {
         });
         canvas.draw(&gfx::batch::bind(&state, &mesh, slice.clone(), &program, &None))
               .unwrap();
+
+        if thumbnail.is_some() {
+            // the frame has to be in the back buffer before `present` swaps it
+            canvas.device.submit(canvas.renderer.as_buffer());
+            canvas.renderer.reset();
+            let win = &canvas.output.window;
+            if let Some(size) = window::physical_size(win) {
+                if thumbnail.as_mut().unwrap().end_frame(|s| win.get_proc_address(s), size) {
+                    break 'main;
+                }
+            }
+        }
         canvas.present();
     }
 }
@@ -0,0 +1,416 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of a `std140` uniform block shared between Rust and
+// GLSL.
+//
+// All the uniforms of the shaders live in a single block, which is filled
+// from a Rust struct and uploaded into a uniform buffer once per frame. In a
+// `std140` block, a vec3 or a vec4 starts on 16 bytes, a vec2 on 8 bytes, a
+// matrix is stored as columns of vec4, and every element of an array takes
+// 16 bytes, even when it is a single float. A `#[repr(C)]` struct only pads
+// for the alignment of Rust types, so it has to spell that padding out.
+//
+// At startup, the offsets of the struct are checked against the ones given
+// by the `std140` rules, and its size against the size of the block reported
+// by the linked program, and the example stops if they differ. The same
+// check is printed for a naive struct that follows the block member for
+// member, showing where it goes wrong.
+//
+// Press P to upload the naive struct instead, M to switch between lighting
+// and normals, and T to change the tint.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::mem;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Point3, Vector3, EuclideanVector};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+const NUM_CUBES: usize = 4;
+
+// The block as the shaders see it, with each member on the offset that
+// `std140` gives it
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Globals {
+    view_proj: [[f32; 4]; 4], // 0
+    light_dir: [f32; 3],      // 64
+    time: f32,                // 76, in the last four bytes of the vec3
+    ambient: [f32; 3],        // 80
+    // a vec2 starts on 8 bytes, so it can't follow the vec3
+    _pad0: f32,
+    wobble: [f32; 2],         // 96
+    mode: i32,                // 104
+    // a matrix starts on 16 bytes
+    _pad1: f32,
+    tint: [[f32; 4]; 3],      // 112, each column padded to a vec4
+    pulse: [[f32; 4]; NUM_CUBES], // 160, each float padded to a vec4
+}
+
+// The same members, but laid out the way Rust would
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct NaiveGlobals {
+    view_proj: [[f32; 4]; 4],
+    light_dir: [f32; 3],
+    time: f32,
+    ambient: [f32; 3],
+    wobble: [f32; 2],
+    mode: i32,
+    tint: [[f32; 3]; 3],
+    pulse: [f32; NUM_CUBES],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_Globals"]
+    globals: gfx::RawBufferHandle<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    layout(std140)
+    uniform u_Globals {
+        mat4 u_ViewProj;
+        vec3 u_LightDir;
+        float u_Time;
+        vec3 u_Ambient;
+        vec2 u_Wobble;
+        int u_Mode;
+        mat3 u_Tint;
+        float u_Pulse[4];
+    };
+
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_Normal;
+    flat out float v_Pulse;
+
+    void main() {
+        // the cubes spin and bob, each a little behind the previous one
+        float i = float(gl_InstanceID);
+        float a = u_Time + i;
+        mat3 spin = mat3(cos(a), sin(a), 0.0, -sin(a), cos(a), 0.0, 0.0, 0.0, 1.0);
+        vec3 offset = vec3(3.0 * i - 4.5, 0.0, u_Wobble.x * sin(u_Wobble.y * u_Time + i));
+        v_Normal = spin * a_Normal;
+        v_Pulse = u_Pulse[gl_InstanceID];
+        gl_Position = u_ViewProj * vec4(spin * a_Pos + offset, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    layout(std140)
+    uniform u_Globals {
+        mat4 u_ViewProj;
+        vec3 u_LightDir;
+        float u_Time;
+        vec3 u_Ambient;
+        vec2 u_Wobble;
+        int u_Mode;
+        mat3 u_Tint;
+        float u_Pulse[4];
+    };
+
+    in vec3 v_Normal;
+    flat in float v_Pulse;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 color;
+        if (u_Mode == 0) {
+            float d = max(0.0, dot(n, u_LightDir));
+            color = u_Tint * vec3(0.9, 0.6, 0.3) * (u_Ambient + d) * v_Pulse;
+        } else {
+            color = n * 0.5 + 0.5;
+        }
+        o_Color = vec4(color, 1.0);
+    }
+";
+
+// Returns the offset of a field from the start of its struct
+macro_rules! offset_of {
+    ($ty:ty, $field:ident) => {
+        unsafe {
+            let value: $ty = mem::zeroed();
+            &value.$field as *const _ as usize - &value as *const _ as usize
+        }
+    }
+}
+
+// The GLSL types used in the block
+#[derive(Clone, Copy)]
+enum Member {
+    Scalar,
+    Vec2,
+    Vec3,
+    Mat3,
+    Mat4,
+    ScalarArray(usize),
+}
+
+impl Member {
+    fn align(&self) -> usize {
+        match *self {
+            Member::Scalar => 4,
+            Member::Vec2 => 8,
+            // everything else starts on a vec4
+            _ => 16,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match *self {
+            Member::Scalar => 4,
+            Member::Vec2 => 8,
+            Member::Vec3 => 12,
+            Member::Mat3 => 3 * 16,
+            Member::Mat4 => 4 * 16,
+            Member::ScalarArray(len) => len * 16,
+        }
+    }
+}
+
+static BLOCK: [(&'static str, Member); 8] = [
+    ("u_ViewProj", Member::Mat4),
+    ("u_LightDir", Member::Vec3),
+    ("u_Time", Member::Scalar),
+    ("u_Ambient", Member::Vec3),
+    ("u_Wobble", Member::Vec2),
+    ("u_Mode", Member::Scalar),
+    ("u_Tint", Member::Mat3),
+    ("u_Pulse", Member::ScalarArray(NUM_CUBES)),
+];
+
+// Returns the offset of every member of `BLOCK` following the `std140`
+// rules, and the size of the block
+fn std140_layout() -> (Vec<usize>, usize) {
+    let mut offsets = Vec::new();
+    let mut end = 0;
+    for &(_, member) in BLOCK.iter() {
+        let align = member.align();
+        let offset = (end + align - 1) / align * align;
+        offsets.push(offset);
+        end = offset + member.size();
+    }
+    // the block is padded to a multiple of a vec4
+    (offsets, (end + 15) / 16 * 16)
+}
+
+// Prints the offsets of a struct next to the expected ones, returning
+// whether they all match
+fn check_layout(name: &str, offsets: &[usize], size: usize) -> bool {
+    let (expected, expected_size) = std140_layout();
+    println!("{}:", name);
+    let mut matches = size == expected_size;
+    for (&(member, _), (&offset, &std140)) in BLOCK.iter().zip(offsets.iter().zip(&expected)) {
+        matches &= offset == std140;
+        let mark = if offset == std140 { "" } else { "  <- wrong" };
+        println!("    {:<12} std140 {:>3}, struct {:>3}{}", member, std140, offset, mark);
+    }
+    println!("    {:<12} std140 {:>3}, struct {:>3}", "size", expected_size, size);
+    matches
+}
+
+fn tint(index: usize) -> [[f32; 3]; 3] {
+    match index {
+        // none
+        0 => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        // red and blue swapped
+        1 => [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+        // gray
+        _ => [[0.33, 0.33, 0.33], [0.33, 0.33, 0.33], [0.33, 0.33, 0.33]],
+    }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Uniform block example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+
+    let matches = check_layout("Globals", &[
+        offset_of!(Globals, view_proj),
+        offset_of!(Globals, light_dir),
+        offset_of!(Globals, time),
+        offset_of!(Globals, ambient),
+        offset_of!(Globals, wobble),
+        offset_of!(Globals, mode),
+        offset_of!(Globals, tint),
+        offset_of!(Globals, pulse),
+    ], mem::size_of::<Globals>());
+    assert!(matches, "Globals does not follow the std140 layout");
+    check_layout("NaiveGlobals", &[
+        offset_of!(NaiveGlobals, view_proj),
+        offset_of!(NaiveGlobals, light_dir),
+        offset_of!(NaiveGlobals, time),
+        offset_of!(NaiveGlobals, ambient),
+        offset_of!(NaiveGlobals, wobble),
+        offset_of!(NaiveGlobals, mode),
+        offset_of!(NaiveGlobals, tint),
+        offset_of!(NaiveGlobals, pulse),
+    ], mem::size_of::<NaiveGlobals>());
+    // the driver knows the real size of the block
+    let block = program.get_info().blocks.iter().find(|b| b.name == "u_Globals")
+                       .expect("u_Globals is not used by the program");
+    assert_eq!(block.size, mem::size_of::<Globals>());
+
+    let buffer = factory.create_buffer::<Globals>(1, gfx::BufferUsage::Stream);
+    // the naive struct is smaller than the block, and the shaders read all
+    // of it, so this one holds two
+    let naive_buffer = factory.create_buffer::<NaiveGlobals>(2, gfx::BufferUsage::Stream);
+
+    let mut meshes = MeshCache::new();
+    let (mesh, slice) = meshes.cube(&mut factory);
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let data = Params {
+        globals: buffer.raw().clone(),
+    };
+    let mut batch = context.make_batch(&program, data, &mesh, slice, &state)
+                           .unwrap();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.5, 100.0);
+    let view: AffineMatrix3<f32> = Transform::look_at(
+        &Point3::new(0.0, -14.0, 5.0),
+        &Point3::new(0.0, 0.0, 0.0),
+        &Vector3::unit_z(),
+    );
+    let view_proj = proj.mul_m(&view.mat).into_fixed();
+
+    let clear_data = gfx::ClearData {
+        color: [0.2, 0.2, 0.25, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut naive = false;
+    let mut mode = 0;
+    let mut tint_index = 0;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::P => naive = !naive,
+                    VirtualKeyCode::M => mode = 1 - mode,
+                    VirtualKeyCode::T => tint_index = (tint_index + 1) % 3,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            time = clock.time() as f32;
+        }
+        let t = prev_time + (time - prev_time) * clock.alpha();
+
+        let (light_sin, light_cos) = (0.5 * t).sin_cos();
+        let light_dir = Vector3::new(light_cos, light_sin, 1.0).normalize();
+        let m = tint(tint_index);
+        let mut pulse = [0.0; NUM_CUBES];
+        for (i, p) in pulse.iter_mut().enumerate() {
+            *p = 0.75 + 0.25 * (3.0 * t + i as f32).sin();
+        }
+
+        if naive {
+            factory.update_buffer(&naive_buffer, &[NaiveGlobals {
+                view_proj: view_proj,
+                light_dir: light_dir.into_fixed(),
+                time: t,
+                ambient: [0.2, 0.2, 0.3],
+                wobble: [0.5, 2.0],
+                mode: mode,
+                tint: m,
+                pulse: pulse,
+            }], 0);
+            batch.params.globals = naive_buffer.raw().clone();
+        } else {
+            factory.update_buffer(&buffer, &[Globals {
+                view_proj: view_proj,
+                light_dir: light_dir.into_fixed(),
+                time: t,
+                ambient: [0.2, 0.2, 0.3],
+                _pad0: 0.0,
+                wobble: [0.5, 2.0],
+                mode: mode,
+                _pad1: 0.0,
+                tint: [[m[0][0], m[0][1], m[0][2], 0.0],
+                       [m[1][0], m[1][1], m[1][2], 0.0],
+                       [m[2][0], m[2][1], m[2][2], 0.0]],
+                pulse: [[pulse[0], 0.0, 0.0, 0.0], [pulse[1], 0.0, 0.0, 0.0],
+                        [pulse[2], 0.0, 0.0, 0.0], [pulse[3], 0.0, 0.0, 0.0]],
+            }], 0);
+            batch.params.globals = buffer.raw().clone();
+        }
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw_instanced(&(&batch, &context), NUM_CUBES as u32, 0, &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(if naive {
+            "Uniform block example: naive struct"
+        } else {
+            "Uniform block example: std140 struct"
+        });
+    }
+}
@@ -0,0 +1,317 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of playing an animation back from a vertex animation
+// texture.
+//
+// At startup the swimming motion of a fish is worked out on the CPU for each
+// of a loop of frames, and the position and normal of every vertex in every
+// frame is written into a float texture: a column for each vertex, and two
+// rows, positions and normals, for each frame. The motion here comes from a
+// wave running down the body, but it could be anything, a cloth simulation
+// or a skinned character exported from a modelling tool, since the shader
+// only ever sees the baked result.
+//
+// A whole shoal is then drawn with one instanced draw call. The vertex shader
+// picks the frame of each fish from the time and the fish's own phase and
+// speed, fetches its vertex from the two frames around it by gl_VertexID,
+// and blends them. Where the fish swims is worked out in the shader too, so
+// nothing is uploaded after startup.
+//
+// Press I to toggle blending between frames, Up and Down to change the number
+// of fish, and Space to stop them.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate rand;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+// frames in the loop of the animation
+const FRAMES: usize = 32;
+const MAX_FISH: usize = 8192;
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Fish {
+    // radius, height, angular speed and starting angle of its circle
+    #[name = "a_Orbit"]
+    orbit: [f32; 4],
+    // phase and speed of the animation, size and hue
+    #[name = "a_Anim"]
+    anim: [f32; 4],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Time"]
+    time: f32,
+    #[name = "u_Frames"]
+    frames: f32,
+    #[name = "u_Interpolate"]
+    interpolate: f32,
+    #[name = "t_Animation"]
+    animation: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform float u_Time;
+    uniform float u_Frames;
+    uniform float u_Interpolate;
+    uniform sampler2D t_Animation;
+    in vec2 a_TexCoord;
+    in vec4 a_Orbit;
+    in vec4 a_Anim;
+    out vec3 v_Normal;
+    out vec2 v_TexCoord;
+    out float v_Hue;
+    out float v_Distance;
+
+    void main() {
+        // the two frames around this moment of this fish's loop
+        float frame = fract(a_Anim.x + u_Time * a_Anim.y) * u_Frames;
+        int f0 = int(frame);
+        int f1 = (f0 + 1) % int(u_Frames);
+        float k = u_Interpolate * fract(frame);
+        vec3 pos = mix(texelFetch(t_Animation, ivec2(gl_VertexID, 2 * f0), 0).xyz,
+                       texelFetch(t_Animation, ivec2(gl_VertexID, 2 * f1), 0).xyz, k);
+        vec3 normal = mix(texelFetch(t_Animation, ivec2(gl_VertexID, 2 * f0 + 1), 0).xyz,
+                          texelFetch(t_Animation, ivec2(gl_VertexID, 2 * f1 + 1), 0).xyz, k);
+
+        // the fish swims around its circle, head first
+        float angle = a_Orbit.w + u_Time * a_Orbit.z;
+        vec2 forward = sign(a_Orbit.z) * vec2(-sin(angle), cos(angle));
+        vec2 left = vec2(-forward.y, forward.x);
+        mat3 rotation = mat3(vec3(forward, 0.0), vec3(left, 0.0), vec3(0.0, 0.0, 1.0));
+        vec3 center = vec3(a_Orbit.x * cos(angle), a_Orbit.x * sin(angle), a_Orbit.y);
+
+        v_Normal = rotation * normal;
+        v_TexCoord = a_TexCoord;
+        v_Hue = a_Anim.w;
+        gl_Position = u_ViewProj * vec4(center + rotation * pos * a_Anim.z, 1.0);
+        v_Distance = gl_Position.w;
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 v_Normal;
+    in vec2 v_TexCoord;
+    in float v_Hue;
+    in float v_Distance;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 base = 0.5 + 0.5 * cos(6.2832 * (v_Hue + vec3(0.0, 0.33, 0.67)));
+        // dark stripes across the back, which is the first half around
+        float stripe = step(0.5, fract(10.0 * v_TexCoord.y)) * step(v_TexCoord.x, 0.5);
+        vec3 color = mix(base, base * 0.3, stripe);
+        float light = max(dot(normalize(v_Normal), normalize(vec3(0.2, 0.3, 1.0))), 0.0);
+        color *= 0.3 + 0.7 * light;
+        float fog = clamp(v_Distance / 60.0, 0.0, 1.0);
+        o_Color = vec4(mix(color, vec3(0.05, 0.2, 0.3), fog), 1.0);
+    }
+";
+
+// The sideways offset of the body `t` of the way from the head to the tail
+fn swim(t: f32, phase: f32) -> f32 {
+    (0.05 + 0.3 * t * t) * (phase - 1.5 * PI * t).sin()
+}
+
+// Positions and normals of each vertex for every frame, a row of each per
+// frame, for the unit sphere stretched into a fish along X with its head at 1
+fn bake(vertices: &[shape::Vertex]) -> Vec<[f32; 4]> {
+    let mut texels = Vec::with_capacity(vertices.len() * FRAMES * 2);
+    for frame in 0 .. FRAMES {
+        let phase = 2.0 * PI * frame as f32 / FRAMES as f32;
+        let mut normals = Vec::with_capacity(vertices.len());
+        for v in vertices.iter() {
+            // the sphere's poles along the body, narrowing towards the tail
+            let (x, y, z) = (v.pos[2], v.pos[0], v.pos[1]);
+            let t = 0.5 * (1.0 - x);
+            let (sy, sz) = (0.25 * (1.0 - 0.6 * t), 0.35 * (1.0 - 0.5 * t));
+            let e = 0.01;
+            let slope = 0.5 * (swim(t - e, phase) - swim(t + e, phase)) / (2.0 * e);
+            texels.push([x, y * sy + swim(t, phase), z * sz, 0.0]);
+            // normals go through the inverse transpose of the bend and scale
+            let n = [v.normal[2], v.normal[0] / sy, v.normal[1] / sz];
+            let n = [n[0] - slope * n[1], n[1], n[2]];
+            let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            normals.push([n[0] / length, n[1] / length, n[2] / length, 0.0]);
+        }
+        texels.extend(normals.into_iter());
+    }
+    texels
+}
+
+fn create_fish<R: Rng>(rng: &mut R) -> Fish {
+    let radius = 4.0 + 20.0 * rng.gen::<f32>();
+    let speed = 2.0 + 2.0 * rng.gen::<f32>();
+    let direction = if rng.gen::<bool>() { 1.0 } else { -1.0 };
+    Fish {
+        orbit: [radius, 12.0 * (rng.gen::<f32>() - 0.5),
+                direction * speed / radius, 2.0 * PI * rng.gen::<f32>()],
+        anim: [rng.gen::<f32>(), 0.5 + 0.4 * speed, 0.4 + 0.3 * rng.gen::<f32>(),
+               0.3 + 0.3 * rng.gen::<f32>()],
+    }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Vertex animation example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let (vertex_data, index_data) = shape::sphere(12, 24);
+    let animation = {
+        let info = gfx::tex::TextureInfo {
+            width: vertex_data.len() as u16,
+            height: (FRAMES * 2) as u16,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::Format::Float(gfx::tex::Components::RGBA,
+                                            gfx::attrib::FloatSize::F32),
+        };
+        let texture = factory.create_texture(info).unwrap();
+        factory.update_texture(&texture, &info.to_image_info(), &bake(&vertex_data), None)
+               .unwrap();
+        texture
+    };
+    let sampler = factory.create_sampler(
+        gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                   gfx::tex::WrapMode::Clamp)
+    );
+
+    let mut rng = rand::XorShiftRng::new_unseeded();
+    let fish: Vec<Fish> = (0 .. MAX_FISH).map(|_| create_fish(&mut rng)).collect();
+    let instance_buffer = factory.create_buffer(MAX_FISH, gfx::BufferUsage::Static);
+    factory.update_buffer(&instance_buffer, &fish, 0);
+
+    let mut batch = {
+        let vertex_buffer = factory.create_buffer_static(&vertex_data);
+        let mesh = gfx::Mesh::from_format_instanced(vertex_buffer,
+                                                    vertex_data.len() as gfx::VertexCount,
+                                                    instance_buffer);
+        let slice = factory.create_buffer_index::<u16>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+        let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            time: 0.0,
+            frames: FRAMES as f32,
+            interpolate: 1.0,
+            animation: (animation, Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(60.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 35.0, -1.0, 0.3);
+
+    let clear_data = gfx::ClearData {
+        color: [0.05, 0.2, 0.3, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut count = 2048;
+    let mut swimming = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_time, mut time) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::I =>
+                        batch.params.interpolate = 1.0 - batch.params.interpolate,
+                    VirtualKeyCode::Up => count = (count * 2).min(MAX_FISH),
+                    VirtualKeyCode::Down => count = (count / 2).max(1),
+                    VirtualKeyCode::Space => swimming = !swimming,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_time = time;
+            if swimming {
+                time += clock.step();
+            }
+        }
+        batch.params.time = prev_time + (time - prev_time) * clock.alpha();
+        batch.params.view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw_instanced(&(&batch, &context), count as u32, 0, &wrap)
+                .unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "Vertex animation example: {} fish, {} frames {}",
+            count, FRAMES,
+            if batch.params.interpolate > 0.5 { "blended" } else { "stepped" }));
+    }
+}
@@ -0,0 +1,430 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of virtual texturing.
+//
+// The terrain is covered by a single texture of 15360 by 15360 texels, far
+// too large to keep in memory, with a mip chain of eight levels. It is split
+// into pages of 120 texels, generated on demand: every frame the pages the
+// camera needs are selected from a quadtree, coarse ones far away and fine
+// ones close by, and those missing are generated and uploaded into free
+// slots of a physical atlas of 16 by 16 pages, reusing the least recently
+// used slots once it is full, a few pages per frame. Each page is stored
+// with a border of 4 texels from its neighbours, so bilinear filtering does
+// not bleed across pages. An indirection texture with one texel per page of
+// the finest level tells the terrain shader where to find the finest page
+// in the atlas covering that place, which is a coarser one while the fine
+// one is still missing.
+//
+// Drag with the left mouse button to look around, and press W, A, S and D
+// to move, E and Q to go up and down, and hold Left Shift to go faster.
+// Press P to show the pages and their levels.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate noise;
+extern crate rand;
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::heightfield::Heightfield;
+use gfx_examples::{capture, window, Clock, Thumbnail};
+use noise::{Seed, perlin2};
+
+// Width of the terrain, in meters
+const TERRAIN_SIZE: f32 = 1024.0;
+// Pages across the finest level of the virtual texture, and its levels
+const PAGES: u32 = 128;
+const LEVELS: u8 = 8;
+// Texels across a page in the atlas, and of them, the border on each side
+const PAGE_SIZE: u32 = 128;
+const BORDER: u32 = 4;
+// Pages across the atlas
+const ATLAS_PAGES: u32 = 16;
+// Pages generated and uploaded per frame at most
+const UPLOADS_PER_FRAME: usize = 4;
+// Vertical field of view, in degrees
+const FOV: f32 = 60.0;
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_ShowPages"]
+    show_pages: f32,
+    #[name = "t_Indirection"]
+    indirection: gfx::shade::TextureParam<R>,
+    #[name = "t_Atlas"]
+    atlas: gfx::shade::TextureParam<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec2 a_TexCoord;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        gl_Position = u_ViewProj * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const float PAGES = 128.0;
+    const float PAGE_SIZE = 128.0;
+    const float BORDER = 4.0;
+    const float ATLAS_SIZE = 16.0 * 128.0;
+
+    uniform float u_ShowPages;
+    uniform sampler2D t_Indirection;
+    uniform sampler2D t_Atlas;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    void main() {
+        // the atlas slot and the level of the finest page here
+        ivec2 finest = clamp(ivec2(v_TexCoord * PAGES), ivec2(0), ivec2(PAGES - 1.0));
+        vec3 entry = texelFetch(t_Indirection, finest, 0).xyz * 255.0;
+        float level = entry.z;
+        vec2 in_page = fract(v_TexCoord * PAGES / exp2(level));
+        vec2 tc = (entry.xy * PAGE_SIZE + BORDER + in_page * (PAGE_SIZE - 2.0 * BORDER)) /
+                  ATLAS_SIZE;
+        vec3 color = textureLod(t_Atlas, tc, 0.0).rgb;
+        if (u_ShowPages > 0.5) {
+            vec3 tint = 0.5 + 0.5 * cos(vec3(0.0, 2.0, 4.0) + 0.9 * level);
+            vec2 edge = min(in_page, 1.0 - in_page) / fwidth(in_page);
+            float line = 1.0 - min(min(edge.x, edge.y), 1.0);
+            color = mix(color * tint, vec3(1.0), line);
+        }
+        o_Color = vec4(color, 1.0);
+    }
+";
+
+// A page of the virtual texture, where a page of `level` covers 2^level
+// pages of the finest level on each side
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct Page {
+    level: u8,
+    x: u32,
+    y: u32,
+}
+
+impl Page {
+    fn children(&self) -> [Page; 4] {
+        let child = |dx, dy| Page {
+            level: self.level - 1,
+            x: 2 * self.x + dx,
+            y: 2 * self.y + dy,
+        };
+        [child(0, 0), child(1, 0), child(0, 1), child(1, 1)]
+    }
+
+    // Meters across the page
+    fn size(&self) -> f32 {
+        TERRAIN_SIZE * (1u32 << self.level) as f32 / PAGES as f32
+    }
+
+    // The corner of the page, in meters
+    fn origin(&self) -> (f32, f32) {
+        (self.x as f32 * self.size() - 0.5 * TERRAIN_SIZE,
+         self.y as f32 * self.size() - 0.5 * TERRAIN_SIZE)
+    }
+}
+
+struct Slot {
+    page: Option<Page>,
+    last_used: u64,
+}
+
+// Hills and mountains
+fn terrain_height(seed: &Seed, x: f32, y: f32) -> f32 {
+    let mut height = 0.0;
+    for i in 0 .. 5 {
+        let scale = (1 << i) as f32;
+        height += perlin2(seed, &[x * scale / 300.0, y * scale / 300.0]) / scale;
+    }
+    80.0 * height
+}
+
+// The color of the terrain at a point, with the details of `octaves` levels
+// of noise, so a coarse page does not alias
+fn terrain_color(seed: &Seed, x: f32, y: f32, octaves: u32) -> [u8; 4] {
+    let height = terrain_height(seed, x, y);
+    let mut detail = 0.0;
+    for i in 0 .. octaves {
+        let scale = (1 << i) as f32;
+        detail += perlin2(seed, &[x * scale / 16.0 + 31.0, y * scale / 16.0 - 7.0]) / scale;
+    }
+    let ramp = [(-80.0, [0.1, 0.2, 0.45]), (-20.0, [0.2, 0.35, 0.6]), (-15.0, [0.75, 0.7, 0.5]),
+                (-10.0, [0.3, 0.5, 0.2]), (25.0, [0.2, 0.38, 0.15]), (40.0, [0.45, 0.42, 0.38]),
+                (55.0, [0.95, 0.95, 0.97])];
+    let h = height + 4.0 * detail;
+    let mut color = ramp[0].1;
+    for pair in ramp.windows(2) {
+        let ((h0, c0), (h1, c1)) = (pair[0], pair[1]);
+        if h > h0 {
+            let t = ((h - h0) / (h1 - h0)).min(1.0);
+            color = [c0[0] + t * (c1[0] - c0[0]), c0[1] + t * (c1[1] - c0[1]),
+                     c0[2] + t * (c1[2] - c0[2])];
+        }
+    }
+    // a path every 64 meters, to show how sharp the texture is
+    let path = |v: f32| ((v / 64.0).fract().abs() - 0.5).abs() * 64.0 > 31.5;
+    let shade = if path(x) || path(y) { 0.6 } else { 1.0 + 0.15 * detail };
+    let byte = |c: f32| ((c * shade).max(0.0).min(1.0) * 255.0) as u8;
+    [byte(color[0]), byte(color[1]), byte(color[2]), 0xFF]
+}
+
+// Generates the texels of a page, border included
+fn generate_page(seed: &Seed, page: Page) -> Vec<u8> {
+    let texel = page.size() / (PAGE_SIZE - 2 * BORDER) as f32;
+    // one more octave of detail for each level finer
+    let octaves = 1 + (LEVELS - 1 - page.level) as u32;
+    let (x0, y0) = page.origin();
+    let mut data = Vec::with_capacity((PAGE_SIZE * PAGE_SIZE * 4) as usize);
+    for j in 0 .. PAGE_SIZE {
+        for i in 0 .. PAGE_SIZE {
+            let x = x0 + (i as f32 - BORDER as f32 + 0.5) * texel;
+            let y = y0 + (j as f32 - BORDER as f32 + 0.5) * texel;
+            data.extend(terrain_color(seed, x, y, octaves).iter().cloned());
+        }
+    }
+    data
+}
+
+// Walks down the quadtree from `page`, splitting pages whose texels would
+// be larger than a pixel on the screen, and skipping those behind the
+// camera. The pages needed go to `needed`, coarse ones first.
+fn select(page: Page, camera: &FlyCamera, ground: f32, pixels: f32,
+          needed: &mut Vec<Page>) {
+    let mut queue = vec![page];
+    let mut next = 0;
+    while next < queue.len() {
+        let page = queue[next];
+        next += 1;
+        let (x0, y0) = page.origin();
+        let size = page.size();
+        let p = camera.position;
+        // the distance to the closest point of the page, on the ground
+        let dx = (x0 - p.x).max(p.x - x0 - size).max(0.0);
+        let dy = (y0 - p.y).max(p.y - y0 - size).max(0.0);
+        let dz = (p.z - ground).max(1.0);
+        let distance = (dx * dx + dy * dy + dz * dz).sqrt();
+        let forward = camera.forward();
+        let (cx, cy) = (x0 + 0.5 * size - p.x, y0 + 0.5 * size - p.y);
+        if forward.x * cx + forward.y * cy < -size && page.level + 1 < LEVELS {
+            continue;
+        }
+        needed.push(page);
+        let texel = size / (PAGE_SIZE - 2 * BORDER) as f32;
+        if page.level > 0 && texel * pixels > distance &&
+           needed.len() + queue.len() - next + 4 < (ATLAS_PAGES * ATLAS_PAGES) as usize {
+            queue.extend(page.children().iter().cloned());
+        }
+    }
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Virtual texture example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let seed = Seed::new(match thumbnail {
+        Some(_) => capture::SEED,
+        None => rand::thread_rng().gen(),
+    });
+    let terrain = Heightfield::new(256, TERRAIN_SIZE / 256.0, |x, y| terrain_height(&seed, x, y));
+
+    let atlas = factory.create_texture_rgba8((ATLAS_PAGES * PAGE_SIZE) as u16,
+                                             (ATLAS_PAGES * PAGE_SIZE) as u16, false).unwrap();
+    let atlas_info = *atlas.get_info();
+    let indirection = factory.create_texture_rgba8(PAGES as u16, PAGES as u16, false).unwrap();
+    let indirection_info = indirection.get_info().to_image_info();
+    let mut indirection_data = vec![0u8; (PAGES * PAGES * 4) as usize];
+    let mut slots: Vec<Slot> = (0 .. ATLAS_PAGES * ATLAS_PAGES).map(|_| Slot {
+        page: None,
+        last_used: 0,
+    }).collect();
+    let mut resident: HashMap<Page, usize> = HashMap::new();
+    let root = Page { level: LEVELS - 1, x: 0, y: 0 };
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut batch = {
+        let (vertex_data, index_data) = terrain.mesh();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let nearest = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Scale,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let bilinear = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            show_pages: 0.0,
+            indirection: (indirection.clone(), Some(nearest)),
+            atlas: (atlas.clone(), Some(bilinear)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(FOV), aspect, 0.5, 2000.0);
+    // the pixels a meter covers on the screen, a meter away
+    let pixels = 0.5 * h as f32 / (0.5 * FOV * PI / 180.0).tan();
+    let start = terrain.height(0.0, 0.0) + 30.0;
+    let mut camera = FlyCamera::new(Point3::new(0.0, 0.0, start), 0.5 * PI, -0.3, 20.0);
+    let mut show_pages = false;
+    let mut frame = 0u64;
+    let mut needed = Vec::new();
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.7, 0.85, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::P)) =>
+                    show_pages = !show_pages,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            camera.update(clock.step());
+        }
+
+        // Find the pages in view, and mark those already in the atlas
+        frame += 1;
+        needed.clear();
+        let ground = terrain.height(camera.position.x, camera.position.y).min(camera.position.z);
+        select(root, &camera, ground, pixels, &mut needed);
+        let mut uploads = 0;
+        for &page in needed.iter() {
+            if let Some(&slot) = resident.get(&page) {
+                slots[slot].last_used = frame;
+                continue;
+            }
+            if uploads == UPLOADS_PER_FRAME {
+                continue;
+            }
+            // a free slot, or the one unused for the longest time
+            let slot = match (0 .. slots.len()).filter(|&i| slots[i].last_used < frame)
+                                               .min_by_key(|&i| slots[i].last_used) {
+                Some(slot) => slot,
+                None => break,
+            };
+            if let Some(old) = slots[slot].page {
+                resident.remove(&old);
+            }
+            slots[slot] = Slot { page: Some(page), last_used: frame };
+            resident.insert(page, slot);
+            let mut image = atlas_info.to_image_info();
+            image.xoffset = (slot as u32 % ATLAS_PAGES * PAGE_SIZE) as u16;
+            image.yoffset = (slot as u32 / ATLAS_PAGES * PAGE_SIZE) as u16;
+            image.width = PAGE_SIZE as u16;
+            image.height = PAGE_SIZE as u16;
+            factory.update_texture(&atlas, &image, &generate_page(&seed, page), None).unwrap();
+            uploads += 1;
+        }
+
+        // Point every page of the finest level to the finest page in the
+        // atlas covering it
+        if uploads > 0 {
+            for y in 0 .. PAGES {
+                for x in 0 .. PAGES {
+                    let entry = (0 .. LEVELS).filter_map(|level| {
+                        let page = Page { level: level, x: x >> level, y: y >> level };
+                        resident.get(&page).map(|&slot| (slot as u32, level))
+                    }).next();
+                    if let Some((slot, level)) = entry {
+                        let i = ((y * PAGES + x) * 4) as usize;
+                        indirection_data[i] = (slot % ATLAS_PAGES) as u8;
+                        indirection_data[i + 1] = (slot / ATLAS_PAGES) as u8;
+                        indirection_data[i + 2] = level;
+                        indirection_data[i + 3] = 0xFF;
+                    }
+                }
+            }
+            factory.update_texture(&indirection, &indirection_info, &indirection_data, None)
+                   .unwrap();
+        }
+
+        batch.params.view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        batch.params.show_pages = if show_pages { 1.0 } else { 0.0 };
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        renderer.draw(&(&batch, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let missing = needed.iter().filter(|page| !resident.contains_key(page)).count();
+        wrap.window.set_title(&format!(
+            "Virtual texture example: {} pages needed, {} missing, {} of {} slots in use",
+            needed.len(), missing, resident.len(), slots.len()));
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of side by side stereo, the way a head mounted display
+// shows it.
+//
+// Each eye sees the scene from half the interpupillary distance to the side
+// of a flying camera, rendered into a texture of its own. The two textures
+// are then drawn side by side into the window through a barrel distortion
+// around the center of each half, which the pincushion distortion of the
+// lenses of a headset would undo. Those lenses also bend red less than blue,
+// so each color channel is looked up at a slightly different radius to
+// cancel out the color fringes. The eye textures are larger than their half
+// of the window, since the distortion spreads their center over more pixels.
+//
+// Drag with the left mouse button to look around, and press W, A, S and D to
+// move. Press Up and Down to change the distortion, Left and Right to change
+// the interpupillary distance, C to turn the chromatic correction on and off,
+// and L to turn the distortion on and off.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use std::f32::consts::PI;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point, Point3, Vector, Vector3};
+use cgmath::{Transform, AffineMatrix3};
+use gfx::traits::*;
+use gfx::Plane;
+use gfx_examples::camera::FlyCamera;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{window, Clock, Thumbnail};
+
+// Size of an eye texture, relative to its half of the window
+const EYE_SCALE: f32 = 1.5;
+// Half of the horizontal field of view of each eye, in degrees
+const HALF_FOV: f32 = 50.0;
+// The lens of a DK1 headset: the coefficients of r^2 and r^4 in the radial
+// distortion, and the scales of the radius for red and blue
+const DISTORTION: [f32; 2] = [0.22, 0.24];
+const CHROMA: [f32; 2] = [0.996, 1.014];
+
+#[shader_param]
+struct SceneParams<R: gfx::Resources> {
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+#[shader_param]
+struct LensParams<R: gfx::Resources> {
+    // width over height of an eye
+    #[name = "u_Aspect"]
+    aspect: f32,
+    #[name = "u_Distortion"]
+    distortion: [f32; 2],
+    #[name = "u_Chroma"]
+    chroma: [f32; 2],
+    #[name = "t_Left"]
+    left: gfx::shade::TextureParam<R>,
+    #[name = "t_Right"]
+    right: gfx::shade::TextureParam<R>,
+}
+
+static SCENE_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_Model;
+    uniform mat4 u_ViewProj;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        vec4 world = u_Model * vec4(a_Pos, 1.0);
+        v_World = world.xyz;
+        v_Normal = mat3(u_Model) * a_Normal;
+        gl_Position = u_ViewProj * world;
+    }
+";
+
+static SCENE_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        float diffuse = max(dot(n, normalize(vec3(0.3, -0.5, 0.8))), 0.0);
+        vec3 color = u_Color * (0.8 * diffuse + 0.25);
+        // lines a unit apart, which show how the distortion bends them
+        vec2 cell = abs(fract(v_World.xy + 0.5) - 0.5);
+        vec2 width = fwidth(v_World.xy);
+        float line = 1.0 - min(min(cell.x / width.x, cell.y / width.y), 1.0);
+        color *= 1.0 - 0.6 * line * step(0.5, abs(n.z));
+        o_Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+static LENS_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec3 a_Pos;
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_Pos.xy * 0.5 + 0.5;
+        gl_Position = vec4(a_Pos.xy, 0.0, 1.0);
+    }
+";
+
+static LENS_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform float u_Aspect;
+    uniform vec2 u_Distortion;
+    uniform vec2 u_Chroma;
+    uniform sampler2D t_Left;
+    uniform sampler2D t_Right;
+    in vec2 v_TexCoord;
+    out vec4 o_Color;
+
+    // looks up the eye texture at the point `p` of its image plane, which
+    // has a radius of 1 at the left and right edges
+    vec4 sample_eye(bool right, vec2 p) {
+        vec2 tc = (p * vec2(1.0, u_Aspect)) * 0.5 + 0.5;
+        if (any(lessThan(tc, vec2(0.0))) || any(greaterThan(tc, vec2(1.0)))) {
+            return vec4(0.0);
+        }
+        return right ? texture(t_Right, tc) : texture(t_Left, tc);
+    }
+
+    void main() {
+        bool right = v_TexCoord.x > 0.5;
+        vec2 uv = vec2(fract(2.0 * v_TexCoord.x), v_TexCoord.y);
+        vec2 p = (2.0 * uv - 1.0) * vec2(1.0, 1.0 / u_Aspect);
+        // the barrel distortion, scaled so the left and right edges of the
+        // view stay where they are
+        float r2 = dot(p, p);
+        float fit = 1.0 + u_Distortion.x + u_Distortion.y;
+        vec2 q = p * (1.0 + u_Distortion.x * r2 + u_Distortion.y * r2 * r2) / fit;
+        o_Color = vec4(sample_eye(right, q * u_Chroma.x).r,
+                       sample_eye(right, q).g,
+                       sample_eye(right, q * u_Chroma.y).b, 1.0);
+    }
+";
+
+fn model_matrix(pos: [f32; 3], scale: [f32; 3]) -> Matrix4<f32> {
+    Matrix4::new(scale[0], 0.0, 0.0, 0.0,
+                 0.0, scale[1], 0.0, 0.0,
+                 0.0, 0.0, scale[2], 0.0,
+                 pos[0], pos[1], pos[2], 1.0)
+}
+
+// The view of an eye `offset` to the right of the camera, looking the same
+// way
+fn eye_view(camera: &FlyCamera, offset: f32) -> AffineMatrix3<f32> {
+    let eye = camera.position.add_v(&camera.right().mul_s(offset));
+    Transform::look_at(&eye, &eye.add_v(&camera.forward()), &Vector3::unit_z())
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("VR example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let (eye_w, eye_h) = ((EYE_SCALE * 0.5 * w as f32) as u16, (EYE_SCALE * h as f32) as u16);
+    let eyes: Vec<_> = (0 .. 2).map(|_| {
+        let color = factory.create_texture(gfx::tex::TextureInfo {
+            width: eye_w,
+            height: eye_h,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::RGBA8,
+        }).unwrap();
+        let depth = factory.create_texture(gfx::tex::TextureInfo {
+            width: eye_w,
+            height: eye_h,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::Format::DEPTH24_STENCIL8,
+        }).unwrap();
+        let frame = gfx::Frame {
+            colors: vec![Plane::Texture(color.clone(), 0, None)],
+            depth: Some(Plane::Texture(depth, 0, None)),
+            .. gfx::Frame::empty(eye_w, eye_h)
+        };
+        (color, frame)
+    }).collect();
+
+    let program = factory.link_program(SCENE_VERTEX_SRC, SCENE_FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let mut meshes = MeshCache::new();
+    let shapes = vec![meshes.plane(&mut factory), meshes.cube(&mut factory),
+                      meshes.sphere(&mut factory, 24, 48)];
+
+    // mesh index, position, scale and color: a floor, two rows of pillars
+    // down a hall, and a few balls between them
+    let mut objects = vec![
+        (0, [0.0, 0.0, 0.0], [40.0, 40.0, 1.0], [0.55, 0.5, 0.45]),
+        (2, [1.0, 4.0, 0.8], [0.8, 0.8, 0.8], [0.9, 0.2, 0.1]),
+        (2, [-1.5, 8.0, 0.5], [0.5, 0.5, 0.5], [0.2, 0.7, 0.2]),
+        (2, [0.5, 13.0, 1.2], [1.2, 1.2, 1.2], [0.2, 0.4, 0.9]),
+    ];
+    for i in 0 .. 10 {
+        let y = 3.0 * i as f32 - 3.0;
+        for &x in [-4.0f32, 4.0].iter() {
+            objects.push((1, [x, y, 2.5], [0.4, 0.4, 2.5], [0.85, 0.85, 0.8]));
+        }
+    }
+
+    let mut batches: Vec<_> = objects.iter().map(|&(mesh_id, pos, scale, color)| {
+        let (ref mesh, ref slice) = shapes[mesh_id];
+        let data = SceneParams {
+            model: model_matrix(pos, scale).into_fixed(),
+            view_proj: Matrix4::identity().into_fixed(),
+            color: color,
+            _dummy: std::marker::PhantomData,
+        };
+        context.make_batch(&program, data, mesh, slice.clone(), &state).unwrap()
+    }).collect();
+
+    let aspect = 0.5 * w as f32 / h as f32;
+    let mut lens = {
+        let (mesh, slice) = meshes.quad(&mut factory);
+        let program = factory.link_program(LENS_VERTEX_SRC, LENS_FRAGMENT_SRC).unwrap();
+        let sampler = factory.create_sampler(
+            gfx::tex::SamplerInfo::new(gfx::tex::FilterMethod::Bilinear,
+                                       gfx::tex::WrapMode::Clamp)
+        );
+        let data = LensParams {
+            aspect: aspect,
+            distortion: DISTORTION,
+            chroma: CHROMA,
+            left: (eyes[0].0.clone(), Some(sampler.clone())),
+            right: (eyes[1].0.clone(), Some(sampler)),
+        };
+        context.make_batch(&program, data, &mesh, slice, &gfx::DrawState::new()).unwrap()
+    };
+
+    // the vertical field of view that goes with the horizontal one
+    let fov_y = 2.0 * ((HALF_FOV * PI / 180.0).tan() / aspect).atan();
+    let eye_proj = cgmath::perspective(cgmath::rad(fov_y), aspect, 0.05, 100.0);
+    let mut camera = FlyCamera::new(Point3::new(0.0, -6.0, 1.7), 0.5 * PI, 0.0, 3.0);
+    // in meters
+    let mut interpupillary = 0.064f32;
+    let mut distortion = DISTORTION;
+    let mut lens_on = true;
+    let mut chroma_on = true;
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.7, 0.85, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::Up => distortion[0] += 0.02,
+                    VirtualKeyCode::Down => distortion[0] -= 0.02,
+                    VirtualKeyCode::Right => interpupillary += 0.002,
+                    VirtualKeyCode::Left => interpupillary -= 0.002,
+                    VirtualKeyCode::C => chroma_on = !chroma_on,
+                    VirtualKeyCode::L => lens_on = !lens_on,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+        distortion[0] = distortion[0].max(0.0).min(1.0);
+        interpupillary = interpupillary.max(0.0).min(0.1);
+
+        clock.update();
+        while clock.tick() {
+            camera.update(clock.step());
+        }
+
+        for (&(_, ref frame), &offset) in eyes.iter()
+                                              .zip([-0.5 * interpupillary,
+                                                    0.5 * interpupillary].iter()) {
+            let view_proj = eye_proj.mul_m(&eye_view(&camera, offset).mat).into_fixed();
+            renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, frame);
+            for batch in batches.iter_mut() {
+                batch.params.view_proj = view_proj;
+                renderer.draw(&(&*batch, &context), frame).unwrap();
+            }
+        }
+
+        lens.params.distortion = if lens_on { distortion } else { [0.0; 2] };
+        lens.params.chroma = if chroma_on { CHROMA } else { [1.0; 2] };
+        renderer.draw(&(&lens, &context), &wrap).unwrap();
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!(
+            "VR example: interpupillary {:.0} mm, distortion {}, chromatic correction {}",
+            interpupillary * 1000.0,
+            if lens_on { format!("{:.2} {:.2}", distortion[0], distortion[1]) }
+            else { "off".to_string() },
+            if chroma_on { "on" } else { "off" }));
+    }
+}
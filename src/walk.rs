@@ -0,0 +1,134 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Walking on the ground, with gravity.
+//!
+//! A `Walker` is a body with its feet on a ground given as a height at every
+//! point, such as a heightfield with boxes on it. Each step, it heads where
+//! it is asked to at its walking speed, falls when nothing holds it up, and
+//! jumps on demand. While it is on the ground it follows the ground down
+//! instead of flying off every slope and ledge, and steps up anything no
+//! higher than `max_step`. Rises higher than that, and slopes steeper than
+//! `max_slope`, stop it along that axis, so it slides along them.
+//!
+//! ```ignore
+//! let mut walker = Walker::new(Point3::new(0.0, 0.0, ground(0.0, 0.0)));
+//! // every tick
+//! walker.update(dt, Vector2::new(0.0, 1.0), jump, |x, y| ground(x, y));
+//! ```
+
+use cgmath::{Point3, Vector, Vector2, Vector3};
+
+// How far ahead slopes are measured
+const SLOPE_PROBE: f32 = 1.0;
+// How quickly the walker turns its motion towards where it heads while in
+// the air, per second
+const AIR_CONTROL: f32 = 2.0;
+
+/// A body walking and jumping on the ground.
+pub struct Walker {
+    /// Position of the feet.
+    pub position: Point3<f32>,
+    pub velocity: Vector3<f32>,
+    /// Units per second.
+    pub speed: f32,
+    /// Upwards speed at the start of a jump.
+    pub jump_speed: f32,
+    /// Units per second squared.
+    pub gravity: f32,
+    /// Highest rise walked up without jumping.
+    pub max_step: f32,
+    /// Steepest slope walked up, as rise over run.
+    pub max_slope: f32,
+    on_ground: bool,
+}
+
+impl Walker {
+    pub fn new(position: Point3<f32>) -> Walker {
+        Walker {
+            position: position,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            speed: 4.0,
+            jump_speed: 5.0,
+            gravity: 15.0,
+            max_step: 0.4,
+            max_slope: 1.0,
+            on_ground: false,
+        }
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    /// Moves the walker during `dt` seconds, heading along `heading`, whose
+    /// length scales the walking speed, and jumping if `jump` is set while on
+    /// the ground. `ground` returns the height of the
+    /// ground at a point.
+    pub fn update<F>(&mut self, dt: f32, heading: Vector2<f32>, jump: bool, ground: F)
+                     where F: Fn(f32, f32) -> f32 {
+        let wish = heading.mul_s(self.speed);
+        if self.on_ground {
+            self.velocity.x = wish.x;
+            self.velocity.y = wish.y;
+            if jump {
+                self.velocity.z = self.jump_speed;
+                self.on_ground = false;
+            }
+        } else {
+            let turn = (AIR_CONTROL * dt).min(1.0);
+            self.velocity.x += (wish.x - self.velocity.x) * turn;
+            self.velocity.y += (wish.y - self.velocity.y) * turn;
+        }
+
+        // along each axis on its own, so a blocked move keeps the other one
+        for &(ax, ay) in [(1.0f32, 0.0f32), (0.0, 1.0)].iter() {
+            let (dx, dy) = (ax * self.velocity.x * dt, ay * self.velocity.y * dt);
+            if dx == 0.0 && dy == 0.0 {
+                continue;
+            }
+            let (x, y) = (self.position.x + dx, self.position.y + dy);
+            let (probe_x, probe_y) = (x + ax * SLOPE_PROBE * dx.signum(),
+                                      y + ay * SLOPE_PROBE * dy.signum());
+            let too_high = ground(x, y) - self.position.z > self.max_step;
+            let too_steep = self.on_ground && ground(probe_x, probe_y) - self.position.z >
+                                              self.max_step + self.max_slope * SLOPE_PROBE;
+            if too_high || too_steep {
+                self.velocity.x *= 1.0 - ax;
+                self.velocity.y *= 1.0 - ay;
+            } else {
+                self.position.x = x;
+                self.position.y = y;
+            }
+        }
+
+        let height = ground(self.position.x, self.position.y);
+        if !self.on_ground {
+            self.velocity.z -= self.gravity * dt;
+        }
+        self.position.z += self.velocity.z * dt;
+        if self.position.z <= height {
+            // landed
+            self.position.z = height;
+            self.velocity.z = 0.0;
+            self.on_ground = true;
+        } else if self.on_ground && self.position.z - height <= self.max_step {
+            // down a slope or a step, staying on the ground
+            self.position.z = height;
+        } else {
+            // off a ledge
+            self.on_ground = false;
+        }
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of walking over terrain on foot.
+//
+// The terrain is a heightfield of Perlin noise, with plateaus whose edges
+// are too steep to walk up. The camera is a `WalkCamera`, which stands on a
+// `walk::Walker`: it falls with gravity, jumps, and follows the ground down
+// slopes and steps instead of flying off them. The ground it walks on is the
+// same triangles that are drawn, plus the tops of a few boxes: a staircase
+// with steps low enough to walk up, and a ledge that needs a jump. Rock
+// shows where the terrain is steeper than the walker can climb.
+//
+// Drag with the left mouse button to look around, press W, A, S and D to
+// walk, Space to jump, and hold Left Shift to run.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate noise;
+extern crate rand;
+
+use std::f32::consts::PI;
+use rand::Rng;
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix4, Point3};
+use gfx::traits::*;
+use gfx_examples::camera::WalkCamera;
+use gfx_examples::heightfield::Heightfield;
+use gfx_examples::mesh_cache::MeshCache;
+use gfx_examples::{capture, window, Clock, Thumbnail};
+use noise::{Seed, perlin2};
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Offset"]
+    offset: [f32; 3],
+    #[name = "u_Scale"]
+    scale: [f32; 3],
+    // 1 to color by the slope, 0 to use `u_Color`
+    #[name = "u_Terrain"]
+    terrain: f32,
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // the slope the walker can climb, as rise over run
+    #[name = "u_MaxSlope"]
+    max_slope: f32,
+    #[name = "u_Eye"]
+    eye: [f32; 3],
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform vec3 u_Offset;
+    uniform vec3 u_Scale;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    out vec3 v_World;
+    out vec3 v_Normal;
+
+    void main() {
+        v_World = u_Scale * a_Pos + u_Offset;
+        v_Normal = a_Normal / u_Scale;
+        gl_Position = u_ViewProj * vec4(v_World, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    const vec3 SKY = vec3(0.6, 0.72, 0.88);
+
+    uniform float u_Terrain;
+    uniform vec3 u_Color;
+    uniform float u_MaxSlope;
+    uniform vec3 u_Eye;
+    in vec3 v_World;
+    in vec3 v_Normal;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 n = normalize(v_Normal);
+        vec3 albedo = u_Color;
+        if (u_Terrain > 0.5) {
+            float slope = sqrt(max(1.0 - n.z * n.z, 0.0)) / max(n.z, 1e-3);
+            float rock = smoothstep(0.8 * u_MaxSlope, u_MaxSlope, slope);
+            albedo = mix(vec3(0.25, 0.45, 0.15), vec3(0.45, 0.4, 0.35), rock);
+        }
+        float diffuse = max(dot(n, normalize(vec3(0.4, 0.3, 0.8))), 0.0);
+        vec3 color = albedo * (vec3(1.0, 0.95, 0.85) * diffuse + 0.35 * SKY);
+        float fog = 1.0 - exp(-0.008 * length(v_World - u_Eye));
+        o_Color = vec4(pow(mix(color, SKY, fog), vec3(1.0 / 2.2)), 1.0);
+    }
+";
+
+// Hills, and plateaus with a steep rise around them
+fn terrain_height(seed: &Seed, x: f32, y: f32) -> f32 {
+    let hills = 12.0 * perlin2(seed, &[x / 40.0, y / 40.0]) +
+                2.0 * perlin2(seed, &[x / 9.0, y / 9.0]);
+    let plateau = perlin2(seed, &[x / 60.0 + 17.0, y / 60.0 - 5.0]);
+    let t = ((plateau - 0.15) / 0.08).max(0.0).min(1.0);
+    hills + 8.0 * t * t * (3.0 - 2.0 * t)
+}
+
+// A box standing on the terrain, from its corners in x and y, and the height
+// of its top
+struct Block {
+    min: [f32; 2],
+    max: [f32; 2],
+    top: f32,
+}
+
+impl Block {
+    fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.min[0] && x <= self.max[0] && y >= self.min[1] && y <= self.max[1]
+    }
+}
+
+// A staircase up to a landing in front of the start, and a ledge beside it
+// too high to step on
+fn blocks(terrain: &Heightfield) -> Vec<Block> {
+    let base = terrain.height(0.0, 4.0);
+    let mut blocks: Vec<_> = (0 .. 8).map(|i| Block {
+        min: [-1.5, 4.0 + i as f32],
+        max: [1.5, 5.0 + i as f32],
+        top: base + 0.3 * (i + 1) as f32,
+    }).collect();
+    blocks.push(Block { min: [-1.5, 12.0], max: [1.5, 18.0], top: base + 2.4 });
+    let ledge = terrain.height(5.0, 6.0);
+    blocks.push(Block { min: [3.5, 4.0], max: [6.5, 9.0], top: ledge + 0.7 });
+    blocks
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Walk example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let seed = Seed::new(match thumbnail {
+        Some(_) => capture::SEED,
+        None => rand::thread_rng().gen(),
+    });
+    let terrain = Heightfield::new(256, 1.0, |x, y| terrain_height(&seed, x, y));
+    let blocks = blocks(&terrain);
+    let ground = |x: f32, y: f32| {
+        blocks.iter().filter(|b| b.contains(x, y))
+              .fold(terrain.height(x, y), |h, b| h.max(b.top))
+    };
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    let data = || Params {
+        view_proj: Matrix4::identity().into_fixed(),
+        offset: [0.0; 3],
+        scale: [1.0; 3],
+        terrain: 0.0,
+        color: [0.6, 0.58, 0.55],
+        max_slope: 1.0,
+        eye: [0.0; 3],
+        _dummy: std::marker::PhantomData,
+    };
+    let mut terrain_batch = {
+        let (vertex_data, index_data) = terrain.mesh();
+        let mesh = factory.create_mesh(&vertex_data);
+        let slice = factory.create_buffer_index::<u32>(&index_data)
+                           .to_slice(gfx::PrimitiveType::TriangleList);
+        let mut data = data();
+        data.terrain = 1.0;
+        context.make_batch(&program, data, &mesh, slice, &state).unwrap()
+    };
+    let mut meshes = MeshCache::new();
+    let mut cube = {
+        let (mesh, slice) = meshes.cube(&mut factory);
+        context.make_batch(&program, data(), &mesh, slice, &state).unwrap()
+    };
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(70.0f32), aspect, 0.05, 400.0);
+    let mut camera = WalkCamera::new(Point3::new(0.0, 0.0, ground(0.0, 0.0)), 0.5 * PI, 0.0);
+    terrain_batch.params.max_slope = camera.walker.max_slope;
+
+    let clear_data = gfx::ClearData {
+        color: [0.6, 0.72, 0.88, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            camera.update(clock.step(), &ground);
+        }
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        let eye = camera.eye().into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        terrain_batch.params.view_proj = view_proj;
+        terrain_batch.params.eye = eye;
+        renderer.draw(&(&terrain_batch, &context), &wrap).unwrap();
+        for block in blocks.iter() {
+            // down into the terrain, below where it is lowest
+            let bottom = block.top - 5.0;
+            cube.params.view_proj = view_proj;
+            cube.params.eye = eye;
+            cube.params.offset = [0.5 * (block.min[0] + block.max[0]),
+                                  0.5 * (block.min[1] + block.max[1]),
+                                  0.5 * (block.top + bottom)];
+            cube.params.scale = [0.5 * (block.max[0] - block.min[0]),
+                                 0.5 * (block.max[1] - block.min[1]),
+                                 0.5 * (block.top - bottom)];
+            renderer.draw(&(&cube, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        let walker = &camera.walker;
+        wrap.window.set_title(&format!("Walk example: {}, height {:.1}",
+                                       if walker.on_ground() { "on the ground" }
+                                       else { "in the air" },
+                                       walker.position.z));
+    }
+}
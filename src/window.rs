@@ -0,0 +1,104 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Window size queries that account for the display scale.
+//!
+//! On HiDPI displays glutin reports window sizes in points, while GL draws
+//! into a surface measured in pixels. Anything sized to match the surface,
+//! like off-screen render targets or `gl_FragCoord` based lookups, has to
+//! use the physical size, and so does the window output itself, which is why
+//! the examples wrap it with `init`.
+
+use std::ops::Deref;
+use gfx;
+use gfx_window_glutin::Wrap;
+use glutin;
+
+/// Returns the number of physical pixels per point of the window surface.
+pub fn scale_factor(window: &glutin::Window) -> f32 {
+    window.hidpi_factor()
+}
+
+/// Returns the size of the window surface in physical pixels, or `None` if
+/// the window no longer exists.
+pub fn physical_size(window: &glutin::Window) -> Option<(gfx::tex::Size, gfx::tex::Size)> {
+    let factor = scale_factor(window);
+    window.get_inner_size().map(|(w, h)| {
+        ((w as f32 * factor) as gfx::tex::Size, (h as f32 * factor) as gfx::tex::Size)
+    })
+}
+
+/// The output of a `gfx_window_glutin` window, reporting its size in
+/// physical pixels, so passes drawing to it get a viewport covering the
+/// whole surface. It derefs to the wrapped output, `output.window` still
+/// being the glutin window.
+pub struct Output<R: gfx::Resources>(Wrap<R>);
+
+impl<R: gfx::Resources> Deref for Output<R> {
+    type Target = Wrap<R>;
+
+    fn deref(&self) -> &Wrap<R> {
+        &self.0
+    }
+}
+
+impl<R: gfx::Resources> gfx::Output<R> for Output<R> {
+    fn get_handle(&self) -> Option<&gfx::handle::FrameBuffer<R>> {
+        self.0.get_handle()
+    }
+
+    fn get_size(&self) -> (gfx::tex::Size, gfx::tex::Size) {
+        physical_size(&self.0.window).unwrap_or((0, 0))
+    }
+
+    fn get_mask(&self) -> gfx::Mask {
+        self.0.get_mask()
+    }
+}
+
+/// Wraps what `gfx_window_glutin::init` returns, the output reporting its
+/// size in physical pixels.
+///
+/// ```ignore
+/// let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(builder));
+/// ```
+pub fn init<R: gfx::Resources, D, F>((wrap, device, factory): (Wrap<R>, D, F))
+                                     -> (Output<R>, D, F) {
+    (Output(wrap), device, factory)
+}
+
+/// Opens another window from `builder`, its context sharing textures,
+/// buffers and programs with the one of `main`, so that resources made by
+/// either factory can be drawn in both windows. `init` is
+/// `gfx_window_glutin::init`, and the context of `main` is current again
+/// once it returns.
+///
+/// The new window comes with a device of its own. A device caches the state
+/// of a single context and owns its frame buffers and vertex arrays, which GL
+/// doesn't share between contexts, and `gfx_window_glutin` has no way to hand
+/// out several windows on one device. Make the context of the window current
+/// before submitting to its device, and the one of `main` again after.
+///
+/// ```ignore
+/// let (second, mut second_device, mut second_factory) = window::init_shared(
+///     &wrap, glutin::WindowBuilder::new(), gfx_window_glutin::init);
+/// ```
+pub fn init_shared<'a, R, D, F, I>(main: &'a Output<R>, builder: glutin::WindowBuilder<'a>,
+                                   init: I) -> (Output<R>, D, F)
+                   where R: gfx::Resources, I: FnOnce(glutin::Window) -> (Wrap<R>, D, F) {
+    let window = builder.with_shared_lists(&main.window).build().unwrap();
+    let shared = self::init(init(window));
+    unsafe { main.window.make_current() };
+    shared
+}
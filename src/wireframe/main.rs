@@ -0,0 +1,257 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This is an example of drawing a wireframe over solid geometry in one pass.
+//
+// Each triangle gets its own three vertices, with the barycentric coordinates
+// (1, 0, 0), (0, 1, 0) and (0, 0, 1). Interpolated over the triangle, the
+// smallest of them says how close a fragment is to an edge. Dividing by how
+// fast the coordinates change across a pixel, from fwidth, turns that into a
+// distance in pixels, so that the lines keep the same width on screen at any
+// distance and angle, and fade out over a pixel at their sides instead of
+// stair-stepping.
+//
+// Unlike drawing the mesh again as lines, this needs no second pass and no
+// depth offset, and the lines can be as thick as wanted. The cost is that
+// vertices can't be shared between triangles anymore.
+//
+// Press W to cycle through solid with wires, wires only and solid only, Up
+// and Down to change the line width, and Space to stop the shapes.
+
+#![feature(plugin, custom_attribute)]
+#![plugin(gfx_macros)]
+
+extern crate cgmath;
+extern crate gfx;
+extern crate gfx_examples;
+extern crate gfx_window_glutin;
+extern crate glutin;
+
+use cgmath::FixedArray;
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, Vector4, ToMatrix4};
+use gfx::traits::*;
+use gfx_examples::camera::OrbitCamera;
+use gfx_examples::{shape, window, Clock, Thumbnail};
+
+const MODES: [&'static str; 3] = ["solid with wires", "wires only", "solid only"];
+
+#[vertex_format]
+#[derive(Clone, Copy)]
+struct Vertex {
+    #[name = "a_Pos"]
+    pos: [f32; 3],
+    #[name = "a_Normal"]
+    normal: [f32; 3],
+    #[name = "a_Barycentric"]
+    barycentric: [f32; 3],
+}
+
+#[shader_param]
+struct Params<R: gfx::Resources> {
+    #[name = "u_ViewProj"]
+    view_proj: [[f32; 4]; 4],
+    #[name = "u_Model"]
+    model: [[f32; 4]; 4],
+    #[name = "u_Color"]
+    color: [f32; 3],
+    // in pixels
+    #[name = "u_LineWidth"]
+    line_width: f32,
+    #[name = "u_Mode"]
+    mode: f32,
+    _dummy: std::marker::PhantomData<R>,
+}
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 u_ViewProj;
+    uniform mat4 u_Model;
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec3 a_Barycentric;
+    out vec3 v_Normal;
+    out vec3 v_Barycentric;
+
+    void main() {
+        v_Normal = mat3(u_Model) * a_Normal;
+        v_Barycentric = a_Barycentric;
+        gl_Position = u_ViewProj * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform vec3 u_Color;
+    uniform float u_LineWidth;
+    uniform float u_Mode;
+    in vec3 v_Normal;
+    in vec3 v_Barycentric;
+    out vec4 o_Color;
+
+    void main() {
+        // distances to the three edges in pixels, then coverage by the line
+        vec3 pixels = v_Barycentric / fwidth(v_Barycentric);
+        float distance = min(min(pixels.x, pixels.y), pixels.z);
+        float line = 1.0 - smoothstep(0.5 * u_LineWidth - 0.5, 0.5 * u_LineWidth + 0.5, distance);
+
+        vec3 n = normalize(v_Normal);
+        float diffuse = abs(dot(n, normalize(vec3(0.3, -0.5, 0.8))));
+        vec3 solid = u_Color * (0.2 + 0.8 * diffuse);
+        vec3 wire = vec3(0.05, 0.05, 0.1);
+        if (u_Mode < 0.5) {
+            o_Color = vec4(mix(solid, wire, line), 1.0);
+        } else if (u_Mode < 1.5) {
+            if (line < 0.01) {
+                discard;
+            }
+            o_Color = vec4(mix(vec3(0.3, 0.3, 0.35), vec3(0.9, 0.95, 1.0), line), 1.0);
+        } else {
+            o_Color = vec4(solid, 1.0);
+        }
+    }
+";
+
+// Each triangle with its own corners, numbered by its barycentric coordinates
+fn unshare(vertices: &[shape::Vertex], indices: &[u16]) -> Vec<Vertex> {
+    let corners = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    indices.iter().enumerate().map(|(i, &index)| {
+        let v = &vertices[index as usize];
+        Vertex {
+            pos: v.pos,
+            normal: v.normal,
+            barycentric: corners[i % 3],
+        }
+    }).collect()
+}
+
+fn place(transform: Matrix3<f32>, x: f32, y: f32, z: f32) -> Matrix4<f32> {
+    let mut model = transform.to_matrix4();
+    model.w = Vector4::new(x, y, z, 1.0);
+    model
+}
+
+pub fn main() {
+    let (wrap, mut device, mut factory) = window::init(gfx_window_glutin::init(
+        glutin::WindowBuilder::new()
+            .with_title("Wireframe example with gfx-rs".to_string())
+            .with_dimensions(800, 600)
+            .with_gl(glutin::GlRequest::Latest)
+            .build().unwrap()
+    ));
+
+    let (w, h) = window::physical_size(&wrap.window).unwrap();
+    let mut thumbnail = Thumbnail::from_env();
+    let mut renderer = factory.create_renderer();
+    let mut context = gfx::batch::Context::new();
+
+    let program = factory.link_program(VERTEX_SRC, FRAGMENT_SRC).unwrap();
+    let state = gfx::DrawState::new().depth(gfx::state::Comparison::LessEqual, true);
+    // (mesh, position, color) of each shape
+    let shapes = vec![
+        (shape::cube(), -3.0, [0.8, 0.4, 0.2]),
+        (shape::sphere(12, 24), 0.0, [0.3, 0.7, 0.4]),
+        (shape::torus(24, 12, 0.8, 0.35), 3.0, [0.3, 0.5, 0.9]),
+    ];
+    let mut batches: Vec<_> = shapes.into_iter().map(|((vertex_data, index_data), x, color)| {
+        let mesh = factory.create_mesh(&unshare(&vertex_data, &index_data));
+        let slice = mesh.to_slice(gfx::PrimitiveType::TriangleList);
+        let data = Params {
+            view_proj: Matrix4::identity().into_fixed(),
+            model: Matrix4::identity().into_fixed(),
+            color: color,
+            line_width: 1.5,
+            mode: 0.0,
+            _dummy: std::marker::PhantomData,
+        };
+        (context.make_batch(&program, data, &mesh, slice, &state).unwrap(), x)
+    }).collect();
+
+    let aspect = w as f32 / h as f32;
+    let proj = cgmath::perspective(cgmath::deg(45.0f32), aspect, 0.1, 100.0);
+    let mut camera = OrbitCamera::new(Point3::new(0.0, 0.0, 0.0), 9.0, -1.2, 0.4);
+
+    let clear_data = gfx::ClearData {
+        color: [0.3, 0.3, 0.35, 1.0],
+        depth: 1.0,
+        stencil: 0,
+    };
+
+    let mut mode = 0;
+    let mut line_width = 1.5f32;
+    let mut turning = true;
+    let mut clock = match thumbnail {
+        Some(_) => Clock::fixed(60),
+        None => Clock::new(60),
+    };
+    let (mut prev_angle, mut angle) = (0.0f32, 0.0f32);
+
+    'main: loop {
+        // quit when Esc is pressed.
+        for event in wrap.window.poll_events() {
+            use glutin::{ElementState, Event, VirtualKeyCode};
+            camera.handle_event(&event);
+            match event {
+                Event::Closed => break 'main,
+                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
+                    break 'main,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => match key {
+                    VirtualKeyCode::W => mode = (mode + 1) % MODES.len(),
+                    VirtualKeyCode::Up => line_width = (line_width + 0.5).min(8.0),
+                    VirtualKeyCode::Down => line_width = (line_width - 0.5).max(0.5),
+                    VirtualKeyCode::Space => turning = !turning,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+
+        clock.update();
+        while clock.tick() {
+            prev_angle = angle;
+            if turning {
+                angle += 0.4 * clock.step();
+            }
+        }
+        let a = prev_angle + (angle - prev_angle) * clock.alpha();
+
+        let view_proj = proj.mul_m(&camera.view().mat).into_fixed();
+        renderer.clear(clear_data, gfx::COLOR | gfx::DEPTH, &wrap);
+        for &mut (ref mut batch, x) in batches.iter_mut() {
+            let spin = Matrix3::from_angle_z(cgmath::rad(a + x))
+                               .mul_m(&Matrix3::from_angle_x(cgmath::rad(0.6 * a)));
+            batch.params.view_proj = view_proj;
+            batch.params.model = place(spin, x, 0.0, 0.0).into_fixed();
+            batch.params.line_width = line_width;
+            batch.params.mode = mode as f32;
+            renderer.draw(&(&*batch, &context), &wrap).unwrap();
+        }
+
+        device.submit(renderer.as_buffer());
+        renderer.reset();
+
+        let win = &wrap.window;
+        if thumbnail.as_mut().map_or(false, |t| t.end_frame(|s| win.get_proc_address(s), (w, h))) {
+            break 'main;
+        }
+
+        wrap.window.swap_buffers();
+        device.after_frame();
+        factory.cleanup();
+
+        wrap.window.set_title(&format!("Wireframe example: {}, lines {:.1} pixels wide",
+                                       MODES[mode], line_width));
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2014 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rewriting a buffer every frame.
+//!
+//! A `BufferWriter` owns a buffer and fills it from a function of the index
+//! of each element. In `WriteMode::Mapped`, the buffer is mapped and every
+//! element is written straight into it, with no copy on the CPU side. In
+//! `WriteMode::Copy`, the elements are written into a vector kept from one
+//! frame to the next, and then uploaded with `update_buffer`, for drivers
+//! where mapping a buffer the GPU may still be reading from stalls. In
+//! `WriteMode::Changed`, the vector is kept in step with the buffer, and only
+//! the runs of elements that differ from what it already holds are uploaded,
+//! each at its offset, for buffers where most elements stay the same.
+//!
+//! ```ignore
+//! let mut writer = BufferWriter::new(&mut factory, 250, WriteMode::Mapped);
+//! writer.write(&mut factory, |i| positions[i]);
+//! let data = Params { lights: writer.buffer().raw().clone(), .. };
+//! ```
+
+use gfx;
+use gfx::traits::*;
+
+/// How a `BufferWriter` gets the elements into the buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WriteMode {
+    /// Mapping the buffer and writing into it.
+    Mapped,
+    /// Writing into a vector on the CPU and uploading it.
+    Copy,
+    /// Writing into a vector on the CPU and uploading the elements that
+    /// changed.
+    Changed,
+}
+
+/// A buffer of `T` that is written as a whole, or where it changed.
+pub struct BufferWriter<R: gfx::Resources, T> {
+    pub mode: WriteMode,
+    buffer: gfx::BufferHandle<R, T>,
+    // what the buffer holds, unless a mapped write left it short
+    staging: Vec<T>,
+    count: usize,
+    written: usize,
+}
+
+impl<R: gfx::Resources, T: Copy + PartialEq> BufferWriter<R, T> {
+    /// Makes a stream buffer of `count` elements.
+    pub fn new<F: gfx::Factory<R>>(factory: &mut F, count: usize, mode: WriteMode)
+               -> BufferWriter<R, T> {
+        BufferWriter {
+            mode: mode,
+            buffer: factory.create_buffer(count, gfx::BufferUsage::Stream),
+            staging: Vec::with_capacity(count),
+            count: count,
+            written: 0,
+        }
+    }
+
+    pub fn buffer(&self) -> &gfx::BufferHandle<R, T> {
+        &self.buffer
+    }
+
+    /// Number of elements that the last `write` uploaded.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Sets every element to `f` of its index, in order. A changed write
+    /// uploads the whole buffer when the vector isn't in step with it yet.
+    pub fn write<F, W>(&mut self, factory: &mut F, mut f: W)
+                 where F: gfx::Factory<R>, W: FnMut(usize) -> T {
+        match self.mode {
+            WriteMode::Mapped => {
+                // unmapped when dropped
+                let mut mapping = factory.map_buffer_writable(&self.buffer);
+                for i in 0 .. self.count {
+                    mapping.set(i, f(i));
+                }
+                self.staging.clear();
+                self.written = self.count;
+            },
+            WriteMode::Changed if self.staging.len() == self.count => {
+                self.written = 0;
+                // the start of the run of changed elements being gathered
+                let mut run = None;
+                for i in 0 .. self.count {
+                    let value = f(i);
+                    if value != self.staging[i] {
+                        self.staging[i] = value;
+                        run = run.or(Some(i));
+                    } else if let Some(start) = run.take() {
+                        factory.update_buffer(&self.buffer, &self.staging[start .. i], start);
+                        self.written += i - start;
+                    }
+                }
+                if let Some(start) = run {
+                    factory.update_buffer(&self.buffer, &self.staging[start ..], start);
+                    self.written += self.count - start;
+                }
+            },
+            WriteMode::Copy | WriteMode::Changed => {
+                self.staging.clear();
+                for i in 0 .. self.count {
+                    self.staging.push(f(i));
+                }
+                factory.update_buffer(&self.buffer, &self.staging, 0);
+                self.written = self.count;
+            },
+        }
+    }
+}